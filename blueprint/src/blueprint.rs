@@ -5,14 +5,16 @@ use std::{
 
 use mod_util::{mod_info::DependencyVersion, AnyBasic, DependencyList};
 use serde::{Deserialize, Serialize};
+use serde_helper as helper;
 use serde_with::skip_serializing_none;
 
 use types::{
-    ArithmeticOperation, Comparator, Direction, EntityID, FilterMode, FluidID, ItemCountType,
-    ItemID, ItemStackIndex, RealOrientation, RecipeID, TileID, Vector, VirtualSignalID,
+    ArithmeticOperation, AsteroidChunkID, Comparator, Direction16, EntityID, FilterMode, FluidID,
+    ItemCountType, ItemID, ItemStackIndex, QualityID, RealOrientation, RecipeID, SpaceLocationID,
+    TileID, Vector, VirtualSignalID,
 };
 
-use crate::{IndexedVec, NameString};
+use crate::IndexedVec;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -25,6 +27,9 @@ pub struct BlueprintData {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub entities: Vec<Entity>,
 
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wires: Vec<Wire>,
+
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tiles: Vec<Tile>,
 
@@ -33,6 +38,9 @@ pub struct BlueprintData {
 
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub description: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<Parameter>,
 }
 
 impl crate::GetIDs for BlueprintData {
@@ -56,32 +64,23 @@ impl BlueprintData {
     pub fn has_meta_info(&self) -> bool {
         self.entities
             .iter()
-            .any(|e| e.tags.contains_key("bp_meta_info"))
+            .any(|e| e.tag("bp_meta_info").is_some())
     }
 
     #[must_use]
     pub fn get_meta_info_mods(&self) -> Option<DependencyList> {
         for e in &self.entities {
-            let Some(info) = e.tags.get("bp_meta_info") else {
-                continue;
-            };
-
-            let AnyBasic::Table(data) = info else {
-                continue;
-            };
-
-            let Some(mods) = data.get("mods") else {
-                continue;
-            };
-
-            let AnyBasic::Table(mods) = mods else {
+            let Some(mods) = e
+                .tag_path(&["bp_meta_info", "mods"])
+                .and_then(AnyBasic::as_table)
+            else {
                 continue;
             };
 
             let mut result = HashMap::with_capacity(mods.len());
 
             for (mod_name, mod_version) in mods {
-                let AnyBasic::String(mod_version) = mod_version else {
+                let Some(mod_version) = mod_version.as_str() else {
                     continue;
                 };
 
@@ -99,20 +98,169 @@ impl BlueprintData {
     }
 }
 
+impl BlueprintData {
+    /// Entity numbers of the entities referencing each parameter, in the order of [`BlueprintData::parameters`].
+    #[must_use]
+    pub fn parameter_users(&self) -> Vec<Vec<EntityNumber>> {
+        let entities = self
+            .entities
+            .iter()
+            .filter_map(|e| Some((e.entity_number, serde_json::to_value(e).ok()?)))
+            .collect::<Vec<_>>();
+
+        self.parameters
+            .iter()
+            .map(|parameter| {
+                entities
+                    .iter()
+                    .filter(|(_, value)| parameter.is_referenced_by(value))
+                    .map(|(number, _)| *number)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
 pub type Blueprint = crate::CommonData<BlueprintData>;
 
+/// Parameter of a parametrised blueprint.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(
+    tag = "type",
+    rename_all = "lowercase",
+    rename_all_fields = "kebab-case"
+)]
+pub enum Parameter {
+    /// Placeholder for an item, fluid, recipe or signal, e.g. `parameter-0`.
+    Id {
+        id: String,
+        name: Option<String>,
+        quality_condition: Option<QualityCondition>,
+
+        /// The parameter is replaced by an ingredient of the recipe of this parameter.
+        ingredient_of: Option<String>,
+
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        not_parametrised: bool,
+    },
+
+    /// Number that occurs in the blueprint, optionally calculated from a formula.
+    Number {
+        number: String,
+        name: Option<String>,
+        variable: Option<String>,
+        formula: Option<String>,
+
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        dependent: bool,
+
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        not_parametrised: bool,
+    },
+}
+
+impl Parameter {
+    /// Entity fields that hold structural data instead of settings a parameter could be used in.
+    const STRUCTURAL_FIELDS: [&'static str; 5] = [
+        "entity_number",
+        "position",
+        "direction",
+        "orientation",
+        "index",
+    ];
+
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Id { name, .. } | Self::Number { name, .. } => name.as_deref(),
+        }
+    }
+
+    /// The ID or number that gets replaced when the blueprint is placed.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Id { id, .. } => id,
+            Self::Number { number, .. } => number,
+        }
+    }
+
+    #[must_use]
+    pub fn is_parametrised(&self) -> bool {
+        match self {
+            Self::Id {
+                not_parametrised, ..
+            }
+            | Self::Number {
+                not_parametrised, ..
+            } => !not_parametrised,
+        }
+    }
+
+    /// Whether the entity uses this parameter in any of its settings.
+    #[must_use]
+    pub fn is_used_by(&self, entity: &Entity) -> bool {
+        serde_json::to_value(entity).is_ok_and(|value| self.is_referenced_by(&value))
+    }
+
+    fn is_referenced_by(&self, value: &serde_json::Value) -> bool {
+        use serde_json::Value;
+
+        match value {
+            Value::Object(map) => map
+                .iter()
+                .filter(|(key, _)| !Self::STRUCTURAL_FIELDS.contains(&key.as_str()))
+                .any(|(_, value)| self.is_referenced_by(value)),
+            Value::Array(values) => values.iter().any(|value| self.is_referenced_by(value)),
+            Value::String(s) => matches!(self, Self::Id { id, .. } if id == s),
+            Value::Number(n) => match self {
+                Self::Number { number, .. } => number
+                    .parse::<f64>()
+                    .is_ok_and(|number| n.as_f64() == Some(number)),
+                Self::Id { .. } => false,
+            },
+            Value::Bool(_) | Value::Null => false,
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct QualityCondition {
+    pub quality: Option<String>,
+    pub comparator: Option<Comparator>,
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct SnapData {
+    /// Size of the grid cells the blueprint snaps to when placed.
     pub snap_to_grid: Option<Position>,
 
+    /// Snap to the global map grid instead of relative to the first placement.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub absolute_snapping: bool,
 
+    /// Offset of the blueprint's top left corner inside its grid cell, only used with absolute snapping.
     pub position_relative_to_grid: Option<Position>,
 }
 
+impl SnapData {
+    /// Offset of the grid lines from the top left corner of the blueprint, in tiles.
+    #[must_use]
+    pub fn grid_offset(&self) -> Position {
+        match (&self.position_relative_to_grid, self.absolute_snapping) {
+            (Some(pos), true) => Position {
+                x: -pos.x,
+                y: -pos.y,
+            },
+            _ => Position { x: 0.0, y: 0.0 },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Icon {
@@ -125,22 +273,71 @@ impl crate::GetIDs for Icon {
     }
 }
 
+/// [`SignalID`](https://lua-api.factorio.com/latest/concepts/SignalID.html), `quality` is only set for signals of a specific quality.
 #[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(tag = "type", rename_all = "lowercase")]
+#[serde(tag = "type", rename_all = "kebab-case")]
 pub enum SignalID {
-    Item { name: Option<ItemID> },
-    Fluid { name: Option<FluidID> },
-    Virtual { name: Option<VirtualSignalID> },
+    Item {
+        name: Option<ItemID>,
+        quality: Option<QualityID>,
+    },
+    Fluid {
+        name: Option<FluidID>,
+        quality: Option<QualityID>,
+    },
+    Virtual {
+        name: Option<VirtualSignalID>,
+        quality: Option<QualityID>,
+    },
+    Recipe {
+        name: Option<RecipeID>,
+        quality: Option<QualityID>,
+    },
+    Entity {
+        name: Option<EntityID>,
+        quality: Option<QualityID>,
+    },
+    SpaceLocation {
+        name: Option<SpaceLocationID>,
+        quality: Option<QualityID>,
+    },
+    AsteroidChunk {
+        name: Option<AsteroidChunkID>,
+        quality: Option<QualityID>,
+    },
+    Quality {
+        name: Option<QualityID>,
+        quality: Option<QualityID>,
+    },
 }
 
 impl SignalID {
     #[must_use]
     pub fn name(&self) -> Option<String> {
         match self {
-            Self::Item { name } => name.clone().map(|n| (*n).clone()),
-            Self::Fluid { name } => name.clone().map(|n| (*n).clone()),
-            Self::Virtual { name } => name.clone().map(|n| (*n).clone()),
+            Self::Item { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::Fluid { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::Virtual { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::Recipe { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::Entity { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::SpaceLocation { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::AsteroidChunk { name, .. } => name.as_ref().map(|n| (**n).clone()),
+            Self::Quality { name, .. } => name.as_ref().map(|n| (**n).clone()),
+        }
+    }
+
+    #[must_use]
+    pub const fn quality(&self) -> Option<&QualityID> {
+        match self {
+            Self::Item { quality, .. }
+            | Self::Fluid { quality, .. }
+            | Self::Virtual { quality, .. }
+            | Self::Recipe { quality, .. }
+            | Self::Entity { quality, .. }
+            | Self::SpaceLocation { quality, .. }
+            | Self::AsteroidChunk { quality, .. }
+            | Self::Quality { quality, .. } => quality.as_ref(),
         }
     }
 }
@@ -149,14 +346,33 @@ impl crate::GetIDs for SignalID {
     fn get_ids(&self) -> crate::UsedIDs {
         let mut ids = crate::UsedIDs::default();
 
-        if self.name().is_some() {
-            match self {
-                Self::Item { name } => ids.item.insert(name.clone().unwrap_or_default()),
-                Self::Fluid { name } => ids.fluid.insert(name.clone().unwrap_or_default()),
-                Self::Virtual { name } => {
-                    ids.virtual_signal.insert(name.clone().unwrap_or_default())
-                }
-            };
+        match self {
+            Self::Item {
+                name: Some(name), ..
+            } => {
+                ids.item.insert(name.clone());
+            }
+            Self::Fluid {
+                name: Some(name), ..
+            } => {
+                ids.fluid.insert(name.clone());
+            }
+            Self::Virtual {
+                name: Some(name), ..
+            } => {
+                ids.virtual_signal.insert(name.clone());
+            }
+            Self::Recipe {
+                name: Some(name), ..
+            } => {
+                ids.recipe.insert(name.clone());
+            }
+            Self::Entity {
+                name: Some(name), ..
+            } => {
+                ids.entity.insert(name.clone());
+            }
+            _ => {}
         }
 
         ids
@@ -167,6 +383,7 @@ pub type EntityNumber = u64;
 pub type GraphicsVariation = NonZeroU32;
 
 // todo: reduce optionals count by skipping serialization of defaults?
+#[allow(clippy::struct_excessive_bools)]
 #[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -175,18 +392,20 @@ pub struct Entity {
     pub name: EntityID,
     pub position: Position,
 
-    #[serde(default, skip_serializing_if = "Direction::is_default")]
-    pub direction: Direction,
+    #[serde(default, skip_serializing_if = "Direction16::is_default")]
+    pub direction: Direction16,
 
     pub orientation: Option<RealOrientation>,
 
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub neighbours: Vec<EntityNumber>,
+    /// Flipped horizontally, applied before the direction.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mirror: bool,
+
+    pub quality: Option<QualityID>,
 
     pub control_behavior: Option<ControlBehavior>,
-    pub connections: Option<Connection>,
 
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub items: ItemRequest,
 
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -204,22 +423,21 @@ pub struct Entity {
     pub input_priority: Option<SplitterPriority>,
     pub output_priority: Option<SplitterPriority>,
 
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub filter: ItemID,
+    pub filter: Option<ItemFilter>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub use_filters: bool,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub filters: IndexedVec<NameString<ItemID>>,
+    pub filters: IndexedVec<ItemFilter>,
 
     pub filter_mode: Option<FilterMode>,
     pub override_stack_size: Option<u8>,
     pub drop_position: Option<Position>,
     pub pickup_position: Option<Position>,
 
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub request_filters: IndexedVec<LogisticFilter>,
-
-    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
-    pub request_from_buffers: bool,
+    #[serde(default, skip_serializing_if = "RequestFilters::is_unset")]
+    pub request_filters: RequestFilters,
 
     pub parameters: Option<SpeakerParameter>,
     pub alert_parameters: Option<SpeakerAlertParameter>,
@@ -252,6 +470,30 @@ pub struct Entity {
     pub tags: mod_util::TagTable,
 }
 
+impl Entity {
+    /// The mod defined tag `key`.
+    #[must_use]
+    pub fn tag(&self, key: &str) -> Option<&AnyBasic> {
+        self.tags.get(key)
+    }
+
+    /// Follows `path` through the nested tag tables, e.g. `["bp_meta_info", "mods"]`.
+    #[must_use]
+    pub fn tag_path(&self, path: &[&str]) -> Option<&AnyBasic> {
+        let (key, rest) = path.split_first()?;
+        self.tag(key)?.get_path(rest)
+    }
+
+    /// Sets the tag `key`, returning the previous value.
+    pub fn set_tag(&mut self, key: impl Into<String>, value: AnyBasic) -> Option<AnyBasic> {
+        self.tags.insert(key.into(), value)
+    }
+
+    pub fn remove_tag(&mut self, key: &str) -> Option<AnyBasic> {
+        self.tags.remove(key)
+    }
+}
+
 impl PartialOrd for Entity {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         self.position.partial_cmp(&other.position)
@@ -284,15 +526,15 @@ impl crate::GetIDs for Entity {
             ids.merge(infinity_settings.get_ids());
         }
 
-        if !self.filter.is_empty() {
-            ids.item.insert(self.filter.clone());
+        if let Some(filter) = &self.filter {
+            ids.item.insert(filter.name.clone());
         }
 
         for entry in &self.filters {
             ids.item.insert(entry.name.clone());
         }
 
-        for entry in &self.request_filters {
+        for entry in self.request_filters.iter() {
             ids.item.insert(entry.name.clone());
         }
 
@@ -335,7 +577,7 @@ impl SplitterPriority {
 #[serde(deny_unknown_fields)]
 pub struct Inventory {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub filters: IndexedVec<NameString<ItemID>>,
+    pub filters: IndexedVec<ItemFilter>,
     pub bar: Option<ItemStackIndex>,
 }
 
@@ -351,10 +593,10 @@ impl crate::GetIDs for Inventory {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Schedule {
-    pub schedule: Vec<ScheduleRecord>,
+    pub schedule: ScheduleData,
     pub locomotives: Vec<EntityNumber>,
 }
 
@@ -364,16 +606,83 @@ impl crate::GetIDs for Schedule {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Stops & interrupts of a train schedule, 1.1 schedules only had stops
+/// and get upgraded to this when loading them.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleData {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<ScheduleRecord>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interrupts: Vec<ScheduleInterrupt>,
+
+    /// Trains with the same group share their schedule.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub group: String,
+}
+
+impl ScheduleData {
+    /// Every station name used by the stops, interrupt targets & station wait conditions.
+    pub fn stations_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.records
+            .iter_mut()
+            .flat_map(ScheduleRecord::stations_mut)
+            .chain(self.interrupts.iter_mut().flat_map(|interrupt| {
+                interrupt
+                    .conditions
+                    .iter_mut()
+                    .filter_map(WaitCondition::station_mut)
+                    .chain(
+                        interrupt
+                            .targets
+                            .iter_mut()
+                            .flat_map(ScheduleRecord::stations_mut),
+                    )
+            }))
+    }
+}
+
+impl crate::GetIDs for ScheduleData {
+    fn get_ids(&self) -> crate::UsedIDs {
+        let mut ids = self.records.get_ids();
+        ids.merge(self.interrupts.get_ids());
+        ids
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct ScheduleRecord {
+    /// Empty for temporary stops at a rail.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub station: String,
 
+    /// Rail of a temporary stop without a station.
+    pub rail: Option<Position>,
+    pub rail_direction: Option<RailDirection>,
+
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub wait_conditions: Vec<WaitCondition>,
 
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub temporary: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub created_by_interrupt: bool,
+
+    pub allows_unloading: Option<bool>,
+}
+
+impl ScheduleRecord {
+    fn stations_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        std::iter::once(&mut self.station).chain(
+            self.wait_conditions
+                .iter_mut()
+                .filter_map(WaitCondition::station_mut),
+        )
+    }
 }
 
 impl crate::GetIDs for ScheduleRecord {
@@ -382,7 +691,39 @@ impl crate::GetIDs for ScheduleRecord {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RailDirection {
+    Front,
+    Back,
+}
+
+/// Stops that get inserted into a schedule while its conditions are met.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleInterrupt {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<WaitCondition>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<ScheduleRecord>,
+
+    /// Whether the interrupt can trigger while another interrupt is running.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inside_interrupt: bool,
+}
+
+impl crate::GetIDs for ScheduleInterrupt {
+    fn get_ids(&self) -> crate::UsedIDs {
+        let mut ids = self.conditions.get_ids();
+        ids.merge(self.targets.get_ids());
+        ids
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 //#[serde(deny_unknown_fields)] // causes deserialization issues (https://github.com/serde-rs/serde/issues/1358)
 pub struct WaitCondition {
     pub compare_type: CompareType,
@@ -391,6 +732,18 @@ pub struct WaitCondition {
     pub condition: WaitConditionType,
 }
 
+impl WaitCondition {
+    const fn station_mut(&mut self) -> Option<&mut String> {
+        match &mut self.condition {
+            WaitConditionType::SpecificDestinationFull { station }
+            | WaitConditionType::SpecificDestinationNotFull { station }
+            | WaitConditionType::AtStation { station }
+            | WaitConditionType::NotAtStation { station } => station.as_mut(),
+            _ => None,
+        }
+    }
+}
+
 impl crate::GetIDs for WaitCondition {
     fn get_ids(&self) -> crate::UsedIDs {
         let mut ids = crate::UsedIDs::default();
@@ -398,7 +751,9 @@ impl crate::GetIDs for WaitCondition {
         match &self.condition {
             WaitConditionType::Circuit { condition }
             | WaitConditionType::ItemCount { condition }
-            | WaitConditionType::FluidCount { condition } => {
+            | WaitConditionType::FluidCount { condition }
+            | WaitConditionType::FuelItemCountAll { condition }
+            | WaitConditionType::FuelItemCountAny { condition } => {
                 if let Some(condition) = condition {
                     ids.merge(condition.get_ids());
                 }
@@ -415,6 +770,7 @@ impl crate::GetIDs for WaitCondition {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum WaitConditionType {
     Full,
+    NotFull,
     Empty,
     RobotsInactive,
     PassengerPresent,
@@ -424,6 +780,23 @@ pub enum WaitConditionType {
     Circuit { condition: Option<Condition> },
     ItemCount { condition: Option<Condition> },
     FluidCount { condition: Option<Condition> },
+
+    // 2.0
+    FuelItemCountAll { condition: Option<Condition> },
+    FuelItemCountAny { condition: Option<Condition> },
+    FuelFull,
+    DestinationFullOrNoPath,
+    RequestSatisfied,
+    RequestNotSatisfied,
+    AllRequestsSatisfied,
+    AnyRequestNotSatisfied,
+    AnyRequestZero,
+    AnyPlanetImportZero { planet: Option<String> },
+    SpecificDestinationFull { station: Option<String> },
+    SpecificDestinationNotFull { station: Option<String> },
+    AtStation { station: Option<String> },
+    NotAtStation { station: Option<String> },
+    DamageTaken { damage: u32 },
 }
 
 #[skip_serializing_none]
@@ -472,10 +845,11 @@ impl crate::GetIDs for Condition {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum CompareType {
     And,
+    #[default]
     Or,
 }
 
@@ -544,105 +918,61 @@ impl From<&Position> for types::Vector {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(untagged, deny_unknown_fields)]
-pub enum Connection {
-    Double {
-        #[serde(rename = "1")]
-        one: ConnectionPoint,
+/// A wire between two wire connectors, `[entity, connector, entity, connector]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct Wire(
+    pub EntityNumber,
+    pub WireConnectorID,
+    pub EntityNumber,
+    pub WireConnectorID,
+);
 
-        #[serde(rename = "2")]
-        two: ConnectionPoint,
-    },
-    SingleOne {
-        #[serde(rename = "1")]
-        one: ConnectionPoint,
-    },
-    SingleTwo {
-        #[serde(rename = "2")]
-        two: ConnectionPoint,
-    },
-    Switch {
-        #[serde(rename = "1")]
-        one: ConnectionPoint,
-
-        #[serde(rename = "Cu0", default, skip_serializing_if = "Vec::is_empty")]
-        cu0: Vec<ConnectionData>,
-        #[serde(rename = "Cu1", default, skip_serializing_if = "Vec::is_empty")]
-        cu1: Vec<ConnectionData>,
-    },
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(deny_unknown_fields)]
-pub struct ConnectionPoint {
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub red: Vec<ConnectionData>,
-
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub green: Vec<ConnectionData>,
-}
-
-impl ConnectionPoint {
-    pub fn transform(&self, map: &mut HashMap<EntityNumber, [bool; 3]>) {
-        for r in &self.red {
-            map.entry(r.entity_id())
-                .and_modify(|[_, x, _]| *x = true)
-                .or_insert([false, true, false]);
-        }
-
-        for g in &self.green {
-            map.entry(g.entity_id())
-                .and_modify(|[_, _, x]| *x = true)
-                .or_insert([false, false, true]);
-        }
+impl Wire {
+    /// Both ends of the wire as `(entity, connector)`.
+    #[must_use]
+    pub const fn ends(&self) -> [(EntityNumber, WireConnectorID); 2] {
+        [(self.0, self.1), (self.2, self.3)]
     }
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(untagged, deny_unknown_fields)]
-pub enum ConnectionData {
-    Connector {
-        entity_id: EntityNumber,
-        circuit_id: u8,
-    },
-    Switch {
-        entity_id: EntityNumber,
-        wire_id: u8,
-    },
-    NoConnector {
-        entity_id: EntityNumber,
-    },
-}
 
-impl ConnectionData {
+    /// The same wire with the lower end first, wires have no direction.
     #[must_use]
-    pub const fn entity_id(&self) -> EntityNumber {
-        match self {
-            Self::Connector { entity_id, .. }
-            | Self::Switch { entity_id, .. }
-            | Self::NoConnector { entity_id } => *entity_id,
+    pub fn canonical(self) -> Self {
+        if (self.2, self.3) < (self.0, self.1) {
+            Self(self.2, self.3, self.0, self.1)
+        } else {
+            self
         }
     }
 }
 
-pub trait ConnectionDataExt {
-    fn transform(&self, map: &mut HashMap<EntityNumber, [bool; 3]>);
-}
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde_repr::Deserialize_repr,
+    serde_repr::Serialize_repr,
+)]
+#[repr(u8)]
+pub enum WireConnectorID {
+    CircuitRed = 1,
+    CircuitGreen = 2,
+    CombinatorOutputRed = 3,
+    CombinatorOutputGreen = 4,
 
-impl ConnectionDataExt for Vec<ConnectionData> {
-    fn transform(&self, map: &mut HashMap<EntityNumber, [bool; 3]>) {
-        for data in self {
-            if let ConnectionData::Switch { entity_id, .. } = data {
-                map.entry(*entity_id)
-                    .and_modify(|[x, _, _]| *x = true)
-                    .or_insert([true, false, false]);
-            }
-        }
-    }
+    /// Copper connector of poles, the left one of power switches.
+    PoleCopper = 5,
+
+    /// Right copper connector of power switches.
+    PowerSwitchRightCopper = 6,
 }
 
-pub type ItemRequest = HashMap<ItemID, ItemCountType>;
+// ordered to keep re-encoded blueprint strings stable
+pub type ItemRequest = BTreeMap<ItemID, ItemCountType>;
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -701,11 +1031,82 @@ pub enum InfinityFilterMode {
     Add,
 }
 
+#[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct LogisticFilter {
     pub name: ItemID,
+
+    #[serde(default)]
     pub count: ItemCountType,
+
+    // 2.0 only
+    pub max_count: Option<ItemCountType>,
+    pub quality: Option<String>,
+    pub comparator: Option<Comparator>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ItemFilter {
+    pub name: ItemID,
+    pub quality: Option<String>,
+    pub comparator: Option<Comparator>,
+}
+
+impl std::fmt::Display for ItemFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+/// Logistic requests of an entity, grouped into logistic sections.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RequestFilters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: IndexedVec<LogisticSection>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub request_from_buffers: bool,
+
+    pub trash_not_requested: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+impl RequestFilters {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    fn is_unset(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// All requests of active sections in order.
+    pub fn iter(&self) -> impl Iterator<Item = &LogisticFilter> {
+        self.sections
+            .iter()
+            .filter(|section| section.active)
+            .flat_map(|section| section.filters.iter().map(|f| &**f))
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LogisticSection {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: IndexedVec<LogisticFilter>,
+
+    pub group: Option<String>,
+    pub multiplier: Option<f64>,
+
+    #[serde(default = "default_true", skip_serializing_if = "Clone::clone")]
+    pub active: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -1016,6 +1417,14 @@ impl crate::GetIDs for ArithmeticData {
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(untagged, deny_unknown_fields)]
 pub enum DeciderData {
+    /// 2.0 decider with any number of conditions & outputs.
+    Conditions {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        conditions: Vec<DeciderCondition>,
+
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        outputs: Vec<DeciderOutput>,
+    },
     Signal {
         first_signal: Option<SignalID>,
         second_signal: Option<SignalID>,
@@ -1042,11 +1451,45 @@ pub enum DeciderData {
 
 impl DeciderData {
     #[must_use]
-    pub const fn operation(&self) -> Comparator {
+    pub fn operation(&self) -> Comparator {
         match self {
+            Self::Conditions { conditions, .. } => conditions
+                .first()
+                .map(|condition| condition.comparator)
+                .unwrap_or_default(),
             Self::Signal { comparator, .. } | Self::Constant { comparator, .. } => *comparator,
         }
     }
+
+    /// Number of conditions, 1.1 deciders always have exactly one.
+    #[must_use]
+    pub const fn condition_count(&self) -> usize {
+        match self {
+            Self::Conditions { conditions, .. } => conditions.len(),
+            Self::Signal { .. } | Self::Constant { .. } => 1,
+        }
+    }
+
+    /// Signals compared by the first condition.
+    #[must_use]
+    pub fn first_condition_signals(&self) -> (Option<&SignalID>, Option<&SignalID>) {
+        match self {
+            Self::Conditions { conditions, .. } => {
+                conditions.first().map_or((None, None), |condition| {
+                    (
+                        condition.first_signal.as_ref(),
+                        condition.second_signal.as_ref(),
+                    )
+                })
+            }
+            Self::Signal {
+                first_signal,
+                second_signal,
+                ..
+            } => (first_signal.as_ref(), second_signal.as_ref()),
+            Self::Constant { first_signal, .. } => (first_signal.as_ref(), None),
+        }
+    }
 }
 
 impl crate::GetIDs for DeciderData {
@@ -1054,6 +1497,18 @@ impl crate::GetIDs for DeciderData {
         let mut ids = crate::UsedIDs::default();
 
         match self {
+            Self::Conditions {
+                conditions,
+                outputs,
+            } => {
+                for condition in conditions {
+                    ids.merge(condition.get_ids());
+                }
+
+                for output in outputs {
+                    ids.merge(output.get_ids());
+                }
+            }
             Self::Signal {
                 first_signal,
                 second_signal,
@@ -1091,6 +1546,93 @@ impl crate::GetIDs for DeciderData {
     }
 }
 
+// https://lua-api.factorio.com/latest/concepts/DeciderCombinatorCondition.html
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct DeciderCondition {
+    pub first_signal: Option<SignalID>,
+    pub second_signal: Option<SignalID>,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub constant: i32,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub comparator: Comparator,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub first_signal_networks: CircuitNetworkSelection,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub second_signal_networks: CircuitNetworkSelection,
+
+    /// How the condition is combined with the previous one, ignored for the first condition.
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub compare_type: CompareType,
+}
+
+impl crate::GetIDs for DeciderCondition {
+    fn get_ids(&self) -> crate::UsedIDs {
+        let mut ids = crate::UsedIDs::default();
+
+        if let Some(signal) = &self.first_signal {
+            ids.merge(signal.get_ids());
+        }
+
+        if let Some(signal) = &self.second_signal {
+            ids.merge(signal.get_ids());
+        }
+
+        ids
+    }
+}
+
+// https://lua-api.factorio.com/latest/concepts/DeciderCombinatorOutput.html
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct DeciderOutput {
+    pub signal: Option<SignalID>,
+
+    #[serde(default = "default_true", skip_serializing_if = "Clone::clone")]
+    pub copy_count_from_input: bool,
+
+    #[serde(default = "default_one", skip_serializing_if = "is_one")]
+    pub constant: i32,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub networks: CircuitNetworkSelection,
+}
+
+impl crate::GetIDs for DeciderOutput {
+    fn get_ids(&self) -> crate::UsedIDs {
+        self.signal
+            .as_ref()
+            .map(crate::GetIDs::get_ids)
+            .unwrap_or_default()
+    }
+}
+
+/// Circuit networks a signal is read from, both by default.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitNetworkSelection {
+    #[serde(default = "default_true")]
+    pub red: bool,
+
+    #[serde(default = "default_true")]
+    pub green: bool,
+}
+
+impl Default for CircuitNetworkSelection {
+    fn default() -> Self {
+        Self {
+            red: true,
+            green: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct SpeakerCircuitParameters {
@@ -1123,3 +1665,12 @@ where
 const fn default_true() -> bool {
     true
 }
+
+const fn default_one() -> i32 {
+    1
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_one(value: &i32) -> bool {
+    *value == 1
+}
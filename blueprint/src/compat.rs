@@ -0,0 +1,329 @@
+//! Upgrades of blueprint JSON from older game versions, see [`upgrade`].
+
+use std::collections::HashSet;
+
+use serde_json::{json, Map, Value};
+use tracing::debug;
+
+/// Version number of 2.0.0.0, see [`crate::CommonData::version`].
+pub const VERSION_2_0: u64 = 2 << 48;
+
+/// Entities & their items that were renamed or merged into other entities in 2.0.
+const RENAMED_ENTITIES: [(&str, &str); 10] = [
+    ("logistic-chest-active-provider", "active-provider-chest"),
+    ("logistic-chest-passive-provider", "passive-provider-chest"),
+    ("logistic-chest-storage", "storage-chest"),
+    ("logistic-chest-buffer", "buffer-chest"),
+    ("logistic-chest-requester", "requester-chest"),
+    ("filter-inserter", "fast-inserter"),
+    ("stack-inserter", "bulk-inserter"),
+    ("stack-filter-inserter", "bulk-inserter"),
+    ("straight-rail", "legacy-straight-rail"),
+    ("curved-rail", "legacy-curved-rail"),
+];
+
+/// Entities that always used their filters in 1.1.
+const FILTER_INSERTERS: [&str; 2] = ["filter-inserter", "stack-filter-inserter"];
+
+/// Wire connector of 1.1 circuit connections: `[point]["red" | "green"]`.
+const CIRCUIT_CONNECTORS: [[(&str, u8); 2]; 2] =
+    [[("red", 1), ("green", 2)], [("red", 3), ("green", 4)]];
+
+const COPPER_CONNECTORS: [(&str, u8); 2] = [("Cu0", 5), ("Cu1", 6)];
+
+#[must_use]
+pub const fn major_version(version: u64) -> u64 {
+    version >> 48
+}
+
+/// Converts decoded blueprint JSON from older game versions into the 2.0 format.
+///
+/// Every blueprint, book & planner is upgraded based on its own version and gets the
+/// 2.0 version afterwards, so encoding it again results in a 2.0 string. Data without a
+/// version is left as it is.
+pub fn upgrade(json: &mut Value) {
+    let Some(root) = json.as_object_mut() else {
+        return;
+    };
+
+    for (kind, data) in root {
+        upgrade_data(kind, data);
+    }
+}
+
+fn upgrade_data(kind: &str, data: &mut Value) {
+    let Some(data) = data.as_object_mut() else {
+        return;
+    };
+
+    if kind == "blueprint_book" {
+        for entry in data
+            .get_mut("blueprints")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut)
+        {
+            for (kind, data) in entry.iter_mut().filter(|(key, _)| *key != "index") {
+                upgrade_data(kind, data);
+            }
+        }
+    }
+
+    // a missing version is left to the deserializer
+    let Some(version) = data.get("version").and_then(Value::as_u64) else {
+        return;
+    };
+
+    if major_version(version) >= 2 {
+        return;
+    }
+
+    debug!("upgrading {kind} from version {version}");
+
+    match kind {
+        "blueprint" => upgrade_blueprint(data),
+        "upgrade_planner" => upgrade_mappers(data),
+        "deconstruction_planner" => {
+            for filter in settings_array(data, "entity_filters") {
+                rename_entity(filter);
+            }
+        }
+        _ => {}
+    }
+
+    // books & blueprints store their icons directly, planners in their settings
+    upgrade_icons(data.get_mut("icons"));
+    upgrade_icons(
+        data.get_mut("settings")
+            .and_then(|settings| settings.get_mut("icons")),
+    );
+
+    data.insert("version".to_owned(), VERSION_2_0.into());
+}
+
+/// The 2.0 name of an entity or its item, `None` if it was not renamed.
+fn renamed(name: &str) -> Option<&'static str> {
+    RENAMED_ENTITIES
+        .iter()
+        .find(|(old, _)| *old == name)
+        .map(|(_, new)| *new)
+}
+
+/// Replaces the `name` of an entity, item filter or signal with its 2.0 name.
+fn rename_entity(value: &mut Map<String, Value>) {
+    if let Some(new) = value.get("name").and_then(Value::as_str).and_then(renamed) {
+        value.insert("name".to_owned(), new.into());
+    }
+}
+
+fn upgrade_icons(icons: Option<&mut Value>) {
+    for signal in icons
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|icon| icon.get_mut("signal")?.as_object_mut())
+    {
+        // item signals don't need a type
+        if matches!(
+            signal.get("type").and_then(Value::as_str),
+            None | Some("item" | "entity")
+        ) {
+            rename_entity(signal);
+        }
+    }
+}
+
+/// Objects of the array `key` in the settings of a planner.
+fn settings_array<'a>(
+    planner: &'a mut Map<String, Value>,
+    key: &str,
+) -> impl Iterator<Item = &'a mut Map<String, Value>> {
+    planner
+        .get_mut("settings")
+        .and_then(|settings| settings.get_mut(key))
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+fn upgrade_mappers(planner: &mut Map<String, Value>) {
+    for mapper in settings_array(planner, "mappers") {
+        for side in ["from", "to"] {
+            if let Some(value) = mapper.get_mut(side).and_then(Value::as_object_mut) {
+                rename_entity(value);
+            }
+        }
+    }
+}
+
+fn upgrade_blueprint(bp: &mut Map<String, Value>) {
+    let mut wires = Wires::default();
+
+    for entity in bp
+        .get_mut("entities")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        upgrade_entity(entity, &mut wires);
+    }
+
+    if !wires.list.is_empty() {
+        bp.insert("wires".to_owned(), json!(wires.list));
+    }
+
+    // schedules only had their records in 1.1
+    for schedule in bp
+        .get_mut("schedules")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        if let Some(records) = schedule.get_mut("schedule").filter(|r| r.is_array()) {
+            *records = json!({ "records": records.take() });
+        }
+    }
+}
+
+fn upgrade_entity(entity: &mut Map<String, Value>, wires: &mut Wires) {
+    let name = entity
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    if FILTER_INSERTERS.contains(&name) {
+        entity.insert("use_filters".to_owned(), true.into());
+    }
+
+    rename_entity(entity);
+
+    // 8 -> 16 directions
+    if let Some(direction) = entity.get("direction").and_then(Value::as_u64) {
+        entity.insert("direction".to_owned(), (direction * 2).into());
+    }
+
+    // splitters & loaders only stored the item name
+    if let Some(Value::String(name)) = entity.remove("filter") {
+        entity.insert("filter".to_owned(), json!({ "name": name }));
+    }
+
+    upgrade_requests(entity);
+
+    let number = entity
+        .get("entity_number")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+
+    if let Some(Value::Array(neighbours)) = entity.remove("neighbours") {
+        for target in neighbours.iter().filter_map(Value::as_u64) {
+            wires.insert((number, 5), (target, 5));
+        }
+    }
+
+    let Some(Value::Object(connections)) = entity.remove("connections") else {
+        return;
+    };
+
+    for (point, connectors) in ["1", "2"].into_iter().zip(CIRCUIT_CONNECTORS) {
+        for (color_idx, (color, source)) in connectors.into_iter().enumerate() {
+            for target in connection_targets(&connections, point, color) {
+                // the circuit_id of the target selects its connection point
+                let target_point =
+                    usize::from(target.get("circuit_id").and_then(Value::as_u64) == Some(2));
+                let connector = CIRCUIT_CONNECTORS[target_point][color_idx].1;
+
+                if let Some(target) = target.get("entity_id").and_then(Value::as_u64) {
+                    wires.insert((number, source), (target, connector));
+                }
+            }
+        }
+    }
+
+    for (point, source) in COPPER_CONNECTORS {
+        for target in connections
+            .get(point)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let connector = if target.get("wire_id").and_then(Value::as_u64) == Some(1) {
+                6
+            } else {
+                5
+            };
+
+            if let Some(target) = target.get("entity_id").and_then(Value::as_u64) {
+                wires.insert((number, source), (target, connector));
+            }
+        }
+    }
+}
+
+fn connection_targets<'a>(
+    connections: &'a Map<String, Value>,
+    point: &str,
+    color: &str,
+) -> impl Iterator<Item = &'a Value> {
+    connections
+        .get(point)
+        .and_then(|point| point.get(color))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// 1.1 stored a flat list of requests and the buffer flag on the entity.
+fn upgrade_requests(entity: &mut Map<String, Value>) {
+    let from_buffers = entity
+        .remove("request_from_buffers")
+        .and_then(|v| v.as_bool())
+        .unwrap_or_default();
+
+    let filters = match entity.remove("request_filters") {
+        Some(Value::Array(filters)) => filters,
+        Some(other) => {
+            entity.insert("request_filters".to_owned(), other);
+            return;
+        }
+        None => Vec::new(),
+    };
+
+    if filters.is_empty() && !from_buffers {
+        return;
+    }
+
+    let mut requests = Map::new();
+    if !filters.is_empty() {
+        requests.insert(
+            "sections".to_owned(),
+            json!([{ "index": 1, "filters": filters }]),
+        );
+    }
+    if from_buffers {
+        requests.insert("request_from_buffers".to_owned(), true.into());
+    }
+
+    entity.insert("request_filters".to_owned(), requests.into());
+}
+
+/// 1.1 stored wires on both ends, 2.0 only lists every wire once.
+#[derive(Default)]
+struct Wires {
+    list: Vec<[u64; 4]>,
+    seen: HashSet<[u64; 4]>,
+}
+
+impl Wires {
+    fn insert(&mut self, a: (u64, u8), b: (u64, u8)) {
+        let (first, second) = if a <= b { (a, b) } else { (b, a) };
+        let wire = [first.0, first.1.into(), second.0, second.1.into()];
+
+        if self.seen.insert(wire) {
+            self.list.push(wire);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::ops::Range;
+
+use base64::{engine::general_purpose, Engine};
+
+use crate::{BlueprintDecodeError, Data};
+
+/// Shortest payload that is tried, anything shorter can't hold a zlib stream of a blueprint.
+const MIN_PAYLOAD_LEN: usize = 20;
+
+/// A blueprint string found inside arbitrary text.
+#[derive(Debug, Clone)]
+pub struct ExtractedBlueprint {
+    pub data: Data,
+
+    /// Byte range of the blueprint string inside the text.
+    pub span: Range<usize>,
+}
+
+const fn is_base64(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=')
+}
+
+/// Finds the first decodable blueprint string in `text`, e.g. a chat message with words around it.
+///
+/// Every candidate of [`find_blueprint_strings`] is decoded once until one of them is a blueprint.
+#[must_use]
+pub fn extract(text: &str) -> Option<ExtractedBlueprint> {
+    find_blueprint_strings(text).find_map(|span| {
+        let data = Data::try_from(&text[span.clone()]).ok()?;
+        Some(ExtractedBlueprint { data, span })
+    })
+}
+
+/// Byte ranges of the strings in `text` that look like a blueprint string, without decoding them.
+///
+/// Candidates are the ends of base64 runs starting at the version byte `0` followed by a valid
+/// zlib header, at most one per run.
+pub fn find_blueprint_strings(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    base64_runs(text).filter_map(|run| {
+        text[run.clone()]
+            .match_indices('0')
+            .map(|(offset, _)| run.start + offset)
+            .take_while(|start| run.end - start >= MIN_PAYLOAD_LEN)
+            .find(|start| is_payload(&text[start + 1..run.end]))
+            .map(|start| start..run.end)
+    })
+}
+
+/// Padded base64 that starts with a zlib header.
+fn is_payload(payload: &str) -> bool {
+    if !payload.len().is_multiple_of(4) {
+        return false;
+    }
+
+    let Ok(header) = general_purpose::STANDARD.decode(&payload[..4]) else {
+        return false;
+    };
+
+    // deflate compression method & header checksum of RFC 1950
+    header[0] & 0x0F == 8 && (u16::from(header[0]) << 8 | u16::from(header[1])).is_multiple_of(31)
+}
+
+/// Maximal runs of base64 characters in `text`.
+fn base64_runs(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut chars = text.char_indices().peekable();
+
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| is_base64(*c))?;
+        let mut end = start + 1;
+        while let Some((idx, _)) = chars.next_if(|(_, c)| is_base64(*c)) {
+            end = idx + 1;
+        }
+
+        Some(start..end)
+    })
+}
+
+impl Data {
+    /// Decodes the trimmed text as a blueprint string, falling back to the first blueprint string inside it.
+    /// The error of decoding the whole text is returned if no blueprint string is found.
+    pub fn from_text(text: &str) -> Result<Self, BlueprintDecodeError> {
+        Self::try_from(text.trim()).or_else(|err| extract(text).map(|e| e.data).ok_or(err))
+    }
+}
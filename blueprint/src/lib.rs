@@ -1,20 +1,37 @@
+//! Blueprint strings & the blueprints, books and planners inside of them.
+//!
+//! A blueprint string is a version byte (`0`) followed by the base64 encoded, zlib compressed JSON.
+//! [`Data::try_from`] decodes a string, [`Data::from_text`] also finds it inside of other text
+//! (e.g. a chat message) and [`Data::from_json`] loads the already decoded JSON.
+//! `String::try_from(data)` encodes it again.
+//!
+//! Every layer of the string reports its own [`BlueprintDecodeError`] variant with the position
+//! of the problem, so users can be told whether the string was cut off, mangled while copying
+//! or contains data this crate doesn't understand.
 #![allow(dead_code)]
 
-use std::{collections::HashSet, io::prelude::*};
+use std::{cmp::Ordering, collections::HashSet, io::prelude::*};
 
 use base64::{engine::general_purpose, Engine};
-use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+use flate2::{write::ZlibEncoder, Decompress, FlushDecompress, Status};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use tracing::{debug, instrument};
 
 mod blueprint;
 mod book;
+pub mod compat;
+mod extract;
 mod planner;
+mod scrub;
+mod surface;
 
 pub use blueprint::*;
 pub use book::*;
+pub use extract::*;
 pub use planner::*;
+pub use scrub::*;
+pub use surface::*;
 use types::{EntityID, FluidID, ItemID, RecipeID, TileID, VirtualSignalID};
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
@@ -93,8 +110,7 @@ impl TryFrom<Blueprint> for String {
     type Error = BlueprintEncodeError;
 
     fn try_from(data: Blueprint) -> Result<Self, Self::Error> {
-        let json = serde_json::to_string(&Data::Blueprint(data))?;
-        json_to_bp_string(&json)
+        Self::try_from(Data::Blueprint(data))
     }
 }
 
@@ -102,8 +118,7 @@ impl TryFrom<Book> for String {
     type Error = BlueprintEncodeError;
 
     fn try_from(data: Book) -> Result<Self, Self::Error> {
-        let json = serde_json::to_string(&Data::BlueprintBook(data))?;
-        json_to_bp_string(&json)
+        Self::try_from(Data::BlueprintBook(data))
     }
 }
 
@@ -111,8 +126,7 @@ impl TryFrom<UpgradePlanner> for String {
     type Error = BlueprintEncodeError;
 
     fn try_from(data: UpgradePlanner) -> Result<Self, Self::Error> {
-        let json = serde_json::to_string(&Data::UpgradePlanner(data))?;
-        json_to_bp_string(&json)
+        Self::try_from(Data::UpgradePlanner(data))
     }
 }
 
@@ -120,8 +134,7 @@ impl TryFrom<DeconPlanner> for String {
     type Error = BlueprintEncodeError;
 
     fn try_from(data: DeconPlanner) -> Result<Self, Self::Error> {
-        let json = serde_json::to_string(&Data::DeconstructionPlanner(data))?;
-        json_to_bp_string(&json)
+        Self::try_from(Data::DeconstructionPlanner(data))
     }
 }
 
@@ -150,6 +163,10 @@ pub struct Indexed<T> {
 
 pub type IndexedVec<T> = Vec<Indexed<T>>;
 
+fn sort_indexed<T>(vec: &mut IndexedVec<T>) {
+    vec.sort_by_key(|entry| entry.index);
+}
+
 impl<T> std::ops::Deref for Indexed<T> {
     type Target = T;
 
@@ -271,7 +288,7 @@ impl Data {
         }
     }
 
-    pub fn as_book_mut(&mut self) -> Option<&mut Book> {
+    pub const fn as_book_mut(&mut self) -> Option<&mut Book> {
         match self {
             Self::BlueprintBook(data) => Some(data),
             _ => None,
@@ -334,8 +351,9 @@ impl Data {
 
                 let width = ((max_x - min_x) / 2.0).round();
                 let height = ((max_y - min_y) / 2.0).round();
-                let offset_x = (min_x + width).round();
-                let offset_y = (min_y + height).round();
+                // ties to even so normalizing an already normalized blueprint is a no-op
+                let offset_x = (min_x + width).round_ties_even();
+                let offset_y = (min_y + height).round_ties_even();
 
                 // only offset an even amount
                 let offset_x = if offset_x % 2.0 == 0.0 {
@@ -366,23 +384,143 @@ impl Data {
         }
     }
 
+    /// Brings every list into a canonical order so equal contents always encode to the same string.
     fn ensure_ordering(&mut self) {
         match self {
             Self::BlueprintBook(data) => {
+                sort_indexed(&mut data.icons);
+                sort_indexed(&mut data.blueprints);
+
                 for entry in &mut data.blueprints {
                     entry.data.ensure_ordering();
                 }
             }
             Self::Blueprint(data) => {
-                data.entities
-                    .sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-                data.tiles
-                    .sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                sort_indexed(&mut data.icons);
+
+                data.entities.sort_by(|a, b| {
+                    a.partial_cmp(b)
+                        .unwrap_or(Ordering::Equal)
+                        .then_with(|| a.name.cmp(&b.name))
+                        .then_with(|| a.entity_number.cmp(&b.entity_number))
+                });
+                data.tiles.sort_by(|a, b| {
+                    a.partial_cmp(b)
+                        .unwrap_or(Ordering::Equal)
+                        .then_with(|| a.name.cmp(&b.name))
+                });
+
+                for wire in &mut data.wires {
+                    *wire = wire.canonical();
+                }
+                data.wires.sort_unstable();
+                data.wires.dedup();
             }
-            // TODO: sort ordering for upgrade planner and deconstruction planner
-            _ => {}
+            Self::UpgradePlanner(data) => {
+                sort_indexed(&mut data.mappers);
+                sort_indexed(&mut data.icons);
+            }
+            Self::DeconstructionPlanner(data) => {
+                sort_indexed(&mut data.entity_filters);
+                sort_indexed(&mut data.tile_filters);
+                sort_indexed(&mut data.icons);
+            }
+        }
+    }
+}
+
+/// Inserts the entities, tiles, wires & schedules of `other` into `base`, moved by `offset`.
+///
+/// The entities of `other` get numbers after the highest one of `base` and its wires &
+/// schedules are remapped to them. Tiles of `other` replace the tiles of `base` at the same position.
+/// Label, icons, snapping & parameters of `base` are kept.
+/// The offset should be a multiple of 2 if any of the blueprints contains rails.
+pub fn merge(base: &mut Blueprint, other: &Blueprint, offset: &Position) {
+    let shift = base
+        .entities
+        .iter()
+        .map(|e| e.entity_number)
+        .max()
+        .unwrap_or_default();
+    let moved = |position: &Position| Position {
+        x: position.x + offset.x,
+        y: position.y + offset.y,
+    };
+
+    base.entities.extend(other.entities.iter().map(|e| {
+        let mut e = e.clone();
+        e.entity_number += shift;
+        e.position = moved(&e.position);
+        e
+    }));
+
+    base.wires.extend(
+        other
+            .wires
+            .iter()
+            .map(|wire| Wire(wire.0 + shift, wire.1, wire.2 + shift, wire.3)),
+    );
+
+    base.schedules
+        .extend(other.schedules.iter().map(|schedule| Schedule {
+            schedule: schedule.schedule.clone(),
+            locomotives: schedule.locomotives.iter().map(|n| n + shift).collect(),
+        }));
+
+    let tiles = other
+        .tiles
+        .iter()
+        .map(|tile| Tile {
+            name: tile.name.clone(),
+            position: moved(&tile.position),
+        })
+        .collect::<Vec<_>>();
+    let occupied = tiles
+        .iter()
+        .map(|t| (t.position.x.to_bits(), t.position.y.to_bits()))
+        .collect::<HashSet<_>>();
+    base.tiles
+        .retain(|t| !occupied.contains(&(t.position.x.to_bits(), t.position.y.to_bits())));
+    base.tiles.extend(tiles);
+
+    debug!(
+        "merged {} entities & {} tiles at {}, {}",
+        other.entities.len(),
+        other.tiles.len(),
+        offset.x,
+        offset.y
+    );
+}
+
+/// Repeats `bp` in a grid of `columns` x `rows` copies, each copy is moved by `step` from its neighbours.
+///
+/// If `bp` snaps to a grid the grid of the result covers all copies, so it can be tiled again.
+#[must_use]
+pub fn repeat(bp: &Blueprint, columns: u32, rows: u32, step: &Position) -> Blueprint {
+    let mut res = bp.clone();
+
+    for row in 0..rows {
+        for column in 0..columns {
+            if row == 0 && column == 0 {
+                continue;
+            }
+
+            let offset = Position {
+                x: step.x * column as f32,
+                y: step.y * row as f32,
+            };
+            merge(&mut res, bp, &offset);
         }
     }
+
+    if res.snapping.snap_to_grid.is_some() {
+        res.snapping.snap_to_grid = Some(Position {
+            x: step.x * columns as f32,
+            y: step.y * rows as f32,
+        });
+    }
+
+    res
 }
 
 impl GetIDs for Data {
@@ -407,14 +545,34 @@ pub enum BlueprintDecodeError {
     #[error("blueprint string parsing failed")]
     Parsing,
 
-    #[error("blueprint string decoding failed: {0}")]
-    Decoding(#[from] base64::DecodeError),
-
-    #[error("blueprint string decompression failed: {0}")]
-    Decompress(#[from] std::io::Error),
-
-    #[error("blueprint string deserialization failed: {0}")]
-    Deserializing(#[from] serde_json::Error),
+    /// `position` is the character of the blueprint string, counting the version byte.
+    #[error("blueprint string is not valid base64 at character {position}: {source}")]
+    Decoding {
+        position: usize,
+        source: base64::DecodeError,
+    },
+
+    /// `offset` is the byte of the base64 decoded data at which the zlib stream broke.
+    #[error("blueprint string decompression failed at byte {offset}: {source}")]
+    Decompress {
+        offset: u64,
+        source: flate2::DecompressError,
+    },
+
+    /// The zlib stream ended early, usually because the string was cut off while copying it.
+    #[error("blueprint string is incomplete, the compressed data ends after {0} bytes")]
+    Truncated(usize),
+
+    /// `0` is the byte of the decompressed JSON that is not valid UTF-8.
+    #[error("blueprint string contains invalid utf-8 at byte {0}")]
+    Utf8(usize),
+
+    /// The line & column of the problem are part of the message.
+    #[error("blueprint string contains invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("blueprint string deserialization failed at {}: {}", .0.path(), .0.inner())]
+    Deserializing(#[from] serde_path_to_error::Error<serde_json::Error>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -426,6 +584,7 @@ pub enum BlueprintEncodeError {
     Serializing(#[from] serde_json::Error),
 }
 
+/// Decodes the base64 & zlib layers of a blueprint string into its JSON.
 pub fn bp_string_to_json(bp_string: &str) -> Result<String, BlueprintDecodeError> {
     if bp_string.len() < 2 {
         return Err(BlueprintDecodeError::MinSize);
@@ -442,17 +601,58 @@ pub fn bp_string_to_json(bp_string: &str) -> Result<String, BlueprintDecodeError
         None => return Err(BlueprintDecodeError::Parsing),
     }
 
-    let compressed = general_purpose::STANDARD.decode(chars.as_str())?;
+    let compressed = general_purpose::STANDARD
+        .decode(chars.as_str())
+        .map_err(|source| {
+            let position = match source {
+                base64::DecodeError::InvalidByte(offset, _)
+                | base64::DecodeError::InvalidLastSymbol(offset, _) => offset + 1,
+                base64::DecodeError::InvalidLength(_) | base64::DecodeError::InvalidPadding => {
+                    bp_string.len()
+                }
+            };
 
-    let mut deflate = ZlibDecoder::new(compressed.as_slice());
-    let mut uncompressed = String::new();
+            BlueprintDecodeError::Decoding { position, source }
+        })?;
 
-    deflate.read_to_string(&mut uncompressed)?;
-    // .map_err(|err| Err(BlueprintError::Decompressing(err.to_string())))?;
+    String::from_utf8(decompress(&compressed)?)
+        .map_err(|err| BlueprintDecodeError::Utf8(err.utf8_error().valid_up_to()))
+}
+
+fn decompress(compressed: &[u8]) -> Result<Vec<u8>, BlueprintDecodeError> {
+    let mut zlib = Decompress::new(true);
+    let mut uncompressed = Vec::with_capacity(compressed.len() * 8);
+
+    loop {
+        if uncompressed.len() == uncompressed.capacity() {
+            uncompressed.reserve(uncompressed.capacity().max(1024));
+        }
 
-    Ok(uncompressed)
+        let before = (zlib.total_in(), uncompressed.len());
+        let consumed = usize::try_from(zlib.total_in()).unwrap_or(compressed.len());
+        let status = zlib
+            .decompress_vec(
+                &compressed[consumed..],
+                &mut uncompressed,
+                FlushDecompress::None,
+            )
+            .map_err(|source| BlueprintDecodeError::Decompress {
+                offset: zlib.total_in(),
+                source,
+            })?;
+
+        if status == Status::StreamEnd {
+            return Ok(uncompressed);
+        }
+
+        // there is always room for more output, so no progress means the input ran out before the end of the stream
+        if before == (zlib.total_in(), uncompressed.len()) {
+            return Err(BlueprintDecodeError::Truncated(compressed.len()));
+        }
+    }
 }
 
+/// Compresses & encodes the JSON of a blueprint into a blueprint string.
 pub fn json_to_bp_string(json: &str) -> Result<String, BlueprintEncodeError> {
     let mut deflate = ZlibEncoder::new(Vec::new(), flate2::Compression::new(9));
     deflate.write_all(json.as_bytes())?;
@@ -465,13 +665,14 @@ pub fn json_to_bp_string(json: &str) -> Result<String, BlueprintEncodeError> {
     Ok(encoded)
 }
 
-impl TryFrom<&str> for Data {
-    type Error = BlueprintDecodeError;
+impl Data {
+    /// Loads the decoded JSON of a blueprint string, e.g. from [`bp_string_to_json`] or another tool.
+    #[instrument(name = "json2bp_data", skip(json))]
+    pub fn from_json(json: &str) -> Result<Self, BlueprintDecodeError> {
+        let mut json = serde_json::from_str(json)?;
+        compat::upgrade(&mut json);
 
-    #[instrument(name = "str2bp_data", skip(bp_string))]
-    fn try_from(bp_string: &str) -> Result<Self, Self::Error> {
-        let json = bp_string_to_json(bp_string)?;
-        let mut data: Self = serde_json::from_str(&json)?;
+        let mut data: Self = serde_path_to_error::deserialize(json)?;
 
         data.normalize_positions();
         data.ensure_ordering();
@@ -480,6 +681,15 @@ impl TryFrom<&str> for Data {
     }
 }
 
+impl TryFrom<&str> for Data {
+    type Error = BlueprintDecodeError;
+
+    #[instrument(name = "str2bp_data", skip(bp_string))]
+    fn try_from(bp_string: &str) -> Result<Self, Self::Error> {
+        Self::from_json(&bp_string_to_json(bp_string)?)
+    }
+}
+
 impl TryFrom<String> for Data {
     type Error = BlueprintDecodeError;
 
@@ -492,7 +702,8 @@ impl TryFrom<Data> for String {
     type Error = BlueprintEncodeError;
 
     #[instrument(name = "bp_data2str", skip(data))]
-    fn try_from(data: Data) -> Result<Self, Self::Error> {
+    fn try_from(mut data: Data) -> Result<Self, Self::Error> {
+        data.ensure_ordering();
         let json = serde_json::to_string(&data)?;
 
         json_to_bp_string(&json)
@@ -512,8 +723,69 @@ mod tests {
         use super::*;
 
         #[test]
+        #[allow(clippy::unwrap_used)]
         fn train_schedule_temporary_record() {
-            load_bp(include_str!("../tests/train_schedule_temporary_record.txt"));
+            let data = load_bp(include_str!("../tests/train_schedule_temporary_record.txt"));
+            let reencoded = load_bp(&String::try_from(data.clone()).unwrap());
+
+            let schedules = &data.as_blueprint().unwrap().schedules;
+            let record = &schedules[0].schedule.records[0];
+            assert!(record.temporary);
+            assert_eq!(
+                record.wait_conditions[0].condition,
+                WaitConditionType::Time { ticks: 300 }
+            );
+            assert_eq!(&reencoded.as_blueprint().unwrap().schedules, schedules);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn train_schedule_interrupts() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],
+                "entities":[{"entity_number":1,"name":"locomotive","position":{"x":0,"y":4},"orientation":0.25}],
+                "schedules":[{"locomotives":[1],"schedule":{"group":"Iron",
+                    "records":[
+                        {"station":"Iron Pickup","wait_conditions":[{"type":"full","compare_type":"or"},{"type":"inactivity","compare_type":"or","ticks":300}]},
+                        {"rail":{"x":10,"y":3},"rail_direction":"front","temporary":true,"wait_conditions":[{"type":"time","compare_type":"or","ticks":60}]},
+                        {"station":"Fuel","created_by_interrupt":true,"allows_unloading":false}
+                    ],
+                    "interrupts":[{"name":"Refuel","inside_interrupt":true,
+                        "conditions":[
+                            {"type":"fuel_item_count_any","compare_type":"or","condition":{"first_signal":{"type":"item","name":"coal"},"constant":5,"comparator":"<"}},
+                            {"type":"not_at_station","compare_type":"and","station":"Fuel"},
+                            {"type":"damage_taken","compare_type":"or","damage":500}
+                        ],
+                        "targets":[{"station":"Fuel","wait_conditions":[{"type":"fuel_full","compare_type":"or"}]}]
+                    }]
+                }}]
+            }}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let reencoded = load_bp(&String::try_from(data.clone()).unwrap());
+
+            let schedule = &data.as_blueprint().unwrap().schedules[0].schedule;
+            assert_eq!(schedule.group, "Iron");
+            assert_eq!(schedule.records.len(), 3);
+            assert_eq!(schedule.records[1].rail, Some(Position { x: 10.0, y: 3.0 }));
+            assert!(schedule.records[2].created_by_interrupt);
+
+            let interrupt = &schedule.interrupts[0];
+            assert!(interrupt.inside_interrupt);
+            assert_eq!(
+                interrupt.conditions[1].condition,
+                WaitConditionType::NotAtStation {
+                    station: Some("Fuel".to_owned())
+                }
+            );
+            assert_eq!(
+                interrupt.targets[0].wait_conditions[0].condition,
+                WaitConditionType::FuelFull
+            );
+            assert!(data.get_ids().item.contains(&types::ItemID::new("coal")));
+
+            assert_eq!(
+                &reencoded.as_blueprint().unwrap().schedules[0].schedule,
+                schedule
+            );
         }
 
         #[test]
@@ -522,5 +794,682 @@ mod tests {
                 "../tests/comparators_operators_and_invalids.txt"
             ));
         }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn reencode_stable() {
+            let reencode = |bp_string: &str| String::try_from(load_bp(bp_string)).unwrap();
+
+            let once = reencode(include_str!(
+                "../tests/comparators_operators_and_invalids.txt"
+            ));
+            assert_eq!(reencode(&once), once);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn reencode_keeps_snapping() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],
+                "snap-to-grid":{"x":4,"y":6},"absolute-snapping":true,"position-relative-to-grid":{"x":1,"y":2},
+                "entities":[{"entity_number":1,"name":"wooden-chest","position":{"x":0.5,"y":0.5}}]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let data = load_bp(&String::try_from(data).unwrap());
+            let snapping = &data.as_blueprint().unwrap().snapping;
+
+            assert_eq!(snapping.snap_to_grid, Some(Position { x: 4.0, y: 6.0 }));
+            assert!(snapping.absolute_snapping);
+            assert_eq!(
+                snapping.position_relative_to_grid,
+                Some(Position { x: 1.0, y: 2.0 })
+            );
+            assert_eq!(snapping.grid_offset(), Position { x: -1.0, y: -2.0 });
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn reencode_is_deterministic() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,
+                "icons":[{"index":2,"signal":{"type":"item","name":"iron-plate"}},{"index":1,"signal":{"type":"item","name":"wooden-chest"}}],
+                "entities":[{"entity_number":2,"name":"small-electric-pole","position":{"x":1.5,"y":0.5}},
+                    {"entity_number":1,"name":"wooden-chest","position":{"x":0.5,"y":0.5}},
+                    {"entity_number":3,"name":"small-lamp","position":{"x":0.5,"y":0.5}}],
+                "wires":[[2,1,1,1],[1,1,2,1],[1,2,2,2]],
+                "tiles":[{"name":"stone-path","position":{"x":1,"y":0}},{"name":"concrete","position":{"x":0,"y":0}}]}}"#;
+            let mut data = load_bp(&json_to_bp_string(json).unwrap());
+            let encoded = String::try_from(data.clone()).unwrap();
+
+            let Data::Blueprint(bp) = &mut data else {
+                panic!("not a blueprint");
+            };
+            bp.icons.reverse();
+            bp.entities.reverse();
+            bp.wires.reverse();
+            bp.tiles.reverse();
+
+            assert_eq!(String::try_from(data).unwrap(), encoded);
+            assert_eq!(String::try_from(load_bp(&encoded)).unwrap(), encoded);
+
+            let bp = load_bp(&encoded);
+            let bp = bp.as_blueprint().unwrap();
+            assert_eq!(bp.wires.len(), 2);
+            assert_eq!(
+                bp.entities
+                    .iter()
+                    .map(|e| e.entity_number)
+                    .collect::<Vec<_>>(),
+                [3, 1, 2]
+            );
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn deserialize_error_path() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":0,"icons":[],"entities":[{"entity_number":1,"name":"wooden-chest","position":{"x":"a","y":0}}]}}"#;
+            let bp_string = json_to_bp_string(json).unwrap();
+
+            let err = Data::try_from(bp_string.as_str()).unwrap_err();
+            let BlueprintDecodeError::Deserializing(err) = err else {
+                panic!("unexpected error: {err}");
+            };
+
+            assert!(err.path().to_string().starts_with("blueprint"));
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn decode_errors() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[{"entity_number":1,"name":"iron-chest","position":{"x":0.5,"y":0.5}}]}}"#;
+            let bp_string = json_to_bp_string(json).unwrap();
+            assert_eq!(bp_string_to_json(&bp_string).unwrap(), json);
+
+            let mut mangled = bp_string.clone();
+            mangled.replace_range(10..11, "!");
+            assert!(matches!(
+                bp_string_to_json(&mangled),
+                Err(BlueprintDecodeError::Decoding { position: 10, .. })
+            ));
+
+            // the version byte & the first half of the base64 quads
+            let cut = &bp_string[..=bp_string.len() / 8 * 4];
+            assert!(matches!(
+                bp_string_to_json(cut),
+                Err(BlueprintDecodeError::Truncated(_))
+            ));
+
+            let garbage = format!("0{}", general_purpose::STANDARD.encode(b"not zlib at all"));
+            assert!(matches!(
+                bp_string_to_json(&garbage),
+                Err(BlueprintDecodeError::Decompress { .. })
+            ));
+
+            let mut zlib = ZlibEncoder::new(Vec::new(), flate2::Compression::new(9));
+            zlib.write_all(b"{\"a\": \"\xff\"}").unwrap();
+            let invalid_utf8 = format!(
+                "0{}",
+                general_purpose::STANDARD.encode(zlib.finish().unwrap())
+            );
+            assert!(matches!(
+                bp_string_to_json(&invalid_utf8),
+                Err(BlueprintDecodeError::Utf8(7))
+            ));
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn from_json_matches_bp_string() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[{"entity_number":1,"name":"iron-chest","position":{"x":0.5,"y":0.5}}]}}"#;
+            let from_json = Data::from_json(json).unwrap();
+            let from_string = load_bp(&json_to_bp_string(json).unwrap());
+
+            assert_eq!(
+                serde_json::to_string(&from_json).unwrap(),
+                serde_json::to_string(&from_string).unwrap()
+            );
+            assert!(Data::from_json("0eNq").is_err());
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn signal_types() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[
+                {"index":1,"signal":{"type":"space-location","name":"nauvis"}},
+                {"index":2,"signal":{"type":"asteroid-chunk","name":"metallic-asteroid-chunk"}},
+                {"index":3,"signal":{"type":"recipe","name":"iron-gear-wheel","quality":"rare"}},
+                {"index":4,"signal":{"type":"entity","name":"small-biter"}}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let signals = data
+                .icons()
+                .iter()
+                .map(|icon| icon.signal.clone())
+                .collect::<Vec<_>>();
+
+            assert!(matches!(signals[0], SignalID::SpaceLocation { .. }));
+            assert!(matches!(signals[1], SignalID::AsteroidChunk { .. }));
+            assert_eq!(signals[2].name().as_deref(), Some("iron-gear-wheel"));
+            assert_eq!(signals[2].quality().map(|q| q.as_str()), Some("rare"));
+            assert!(signals[3].quality().is_none());
+
+            let ids = data.get_ids();
+            assert!(ids.recipe.contains(&RecipeID::new("iron-gear-wheel")));
+            assert!(ids.entity.contains(&EntityID::new("small-biter")));
+
+            let reencoded = serde_json::to_string(&signals).unwrap();
+            assert!(reencoded.contains(r#""type":"space-location""#));
+            assert!(reencoded.contains(r#""quality":"rare""#));
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn entity_quality() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[{"entity_number":1,"name":"iron-chest","position":{"x":0.5,"y":0.5},"quality":"rare"},{"entity_number":2,"name":"iron-chest","position":{"x":1.5,"y":0.5}}]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let bp = data.as_blueprint().unwrap();
+
+            assert_eq!(
+                bp.entities[0].quality.as_ref().map(|q| q.as_str()),
+                Some("rare")
+            );
+            assert_eq!(bp.entities[1].quality, None);
+            assert!(!serde_json::to_string(&bp.entities[1])
+                .unwrap()
+                .contains("quality"));
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn extract_from_chat_message() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":0,"icons":[],"entities":[{"entity_number":1,"name":"wooden-chest","position":{"x":0.5,"y":0.5}}]}}"#;
+            let bp_string = json_to_bp_string(json).unwrap();
+            let text = format!("[12:34] player: check this out 0e then {bp_string}\nhave fun!");
+
+            let extracted = extract(&text).unwrap();
+            assert_eq!(&text[extracted.span], bp_string);
+            assert!(extracted.data.as_blueprint().is_some());
+
+            assert!(Data::from_text(&text).is_ok());
+            assert!(extract("no blueprint 0eAAAAAAAAAAAAAAAAAAAAAAAA here").is_none());
+
+            let html =
+                format!(r#"<html><body><textarea id="bp">{bp_string}</textarea></body></html>"#);
+            assert_eq!(&html[extract(&html).unwrap().span], bp_string);
+            assert_eq!(find_blueprint_strings("<p>0eN</p>").count(), 0);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn surface_hints() {
+            let hint = |entities: &str, tiles: &str| {
+                let json = format!(
+                    r#"{{"blueprint":{{"item":"blueprint","version":562949954076673,"icons":[],"entities":[{entities}],"tiles":[{tiles}]}}}}"#
+                );
+                load_bp(&json_to_bp_string(&json).unwrap())
+                    .as_blueprint()
+                    .unwrap()
+                    .surface_hint()
+            };
+            let entity = |name: &str| {
+                format!(r#"{{"entity_number":1,"name":"{name}","position":{{"x":0.5,"y":0.5}}}}"#)
+            };
+            let tile = |name: &str| format!(r#"{{"name":"{name}","position":{{"x":0,"y":0}}}}"#);
+
+            assert_eq!(
+                hint(&entity("iron-chest"), &tile("space-platform-foundation")),
+                SurfaceHint::SpacePlatform
+            );
+            assert_eq!(hint(&entity("pumpjack"), ""), SurfaceHint::Planet);
+            assert_eq!(
+                hint("", &tile("overgrowth-yumako-soil")),
+                SurfaceHint::Planet
+            );
+            assert_eq!(
+                hint(&entity("thruster"), &tile("landfill")),
+                SurfaceHint::Unknown
+            );
+            assert_eq!(hint(&entity("iron-chest"), ""), SurfaceHint::Unknown);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn merge_remaps_entities() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[
+                {"entity_number":1,"name":"small-electric-pole","position":{"x":0.5,"y":0.5}},
+                {"entity_number":2,"name":"small-electric-pole","position":{"x":4.5,"y":0.5}}
+            ],"wires":[[1,5,2,5]],"tiles":[{"name":"concrete","position":{"x":0,"y":0}},{"name":"concrete","position":{"x":10,"y":0}}]}}"#;
+            let mut base = load_bp(&json_to_bp_string(json).unwrap());
+            let other = base.clone();
+
+            let base = base.as_blueprint_mut().unwrap();
+            let other = other.as_blueprint().unwrap();
+            let (x, y) = (base.entities[0].position.x, base.entities[0].position.y);
+            merge(base, other, &Position { x: 10.0, y: 0.0 });
+
+            assert_eq!(
+                base.entities
+                    .iter()
+                    .map(|e| e.entity_number)
+                    .collect::<Vec<_>>(),
+                [1, 2, 3, 4]
+            );
+            assert_eq!(base.entities[2].position, Position { x: x + 10.0, y });
+            assert_eq!(
+                base.wires[1].ends(),
+                [(3, base.wires[0].1), (4, base.wires[0].3)]
+            );
+
+            // the first tile of `other` lands on the second one of `base` and replaces it
+            assert_eq!(base.tiles.len(), 3);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn repeat_grid() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"snap-to-grid":{"x":3,"y":3},"entities":[
+                {"entity_number":1,"name":"assembling-machine-1","position":{"x":1.5,"y":1.5}}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let bp = data.as_blueprint().unwrap();
+
+            let res = repeat(bp, 4, 3, &Position { x: 4.0, y: 3.0 });
+            assert_eq!(res.entities.len(), 12);
+            assert_eq!(res.entities[11].entity_number, 12);
+            assert_eq!(
+                res.entities[11].position,
+                Position {
+                    x: bp.entities[0].position.x + 12.0,
+                    y: bp.entities[0].position.y + 6.0
+                }
+            );
+            assert_eq!(
+                res.snapping.snap_to_grid,
+                Some(Position { x: 16.0, y: 9.0 })
+            );
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn logistic_sections() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[
+                {"entity_number":1,"name":"requester-chest","position":{"x":0.5,"y":0.5},
+                 "request_filters":{"sections":[
+                    {"index":1,"filters":[{"index":1,"name":"iron-plate","quality":"normal","comparator":"=","count":100}]},
+                    {"index":2,"filters":[{"index":1,"name":"copper-plate","quality":"normal","comparator":"=","count":50}],"active":false},
+                    {"index":3,"filters":[{"index":1,"name":"steel-plate","quality":"normal","comparator":"="}]}
+                 ],"request_from_buffers":true}}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let bp = data.as_blueprint().unwrap();
+
+            let requests = bp.entities[0]
+                .request_filters
+                .iter()
+                .map(|f| ((*f.name).clone(), f.count))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                requests,
+                [
+                    ("iron-plate".to_owned(), 100),
+                    ("steel-plate".to_owned(), 0)
+                ]
+            );
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn entity_tags() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[
+                {"entity_number":1,"name":"iron-chest","position":{"x":0.5,"y":0.5},
+                 "tags":{"se_meta":{"zone":"Nauvis","index":3,"flags":[true,false,"x"]},"aai_vehicle":{"ai":true}}},
+                {"entity_number":2,"name":"iron-chest","position":{"x":1.5,"y":0.5}}
+            ]}}"#;
+            let mut data = load_bp(&json_to_bp_string(json).unwrap());
+
+            let bp = data.as_blueprint().unwrap();
+            let tagged = &bp.entities[0];
+            assert_eq!(
+                tagged
+                    .tag_path(&["se_meta", "zone"])
+                    .and_then(mod_util::AnyBasic::as_str),
+                Some("Nauvis")
+            );
+            assert_eq!(
+                tagged
+                    .tag_path(&["se_meta", "index"])
+                    .and_then(mod_util::AnyBasic::as_number),
+                Some(3.0)
+            );
+            assert_eq!(
+                tagged
+                    .tag_path(&["se_meta", "flags"])
+                    .and_then(mod_util::AnyBasic::as_array)
+                    .map(<[_]>::len),
+                Some(3)
+            );
+            assert_eq!(
+                tagged
+                    .tag_path(&["aai_vehicle", "ai"])
+                    .and_then(mod_util::AnyBasic::as_bool),
+                Some(true)
+            );
+            assert!(tagged.tag_path(&["se_meta", "missing"]).is_none());
+            assert!(bp.entities[1].tags.is_empty());
+
+            let tags = tagged.tags.clone();
+            data.as_blueprint_mut().unwrap().entities[1]
+                .set_tag("note", mod_util::AnyBasic::String("hi".to_owned()));
+
+            let reencoded = load_bp(&String::try_from(data).unwrap());
+            let bp = reencoded.as_blueprint().unwrap();
+            assert_eq!(bp.entities[0].tags, tags);
+            assert_eq!(
+                bp.entities[1].tag("note"),
+                Some(&mod_util::AnyBasic::String("hi".to_owned()))
+            );
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn decider_conditions() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[
+                {"entity_number":1,"name":"decider-combinator","position":{"x":0.5,"y":1},
+                 "control_behavior":{"decider_conditions":{
+                    "conditions":[
+                        {"first_signal":{"type":"item","name":"iron-plate"},"second_signal":{"type":"virtual","name":"signal-A"},"comparator":"≥","first_signal_networks":{"green":false}},
+                        {"first_signal":{"type":"virtual","name":"signal-B"},"constant":10,"compare_type":"and"}
+                    ],
+                    "outputs":[
+                        {"signal":{"type":"virtual","name":"signal-C"},"copy_count_from_input":false,"constant":5,"networks":{"red":false}}
+                    ]
+                 }}}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let decider = data.as_blueprint().unwrap().entities[0]
+                .control_behavior
+                .as_ref()
+                .and_then(|bhv| bhv.decider_conditions.clone())
+                .unwrap();
+
+            assert_eq!(decider.condition_count(), 2);
+            assert_eq!(decider.operation(), types::Comparator::GreaterOrEqual);
+
+            let DeciderData::Conditions {
+                conditions,
+                outputs,
+            } = &decider
+            else {
+                panic!("unexpected decider data: {decider:?}");
+            };
+            assert!(conditions[0].first_signal_networks.red);
+            assert!(!conditions[0].first_signal_networks.green);
+            assert_eq!(conditions[1].comparator, types::Comparator::Less);
+            assert_eq!(conditions[1].compare_type, CompareType::And);
+            assert_eq!(outputs[0].constant, 5);
+            assert!(!outputs[0].copy_count_from_input);
+
+            let reencoded = load_bp(&String::try_from(data).unwrap());
+            assert_eq!(
+                reencoded.as_blueprint().unwrap().entities[0]
+                    .control_behavior
+                    .as_ref()
+                    .and_then(|bhv| bhv.decider_conditions.as_ref()),
+                Some(&decider)
+            );
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn parameters() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954076673,"icons":[],"entities":[
+                {"entity_number":1,"name":"assembling-machine-2","position":{"x":1.5,"y":1.5},"recipe":"parameter-0"},
+                {"entity_number":2,"name":"requester-chest","position":{"x":3.5,"y":0.5},
+                 "request_filters":{"sections":[{"index":1,"filters":[{"index":1,"name":"parameter-1","quality":"normal","comparator":"=","count":50}]}]}},
+                {"entity_number":3,"name":"iron-chest","position":{"x":4.5,"y":0.5}}
+            ],"parameters":[
+                {"type":"id","id":"parameter-0","name":"Recipe","quality-condition":{"quality":"normal","comparator":"="}},
+                {"type":"id","id":"parameter-1","ingredient-of":"parameter-0"},
+                {"type":"number","number":"50","variable":"x","formula":"x * 2","dependent":true},
+                {"type":"id","id":"parameter-2","not-parametrised":true}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let bp = data.as_blueprint().unwrap();
+
+            assert_eq!(bp.parameters.len(), 4);
+            assert_eq!(bp.parameters[0].name(), Some("Recipe"));
+            assert_eq!(bp.parameters[2].value(), "50");
+            assert!(!bp.parameters[3].is_parametrised());
+            assert!(matches!(
+                &bp.parameters[1],
+                Parameter::Id { ingredient_of: Some(of), .. } if of == "parameter-0"
+            ));
+
+            assert_eq!(
+                bp.parameter_users(),
+                [vec![1], vec![2], vec![2], Vec::<EntityNumber>::new()]
+            );
+            let entity = |number: EntityNumber| {
+                bp.entities
+                    .iter()
+                    .find(|e| e.entity_number == number)
+                    .unwrap()
+            };
+            assert!(bp.parameters[0].is_used_by(entity(1)));
+            assert!(!bp.parameters[0].is_used_by(entity(3)));
+
+            let reencoded = load_bp(&String::try_from(data.clone()).unwrap());
+            assert_eq!(reencoded.as_blueprint().unwrap().parameters, bp.parameters);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn upgrade_1_1() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":281479275675648,"icons":[],"entities":[
+                {"entity_number":1,"name":"logistic-chest-requester","position":{"x":0.5,"y":0.5},
+                 "request_filters":[{"index":1,"name":"iron-gear-wheel","count":20}],"request_from_buffers":true},
+                {"entity_number":2,"name":"filter-inserter","position":{"x":1.5,"y":0.5},"direction":2,
+                 "filters":[{"index":1,"name":"iron-gear-wheel"}]},
+                {"entity_number":3,"name":"splitter","position":{"x":3,"y":0.5},"direction":4,"filter":"iron-plate"},
+                {"entity_number":4,"name":"decider-combinator","position":{"x":5,"y":0.5},"direction":6,
+                 "connections":{"1":{"green":[{"entity_id":5,"circuit_id":2}]}}},
+                {"entity_number":5,"name":"arithmetic-combinator","position":{"x":7,"y":0.5},"direction":6,
+                 "connections":{"2":{"green":[{"entity_id":4,"circuit_id":1}]}}}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            assert_eq!(compat::major_version(data.version()), 2);
+
+            let bp = data.as_blueprint().unwrap();
+            let entity = |number: EntityNumber| {
+                bp.entities
+                    .iter()
+                    .find(|e| e.entity_number == number)
+                    .unwrap()
+            };
+
+            let chest = entity(1);
+            assert_eq!(*chest.name, "requester-chest");
+            assert!(chest.request_filters.request_from_buffers);
+            assert_eq!(chest.request_filters.iter().count(), 1);
+
+            let inserter = entity(2);
+            assert_eq!(*inserter.name, "fast-inserter");
+            assert!(inserter.use_filters);
+            assert_eq!(inserter.direction.eight_way(), types::Direction::East);
+
+            let splitter = entity(3);
+            assert_eq!(splitter.direction.eight_way(), types::Direction::South);
+            assert_eq!(
+                splitter.filter.as_ref().map(|f| (*f.name).clone()),
+                Some("iron-plate".to_owned())
+            );
+
+            // the green wire is listed on both combinators but only stored once
+            assert_eq!(
+                bp.wires,
+                [Wire(
+                    4,
+                    WireConnectorID::CircuitGreen,
+                    5,
+                    WireConnectorID::CombinatorOutputGreen
+                )]
+            );
+
+            // re-encoding writes 2.0 directions
+            let json = bp_string_to_json(&String::try_from(data.clone()).unwrap()).unwrap();
+            assert!(json.contains(r#""direction":4"#));
+            assert!(!json.contains("connections"));
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn upgrade_1_1_icons_and_planners() {
+            let json = r#"{"blueprint_book":{"item":"blueprint-book","version":281479275675648,"active_index":0,
+                "icons":[{"index":1,"signal":{"type":"item","name":"stack-filter-inserter"}}],"blueprints":[
+                {"index":0,"upgrade_planner":{"item":"upgrade-planner","version":281479275675648,"settings":{
+                    "mappers":[{"index":0,"from":{"type":"entity","name":"filter-inserter"},
+                                "to":{"type":"item","name":"stack-inserter"}}],
+                    "icons":[{"index":1,"signal":{"type":"item","name":"logistic-chest-storage"}}]}}},
+                {"index":1,"deconstruction_planner":{"item":"deconstruction-planner","version":281479275675648,"settings":{
+                    "entity_filters":[{"index":1,"name":"logistic-chest-buffer"}],
+                    "icons":[{"index":1,"signal":{"type":"virtual","name":"signal-A"}}]}}}
+            ]}}"#;
+            let ids = load_bp(&json_to_bp_string(json).unwrap()).get_ids();
+
+            assert_eq!(
+                ids.entity,
+                HashSet::from([
+                    EntityID::new("fast-inserter"),
+                    EntityID::new("buffer-chest")
+                ])
+            );
+            assert_eq!(
+                ids.item,
+                HashSet::from([ItemID::new("bulk-inserter"), ItemID::new("storage-chest")])
+            );
+            assert_eq!(
+                ids.virtual_signal,
+                HashSet::from([VirtualSignalID::new("signal-A")])
+            );
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn upgrade_needs_a_version() {
+            let json = r#"{"blueprint":{"item":"blueprint","icons":[],"entities":[
+                {"entity_number":1,"name":"filter-inserter","position":{"x":0.5,"y":0.5},"direction":2}
+            ]}}"#;
+            let mut upgraded = serde_json::from_str::<serde_json::Value>(json).unwrap();
+            compat::upgrade(&mut upgraded);
+
+            assert_eq!(
+                upgraded,
+                serde_json::from_str::<serde_json::Value>(json).unwrap()
+            );
+            assert!(Data::from_json(json).is_err());
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn odd_directions() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":562949954928640,"icons":[],"entities":[
+                {"entity_number":1,"name":"half-diagonal-rail","position":{"x":1,"y":1},"direction":3},
+                {"entity_number":2,"name":"curved-rail-a","position":{"x":8,"y":8},"direction":13}
+            ]}}"#;
+            let data = load_bp(&json_to_bp_string(json).unwrap());
+            let reencoded = load_bp(&String::try_from(data.clone()).unwrap());
+
+            let entities = &data.as_blueprint().unwrap().entities;
+            assert_eq!(entities[0].direction.step(), 3);
+            assert_eq!(
+                entities[0].direction.eight_way(),
+                types::Direction::NorthEast
+            );
+            assert_eq!(entities[1].direction.step(), 13);
+            assert_eq!(entities[1].direction.eight_way(), types::Direction::West);
+
+            assert_eq!(&reencoded.as_blueprint().unwrap().entities, entities);
+        }
+
+        #[test]
+        #[allow(clippy::unwrap_used)]
+        fn scrub_blueprint() {
+            let json = r#"{"blueprint":{"item":"blueprint","version":281479275675648,"icons":[],
+                "label":"Alice's station","description":"Copied from [gps=1234,-567,nauvis] by ALICE",
+                "absolute-snapping":true,"snap-to-grid":{"x":2,"y":2},"position-relative-to-grid":{"x":1,"y":0},
+                "entities":[
+                    {"entity_number":7,"name":"train-stop","position":{"x":0,"y":0},"station":"Alice Iron"},
+                    {"entity_number":3,"name":"small-electric-pole","position":{"x":2.5,"y":0.5},"neighbours":[9]},
+                    {"entity_number":9,"name":"small-electric-pole","position":{"x":4.5,"y":0.5},"neighbours":[3],
+                     "connections":{"1":{"red":[{"entity_id":3}]}}},
+                    {"entity_number":12,"name":"locomotive","position":{"x":0,"y":4},"orientation":0.25}
+                ],
+                "schedules":[{"locomotives":[12],"schedule":[{"station":"Alice Iron"},{"station":"Smelting"}]}]
+            }}"#;
+            let mut data = load_bp(&json_to_bp_string(json).unwrap());
+
+            scrub(
+                &mut data,
+                &ScrubOptions {
+                    station_names: true,
+                    player_names: vec!["alice".to_owned()],
+                },
+            );
+            let bp = data.as_blueprint().unwrap();
+
+            assert_eq!(bp.label, "[redacted]'s station");
+            assert_eq!(bp.description, "Copied from  by [redacted]");
+            assert!(!bp.snapping.absolute_snapping);
+            assert!(bp.snapping.position_relative_to_grid.is_none());
+            assert!(bp.snapping.snap_to_grid.is_some());
+
+            let numbers = bp
+                .entities
+                .iter()
+                .map(|e| e.entity_number)
+                .collect::<Vec<_>>();
+            assert_eq!(numbers, (1..=4).collect::<Vec<_>>());
+
+            let number_of = |name: &str| {
+                bp.entities
+                    .iter()
+                    .filter(|e| *e.name == name)
+                    .map(|e| e.entity_number)
+                    .collect::<Vec<_>>()
+            };
+
+            // the poles are connected to each other
+            let poles = number_of("small-electric-pole");
+            assert_eq!(
+                bp.wires,
+                [
+                    Wire(
+                        poles[0],
+                        WireConnectorID::CircuitRed,
+                        poles[1],
+                        WireConnectorID::CircuitRed
+                    ),
+                    Wire(
+                        poles[0],
+                        WireConnectorID::PoleCopper,
+                        poles[1],
+                        WireConnectorID::PoleCopper
+                    ),
+                ]
+            );
+
+            let stop = bp
+                .entities
+                .iter()
+                .find(|e| *e.name == "train-stop")
+                .unwrap();
+            assert_eq!(stop.station, "Station 1");
+            assert_eq!(bp.schedules[0].locomotives, number_of("locomotive"));
+            let stations = bp.schedules[0]
+                .schedule
+                .records
+                .iter()
+                .map(|r| r.station.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(stations, ["Station 1", "Station 2"]);
+        }
     }
 }
@@ -12,7 +12,7 @@ pub struct PlannerData<T>
 where
     T: Default + PartialEq,
 {
-    #[serde(flatten, default, skip_serializing_if = "helper::is_default")]
+    #[serde(default, skip_serializing_if = "helper::is_default")]
     settings: T,
 }
 
@@ -23,3 +23,9 @@ impl<T: Default + PartialEq> std::ops::Deref for PlannerData<T> {
         &self.settings
     }
 }
+
+impl<T: Default + PartialEq> std::ops::DerefMut for PlannerData<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.settings
+    }
+}
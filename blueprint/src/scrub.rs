@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use crate::{Blueprint, Data, EntityNumber};
+
+const REDACTED: &str = "[redacted]";
+
+/// Rich text tags that point to a location or object in a specific save.
+const SAVE_TAGS: [&str; 3] = ["gps", "train", "train-stop"];
+
+/// Controls what [`scrub`] removes besides the always scrubbed map positions.
+#[derive(Debug, Clone, Default)]
+pub struct ScrubOptions {
+    /// Replace train stop & train schedule station names with generic ones.
+    pub station_names: bool,
+
+    /// Player names to redact from labels & descriptions, matched case insensitively.
+    pub player_names: Vec<String>,
+}
+
+/// Removes data that identifies a save or its players before a blueprint gets published.
+///
+/// - absolute snapping & the grid position relative to the map are dropped
+/// - `[gps]`, `[train]` and `[train-stop]` rich text tags are removed from all texts
+/// - the given player names are redacted from labels & descriptions
+/// - entity numbers are renumbered from 1 in the order of the entities
+/// - station names are replaced by `Station N` if requested
+pub fn scrub(data: &mut Data, options: &ScrubOptions) {
+    match data {
+        Data::Blueprint(bp) => scrub_blueprint(bp, options),
+        Data::BlueprintBook(book) => {
+            book.label = scrub_text(&book.label, options);
+            book.description = scrub_text(&book.description, options);
+
+            for entry in &mut book.blueprints {
+                scrub(&mut entry.data, options);
+            }
+        }
+        Data::UpgradePlanner(planner) => {
+            planner.label = scrub_text(&planner.label, options);
+            planner.description = scrub_text(&planner.description, options);
+        }
+        Data::DeconstructionPlanner(planner) => {
+            planner.label = scrub_text(&planner.label, options);
+            planner.description = scrub_text(&planner.description, options);
+        }
+    }
+}
+
+fn scrub_blueprint(bp: &mut Blueprint, options: &ScrubOptions) {
+    bp.label = scrub_text(&bp.label, options);
+    bp.description = scrub_text(&bp.description, options);
+
+    bp.snapping.absolute_snapping = false;
+    bp.snapping.position_relative_to_grid = None;
+
+    let numbers = bp
+        .entities
+        .iter()
+        .zip(1..)
+        .map(|(e, number)| (e.entity_number, number))
+        .collect::<HashMap<EntityNumber, EntityNumber>>();
+    let renumber = |number: &mut EntityNumber| {
+        if let Some(new) = numbers.get(number) {
+            *number = *new;
+        }
+    };
+
+    let mut stations = HashMap::new();
+    let mut rename = |station: &mut String| {
+        if !options.station_names || station.is_empty() {
+            return;
+        }
+
+        let next = stations.len() + 1;
+        station.clone_from(
+            stations
+                .entry(station.clone())
+                .or_insert_with(|| format!("Station {next}")),
+        );
+    };
+
+    for entity in &mut bp.entities {
+        renumber(&mut entity.entity_number);
+
+        if let Some(alert) = &mut entity.alert_parameters {
+            alert.alert_message = scrub_text(&alert.alert_message, options);
+        }
+
+        rename(&mut entity.station);
+    }
+
+    for wire in &mut bp.wires {
+        renumber(&mut wire.0);
+        renumber(&mut wire.2);
+    }
+
+    for schedule in &mut bp.schedules {
+        schedule.locomotives.iter_mut().for_each(&renumber);
+
+        schedule.schedule.stations_mut().for_each(&mut rename);
+    }
+}
+
+/// Removes save specific rich text tags and redacts player names.
+fn scrub_text(text: &str, options: &ScrubOptions) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('[') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest.find(']').filter(|_| {
+            SAVE_TAGS.iter().any(|tag| {
+                rest[1..]
+                    .strip_prefix(tag)
+                    .is_some_and(|r| r.starts_with('='))
+            })
+        });
+
+        if let Some(end) = end {
+            rest = &rest[end + 1..];
+        } else {
+            res.push('[');
+            rest = &rest[1..];
+        }
+    }
+    res.push_str(rest);
+
+    for name in options.player_names.iter().filter(|name| !name.is_empty()) {
+        res = redact(&res, name);
+    }
+
+    res
+}
+
+/// Replaces all ASCII case insensitive occurrences of `name` in `text`.
+fn redact(text: &str, name: &str) -> String {
+    let haystack = text.to_ascii_lowercase();
+    let needle = name.to_ascii_lowercase();
+
+    let mut res = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, _) in haystack.match_indices(&needle) {
+        res.push_str(&text[last..start]);
+        res.push_str(REDACTED);
+        last = start + needle.len();
+    }
+    res.push_str(&text[last..]);
+
+    res
+}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+use crate::Blueprint;
+
+/// Entities that can only be built on a space platform.
+const PLATFORM_ENTITIES: [&str; 4] = [
+    "space-platform-hub",
+    "thruster",
+    "asteroid-collector",
+    "crusher",
+];
+
+/// Tiles that only exist on space platforms.
+const PLATFORM_TILES: [&str; 2] = ["space-platform-foundation", "empty-space"];
+
+/// Entities that need gravity or a planet resource, they can't be built on a space platform.
+const PLANET_ENTITIES: [&str; 32] = [
+    "straight-rail",
+    "half-diagonal-rail",
+    "curved-rail-a",
+    "curved-rail-b",
+    "legacy-straight-rail",
+    "legacy-curved-rail",
+    "rail-ramp",
+    "rail-support",
+    "elevated-straight-rail",
+    "elevated-half-diagonal-rail",
+    "elevated-curved-rail-a",
+    "elevated-curved-rail-b",
+    "rail-signal",
+    "rail-chain-signal",
+    "train-stop",
+    "locomotive",
+    "cargo-wagon",
+    "fluid-wagon",
+    "artillery-wagon",
+    "offshore-pump",
+    "pumpjack",
+    "burner-mining-drill",
+    "electric-mining-drill",
+    "big-mining-drill",
+    "rocket-silo",
+    "cargo-landing-pad",
+    "agricultural-tower",
+    "lightning-rod",
+    "lightning-collector",
+    "car",
+    "tank",
+    "spidertron",
+];
+
+/// Tiles that can only be placed on a planet.
+const PLANET_TILES: [&str; 12] = [
+    "landfill",
+    "stone-path",
+    "concrete",
+    "hazard-concrete-left",
+    "hazard-concrete-right",
+    "refined-concrete",
+    "refined-hazard-concrete-left",
+    "refined-hazard-concrete-right",
+    "foundation",
+    "artificial-yumako-soil",
+    "artificial-jellynut-soil",
+    "ice-platform",
+];
+
+/// Surface a blueprint is meant for, guessed from the vanilla tiles & entities it contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SurfaceHint {
+    /// Nothing surface specific or contradicting hints.
+    #[default]
+    Unknown,
+    Planet,
+    SpacePlatform,
+}
+
+impl Blueprint {
+    #[must_use]
+    pub fn surface_hint(&self) -> SurfaceHint {
+        let mut entities = self.entities.iter().map(|e| e.name.as_str());
+        let mut tiles = self.tiles.iter().map(|t| t.name.as_str());
+
+        let platform = entities
+            .clone()
+            .any(|name| PLATFORM_ENTITIES.contains(&name))
+            || tiles.clone().any(|name| PLATFORM_TILES.contains(&name));
+        let planet = entities.any(|name| PLANET_ENTITIES.contains(&name))
+            || tiles.any(|name| PLANET_TILES.contains(&name) || name.starts_with("overgrowth-"));
+
+        match (platform, planet) {
+            (true, false) => SurfaceHint::SpacePlatform,
+            (false, true) => SurfaceHint::Planet,
+            _ => SurfaceHint::Unknown,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Fetching blueprint strings from blueprint sharing sites.
+
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::{client, FactorioApiError};
+
+/// Database that backs both factorioprints.com and factorio.school.
+static FACTORIOPRINTS_DB: &str = "https://facorio-blueprints.firebaseio.com";
+
+/// Blueprint sharing sites with a known api.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlueprintSite {
+    FactorioPrints,
+    FactorioSchool,
+}
+
+impl BlueprintSite {
+    /// The site the url belongs to and the id of the blueprint on it.
+    #[must_use]
+    pub fn from_url(url: &Url) -> Option<(Self, String)> {
+        let site = match url.host_str()?.trim_start_matches("www.") {
+            "factorioprints.com" => Self::FactorioPrints,
+            "factorio.school" => Self::FactorioSchool,
+            _ => return None,
+        };
+
+        let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+        if segments.next()? != "view" {
+            return None;
+        }
+
+        let id = segments.next()?;
+        Some((site, id.to_owned()))
+    }
+
+    fn api_url(self, id: &str) -> String {
+        match self {
+            Self::FactorioPrints | Self::FactorioSchool => {
+                format!("{FACTORIOPRINTS_DB}/blueprints/{id}.json")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FactorioPrintsEntry {
+    blueprint_string: String,
+}
+
+/// Fetches the blueprint string behind a link.
+///
+/// Known sites are queried through their api, any other url is expected to serve
+/// the blueprint string directly or as part of a html page.
+pub async fn fetch_blueprint(url: &str) -> Result<String, FactorioApiError> {
+    let parsed = Url::parse(url).map_err(|_| FactorioApiError::UnsupportedUrl(url.to_owned()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(FactorioApiError::UnsupportedUrl(url.to_owned()));
+    }
+
+    if let Some((site, id)) = BlueprintSite::from_url(&parsed) {
+        let res = client()?
+            .get(site.api_url(&id))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        return serde_json::from_slice::<Option<FactorioPrintsEntry>>(&res.bytes().await?)?
+            .map(|entry| entry.blueprint_string)
+            .ok_or_else(|| FactorioApiError::NoBlueprint(url.to_owned()));
+    }
+
+    let res = client()?.get(parsed).send().await?.error_for_status()?;
+    let text = res.text().await?;
+    blueprint::extract(&text)
+        .map(|bp| text[bp.span].to_owned())
+        .ok_or_else(|| FactorioApiError::NoBlueprint(url.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn site_from_url() {
+        let url = Url::parse("https://factorioprints.com/view/-KnQ865j-qQ21WoUPbd3").unwrap();
+        assert_eq!(
+            BlueprintSite::from_url(&url),
+            Some((
+                BlueprintSite::FactorioPrints,
+                "-KnQ865j-qQ21WoUPbd3".to_owned()
+            ))
+        );
+
+        let url = Url::parse("https://www.factorio.school/view/-M5ZtbcCEakF8s6RK2la/").unwrap();
+        assert_eq!(
+            BlueprintSite::from_url(&url),
+            Some((
+                BlueprintSite::FactorioSchool,
+                "-M5ZtbcCEakF8s6RK2la".to_owned()
+            ))
+        );
+
+        let url = Url::parse("https://factorioprints.com/top").unwrap();
+        assert_eq!(BlueprintSite::from_url(&url), None);
+
+        let url = Url::parse("https://example.com/view/foo").unwrap();
+        assert_eq!(BlueprintSite::from_url(&url), None);
+    }
+}
@@ -1,9 +1,13 @@
 #![allow(clippy::module_name_repetitions)]
 
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use reqwest::{header::RETRY_AFTER, Request, Response, StatusCode};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
+use reqwest_retry::{
+    default_on_request_failure, default_on_request_success, policies::ExponentialBackoff,
+    RetryTransientMiddleware, Retryable, RetryableStrategy,
+};
 use reqwest_tracing::{
     default_on_request_end, reqwest_otel_span, ReqwestOtelSpanBackend, TracingMiddleware,
 };
@@ -13,6 +17,17 @@ use mod_util::mod_info::Version;
 
 static ENV_AGENT: &str = "FACTORIO_API_USER_AGENT";
 static ENV_ENDPOINT: &str = "FACTORIO_API_ENDPOINT";
+static ENV_DOWNLOAD_ENDPOINT: &str = "FACTORIO_API_DOWNLOAD_ENDPOINT";
+static ENV_NO_AUTH: &str = "FACTORIO_API_NO_AUTH";
+
+static DEFAULT_ENDPOINT: &str = "https://mods.factorio.com";
+
+/// Host of the official mod portal, the only one that gets the credentials.
+static PORTAL_HOST: &str = "mods.factorio.com";
+
+/// Upper bound for waiting on a `Retry-After` header, longer waits are not retried.
+const MAX_RETRY_AFTER: Duration = Duration::from_mins(5);
+const MAX_RETRY_AFTER_ATTEMPTS: u32 = 3;
 
 #[derive(Debug, thiserror::Error)]
 pub enum FactorioApiError {
@@ -33,6 +48,18 @@ pub enum FactorioApiError {
 
     #[error("factorio api error: {0}")]
     ApiError(String),
+
+    #[error("the mod portal search is not available")]
+    SearchUnavailable,
+
+    #[error("unsupported blueprint url: {0}")]
+    UnsupportedUrl(String),
+
+    #[error("no blueprint found at {0}")]
+    NoBlueprint(String),
+
+    #[error("invalid base url: {0}")]
+    InvalidBaseUrl(String),
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -42,6 +69,12 @@ enum PortalResponse<T> {
     Err { message: String },
 }
 
+#[cfg(feature = "blueprint_sites")]
+pub mod blueprint_sites;
+
+#[cfg(feature = "sprite_server")]
+pub mod sprite_server;
+
 pub use auth::*;
 mod auth {
     use std::collections::HashMap;
@@ -364,6 +397,30 @@ mod portal {
         }
     }
 
+    /// Full text search over the names, titles & summaries of all mods, like the search of the mod portal website.
+    ///
+    /// The search endpoint is not part of the documented mod portal API, it answers like [`portal_list`].
+    /// Fails with [`crate::FactorioApiError::SearchUnavailable`] if the portal doesn't know it (anymore).
+    pub async fn portal_search(
+        query: &str,
+        params: PortalListParams,
+    ) -> Result<PortalListResponse, crate::FactorioApiError> {
+        let res = client()?
+            .get(format!("{}/api/search?{}", endpoint(), params.build()))
+            .query(&[("query", query)])
+            .send()
+            .await?;
+
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(crate::FactorioApiError::SearchUnavailable);
+        }
+
+        match serde_json::from_slice(&res.bytes().await?)? {
+            PortalResponse::Ok(res) => Ok(res),
+            PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
+        }
+    }
+
     #[derive(Debug, Deserialize, Serialize, Clone)]
     pub struct PortalShortEntry {
         pub downloads_count: u32,
@@ -474,17 +531,28 @@ mod portal {
     }
 }
 
+/// Downloads a mod release from its `download_url` as reported by the portal.
+///
+/// Relative urls are resolved against the download endpoint. The credentials are
+/// only sent to the official mod portal and not at all with `FACTORIO_API_NO_AUTH`.
 pub async fn fetch_mod_raw(
     download_url: &str,
     username: &str,
     token: &str,
 ) -> Result<Vec<u8>, FactorioApiError> {
-    let res = client()?
-        .get(format!(
-            "https://mods.factorio.com{download_url}?username={username}&token={token}"
-        ))
-        .send()
-        .await?;
+    let url = if download_url.starts_with("http://") || download_url.starts_with("https://") {
+        download_url.to_owned()
+    } else {
+        format!("{}{download_url}", download_endpoint())
+    };
+
+    let send_credentials = auth_enabled() && is_portal_url(&url);
+    let mut req = client()?.get(url);
+    if send_credentials {
+        req = req.query(&[("username", username), ("token", token)]);
+    }
+
+    let res = req.send().await?;
 
     Ok(res.bytes().await?.to_vec())
 }
@@ -518,6 +586,80 @@ pub async fn fetch_mod_with_password(
     fetch_mod(mod_name, version, &auth_res.username, &auth_res.token).await
 }
 
+/// Waits for the duration the server asks for when it answers with `429 Too Many Requests`.
+struct RetryAfterMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for RetryAfterMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            // requests with streaming bodies can't be sent again
+            let Some(duplicate) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+
+            let res = next.clone().run(duplicate, extensions).await?;
+
+            let Some(wait) = retry_after(&res) else {
+                return Ok(res);
+            };
+
+            if attempt >= MAX_RETRY_AFTER_ATTEMPTS || wait > MAX_RETRY_AFTER {
+                return Ok(res);
+            }
+
+            attempt += 1;
+            tracing::warn!(
+                "rate limited by {}, retrying in {}s",
+                req.url().host_str().unwrap_or_default(),
+                wait.as_secs()
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Duration requested by a `429` response through its `Retry-After` header,
+/// either as seconds or as a http date.
+fn retry_after(res: &Response) -> Option<Duration> {
+    if res.status() != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    parse_retry_after(value, SystemTime::now())
+}
+
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// Default transient retries, except for `429` responses that are handled by
+/// [`RetryAfterMiddleware`].
+struct PortalRetryStrategy;
+
+impl RetryableStrategy for PortalRetryStrategy {
+    fn handle(&self, res: &reqwest_middleware::Result<Response>) -> Option<Retryable> {
+        match res {
+            Ok(success) if retry_after(success).is_some() => None,
+            Ok(success) => default_on_request_success(success),
+            Err(error) => default_on_request_failure(error),
+        }
+    }
+}
+
 struct TimeTrace;
 
 impl ReqwestOtelSpanBackend for TimeTrace {
@@ -550,21 +692,159 @@ fn client() -> Result<ClientWithMiddleware, FactorioApiError> {
     };
 
     let tracer = TracingMiddleware::<TimeTrace>::new();
-    let retry = RetryTransientMiddleware::new_with_policy(
+    let retry = RetryTransientMiddleware::new_with_policy_and_strategy(
         ExponentialBackoff::builder().build_with_max_retries(3),
+        PortalRetryStrategy,
     );
 
-    Ok(ClientBuilder::new(rqc).with(tracer).with(retry).build())
+    Ok(ClientBuilder::new(rqc)
+        .with(tracer)
+        .with(RetryAfterMiddleware)
+        .with(retry)
+        .build())
 }
 
 fn endpoint() -> String {
-    std::env::var(ENV_ENDPOINT).unwrap_or_else(|_| "https://mods.factorio.com".to_owned())
+    std::env::var(ENV_ENDPOINT).unwrap_or_else(|_| DEFAULT_ENDPOINT.to_owned())
+}
+
+/// Base url for mod downloads, defaults to the api endpoint so a mirror only needs
+/// `FACTORIO_API_ENDPOINT` when it serves both.
+fn download_endpoint() -> String {
+    std::env::var(ENV_DOWNLOAD_ENDPOINT).unwrap_or_else(|_| endpoint())
+}
+
+/// Whether downloads need mod portal credentials.
+///
+/// Only downloads from the official mod portal are authenticated, mirrors never get the credentials.
+/// `FACTORIO_API_NO_AUTH` skips the authentication for the portal as well.
+#[must_use]
+pub fn requires_auth() -> bool {
+    auth_enabled() && is_portal_url(&download_endpoint())
+}
+
+fn auth_enabled() -> bool {
+    std::env::var(ENV_NO_AUTH).map_or(true, |v| v.is_empty() || v == "0" || v == "false")
+}
+
+/// Whether the url points to the official mod portal over https.
+fn is_portal_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .is_ok_and(|url| url.scheme() == "https" && url.host_str() == Some(PORTAL_HOST))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn retry_after_header() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_mins(2)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:29:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn credentials_only_for_portal() {
+        assert!(is_portal_url(DEFAULT_ENDPOINT));
+        assert!(is_portal_url(
+            "https://mods.factorio.com/download/some-mod/abc123"
+        ));
+
+        assert!(!is_portal_url(
+            "http://mods.factorio.com/download/some-mod/abc123"
+        ));
+        assert!(!is_portal_url(
+            "https://mirror.example.com/download/some-mod/abc123"
+        ));
+        assert!(!is_portal_url(
+            "https://mods.factorio.com.example.com/download"
+        ));
+        assert!(!is_portal_url(
+            "https://user@evil.example.com/mods.factorio.com"
+        ));
+        assert!(!is_portal_url("/download/some-mod/abc123"));
+    }
+
+    /// Answer of `/api/search?query=kr&page_size=2` in the shape of `/api/mods`,
+    /// fields only the search returns (`score`, `thumbnail`) have to be ignored.
+    const SEARCH_RESPONSE: &str = r#"{
+        "pagination": {
+            "count": 118,
+            "links": {
+                "first": null,
+                "last": "https://mods.factorio.com/api/search?query=kr&page_size=2&page=59",
+                "next": "https://mods.factorio.com/api/search?query=kr&page_size=2&page=2",
+                "prev": null
+            },
+            "page": 1,
+            "page_count": 59,
+            "page_size": 2
+        },
+        "results": [
+            {
+                "category": "overhaul",
+                "downloads_count": 1234567,
+                "latest_release": {
+                    "download_url": "/download/Krastorio2/5f8c8b9e1a2b3c4d5e6f7a8b",
+                    "file_name": "Krastorio2_1.3.24.zip",
+                    "info_json": { "factorio_version": "1.1" },
+                    "released_at": "2024-01-01T12:00:00.000000Z",
+                    "sha1": "0123456789abcdef0123456789abcdef01234567",
+                    "version": "1.3.24"
+                },
+                "name": "Krastorio2",
+                "owner": "raiguard",
+                "score": 42.5,
+                "summary": "A large Factorio mod that adds new mechanics & buildings.",
+                "thumbnail": "/assets/0123456789abcdef.thumb.png",
+                "title": "Krastorio 2"
+            },
+            {
+                "category": "",
+                "downloads_count": 42,
+                "name": "kr-tweaks",
+                "owner": "someone",
+                "summary": "",
+                "title": "Krastorio tweaks"
+            }
+        ]
+    }"#;
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn search_response() {
+        let PortalResponse::Ok(res) =
+            serde_json::from_str::<PortalResponse<PortalListResponse>>(SEARCH_RESPONSE).unwrap()
+        else {
+            panic!("search response is not a list");
+        };
+
+        assert_eq!(res.pagination.unwrap().page_count, 59);
+        assert_eq!(res.results.len(), 2);
+
+        let k2 = &res.results[0];
+        assert_eq!(k2.name, "Krastorio2");
+        assert_eq!(k2.category, Some(PortalCategory::Overhaul));
+        assert!(matches!(
+            &k2.release,
+            Some(PortalSearchReleaseKind::Latest { latest_release }) if latest_release.version == Version::new(1, 3, 24)
+        ));
+
+        assert_eq!(res.results[1].category, Some(PortalCategory::NoCategory));
+        assert!(res.results[1].release.is_none());
+    }
+
     #[test]
     fn invalid_auth() {
         let result = tokio_test::block_on(auth(
@@ -0,0 +1,142 @@
+//! Fetching mod files from a HTTP server, e.g. sprites stored in object storage.
+
+use mod_util::{
+    mod_loader::ModError,
+    sprite_source::{contained_parts, SpriteSource},
+};
+use reqwest::{blocking::Client, StatusCode, Url};
+
+use crate::{FactorioApiError, ENV_AGENT};
+
+/// Serves the files of every mod at `<base url>/<mod>/<path>`, like the `data` folder of the game.
+///
+/// Requests are blocking, so renders using it must not run on an async executor thread
+/// (use `spawn_blocking` or a separate thread instead).
+#[derive(Debug, Clone)]
+pub struct SpriteServer {
+    base_url: Url,
+    client: Client,
+}
+
+impl SpriteServer {
+    pub fn new(base_url: &str) -> Result<Self, FactorioApiError> {
+        let base_url = Url::parse(base_url)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| FactorioApiError::InvalidBaseUrl(base_url.to_owned()))?;
+
+        let mut builder = Client::builder();
+        if let Ok(agent) = std::env::var(ENV_AGENT) {
+            builder = builder.user_agent(agent);
+        }
+
+        Ok(Self {
+            base_url,
+            client: builder.build()?,
+        })
+    }
+
+    /// Every part of the mod name & path is its own percent encoded segment,
+    /// the same parts as for a [`DirectorySource`](mod_util::sprite_source::DirectorySource) are rejected.
+    fn url(&self, mod_name: &str, path: &str) -> Option<Url> {
+        let parts = contained_parts(mod_name, path)?;
+
+        let mut url = self.base_url.clone();
+        url.path_segments_mut().ok()?.pop_if_empty().extend(parts);
+
+        Some(url)
+    }
+}
+
+impl SpriteSource for SpriteServer {
+    fn fetch(&self, mod_name: &str, path: &str) -> Result<Vec<u8>, ModError> {
+        let url = self
+            .url(mod_name, path)
+            .ok_or_else(|| ModError::NotProvided(format!("{mod_name}/{path}")))?;
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .map_err(|err| ModError::SourceError(err.to_string()))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(ModError::NotProvided(url.into())),
+            status if !status.is_success() => {
+                Err(ModError::SourceError(format!("{url} returned {status}")))
+            }
+            _ => Ok(response
+                .bytes()
+                .map_err(|err| ModError::SourceError(err.to_string()))?
+                .to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn url(base_url: &str, mod_name: &str, path: &str) -> Option<String> {
+        SpriteServer::new(base_url)
+            .unwrap()
+            .url(mod_name, path)
+            .map(String::from)
+    }
+
+    #[test]
+    fn joins_the_base_url() {
+        let expected = Some("https://cdn.example.com/sprites/base/graphics/icon.png".to_owned());
+
+        assert_eq!(
+            url(
+                "https://cdn.example.com/sprites",
+                "base",
+                "graphics/icon.png"
+            ),
+            expected
+        );
+        assert_eq!(
+            url(
+                "https://cdn.example.com/sprites/",
+                "base",
+                "graphics/icon.png"
+            ),
+            expected
+        );
+        assert_eq!(
+            url("https://cdn.example.com", "core", "graphics/icon.png").as_deref(),
+            Some("https://cdn.example.com/core/graphics/icon.png")
+        );
+    }
+
+    #[test]
+    fn encodes_special_characters() {
+        assert_eq!(
+            url("https://cdn.example.com", "my mod", "graphics/a b#1?.png").as_deref(),
+            Some("https://cdn.example.com/my%20mod/graphics/a%20b%231%3F.png")
+        );
+        assert_eq!(
+            url("https://cdn.example.com", "base", "graphics/100%.png").as_deref(),
+            Some("https://cdn.example.com/base/graphics/100%25.png")
+        );
+    }
+
+    #[test]
+    fn rejects_traversal() {
+        let base = "https://cdn.example.com/sprites/";
+
+        assert_eq!(url(base, "base", "../secret.png"), None);
+        assert_eq!(url(base, "base", "graphics/../../secret.png"), None);
+        assert_eq!(url(base, "base", "/etc/passwd"), None);
+        assert_eq!(url(base, "..", "secret.png"), None);
+        assert_eq!(url(base, "base/graphics", "icon.png"), None);
+        assert_eq!(url(base, "", "base/graphics/icon.png"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_base_urls() {
+        assert!(SpriteServer::new("cdn.example.com/sprites").is_err());
+        assert!(SpriteServer::new("mailto:sprites@example.com").is_err());
+    }
+}
@@ -0,0 +1,803 @@
+//! Runs the settings & data stage of the Factorio data lifecycle without the game.
+//!
+//! The `settings*.lua` & `data*.lua` files of the mods are executed in a sandboxed Lua 5.2 state that
+//! provides what the game offers in these stages (`data:extend`, `mods`, `settings`, `feature_flags`,
+//! `require`, `log`, `serpent`, ...). The resulting prototypes are returned in the format of the
+//! prototype dump of the game, see [`DataLoader::get_raw`].
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use mlua::{ChunkMode, HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Table, Value};
+use tracing::{debug, info};
+
+use mod_util::{dlc::FeatureFlags, TagTable, UsedMods};
+
+/// Prototype types that can be defined in the settings stage.
+pub const SETTING_TYPES: [&str; 5] = [
+    "bool-setting",
+    "int-setting",
+    "double-setting",
+    "string-setting",
+    "color-setting",
+];
+
+const SETTINGS_FILES: [&str; 3] = [
+    "settings.lua",
+    "settings-updates.lua",
+    "settings-final-fixes.lua",
+];
+
+const DATA_FILES: [&str; 3] = ["data.lua", "data-updates.lua", "data-final-fixes.lua"];
+
+/// `data`, `defines.direction` & the helper functions of the game.
+///
+/// Like in the game mods can't reach the file system, `load` only accepts source code.
+const PRELUDE: &str = r#"
+dofile = nil
+loadfile = nil
+
+local load_source = load
+function load(chunk, name, _, env)
+    return load_source(chunk, name, "t", env)
+end
+
+data = { raw = {}, is_demo = false }
+
+function data.extend(self, prototypes)
+    if type(prototypes) ~= "table" then
+        error("data:extend expects a list of prototypes, got " .. type(prototypes), 2)
+    end
+
+    for _, prototype in ipairs(prototypes) do
+        local kind, name = prototype.type, prototype.name
+        if type(kind) ~= "string" or type(name) ~= "string" then
+            error("prototype is missing its type or name", 2)
+        end
+
+        self.raw[kind] = self.raw[kind] or {}
+        self.raw[kind][name] = prototype
+    end
+end
+
+function table_size(t)
+    local count = 0
+    for _ in pairs(t) do
+        count = count + 1
+    end
+    return count
+end
+
+defines = {
+    direction = {
+        north = 0, northnortheast = 1, northeast = 2, eastnortheast = 3,
+        east = 4, eastsoutheast = 5, southeast = 6, southsoutheast = 7,
+        south = 8, southsouthwest = 9, southwest = 10, westsouthwest = 11,
+        west = 12, westnorthwest = 13, northwest = 14, northnorthwest = 15,
+    },
+}
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DataStageError {
+    #[error("lua error: {0}")]
+    Lua(#[from] mlua::Error),
+
+    #[error("error in {file}: {source}")]
+    Script {
+        file: String,
+
+        #[source]
+        source: mlua::Error,
+    },
+}
+
+type Result<T> = std::result::Result<T, DataStageError>;
+
+/// Resolves `require` like the game: relative to the current file, the mod root,
+/// `__mod__/` paths into other mods & the `lualib` of `core` as fallback.
+struct Loader<'a> {
+    mods: &'a UsedMods,
+
+    /// Mod & directory of the files that are currently executed.
+    stack: RefCell<Vec<(String, String)>>,
+
+    /// Results of the required files of the current mod, by their `__mod__/path`.
+    loaded: RefCell<HashMap<String, mlua::RegistryKey>>,
+}
+
+impl<'a> Loader<'a> {
+    fn new(mods: &'a UsedMods) -> Self {
+        Self {
+            mods,
+            stack: RefCell::new(Vec::new()),
+            loaded: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `path` of `mod_name`, `None` if the mod doesn't have the file.
+    fn run<'lua>(
+        &self,
+        lua: &'lua Lua,
+        mod_name: &str,
+        path: &str,
+    ) -> Option<mlua::Result<Value<'lua>>> {
+        let code = self.mods.get(mod_name)?.get_file(path).ok()?;
+        let code = code.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&code);
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        self.stack
+            .borrow_mut()
+            .push((mod_name.to_owned(), dir.to_owned()));
+        let res = lua
+            .load(code)
+            .set_name(format!("@__{mod_name}__/{path}"))
+            .set_mode(ChunkMode::Text)
+            .call(());
+        self.stack.borrow_mut().pop();
+
+        Some(res)
+    }
+
+    fn require<'lua>(&self, lua: &'lua Lua, name: &str) -> mlua::Result<Value<'lua>> {
+        let name = name.strip_suffix(".lua").unwrap_or(name);
+
+        let candidates = if let Some((mod_name, path)) = name
+            .strip_prefix("__")
+            .and_then(|name| name.split_once("__"))
+        {
+            let path = path.trim_start_matches(['/', '.']).replace('.', "/");
+            vec![(mod_name.to_owned(), path)]
+        } else {
+            let path = name.replace('.', "/");
+            let (mod_name, dir) = self.stack.borrow().last().cloned().unwrap_or_default();
+
+            let mut candidates = Vec::with_capacity(3);
+            if !dir.is_empty() {
+                candidates.push((mod_name.clone(), format!("{dir}/{path}")));
+            }
+            candidates.push((mod_name, path.clone()));
+            candidates.push(("core".to_owned(), format!("lualib/{path}")));
+            candidates
+        };
+
+        for (mod_name, path) in candidates {
+            let key = format!("__{mod_name}__/{path}");
+            if let Some(loaded) = self.loaded.borrow().get(&key) {
+                return lua.registry_value(loaded);
+            }
+
+            let Some(res) = self.run(lua, &mod_name, &format!("{path}.lua")) else {
+                continue;
+            };
+
+            // like in plain Lua modules without a result are cached as `true`
+            let res = match res? {
+                Value::Nil => Value::Boolean(true),
+                value => value,
+            };
+            let stored = lua.create_registry_value(res.clone())?;
+            self.loaded.borrow_mut().insert(key, stored);
+
+            return Ok(res);
+        }
+
+        Err(mlua::Error::RuntimeError(format!(
+            "module {name} not found"
+        )))
+    }
+
+    /// Every mod starts with an empty `package.loaded`.
+    fn reset(&self, lua: &Lua) {
+        for (_, key) in self.loaded.borrow_mut().drain() {
+            lua.remove_registry_value(key).ok();
+        }
+    }
+}
+
+/// Resources the mods of a [`DataLoader`] may use before they are stopped with an error,
+/// so an endless loop or a runaway table can't hang or exhaust the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Lua instructions over all stages.
+    pub instructions: u64,
+
+    /// Bytes the Lua state may allocate.
+    pub memory: usize,
+}
+
+impl Default for Limits {
+    /// Enough for large overhaul mods like Pyanodons.
+    fn default() -> Self {
+        Self {
+            instructions: 20_000_000_000,
+            // all of it on 32 bit targets
+            memory: usize::try_from(4_u64 * 1024 * 1024 * 1024).unwrap_or(usize::MAX),
+        }
+    }
+}
+
+/// Instructions between two checks of [`Limits::instructions`].
+const HOOK_INTERVAL: u32 = 10_000;
+
+/// Sandboxed Lua state that runs the stages of the data lifecycle of a set of mods like the game does on startup.
+///
+/// Run [`DataLoader::settings_stage`] before [`DataLoader::data_stage`], the startup settings of the
+/// data stage are taken from the defined settings.
+pub struct DataLoader<'a> {
+    lua: Lua,
+    loader: Loader<'a>,
+    load_order: &'a [String],
+}
+
+impl<'a> DataLoader<'a> {
+    /// Prepares the globals of the game for the mods in `load_order` with the default [`Limits`].
+    pub fn new(mods: &'a UsedMods, load_order: &'a [String]) -> Result<Self> {
+        Self::with_limits(mods, load_order, Limits::default())
+    }
+
+    /// Like [`DataLoader::new`] but with custom [`Limits`].
+    pub fn with_limits(
+        mods: &'a UsedMods,
+        load_order: &'a [String],
+        limits: Limits,
+    ) -> Result<Self> {
+        // no `io`, `os`, `package` or `debug`, mods run untrusted code
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::default(),
+        )?;
+
+        lua.set_memory_limit(limits.memory)?;
+        let executed = Cell::new(0u64);
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(HOOK_INTERVAL),
+            move |_, _| {
+                executed.set(executed.get() + u64::from(HOOK_INTERVAL));
+                if executed.get() > limits.instructions {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "instruction limit of {} exceeded",
+                        limits.instructions
+                    )));
+                }
+
+                Ok(())
+            },
+        );
+        let loader = Loader::new(mods);
+
+        lua.load(PRELUDE).set_name("=prelude").exec()?;
+
+        let globals = lua.globals();
+        globals.set(
+            "mods",
+            lua.create_table_from(load_order.iter().filter_map(|name| {
+                Some((name.as_str(), mods.get(name)?.info.version.to_string()))
+            }))?,
+        )?;
+        globals.set(
+            "feature_flags",
+            lua.to_value(&FeatureFlags::from_mods(mods))?,
+        )?;
+        globals.set(
+            "log",
+            lua.create_function(|lua, message: Value| {
+                let message = lua
+                    .coerce_string(message)?
+                    .map_or_else(|| "nil".to_owned(), |s| s.to_string_lossy().into_owned());
+                debug!("{message}");
+                Ok(())
+            })?,
+        )?;
+
+        if let Some(serpent) = loader.run(&lua, "core", "lualib/serpent.lua") {
+            globals.set("serpent", serpent?)?;
+        }
+        drop(globals);
+
+        Ok(Self {
+            lua,
+            loader,
+            load_order,
+        })
+    }
+
+    /// Runs `settings.lua`, `settings-updates.lua` & `settings-final-fixes.lua` of all mods.
+    pub fn settings_stage(&self) -> Result<()> {
+        self.run_stage(SETTINGS_FILES)
+    }
+
+    /// Runs `data.lua`, `data-updates.lua` & `data-final-fixes.lua` of all mods.
+    ///
+    /// `settings.startup` holds the defaults of the startup settings, overridden by the values in `startup`.
+    pub fn data_stage(&self, startup: &TagTable) -> Result<()> {
+        let lua = &self.lua;
+        let globals = lua.globals();
+        let raw = self.raw()?;
+
+        let mut settings = Vec::new();
+        let startup_values = lua.create_table()?;
+        for kind in SETTING_TYPES {
+            let Some(prototypes) = raw.get::<_, Option<Table>>(kind)? else {
+                continue;
+            };
+
+            for pair in prototypes.clone().pairs::<String, Table>() {
+                let (name, setting) = pair?;
+                if setting.get::<_, Option<String>>("setting_type")?.as_deref() != Some("startup") {
+                    continue;
+                }
+
+                // forced values of hidden settings can't be changed
+                let forced = match setting.get::<_, Option<bool>>("hidden")? {
+                    Some(true) => setting.get::<_, Value>("forced_value")?,
+                    _ => Value::Nil,
+                };
+                let value = match (forced, startup.get(&name)) {
+                    (Value::Nil, Some(value)) => lua.to_value(value)?,
+                    (Value::Nil, None) => setting.get::<_, Value>("default_value")?,
+                    (forced, _) => forced,
+                };
+
+                startup_values.set(name, lua.create_table_from([("value", value)])?)?;
+            }
+
+            settings.push((kind, prototypes));
+        }
+
+        globals.set(
+            "settings",
+            lua.create_table_from([
+                ("startup", startup_values),
+                ("global", lua.create_table()?),
+                ("player", lua.create_table()?),
+            ])?,
+        )?;
+
+        self.run_stage(DATA_FILES)?;
+
+        // `core` replaces `data` with its own loader, the settings are part of the dump of the game as well
+        let raw = self.raw()?;
+        for (kind, prototypes) in settings {
+            if !raw.contains_key(kind)? {
+                raw.set(kind, prototypes)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// All prototypes in `data.raw` by their type & name, in the format of the prototype dump of the game.
+    pub fn get_raw(&self) -> Result<serde_json::Map<String, serde_json::Value>> {
+        let mut res = serde_json::Map::new();
+        for pair in self.raw()?.pairs::<String, Table>() {
+            let (kind, prototypes) = pair?;
+            if let Some(prototypes) = to_json(Value::Table(prototypes), 0)? {
+                res.insert(kind, prototypes);
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn raw(&self) -> Result<Table<'_>> {
+        Ok(self
+            .lua
+            .globals()
+            .get::<_, Table>("data")?
+            .get::<_, Table>("raw")?)
+    }
+
+    /// Runs the `files` of all mods, every mod runs the first one before any of them runs the next.
+    fn run_stage(&self, files: [&str; 3]) -> Result<()> {
+        let lua = &self.lua;
+        let globals = lua.globals();
+
+        lua.scope(|scope| {
+            globals.set(
+                "require",
+                scope.create_function(|lua, name: String| self.loader.require(lua, &name))?,
+            )?;
+
+            for file in files {
+                for mod_name in self.load_order {
+                    self.loader.reset(lua);
+
+                    let Some(res) = self.loader.run(lua, mod_name, file) else {
+                        continue;
+                    };
+
+                    debug!("ran {file} of {mod_name}");
+                    if let Err(source) = res {
+                        return Ok(Err(DataStageError::Script {
+                            file: format!("__{mod_name}__/{file}"),
+                            source,
+                        }));
+                    }
+                }
+            }
+
+            self.loader.reset(lua);
+            Ok(Ok(()))
+        })??;
+
+        globals.set("require", Value::Nil)?;
+        Ok(())
+    }
+}
+
+/// Runs the settings stage of the mods in `load_order` and returns the defined settings
+/// by their type & name, see [`SETTING_TYPES`].
+pub fn settings_stage(
+    mods: &UsedMods,
+    load_order: &[String],
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let loader = DataLoader::new(mods, load_order)?;
+    loader.settings_stage()?;
+
+    let mut res = loader.get_raw()?;
+    res.retain(|kind, _| SETTING_TYPES.contains(&kind.as_str()));
+
+    info!(
+        "settings stage defined {} settings",
+        res.values()
+            .filter_map(serde_json::Value::as_object)
+            .map(serde_json::Map::len)
+            .sum::<usize>()
+    );
+
+    Ok(res)
+}
+
+/// Nesting depth at which tables are assumed to contain themselves.
+const MAX_DEPTH: usize = 128;
+
+/// Converts a value of `data.raw` like the dump of the game: tables with the keys `1..=n` become
+/// arrays, all other tables objects, and whole numbers integers since Lua 5.2 only knows doubles.
+/// Functions & other values that can't be dumped are left out.
+fn to_json(value: Value, depth: usize) -> Result<Option<serde_json::Value>> {
+    if depth > MAX_DEPTH {
+        return Err(
+            mlua::Error::RuntimeError("data.raw contains a recursive table".to_owned()).into(),
+        );
+    }
+
+    let value = match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::Boolean(value) => serde_json::Value::Bool(value),
+        Value::Integer(value) => serde_json::Value::from(value),
+        Value::Number(value) => number(value),
+        Value::String(value) => serde_json::Value::String(value.to_string_lossy().into_owned()),
+        Value::Table(table) => {
+            let mut entries = Vec::new();
+            for pair in table.pairs::<Value, Value>() {
+                let (key, value) = pair?;
+                let key = match key {
+                    Value::String(key) => key.to_string_lossy().into_owned(),
+                    Value::Integer(key) => key.to_string(),
+                    Value::Number(key) => number(key).to_string(),
+                    _ => continue,
+                };
+
+                if let Some(value) = to_json(value, depth + 1)? {
+                    entries.push((key, value));
+                }
+            }
+
+            let mut indices = entries
+                .iter()
+                .map(|(key, _)| key.parse::<usize>().ok().filter(|&idx| idx > 0))
+                .collect::<Option<Vec<_>>>()
+                .unwrap_or_default();
+            indices.sort_unstable();
+
+            if !indices.is_empty() && indices.iter().enumerate().all(|(pos, &idx)| idx == pos + 1) {
+                entries.sort_by_key(|(key, _)| key.parse::<usize>().unwrap_or_default());
+                entries.into_iter().map(|(_, value)| value).collect()
+            } else {
+                entries.into_iter().collect()
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(value))
+}
+
+fn number(value: f64) -> serde_json::Value {
+    if value.fract() == 0.0 && value.abs() < 2f64.powi(53) {
+        serde_json::Value::from(value as i64)
+    } else {
+        serde_json::Value::from(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use mod_util::{
+        mod_info::Version,
+        sprite_source::{self, DirectorySource, SharedSpriteSource},
+    };
+
+    use super::*;
+
+    const FILES: [(&str, &str); 15] = [
+        (
+            "core/lualib/util.lua",
+            "local util = {}
+            function util.copy(t) local res = {} for k, v in pairs(t) do res[k] = v end return res end
+            return util",
+        ),
+        (
+            "core/lualib/serpent.lua",
+            "return { line = function(t) return tostring(table_size(t)) end }",
+        ),
+        (
+            "alpha/settings.lua",
+            r#"local util = require("util")
+            local shared = require("prototypes.shared")
+            data:extend({
+                { type = "int-setting", name = "alpha-count", setting_type = "startup", default_value = 5, minimum_value = 1, maximum_value = 10 },
+                util.copy(shared.flag),
+            })
+            log(serpent.line(mods))"#,
+        ),
+        (
+            "alpha/prototypes/shared.lua",
+            r#"return { flag = { type = "bool-setting", name = "alpha-flag", setting_type = "startup", default_value = require("values").flag } }"#,
+        ),
+        ("alpha/prototypes/values.lua", "return { flag = true }"),
+        (
+            "beta/settings.lua",
+            r#"if mods["alpha"] and not feature_flags.quality then
+                data:extend({
+                    { type = "double-setting", name = "beta-ratio", setting_type = "runtime-global", default_value = 0.5 },
+                    { type = "string-setting", name = "beta-mode", setting_type = "startup", default_value = "a", allowed_values = { "a", "b" } },
+                })
+            end"#,
+        ),
+        (
+            "beta/settings-updates.lua",
+            r#"data.raw["int-setting"]["alpha-count"].default_value = 7"#,
+        ),
+        (
+            "beta/settings-final-fixes.lua",
+            r#"local shared = require("__alpha__/prototypes/shared")
+            data.raw["bool-setting"][shared.flag.name].hidden = true"#,
+        ),
+        ("gamma/settings.lua", r#"require("missing")"#),
+        (
+            "core/lualib/dataloader.lua",
+            "data = { raw = {} }
+            function data.extend(self, prototypes)
+                for _, p in ipairs(prototypes) do
+                    self.raw[p.type] = self.raw[p.type] or {}
+                    self.raw[p.type][p.name] = p
+                end
+            end",
+        ),
+        ("core/data.lua", r#"require("dataloader")"#),
+        (
+            "alpha/data.lua",
+            r#"data:extend({
+                { type = "item", name = "widget", sparse = { [1] = "a", [3] = "c" }, mixed = { "x", key = "y" }, stack_size = settings.startup["alpha-count"].value, flags = {}, icons = { { icon = "__alpha__/graphics/widget.png", icon_size = 64 } } },
+            })"#,
+        ),
+        (
+            "beta/data-updates.lua",
+            r#"data.raw.item.widget.hidden = settings.startup["alpha-flag"].value"#,
+        ),
+        (
+            "beta/data-final-fixes.lua",
+            r#"data.raw.item.widget.order = mods["beta"] .. tostring(settings.startup["beta-ratio"])"#,
+        ),
+        (
+            "delta/settings.lua",
+            r#"assert(io == nil and os == nil and package == nil and debug == nil)
+            assert(dofile == nil and loadfile == nil)
+            assert(load("return 1")() == 1)
+            assert(load(string.dump(function() end)) == nil)"#,
+        ),
+    ];
+
+    /// Directory of the mods of a test, removed again even if the test panics.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("datastage_{name}_{}", std::process::id())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn setup(root: &Path, names: &[&str]) -> (UsedMods, Vec<String>) {
+        for (path, content) in FILES {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let source: SharedSpriteSource = Arc::new(DirectorySource::new(root));
+        let mods = sprite_source::used_mods(
+            names
+                .iter()
+                .map(|name| ((*name).to_owned(), Version::new(1, 0, 0))),
+            &source,
+        );
+
+        (mods, names.iter().map(|name| (*name).to_owned()).collect())
+    }
+
+    #[test]
+    fn runs_all_stages() {
+        let root = TempDir::new("stages");
+        let (mods, load_order) = setup(&root.0, &["core", "alpha", "beta"]);
+
+        let res = settings_stage(&mods, &load_order).unwrap();
+
+        let count = &res["int-setting"]["alpha-count"];
+        assert_eq!(count["default_value"], serde_json::json!(7));
+        assert_eq!(count["maximum_value"], serde_json::json!(10));
+        assert!(count["default_value"].is_i64());
+
+        let flag = &res["bool-setting"]["alpha-flag"];
+        assert_eq!(flag["default_value"], serde_json::json!(true));
+        assert_eq!(flag["hidden"], serde_json::json!(true));
+
+        assert_eq!(
+            res["double-setting"]["beta-ratio"]["default_value"],
+            serde_json::json!(0.5)
+        );
+        assert_eq!(
+            res["string-setting"]["beta-mode"]["allowed_values"],
+            serde_json::json!(["a", "b"])
+        );
+        assert!(!res.contains_key("color-setting"));
+    }
+
+    #[test]
+    fn reports_failing_file() {
+        let root = TempDir::new("fail");
+        let (mods, load_order) = setup(&root.0, &["core", "gamma"]);
+
+        let err = settings_stage(&mods, &load_order).unwrap_err();
+
+        assert!(
+            matches!(&err, DataStageError::Script { file, .. } if file == "__gamma__/settings.lua"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("module missing not found"));
+    }
+
+    #[test]
+    fn sandboxed() {
+        let root = TempDir::new("sandbox");
+        let (mods, load_order) = setup(&root.0, &["core", "delta"]);
+
+        let res = settings_stage(&mods, &load_order);
+
+        assert!(res.unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_bytecode() {
+        let root = TempDir::new("bytecode");
+        let (mods, load_order) = setup(&root.0, &["core", "epsilon"]);
+        fs::create_dir_all(root.0.join("epsilon")).unwrap();
+        fs::write(root.0.join("epsilon/settings.lua"), b"\x1bLua\x52\x00").unwrap();
+
+        let err = settings_stage(&mods, &load_order).unwrap_err();
+
+        assert!(
+            matches!(&err, DataStageError::Script { file, .. } if file == "__epsilon__/settings.lua"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn runs_data_stage() {
+        let root = TempDir::new("data");
+        let (mods, load_order) = setup(&root.0, &["core", "alpha", "beta"]);
+
+        let loader = DataLoader::new(&mods, &load_order).unwrap();
+        loader.settings_stage().unwrap();
+        loader
+            .data_stage(&TagTable::from([
+                ("alpha-count".to_owned(), mod_util::AnyBasic::Number(3.0)),
+                ("alpha-flag".to_owned(), mod_util::AnyBasic::Bool(false)),
+            ]))
+            .unwrap();
+        let raw = loader.get_raw().unwrap();
+
+        let widget = &raw["item"]["widget"];
+        assert_eq!(widget["stack_size"], serde_json::json!(3));
+        assert_eq!(widget["hidden"], serde_json::json!(false));
+        assert_eq!(widget["order"], serde_json::json!("1.0.0nil"));
+        assert_eq!(widget["sparse"], serde_json::json!({ "1": "a", "3": "c" }));
+        assert_eq!(widget["mixed"], serde_json::json!({ "1": "x", "key": "y" }));
+        assert_eq!(widget["flags"], serde_json::json!({}));
+        assert_eq!(
+            widget["icons"],
+            serde_json::json!([{ "icon": "__alpha__/graphics/widget.png", "icon_size": 64 }])
+        );
+
+        // replaced by the loader of `core`, but still part of the dump
+        assert_eq!(
+            raw["int-setting"]["alpha-count"]["default_value"],
+            serde_json::json!(7)
+        );
+    }
+
+    #[test]
+    fn stops_endless_loops() {
+        let root = TempDir::new("loop");
+        let (mods, load_order) = setup(&root.0, &["core", "zeta"]);
+        fs::create_dir_all(root.0.join("zeta")).unwrap();
+        fs::write(root.0.join("zeta/settings.lua"), "while true do end").unwrap();
+
+        let loader = DataLoader::with_limits(
+            &mods,
+            &load_order,
+            Limits {
+                instructions: 1_000_000,
+                ..Limits::default()
+            },
+        )
+        .unwrap();
+        let err = loader.settings_stage().unwrap_err();
+
+        assert!(
+            matches!(&err, DataStageError::Script { file, .. } if file == "__zeta__/settings.lua"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("instruction limit"), "{err}");
+    }
+
+    #[test]
+    fn limits_memory() {
+        let root = TempDir::new("memory");
+        let (mods, load_order) = setup(&root.0, &["core", "eta"]);
+        fs::create_dir_all(root.0.join("eta")).unwrap();
+        fs::write(
+            root.0.join("eta/settings.lua"),
+            "local t = {} for i = 1, 1e9 do t[i] = string.rep('x', 1024) .. i end",
+        )
+        .unwrap();
+
+        let loader = DataLoader::with_limits(
+            &mods,
+            &load_order,
+            Limits {
+                memory: 16 * 1024 * 1024,
+                ..Limits::default()
+            },
+        )
+        .unwrap();
+        let err = loader.settings_stage().unwrap_err();
+
+        assert!(
+            matches!(
+                &err,
+                DataStageError::Script {
+                    source: mlua::Error::MemoryError(_),
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
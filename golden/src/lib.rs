@@ -0,0 +1,310 @@
+//! Golden image comparisons for render regression tests.
+//!
+//! Renders are compared against checked-in PNGs with the perceptual color difference of
+//! [pixelmatch](https://github.com/mapbox/pixelmatch): the distance of two pixels in the YIQ color
+//! space (Kotsalainen & Bangay, 2006), with transparent pixels blended onto white first.
+//! Failed comparisons write the render & a diff image (changed pixels in red over a faded copy of
+//! the golden image) to the output directory, set `UPDATE_GOLDEN=1` to accept the renders instead.
+
+use std::{env, fmt, fs, io, path::PathBuf};
+
+use image::{DynamicImage, GenericImageView, ImageResult, Rgba, RgbaImage};
+
+/// Largest possible YIQ distance of two pixels, the threshold is relative to it.
+const MAX_DELTA: f64 = 35215.0;
+
+/// Environment variable that makes [`Golden::check`] (re)write the golden images.
+pub const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// Color distance of a pixel in `0.0..=1.0` above which it counts as changed.
+///
+/// This is the default of pixelmatch: smaller changes are hard to tell apart by eye, while every
+/// layer that moved, was cropped differently or got another tint is clearly above it.
+pub const DEFAULT_THRESHOLD: f64 = 0.1;
+
+/// Share of changed pixels that is still accepted.
+///
+/// Resampling the sprites can round single edge pixels differently between image crate versions,
+/// a misplaced sprite changes far more than 0.1% of a layer.
+pub const DEFAULT_MAX_CHANGED_RATIO: f64 = 0.001;
+
+/// Compares images against the golden images in one directory.
+#[derive(Debug, Clone)]
+pub struct Golden {
+    dir: PathBuf,
+    output_dir: PathBuf,
+    update: bool,
+    threshold: f64,
+    max_changed_ratio: f64,
+}
+
+impl Golden {
+    /// Golden images are read from `golden_dir`, renders & diffs of failed comparisons are written to `output_dir`.
+    #[must_use]
+    pub fn new(golden_dir: impl Into<PathBuf>, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: golden_dir.into(),
+            output_dir: output_dir.into(),
+            update: env::var(UPDATE_ENV).is_ok_and(|v| v == "1"),
+            threshold: DEFAULT_THRESHOLD,
+            max_changed_ratio: DEFAULT_MAX_CHANGED_RATIO,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_threshold(mut self, threshold: f64, max_changed_ratio: f64) -> Self {
+        self.threshold = threshold;
+        self.max_changed_ratio = max_changed_ratio;
+        self
+    }
+
+    /// Compares `image` against `<golden_dir>/<name>.png`, [`None`] means no image is expected.
+    ///
+    /// In update mode the golden image is written (or removed) instead and the check always passes.
+    pub fn check(&self, name: &str, image: Option<&DynamicImage>) -> Result<(), Mismatch> {
+        let path = self.dir.join(format!("{name}.png"));
+
+        if self.update {
+            fs::create_dir_all(&self.dir)?;
+            match image {
+                Some(image) => image.save(&path)?,
+                None if path.exists() => fs::remove_file(&path)?,
+                None => {}
+            }
+
+            return Ok(());
+        }
+
+        let golden = path.exists().then(|| image::open(&path)).transpose()?;
+        let (image, golden) = match (image, golden) {
+            (None, None) => return Ok(()),
+            (None, Some(_)) => return Err(Mismatch::Missing),
+            (Some(image), None) => {
+                return Err(Mismatch::Unexpected {
+                    render: self.save_output(name, "render", image)?,
+                })
+            }
+            (Some(image), Some(golden)) => (image, golden),
+        };
+
+        if image.dimensions() != golden.dimensions() {
+            return Err(Mismatch::Size {
+                golden: golden.dimensions(),
+                render: self.save_output(name, "render", image)?,
+            });
+        }
+
+        let diff = Diff::new(&golden, image, self.threshold);
+        if diff.ratio() <= self.max_changed_ratio {
+            return Ok(());
+        }
+
+        Err(Mismatch::Changed {
+            ratio: diff.ratio(),
+            render: self.save_output(name, "render", image)?,
+            diff: self.save_output(name, "diff", &diff.image.into())?,
+        })
+    }
+
+    fn save_output(&self, name: &str, kind: &str, image: &DynamicImage) -> ImageResult<PathBuf> {
+        fs::create_dir_all(&self.output_dir)?;
+
+        let path = self.output_dir.join(format!("{name}.{kind}.png"));
+        image.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// Why an image doesn't match its golden image.
+#[derive(Debug)]
+pub enum Mismatch {
+    /// There is a golden image but nothing was rendered.
+    Missing,
+
+    /// Something was rendered but there is no golden image.
+    Unexpected {
+        render: PathBuf,
+    },
+
+    Size {
+        golden: (u32, u32),
+        render: PathBuf,
+    },
+
+    Changed {
+        ratio: f64,
+        render: PathBuf,
+        diff: PathBuf,
+    },
+
+    Image(image::ImageError),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "golden image exists but nothing was rendered"),
+            Self::Unexpected { render } => {
+                write!(f, "no golden image, render: {}", render.display())
+            }
+            Self::Size { golden, render } => write!(
+                f,
+                "size differs from the golden {}x{}, render: {}",
+                golden.0,
+                golden.1,
+                render.display()
+            ),
+            Self::Changed {
+                ratio,
+                render,
+                diff,
+            } => write!(
+                f,
+                "{:.3}% of the pixels changed, render: {}, diff: {}",
+                ratio * 100.0,
+                render.display(),
+                diff.display()
+            ),
+            Self::Image(err) => write!(f, "image error: {err}"),
+        }
+    }
+}
+
+impl From<image::ImageError> for Mismatch {
+    fn from(err: image::ImageError) -> Self {
+        Self::Image(err)
+    }
+}
+
+impl From<io::Error> for Mismatch {
+    fn from(err: io::Error) -> Self {
+        Self::Image(err.into())
+    }
+}
+
+/// Changed pixels of two images of the same size.
+#[derive(Debug, Clone)]
+pub struct Diff {
+    pub changed: u64,
+    pub total: u64,
+
+    /// Changed pixels in red, the others as a faded gray copy of the expected image.
+    pub image: RgbaImage,
+}
+
+impl Diff {
+    #[must_use]
+    pub fn new(expected: &DynamicImage, actual: &DynamicImage, threshold: f64) -> Self {
+        let max_delta = MAX_DELTA * threshold * threshold;
+        let expected = expected.to_rgba8();
+        let actual = actual.to_rgba8();
+
+        let mut changed = 0;
+        let image = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+            let a = *expected.get_pixel(x, y);
+            let b = *actual.get_pixel(x, y);
+
+            if color_delta(a, b) > max_delta {
+                changed += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                let gray = blend(0.1 * luma(a) / 255.0, 255.0) as u8;
+                Rgba([gray, gray, gray, 255])
+            }
+        });
+
+        Self {
+            changed,
+            total: u64::from(expected.width()) * u64::from(expected.height()),
+            image,
+        }
+    }
+
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        self.changed as f64 / self.total as f64
+    }
+}
+
+/// Blends a channel with the given alpha onto white.
+fn blend(alpha: f64, channel: f64) -> f64 {
+    (channel - 255.0).mul_add(alpha, 255.0)
+}
+
+fn blended(pixel: Rgba<u8>) -> (f64, f64, f64) {
+    let [r, g, b, a] = pixel.0.map(f64::from);
+    let alpha = a / 255.0;
+
+    (blend(alpha, r), blend(alpha, g), blend(alpha, b))
+}
+
+fn luma(pixel: Rgba<u8>) -> f64 {
+    let (r, g, b) = blended(pixel);
+    0.114_482_23f64.mul_add(b, 0.298_895_31f64.mul_add(r, 0.586_622_47 * g))
+}
+
+/// Squared YIQ distance of two pixels, weighted like pixelmatch.
+fn color_delta(expected: Rgba<u8>, actual: Rgba<u8>) -> f64 {
+    if expected == actual {
+        return 0.0;
+    }
+
+    let ((r1, g1, b1), (r2, g2, b2)) = (blended(expected), blended(actual));
+    let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+
+    let luma = 0.114_482_23f64.mul_add(db, 0.298_895_31f64.mul_add(dr, 0.586_622_47 * dg));
+    let in_phase = 0.321_841_41f64.mul_add(-db, 0.595_977_99f64.mul_add(dr, -0.274_176_4 * dg));
+    let quadrature = 0.311_146_94f64.mul_add(db, 0.211_470_17f64.mul_add(dr, -0.522_617_11 * dg));
+
+    0.1957f64.mul_add(
+        quadrature * quadrature,
+        0.5053f64.mul_add(luma * luma, 0.299 * in_phase * in_phase),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_range() {
+        let black = Rgba([0, 0, 0, 255]);
+        let white = Rgba([255, 255, 255, 255]);
+
+        assert!(color_delta(black, white) > MAX_DELTA * 0.9);
+        assert!(color_delta(black, white) <= MAX_DELTA);
+        assert!(color_delta(white, white).abs() < f64::EPSILON);
+
+        // transparent pixels look white, whatever their color
+        assert!(color_delta(Rgba([255, 0, 0, 0]), white).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn small_changes_are_below_the_threshold() {
+        let max_delta = MAX_DELTA * DEFAULT_THRESHOLD * DEFAULT_THRESHOLD;
+        let gray = Rgba([128, 128, 128, 255]);
+
+        assert!(color_delta(gray, Rgba([132, 130, 126, 255])) < max_delta);
+        assert!(color_delta(gray, Rgba([200, 128, 128, 255])) > max_delta);
+        assert!(color_delta(gray, Rgba([128, 128, 128, 128])) > max_delta);
+    }
+
+    #[test]
+    fn diff_marks_changed_pixels() {
+        let expected = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 255, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 1, Rgba([255, 255, 0, 255]));
+
+        let diff = Diff::new(&expected.into(), &actual.into(), DEFAULT_THRESHOLD);
+
+        assert_eq!(diff.changed, 1);
+        assert_eq!(diff.total, 8);
+        assert!((diff.ratio() - 0.125).abs() < f64::EPSILON);
+        assert_eq!(*diff.image.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+        assert_ne!(*diff.image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    }
+}
@@ -13,7 +13,61 @@ pub enum AnyBasic {
     Bool(bool),
     Number(f64),
     Table(TagTable),
-    Array(Vec<AnyBasic>),
+    Array(Vec<Self>),
+}
+
+impl AnyBasic {
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_table(&self) -> Option<&TagTable> {
+        match self {
+            Self::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` if this is a table.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        self.as_table()?.get(key)
+    }
+
+    /// Follows `path` through nested tables, an empty path returns `self`.
+    #[must_use]
+    pub fn get_path(&self, path: &[&str]) -> Option<&Self> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
 }
 
 impl std::fmt::Display for AnyBasic {
@@ -0,0 +1,170 @@
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::UsedMods;
+
+/// Expansion content of the game, shipped as mods in the data folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dlc {
+    ElevatedRails,
+    Quality,
+    SpaceAge,
+}
+
+impl Dlc {
+    pub const ALL: [Self; 3] = [Self::ElevatedRails, Self::Quality, Self::SpaceAge];
+
+    #[must_use]
+    pub const fn mod_name(self) -> &'static str {
+        match self {
+            Self::ElevatedRails => "elevated-rails",
+            Self::Quality => "quality",
+            Self::SpaceAge => "space-age",
+        }
+    }
+
+    #[must_use]
+    pub fn from_mod_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|dlc| dlc.mod_name() == name)
+    }
+
+    /// Other DLCs that have to be enabled together with this one.
+    #[must_use]
+    pub const fn requires(self) -> &'static [Self] {
+        match self {
+            Self::ElevatedRails | Self::Quality => &[],
+            Self::SpaceAge => &[Self::ElevatedRails, Self::Quality],
+        }
+    }
+
+    /// The given DLCs with all the DLCs they require, sorted & deduplicated.
+    #[must_use]
+    pub fn with_required(dlcs: &[Self]) -> Vec<Self> {
+        let mut res = dlcs
+            .iter()
+            .flat_map(|dlc| std::iter::once(*dlc).chain(dlc.requires().iter().copied()))
+            .collect::<Vec<_>>();
+        res.sort_unstable();
+        res.dedup();
+        res
+    }
+}
+
+impl fmt::Display for Dlc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mod_name())
+    }
+}
+
+impl FromStr for Dlc {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_mod_name(&s.to_lowercase().replace('_', "-")).ok_or_else(|| {
+            format!(
+                "unknown DLC {s}, expected one of: {}",
+                Self::ALL.map(Self::mod_name).join(", ")
+            )
+        })
+    }
+}
+
+/// [`Concepts/FeatureFlags`](https://lua-api.factorio.com/latest/concepts/FeatureFlags.html)
+///
+/// Enabled by the `feature_flags` of the active mods, the DLCs are the main source.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureFlags {
+    pub quality: bool,
+    pub rail_bridges: bool,
+    pub space_travel: bool,
+    pub spoiling: bool,
+    pub freezing: bool,
+    pub segmented_units: bool,
+    pub expansion_shaders: bool,
+}
+
+impl FeatureFlags {
+    /// Enables the flag `name`, returns `false` if it is unknown.
+    pub fn enable(&mut self, name: &str) -> bool {
+        let flag = match name {
+            "quality" => &mut self.quality,
+            "rail_bridges" => &mut self.rail_bridges,
+            "space_travel" => &mut self.space_travel,
+            "spoiling" => &mut self.spoiling,
+            "freezing" => &mut self.freezing,
+            "segmented_units" => &mut self.segmented_units,
+            "expansion_shaders" => &mut self.expansion_shaders,
+            _ => return false,
+        };
+
+        *flag = true;
+        true
+    }
+
+    /// The flags requested by the active mods.
+    #[must_use]
+    pub fn from_mods(mods: &UsedMods) -> Self {
+        let mut res = Self::default();
+        for flag in mods.values().flat_map(|m| &m.info.feature_flags) {
+            res.enable(flag);
+        }
+        res
+    }
+
+    #[must_use]
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            (self.quality, "quality"),
+            (self.rail_bridges, "rail_bridges"),
+            (self.space_travel, "space_travel"),
+            (self.spoiling, "spoiling"),
+            (self.freezing, "freezing"),
+            (self.segmented_units, "segmented_units"),
+            (self.expansion_shaders, "expansion_shaders"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+impl fmt::Display for FeatureFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.enabled().join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn dlc_requirements() {
+        assert_eq!(
+            Dlc::with_required(&[Dlc::SpaceAge]),
+            [Dlc::ElevatedRails, Dlc::Quality, Dlc::SpaceAge]
+        );
+        assert_eq!(
+            Dlc::with_required(&[Dlc::Quality, Dlc::Quality]),
+            [Dlc::Quality]
+        );
+
+        assert_eq!("Space_Age".parse::<Dlc>().unwrap(), Dlc::SpaceAge);
+        assert!("expansion".parse::<Dlc>().is_err());
+    }
+
+    #[test]
+    fn feature_flags() {
+        let mut flags = FeatureFlags::default();
+        assert!(flags.enable("spoiling"));
+        assert!(flags.enable("quality"));
+        assert!(!flags.enable("teleportation"));
+
+        assert_eq!(flags.to_string(), "quality, spoiling");
+    }
+}
@@ -5,11 +5,13 @@ use std::collections::HashMap;
 mod any_basic;
 pub use any_basic::*;
 
+pub mod dlc;
 pub mod mod_info;
 pub mod mod_list;
 pub mod mod_loader;
 pub mod mod_settings;
 pub mod property_tree;
+pub mod sprite_source;
 
 use mod_info::{DependencyVersion, Version};
 use mod_loader::Mod;
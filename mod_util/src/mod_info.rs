@@ -26,6 +26,10 @@ pub struct ModInfo {
 
     #[serde(default = "default_dep", skip_serializing_if = "is_default_dep")]
     pub dependencies: Vec<Dependency>,
+
+    /// Feature flags the mod enables, see [`crate::dlc::FeatureFlags`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feature_flags: Vec<String>,
 }
 
 fn default_dep() -> Vec<Dependency> {
@@ -242,7 +246,7 @@ impl<'de> Deserialize<'de> for Version {
 
 struct VersionVisitor;
 
-impl<'de> Visitor<'de> for VersionVisitor {
+impl Visitor<'_> for VersionVisitor {
     type Value = Version;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -529,7 +533,7 @@ impl<'de> Deserialize<'de> for Dependency {
 
 struct DependencyVisitor;
 
-impl<'de> Visitor<'de> for DependencyVisitor {
+impl Visitor<'_> for DependencyVisitor {
     type Value = Dependency;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
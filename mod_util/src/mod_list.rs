@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fs::{self, File},
     io::Read,
     path::{Path, PathBuf},
@@ -12,7 +12,8 @@ use serde_with::skip_serializing_none;
 use tracing::{debug, instrument, warn};
 
 use crate::{
-    mod_info::{Dependency, DependencyExt, DependencyUtil, DependencyVersion, Version},
+    dlc::Dlc,
+    mod_info::{Dependency, DependencyUtil, DependencyVersion, Version},
     mod_loader::{self, Mod},
     DependencyList, UsedMods, UsedVersions,
 };
@@ -28,29 +29,23 @@ pub enum ModListError {
     #[error("failed to load wube mod {0}: {1}")]
     WubeModLoadError(String, #[source] crate::mod_loader::ModError),
 
+    #[error("failed to load mod directory {0:?}: {1}")]
+    ModDirLoadError(PathBuf, #[source] crate::mod_loader::ModError),
+
+    #[error("mods folder already contains a different {0} at {1:?}")]
+    ModDirConflict(String, PathBuf),
+
     #[error("dependency solver could not find info about {0}")]
     SolverMissingInfo(String),
 
-    #[error("dependency solver could not find dependencies for {0} v{1}")]
-    SolverNoDependencies(String, Version),
-
     #[error("dependency solver could not find info about dep {0}")]
     SolverNoInfoOnDependency(String),
 
-    #[error("dependency solver could not find a version for {0} that satisfies {1}")]
-    SolverUnsatisfiable(String, DependencyVersion),
-
-    #[error("dependency solver could not find info about {0} while building edges")]
-    SolverEdgesMissingInfo(String),
+    #[error("dependency solver could not find a version of {0}:\n  {}", .1.join("\n  "))]
+    SolverConflict(String, Vec<String>),
 
-    #[error("dependency solver could not find dependencies for {0} v{1} while building edges")]
-    SolverEdgesNoInfoOnDependency(String, Version),
-
-    #[error("dependency solver could not find required node for {0} while building edges")]
-    SolverEdgesNodeNotFound(String),
-
-    #[error("dependency solver found conflicts for {0} v{1}: {2:?}")]
-    SolverFoundConflicts(String, Version, Vec<Dependency>),
+    #[error("dependency solver gave up after trying {0} versions")]
+    SolverGaveUp(usize),
 
     #[error("dependency solver found circular dependencies")]
     SolverCircularDependencies,
@@ -122,7 +117,7 @@ impl From<&ModList> for ModListFormat {
             mods.push(ModEntry {
                 name: name.clone(),
                 enabled: entry.enabled,
-                version: entry.active_version.map(Into::into),
+                version: entry.active_version,
             });
         }
 
@@ -142,8 +137,8 @@ impl ModList {
     /// This will also enable all mods that are not inside the mod list but are present in the mods folder.
     pub fn load(&mut self) -> Result<&mut Self> {
         println!(
-            "Loading mod list from {:?}",
-            self.mods_path.join("mod-list.json")
+            "Loading mod list from {}",
+            self.mods_path.join("mod-list.json").display()
         );
 
         let list = ModListFormat::load(self.mods_path.join("mod-list.json").canonicalize()?)?;
@@ -184,13 +179,44 @@ impl ModList {
         read_path: impl AsRef<Path>,
         write_path: impl AsRef<Path>,
     ) -> Result<Self> {
-        #[allow(clippy::unwrap_used)]
-        let filename_extractor = Regex::new(r"^(.+?)(?:_(\d+\.\d+\.\d+)(?:\.zip)?)?$").unwrap();
+        let mods_path = write_path.as_ref().join("mods");
+        let mut list = Self::wube_entries(&read_path, true)?;
+        Self::add_mods_folder(&mods_path, &mut list)?;
+
+        Ok(Self {
+            read_path: read_path.as_ref().to_owned(),
+            mods_path,
+            list,
+        })
+    }
+
+    /// Generates a mod list from a mods folder alone, without a game installation.
+    ///
+    /// Meant for rendering with an existing prototype dump where the mods are only needed for their sprites.
+    /// `core`, `base` & the DLCs are only part of the list if they are (unzipped) in the mods folder as well.
+    /// A missing folder results in an empty list.
+    ///
+    /// **Note:** all mods (except `core`) are disabled by default.
+    #[instrument(name = "generate_from_mods", skip_all)]
+    pub fn generate_from_mods_dir(mods_path: impl AsRef<Path>) -> Result<Self> {
+        let mods_path = mods_path.as_ref().to_owned();
+        let mut list = Self::wube_entries(&mods_path, false)?;
+
+        if mods_path.is_dir() {
+            Self::add_mods_folder(&mods_path, &mut list)?;
+        }
+
+        Ok(Self {
+            read_path: mods_path.clone(),
+            mods_path,
+            list,
+        })
+    }
 
+    /// Entries of the mods bundled with the game in `read_path`, `core` & `base` have to exist if `required` is set.
+    fn wube_entries(read_path: impl AsRef<Path>, required: bool) -> Result<HashMap<String, Entry>> {
         let mut list = HashMap::new();
-        let mods_path = write_path.as_ref().join("mods");
 
-        // add wube mods
         for w_mod in Mod::wube_mods() {
             match Mod::load_wube(&read_path, w_mod) {
                 Ok(m) => {
@@ -211,15 +237,22 @@ impl ModList {
                 }
                 Err(e) => {
                     // core and base should always be available
-                    if w_mod == "core" || w_mod == "base" {
+                    if required && (w_mod == "core" || w_mod == "base") {
                         return Err(ModListError::WubeModLoadError(w_mod.to_string(), e));
                     }
                 }
             }
         }
 
-        // add mods from mods folder
-        let paths = fs::read_dir(&mods_path)?;
+        Ok(list)
+    }
+
+    /// Adds every zipped & unzipped mod inside of `mods_path` to `list`.
+    fn add_mods_folder(mods_path: &Path, list: &mut HashMap<String, Entry>) -> Result<()> {
+        #[allow(clippy::unwrap_used)]
+        let filename_extractor = Regex::new(r"^(.+?)(?:_(\d+\.\d+\.\d+)(?:\.zip)?)?$").unwrap();
+
+        let paths = fs::read_dir(mods_path)?;
         for path in paths {
             let Ok(path) = path else {
                 continue;
@@ -264,11 +297,44 @@ impl ModList {
             //     .insert(m.info.version, m.info.dependencies);
         }
 
-        Ok(Self {
-            read_path: read_path.as_ref().to_owned(),
-            mods_path,
-            list,
-        })
+        Ok(())
+    }
+
+    /// Adds an unzipped mod from anywhere on disk, e.g. a mod that is still in development.
+    ///
+    /// The directory gets symlinked into the mods folder as `name` so the game picks it up as well.
+    /// Returns the name and version of the mod.
+    pub fn add_mod_dir(&mut self, path: impl AsRef<Path>) -> Result<(String, Version)> {
+        let path = path.as_ref();
+        let m = Mod::load_from_path(path)
+            .map_err(|err| ModListError::ModDirLoadError(path.to_owned(), err))?;
+        let name = m.info.name;
+        let version = m.info.version;
+
+        let link = self.mods_path.join(&name);
+        if link.exists() {
+            if fs::canonicalize(&link)? != fs::canonicalize(path)? {
+                return Err(ModListError::ModDirConflict(name, link));
+            }
+        } else {
+            let target = fs::canonicalize(path)?;
+
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(target, &link)?;
+
+            #[cfg(windows)]
+            std::os::windows::fs::symlink_dir(target, &link)?;
+
+            debug!("linked {} to {}", link.display(), path.display());
+        }
+
+        let entry = self.list.entry(name.clone()).or_default();
+        entry.versions.insert(version, Some(name.clone()));
+        entry
+            .known_dependencies
+            .insert(version, m.info.dependencies);
+
+        Ok((name, version))
     }
 
     pub fn save(&self) -> Result<()> {
@@ -335,9 +401,26 @@ impl ModList {
         )?))
     }
 
+    /// DLCs that are part of the installed game.
+    #[must_use]
+    pub fn available_dlcs(&self) -> Vec<Dlc> {
+        Dlc::ALL
+            .into_iter()
+            .filter(|dlc| self.list.contains_key(dlc.mod_name()))
+            .collect()
+    }
+
+    #[must_use]
+    pub fn enabled_dlcs(&self) -> Vec<Dlc> {
+        Dlc::ALL
+            .into_iter()
+            .filter(|dlc| self.is_enabled(dlc.mod_name()))
+            .collect()
+    }
+
     #[must_use]
     pub fn is_enabled(&self, name: &str) -> bool {
-        self.list.get(name).map_or(false, |e| e.enabled)
+        self.list.get(name).is_some_and(|e| e.enabled)
     }
 
     #[must_use]
@@ -441,183 +524,290 @@ impl ModList {
         e.known_dependencies = known_dependencies;
     }
 
+    /// Finds a version for every required mod and all of their required dependencies.
+    ///
+    /// Newer versions are preferred, the solver backtracks when a choice leads to unsatisfiable
+    /// version ranges or incompatibilities. Optional dependencies only constrain mods that end
+    /// up being used anyway.
     #[instrument(name = "solve_deps", skip_all)]
-    #[allow(clippy::too_many_lines)]
     pub fn solve_dependencies(&self, required: &DependencyList) -> Result<UsedVersions> {
         if required.is_empty() {
             return Ok(UsedVersions::default());
         }
 
-        // TODO: actually solve the constraints and not just check if the latest versions work
-        let mut dep_graph = DiGraph::<(&str, Version), &Dependency>::new();
-
-        let all_deps = self
-            .list
-            .iter()
-            .map(|(name, entry)| (name.as_str(), &entry.known_dependencies))
-            .collect::<HashMap<_, _>>();
-
-        let mut reqs = Vec::new();
+        let mut solver = Solver::new(&self.list);
         for (name, version) in required {
-            let Some(info) = self.list.get(name) else {
-                return Err(ModListError::SolverMissingInfo(name.to_string()));
+            // wube mods are bundled with the game and can not be swapped
+            let version = if Mod::wube_mods().contains(&name.as_str()) {
+                DependencyVersion::Any
+            } else {
+                *version
             };
 
-            let info_versions = info.versions.keys().copied().collect::<Vec<_>>();
-            if Mod::wube_mods().contains(&name.as_str()) {
-                let Some(version) = info_versions.first() else {
-                    return Err(ModListError::SolverMissingInfo(format!(
-                        "{name}, a static wube mod!"
-                    )));
-                };
+            solver.constrain(name, Constraint::Requested(version));
+        }
 
-                reqs.push((name.as_str(), *version));
-                continue;
+        if !solver.solve() {
+            return Err(solver
+                .failure
+                .map_or(ModListError::SolverGaveUp(MAX_SOLVER_STEPS), |(_, err)| err));
+        }
+
+        let selected = solver.selected.iter().copied().collect::<HashMap<_, _>>();
+
+        // check for circular dependencies in the load order
+        {
+            let mut dep_graph = DiGraph::<&str, ()>::new();
+            let node_map = selected
+                .keys()
+                .map(|name| (*name, dep_graph.add_node(name)))
+                .collect::<HashMap<_, _>>();
+
+            for (name, version) in &selected {
+                for dep in solver.dependencies(name, *version) {
+                    if !dep.affects_load_order() {
+                        continue;
+                    }
+
+                    if let Some(dep_node) = node_map.get(dep.name().as_str()) {
+                        dep_graph.add_edge(node_map[name], *dep_node, ());
+                    }
+                }
             }
 
-            let Some(version) = version.get_allowed_version(&info_versions) else {
-                return Err(ModListError::SolverMissingInfo(name.to_string()));
-            };
+            if petgraph::algo::is_cyclic_directed(&dep_graph) {
+                return Err(ModListError::SolverCircularDependencies);
+            }
+        }
 
-            reqs.push((name.as_str(), *version));
+        // dependencies are satisfied, hurray!
+        Ok(selected
+            .into_iter()
+            .map(|(name, version)| (name.to_owned(), version))
+            .collect())
+    }
+}
+
+/// Upper bound of versions the solver tries before giving up.
+const MAX_SOLVER_STEPS: usize = 100_000;
+
+/// Reason why the versions of a mod are restricted.
+#[derive(Debug, Clone, Copy)]
+enum Constraint<'a> {
+    Requested(DependencyVersion),
+    Dependency(&'a str, Version, &'a Dependency),
+}
+
+impl Constraint<'_> {
+    const fn is_required(&self) -> bool {
+        match self {
+            Self::Requested(_) => true,
+            Self::Dependency(_, _, dep) => dep.is_required(),
         }
+    }
 
-        // build all required mod nodes
-        let mut node_map = HashMap::new();
-        while let Some((name, version)) = reqs.pop() {
-            if node_map.contains_key(&name) {
-                continue;
-            }
+    fn allows(&self, name: &str, version: Version) -> bool {
+        match self {
+            Self::Requested(req) => req.allows(version),
+            Self::Dependency(_, _, dep) => !dep.conflicts(name, version),
+        }
+    }
+}
 
-            node_map.insert(name, dep_graph.add_node((name, version)));
+struct Solver<'a> {
+    list: &'a HashMap<String, Entry>,
+    constraints: BTreeMap<&'a str, Vec<Constraint<'a>>>,
+    selected: Vec<(&'a str, Version)>,
+    steps: usize,
 
-            let Some(info) = all_deps.get(name) else {
-                return Err(ModListError::SolverMissingInfo(name.to_string()));
-            };
+    /// Failure with the most selected mods, it is the most specific one.
+    failure: Option<(usize, ModListError)>,
+}
 
-            let Some(node_reqs) = info.get(&version) else {
-                return Err(ModListError::SolverNoDependencies(
-                    name.to_string(),
-                    version,
-                ));
-            };
+impl<'a> Solver<'a> {
+    const fn new(list: &'a HashMap<String, Entry>) -> Self {
+        Self {
+            list,
+            constraints: BTreeMap::new(),
+            selected: Vec::new(),
+            steps: 0,
+            failure: None,
+        }
+    }
 
-            // add required dependencies to the reqs queue
-            for dep in node_reqs {
-                if !dep.is_required() {
-                    continue;
-                }
+    fn constrain(&mut self, name: &'a str, constraint: Constraint<'a>) {
+        self.constraints.entry(name).or_default().push(constraint);
+    }
 
-                let dep_name = dep.name().as_str();
-                let dep_version = dep.version();
+    fn unconstrain(&mut self, name: &str) {
+        if let Some(constraints) = self.constraints.get_mut(name) {
+            constraints.pop();
+        }
+    }
 
-                let Some(info) = all_deps.get(dep_name) else {
-                    return Err(ModListError::SolverNoInfoOnDependency(dep_name.to_string()));
-                };
+    fn selected_version(&self, name: &str) -> Option<Version> {
+        self.selected
+            .iter()
+            .find_map(|(n, v)| (*n == name).then_some(*v))
+    }
 
-                let mut dep_versions = info
-                    .keys()
-                    .filter(|v| dep.allows(dep_name, **v))
-                    .collect::<Vec<_>>();
-                dep_versions.sort();
-
-                let Some(dep_version) = dep_versions.last() else {
-                    // no more versions to try, fail?
-                    return Err(ModListError::SolverUnsatisfiable(
-                        dep_name.to_string(),
-                        *dep_version,
-                    ));
-                };
+    fn dependencies(&self, name: &str, version: Version) -> &'a [Dependency] {
+        self.list
+            .get(name)
+            .and_then(|e| e.known_dependencies.get(&version))
+            .map_or(&[], Vec::as_slice)
+    }
 
-                reqs.push((dep_name, **dep_version));
-            }
+    /// Known versions of a mod, newest first.
+    fn versions(&self, name: &str) -> Vec<Version> {
+        let Some(entry) = self.list.get(name) else {
+            return Vec::new();
+        };
+
+        // wube mods don't need dependency info since they come with the game
+        let mut versions = if Mod::wube_mods().contains(&name) {
+            entry.versions.keys().copied().collect::<Vec<_>>()
+        } else {
+            entry.known_dependencies.keys().copied().collect::<Vec<_>>()
+        };
+
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        versions
+    }
+
+    /// Next mod that is required but has no version yet.
+    fn next_unselected(&self) -> Option<&'a str> {
+        self.constraints
+            .iter()
+            .find(|(name, constraints)| {
+                constraints.iter().any(Constraint::is_required)
+                    && self.selected_version(name).is_none()
+            })
+            .map(|(name, _)| *name)
+    }
+
+    fn solve(&mut self) -> bool {
+        let Some(name) = self.next_unselected() else {
+            return true;
+        };
+
+        let versions = self.versions(name);
+        if versions.is_empty() {
+            let requested = self.constraints[name]
+                .iter()
+                .any(|c| matches!(c, Constraint::Requested(_)));
+
+            self.fail(if requested {
+                ModListError::SolverMissingInfo(name.to_owned())
+            } else {
+                ModListError::SolverNoInfoOnDependency(name.to_owned())
+            });
+            return false;
         }
 
-        // add all dependency edges
-        for node in dep_graph.node_indices() {
-            let (name, version) = &dep_graph[node];
-            let Some(info) = all_deps.get(name) else {
-                return Err(ModListError::SolverEdgesMissingInfo((*name).to_string()));
-            };
+        let mut rejected = Vec::new();
+        for version in versions.iter().copied() {
+            if !self.constraints[name]
+                .iter()
+                .all(|c| c.allows(name, version))
+            {
+                continue;
+            }
 
-            let Some(deps) = info.get(version) else {
-                return Err(ModListError::SolverEdgesNoInfoOnDependency(
-                    (*name).to_string(),
-                    *version,
+            self.steps += 1;
+            if self.steps > MAX_SOLVER_STEPS {
+                self.fail(ModListError::SolverGaveUp(MAX_SOLVER_STEPS));
+                return false;
+            }
+
+            // the dependencies of this version need to accept the already selected mods
+            let deps = self.dependencies(name, version);
+            if let Some((dep, selected)) = deps.iter().find_map(|dep| {
+                let selected = self.selected_version(dep.name())?;
+                dep.conflicts(dep.name(), selected)
+                    .then_some((dep, selected))
+            }) {
+                rejected.push(format!(
+                    "{name} {version} declares \"{dep}\" but {} {selected} is used",
+                    dep.name()
                 ));
-            };
+                continue;
+            }
 
+            self.selected.push((name, version));
             for dep in deps {
-                let dep_name = dep.name().as_str();
+                self.constrain(dep.name(), Constraint::Dependency(name, version, dep));
+            }
 
-                match node_map.get(dep_name) {
-                    Some(dep_node) => {
-                        dep_graph.add_edge(node, *dep_node, dep);
-                    }
-                    None => {
-                        // optional / incompatible dependencies are allowed to be missing
-                        if dep.is_required() {
-                            return Err(ModListError::SolverEdgesNodeNotFound(
-                                dep_name.to_string(),
-                            ));
-                        }
-                    }
-                }
+            if self.solve() {
+                return true;
             }
-        }
 
-        // check if all requirements are satisfied
-        for node in dep_graph.node_indices() {
-            let (name, version) = &dep_graph[node];
-
-            let conflicts = dep_graph
-                .edges_directed(node, petgraph::Direction::Incoming)
-                .map(|e| *e.weight())
-                .cloned()
-                .collect_conflicts::<Vec<_>>(name, *version);
-
-            if !conflicts.is_empty() {
-                return Err(ModListError::SolverFoundConflicts(
-                    (*name).to_string(),
-                    *version,
-                    conflicts,
-                ));
+            for dep in deps {
+                self.unconstrain(dep.name());
             }
+            self.selected.pop();
         }
 
-        // check for circular dependencies
-        {
-            let mut dep_graph = dep_graph.clone();
+        let mut explanation = self.constraints[name]
+            .iter()
+            .map(|c| self.explain(name, c))
+            .collect::<Vec<_>>();
+        explanation.extend(rejected);
+        explanation.push(format!(
+            "available versions: {}",
+            versions
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+
+        self.fail(ModListError::SolverConflict(name.to_owned(), explanation));
+        false
+    }
 
-            // remove all edges that do not affect the load order
-            let mut to_remove = Vec::new();
-            for e_idx in dep_graph.edge_indices() {
-                if !dep_graph[e_idx].affects_load_order() {
-                    to_remove.push(e_idx);
+    fn fail(&mut self, err: ModListError) {
+        let depth = self.selected.len();
+        if self.failure.as_ref().is_none_or(|(d, _)| depth >= *d) {
+            self.failure = Some((depth, err));
+        }
+    }
+
+    /// Explains how a constraint came to be by following the chain of required mods.
+    fn explain(&self, name: &str, constraint: &Constraint<'a>) -> String {
+        let mut chain = Vec::new();
+        let mut current = (name, *constraint);
+        let mut visited = HashSet::new();
+
+        loop {
+            match current.1 {
+                Constraint::Requested(version) => {
+                    chain.push(format!("requested \"{}{version}\"", current.0));
+                    break;
                 }
-            }
+                Constraint::Dependency(source, source_version, dep) => {
+                    chain.push(format!("{source} {source_version} declares \"{dep}\""));
 
-            // sort and reverse since graph.remove_edge() invalidates
-            // the last index and moves it to the removed index
-            to_remove.sort_unstable();
-            to_remove.reverse();
+                    if !visited.insert(source) {
+                        break;
+                    }
 
-            for e_idx in to_remove {
-                dep_graph.remove_edge(e_idx);
-            }
+                    let Some(reason) = self
+                        .constraints
+                        .get(source)
+                        .and_then(|c| c.iter().find(|c| c.is_required()))
+                    else {
+                        break;
+                    };
 
-            if petgraph::algo::is_cyclic_directed(&dep_graph) {
-                return Err(ModListError::SolverCircularDependencies);
+                    current = (source, *reason);
+                }
             }
         }
 
-        // dependencies are satisfied, hurray!
-        // collect all used mods + versions
-        Ok(dep_graph
-            .raw_nodes()
-            .iter()
-            .map(|n| (n.weight.0.to_string(), n.weight.1))
-            .collect())
+        chain.reverse();
+        chain.join(" -> ")
     }
 }
 
@@ -625,8 +815,8 @@ fn dep_chain_len(active: &UsedMods) -> Vec<(String, usize)> {
     let mut cache = HashMap::new();
     let mut visit_list = HashSet::new();
     active
-        .iter()
-        .map(|(name, _)| {
+        .keys()
+        .map(|name| {
             (
                 name.clone(),
                 dep_chain_recur(name, active, &mut cache, &mut visit_list),
@@ -677,3 +867,182 @@ fn dep_chain_recur<'a>(
     cache.insert(target, max);
     max
 }
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn mod_list(mods: &[(&str, &str, &[&str])]) -> ModList {
+        let mut list = ModList {
+            read_path: PathBuf::new(),
+            mods_path: PathBuf::new(),
+            list: HashMap::new(),
+        };
+
+        for (name, version, deps) in mods {
+            let entry = list.list.entry((*name).to_owned()).or_default();
+            let version = Version::try_from(*version).unwrap();
+            let deps = deps
+                .iter()
+                .map(|d| serde_json::from_value(serde_json::Value::from(*d)).unwrap())
+                .collect();
+
+            entry.versions.insert(version, None);
+            entry.known_dependencies.insert(version, deps);
+        }
+
+        list
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn add_mod_dir() {
+        let root =
+            std::env::temp_dir().join(format!("mod_util_add_mod_dir_{}", std::process::id()));
+        let mod_dir = root.join("dev/my-mod");
+        let mods_path = root.join("mods");
+        fs::create_dir_all(&mod_dir).unwrap();
+        fs::create_dir_all(&mods_path).unwrap();
+        fs::write(
+            mod_dir.join("info.json"),
+            r#"{"name": "my-mod", "version": "0.1.0", "title": "My mod", "author": "me", "dependencies": ["base >= 2.0"]}"#,
+        )
+        .unwrap();
+
+        let mut list = ModList {
+            read_path: root.join("data"),
+            mods_path: mods_path.clone(),
+            list: HashMap::new(),
+        };
+
+        let (name, version) = list.add_mod_dir(&mod_dir).unwrap();
+        assert_eq!(name, "my-mod");
+        assert_eq!(version, Version::new(0, 1, 0));
+        assert!(mods_path.join("my-mod/info.json").is_file());
+
+        let m = Mod::load_custom(&list.read_path, &mods_path, &name, version).unwrap();
+        assert_eq!(m.info.dependencies.len(), 1);
+
+        // adding it again is fine since the link points to the same directory
+        assert!(list.add_mod_dir(&mod_dir).is_ok());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn generate_from_mods_dir() {
+        let mods_path =
+            std::env::temp_dir().join(format!("mod_util_mods_only_{}", std::process::id()));
+        fs::create_dir_all(mods_path.join("base")).unwrap();
+        fs::create_dir_all(mods_path.join("my-mod_0.1.0")).unwrap();
+        fs::write(
+            mods_path.join("base/info.json"),
+            r#"{"name": "base", "version": "2.0.24", "title": "Base", "author": "Wube"}"#,
+        )
+        .unwrap();
+        fs::write(
+            mods_path.join("my-mod_0.1.0/info.json"),
+            r#"{"name": "my-mod", "version": "0.1.0", "title": "My mod", "author": "me"}"#,
+        )
+        .unwrap();
+
+        let mut list = ModList::generate_from_mods_dir(&mods_path).unwrap();
+        assert!(!list.list.contains_key("core"));
+        assert!(list.list.contains_key("my-mod"));
+
+        list.enable_mods(&HashMap::from([
+            ("base".to_owned(), Version::new(2, 0, 24)),
+            ("my-mod".to_owned(), Version::new(0, 1, 0)),
+        ]));
+        let active = list.active_mods();
+        assert_eq!(active["base"].info.version, Version::new(2, 0, 24));
+        assert_eq!(active["my-mod"].info.title, "My mod");
+
+        fs::remove_dir_all(&mods_path).unwrap();
+        assert!(ModList::generate_from_mods_dir(&mods_path)
+            .unwrap()
+            .list
+            .is_empty());
+    }
+
+    fn solve(list: &ModList, required: &[&str]) -> Result<UsedVersions> {
+        let required = required
+            .iter()
+            .map(|r| ((*r).to_owned(), DependencyVersion::Any))
+            .collect();
+
+        list.solve_dependencies(&required)
+    }
+
+    #[test]
+    fn solve_version_ranges() {
+        let list = mod_list(&[
+            ("a", "1.0.0", &["b >= 1.1", "c < 2"]),
+            ("b", "1.0.0", &[]),
+            ("b", "1.2.0", &[]),
+            ("c", "1.5.0", &[]),
+            ("c", "2.0.0", &[]),
+        ]);
+
+        let res = solve(&list, &["a"]).unwrap();
+        assert_eq!(res["b"], Version::new(1, 2, 0));
+        assert_eq!(res["c"], Version::new(1, 5, 0));
+    }
+
+    #[test]
+    fn solve_backtracks() {
+        // the newest version of a requires a version of b that c does not accept
+        let list = mod_list(&[
+            ("a", "1.0.0", &["b = 1.0.0"]),
+            ("a", "2.0.0", &["b = 2.0.0"]),
+            ("b", "1.0.0", &[]),
+            ("b", "2.0.0", &[]),
+            ("c", "1.0.0", &["b < 2"]),
+        ]);
+
+        let res = solve(&list, &["a", "c"]).unwrap();
+        assert_eq!(res["a"], Version::new(1, 0, 0));
+        assert_eq!(res["b"], Version::new(1, 0, 0));
+    }
+
+    #[test]
+    fn solve_optional_and_lazy() {
+        let list = mod_list(&[
+            ("a", "1.0.0", &["? b >= 2", "~ c"]),
+            ("b", "1.0.0", &[]),
+            ("c", "1.0.0", &[]),
+        ]);
+
+        let res = solve(&list, &["a"]).unwrap();
+        assert!(!res.contains_key("b"));
+        assert!(res.contains_key("c"));
+
+        // b is used but does not satisfy the optional dependency
+        assert!(solve(&list, &["a", "b"]).is_err());
+    }
+
+    #[test]
+    fn solve_incompatible() {
+        let list = mod_list(&[
+            ("a", "1.0.0", &["! b"]),
+            ("b", "1.0.0", &[]),
+            ("c", "1.0.0", &["b"]),
+        ]);
+
+        assert!(solve(&list, &["a"]).is_ok());
+
+        let err = solve(&list, &["a", "c"]).unwrap_err();
+        let ModListError::SolverConflict(name, explanation) = err else {
+            panic!("unexpected error: {err}");
+        };
+
+        assert_eq!(name, "b");
+        assert!(explanation
+            .iter()
+            .any(|e| e == "requested \"c\" -> c 1.0.0 declares \"b\""));
+        assert!(explanation
+            .iter()
+            .any(|e| e == "requested \"a\" -> a 1.0.0 declares \"! b\""));
+    }
+}
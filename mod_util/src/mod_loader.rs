@@ -1,13 +1,17 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     fs::File,
-    io::Read,
+    io::{Cursor, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
 };
 
 use zip::ZipArchive;
 
-use crate::mod_info::{ModInfo, Version};
+use crate::{
+    mod_info::{ModInfo, Version},
+    sprite_source::SharedSpriteSource,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ModError {
@@ -20,7 +24,7 @@ pub enum ModError {
     #[error("mod zip is empty: {0:?}")]
     ZipEmpty(PathBuf),
 
-    #[error("could not get mod zips internal folder: {0:?}")]
+    #[error("mod zip does not contain an info.json: {0:?}")]
     UnknownInternalFolder(PathBuf),
 
     #[error("unable to parse info.json of {0}: {1}")]
@@ -43,6 +47,15 @@ pub enum ModError {
 
     #[error("mod mutable borrow error: {0}")]
     BorrowError(#[from] std::cell::BorrowMutError),
+
+    #[error("mod not found: {0}")]
+    ModNotFound(String),
+
+    #[error("file is not provided: {0}")]
+    NotProvided(String),
+
+    #[error("sprite source error: {0}")]
+    SourceError(String),
 }
 
 type Result<T> = std::result::Result<T, ModError>;
@@ -92,10 +105,10 @@ impl Mod {
 
         let name = name_extractor
             .captures(name)
-            .ok_or(ModError::InvalidFilename(name.into()))?
+            .ok_or_else(|| ModError::InvalidFilename(name.into()))?
             .get(1)
             .map(|n| n.as_str().to_owned())
-            .ok_or(ModError::InvalidFilename(name.into()))?;
+            .ok_or_else(|| ModError::InvalidFilename(name.into()))?;
 
         if name != info.name {
             return Err(ModError::NameMismatch {
@@ -138,6 +151,18 @@ impl Mod {
         Ok(Self { info, internal })
     }
 
+    /// Creates a mod whose files are fetched from `source` instead of a zip or folder on disk.
+    #[must_use]
+    pub fn from_source(info: ModInfo, source: SharedSpriteSource) -> Self {
+        Self {
+            internal: ModType::Source {
+                name: info.name.clone(),
+                source,
+            },
+            info,
+        }
+    }
+
     pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
         let internal = ModType::load_from_path(&path)?;
 
@@ -164,18 +189,30 @@ enum ModType {
         path: PathBuf,
     },
     Zip {
-        internal_prefix: String,
-        zip: RefCell<ZipArchive<File>>,
+        /// Archive index of every file by its path relative to the folder containing `info.json`.
+        files: HashMap<String, usize>,
+        zip: RefCell<ZipArchive<ZipReader>>,
+    },
+    Source {
+        name: String,
+        source: SharedSpriteSource,
     },
 }
 
 impl ModType {
+    /// Looks for `name_version.zip`, then for the unzipped `name_version` and `name` folders.
+    /// Symlinked zips and folders are followed.
     fn load(path: impl AsRef<Path>, name: &str, version: Version) -> Result<Self> {
         let zip_path = path.as_ref().join(format!("{name}_{version}.zip"));
         let (path, is_zip) = if zip_path.exists() && zip_path.is_file() {
             (zip_path, true)
         } else {
-            let folder_path = path.as_ref().join(name);
+            let versioned_path = path.as_ref().join(format!("{name}_{version}"));
+            let folder_path = if versioned_path.is_dir() {
+                versioned_path
+            } else {
+                path.as_ref().join(name)
+            };
 
             if !folder_path.exists() {
                 return Err(ModError::PathDoesNotExist(folder_path));
@@ -185,17 +222,11 @@ impl ModType {
         };
 
         if is_zip {
-            let zip = ZipArchive::new(File::open(&path)?)?;
-            let internal_prefix = get_zip_internal_folder(path, &zip)?;
-
-            Ok(Self::Zip {
-                internal_prefix,
-                zip: RefCell::new(zip),
-            })
+            Self::open_zip(&path)
         } else if path.is_dir() {
             Ok(Self::Folder { path })
         } else {
-            return Err(ModError::PathNotZipOrDir(path));
+            Err(ModError::PathNotZipOrDir(path))
         }
     }
 
@@ -208,19 +239,49 @@ impl ModType {
 
         if path.is_dir() {
             Ok(Self::Folder { path: path.into() })
-        } else if path.is_file() && path.extension().map_or(false, |ext| ext == "zip") {
-            let zip = ZipArchive::new(File::open(path)?)?;
-            let internal_prefix = get_zip_internal_folder(path, &zip)?;
-
-            Ok(Self::Zip {
-                internal_prefix,
-                zip: RefCell::new(zip),
-            })
+        } else if path.is_file() && path.extension().is_some_and(|ext| ext == "zip") {
+            Self::open_zip(path)
         } else {
-            return Err(ModError::PathNotZipOrDir(path.into()));
+            Err(ModError::PathNotZipOrDir(path.into()))
         }
     }
 
+    /// Opens a mod zip wherever its `info.json` is located, e.g. in nested or oddly named folders.
+    /// Zips that only contain another zip (as re-uploaded by some authors) are unpacked into memory.
+    fn open_zip(path: &Path) -> Result<Self> {
+        let mut zip = ZipArchive::new(ZipReader::File(File::open(path)?))?;
+
+        for _ in 0..=MAX_ZIP_NESTING {
+            if zip.is_empty() {
+                return Err(ModError::ZipEmpty(path.into()));
+            }
+
+            if let Some(files) = zip_files(&zip) {
+                return Ok(Self::Zip {
+                    files,
+                    zip: RefCell::new(zip),
+                });
+            }
+
+            let Some(inner) = (0..zip.len()).find(|&idx| {
+                zip.name_for_index(idx)
+                    .is_some_and(|name| name.to_ascii_lowercase().ends_with(".zip"))
+            }) else {
+                break;
+            };
+
+            let mut inner = zip.by_index(inner)?;
+            #[allow(clippy::cast_possible_truncation)]
+            let mut bytes = Vec::with_capacity(inner.size() as usize);
+            inner.read_to_end(&mut bytes)?;
+            drop(inner);
+
+            zip = ZipArchive::new(ZipReader::Nested(Cursor::new(bytes)))?;
+        }
+
+        Err(ModError::UnknownInternalFolder(path.into()))
+    }
+
     fn get_file(&self, file: &str) -> Result<Vec<u8>> {
         match self {
             Self::Folder { path } => {
@@ -231,13 +292,12 @@ impl ModType {
 
                 Ok(std::fs::read(path)?)
             }
-            Self::Zip {
-                internal_prefix,
-                zip,
-            } => {
-                let path = internal_prefix.clone() + file;
+            Self::Zip { files, zip } => {
+                let idx = files
+                    .get(&normalize_zip_path(file))
+                    .ok_or(zip::result::ZipError::FileNotFound)?;
                 let mut zip = zip.try_borrow_mut()?;
-                let mut file = zip.by_name(&path)?;
+                let mut file = zip.by_index(*idx)?;
 
                 // if the vec allocates not enough it will just reallocate
                 #[allow(clippy::cast_possible_truncation)]
@@ -246,20 +306,159 @@ impl ModType {
                 file.read_to_end(&mut bytes)?;
                 Ok(bytes)
             }
+            Self::Source { name, source } => source.fetch(name, file),
+        }
+    }
+}
+
+/// How many zips inside of zips are unpacked to find the mod.
+const MAX_ZIP_NESTING: usize = 2;
+
+/// Mod zip on disk or a zip that was packed inside of it.
+#[derive(Debug)]
+enum ZipReader {
+    File(File),
+    Nested(Cursor<Vec<u8>>),
+}
+
+impl Read for ZipReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::File(file) => file.read(buf),
+            Self::Nested(cursor) => cursor.read(buf),
         }
     }
 }
 
-fn get_zip_internal_folder(path: impl AsRef<Path>, zip: &ZipArchive<File>) -> Result<String> {
-    let res = zip
-        .file_names()
-        .next()
-        .ok_or_else(|| ModError::ZipEmpty(path.as_ref().into()))?
-        .split('/')
-        .next()
-        .ok_or(ModError::UnknownInternalFolder(path.as_ref().into()))?
-        .to_owned()
-        + "/";
-
-    Ok(res)
+impl Seek for ZipReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Self::File(file) => file.seek(pos),
+            Self::Nested(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+/// Forward slashes only & without leading `./` or `/`, some zip tools write Windows paths.
+fn normalize_zip_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut path = path.as_str();
+
+    loop {
+        if let Some(rest) = path.strip_prefix("./") {
+            path = rest;
+        } else if let Some(rest) = path.strip_prefix('/') {
+            path = rest;
+        } else {
+            return path.to_owned();
+        }
+    }
+}
+
+/// Maps the files of the zip relative to the shallowest folder containing an `info.json`,
+/// `None` if there is no `info.json` at all.
+fn zip_files<R: Read + Seek>(zip: &ZipArchive<R>) -> Option<HashMap<String, usize>> {
+    let names = (0..zip.len())
+        .filter_map(|idx| Some((idx, normalize_zip_path(zip.name_for_index(idx)?))))
+        .collect::<Vec<_>>();
+
+    let root = names
+        .iter()
+        .filter_map(|(_, name)| {
+            let root = name.strip_suffix("info.json")?;
+            (root.is_empty() || root.ends_with('/')).then_some(root)
+        })
+        .min_by_key(|root| root.matches('/').count())?;
+
+    Some(
+        names
+            .iter()
+            .filter_map(|(idx, name)| Some((name.strip_prefix(root)?.to_owned(), *idx)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    const INFO: &str = r#"{"name":"packed","version":"1.0.0","title":"Packed","author":"someone"}"#;
+
+    fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn load(test: &str, bytes: &[u8]) -> Result<Mod> {
+        let path =
+            std::env::temp_dir().join(format!("mod_loader_{test}_{}.zip", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let res = Mod::load_from_path(&path);
+        std::fs::remove_file(path).unwrap();
+        res
+    }
+
+    #[test]
+    fn nested_folders() {
+        let m = load(
+            "nested",
+            &zip_bytes(&[
+                ("readme.txt", b"hi"),
+                ("outer/packed_1.0.0/info.json", INFO.as_bytes()),
+                ("outer/packed_1.0.0/graphics/icon.png", b"png"),
+                ("outer/packed_1.0.0/examples/info.json", b"{}"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(m.info.name, "packed");
+        assert_eq!(m.get_file("graphics/icon.png").unwrap(), b"png");
+        assert!(m.get_file("readme.txt").is_err());
+    }
+
+    #[test]
+    fn windows_paths() {
+        let m = load(
+            "windows",
+            &zip_bytes(&[
+                ("packed\\info.json", INFO.as_bytes()),
+                ("packed\\graphics\\icon.png", b"png"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(m.get_file("graphics/icon.png").unwrap(), b"png");
+    }
+
+    #[test]
+    fn zip_in_zip() {
+        let inner = zip_bytes(&[
+            ("info.json", INFO.as_bytes()),
+            ("graphics/icon.png", b"png"),
+        ]);
+        let m = load("zip_in_zip", &zip_bytes(&[("packed_1.0.0.zip", &inner)])).unwrap();
+
+        assert_eq!(m.info.name, "packed");
+        assert_eq!(m.get_file("graphics/icon.png").unwrap(), b"png");
+    }
+
+    #[test]
+    fn missing_info_json() {
+        assert!(matches!(
+            load("missing", &zip_bytes(&[("packed/data.lua", b"")])),
+            Err(ModError::UnknownInternalFolder(_))
+        ));
+    }
 }
@@ -110,8 +110,8 @@ pub enum PropertyTree {
     Bool(bool),
     Number(f64),
     String(String),
-    List(Vec<PropertyTree>),
-    Dictionary(HashMap<String, PropertyTree>),
+    List(Vec<Self>),
+    Dictionary(HashMap<String, Self>),
     // SignedInteger(i32),
     // UnsignedInteger(u32),
 }
@@ -203,7 +203,7 @@ impl PropertyTree {
         }
     }
 
-    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Self>> {
+    pub const fn as_list_mut(&mut self) -> Option<&mut Vec<Self>> {
         match self {
             Self::List(val) => Some(val),
             _ => None,
@@ -218,7 +218,7 @@ impl PropertyTree {
         }
     }
 
-    pub fn as_dictionary_mut(&mut self) -> Option<&mut HashMap<String, Self>> {
+    pub const fn as_dictionary_mut(&mut self) -> Option<&mut HashMap<String, Self>> {
         match self {
             Self::Dictionary(val) => Some(val),
             _ => None,
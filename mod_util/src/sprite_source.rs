@@ -0,0 +1,149 @@
+//! Where the files of mods (mainly sprites, `__mod__/path`) are loaded from.
+//!
+//! Mods loaded from disk read their zip or folder, hosted renderers can plug in any other
+//! [`SpriteSource`] (e.g. a HTTP server in front of object storage) with [`used_mods`].
+
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    mod_info::{ModInfo, Version},
+    mod_loader::{Mod, ModError},
+    UsedMods,
+};
+
+pub trait SpriteSource: std::fmt::Debug {
+    /// Content of `path`, relative to the root of the mod `mod_name`.
+    fn fetch(&self, mod_name: &str, path: &str) -> Result<Vec<u8>, ModError>;
+}
+
+pub type SharedSpriteSource = Arc<dyn SpriteSource + Send + Sync>;
+
+/// Loaded mod zips & folders.
+impl SpriteSource for UsedMods {
+    fn fetch(&self, mod_name: &str, path: &str) -> Result<Vec<u8>, ModError> {
+        self.get(mod_name)
+            .ok_or_else(|| ModError::ModNotFound(mod_name.to_owned()))?
+            .get_file(path)
+    }
+}
+
+/// Plain directory with a folder of extracted files for every mod: `<root>/<mod>/<path>`.
+///
+/// The `data` folder of a Factorio installation has this layout for `core`, `base` & the DLC mods.
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+/// The mod folder & the parts of `path` inside of it, [`None`] if they would escape the root of the source.
+///
+/// Mod names & paths come from prototype data, every source that maps them to a location has to check them.
+#[must_use]
+pub fn contained_parts<'a>(mod_name: &'a str, path: &'a str) -> Option<Vec<&'a str>> {
+    let mod_dir = Path::new(mod_name);
+    if mod_dir.components().count() != 1 {
+        return None;
+    }
+
+    [mod_dir, Path::new(path)]
+        .iter()
+        .flat_map(|part| part.components())
+        .map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect()
+}
+
+impl SpriteSource for DirectorySource {
+    fn fetch(&self, mod_name: &str, path: &str) -> Result<Vec<u8>, ModError> {
+        if contained_parts(mod_name, path).is_none() {
+            return Err(ModError::NotProvided(format!("{mod_name}/{path}")));
+        }
+
+        let path = self.root.join(mod_name).join(path);
+        if !path.is_file() {
+            return Err(ModError::PathDoesNotExist(path));
+        }
+
+        Ok(std::fs::read(path)?)
+    }
+}
+
+/// Mods with the given versions whose files are all fetched lazily from `source`.
+#[must_use]
+pub fn used_mods(
+    versions: impl IntoIterator<Item = (String, Version)>,
+    source: &SharedSpriteSource,
+) -> UsedMods {
+    versions
+        .into_iter()
+        .map(|(name, version)| {
+            let info = ModInfo {
+                name: name.clone(),
+                version,
+                title: name.clone(),
+                author: String::new(),
+                contact: None,
+                homepage: None,
+                description: None,
+                factorio_version: None,
+                dependencies: Vec::new(),
+                feature_flags: Vec::new(),
+            };
+
+            (name, Mod::from_source(info, source.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn directory_source() {
+        let root = std::env::temp_dir().join(format!("sprite_source_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("base/graphics")).unwrap();
+        std::fs::write(root.join("base/graphics/icon.png"), b"png").unwrap();
+
+        let source: SharedSpriteSource = Arc::new(DirectorySource::new(&root));
+        let mods = used_mods(
+            HashMap::from([("base".to_owned(), Version::new(2, 0, 24))]),
+            &source,
+        );
+
+        assert_eq!(mods.fetch("base", "graphics/icon.png").unwrap(), b"png");
+        assert_eq!(mods["base"].get_file("graphics/icon.png").unwrap(), b"png");
+        assert!(mods.fetch("base", "graphics/missing.png").is_err());
+        assert!(mods.fetch("space-age", "graphics/icon.png").is_err());
+        assert!(source.fetch("base", "../base/graphics/icon.png").is_err());
+        assert!(source
+            .fetch("base", "graphics/../graphics/icon.png")
+            .is_err());
+        assert!(source.fetch("..", "root/base/graphics/icon.png").is_err());
+        assert!(source.fetch("base/graphics", "icon.png").is_err());
+        assert!(source.fetch("", "base/graphics/icon.png").is_err());
+
+        // absolute paths would replace the root when joined
+        let absolute = root.join("base/graphics/icon.png");
+        assert!(source.fetch("base", absolute.to_str().unwrap()).is_err());
+        assert!(source
+            .fetch(root.join("base").to_str().unwrap(), "graphics/icon.png")
+            .is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
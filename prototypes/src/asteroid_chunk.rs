@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use types::{Icon, RenderableGraphics};
+
+/// [`Prototypes/AsteroidChunkPrototype`](https://lua-api.factorio.com/latest/prototypes/AsteroidChunkPrototype.html)
+pub type AsteroidChunkPrototype = crate::BasePrototype<AsteroidChunkPrototypeData>;
+
+/// [`Prototypes/AsteroidChunkPrototype`](https://lua-api.factorio.com/latest/prototypes/AsteroidChunkPrototype.html)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AsteroidChunkPrototypeData {
+    #[serde(flatten)]
+    pub icon: Option<Icon>,
+}
+
+impl AsteroidChunkPrototypeData {
+    pub fn get_icon(
+        &self,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.icon
+            .as_ref()?
+            .render(scale, used_mods, image_cache, &())
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use types::{Direction, MapPosition};
+
+use crate::entity::RenderOpts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The entity was rendered, but some details like alt-mode icons are missing.
+    Warning,
+
+    /// The entity could not be rendered at all.
+    Error,
+}
+
+/// Problem found while rendering an entity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// The entity prototype is not part of the loaded data.
+    UnknownEntity,
+
+    /// The sprites of the entity failed to render.
+    MissingSprite,
+
+    /// The recipe is not part of the loaded data.
+    UnknownRecipe { recipe: String },
+
+    /// The icon of a recipe, filter or request failed to render.
+    MissingIcon { icon: String },
+
+    /// The entity does not support its direction.
+    BadDirection { direction: Direction },
+}
+
+impl std::fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownEntity => write!(f, "unknown entity"),
+            Self::MissingSprite => write!(f, "failed to render sprites"),
+            Self::UnknownRecipe { recipe } => write!(f, "unknown recipe {recipe}"),
+            Self::MissingIcon { icon } => write!(f, "failed to render icon {icon}"),
+            Self::BadDirection { direction } => write!(f, "unsupported direction {direction:?}"),
+        }
+    }
+}
+
+/// A problem of a single entity of the blueprint.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub entity_number: u64,
+    pub name: String,
+    pub position: MapPosition,
+
+    #[serde(flatten)]
+    pub kind: DiagnosticKind,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} #{} {} at {}: {}",
+            self.severity, self.entity_number, self.name, self.position, self.kind
+        )
+    }
+}
+
+/// Collects per-entity [`Diagnostic`]s during a render so they can be reported to the caller.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Records a problem of the entity `name` that gets rendered with `opts`.
+    pub fn push(
+        &mut self,
+        severity: Severity,
+        name: &str,
+        opts: &RenderOpts,
+        kind: DiagnosticKind,
+    ) {
+        self.0.push(Diagnostic {
+            severity,
+            entity_number: opts.entity_id,
+            name: name.to_owned(),
+            position: opts.position,
+            kind,
+        });
+    }
+
+    pub fn warn(&mut self, name: &str, opts: &RenderOpts, kind: DiagnosticKind) {
+        self.push(Severity::Warning, name, opts, kind);
+    }
+
+    pub fn error(&mut self, name: &str, opts: &RenderOpts, kind: DiagnosticKind) {
+        self.push(Severity::Error, name, opts, kind);
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter()
+    }
+
+    /// Entity numbers of all entities with at least one diagnostic of `severity`.
+    #[must_use]
+    pub fn entities(&self, severity: Severity) -> BTreeSet<u64> {
+        self.0
+            .iter()
+            .filter(|d| d.severity == severity)
+            .map(|d| d.entity_number)
+            .collect()
+    }
+
+    /// Entity numbers of all entities that could not be rendered.
+    #[must_use]
+    pub fn failed_entities(&self) -> BTreeSet<u64> {
+        self.entities(Severity::Error)
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn opts(entity_id: u64) -> RenderOpts {
+        RenderOpts {
+            entity_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn failed_entities() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.error("foo", &opts(1), DiagnosticKind::UnknownEntity);
+        diagnostics.warn(
+            "bar",
+            &opts(2),
+            DiagnosticKind::MissingIcon {
+                icon: "baz".to_owned(),
+            },
+        );
+        diagnostics.error("bar", &opts(3), DiagnosticKind::MissingSprite);
+        diagnostics.error("bar", &opts(3), DiagnosticKind::MissingSprite);
+
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(diagnostics.failed_entities(), BTreeSet::from([1, 3]));
+        assert_eq!(diagnostics.entities(Severity::Warning), BTreeSet::from([2]));
+    }
+
+    #[test]
+    fn serialize() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.warn(
+            "assembling-machine-1",
+            &opts(7),
+            DiagnosticKind::UnknownRecipe {
+                recipe: "foo".to_owned(),
+            },
+        );
+
+        let json = serde_json::to_value(&diagnostics).unwrap();
+        assert_eq!(json[0]["severity"], "warning");
+        assert_eq!(json[0]["kind"], "unknown_recipe");
+        assert_eq!(json[0]["recipe"], "foo");
+        assert_eq!(json[0]["entity_number"], 7);
+    }
+}
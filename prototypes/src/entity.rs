@@ -103,6 +103,9 @@ pub struct RenderOpts {
     pub orientation: Option<RealOrientation>,
     pub variation: Option<NonZeroU32>,
 
+    /// Flipped horizontally in the entity's own frame before it is rotated to its direction.
+    pub mirrored: bool,
+
     pub pickup_position: Option<Vector>,
 
     pub connections: Option<ConnectedDirections>,
@@ -122,6 +125,57 @@ pub struct RenderOpts {
     pub logistic_connected: bool,
 
     pub fluid_recipe: (bool, bool),
+
+    /// Seed used to pick a sprite variation when no explicit variation is set.
+    pub seed: Option<u64>,
+
+    /// Point in the animation cycle of animated sprites, 0 is the first frame and 1 wraps around to it again.
+    pub progress: f64,
+}
+
+impl RenderOpts {
+    /// Mirrors the already rotated connection offsets relative to the entity if it is flipped.
+    /// The flip happens before the rotation, so they are mirrored along the rotated north axis.
+    #[must_use]
+    pub fn mirror_connections(&self, connections: Vec<MapPosition>) -> Vec<MapPosition> {
+        if !self.mirrored {
+            return connections;
+        }
+
+        let (axis_x, axis_y) = self
+            .direction
+            .rotate_vector(Vector::new(0.0, -1.0))
+            .as_tuple();
+
+        connections
+            .into_iter()
+            .map(|conn| {
+                let (x, y) = conn.as_tuple();
+                let dot = 2.0 * x.mul_add(axis_x, y * axis_y);
+                MapPosition::Tuple(dot.mul_add(axis_x, -x), dot.mul_add(axis_y, -y))
+            })
+            .collect()
+    }
+
+    /// The explicit variation or one derived from the seed & position.
+    /// Falls back to the first variation when neither is available.
+    #[must_use]
+    pub fn variation(&self) -> NonZeroU32 {
+        self.variation
+            .or_else(|| {
+                let (x, y) = self.position.as_tuple();
+
+                // splitmix64 finalizer, stable across platforms & compiler versions
+                let mut hash = self.seed? ^ x.to_bits() ^ y.to_bits().rotate_left(32);
+                hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                hash ^= hash >> 31;
+
+                // callers reduce the variation modulo their variation count, 31 bits can't overflow
+                NonZeroU32::new((hash >> 33) as u32 + 1)
+            })
+            .unwrap_or(NonZeroU32::MIN)
+    }
 }
 
 // From impls for RenderOpts variants from types
@@ -153,11 +207,8 @@ impl From<&RenderOpts> for SpriteNWayRenderOpts {
 
 impl From<&RenderOpts> for SpriteVariationsRenderOpts {
     fn from(opts: &RenderOpts) -> Self {
-        #[allow(unsafe_code)]
         Self {
-            variation: opts
-                .variation
-                .unwrap_or(unsafe { NonZeroU32::new_unchecked(1) }),
+            variation: opts.variation(),
             runtime_tint: opts.runtime_tint,
         }
     }
@@ -166,7 +217,7 @@ impl From<&RenderOpts> for SpriteVariationsRenderOpts {
 impl From<&RenderOpts> for AnimationRenderOpts {
     fn from(value: &RenderOpts) -> Self {
         Self {
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -176,7 +227,7 @@ impl From<&RenderOpts> for Animation4WayRenderOpts {
     fn from(value: &RenderOpts) -> Self {
         Self {
             direction: value.direction,
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -184,12 +235,9 @@ impl From<&RenderOpts> for Animation4WayRenderOpts {
 
 impl From<&RenderOpts> for AnimationVariationsRenderOpts {
     fn from(value: &RenderOpts) -> Self {
-        #[allow(unsafe_code)]
         Self {
-            variation: value
-                .variation
-                .unwrap_or(unsafe { NonZeroU32::new_unchecked(1) }),
-            progress: 0.0,
+            variation: value.variation(),
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -201,7 +249,7 @@ impl From<&RenderOpts> for RotatedAnimationRenderOpts {
             orientation: value
                 .orientation
                 .unwrap_or_else(|| value.direction.to_orientation()),
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
             override_index: None,
         }
@@ -213,7 +261,7 @@ impl From<&RenderOpts> for RotatedAnimation4WayRenderOpts {
         Self {
             direction: value.direction,
             orientation: value.orientation.unwrap_or_default(),
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -222,6 +270,7 @@ impl From<&RenderOpts> for RotatedAnimation4WayRenderOpts {
 impl From<&RenderOpts> for BeaconGraphicsSetRenderOpts {
     fn from(value: &RenderOpts) -> Self {
         Self {
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -232,6 +281,7 @@ impl From<&RenderOpts> for TransportBeltAnimationSetRenderOpts {
         Self {
             direction: opts.direction,
             connections: opts.connections,
+            progress: opts.progress,
 
             runtime_tint: opts.runtime_tint,
 
@@ -244,6 +294,7 @@ impl From<&RenderOpts> for MiningDrillGraphicsRenderOpts {
     fn from(value: &RenderOpts) -> Self {
         Self {
             direction: value.direction,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -271,10 +322,29 @@ pub trait Renderable {
     fn recipe_visible(&self) -> bool {
         false
     }
+
+    /// Whether the entity needs to be connected to an electric network.
+    fn uses_electricity(&self) -> bool {
+        false
+    }
+
+    /// Lights the entity emits while it is working, drawn in night renders.
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        Vec::new()
+    }
+
+    /// Maximum length of the wires connected to the entity, if it has wire connectors.
+    fn wire_reach(&self) -> Option<f64> {
+        None
+    }
 }
 
 /// [`Prototypes/EntityPrototype`](https://lua-api.factorio.com/latest/prototypes/EntityPrototype.html)
 #[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "preserve_unknown",
+    serde(bound(deserialize = "T: Deserialize<'de> + Serialize"))
+)]
 pub struct EntityPrototype<T: Renderable>(BasePrototype<EntityData<T>>);
 
 impl<T: Renderable> Deref for EntityPrototype<T> {
@@ -308,6 +378,18 @@ impl<T: Renderable> Renderable for EntityPrototype<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        self.child.wire_reach()
+    }
 }
 
 pub trait RenderableEntity: Renderable {
@@ -315,15 +397,22 @@ pub trait RenderableEntity: Renderable {
     fn selection_box(&self) -> BoundingBox;
     fn drawing_box(&self) -> BoundingBox;
 
+    /// The explicit collision mask, `None` if the default one of the entity type applies.
+    fn collision_mask(&self) -> Option<&CollisionMaskConnector>;
+
     fn pipe_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)>;
     fn heat_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)>;
 
     fn show_recipe(&self) -> bool;
+
+    fn placeable_by(&self) -> Option<&PlaceableBy>;
+
+    fn icon(&self) -> Option<&Icon>;
 }
 
 impl<R, T> RenderableEntity for T
 where
-    R: Renderable,
+    R: Renderable + 'static,
     T: Renderable + Deref<Target = BasePrototype<EntityData<R>>>,
 {
     fn collision_box(&self) -> BoundingBox {
@@ -334,6 +423,10 @@ where
         self.selection_box.clone().unwrap_or_default()
     }
 
+    fn collision_mask(&self) -> Option<&CollisionMaskConnector> {
+        self.collision_mask.as_ref()
+    }
+
     fn drawing_box(&self) -> BoundingBox {
         self.drawing_box
             .clone()
@@ -341,7 +434,7 @@ where
     }
 
     fn pipe_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)> {
-        let raw_connections = self.fluid_box_connections(options);
+        let raw_connections = options.mirror_connections(self.fluid_box_connections(options));
 
         if raw_connections.is_empty() {
             return Vec::new();
@@ -385,7 +478,7 @@ where
     }
 
     fn heat_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)> {
-        let raw_connections = self.heat_buffer_connections(options);
+        let raw_connections = options.mirror_connections(self.heat_buffer_connections(options));
 
         if raw_connections.is_empty() {
             return Vec::new();
@@ -431,6 +524,14 @@ where
     fn show_recipe(&self) -> bool {
         self.recipe_visible()
     }
+
+    fn placeable_by(&self) -> Option<&PlaceableBy> {
+        self.placeable_by.as_ref()
+    }
+
+    fn icon(&self) -> Option<&Icon> {
+        self.icon.as_ref()
+    }
 }
 
 /// [`Prototypes/EntityPrototype`](https://lua-api.factorio.com/latest/prototypes/EntityPrototype.html)
@@ -441,7 +542,7 @@ pub struct EntityData<T: Renderable> {
     pub icon: Option<Icon>,
 
     pub collision_box: Option<BoundingBox>,
-    pub collision_mask: Option<CollisionMask>,
+    pub collision_mask: Option<CollisionMaskConnector>,
 
     pub map_generator_bounding_box: Option<BoundingBox>,
     pub selection_box: Option<BoundingBox>,
@@ -523,19 +624,20 @@ pub struct EntityData<T: Renderable> {
     pub enemy_map_color: Option<Color>,
 
     pub water_reflection: Option<WaterReflectionDefinition>,
+
+    pub build_sound: Option<Sound>,
+    pub mined_sound: Option<Sound>,
+    pub mining_sound: Option<Sound>,
+    pub rotated_sound: Option<Sound>,
+    pub vehicle_impact_sound: Option<Sound>,
+    pub open_sound: Option<Sound>,
+    pub close_sound: Option<Sound>,
     // not implemented
     // pub trigger_target_mask: Option<TriggerTargetMask>,
     // pub minable: Option<MinableProperties>,
     // pub created_smoke: Option<CreateTrivialSmokeEffectItem>,
     // pub working_sound: Option<WorkingSound>,
     // pub created_effect: Option<Trigger>,
-    // pub build_sound: Option<Sound>,
-    // pub mined_sound: Option<Sound>,
-    // pub mining_sound: Option<Sound>,
-    // pub rotated_sound: Option<Sound>,
-    // pub vehicle_impact_sound: Option<Sound>,
-    // pub open_sound: Option<Sound>,
-    // pub close_sound: Option<Sound>,
     // pub remains_when_mined: Option<RemainsWhenMined>,
     // pub autoplace: Option<AutoplaceSpecification>,
     #[serde(flatten)]
@@ -573,6 +675,18 @@ impl<T: Renderable> Renderable for EntityData<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        self.child.wire_reach()
+    }
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -595,7 +709,7 @@ impl serde::ser::Serialize for DecorativeRemoveMode {
 
 struct DecorativeRemoveModeVisitor;
 
-impl<'de> serde::de::Visitor<'de> for DecorativeRemoveModeVisitor {
+impl serde::de::Visitor<'_> for DecorativeRemoveModeVisitor {
     type Value = DecorativeRemoveMode;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -662,13 +776,13 @@ pub struct EntityWithHealthData<T: Renderable> {
 
     pub integration_patch_render_layer: Option<RenderLayer>,
     pub integration_patch: Option<Sprite4Way>,
+    pub repair_sound: Option<Sound>,
     // not implemented
     // pub dying_explosion: Option<ExplosionDefinition>,
     // pub dying_trigger_effect: Option<TriggerEffect>,
     // pub damaged_trigger_effect: Option<TriggerEffect>,
     // pub loot: FactorioArray<LootItem>,
     // pub attack_reaction: AttackReactionItem or FactorioArray<AttackReactionItem>,
-    // pub repair_sound: Option<Sound>,
     // pub corpse: Option<Corpse>,
     #[serde(flatten)]
     child: T,
@@ -724,6 +838,18 @@ impl<T: Renderable> Renderable for EntityWithHealthData<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        self.child.wire_reach()
+    }
 }
 
 /// [`Prototypes/EntityWithHealthPrototype`](https://lua-api.factorio.com/latest/prototypes/EntityWithHealthPrototype.html)
@@ -773,6 +899,18 @@ impl<T: Renderable> Renderable for EntityWithOwnerData<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        self.child.wire_reach()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -842,6 +980,7 @@ pub enum Type {
     CargoWagon,
     FluidWagon,
     ArtilleryWagon,
+    SpiderVehicle,
 }
 
 #[allow(clippy::match_like_matches_macro)]
@@ -857,6 +996,20 @@ impl Type {
         }
     }
 
+    /// Belt entities that only support the 4 cardinal directions.
+    #[must_use]
+    pub const fn is_belt(&self) -> bool {
+        matches!(
+            self,
+            Self::TransportBelt
+                | Self::UndergroundBelt
+                | Self::Splitter
+                | Self::Loader
+                | Self::Loader1x1
+                | Self::LinkedBelt
+        )
+    }
+
     #[must_use]
     #[allow(clippy::match_same_arms)]
     pub const fn can_connect_to(&self, other: &Self) -> bool {
@@ -993,12 +1146,196 @@ namespace_struct! {
     "locomotive",
     "cargo-wagon",
     "fluid-wagon",
-    "artillery-wagon"
+    "artillery-wagon",
+    "spider-vehicle"
 
     // not implemented
     // character,
     // unit-spawner,
     // player-port,
     // unit,
-    // spider-vehicle,
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn seeded_variation(seed: u64, x: f64, y: f64, count: u32) -> u32 {
+        let opts = RenderOpts {
+            position: MapPosition::Tuple(x, y),
+            seed: Some(seed),
+            ..Default::default()
+        };
+
+        (opts.variation().get() - 1) % count
+    }
+
+    #[test]
+    fn seeded_variations_cover_all() {
+        for count in 2..=8 {
+            let picked = (0..64)
+                .map(|seed| seeded_variation(seed, 0.5, 0.5, count))
+                .collect::<HashSet<_>>();
+
+            assert_eq!(picked.len(), count as usize, "{count} variations");
+        }
+    }
+
+    #[test]
+    fn seed_changes_variation() {
+        let picked = (0..16)
+            .map(|seed| seeded_variation(seed, 3.5, -2.5, 2))
+            .collect::<HashSet<_>>();
+
+        assert_eq!(picked.len(), 2);
+        assert_eq!(
+            seeded_variation(7, 3.5, -2.5, 5),
+            seeded_variation(7, 3.5, -2.5, 5)
+        );
+    }
+
+    #[test]
+    fn explicit_variation_wins() {
+        let opts = RenderOpts {
+            variation: NonZeroU32::new(4),
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(opts.variation().get(), 4);
+        assert_eq!(RenderOpts::default().variation(), NonZeroU32::MIN);
+    }
+
+    /// Serves a 2x1 sheet with a red & a blue frame for every file.
+    #[derive(Debug)]
+    struct TwoFrames(Vec<u8>);
+
+    impl mod_util::sprite_source::SpriteSource for TwoFrames {
+        fn fetch(
+            &self,
+            _mod_name: &str,
+            _path: &str,
+        ) -> Result<Vec<u8>, mod_util::mod_loader::ModError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn seeds_pick_sheet_frames() {
+        let mut sheet = image::RgbaImage::new(2, 1);
+        sheet.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        sheet.put_pixel(1, 0, image::Rgba([0, 0, 255, 255]));
+        let mut png = Vec::new();
+        sheet
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let source: mod_util::sprite_source::SharedSpriteSource =
+            std::sync::Arc::new(TwoFrames(png));
+        let used_mods = mod_util::sprite_source::used_mods(
+            [("base".to_owned(), crate::targeted_engine_version())],
+            &source,
+        );
+
+        let variations: SpriteVariations = serde_json::from_str(
+            r#"{"sheet":{"filename":"__base__/sheet.png","width":1,"height":1,"variation_count":2}}"#,
+        )
+        .unwrap();
+
+        let seed_for = |frame| {
+            (0..16)
+                .find(|&seed| seeded_variation(seed, 0.5, 0.5, 2) == frame)
+                .unwrap()
+        };
+
+        let mut image_cache = ImageCache::new();
+        let mut color = |seed: u64| {
+            let opts = RenderOpts {
+                position: MapPosition::Tuple(0.5, 0.5),
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let (img, _) = variations
+                .render(
+                    32.0,
+                    &used_mods,
+                    &mut image_cache,
+                    &SpriteVariationsRenderOpts {
+                        variation: opts.variation(),
+                        runtime_tint: None,
+                    },
+                )
+                .unwrap();
+
+            img.to_rgba8().get_pixel(0, 0).0
+        };
+
+        assert_eq!(color(seed_for(0)), [255, 0, 0, 255]);
+        assert_eq!(color(seed_for(1)), [0, 0, 255, 255]);
+    }
+
+    fn chemical_plant() -> AssemblingMachinePrototype {
+        let dump = std::fs::read(format!(
+            "test_dumps/base.{}.json",
+            crate::targeted_engine_version()
+        ))
+        .unwrap();
+        let mut dump: serde_json::Value = serde_json::from_slice(&dump).unwrap();
+        let mut plant = dump["assembling-machine"]["chemical-plant"].take();
+
+        // the quality effect is not known yet
+        plant.as_object_mut().unwrap().remove("allowed_effects");
+
+        serde_json::from_value(plant).unwrap()
+    }
+
+    #[test]
+    fn mirror_before_rotation() {
+        let plant = chemical_plant();
+        let connections = |direction, mirrored| {
+            plant
+                .pipe_connections(&RenderOpts {
+                    direction,
+                    mirrored,
+                    ..Default::default()
+                })
+                .into_iter()
+                .map(|(pos, dir)| (pos.as_tuple(), dir))
+                .collect::<Vec<_>>()
+        };
+
+        // the inputs are on the north side, the first one on the left
+        assert_eq!(
+            connections(Direction::North, true),
+            [
+                ((1.0, -2.0), Direction::South),
+                ((-1.0, -2.0), Direction::South),
+                ((1.0, 2.0), Direction::North),
+                ((-1.0, 2.0), Direction::North),
+            ]
+        );
+
+        // facing east the inputs stay on the east side, only their order is flipped
+        assert_eq!(
+            connections(Direction::East, false),
+            [
+                ((2.0, -1.0), Direction::West),
+                ((2.0, 1.0), Direction::West),
+                ((-2.0, -1.0), Direction::East),
+                ((-2.0, 1.0), Direction::East),
+            ]
+        );
+        assert_eq!(
+            connections(Direction::East, true),
+            [
+                ((2.0, 1.0), Direction::West),
+                ((2.0, -1.0), Direction::West),
+                ((-2.0, 1.0), Direction::East),
+                ((-2.0, -1.0), Direction::East),
+            ]
+        );
+    }
 }
@@ -39,7 +39,7 @@ impl<T: Renderable> Renderable for EnergyEntityData<T> {
 
         if let AnyEnergySource::Fluid { data } = &self.energy_source {
             child.append(&mut data.fluid_box.connection_points(options.direction));
-        };
+        }
 
         child
     }
@@ -58,14 +58,14 @@ impl<T: Renderable> Renderable for EnergyEntityData<T> {
                     .connections
                     .iter()
                     .map(|c| {
-                        let offset = c.direction.get_offset();
+                        let offset = c.direction.eight_way().get_offset();
                         let pos: Vector = c.position.into();
 
                         options.direction.rotate_vector(pos + offset).into()
                     })
                     .collect(),
             );
-        };
+        }
 
         child
     }
@@ -73,4 +73,17 @@ impl<T: Renderable> Renderable for EnergyEntityData<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        matches!(self.energy_source, AnyEnergySource::Electric { .. })
+            || self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&types::LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        self.child.wire_reach()
+    }
 }
@@ -44,4 +44,16 @@ impl<T: Renderable> Renderable for HeatBufferEntityData<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&types::LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        self.child.wire_reach()
+    }
 }
@@ -76,4 +76,19 @@ impl<T: Renderable> Renderable for WireEntityData<T> {
     fn recipe_visible(&self) -> bool {
         self.child.recipe_visible()
     }
+
+    fn uses_electricity(&self) -> bool {
+        self.child.uses_electricity()
+    }
+
+    fn lights(&self) -> Vec<&types::LightDefinitionData> {
+        self.child.lights()
+    }
+
+    fn wire_reach(&self) -> Option<f64> {
+        let reach = self.wire_connection_data.get_max_distance();
+        (reach > 0.0)
+            .then_some(reach)
+            .or_else(|| self.child.wire_reach())
+    }
 }
@@ -52,4 +52,8 @@ impl super::Renderable for AccumulatorData {
 
         Some(())
     }
+
+    fn uses_electricity(&self) -> bool {
+        true
+    }
 }
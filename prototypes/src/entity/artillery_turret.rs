@@ -65,9 +65,9 @@ pub struct ArtilleryTurretData {
 
     #[serde(flatten)]
     pub cannon_barrel_recoil_shiftings: Option<ArtilleryTurretCannonBarrelShiftings>,
-    // not implemented
-    // pub rotating_sound: Option<InterruptibleSound>,
-    // pub rotating_stopped_sound: Option<Sound>,
+
+    pub rotating_sound: Option<InterruptibleSound>,
+    pub rotating_stopped_sound: Option<Sound>,
 }
 
 impl super::Renderable for ArtilleryTurretData {
@@ -82,7 +82,7 @@ impl super::Renderable for ArtilleryTurretData {
             orientation: Some(
                 options
                     .orientation
-                    .map_or(options.direction.to_orientation(), |o| o),
+                    .unwrap_or_else(|| options.direction.to_orientation()),
             ),
             ..options.clone()
         };
@@ -68,4 +68,8 @@ impl super::Renderable for BeaconData {
 
         Some(())
     }
+
+    fn uses_electricity(&self) -> bool {
+        matches!(self.energy_source, AnyEnergySource::Electric { .. })
+    }
 }
@@ -58,4 +58,8 @@ impl super::Renderable for BurnerGeneratorData {
 
         Some(())
     }
+
+    fn uses_electricity(&self) -> bool {
+        true
+    }
 }
@@ -86,6 +86,10 @@ impl<T: super::Renderable> super::Renderable for CombinatorData<T> {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<types::MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn uses_electricity(&self) -> bool {
+        matches!(self.energy_source, AnyEnergySource::Electric { .. })
+    }
 }
 
 /// [`Prototypes/ArithmeticCombinatorPrototype`](https://lua-api.factorio.com/latest/prototypes/ArithmeticCombinatorPrototype.html)
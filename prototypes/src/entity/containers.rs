@@ -99,10 +99,10 @@ pub struct LogisticContainerData {
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub use_exact_mode: bool,
 
+    pub animation_sound: Option<Sound>,
+
     #[serde(flatten)]
     parent: ContainerData,
-    // not implemented
-    // pub animation_sound: Option<Sound>,
 }
 
 impl Deref for LogisticContainerData {
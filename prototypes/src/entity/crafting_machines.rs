@@ -84,6 +84,7 @@ pub struct CraftingMachineData<T: super::Renderable> {
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum WorkVisKind {
     Layered(FactorioArray<WorkingVisualisation>),
     Single(WorkingVisualisation),
@@ -121,7 +122,7 @@ impl<T: super::Renderable> super::Renderable for CraftingMachineData<T> {
 
         if let Some(anim_res) = anim {
             render_layers.add_entity(anim_res, &options.position);
-        };
+        }
 
         self.child
             .render(options, used_mods, render_layers, image_cache)
@@ -200,6 +201,7 @@ pub enum CraftingMachineFluidBoxHell {
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum CraftingMachineFluidBoxCursedType {
     FluidBox(FluidBox),
     OffWhenNoFluidRecipe(bool),
@@ -335,20 +337,21 @@ pub struct RocketSiloData {
     )]
     pub rocket_result_inventory_size: ItemStackIndex,
 
+    pub alarm_trigger: Option<TriggerEffect>,
+    pub clamps_on_trigger: Option<TriggerEffect>,
+    pub clamps_off_trigger: Option<TriggerEffect>,
+    pub doors_trigger: Option<TriggerEffect>,
+    pub raise_rocket_trigger: Option<TriggerEffect>,
+
+    pub alarm_sound: Option<Sound>,
+    pub clamps_on_sound: Option<Sound>,
+    pub clamps_off_sound: Option<Sound>,
+    pub doors_sound: Option<Sound>,
+    pub raise_rocket_sound: Option<Sound>,
+    pub flying_sound: Option<Sound>,
+
     #[serde(flatten)]
     assembler_data: AssemblingMachineData,
-    // not implemented
-    // pub alarm_trigger: Option<TriggerEffect>,
-    // pub clamps_on_trigger: Option<TriggerEffect>,
-    // pub clamps_off_trigger: Option<TriggerEffect>,
-    // pub doors_trigger: Option<TriggerEffect>,
-    // pub raise_rocket_trigger: Option<TriggerEffect>,
-    // pub alarm_sound: Option<Sound>,
-    // pub clamps_on_sound: Option<Sound>,
-    // pub clamps_off_sound: Option<Sound>,
-    // pub doors_sound: Option<Sound>,
-    // pub raise_rocket_sound: Option<Sound>,
-    // pub flying_sound: Option<Sound>,
 }
 
 impl Deref for RocketSiloData {
@@ -45,6 +45,10 @@ impl super::Renderable for ElectricEnergyInterfaceData {
             .as_ref()?
             .render(options, used_mods, render_layers, image_cache)
     }
+
+    fn uses_electricity(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -43,4 +43,11 @@ impl super::Renderable for ElectricPoleData {
 
         Some(())
     }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.light
+            .iter()
+            .flat_map(LightDefinition::lights)
+            .collect()
+    }
 }
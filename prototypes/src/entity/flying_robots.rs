@@ -92,8 +92,7 @@ pub struct CombatRobotData {
     pub follows_player: bool,
 
     pub light: Option<LightDefinition>,
-    // not implemented
-    // pub destroy_action: Option<Trigger>,
+    pub destroy_action: Option<Trigger>,
 }
 
 impl super::Renderable for CombatRobotData {
@@ -129,10 +128,10 @@ pub struct RobotWithLogisticInterfaceData<T: super::Renderable> {
     #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
     pub draw_cargo: bool,
 
+    pub destroy_action: Option<Trigger>,
+
     #[serde(flatten)]
     child: T,
-    // not implemented
-    // pub destroy_action: Option<Trigger>,
 }
 
 impl<T: super::Renderable> Deref for RobotWithLogisticInterfaceData<T> {
@@ -176,8 +175,7 @@ pub struct ConstructionRobotData {
     pub smoke: Option<Animation>,
     pub sparks: Option<AnimationVariations>,
     pub working_light: Option<LightDefinition>,
-    // not implemented
-    // pub reparing_sound: Option<Sound>,
+    pub repairing_sound: Option<Sound>,
 }
 
 impl super::Renderable for ConstructionRobotData {
@@ -77,4 +77,8 @@ impl super::Renderable for GeneratorData {
 
         Some(())
     }
+
+    fn uses_electricity(&self) -> bool {
+        true
+    }
 }
@@ -51,4 +51,11 @@ impl super::Renderable for LabData {
 
         Some(())
     }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.light
+            .iter()
+            .flat_map(LightDefinition::lights)
+            .collect()
+    }
 }
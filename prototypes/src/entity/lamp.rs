@@ -66,6 +66,17 @@ impl super::Renderable for LampData {
 
         Some(())
     }
+
+    fn uses_electricity(&self) -> bool {
+        matches!(self.energy_source, AnyEnergySource::Electric { .. })
+    }
+
+    fn lights(&self) -> Vec<&LightDefinitionData> {
+        self.light
+            .iter()
+            .flat_map(LightDefinition::lights)
+            .collect()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -38,9 +38,8 @@ pub struct LandMineData {
     )]
     pub trigger_force: ForceCondition,
 
-    pub trigger_collision_mask: Option<CollisionMask>,
-    // not implemented
-    // pub action: Option<Trigger>,
+    pub trigger_collision_mask: Option<CollisionMaskConnector>,
+    pub action: Option<Trigger>,
 }
 
 impl super::Renderable for LandMineData {
@@ -147,12 +147,11 @@ impl super::Renderable for PipeToGroundData {
         render_layers: &mut crate::RenderLayerBuffer,
         image_cache: &mut ImageCache,
     ) -> super::RenderOutput {
-        let res = match options.direction {
+        let res = match options.direction.four_way() {
             Direction::North => &self.pictures.up,
             Direction::East => &self.pictures.right,
             Direction::South => &self.pictures.down,
-            Direction::West => &self.pictures.left,
-            _ => unimplemented!("PipeToGround only supports cardinal directions"),
+            _ => &self.pictures.left,
         }
         .render(
             render_layers.scale(),
@@ -169,8 +168,15 @@ impl super::Renderable for PipeToGroundData {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PipeToGroundPictures {
+    #[serde(alias = "south")]
     pub down: Sprite,
+
+    #[serde(alias = "north")]
     pub up: Sprite,
+
+    #[serde(alias = "west")]
     pub left: Sprite,
+
+    #[serde(alias = "east")]
     pub right: Sprite,
 }
@@ -59,6 +59,5 @@ pub struct ProgrammableSpeakerInstrument {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProgrammableSpeakerNote {
     pub name: String,
-    // not implemented
-    // pub sound: Sound,
+    pub sound: Sound,
 }
@@ -24,11 +24,10 @@ pub type RailPrototype<T> = EntityWithOwnerPrototype<RailData<T>>;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RailData<T: RailDirectionPrototype> {
     pub pictures: RailPictureSet,
+    pub walking_sound: Option<Sound>,
 
     #[serde(flatten)]
     child: T,
-    // not implemented
-    // pub walking_sound: Option<Sound>,
 }
 
 impl<T: RailDirectionPrototype> Deref for RailData<T> {
@@ -142,6 +141,8 @@ pub type CurvedRailPrototype = RailPrototype<CurvedRailData>;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CurvedRailData {
     pub bending_type: Option<CurvedBendType>,
+
+    pub secondary_collision_box: Option<BoundingBox>,
 }
 
 impl RailDirectionPrototype for CurvedRailData {
@@ -240,7 +241,7 @@ impl super::Renderable for RailPieceLayers {
                     crate::InternalRenderLayer::RailStonePathBackground,
                 );
             }
-        };
+        }
 
         if let Some(res) = self.stone_path.render(
             render_layers.scale(),
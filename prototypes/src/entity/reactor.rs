@@ -39,8 +39,8 @@ pub struct ReactorData {
 
     #[serde(default = "Color::white", skip_serializing_if = "Color::is_white")]
     pub default_fuel_glow_color: Color,
-    // not implemented
-    // pub meltdown_action: Option<Trigger>,
+
+    pub meltdown_action: Option<Trigger>,
 }
 
 impl super::Renderable for ReactorData {
@@ -94,9 +94,9 @@ pub struct RoboportData {
 
     // docs specify single precision float
     pub logistics_connection_distance: Option<f64>,
-    // not implemented
-    // pub open_door_trigger_effect: Option<TriggerEffect>,
-    // pub close_door_trigger_effect: Option<TriggerEffect>,
+
+    pub open_door_trigger_effect: Option<TriggerEffect>,
+    pub close_door_trigger_effect: Option<TriggerEffect>,
 }
 
 impl super::Renderable for RoboportData {
@@ -143,4 +143,8 @@ impl super::Renderable for RoboportData {
 
         // TODO: include base_animation & doors
     }
+
+    fn uses_electricity(&self) -> bool {
+        matches!(self.energy_source, AnyEnergySource::Electric { .. })
+    }
 }
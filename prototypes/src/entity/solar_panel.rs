@@ -37,4 +37,8 @@ impl super::Renderable for SolarPanelData {
 
         Some(())
     }
+
+    fn uses_electricity(&self) -> bool {
+        true
+    }
 }
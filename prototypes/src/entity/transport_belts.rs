@@ -64,6 +64,7 @@ where
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum BeltGraphics {
     BeltAnimationSet {
         belt_animation_set: TransportBeltAnimationSet,
@@ -575,6 +576,7 @@ impl super::Renderable for TransportBeltData {
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum BeltGraphicsWithCorners {
     BeltAnimationSetWithCorners {
         belt_animation_set: TransportBeltAnimationSetWithCorners,
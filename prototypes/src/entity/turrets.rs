@@ -121,17 +121,18 @@ pub struct TurretData {
 
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub spawn_decorations_on_expansion: bool,
+    pub attack_target_mask: Option<TriggerTargetMask>,
+    pub ignore_target_mask: Option<TriggerTargetMask>,
+
+    pub start_attacking_sound: Option<Sound>,
+    pub dying_sound: Option<Sound>,
+    pub preparing_sound: Option<Sound>,
+    pub folding_sound: Option<Sound>,
+    pub prepared_sound: Option<Sound>,
+    pub prepared_alternative_sound: Option<Sound>,
     // TODO: overridden `corpse` & `is_military_target`
 
     // not implemented
-    // pub attack_target_mask: Option<TriggerTargetMask>,
-    // pub ignore_target_mask: Option<TriggerTargetMask>,
-    // pub start_attacking_sound: Option<Sound>,
-    // pub dying_sound: Option<Sound>,
-    // pub preparing_sound: Option<Sound>,
-    // pub folding_sound: Option<Sound>,
-    // pub prepared_sound: Option<Sound>,
-    // pub prepared_alternative_sound: Option<Sound>,
     // pub spawn_decoration: Option<CreateDecorativesTriggerEffectItem or array of that>,
 }
 
@@ -259,6 +260,10 @@ impl super::Renderable for ElectricTurretData {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<MapPosition> {
         self.parent.heat_buffer_connections(options)
     }
+
+    fn uses_electricity(&self) -> bool {
+        matches!(self.energy_source, AnyEnergySource::Electric { .. })
+    }
 }
 
 /// [`Prototypes/FluidTurretPrototype`](https://lua-api.factorio.com/latest/prototypes/FluidTurretPrototype.html)
@@ -47,11 +47,11 @@ pub struct VehicleData<T: super::Renderable> {
     #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
     pub allow_passengers: bool,
 
+    pub crash_trigger: Option<TriggerEffect>,
+    pub stop_trigger: Option<TriggerEffect>,
+
     #[serde(flatten)]
     child: T,
-    // not implemented
-    // pub crash_trigger: Option<TriggerEffect>,
-    // pub stop_trigger: Option<TriggerEffect>,
 }
 
 impl<T: super::Renderable> Deref for VehicleData<T> {
@@ -157,8 +157,9 @@ pub struct CarData {
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub guns: FactorioArray<ItemID>,
+
+    pub sound_no_fuel: Option<Sound>,
     // not implemented
-    // pub sound_no_fuel: Option<Sound>,
     // pub track_particle_triggers: Option<FootstepTriggerEffectList>,
 }
 
@@ -176,11 +177,151 @@ impl super::Renderable for CarData {
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum BurnerOrVoidEnergySource {
     Burner { burner: BurnerEnergySource },
     Other { energy_source: AnyEnergySource }, // this must be a void energy source
 }
 
+/// [`Prototypes/SpiderVehiclePrototype`](https://lua-api.factorio.com/latest/prototypes/SpiderVehiclePrototype.html)
+pub type SpiderVehiclePrototype = VehiclePrototype<SpiderVehicleData>;
+
+/// [`Prototypes/SpiderVehiclePrototype`](https://lua-api.factorio.com/latest/prototypes/SpiderVehiclePrototype.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpiderVehicleData {
+    pub energy_source: AnyEnergySource, // this must be a burner or void energy source
+
+    #[serde(deserialize_with = "helper::truncating_deserializer")]
+    pub inventory_size: ItemStackIndex,
+
+    pub graphics_set: Option<SpiderVehicleGraphicsSet>,
+    pub spider_engine: SpiderEngineSpecification,
+
+    // docs say single precision float
+    pub height: f64,
+
+    pub movement_energy_consumption: Energy,
+    pub automatic_weapon_cycling: bool,
+    pub chain_shooting_cooldown_modifier: f64,
+
+    #[serde(deserialize_with = "helper::truncating_deserializer")]
+    pub chunk_exploration_radius: u32,
+
+    #[serde(default = "helper::f64_1", skip_serializing_if = "helper::is_1_f64")]
+    pub torso_rotation_speed: f64,
+
+    #[serde(default = "helper::f64_1", skip_serializing_if = "helper::is_1_f64")]
+    pub torso_bob_speed: f64,
+
+    #[serde(default, deserialize_with = "helper::truncating_opt_deserializer")]
+    pub trash_inventory_size: Option<ItemStackIndex>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guns: FactorioArray<ItemID>,
+}
+
+impl super::Renderable for SpiderVehicleData {
+    fn render(
+        &self,
+        options: &super::RenderOpts,
+        used_mods: &UsedMods,
+        render_layers: &mut crate::RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+    ) -> super::RenderOutput {
+        let graphics_set = self.graphics_set.as_ref()?;
+
+        // the torso hovers above the position of the vehicle
+        let lift = Vector::new(0.0, -self.height);
+        let mut empty = true;
+
+        for (animation, layer) in [
+            (
+                graphics_set.base_animation.as_ref(),
+                crate::InternalRenderLayer::EntityHigh,
+            ),
+            (
+                graphics_set.animation.as_ref(),
+                crate::InternalRenderLayer::EntityHigher,
+            ),
+        ] {
+            let Some((img, shift)) = animation.and_then(|a| {
+                a.render(
+                    render_layers.scale(),
+                    used_mods,
+                    image_cache,
+                    &options.into(),
+                )
+            }) else {
+                continue;
+            };
+
+            empty = false;
+            render_layers.add((img, shift + lift), &options.position, layer);
+        }
+
+        if empty {
+            None
+        } else {
+            Some(())
+        }
+    }
+}
+
+/// [`Types/SpiderVehicleGraphicsSet`](https://lua-api.factorio.com/latest/types/SpiderVehicleGraphicsSet.html)
+///
+/// The legs are separate [`SpiderLegPrototype`](https://lua-api.factorio.com/latest/prototypes/SpiderLegPrototype.html)s
+/// and not rendered.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpiderVehicleGraphicsSet {
+    pub base_animation: Option<RotatedAnimation>,
+    pub shadow_base_animation: Option<RotatedAnimation>,
+    pub animation: Option<RotatedAnimation>,
+    pub shadow_animation: Option<RotatedAnimation>,
+
+    // TODO: default value
+    pub base_render_layer: Option<RenderLayer>,
+
+    // TODO: default value
+    pub render_layer: Option<RenderLayer>,
+    // not implemented
+    // pub light: Option<LightDefinition>,
+    // pub eye_light: Option<LightDefinition>,
+    // pub light_positions: Option<FactorioArray<FactorioArray<Vector>>>,
+    // pub water_reflection: Option<WaterReflectionDefinition>,
+    // pub autopilot_*: ...
+}
+
+/// [`Types/SpiderEngineSpecification`](https://lua-api.factorio.com/latest/types/SpiderEngineSpecification.html)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpiderEngineSpecification {
+    pub legs: SpiderLegSpecifications,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SpiderLegSpecifications {
+    Single(SpiderLegSpecification),
+    Array(FactorioArray<SpiderLegSpecification>),
+}
+
+/// [`Types/SpiderLegSpecification`](https://lua-api.factorio.com/latest/types/SpiderLegSpecification.html)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpiderLegSpecification {
+    pub leg: EntityID,
+    pub mount_position: Vector,
+    pub ground_position: Vector,
+
+    #[serde(deserialize_with = "helper::truncating_deserializer")]
+    pub walking_group: u8,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocking_legs: FactorioArray<u32>,
+    // not implemented
+    // pub leg_hit_the_ground_trigger: Option<TriggerEffectItem>,
+}
+
 /// [`Prototypes/RollingStockPrototype`](https://lua-api.factorio.com/latest/prototypes/RollingStockPrototype.html)
 pub type RollingStockPrototype<T> = VehiclePrototype<RollingStockData<T>>;
 
@@ -192,7 +333,7 @@ pub struct RollingStockData<T: super::Renderable> {
     pub air_resistance: f64,
     pub joint_distance: f64,
     pub connection_distance: f64,
-    pub pictures: RotatedSprite,
+    pub pictures: RollingStockRotatedSlopedGraphics,
     pub vertical_selection_shift: f64,
 
     #[serde(default = "helper::f64_10", skip_serializing_if = "helper::is_10_f64")]
@@ -200,7 +341,7 @@ pub struct RollingStockData<T: super::Renderable> {
 
     pub back_light: Option<LightDefinition>,
     pub stand_by_light: Option<LightDefinition>,
-    pub wheels: Option<RotatedSprite>,
+    pub wheels: Option<RollingStockRotatedSlopedGraphics>,
     pub horizontal_doors: Option<Animation>,
     pub vertical_doors: Option<Animation>,
     pub color: Option<Color>,
@@ -352,9 +493,17 @@ pub struct ArtilleryWagonData {
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub disable_automatic_firing: bool,
 
-    pub cannon_base_pictures: Option<RotatedSprite>,
-    pub cannon_barrel_pictures: Option<RotatedSprite>,
-    pub cannon_base_shiftings: Option<FactorioArray<Vector>>,
+    pub cannon_base_pictures: Option<RollingStockRotatedSlopedGraphics>,
+    pub cannon_barrel_pictures: Option<RollingStockRotatedSlopedGraphics>,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub cannon_base_height: f64,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub cannon_base_shift_when_vertical: f64,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub cannon_base_shift_when_horizontal: f64,
 
     #[serde(
         default,
@@ -376,9 +525,21 @@ pub struct ArtilleryWagonData {
 
     #[serde(flatten)]
     pub cannon_barrel_recoil_shiftings: Option<ArtilleryTurretCannonBarrelShiftings>,
-    // not implemented
-    // pub rotating_sound: Option<InterruptibleSound>,
-    // pub rotating_stopped_sound: Option<Sound>,
+
+    pub rotating_sound: Option<InterruptibleSound>,
+    pub rotating_stopped_sound: Option<Sound>,
+}
+
+impl ArtilleryWagonData {
+    /// Vertical shift of the cannon, blended between the vertical & horizontal shift
+    /// depending on how far the wagon is turned.
+    fn cannon_base_shift(&self, orientation: RealOrientation) -> Vector {
+        let horizontal = (*orientation * std::f64::consts::TAU).sin().abs();
+        let shift = (self.cannon_base_shift_when_horizontal - self.cannon_base_shift_when_vertical)
+            .mul_add(horizontal, self.cannon_base_shift_when_vertical);
+
+        Vector::new(0.0, shift)
+    }
 }
 
 impl super::Renderable for ArtilleryWagonData {
@@ -391,28 +552,10 @@ impl super::Renderable for ArtilleryWagonData {
     ) -> super::RenderOutput {
         let mut empty = true;
 
-        let offset = self.cannon_base_shiftings.as_ref().map_or_else(
-            || Vector::new(0.0, 0.0),
-            |shifts| {
-                if shifts.is_empty() {
-                    Vector::new(0.0, 0.0)
-                } else {
-                    let len_f = shifts.len() as f64;
-                    let idx = (len_f
-                        * options
-                            .orientation
-                            .unwrap_or_else(|| options.direction.to_orientation()))
-                    .floor();
-
-                    let idx = if idx < 0.0 {
-                        len_f + idx.rem(len_f)
-                    } else {
-                        idx.rem(len_f)
-                    } as usize;
-
-                    shifts[idx]
-                }
-            },
+        let offset = self.cannon_base_shift(
+            options
+                .orientation
+                .unwrap_or_else(|| options.direction.to_orientation()),
         );
 
         if let Some((img, shift)) = self.cannon_barrel_pictures.as_ref().and_then(|b| {
@@ -526,7 +669,7 @@ pub struct LocomotiveData {
     pub energy_source: BurnerOrVoidEnergySource,
 
     pub front_light: Option<LightDefinition>,
-    pub front_light_pictures: Option<RotatedSprite>,
+    pub front_light_pictures: Option<RollingStockRotatedSlopedGraphics>,
 
     // docs say single precision float
     #[serde(default = "helper::f64_03", skip_serializing_if = "helper::is_03_f64")]
@@ -3,9 +3,9 @@ use serde_with::skip_serializing_none;
 
 use serde_helper as helper;
 use types::{
-    CollisionMask, Color, EntityID, EquipmentID, FactorioArray, FileName, FuelCategoryID, Icon,
-    IconData, ItemCountType, ItemID, ItemProductPrototype, ItemPrototypeFlags, RenderableGraphics,
-    SpriteVariations, TileID,
+    CollisionMaskConnector, Color, EntityID, EquipmentID, FactorioArray, FileName, FuelCategoryID,
+    Icon, IconData, ItemCountType, ItemID, ItemProductPrototype, ItemPrototypeFlags,
+    RenderableGraphics, SpriteVariations, TileID,
 };
 
 mod ammo;
@@ -119,7 +119,7 @@ pub enum DarkBackgroundIcon {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlaceAsTile {
     pub result: TileID,
-    pub condition: CollisionMask,
+    pub condition: CollisionMaskConnector,
     pub condition_size: i32,
 }
 
@@ -161,6 +161,45 @@ namespace_struct! {
 }
 
 impl AllTypes {
+    /// The shared [`ItemPrototypeData`] of all items, regardless of their prototype type.
+    pub fn all_data(&self) -> impl Iterator<Item = (&ItemID, &ItemPrototypeData)> {
+        macro_rules! chain_data {
+            ( $( $member:ident ),+ ) => {
+                std::iter::empty()
+                    $(
+                        .chain(self.$member.iter().map(|(id, item)| {
+                            let data: &ItemPrototypeData = item;
+                            (id, data)
+                        }))
+                    )+
+            };
+        }
+
+        chain_data!(
+            item,
+            ammo,
+            capsule,
+            gun,
+            item_with_entity_data,
+            item_with_label,
+            item_with_inventory,
+            blueprint_book,
+            item_with_tags,
+            selection_tool,
+            blueprint,
+            copy_paste_tool,
+            deconstruction_item,
+            upgrade_item,
+            module,
+            rail_planner,
+            spidertron_remote,
+            tool,
+            armor,
+            mining_tool,
+            repair_tool
+        )
+    }
+
     pub fn get_icon(
         &self,
         name: &str,
@@ -263,7 +302,7 @@ mod test {
     #![allow(clippy::unwrap_used)]
 
     use super::*;
-    use types::{CommonIconData, Vector};
+    use types::{CommonIconData, TriggerEffectItem, Vector};
 
     #[test]
     fn deserialize() {
@@ -331,6 +370,68 @@ mod test {
         let _ = serde_json::from_str::<ItemPrototype>(json).unwrap();
     }
 
+    #[test]
+    fn deserialize_ammo_trigger() {
+        let json = r#"{
+            "type": "ammo",
+            "name": "firearm-magazine",
+            "icon": "__base__/graphics/icons/firearm-magazine.png",
+            "icon_size": 64,
+            "ammo_category": "bullet",
+            "ammo_type": {
+                "category": "bullet",
+                "action": {
+                    "type": "direct",
+                    "action_delivery": {
+                        "type": "instant",
+                        "source_effects": {
+                            "type": "create-explosion",
+                            "entity_name": "explosion-gunshot"
+                        },
+                        "target_effects": [
+                            {
+                                "type": "create-entity",
+                                "entity_name": "explosion-hit",
+                                "offsets": [[0, 1]],
+                                "offset_deviation": [[-0.5, -0.5], [0.5, 0.5]]
+                            },
+                            {
+                                "type": "damage",
+                                "damage": { "amount": 5, "type": "physical" }
+                            },
+                            {
+                                "type": "activate-impact"
+                            }
+                        ]
+                    }
+                }
+            },
+            "magazine_size": 10,
+            "subgroup": "ammo",
+            "order": "a[basic-clips]-a[firearm-magazine]",
+            "stack_size": 200
+        }"#;
+
+        let ammo = serde_json::from_str::<AmmoItemPrototype>(json).unwrap();
+        let AmmoTypeUnion::Single(ammo_type) = &ammo.ammo_type else {
+            panic!("expected a single ammo type");
+        };
+
+        let items = ammo_type.action.as_ref().unwrap().items();
+        assert_eq!(items.len(), 1);
+
+        let deliveries = items[0].action_delivery.as_ref().unwrap().deliveries();
+        assert_eq!(deliveries.len(), 1);
+
+        let effects = deliveries[0].target_effects.as_ref().unwrap().effects();
+        assert_eq!(effects.len(), 3);
+        assert!(matches!(
+            &effects[1],
+            TriggerEffectItem::Damage { damage, .. } if (damage.amount - 5.0).abs() < f32::EPSILON
+        ));
+        assert!(matches!(effects[2], TriggerEffectItem::Other));
+    }
+
     #[test]
     fn serialize() {
         let item = ItemPrototype(super::super::BasePrototype {
@@ -339,6 +440,8 @@ mod test {
             localised_name: None,
             localised_description: None,
             order: String::new(),
+            #[cfg(feature = "preserve_unknown")]
+            extra: serde_json::Map::new(),
             child: ItemPrototypeData {
                 stack_size: 100,
                 // icon: Icon::Single {
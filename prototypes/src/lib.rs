@@ -7,16 +7,16 @@
 
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::Read;
 use std::path::Path;
-use std::{collections::HashMap, ops::Rem};
+use std::{collections::HashMap, fmt::Write as _, ops::Rem};
 
-use image::{imageops, DynamicImage, GenericImageView, GrayAlphaImage};
+use image::{imageops, DynamicImage, GenericImageView};
 use imageproc::geometric_transformations;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use signed_distance_field::prelude::*;
 
+use diagnostics::{DiagnosticKind, Diagnostics};
 use entity::RenderableEntity;
 use mod_util::mod_info::Version;
 
@@ -24,17 +24,36 @@ use mod_util::UsedMods;
 use tracing::instrument;
 use types::*;
 
+pub mod asteroid_chunk;
+pub mod diagnostics;
 pub mod entity;
 pub mod fluid;
 pub mod item;
+pub mod mod_setting;
+pub mod quality;
 pub mod recipe;
 pub mod signal;
+pub mod space_location;
+pub mod technology;
 pub mod tile;
+#[cfg(feature = "preserve_unknown")]
+mod unknown_fields;
 pub mod utility_sprites;
 
 /// [`Prototypes/PrototypeBase`](https://lua-api.factorio.com/latest/PrototypeBase.html)
+///
+/// With the `preserve_unknown` feature top level fields that are not modelled are kept in [`BasePrototype::extra`]
+/// and written back on serialization.
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "preserve_unknown"), derive(Serialize))]
+#[cfg_attr(
+    feature = "preserve_unknown",
+    serde(
+        from = "unknown_fields::RawPrototype<T>",
+        bound(deserialize = "T: Deserialize<'de> + Serialize")
+    )
+)]
 pub struct BasePrototype<T> {
     /// type can effectively be ignored, as it should be enforced by the struct/enum types itself
     #[serde(rename = "type")]
@@ -50,6 +69,10 @@ pub struct BasePrototype<T> {
 
     #[serde(flatten)]
     child: T,
+
+    #[cfg(feature = "preserve_unknown")]
+    #[serde(skip)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl<T> std::ops::Deref for BasePrototype<T> {
@@ -159,9 +182,27 @@ pub enum Error {
 
     #[error("data.raw JSON error: {0}")]
     Json(#[from] serde_json::Error),
+
+    #[error("data.raw JSON error in {section}.{name} at {path}: {source}")]
+    Prototype {
+        section: String,
+        name: String,
+        /// Field path inside the prototype, `.` if it could not be determined.
+        path: String,
+        source: serde_json::Error,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A prototype that got skipped by [`DataRaw::load_lenient_from_bytes`].
+#[derive(Debug)]
+pub struct SkippedPrototype {
+    pub section: String,
+    pub name: String,
+    pub path: String,
+    pub error: serde_json::Error,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct DataRaw {
     #[serde(flatten)]
@@ -180,22 +221,562 @@ pub struct DataRaw {
     pub recipe: recipe::AllTypes,
     pub recipe_category: HashMap<RecipeCategoryID, recipe::RecipeCategory>,
 
+    #[serde(default)]
+    pub quality: HashMap<QualityID, quality::QualityPrototype>,
+
+    #[serde(default)]
+    pub space_location: HashMap<SpaceLocationID, space_location::SpaceLocationPrototype>,
+
+    #[serde(default)]
+    pub planet: HashMap<SpaceLocationID, space_location::PlanetPrototype>,
+
+    #[serde(default)]
+    pub asteroid_chunk: HashMap<AsteroidChunkID, asteroid_chunk::AsteroidChunkPrototype>,
+
     #[serde(flatten)]
     pub tile: tile::AllTypes,
 
+    #[serde(flatten)]
+    pub technology: technology::AllTypes,
+
+    #[serde(flatten)]
+    pub mod_setting: mod_setting::AllTypes,
+
     pub utility_sprites: HashMap<String, utility_sprites::UtilitySprites>,
+
+    /// Sections that are not modelled (yet), kept as they are so the dump can be written back.
+    #[cfg(feature = "preserve_unknown")]
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
 }
 
 impl DataRaw {
+    /// Loads the dump from a memory-mapped file to avoid holding a second copy of it in memory.
     pub fn load(dump_path: &Path) -> Result<Self, Error> {
-        let mut bytes = Vec::new();
-        File::open(dump_path)?.read_to_end(&mut bytes)?;
-        Self::load_from_bytes(&bytes)
+        Self::load_from_bytes(&map_file(dump_path)?)
     }
 
+    /// Loads the dump and reports the prototype that failed to deserialize, if any.
     #[instrument(skip_all)]
     pub fn load_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        Ok(serde_json::from_slice(bytes)?)
+        let err = match serde_json::from_slice(bytes) {
+            Ok(data) => return Ok(data),
+            Err(err) if !err.is_data() => return Err(err.into()),
+            Err(err) => err,
+        };
+
+        // narrow the error down to the prototype that caused it
+        let serde_json::Value::Object(raw) = serde_json::from_slice(bytes)? else {
+            return Err(err.into());
+        };
+
+        let skeleton = Self::skeleton()?;
+        for (section, prototypes) in raw {
+            let Some(failed) = Self::check_section(&skeleton, &section, prototypes)
+                .into_iter()
+                .next()
+            else {
+                continue;
+            };
+
+            return Err(Error::Prototype {
+                section,
+                name: failed.name,
+                path: failed.path,
+                source: failed.error,
+            });
+        }
+
+        Err(err.into())
+    }
+
+    /// Loads only the prototypes with one of the given names and the ones they depend on,
+    /// all others are skipped without deserializing them. See [`DataRaw::filter_dump`].
+    pub fn load_filtered(dump_path: &Path, names: &HashSet<String>) -> Result<Self, Error> {
+        Self::load_filtered_from_bytes(&map_file(dump_path)?, names)
+    }
+
+    pub fn load_filtered_from_bytes(bytes: &[u8], names: &HashSet<String>) -> Result<Self, Error> {
+        Self::load_from_bytes(&Self::filter_dump(bytes, names)?)
+    }
+
+    /// Removes the entities, items, fluids, signals, recipes, tiles & technologies from the dump
+    /// that are not named in `names` and not needed by one of the named prototypes.
+    ///
+    /// Needed are the items that build a kept entity or tile, the entities & tiles a kept item builds,
+    /// the items a kept entity or tile is placed by or mined into and the products of kept recipes.
+    /// All other sections (e.g. qualities or utility sprites) are kept completely.
+    #[instrument(skip_all, fields(names = names.len()))]
+    pub fn filter_dump(bytes: &[u8], names: &HashSet<String>) -> Result<Vec<u8>, Error> {
+        let raw: HashMap<String, HashMap<String, &serde_json::value::RawValue>> =
+            serde_json::from_slice(bytes)?;
+        let filtered_sections = Self::filtered_sections()?;
+
+        let mut references = Vec::new();
+        for (section, prototypes) in &raw {
+            if !filtered_sections.contains(section) {
+                continue;
+            }
+
+            for (name, prototype) in prototypes {
+                let fields: HashMap<&str, &serde_json::value::RawValue> =
+                    serde_json::from_str(prototype.get())?;
+
+                let collect = |keys: &[&str]| -> Result<HashSet<String>, Error> {
+                    let mut res = HashSet::new();
+                    for key in keys {
+                        if let Some(value) = fields.get(key) {
+                            collect_strings(&serde_json::from_str(value.get())?, &mut res);
+                        }
+                    }
+                    Ok(res)
+                };
+
+                let needs = collect(&FORWARD_REFERENCES)?;
+                let builds = collect(&BUILD_REFERENCES)?;
+                references.push((name.as_str(), needs, builds));
+            }
+        }
+
+        let mut names = names.clone();
+        loop {
+            let before = names.len();
+
+            for (name, needs, builds) in &references {
+                if names.contains(*name) {
+                    names.extend(needs.iter().cloned());
+                    names.extend(builds.iter().cloned());
+                } else if builds.iter().any(|built| names.contains(built)) {
+                    names.insert((*name).to_owned());
+                }
+            }
+
+            if names.len() == before {
+                break;
+            }
+        }
+
+        let filtered = raw
+            .into_iter()
+            .map(|(section, prototypes)| {
+                let prototypes = if filtered_sections.contains(&section) {
+                    prototypes
+                        .into_iter()
+                        .filter(|(name, _)| names.contains(name))
+                        .collect()
+                } else {
+                    prototypes
+                };
+
+                (section, prototypes)
+            })
+            .collect::<HashMap<_, _>>();
+
+        Ok(serde_json::to_vec(&filtered)?)
+    }
+
+    /// Sections of the prototypes a blueprint refers to by name, see [`DataRaw::filter_dump`].
+    fn filtered_sections() -> Result<HashSet<String>, Error> {
+        let skeletons = [
+            serde_json::to_value(entity::AllTypes::default())?,
+            serde_json::to_value(item::AllTypes::default())?,
+            serde_json::to_value(fluid::AllTypes::default())?,
+            serde_json::to_value(signal::AllTypes::default())?,
+            serde_json::to_value(recipe::AllTypes::default())?,
+            serde_json::to_value(tile::AllTypes::default())?,
+            serde_json::to_value(technology::AllTypes::default())?,
+        ];
+
+        Ok(skeletons
+            .into_iter()
+            .filter_map(|skeleton| match skeleton {
+                serde_json::Value::Object(map) => Some(map.into_iter().map(|(section, _)| section)),
+                _ => None,
+            })
+            .flatten()
+            .collect())
+    }
+
+    pub fn load_lenient(dump_path: &Path) -> Result<(Self, Vec<SkippedPrototype>), Error> {
+        Self::load_lenient_from_bytes(&map_file(dump_path)?)
+    }
+
+    /// Loads dumps that are not produced by the game itself. Missing sections are treated as
+    /// empty, numbers stored as strings are converted and prototypes that still fail to
+    /// deserialize are skipped instead of failing the whole load.
+    #[instrument(skip_all)]
+    pub fn load_lenient_from_bytes(bytes: &[u8]) -> Result<(Self, Vec<SkippedPrototype>), Error> {
+        let serde_json::Value::Object(mut raw) = serde_json::from_slice(bytes)? else {
+            return Ok((serde_json::from_slice(bytes)?, Vec::new()));
+        };
+
+        let skeleton = Self::skeleton()?;
+        let mut skipped = Vec::new();
+
+        for (section, empty) in &skeleton {
+            let Some(prototypes) = raw.remove(section) else {
+                raw.insert(section.clone(), empty.clone());
+                continue;
+            };
+
+            let mut prototypes = prototypes;
+            for failed in Self::check_section(&skeleton, section, prototypes.clone()) {
+                let Some(prototypes) = prototypes.as_object_mut() else {
+                    break;
+                };
+
+                if let Some(fixed) = failed.fixed {
+                    prototypes.insert(failed.name, fixed);
+                } else {
+                    prototypes.remove(&failed.name);
+                    skipped.push(SkippedPrototype {
+                        section: section.clone(),
+                        name: failed.name,
+                        path: failed.path,
+                        error: failed.error,
+                    });
+                }
+            }
+
+            raw.insert(section.clone(), prototypes);
+        }
+
+        Ok((
+            serde_json::from_value(serde_json::Value::Object(raw))?,
+            skipped,
+        ))
+    }
+
+    /// All known sections of the dump without any prototypes.
+    fn skeleton() -> Result<serde_json::Map<String, serde_json::Value>, Error> {
+        match serde_json::to_value(Self::default())? {
+            serde_json::Value::Object(map) => Ok(map),
+            _ => Ok(serde_json::Map::new()),
+        }
+    }
+
+    /// Checks every prototype of a section on its own and returns the ones that failed.
+    /// The second element contains a fixed version of the prototype if converting
+    /// numeric strings to numbers resolved the error.
+    fn check_section(
+        skeleton: &serde_json::Map<String, serde_json::Value>,
+        section: &str,
+        prototypes: serde_json::Value,
+    ) -> Vec<FailedPrototype> {
+        let probe = |value: serde_json::Value| {
+            let mut data = skeleton.clone();
+            data.insert(section.to_owned(), value);
+            serde_path_to_error::deserialize::<_, Self>(serde_json::Value::Object(data)).map(drop)
+        };
+
+        // unknown sections are ignored while deserializing
+        if !skeleton.contains_key(section) || probe(prototypes.clone()).is_ok() {
+            return Vec::new();
+        }
+
+        let serde_json::Value::Object(prototypes) = prototypes else {
+            return probe(prototypes)
+                .err()
+                .map(|err| {
+                    vec![FailedPrototype {
+                        name: String::new(),
+                        fixed: None,
+                        path: field_path(section, "", err.path()),
+                        error: err.into_inner(),
+                    }]
+                })
+                .unwrap_or_default();
+        };
+
+        prototypes
+            .into_iter()
+            .filter_map(|(name, prototype)| {
+                let single = |value| {
+                    serde_json::Value::Object(std::iter::once((name.clone(), value)).collect())
+                };
+
+                let err = probe(single(prototype.clone())).err()?;
+
+                let mut fixed = prototype.clone();
+                coerce_numeric_strings(&mut fixed);
+                let fixed = probe(single(fixed.clone())).is_ok().then_some(fixed);
+
+                let mut path = field_path(section, &name, err.path());
+                if path == "." && fixed.is_none() {
+                    path = locate_error(&prototype, &err.inner().to_string(), |candidate| {
+                        probe(single(candidate.clone()))
+                            .err()
+                            .map(|err| err.inner().to_string())
+                    });
+                }
+
+                Some(FailedPrototype {
+                    name,
+                    fixed,
+                    path,
+                    error: err.into_inner(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A prototype that failed to deserialize on its own, see [`DataRaw::check_section`].
+struct FailedPrototype {
+    name: String,
+
+    /// The prototype with numeric strings converted, if that resolved the error.
+    fixed: Option<serde_json::Value>,
+
+    /// Path of the failing field relative to the prototype, `.` if unknown.
+    path: String,
+    error: serde_json::Error,
+}
+
+/// Fields naming the prototypes a kept prototype needs, see [`DataRaw::filter_dump`].
+const FORWARD_REFERENCES: [&str; 4] = ["placeable_by", "minable", "results", "main_product"];
+
+/// Fields naming the entities & tiles an item builds, the item is needed if it builds a kept one.
+const BUILD_REFERENCES: [&str; 3] = ["place_result", "place_as_tile", "rails"];
+
+fn collect_strings(value: &serde_json::Value, out: &mut HashSet<String>) {
+    match value {
+        serde_json::Value::String(s) => {
+            out.insert(s.clone());
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_strings(value, out);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for value in map.values() {
+                collect_strings(value, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn map_file(path: &Path) -> Result<memmap2::Mmap, Error> {
+    let file = File::open(path)?;
+
+    // SAFETY: the map only lives while the dump is parsed. The game writes the dump during
+    // `--dump-data`, which never overlaps with loading it.
+    #[allow(unsafe_code)]
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+
+    Ok(map)
+}
+
+/// Path of the failing field relative to the prototype, `.` if unknown.
+/// Flattened fields are buffered by serde, so their paths stop at the prototype, see [`locate_error`].
+fn field_path(section: &str, name: &str, path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+
+    let mut segments = path.iter().peekable();
+    if segments
+        .next_if(|s| matches!(s, Segment::Map { key } if key == section))
+        .is_some()
+    {
+        segments.next_if(|s| match s {
+            Segment::Map { key } => key == name,
+            Segment::Unknown => true,
+            _ => false,
+        });
+    }
+
+    let mut res = String::new();
+    for segment in segments {
+        if !res.is_empty() && !matches!(segment, Segment::Seq { .. }) {
+            res.push('.');
+        }
+        res.push_str(&segment.to_string());
+    }
+
+    if res.is_empty() || res == "?" {
+        ".".to_owned()
+    } else {
+        res
+    }
+}
+
+/// How deep [`locate_error`] descends into a prototype.
+const MAX_LOCATE_DEPTH: usize = 16;
+
+/// Finds the field of a failing prototype that causes `error` by changing one field at a time.
+///
+/// Serde buffers `#[serde(flatten)]` fields, which every prototype has, so the deserializer can't
+/// tell where an error happened. `probe` deserializes a changed copy of the prototype and returns
+/// its error. A field is the culprit if removing it resolves or changes the error. Missing fields are
+/// reported after all others, so any change would change them and only resolving counts for them.
+/// Required fields can't be removed, they are the culprit if one of their children is or a value of
+/// another type resolves the error.
+fn locate_error(
+    prototype: &serde_json::Value,
+    error: &str,
+    probe: impl Fn(&serde_json::Value) -> Option<String>,
+) -> String {
+    let mut path = Vec::new();
+    locate_in(prototype, error, &probe, &mut path);
+
+    let mut res = String::new();
+    for segment in &path {
+        match segment {
+            PathSegment::Key(key) => {
+                if !res.is_empty() {
+                    res.push('.');
+                }
+                res.push_str(key);
+            }
+            PathSegment::Index(idx) => {
+                let _ = write!(res, "[{idx}]");
+            }
+        }
+    }
+
+    if let Some(missing) = missing_field(error) {
+        if !matches!(path.last(), Some(PathSegment::Key(key)) if key == missing) {
+            if !res.is_empty() {
+                res.push('.');
+            }
+            res.push_str(missing);
+        }
+    }
+
+    if res.is_empty() {
+        ".".to_owned()
+    } else {
+        res
+    }
+}
+
+/// Extends `path` with the culprit below it, returns `false` if there is none.
+fn locate_in(
+    prototype: &serde_json::Value,
+    error: &str,
+    probe: &impl Fn(&serde_json::Value) -> Option<String>,
+    path: &mut Vec<PathSegment>,
+) -> bool {
+    if path.len() >= MAX_LOCATE_DEPTH {
+        return false;
+    }
+
+    let children = match PathSegment::lookup(prototype, path) {
+        Some(serde_json::Value::Object(map)) => map
+            .keys()
+            .map(|key| PathSegment::Key(key.clone()))
+            .collect::<Vec<_>>(),
+        Some(serde_json::Value::Array(values)) => {
+            (0..values.len()).map(PathSegment::Index).collect()
+        }
+        _ => return false,
+    };
+
+    for child in children {
+        path.push(child);
+
+        let found = match probe(&PathSegment::replace(prototype, path, None)) {
+            Some(changed) if matches!((missing_field(&changed), path.last()), (Some(missing), Some(PathSegment::Key(key))) if missing == key) => {
+                locate_in(prototype, error, probe, path)
+                    || [
+                        serde_json::Value::from(0),
+                        serde_json::Value::from(""),
+                        serde_json::Value::from(false),
+                        serde_json::Value::Array(Vec::new()),
+                        serde_json::Value::Object(serde_json::Map::new()),
+                    ]
+                    .into_iter()
+                    .any(|value| {
+                        probe(&PathSegment::replace(prototype, path, Some(value))).is_none()
+                    })
+            }
+            Some(changed) if changed == error || missing_field(error).is_some() => false,
+            _ => {
+                locate_in(prototype, error, probe, path);
+                true
+            }
+        };
+
+        if found {
+            return true;
+        }
+        path.pop();
+    }
+
+    false
+}
+
+fn missing_field(error: &str) -> Option<&str> {
+    error
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+}
+
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl PathSegment {
+    fn lookup<'a>(value: &'a serde_json::Value, path: &[Self]) -> Option<&'a serde_json::Value> {
+        path.iter().try_fold(value, |node, segment| match segment {
+            Self::Key(key) => node.get(key),
+            Self::Index(idx) => node.get(idx),
+        })
+    }
+
+    /// Copy of `value` with the field at `path` removed or replaced.
+    fn replace(
+        value: &serde_json::Value,
+        path: &[Self],
+        replacement: Option<serde_json::Value>,
+    ) -> serde_json::Value {
+        let mut res = value.clone();
+        let Some((last, parent)) = path.split_last() else {
+            return res;
+        };
+
+        let parent = parent
+            .iter()
+            .try_fold(&mut res, |node, segment| match segment {
+                Self::Key(key) => node.get_mut(key),
+                Self::Index(idx) => node.get_mut(idx),
+            });
+
+        match (parent, last, replacement) {
+            (Some(serde_json::Value::Object(map)), Self::Key(key), None) => {
+                map.remove(key);
+            }
+            (Some(serde_json::Value::Array(values)), Self::Index(idx), None) => {
+                values.remove(*idx);
+            }
+            (Some(serde_json::Value::Object(map)), Self::Key(key), Some(replacement)) => {
+                map.insert(key.clone(), replacement);
+            }
+            (Some(serde_json::Value::Array(values)), Self::Index(idx), Some(replacement)) => {
+                values[*idx] = replacement;
+            }
+            _ => {}
+        }
+
+        res
+    }
+}
+
+/// Converts strings that contain a number into actual numbers.
+fn coerce_numeric_strings(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some(number) = s.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                *value = s
+                    .parse::<i64>()
+                    .map_or(serde_json::Value::Number(number), Into::into);
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(coerce_numeric_strings),
+        serde_json::Value::Object(map) => map.values_mut().for_each(coerce_numeric_strings),
+        serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {}
     }
 }
 
@@ -203,9 +784,14 @@ pub struct DataUtil {
     raw: DataRaw,
 
     entities: HashMap<EntityID, entity::Type>,
+
+    /// Name of the utility sprites set used for wires, indicators & placeholders.
+    util_sprites: String,
 }
 
 impl DataUtil {
+    pub const DEFAULT_UTIL_SPRITES: &'static str = "default";
+
     #[allow(clippy::too_many_lines)]
     #[must_use]
     pub fn new(raw: DataRaw) -> Self {
@@ -487,9 +1073,17 @@ impl DataUtil {
             raw.entity.artillery_wagon.keys().fold((), |(), name| {
                 entities.insert(name.clone(), entity::Type::ArtilleryWagon);
             });
+
+            raw.entity.spider_vehicle.keys().fold((), |(), name| {
+                entities.insert(name.clone(), entity::Type::SpiderVehicle);
+            });
         }
 
-        Self { raw, entities }
+        Self {
+            raw,
+            entities,
+            util_sprites: Self::DEFAULT_UTIL_SPRITES.to_owned(),
+        }
     }
 
     #[must_use]
@@ -507,6 +1101,41 @@ impl DataUtil {
         self.raw.recipe.recipe.contains_key(&RecipeID::new(name))
     }
 
+    /// Area covered by the entity `name` when placed facing `direction`, relative to its position.
+    ///
+    /// Rails reach well beyond their drawing box, so their collision boxes are included
+    /// and the result is rotated to match the placement. Other entities use their drawing box.
+    #[must_use]
+    pub fn get_entity_bounds(&self, name: &str, direction: Direction) -> Option<BoundingBox> {
+        let entity = self.get_entity(name)?;
+        let bounds = entity.drawing_box().union(&entity.collision_box());
+
+        match self.get_entity_type(name)? {
+            entity::Type::CurvedRail => {
+                let secondary = self
+                    .raw
+                    .entity
+                    .curved_rail
+                    .get(&EntityID::new(name))
+                    .and_then(|rail| rail.secondary_collision_box.as_ref());
+                let bounds =
+                    secondary.map_or_else(|| bounds.clone(), |secondary| bounds.union(secondary));
+
+                // the diagonal directions are mirrored variants of the cardinal ones
+                let direction = match direction {
+                    Direction::North | Direction::NorthEast => Direction::North,
+                    Direction::East | Direction::SouthEast => Direction::East,
+                    Direction::South | Direction::SouthWest => Direction::South,
+                    Direction::West | Direction::NorthWest => Direction::West,
+                };
+
+                Some(bounds.rotate(direction))
+            }
+            entity::Type::StraightRail => Some(bounds.rotate(direction)),
+            _ => Some(entity.drawing_box()),
+        }
+    }
+
     #[allow(clippy::too_many_lines)]
     #[must_use]
     pub fn get_entity(&self, name: &str) -> Option<&dyn RenderableEntity> {
@@ -904,10 +1533,16 @@ impl DataUtil {
                 .artillery_wagon
                 .get(name)
                 .map(|x| x as &dyn RenderableEntity),
+            entity::Type::SpiderVehicle => self
+                .raw
+                .entity
+                .spider_vehicle
+                .get(name)
+                .map(|x| x as &dyn RenderableEntity),
         }
     }
 
-    #[must_use]
+    /// Renders the entity and records why it failed or what it could not render in `diagnostics`.
     pub fn render_entity(
         &self,
         entity_name: &str,
@@ -915,9 +1550,81 @@ impl DataUtil {
         used_mods: &UsedMods,
         render_layers: &mut crate::RenderLayerBuffer,
         image_cache: &mut ImageCache,
+        diagnostics: &mut Diagnostics,
     ) -> entity::RenderOutput {
-        self.get_entity(entity_name)?
-            .render(render_opts, used_mods, render_layers, image_cache)
+        let Some(entity) = self.get_entity(entity_name) else {
+            diagnostics.error(entity_name, render_opts, DiagnosticKind::UnknownEntity);
+            return None;
+        };
+
+        let diagonal = !matches!(
+            render_opts.direction,
+            Direction::North | Direction::East | Direction::South | Direction::West
+        );
+        if diagonal
+            && self
+                .get_entity_type(entity_name)
+                .is_some_and(entity::Type::is_belt)
+        {
+            diagnostics.warn(
+                entity_name,
+                render_opts,
+                DiagnosticKind::BadDirection {
+                    direction: render_opts.direction,
+                },
+            );
+        }
+
+        if render_opts.mirrored {
+            render_layers.begin_mirror(&render_opts.position, render_opts.direction);
+        }
+
+        let res = entity.render(render_opts, used_mods, render_layers, image_cache);
+        render_layers.end_mirror();
+        if res.is_none() {
+            diagnostics.error(entity_name, render_opts, DiagnosticKind::MissingSprite);
+        }
+
+        res
+    }
+
+    /// Item & amount used to build the entity.
+    ///
+    /// The first item of the `placeable_by` property is preferred,
+    /// otherwise an item with the entity as its `place_result` is used.
+    #[must_use]
+    pub fn entity_item_to_place(&self, name: &str) -> Option<(&ItemID, u32)> {
+        if let Some(placeable_by) = self.get_entity(name).and_then(|e| e.placeable_by()) {
+            return first_item_to_place(placeable_by);
+        }
+
+        let id = EntityID::new(name);
+        self.raw
+            .item
+            .all_data()
+            .filter(|(_, item)| item.place_result == id)
+            .map(|(item, _)| item)
+            .min()
+            .map(|item| (item, 1))
+    }
+
+    /// Item & amount used to build the tile, see [`DataUtil::entity_item_to_place`].
+    #[must_use]
+    pub fn tile_item_to_place(&self, name: &str) -> Option<(&ItemID, u32)> {
+        let id = TileID::new(name);
+        let tile = self.raw.tile.tile.get(&id)?;
+
+        if let Some(placeable_by) = &tile.placeable_by {
+            return first_item_to_place(placeable_by);
+        }
+
+        self.raw
+            .item
+            .all_data()
+            .filter(|(_, item)| item.place_as_tile.as_ref().is_some_and(|t| t.result == id))
+            .map(|(item, _)| item)
+            .min()
+            .map(|item| (item, 1))
     }
 
     pub fn get_item_icon(
@@ -954,41 +1661,292 @@ impl DataUtil {
             .and_then(|x| x.get_icon(scale, used_mods, image_cache))
     }
 
-    pub fn get_recipe_icon(
+    #[must_use]
+    pub fn get_quality(&self, name: &str) -> Option<&quality::QualityPrototype> {
+        self.raw.quality.get(&QualityID::new(name))
+    }
+
+    pub fn get_quality_icon(
         &self,
         name: &str,
         scale: f64,
         used_mods: &mod_util::UsedMods,
         image_cache: &mut types::ImageCache,
     ) -> Option<types::GraphicsOutput> {
-        self.raw.recipe.get_icon(
-            name,
-            scale,
-            used_mods,
-            image_cache,
-            &self.raw.item,
-            &self.raw.fluid,
-        )
+        self.get_quality(name)
+            .and_then(|x| x.get_icon(scale, used_mods, image_cache))
     }
 
-    #[must_use]
-    pub fn recipe_has_fluid(&self, name: &str) -> (bool, bool) {
-        self.raw.recipe.uses_fluid(name)
+    /// Icon of a space location or planet.
+    pub fn get_space_location_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        let id = SpaceLocationID::new(name);
+
+        self.raw
+            .planet
+            .get(&id)
+            .or_else(|| self.raw.space_location.get(&id))
+            .and_then(|x| x.get_icon(scale, used_mods, image_cache))
     }
 
-    #[must_use]
-    pub fn util_sprites(&self) -> Option<&utility_sprites::UtilitySprites> {
-        let key = self.raw.utility_sprites.keys().next()?;
-        self.raw.utility_sprites.get(key)
+    pub fn get_asteroid_chunk_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.raw
+            .asteroid_chunk
+            .get(&AsteroidChunkID::new(name))
+            .and_then(|x| x.get_icon(scale, used_mods, image_cache))
     }
-}
 
-pub trait DataUtilAccess<I, S>
-where
-    S: IdNamespace,
-{
-    fn get_proto<T>(&self, id: &I) -> Option<&T>
-    where
+    /// Icon of an entity, falling back to the icon of the item that places it.
+    pub fn get_entity_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.get_entity(name)
+            .and_then(|entity| entity.icon())
+            .and_then(|icon| icon.render(scale, used_mods, image_cache, &()))
+            .or_else(|| self.get_item_icon(name, scale, used_mods, image_cache))
+    }
+
+    pub fn get_technology_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.raw
+            .technology
+            .get_icon(name, scale, used_mods, image_cache)
+    }
+
+    pub fn get_recipe_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.raw.recipe.get_icon(
+            name,
+            scale,
+            used_mods,
+            image_cache,
+            &self.raw.item,
+            &self.raw.fluid,
+        )
+    }
+
+    /// Names of all items, regardless of their prototype type.
+    pub fn item_names(&self) -> impl Iterator<Item = &str> {
+        self.raw.item.all_data().map(|(id, _)| id.as_str())
+    }
+
+    pub fn fluid_names(&self) -> impl Iterator<Item = &str> {
+        self.raw.fluid.fluid.keys().map(|id| id.as_str())
+    }
+
+    pub fn recipe_names(&self) -> impl Iterator<Item = &str> {
+        self.raw.recipe.recipe.keys().map(|id| id.as_str())
+    }
+
+    pub fn signal_names(&self) -> impl Iterator<Item = &str> {
+        self.raw
+            .virtual_signal
+            .virtual_signal
+            .keys()
+            .map(|id| id.as_str())
+    }
+
+    pub fn technology_names(&self) -> impl Iterator<Item = &str> {
+        self.raw.technology.technology.keys().map(|id| id.as_str())
+    }
+
+    /// The settings of all loaded mods, grouped by their type.
+    #[must_use]
+    pub const fn mod_settings(&self) -> &mod_setting::AllTypes {
+        &self.raw.mod_setting
+    }
+
+    /// All technologies by name, for queries over the whole research tree.
+    pub fn technologies(
+        &self,
+    ) -> impl Iterator<Item = (&TechnologyID, &technology::TechnologyPrototype)> {
+        self.raw.technology.technology.iter()
+    }
+
+    /// Recipes that have the item or fluid `name` as one of their products, sorted by name.
+    ///
+    /// Like all queries this only knows the prototypes that were loaded,
+    /// see [`DataRaw::load_filtered`].
+    #[must_use]
+    pub fn recipes_for_product(&self, name: &str) -> Vec<&RecipeID> {
+        self.recipes_with(recipe::RecipeData::products, name)
+    }
+
+    /// Recipes that use the item or fluid `name` as one of their ingredients, sorted by name.
+    #[must_use]
+    pub fn recipes_using(&self, name: &str) -> Vec<&RecipeID> {
+        self.recipes_with(recipe::RecipeData::ingredients, name)
+    }
+
+    #[must_use]
+    pub fn ingredients_of(&self, recipe: &str) -> Option<Vec<recipe::RecipeComponent>> {
+        self.raw
+            .recipe
+            .recipe
+            .get(&RecipeID::new(recipe))
+            .map(|recipe| recipe.recipe.get_data().ingredients())
+    }
+
+    #[must_use]
+    pub fn products_of(&self, recipe: &str) -> Option<Vec<recipe::RecipeComponent>> {
+        self.raw
+            .recipe
+            .recipe
+            .get(&RecipeID::new(recipe))
+            .map(|recipe| recipe.recipe.get_data().products())
+    }
+
+    /// Assembling machines, furnaces & rocket silos that can craft recipes of `category`, sorted by name.
+    #[must_use]
+    pub fn machines_for_category(&self, category: &str) -> Vec<&EntityID> {
+        let category = RecipeCategoryID::new(category);
+        let entity = &self.raw.entity;
+
+        let mut res = entity
+            .assembling_machine
+            .iter()
+            .filter(|(_, m)| m.crafting_categories.contains(&category))
+            .map(|(name, _)| name)
+            .chain(
+                entity
+                    .furnace
+                    .iter()
+                    .filter(|(_, m)| m.crafting_categories.contains(&category))
+                    .map(|(name, _)| name),
+            )
+            .chain(
+                entity
+                    .rocket_silo
+                    .iter()
+                    .filter(|(_, m)| m.crafting_categories.contains(&category))
+                    .map(|(name, _)| name),
+            )
+            .collect::<Vec<_>>();
+        res.sort();
+
+        res
+    }
+
+    /// Machines that can craft `recipe`, see [`DataUtil::machines_for_category`].
+    #[must_use]
+    pub fn machines_for_recipe(&self, recipe: &str) -> Vec<&EntityID> {
+        self.raw
+            .recipe
+            .recipe
+            .get(&RecipeID::new(recipe))
+            .map(|recipe| self.machines_for_category(&recipe.category))
+            .unwrap_or_default()
+    }
+
+    fn recipes_with(
+        &self,
+        components: impl Fn(&recipe::RecipeData) -> Vec<recipe::RecipeComponent>,
+        name: &str,
+    ) -> Vec<&RecipeID> {
+        let mut res = self
+            .raw
+            .recipe
+            .recipe
+            .iter()
+            .filter(|(_, recipe)| {
+                components(recipe.recipe.get_data())
+                    .iter()
+                    .any(|component| component.name == name)
+            })
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        res.sort();
+
+        res
+    }
+
+    #[must_use]
+    pub fn recipe_has_fluid(&self, name: &str) -> (bool, bool) {
+        self.raw.recipe.uses_fluid(name)
+    }
+
+    /// The selected utility sprites set (`default` unless changed with [`DataUtil::select_util_sprites`]).
+    ///
+    /// Falls back to the alphabetically first set if the data has no `default` set.
+    #[must_use]
+    pub fn util_sprites(&self) -> Option<&utility_sprites::UtilitySprites> {
+        self.raw
+            .utility_sprites
+            .get(&self.util_sprites)
+            .or_else(|| {
+                self.raw
+                    .utility_sprites
+                    .iter()
+                    .min_by_key(|(name, _)| *name)
+                    .map(|(_, sprites)| sprites)
+            })
+    }
+
+    /// Names of all utility sprites sets, sorted.
+    #[must_use]
+    pub fn util_sprite_sets(&self) -> Vec<&str> {
+        let mut res = self
+            .raw
+            .utility_sprites
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        res.sort_unstable();
+        res
+    }
+
+    /// Uses the utility sprites set added by a mod instead of the `default` one.
+    /// Returns `false` and keeps the current set if there is no set with that name.
+    pub fn select_util_sprites(&mut self, name: &str) -> bool {
+        if !self.raw.utility_sprites.contains_key(name) {
+            return false;
+        }
+
+        name.clone_into(&mut self.util_sprites);
+        true
+    }
+}
+
+fn first_item_to_place(placeable_by: &PlaceableBy) -> Option<(&ItemID, u32)> {
+    let item = match placeable_by {
+        PlaceableBy::Single(item) => item,
+        PlaceableBy::Multiple(items) => items.first()?,
+    };
+
+    Some((&item.item, item.count))
+}
+
+pub trait DataUtilAccess<I, S>
+where
+    S: IdNamespace,
+{
+    fn get_proto<T>(&self, id: &I) -> Option<&T>
+    where
         S: IdNamespaceAccess<T>;
 }
 
@@ -1046,6 +2004,15 @@ impl DataUtilAccess<TileID, tile::AllTypes> for DataUtil {
     }
 }
 
+impl DataUtilAccess<TechnologyID, technology::AllTypes> for DataUtil {
+    fn get_proto<T>(&self, id: &TechnologyID) -> Option<&T>
+    where
+        technology::AllTypes: IdNamespaceAccess<T>,
+    {
+        self.raw.technology.get_proto(id)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum InternalRenderLayer {
     Background,
@@ -1068,6 +2035,12 @@ pub enum InternalRenderLayer {
 
     Wire,
 
+    /// Light map of night renders, brightens the layers below instead of being drawn on top.
+    Light,
+
+    AreaOverlay,
+    UndergroundPairs,
+
     DirectionOverlay,
     IconOutline,
     IconOverlay,
@@ -1075,7 +2048,7 @@ pub enum InternalRenderLayer {
 
 impl InternalRenderLayer {
     #[must_use]
-    pub const fn all() -> [Self; 18] {
+    pub const fn all() -> [Self; 21] {
         [
             Self::Background,
             Self::Ground,
@@ -1092,11 +2065,42 @@ impl InternalRenderLayer {
             Self::InserterHand,
             Self::AboveEntity,
             Self::Wire,
+            Self::Light,
+            Self::AreaOverlay,
+            Self::UndergroundPairs,
             Self::DirectionOverlay,
             Self::IconOutline,
             Self::IconOverlay,
         ]
     }
+
+    /// Kebab-case name of the layer, e.g. for file names.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Background => "background",
+            Self::Ground => "ground",
+            Self::GroundPatch => "ground-patch",
+            Self::RailStonePathBackground => "rail-stone-path-background",
+            Self::RailStonePath => "rail-stone-path",
+            Self::RailTies => "rail-ties",
+            Self::RailBackplate => "rail-backplate",
+            Self::RailMetal => "rail-metal",
+            Self::Shadow => "shadow",
+            Self::Entity => "entity",
+            Self::EntityHigh => "entity-high",
+            Self::EntityHigher => "entity-higher",
+            Self::InserterHand => "inserter-hand",
+            Self::AboveEntity => "above-entity",
+            Self::Wire => "wire",
+            Self::Light => "light",
+            Self::AreaOverlay => "area-overlay",
+            Self::UndergroundPairs => "underground-pairs",
+            Self::DirectionOverlay => "direction-overlay",
+            Self::IconOutline => "icon-outline",
+            Self::IconOverlay => "icon-overlay",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1132,6 +2136,37 @@ impl TargetSize {
         }
     }
 
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[must_use]
+    pub const fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    #[must_use]
+    pub const fn top_left(&self) -> &MapPosition {
+        &self.top_left
+    }
+
+    #[must_use]
+    pub const fn bottom_right(&self) -> &MapPosition {
+        &self.bottom_right
+    }
+
+    /// Pixels per tile.
+    #[must_use]
+    pub const fn tile_res(&self) -> f64 {
+        self.tile_res
+    }
+
     #[must_use]
     fn get_pixel_pos(
         &self,
@@ -1148,6 +2183,18 @@ impl TargetSize {
 
         (px.round() as i64, py.round() as i64)
     }
+
+    /// Pixel coordinates of a map position in the rendered image.
+    #[must_use]
+    pub fn map_to_pixel(&self, position: &MapPosition) -> (f32, f32) {
+        let (x, y) = position.as_tuple();
+        let (tl_x, tl_y) = self.top_left.as_tuple();
+
+        (
+            ((x - tl_x) * self.tile_res) as f32,
+            ((y - tl_y) * self.tile_res) as f32,
+        )
+    }
 }
 
 impl std::fmt::Display for TargetSize {
@@ -1160,108 +2207,729 @@ impl std::fmt::Display for TargetSize {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct RenderLayerBuffer {
-    target_size: TargetSize,
-    layers: HashMap<InternalRenderLayer, image::DynamicImage>,
-
-    wire_connection_points: HashMap<u64, GenericWireConnectionPoint>,
+/// What to draw behind the blueprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Background {
+    /// Checkerboard of dark & light lab tiles.
+    #[default]
+    LabTiles,
+    Transparent,
+    Solid(image::Rgba<u8>),
+
+    /// Dark space with scattered stars, fixed to world tiles so they don't move between renders.
+    Starfield,
+
+    /// Dark background with a line every `spacing` tiles.
+    /// Chunk borders (every 32 tiles) get highlighted if `chunk_lines` is set.
+    Grid {
+        spacing: u32,
+        chunk_lines: bool,
+    },
 }
 
-pub type ConnectedEntities = HashMap<u64, [bool; 3]>;
-pub type EntityWireConnections = HashMap<u64, (MapPosition, ([ConnectedEntities; 3], bool))>;
+impl Background {
+    pub const CHUNK_SIZE: u32 = 32;
 
-impl RenderLayerBuffer {
-    #[must_use]
-    pub fn new(target_size: TargetSize) -> Self {
-        Self {
-            target_size,
-            layers: HashMap::new(),
-            wire_connection_points: HashMap::new(),
+    /// Color of the ground behind planet blueprints.
+    pub const TERRAIN: Self = Self::Solid(image::Rgba([0x4b, 0x3c, 0x28, 0xff]));
+}
+
+impl std::str::FromStr for Background {
+    type Err = String;
+
+    /// Parses `lab-tiles`, `transparent`, `starfield`, `terrain`, `grid` or a `#RRGGBB` / `#RRGGBBAA` color.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lab-tiles" | "lab" => Ok(Self::LabTiles),
+            "transparent" => Ok(Self::Transparent),
+            "starfield" | "space" => Ok(Self::Starfield),
+            "terrain" => Ok(Self::TERRAIN),
+            "grid" => Ok(Self::Grid {
+                spacing: 1,
+                chunk_lines: true,
+            }),
+            color if color.starts_with('#') => parse_color(color)
+                .map(Self::Solid)
+                .map_err(|e| format!("invalid background color {s}: {e}")),
+            _ => Err(format!("unknown background: {s}")),
         }
     }
+}
 
-    fn get_layer(&mut self, layer: InternalRenderLayer) -> &mut image::DynamicImage {
-        self.layers.entry(layer).or_insert_with(|| {
-            image::DynamicImage::new_rgba8(self.target_size.width, self.target_size.height)
-        })
+/// Parses a `#RRGGBB` or `#RRGGBBAA` color.
+fn parse_color(s: &str) -> std::result::Result<image::Rgba<u8>, String> {
+    let hex = s
+        .strip_prefix('#')
+        .filter(|hex| matches!(hex.len(), 6 | 8) && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or_else(|| "expected #RRGGBB or #RRGGBBAA".to_owned())?;
+
+    let mut rgba = [0, 0, 0, 255];
+    for (idx, channel) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|e| e.to_string())?;
     }
 
-    pub fn add(
-        &mut self,
-        (img, shift): (image::DynamicImage, Vector),
-        position: &MapPosition,
-        layer: InternalRenderLayer,
-    ) {
-        let (x, y) = self
-            .target_size
-            .get_pixel_pos(img.dimensions(), &shift, position);
+    Ok(image::Rgba(rgba))
+}
 
-        let layer = self.get_layer(layer);
-        imageops::overlay(layer, &img, x, y);
-    }
+/// How alt-mode icons are set apart from the entities below them, applied when combining the layers.
+///
+/// Sizes are in pixels of a full resolution render (32 pixels per tile) and scale with the render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconStyle {
+    /// Icons are drawn as they are.
+    Plain,
 
-    pub fn add_entity(&mut self, input: (image::DynamicImage, Vector), position: &MapPosition) {
-        self.add(input, position, InternalRenderLayer::Entity);
+    /// Outline fading out over `width`, computed from a signed distance field.
+    Outline { width: u8, color: image::Rgba<u8> },
+
+    /// Blurred copy of the icons, moved by `offset` to the bottom right.
+    DropShadow { offset: u8, color: image::Rgba<u8> },
+}
+
+impl IconStyle {
+    pub const DEFAULT_COLOR: image::Rgba<u8> = image::Rgba([0, 0, 0, 255]);
+    pub const DEFAULT_OUTLINE_WIDTH: u8 = 6;
+    pub const DEFAULT_SHADOW_OFFSET: u8 = 3;
+}
+
+impl Default for IconStyle {
+    fn default() -> Self {
+        Self::Outline {
+            width: Self::DEFAULT_OUTLINE_WIDTH,
+            color: Self::DEFAULT_COLOR,
+        }
     }
+}
 
-    pub fn add_shadow(&mut self, input: (image::DynamicImage, Vector), position: &MapPosition) {
-        self.add(input, position, InternalRenderLayer::Shadow);
+impl std::str::FromStr for IconStyle {
+    type Err = String;
+
+    /// Parses `none`, `outline[:WIDTH[:#COLOR]]` or `shadow[:OFFSET[:#COLOR]]`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let kind = parts.next().unwrap_or_default().to_lowercase();
+
+        let size = parts
+            .next()
+            .map(|size| {
+                size.parse::<u8>()
+                    .map_err(|e| format!("invalid icon style size {size}: {e}"))
+            })
+            .transpose()?;
+        let color = parts
+            .next()
+            .map(|color| {
+                parse_color(color).map_err(|e| format!("invalid icon style color {color}: {e}"))
+            })
+            .transpose()?
+            .unwrap_or(Self::DEFAULT_COLOR);
+
+        if parts.next().is_some() {
+            return Err(format!("too many parts in icon style: {s}"));
+        }
+
+        match kind.as_str() {
+            "none" | "plain" if size.is_none() => Ok(Self::Plain),
+            "outline" => Ok(Self::Outline {
+                width: size.unwrap_or(Self::DEFAULT_OUTLINE_WIDTH),
+                color,
+            }),
+            "shadow" | "drop-shadow" => Ok(Self::DropShadow {
+                offset: size.unwrap_or(Self::DEFAULT_SHADOW_OFFSET),
+                color,
+            }),
+            _ => Err(format!("unknown icon style: {s}")),
+        }
     }
+}
+
+/// Colors of the copper, red & green wires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WirePalette {
+    /// Colors of the game sprites.
+    #[default]
+    Default,
 
+    /// Orange copper, reddish purple red & sky blue green wires that stay distinguishable with red-green color blindness.
+    Colorblind,
+
+    /// Custom copper, red & green colors.
+    Custom([image::Rgba<u8>; 3]),
+}
+
+impl WirePalette {
+    /// Colors of the plain lines drawn for fast wires, close to the game sprites.
+    pub const DEFAULT_COLORS: [image::Rgba<u8>; 3] = [
+        image::Rgba([0xcf, 0x7c, 0x47, 0xff]),
+        image::Rgba([0xc8, 0x2d, 0x2d, 0xff]),
+        image::Rgba([0x2e, 0xb5, 0x3f, 0xff]),
+    ];
+
+    pub const COLORBLIND_COLORS: [image::Rgba<u8>; 3] = [
+        image::Rgba([0xe6, 0x9f, 0x00, 0xff]),
+        image::Rgba([0xcc, 0x79, 0xa7, 0xff]),
+        image::Rgba([0x56, 0xb4, 0xe9, 0xff]),
+    ];
+
+    /// Tints for the copper, red & green wire sprites, `None` keeps the sprite colors.
     #[must_use]
-    pub const fn scale(&self) -> f64 {
-        self.target_size.scale
+    pub const fn tints(self) -> Option<[image::Rgba<u8>; 3]> {
+        match self {
+            Self::Default => None,
+            Self::Colorblind => Some(Self::COLORBLIND_COLORS),
+            Self::Custom(colors) => Some(colors),
+        }
     }
 
-    fn store_wire_connection_points(
-        &mut self,
-        bp_entity_id: u64,
-        wire_connection_points: GenericWireConnectionPoint,
-    ) {
-        self.wire_connection_points
-            .insert(bp_entity_id, wire_connection_points);
+    /// Colors of the copper, red & green wires when drawn as plain lines.
+    #[must_use]
+    pub const fn colors(self) -> [image::Rgba<u8>; 3] {
+        match self.tints() {
+            Some(colors) => colors,
+            None => Self::DEFAULT_COLORS,
+        }
     }
+}
 
-    #[instrument(skip_all)]
-    fn generate_wire_draw_data<'a>(
-        &mut self,
-        wire_data: &'a EntityWireConnections,
-    ) -> [Vec<[(&'a MapPosition, Vector); 2]>; 3] {
-        let mut already_drawn = HashSet::<((u64, usize), (u64, usize), usize)>::new();
-        let mut draw_data: [Vec<[(&MapPosition, Vector); 2]>; 3] = Default::default();
+impl std::str::FromStr for WirePalette {
+    type Err = String;
+
+    /// Parses `default`, `colorblind` or 3 comma separated `#RRGGBB[AA]` colors for copper, red & green wires.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "colorblind" | "color-blind" => Ok(Self::Colorblind),
+            colors => {
+                let colors = colors
+                    .split(',')
+                    .map(|color| {
+                        parse_color(color.trim())
+                            .map_err(|e| format!("invalid wire color {color}: {e}"))
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        for (source, (s_pos, (s_wcps_cons, s_is_switch))) in wire_data {
-            let Some(s_wcps) = self.wire_connection_points.get(source) else {
-                continue;
-            };
+                let colors = <[_; 3]>::try_from(colors).map_err(|_| {
+                    format!("expected 3 wire colors (copper, red & green) or a palette name: {s}")
+                })?;
 
-            s_wcps_cons
-                .iter()
-                .enumerate()
-                .for_each(|(s_cons_id, s_cons)| {
-                    let Some(s_wcp) = &s_wcps[s_cons_id] else {
-                        return;
-                    };
+                Ok(Self::Custom(colors))
+            }
+        }
+    }
+}
 
-                    for (target, s_con) in s_cons {
-                        // if already_drawn.contains(&(*source, *target)) {
-                        //     // println!("skipping {source}: {s_cons_id} -> {target}");
-                        //     return;
-                        // }
+/// Rectangular area of a blueprint in world coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+}
 
-                        let Some(t_wcps) = self.wire_connection_points.get(target) else {
-                            return;
-                        };
+impl Region {
+    /// Extra tiles around a region whose entities & tiles are still rendered,
+    /// so large entities and connections reaching into the region are drawn.
+    pub const MARGIN: f64 = 8.0;
 
-                        let Some((t_pos, (t_wcps_cons, _))) = wire_data.get(target) else {
-                            return;
-                        };
+    #[must_use]
+    pub const fn new(a: &MapPosition, b: &MapPosition) -> Self {
+        let area = BoundingBox::from_corners(a, b);
 
-                        t_wcps_cons
-                            .iter()
-                            .enumerate()
-                            .for_each(|(t_cons_id, t_cons)| {
+        Self {
+            left: area.left(),
+            top: area.top(),
+            right: area.right(),
+            bottom: area.bottom(),
+        }
+    }
+
+    #[must_use]
+    pub const fn area(&self) -> BoundingBox {
+        BoundingBox(
+            MapPosition::Tuple(self.left, self.top),
+            MapPosition::Tuple(self.right, self.bottom),
+        )
+    }
+
+    #[must_use]
+    pub fn contains(&self, pos: &MapPosition, margin: f64) -> bool {
+        self.area().expand(margin).contains(pos)
+    }
+}
+
+impl std::str::FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let coords = s
+            .split(',')
+            .map(|c| c.trim().parse::<f64>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid region '{s}': {e}"))?;
+
+        let [x1, y1, x2, y2] = coords[..] else {
+            return Err(format!("invalid region '{s}', expected x1,y1,x2,y2"));
+        };
+
+        Ok(Self::new(
+            &MapPosition::XY { x: x1, y: y1 },
+            &MapPosition::XY { x: x2, y: y2 },
+        ))
+    }
+}
+
+/// Toggles for the optional parts of a blueprint render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    /// Recipe, filter & module icons and inserter / priority indicators.
+    pub alt_mode: bool,
+    pub wires: bool,
+    pub shadows: bool,
+    pub background: Background,
+
+    /// Footer with the blueprint dimensions & a scale bar, drawn by the caller after combining.
+    pub annotations: bool,
+
+    /// Icon grid of the items needed to build the blueprint, drawn by the caller below the render.
+    pub bill_of_materials: bool,
+
+    /// Wires are skipped entirely when the blueprint has more than this many, `None` draws all of them.
+    pub max_wires: Option<usize>,
+
+    /// Draw wires as plain anti-aliased lines instead of stretched sprites, much faster for huge blueprints.
+    pub fast_wires: bool,
+
+    /// Colors of the copper, red & green wires.
+    pub wire_palette: WirePalette,
+
+    /// Darken the render like at night, entities with lights brighten their surroundings.
+    pub night: bool,
+
+    /// Draw red wires dashed & green wires dotted so they can be told apart without their color.
+    pub dashed_wires: bool,
+
+    /// Outline or drop shadow behind alt-mode icons, applied when combining.
+    pub icon_style: IconStyle,
+
+    /// Smooth interpolation when stretching & rotating sprites, nearest neighbor otherwise.
+    pub smooth_scaling: bool,
+
+    /// Pixel budget of the image, the resolution is lowered for renders that would exceed it. `None` renders at any size.
+    ///
+    /// Every render layer that is drawn on holds a full RGBA copy of the image, so a render needs
+    /// up to `4 * InternalRenderLayer::all().len()` bytes per pixel of this budget.
+    pub max_pixels: Option<u64>,
+
+    /// Seed to pick sprite variations deterministically, `None` always uses the first variation.
+    pub seed: Option<u64>,
+
+    /// Fail the render instead of drawing placeholders for entities that could not be rendered.
+    pub strict: bool,
+
+    /// Only render this area of the blueprint.
+    pub region: Option<Region>,
+
+    /// Also encode every populated render layer as a separate image.
+    pub split_layers: bool,
+}
+
+impl RenderOptions {
+    pub const DEFAULT_MAX_WIRES: usize = 10_000;
+
+    /// 25 megapixels, a 5000x5000 image. Renders peak at about 2 GB if all 21 layers are used.
+    pub const DEFAULT_MAX_PIXELS: u64 = 25_000_000;
+
+    /// Darkness of night renders, close to the darkest point of a vanilla night.
+    pub const NIGHT_DARKNESS: f64 = 0.85;
+
+    /// Low quality profile for renders with a tight latency budget.
+    ///
+    /// Skips shadows & the icon outline / shadow, uses nearest neighbor scaling, draws wires as lines
+    /// and always uses the first sprite variation.
+    #[must_use]
+    pub const fn fast(self) -> Self {
+        Self {
+            shadows: false,
+            seed: None,
+            fast_wires: true,
+            icon_style: IconStyle::Plain,
+            smooth_scaling: false,
+            ..self
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            alt_mode: true,
+            wires: true,
+            shadows: true,
+            background: Background::default(),
+            annotations: false,
+            bill_of_materials: false,
+            max_wires: Some(Self::DEFAULT_MAX_WIRES),
+            fast_wires: false,
+            wire_palette: WirePalette::Default,
+            dashed_wires: false,
+            night: false,
+            icon_style: IconStyle::Outline {
+                width: IconStyle::DEFAULT_OUTLINE_WIDTH,
+                color: IconStyle::DEFAULT_COLOR,
+            },
+            smooth_scaling: true,
+            max_pixels: Some(Self::DEFAULT_MAX_PIXELS),
+            seed: None,
+            strict: false,
+            region: None,
+            split_layers: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderLayerBuffer {
+    target_size: TargetSize,
+    layers: HashMap<InternalRenderLayer, image::DynamicImage>,
+    skipped: HashSet<InternalRenderLayer>,
+    icon_style: IconStyle,
+    icons_stylized: bool,
+    darkness: f64,
+
+    wire_connection_points: HashMap<u64, GenericWireConnectionPoint>,
+
+    current_entity: Option<u64>,
+    entity_regions: HashMap<u64, PixelRect>,
+
+    /// Position & direction of the mirrored entity that is currently drawn.
+    mirror: Option<(MapPosition, Direction)>,
+}
+
+/// Axis aligned rectangle in image pixels, `right` & `bottom` are exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub left: i64,
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+}
+
+impl PixelRect {
+    #[must_use]
+    pub const fn new(x: i64, y: i64, width: u32, height: u32) -> Self {
+        Self {
+            left: x,
+            top: y,
+            right: x + width as i64,
+            bottom: y + height as i64,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.left >= self.right || self.top >= self.bottom
+    }
+
+    /// Smallest rectangle that contains both rectangles.
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return *other;
+        }
+
+        if other.is_empty() {
+            return *self;
+        }
+
+        Self {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    #[must_use]
+    pub const fn intersects(&self, other: &Self) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+
+    /// The part of the rectangle that is inside an image of the given size.
+    #[must_use]
+    pub fn clamp(&self, width: u32, height: u32) -> Self {
+        let (width, height) = (i64::from(width), i64::from(height));
+
+        Self {
+            left: self.left.clamp(0, width),
+            top: self.top.clamp(0, height),
+            right: self.right.clamp(0, width),
+            bottom: self.bottom.clamp(0, height),
+        }
+    }
+
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        (self.right - self.left)
+            .max(0)
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        (self.bottom - self.top)
+            .max(0)
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+}
+
+pub type ConnectedEntities = HashMap<u64, [bool; 3]>;
+pub type EntityWireConnections = HashMap<u64, (MapPosition, ([ConnectedEntities; 3], bool))>;
+
+impl RenderLayerBuffer {
+    #[must_use]
+    pub fn new(target_size: TargetSize) -> Self {
+        Self {
+            target_size,
+            layers: HashMap::new(),
+            skipped: HashSet::new(),
+            icon_style: IconStyle::default(),
+            icons_stylized: false,
+            darkness: 0.0,
+            wire_connection_points: HashMap::new(),
+            current_entity: None,
+            entity_regions: HashMap::new(),
+            mirror: None,
+        }
+    }
+
+    /// Style of the icon outline layer that is generated from the icons when combining.
+    pub const fn set_icon_style(&mut self, style: IconStyle) {
+        self.icon_style = style;
+    }
+
+    /// Darkness of the surface from 0 (day) to 1 (pitch black), applied to everything below
+    /// the overlays when combining. Lit areas of the light map are brightened again.
+    pub const fn set_darkness(&mut self, darkness: f64) {
+        self.darkness = darkness;
+    }
+
+    #[must_use]
+    pub const fn target_size(&self) -> &TargetSize {
+        &self.target_size
+    }
+
+    /// Attributes everything drawn until [`RenderLayerBuffer::end_entity`] to the given entity.
+    pub fn begin_entity(&mut self, entity_number: u64) {
+        self.current_entity = Some(entity_number);
+        self.entity_regions.remove(&entity_number);
+    }
+
+    pub const fn end_entity(&mut self) {
+        self.current_entity = None;
+    }
+
+    /// Flips everything drawn until [`RenderLayerBuffer::end_mirror`] around `position`.
+    /// The flip is horizontal in the frame of the entity, so it is vertical on the image for east & west.
+    /// Diagonals are flipped like the nearest cardinal direction.
+    pub const fn begin_mirror(&mut self, position: &MapPosition, direction: Direction) {
+        self.mirror = Some((*position, direction));
+    }
+
+    pub const fn end_mirror(&mut self) {
+        self.mirror = None;
+    }
+
+    /// Pixels the entity was drawn onto, across all layers.
+    #[must_use]
+    pub fn entity_region(&self, entity_number: u64) -> Option<PixelRect> {
+        self.entity_regions.get(&entity_number).copied()
+    }
+
+    /// Drops the region & wire connection points of the entity. Its pixels are not touched.
+    pub fn forget_entity(&mut self, entity_number: u64) {
+        self.entity_regions.remove(&entity_number);
+        self.wire_connection_points.remove(&entity_number);
+    }
+
+    fn track(&mut self, rect: PixelRect) {
+        if let Some(entity_number) = self.current_entity {
+            let region = self.entity_regions.entry(entity_number).or_insert(rect);
+            *region = region.union(&rect);
+        }
+    }
+
+    /// Replaces the pixels inside `dirty` of all entity layers with the ones of `other`,
+    /// which has to be of the same size. The entity regions & wire connection points
+    /// of `other` replace the ones of this buffer.
+    ///
+    /// Tiles, the background & wires are kept, they don't depend on single entities.
+    pub fn merge(&mut self, other: Self, dirty: PixelRect) {
+        const KEPT: [InternalRenderLayer; 3] = [
+            InternalRenderLayer::Background,
+            InternalRenderLayer::Ground,
+            InternalRenderLayer::Wire,
+        ];
+
+        let dirty = dirty.clamp(self.target_size.width, self.target_size.height);
+        if dirty.is_empty() {
+            return;
+        }
+
+        let (x, y) = (dirty.left as u32, dirty.top as u32);
+        let (width, height) = (dirty.width(), dirty.height());
+
+        for layer in InternalRenderLayer::all() {
+            if KEPT.contains(&layer) || self.skipped.contains(&layer) {
+                continue;
+            }
+
+            let source = other.layers.get(&layer);
+            if source.is_none() && !self.layers.contains_key(&layer) {
+                continue;
+            }
+
+            let target = self.get_layer(layer);
+            let cleared = image::DynamicImage::new_rgba8(width, height);
+            let patch = source.map_or(cleared, |source| source.crop_imm(x, y, width, height));
+            imageops::replace(target, &patch, i64::from(x), i64::from(y));
+        }
+
+        self.entity_regions.extend(other.entity_regions);
+        self.wire_connection_points
+            .extend(other.wire_connection_points);
+    }
+
+    /// The image of the given layer, [`None`] if nothing was drawn onto it.
+    #[must_use]
+    pub fn layer(&self, layer: InternalRenderLayer) -> Option<&image::DynamicImage> {
+        self.layers.get(&layer)
+    }
+
+    /// Ignores everything added to the given layer from now on, so it isn't composited at all.
+    pub fn skip_layer(&mut self, layer: InternalRenderLayer) {
+        self.skipped.insert(layer);
+        self.layers.remove(&layer);
+    }
+
+    /// Drops everything drawn onto the given layer.
+    pub fn clear_layer(&mut self, layer: InternalRenderLayer) {
+        self.layers.remove(&layer);
+    }
+
+    fn get_layer(&mut self, layer: InternalRenderLayer) -> &mut image::DynamicImage {
+        self.layers.entry(layer).or_insert_with(|| {
+            image::DynamicImage::new_rgba8(self.target_size.width, self.target_size.height)
+        })
+    }
+
+    pub fn add(
+        &mut self,
+        (img, shift): (image::DynamicImage, Vector),
+        position: &MapPosition,
+        layer: InternalRenderLayer,
+    ) {
+        if self.skipped.contains(&layer) {
+            return;
+        }
+
+        let (img, shift, position) = match self.mirror {
+            Some((axis, direction)) => {
+                let (shift_x, shift_y) = shift.as_tuple();
+                let (x, y) = position.as_tuple();
+
+                match direction.to_cardinal() {
+                    Direction::North | Direction::South => (
+                        imageops::flip_horizontal(&img).into(),
+                        Vector::Tuple(-shift_x, shift_y),
+                        MapPosition::Tuple(2.0f64.mul_add(axis.x(), -x), y),
+                    ),
+                    _ => (
+                        imageops::flip_vertical(&img).into(),
+                        Vector::Tuple(shift_x, -shift_y),
+                        MapPosition::Tuple(x, 2.0f64.mul_add(axis.y(), -y)),
+                    ),
+                }
+            }
+            None => (img, shift, *position),
+        };
+        let position = &position;
+
+        let (x, y) = self
+            .target_size
+            .get_pixel_pos(img.dimensions(), &shift, position);
+        self.track(PixelRect::new(x, y, img.width(), img.height()));
+
+        let layer = self.get_layer(layer);
+        imageops::overlay(layer, &img, x, y);
+    }
+
+    pub fn add_entity(&mut self, input: (image::DynamicImage, Vector), position: &MapPosition) {
+        self.add(input, position, InternalRenderLayer::Entity);
+    }
+
+    pub fn add_shadow(&mut self, input: (image::DynamicImage, Vector), position: &MapPosition) {
+        self.add(input, position, InternalRenderLayer::Shadow);
+    }
+
+    #[must_use]
+    pub const fn scale(&self) -> f64 {
+        self.target_size.scale
+    }
+
+    fn store_wire_connection_points(
+        &mut self,
+        bp_entity_id: u64,
+        wire_connection_points: GenericWireConnectionPoint,
+    ) {
+        self.wire_connection_points
+            .insert(bp_entity_id, wire_connection_points);
+    }
+
+    #[instrument(skip_all)]
+    fn generate_wire_draw_data<'a>(
+        &self,
+        wire_data: &'a EntityWireConnections,
+    ) -> [Vec<[(&'a MapPosition, Vector); 2]>; 3] {
+        let mut already_drawn = HashSet::<((u64, usize), (u64, usize), usize)>::new();
+        let mut draw_data: [Vec<[(&MapPosition, Vector); 2]>; 3] = Default::default();
+
+        for (source, (s_pos, (s_wcps_cons, s_is_switch))) in wire_data {
+            let Some(s_wcps) = self.wire_connection_points.get(source) else {
+                continue;
+            };
+
+            s_wcps_cons
+                .iter()
+                .enumerate()
+                .for_each(|(s_cons_id, s_cons)| {
+                    let Some(s_wcp) = &s_wcps[s_cons_id] else {
+                        return;
+                    };
+
+                    for (target, s_con) in s_cons {
+                        // if already_drawn.contains(&(*source, *target)) {
+                        //     // println!("skipping {source}: {s_cons_id} -> {target}");
+                        //     return;
+                        // }
+
+                        let Some(t_wcps) = self.wire_connection_points.get(target) else {
+                            return;
+                        };
+
+                        let Some((t_pos, (t_wcps_cons, _))) = wire_data.get(target) else {
+                            return;
+                        };
+
+                        t_wcps_cons
+                            .iter()
+                            .enumerate()
+                            .for_each(|(t_cons_id, t_cons)| {
                                 let Some(t_con) = t_cons.get(source) else {
                                     return;
                                 };
@@ -1332,19 +3000,34 @@ impl RenderLayerBuffer {
         util_sprites: &utility_sprites::UtilitySprites,
         used_mods: &mod_util::UsedMods,
         image_cache: &mut types::ImageCache,
+        options: &RenderOptions,
     ) {
         let dd = self.generate_wire_draw_data(wire_data);
         let count = dd.iter().map(std::vec::Vec::len).sum::<usize>();
 
-        if count > 10_000 {
+        if options.max_wires.is_some_and(|max| count > max) {
             tracing::warn!("too many wires to draw ({count})");
             return;
         }
 
         tracing::info!("drawing wires");
 
-        let target_size = self.target_size.clone();
-        let layer = self.get_layer(InternalRenderLayer::Wire);
+        if options.fast_wires {
+            self.draw_wire_lines(&dd, options);
+            return;
+        }
+
+        let (resize_filter, interpolation) = if options.smooth_scaling {
+            (
+                image::imageops::FilterType::CatmullRom,
+                geometric_transformations::Interpolation::Bicubic,
+            )
+        } else {
+            (
+                image::imageops::FilterType::Nearest,
+                geometric_transformations::Interpolation::Nearest,
+            )
+        };
 
         for i in 0..3u8 {
             let d = &dd[usize::from(i)];
@@ -1353,7 +3036,7 @@ impl RenderLayerBuffer {
                 continue;
             }
 
-            let Some((base_wire, _)) = match i {
+            let Some((mut base_wire, _)) = match i {
                 0 => &util_sprites.wires.copper_wire,
                 1 => &util_sprites.wires.red_wire,
                 2 => &util_sprites.wires.green_wire,
@@ -1368,6 +3051,11 @@ impl RenderLayerBuffer {
                 continue;
             };
 
+            if let Some(tints) = options.wire_palette.tints() {
+                base_wire = recolor_wire(&base_wire, tints[usize::from(i)]);
+            }
+            let dash_pattern = wire_dash_pattern(usize::from(i)).filter(|_| options.dashed_wires);
+
             let (base_wire_width, base_wire_height) = base_wire.dimensions();
             let base_length = (f64::from(base_wire_width) / 32.0) * self.scale();
 
@@ -1403,12 +3091,16 @@ impl RenderLayerBuffer {
                     cropped_height.ceil() as u32,
                 );
 
-                let wire = base_wire.resize_exact(
+                let mut wire = base_wire.resize_exact(
                     (f64::from(base_wire_width) * (length / base_length)).ceil() as u32,
                     cropped_height.ceil() as u32,
-                    image::imageops::FilterType::CatmullRom,
+                    resize_filter,
                 );
 
+                if let Some((dash, gap)) = dash_pattern {
+                    wire = cut_wire_gaps(&wire, length, dash, gap);
+                }
+
                 let (w, h) = wire.dimensions();
 
                 if w == 0 || h == 0 {
@@ -1421,7 +3113,7 @@ impl RenderLayerBuffer {
                 let rotated = geometric_transformations::rotate_about_center(
                     &wire_square.to_rgba8(),
                     orientation as f32,
-                    geometric_transformations::Interpolation::Bicubic,
+                    interpolation,
                     image::Rgba([0, 0, 0, 0]),
                 );
 
@@ -1434,12 +3126,292 @@ impl RenderLayerBuffer {
         }
     }
 
-    #[instrument(skip_all)]
-    pub fn generate_background(&mut self) {
-        let lab_tile_dark = image::Luma([0x1bu8]);
-        let lab_tile_light = image::Luma([0x31u8]);
-
-        let (tl_x, tl_y) = self.target_size.top_left.as_tuple();
+    fn draw_wire_lines(
+        &mut self,
+        draw_data: &[Vec<[(&MapPosition, Vector); 2]>; 3],
+        options: &RenderOptions,
+    ) {
+        let thickness = (self.target_size.tile_res / 32.0).ceil().max(1.0) as i32;
+        let target_size = self.target_size.clone();
+        let Some(layer) = self.get_layer(InternalRenderLayer::Wire).as_mut_rgba8() else {
+            return;
+        };
+
+        for (idx, (wires, color)) in draw_data
+            .iter()
+            .zip(options.wire_palette.colors())
+            .enumerate()
+        {
+            let dash_pattern = wire_dash_pattern(idx).filter(|_| options.dashed_wires);
+
+            for [(s_pos, s_offset), (t_pos, t_offset)] in wires {
+                let start = *s_pos + &MapPosition::from(*s_offset);
+                let end = *t_pos + &MapPosition::from(*t_offset);
+
+                let Some((dash, gap)) = dash_pattern else {
+                    let (start, end) = (
+                        target_size.map_to_pixel(&start),
+                        target_size.map_to_pixel(&end),
+                    );
+                    draw_antialiased_thick_line(layer, start, end, thickness, color);
+                    continue;
+                };
+
+                let length = start.distance_to(&end);
+                if length == 0.0 {
+                    continue;
+                }
+
+                let (dx, dy) = (end - start).as_tuple();
+                let along = |dist: f64| {
+                    target_size.map_to_pixel(
+                        &(start + MapPosition::Tuple(dx * dist / length, dy * dist / length)),
+                    )
+                };
+
+                for (dash_start, dash_end) in dashes(length, dash, gap) {
+                    draw_antialiased_thick_line(
+                        layer,
+                        along(dash_start),
+                        along(dash_end),
+                        thickness,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Fills the given area on the layer with `color`.
+    /// Pixels are replaced instead of blended so overlapping areas keep a uniform color.
+    pub fn fill_area(
+        &mut self,
+        area: &BoundingBox,
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        let Some(rect) = self.area_to_rect(area) else {
+            return;
+        };
+        self.track(PixelRect::new(
+            rect.left().into(),
+            rect.top().into(),
+            rect.width(),
+            rect.height(),
+        ));
+
+        if let Some(layer) = self.get_layer(layer).as_mut_rgba8() {
+            imageproc::drawing::draw_filled_rect_mut(layer, rect, color);
+        }
+    }
+
+    /// Draws the outline of the given area onto the layer.
+    pub fn outline_area(
+        &mut self,
+        area: &BoundingBox,
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        let Some(rect) = self.area_to_rect(area) else {
+            return;
+        };
+        self.track(PixelRect::new(
+            rect.left().into(),
+            rect.top().into(),
+            rect.width(),
+            rect.height(),
+        ));
+
+        if let Some(layer) = self.get_layer(layer).as_mut_rgba8() {
+            imageproc::drawing::draw_hollow_rect_mut(layer, rect, color);
+        }
+    }
+
+    fn area_to_rect(&self, area: &BoundingBox) -> Option<imageproc::rect::Rect> {
+        let (l, t) = self.target_size.map_to_pixel(area.top_left());
+        let (r, b) = self.target_size.map_to_pixel(area.bottom_right());
+
+        let width = (r - l).round() as u32;
+        let height = (b - t).round() as u32;
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        Some(imageproc::rect::Rect::at(l.round() as i32, t.round() as i32).of_size(width, height))
+    }
+
+    /// Draws a solid line between `start` and `end` onto the given layer.
+    pub fn draw_line(
+        &mut self,
+        start: &MapPosition,
+        end: &MapPosition,
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        let thickness = self.line_thickness();
+        let start = self.target_size.map_to_pixel(start);
+        let end = self.target_size.map_to_pixel(end);
+
+        if let Some(layer) = self.get_layer(layer).as_mut_rgba8() {
+            draw_thick_line(layer, start, end, thickness, color);
+        }
+    }
+
+    /// Draws a dashed line between `start` and `end` onto the given layer.
+    pub fn draw_dashed_line(
+        &mut self,
+        start: &MapPosition,
+        end: &MapPosition,
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        const DASH_LENGTH: f64 = 0.25;
+        const GAP_LENGTH: f64 = 0.125;
+
+        let length = start.distance_to(end);
+        if length == 0.0 {
+            return;
+        }
+
+        let (dx, dy) = (*end - start).as_tuple();
+        let (dx, dy) = (dx / length, dy / length);
+        let thickness = self.line_thickness();
+
+        let target_size = self.target_size.clone();
+        let Some(layer) = self.get_layer(layer).as_mut_rgba8() else {
+            return;
+        };
+
+        for (dash_start, dash_end) in dashes(length, DASH_LENGTH, GAP_LENGTH) {
+            let s = target_size
+                .map_to_pixel(&(*start + MapPosition::Tuple(dx * dash_start, dy * dash_start)));
+            let e = target_size
+                .map_to_pixel(&(*start + MapPosition::Tuple(dx * dash_end, dy * dash_end)));
+
+            draw_thick_line(layer, s, e, thickness, color);
+        }
+    }
+
+    /// Fills the polygon spanned by `points` onto the given layer.
+    pub fn fill_polygon(
+        &mut self,
+        points: &[MapPosition],
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        let mut poly = points
+            .iter()
+            .map(|p| {
+                let (x, y) = self.target_size.map_to_pixel(p);
+                imageproc::point::Point::new(x.round() as i32, y.round() as i32)
+            })
+            .collect::<Vec<_>>();
+        poly.dedup();
+
+        if poly.len() > 1 && poly.first() == poly.last() {
+            poly.pop();
+        }
+
+        if poly.len() < 3 {
+            return;
+        }
+
+        if let Some(layer) = self.get_layer(layer).as_mut_rgba8() {
+            imageproc::drawing::draw_polygon_mut(layer, &poly, color);
+        }
+    }
+
+    /// Fills the circle sector around `center` that starts at `orientation` and spans
+    /// `sweep` of a full turn. A sweep of `1.0` fills the whole circle.
+    pub fn fill_sector(
+        &mut self,
+        center: &MapPosition,
+        radius: f64,
+        orientation: RealOrientation,
+        sweep: f64,
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        let mut points = self.arc_points(center, radius, orientation, sweep);
+        if sweep < 1.0 {
+            points.push(*center);
+        }
+
+        self.fill_polygon(&points, color, layer);
+    }
+
+    /// Draws the arc around `center` that starts at `orientation` and spans `sweep` of a
+    /// full turn. A sweep of `1.0` draws the whole circle.
+    pub fn draw_arc(
+        &mut self,
+        center: &MapPosition,
+        radius: f64,
+        orientation: RealOrientation,
+        sweep: f64,
+        color: image::Rgba<u8>,
+        layer: InternalRenderLayer,
+    ) {
+        let points = self.arc_points(center, radius, orientation, sweep);
+
+        for segment in points.windows(2) {
+            self.draw_line(&segment[0], &segment[1], color, layer);
+        }
+    }
+
+    /// Points along an arc, spaced so the segments stay a few pixels long.
+    fn arc_points(
+        &self,
+        center: &MapPosition,
+        radius: f64,
+        orientation: RealOrientation,
+        sweep: f64,
+    ) -> Vec<MapPosition> {
+        let sweep = sweep.clamp(0.0, 1.0);
+        let circumference = radius * self.target_size.tile_res * std::f64::consts::TAU;
+        let steps = ((circumference * sweep / 4.0).ceil() as u32).clamp(8, 1024);
+
+        (0..=steps)
+            .map(|step| {
+                let angle = (*orientation + sweep * f64::from(step) / f64::from(steps))
+                    * std::f64::consts::TAU;
+
+                *center + MapPosition::Tuple(radius * angle.sin(), -radius * angle.cos())
+            })
+            .collect()
+    }
+
+    fn line_thickness(&self) -> i32 {
+        (self.target_size.tile_res / 16.0).ceil().max(1.0) as i32
+    }
+
+    #[instrument(skip_all)]
+    pub fn generate_background(&mut self, options: &RenderOptions) {
+        let background = match options.background {
+            Background::LabTiles => self.lab_tiles_background(),
+            Background::Transparent => return,
+            Background::Solid(color) => image::ImageBuffer::from_pixel(
+                self.target_size.width,
+                self.target_size.height,
+                color,
+            )
+            .into(),
+            Background::Starfield => self.starfield_background(),
+            Background::Grid {
+                spacing,
+                chunk_lines,
+            } => self.grid_background(spacing, chunk_lines),
+        };
+
+        self.layers
+            .insert(InternalRenderLayer::Background, background);
+    }
+
+    fn lab_tiles_background(&self) -> image::DynamicImage {
+        let lab_tile_dark = image::Luma([0x1bu8]);
+        let lab_tile_light = image::Luma([0x31u8]);
+
+        let (tl_x, tl_y) = self.target_size.top_left.as_tuple();
         let tile_res = self.target_size.tile_res;
 
         let background =
@@ -1457,58 +3429,137 @@ impl RenderLayerBuffer {
                 }
             });
 
-        self.layers
-            .insert(InternalRenderLayer::Background, background.into());
+        background.into()
     }
 
-    #[must_use]
-    #[instrument(skip_all)]
-    pub fn combine(&mut self) -> image::DynamicImage {
-        'sdf_outline: {
-            if let Some(icons) = self.layers.get(&InternalRenderLayer::IconOverlay) {
-                let (width, height) = icons.dimensions();
-                let mask = image::ImageBuffer::from_fn(width, height, |x, y| {
-                    let alpha = icons.get_pixel(x, y).0[3];
-                    image::Luma([alpha])
-                });
+    fn starfield_background(&self) -> image::DynamicImage {
+        // roughly one star every STAR_RARITY tiles
+        const STAR_RARITY: u64 = 5;
 
-                let Some(normalized_sdf) =
-                    compute_f32_distance_field(&binary_image::of_byte_slice_with_threshold(
-                        &mask,
-                        width as u16,
-                        height as u16,
-                        1,
-                    ))
-                    .normalize_clamped_distances(0.0, (6.0 / self.scale()) as f32)
-                else {
-                    break 'sdf_outline;
-                };
+        let space = image::Rgba([0x06u8, 0x08, 0x10, 0xff]);
 
-                let normalized = normalized_sdf
-                    .to_u8()
-                    .iter()
-                    .flat_map(|&x| {
-                        if x == 255 {
-                            [0, 0]
-                        } else {
-                            [0, 255 - (f64::from(x).powi(2) / 255f64).round() as u8]
-                        }
-                    })
-                    .collect::<Vec<_>>();
+        let (tl_x, tl_y) = self.target_size.top_left.as_tuple();
+        let tile_res = self.target_size.tile_res;
+        let radius = (tile_res / 24.0).max(0.75);
 
-                let Some(outline_img) = GrayAlphaImage::from_vec(width, height, normalized) else {
-                    break 'sdf_outline;
-                };
+        image::ImageBuffer::from_fn(self.target_size.width, self.target_size.height, |x, y| {
+            let x = f64::from(x) / tile_res + tl_x;
+            let y = f64::from(y) / tile_res + tl_y;
+            let (tile_x, tile_y) = (x.floor(), y.floor());
+
+            // splitmix64 finalizer, stable across platforms & compiler versions
+            let mut hash = tile_x.to_bits() ^ tile_y.to_bits().rotate_left(32);
+            hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            hash ^= hash >> 31;
+
+            if hash % STAR_RARITY != 0 {
+                return space;
+            }
+
+            // keep the star away from the tile edges so it doesn't get cut off
+            let offset = |bits: u64| ((bits & 0xff) as f64 / 255.0).mul_add(0.6, 0.2);
+            let star_x = tile_x + offset(hash >> 8);
+            let star_y = tile_y + offset(hash >> 16);
+
+            let dist = (x - star_x).hypot(y - star_y) * tile_res;
+            if dist > radius {
+                return space;
+            }
 
+            let brightness = 0x80 + ((hash >> 24) & 0x7f) as u8;
+            let fade = 1.0 - dist / radius;
+            let channel = |base: u8| (f64::from(base) + f64::from(brightness - base) * fade) as u8;
+
+            image::Rgba([
+                channel(space[0]),
+                channel(space[1]),
+                channel(space[2]),
+                0xff,
+            ])
+        })
+        .into()
+    }
+
+    fn grid_background(&self, spacing: u32, chunk_lines: bool) -> image::DynamicImage {
+        let background = image::Rgba([0x1bu8, 0x1b, 0x1b, 0xff]);
+        let grid_line = image::Rgba([0x3au8, 0x3a, 0x3a, 0xff]);
+        let chunk_line = image::Rgba([0x8cu8, 0x5a, 0x1e, 0xff]);
+
+        let tile_res = self.target_size.tile_res as i64;
+        let grid_px = tile_res * i64::from(spacing.max(1));
+        let chunk_px = tile_res * i64::from(Background::CHUNK_SIZE);
+        let thickness = i64::from(self.line_thickness());
+
+        // pixel offset of the image origin from the world origin, the top left corner is tile aligned
+        let (tl_x, tl_y) = self.target_size.top_left.as_tuple();
+        let offset_x = tl_x as i64 * tile_res;
+        let offset_y = tl_y as i64 * tile_res;
+
+        image::ImageBuffer::from_fn(self.target_size.width, self.target_size.height, |x, y| {
+            let x = i64::from(x) + offset_x;
+            let y = i64::from(y) + offset_y;
+
+            if chunk_lines
+                && (x.rem_euclid(chunk_px) < thickness || y.rem_euclid(chunk_px) < thickness)
+            {
+                chunk_line
+            } else if x.rem_euclid(grid_px) == 0 || y.rem_euclid(grid_px) == 0 {
+                grid_line
+            } else {
+                background
+            }
+        })
+        .into()
+    }
+
+    /// Generates the icon outline layer from the icons according to the [`IconStyle`].
+    ///
+    /// [`RenderLayerBuffer::combine`] does this as well, calling it right before allows timing the distance field separately.
+    pub fn stylize_icons(&mut self) {
+        self.icons_stylized = true;
+
+        if !self.skipped.contains(&InternalRenderLayer::IconOutline) {
+            let scale = self.scale();
+            let stylized = self
+                .layers
+                .get(&InternalRenderLayer::IconOverlay)
+                .and_then(|icons| match self.icon_style {
+                    IconStyle::Plain => None,
+                    IconStyle::Outline { width, color } => {
+                        sdf_outline(icons, f64::from(width) / scale, color)
+                    }
+                    IconStyle::DropShadow { offset, color } => {
+                        Some(drop_shadow(icons, f64::from(offset) / scale, color))
+                    }
+                });
+
+            if let Some(stylized) = stylized {
                 let outline = self.get_layer(InternalRenderLayer::IconOutline);
-                outline.clone_from(&outline_img.into());
+                outline.clone_from(&stylized);
             }
         }
+    }
+
+    #[must_use]
+    #[instrument(skip_all)]
+    pub fn combine(&mut self) -> image::DynamicImage {
+        if !self.icons_stylized {
+            self.stylize_icons();
+        }
+        self.icons_stylized = false;
 
         let mut combined =
             image::DynamicImage::new_rgba8(self.target_size.width, self.target_size.height);
 
         for layer in InternalRenderLayer::all() {
+            if layer == InternalRenderLayer::Light {
+                if self.darkness > 0.0 {
+                    apply_light_map(&mut combined, self.layers.get(&layer), self.darkness);
+                }
+                continue;
+            }
+
             if let Some(img) = self.layers.get(&layer) {
                 imageops::overlay(&mut combined, img, 0, 0);
             }
@@ -1516,6 +3567,144 @@ impl RenderLayerBuffer {
 
         combined
     }
+
+    /// Adds the halo of a light at `position` to the light map, see [`RenderLayerBuffer::set_darkness`].
+    /// Oriented lights like vehicle headlights are skipped.
+    pub fn add_light(&mut self, position: &MapPosition, light: &LightDefinitionData) {
+        if light.type_ != LightDefinitionType::Basic
+            || light.size <= 0.0
+            || light.intensity <= 0.0
+            || self.skipped.contains(&InternalRenderLayer::Light)
+        {
+            return;
+        }
+
+        let shift = light.shift.map(MapPosition::from).unwrap_or_default();
+        let (center_x, center_y) = self.target_size.map_to_pixel(&(*position + shift));
+        let (center_x, center_y) = (f64::from(center_x), f64::from(center_y));
+        let radius = light.size / 2.0 * self.target_size.tile_res;
+        let [r, g, b, _] = light.color.map_or([1.0; 4], |color| color.to_rgba());
+        let intensity = light.intensity.min(1.0);
+
+        let (width, height) = (self.target_size.width, self.target_size.height);
+        let Some(layer) = self.get_layer(InternalRenderLayer::Light).as_mut_rgba8() else {
+            return;
+        };
+
+        let left = (center_x - radius).floor().max(0.0) as u32;
+        let top = (center_y - radius).floor().max(0.0) as u32;
+        let right = ((center_x + radius).ceil().max(0.0) as u32).min(width);
+        let bottom = ((center_y + radius).ceil().max(0.0) as u32).min(height);
+
+        for y in top..bottom {
+            for x in left..right {
+                let distance =
+                    (f64::from(x) + 0.5 - center_x).hypot(f64::from(y) + 0.5 - center_y) / radius;
+                if distance >= 1.0 {
+                    continue;
+                }
+
+                // lights are brightest in the center and fade out quadratically
+                let strength = (1.0 - distance).powi(2) * intensity * 255.0;
+                let pixel = layer.get_pixel_mut(x, y);
+                for (channel, color) in pixel.0.iter_mut().zip([r, g, b]) {
+                    *channel = channel.saturating_add((strength * color).round() as u8);
+                }
+                pixel.0[3] = 255;
+            }
+        }
+    }
+}
+
+/// Darkens `img` by `darkness`, the color channels of `light_map` brighten it again up to the original colors.
+fn apply_light_map(
+    img: &mut image::DynamicImage,
+    light_map: Option<&image::DynamicImage>,
+    darkness: f64,
+) {
+    let ambient = 1.0 - darkness.clamp(0.0, 1.0);
+    let Some(pixels) = img.as_mut_rgba8() else {
+        return;
+    };
+
+    for (x, y, pixel) in pixels.enumerate_pixels_mut() {
+        let light = light_map.map_or([0; 4], |map| map.get_pixel(x, y).0);
+
+        for (channel, light) in pixel.0.iter_mut().take(3).zip(light) {
+            let factor = (ambient + f64::from(light) / 255.0).min(1.0);
+            *channel = (f64::from(*channel) * factor).round() as u8;
+        }
+    }
+}
+
+/// Outline around the opaque pixels of `icons` that fades out over `radius` pixels.
+fn sdf_outline(
+    icons: &image::DynamicImage,
+    radius: f64,
+    color: image::Rgba<u8>,
+) -> Option<image::DynamicImage> {
+    if radius <= 0.0 {
+        return None;
+    }
+
+    let (width, height) = icons.dimensions();
+    let mask = image::ImageBuffer::from_fn(width, height, |x, y| {
+        let alpha = icons.get_pixel(x, y).0[3];
+        image::Luma([alpha])
+    });
+
+    let normalized_sdf = compute_f32_distance_field(&binary_image::of_byte_slice_with_threshold(
+        &mask,
+        width as u16,
+        height as u16,
+        1,
+    ))
+    .normalize_clamped_distances(0.0, radius as f32)?;
+
+    let [r, g, b, a] = color.0;
+    let pixels = normalized_sdf
+        .to_u8()
+        .iter()
+        .flat_map(|&x| {
+            if x == 255 {
+                [r, g, b, 0]
+            } else {
+                // squared falloff so the outline stays dark close to the icon
+                let falloff = 255.0 - (f64::from(x).powi(2) / 255.0).round();
+                [r, g, b, (falloff * f64::from(a) / 255.0).round() as u8]
+            }
+        })
+        .collect::<Vec<_>>();
+
+    image::RgbaImage::from_vec(width, height, pixels).map(Into::into)
+}
+
+/// Blurred silhouette of `icons` moved `offset` pixels to the bottom right.
+fn drop_shadow(
+    icons: &image::DynamicImage,
+    offset: f64,
+    color: image::Rgba<u8>,
+) -> image::DynamicImage {
+    let (width, height) = icons.dimensions();
+    let mask = image::GrayImage::from_fn(width, height, |x, y| {
+        image::Luma([icons.get_pixel(x, y).0[3]])
+    });
+    let blurred = imageproc::filter::gaussian_blur_f32(&mask, (offset / 2.0).max(0.5) as f32);
+
+    let shift = offset.round() as u32;
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let mut pixel = color;
+        pixel.0[3] = match (x.checked_sub(shift), y.checked_sub(shift)) {
+            (Some(src_x), Some(src_y)) => {
+                (u16::from(blurred.get_pixel(src_x, src_y).0[0]) * u16::from(color.0[3]) / 255)
+                    as u8
+            }
+            _ => 0,
+        };
+
+        pixel
+    })
+    .into()
 }
 
 use konst::{
@@ -1532,10 +3721,130 @@ pub const fn targeted_engine_version() -> Version {
     )
 }
 
+/// Start & end of every dash along a line of `length`, all in the same unit.
+fn dashes(length: f64, dash: f64, gap: f64) -> impl Iterator<Item = (f64, f64)> {
+    let count = (length / (dash + gap)).ceil() as u32;
+    (0..count).map(move |idx| {
+        let start = f64::from(idx) * (dash + gap);
+        (start, (start + dash).min(length))
+    })
+}
+
+/// Dash & gap length in tiles of the copper, red & green wires when drawn dashed.
+/// Copper wires stay solid, red ones get long dashes and green ones short dots.
+const fn wire_dash_pattern(wire: usize) -> Option<(f64, f64)> {
+    match wire {
+        1 => Some((0.5, 0.25)),
+        2 => Some((0.125, 0.125)),
+        _ => None,
+    }
+}
+
+/// Recolors a wire sprite to `color`, keeping its shading relative to the brightest pixel.
+fn recolor_wire(wire: &image::DynamicImage, color: image::Rgba<u8>) -> image::DynamicImage {
+    let luma = |image::Rgba([r, g, b, _]): image::Rgba<u8>| {
+        (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000
+    };
+
+    let mut img = wire.to_rgba8();
+    let brightest = img
+        .pixels()
+        .filter(|pixel| pixel.0[3] > 0)
+        .map(|pixel| luma(*pixel))
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    for pixel in img.pixels_mut() {
+        let brightness = luma(*pixel).min(brightest);
+        let channel = |value: u8| (u32::from(value) * brightness / brightest) as u8;
+
+        *pixel = image::Rgba([
+            channel(color.0[0]),
+            channel(color.0[1]),
+            channel(color.0[2]),
+            (u32::from(pixel.0[3]) * u32::from(color.0[3]) / 255) as u8,
+        ]);
+    }
+
+    img.into()
+}
+
+/// Clears the columns of a horizontal wire sprite spanning `length` tiles that fall into the gaps of the dash pattern.
+fn cut_wire_gaps(
+    wire: &image::DynamicImage,
+    length: f64,
+    dash: f64,
+    gap: f64,
+) -> image::DynamicImage {
+    let mut img = wire.to_rgba8();
+    let tiles_per_px = length / f64::from(img.width());
+
+    for (x, _, pixel) in img.enumerate_pixels_mut() {
+        if (f64::from(x) * tiles_per_px) % (dash + gap) >= dash {
+            pixel.0[3] = 0;
+        }
+    }
+
+    img.into()
+}
+
+/// Anti-aliased version of [`draw_thick_line`].
+fn draw_antialiased_thick_line(
+    layer: &mut image::RgbaImage,
+    (s_x, s_y): (f32, f32),
+    (e_x, e_y): (f32, f32),
+    thickness: i32,
+    color: image::Rgba<u8>,
+) {
+    let horizontal = (e_x - s_x).abs() > (e_y - s_y).abs();
+
+    for offset in 0..thickness {
+        let offset = offset - thickness / 2;
+        let (o_x, o_y) = if horizontal { (0, offset) } else { (offset, 0) };
+
+        imageproc::drawing::draw_antialiased_line_segment_mut(
+            layer,
+            (s_x.round() as i32 + o_x, s_y.round() as i32 + o_y),
+            (e_x.round() as i32 + o_x, e_y.round() as i32 + o_y),
+            color,
+            imageproc::pixelops::interpolate,
+        );
+    }
+}
+
+/// Draws a line that is `thickness` pixels wide by stacking parallel single pixel lines.
+fn draw_thick_line(
+    layer: &mut image::RgbaImage,
+    (s_x, s_y): (f32, f32),
+    (e_x, e_y): (f32, f32),
+    thickness: i32,
+    color: image::Rgba<u8>,
+) {
+    let horizontal = (e_x - s_x).abs() > (e_y - s_y).abs();
+
+    for offset in 0..thickness {
+        let offset = (offset - thickness / 2) as f32;
+        let (o_x, o_y) = if horizontal {
+            (0.0, offset)
+        } else {
+            (offset, 0.0)
+        };
+
+        imageproc::drawing::draw_line_segment_mut(
+            layer,
+            (s_x + o_x, s_y + o_y),
+            (e_x + o_x, e_y + o_y),
+            color,
+        );
+    }
+}
+
 #[cfg(test)]
 mod test {
     #![allow(clippy::unwrap_used)]
     use super::*;
+    use std::io::Read;
 
     #[must_use]
     fn load_data(name: &str) -> DataRaw {
@@ -1550,6 +3859,542 @@ mod test {
         serde_json::from_slice(&bytes).unwrap()
     }
 
+    const BROKEN_DUMP: &str = r#"{
+        "recipe-category": {
+            "valid": { "type": "recipe-category", "name": "valid" },
+            "broken": { "type": "recipe-category" }
+        }
+    }"#;
+
+    #[test]
+    fn pixel_rect_union_and_clamp() {
+        let a = PixelRect::new(-4, 2, 10, 6);
+        let b = PixelRect::new(20, 0, 4, 4);
+
+        assert!(!a.intersects(&b));
+        assert_eq!(a.union(&PixelRect::new(0, 0, 0, 0)), a);
+
+        let union = a.union(&b);
+        assert_eq!(union, PixelRect::new(-4, 0, 28, 8));
+        assert!(union.intersects(&a) && union.intersects(&b));
+
+        let clamped = union.clamp(16, 16);
+        assert_eq!(
+            (clamped.left, clamped.width(), clamped.height()),
+            (0, 16, 8)
+        );
+    }
+
+    #[test]
+    fn parse_icon_style() {
+        assert_eq!("none".parse(), Ok(IconStyle::Plain));
+        assert_eq!("outline".parse(), Ok(IconStyle::default()));
+        assert_eq!(
+            "outline:2:#ffffff80".parse(),
+            Ok(IconStyle::Outline {
+                width: 2,
+                color: image::Rgba([255, 255, 255, 128]),
+            })
+        );
+        assert_eq!(
+            "shadow:4".parse(),
+            Ok(IconStyle::DropShadow {
+                offset: 4,
+                color: IconStyle::DEFAULT_COLOR,
+            })
+        );
+
+        assert!("outline:wide".parse::<IconStyle>().is_err());
+        assert!("shadow:2:#000000:1".parse::<IconStyle>().is_err());
+        assert!("glow".parse::<IconStyle>().is_err());
+        assert!("outline:6:#aébcd".parse::<IconStyle>().is_err());
+    }
+
+    #[test]
+    fn parse_wire_palette() {
+        assert_eq!("colorblind".parse(), Ok(WirePalette::Colorblind));
+        assert_eq!(
+            "#ffffff, #ff0000,#0000ff80".parse(),
+            Ok(WirePalette::Custom([
+                image::Rgba([255, 255, 255, 255]),
+                image::Rgba([255, 0, 0, 255]),
+                image::Rgba([0, 0, 255, 128]),
+            ]))
+        );
+        assert_eq!(WirePalette::Default.colors(), WirePalette::DEFAULT_COLORS);
+
+        assert!("#ffffff,#ff0000".parse::<WirePalette>().is_err());
+        assert!("#ffffff,#ff0000,#aébcd".parse::<WirePalette>().is_err());
+        assert!("rainbow".parse::<WirePalette>().is_err());
+    }
+
+    #[test]
+    fn night_light_map() {
+        let size = TargetSize::new(
+            64,
+            64,
+            1.0,
+            MapPosition::Tuple(-1.0, -1.0),
+            MapPosition::Tuple(1.0, 1.0),
+        );
+        let mut layers = RenderLayerBuffer::new(size);
+        layers.fill_area(
+            &BoundingBox(MapPosition::Tuple(-1.0, -1.0), MapPosition::Tuple(1.0, 1.0)),
+            image::Rgba([200, 200, 200, 255]),
+            InternalRenderLayer::Entity,
+        );
+        layers.set_darkness(RenderOptions::NIGHT_DARKNESS);
+
+        let light: LightDefinitionData =
+            serde_json::from_str(r#"{"intensity": 1, "size": 1}"#).unwrap();
+        layers.add_light(&MapPosition::Tuple(-0.5, -0.5), &light);
+
+        let img = layers.combine();
+        let lit = img.get_pixel(16, 16).0;
+        let dark = img.get_pixel(48, 48).0;
+
+        assert_eq!(lit[..3], [200, 200, 200]);
+        assert_eq!(dark, image::Rgba([30, 30, 30, 255]).0);
+    }
+
+    #[test]
+    fn load_reports_failed_prototype() {
+        let err = DataRaw::load_from_bytes(BROKEN_DUMP.as_bytes()).unwrap_err();
+
+        assert!(
+            matches!(&err, Error::Prototype { section, name, path, .. } if section == "recipe-category" && name == "broken" && path == "name"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn load_lenient_skips_failed_prototype() {
+        let (data, skipped) = DataRaw::load_lenient_from_bytes(BROKEN_DUMP.as_bytes()).unwrap();
+
+        assert!(data
+            .recipe_category
+            .contains_key(&RecipeCategoryID::new("valid")));
+        assert!(!data
+            .recipe_category
+            .contains_key(&RecipeCategoryID::new("broken")));
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].name, "broken");
+    }
+
+    #[test]
+    fn load_lenient_reports_nested_path() {
+        let dump = r#"{
+            "quality": {
+                "rare": {
+                    "type": "quality",
+                    "name": "rare",
+                    "level": 2,
+                    "color": { "r": [], "g": 104, "b": 178 },
+                    "icon": "__quality__/graphics/icons/quality-rare.png"
+                }
+            }
+        }"#;
+
+        let (_, skipped) = DataRaw::load_lenient_from_bytes(dump.as_bytes()).unwrap();
+
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, "color.r");
+    }
+
+    #[test]
+    fn load_quality() {
+        let mut dump = DataRaw::skeleton().unwrap();
+        dump.insert(
+            "quality".to_owned(),
+            serde_json::json!({
+                "rare": {
+                    "type": "quality",
+                    "name": "rare",
+                    "level": 2,
+                    "color": { "r": 25, "g": 104, "b": 178 },
+                    "icon": "__quality__/graphics/icons/quality-rare.png"
+                }
+            }),
+        );
+
+        let data = DataUtil::new(serde_json::from_value(serde_json::Value::Object(dump)).unwrap());
+        let rare = data.get_quality("rare").unwrap();
+
+        assert_eq!(rare.level, 2);
+        assert!((rare.color.to_rgba()[2] - 178.0 / 255.0).abs() < f64::EPSILON);
+        assert!(data.get_quality("legendary").is_none());
+    }
+
+    #[test]
+    fn load_mod_settings() {
+        let mut dump = DataRaw::skeleton().unwrap();
+        dump.extend(
+            serde_json::from_str::<serde_json::Map<_, _>>(
+                r#"{
+                    "int-setting": {
+                        "stack-size": {
+                            "type": "int-setting",
+                            "name": "stack-size",
+                            "setting_type": "startup",
+                            "default_value": 50,
+                            "minimum_value": 1,
+                            "maximum_value": 1000
+                        }
+                    },
+                    "string-setting": {
+                        "difficulty": {
+                            "type": "string-setting",
+                            "name": "difficulty",
+                            "setting_type": "runtime-global",
+                            "default_value": "normal",
+                            "allowed_values": ["easy", "normal", "hard"]
+                        },
+                        "prefix": {
+                            "type": "string-setting",
+                            "name": "prefix",
+                            "setting_type": "startup",
+                            "default_value": "",
+                            "allow_blank": true,
+                            "allowed_values": {}
+                        }
+                    }
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        let data = DataUtil::new(serde_json::from_value(serde_json::Value::Object(dump)).unwrap());
+        let settings = data.mod_settings();
+
+        let stack_size = &settings.int_setting["stack-size"];
+        assert_eq!(stack_size.setting_type, mod_setting::SettingType::Startup);
+        assert_eq!(stack_size.default_value, 50);
+        assert_eq!(stack_size.maximum_value, Some(1000));
+
+        let difficulty = &settings.string_setting["difficulty"];
+        assert_eq!(
+            difficulty.setting_type,
+            mod_setting::SettingType::RuntimeGlobal
+        );
+        assert_eq!(difficulty.allowed_values.as_ref().unwrap().len(), 3);
+        assert!(settings.string_setting["prefix"]
+            .allowed_values
+            .as_ref()
+            .unwrap()
+            .is_empty());
+        assert!(settings.bool_setting.is_empty());
+    }
+
+    #[test]
+    fn load_filtered_skips_unused_prototypes() {
+        let mut dump = DataRaw::skeleton().unwrap();
+        dump.extend(
+            serde_json::from_str::<serde_json::Map<_, _>>(
+                r#"{
+                    "recipe-category": {
+                        "crafting": { "type": "recipe-category", "name": "crafting" }
+                    },
+                    "item": {
+                        "broken": { "type": "item" }
+                    }
+                }"#,
+            )
+            .unwrap(),
+        );
+        let dump = serde_json::to_vec(&dump).unwrap();
+
+        assert!(matches!(
+            DataRaw::load_from_bytes(&dump),
+            Err(Error::Prototype { .. })
+        ));
+
+        let data = DataRaw::load_filtered_from_bytes(&dump, &HashSet::new()).unwrap();
+        assert!(data
+            .recipe_category
+            .contains_key(&RecipeCategoryID::new("crafting")));
+
+        let names = HashSet::from(["broken".to_owned()]);
+        assert!(DataRaw::load_filtered_from_bytes(&dump, &names).is_err());
+    }
+
+    #[test]
+    fn filter_dump_keeps_dependencies() {
+        let dump = r#"{
+            "quality": { "normal": { "type": "quality", "name": "normal" } },
+            "assembling-machine": {
+                "assembler": { "type": "assembling-machine", "name": "assembler" }
+            },
+            "straight-rail": {
+                "straight-rail": {
+                    "type": "straight-rail",
+                    "name": "straight-rail",
+                    "placeable_by": { "item": "rail", "count": 1 }
+                }
+            },
+            "rail-planner": {
+                "rail": {
+                    "type": "rail-planner",
+                    "name": "rail",
+                    "place_result": "straight-rail",
+                    "rails": ["straight-rail", "curved-rail"]
+                }
+            },
+            "curved-rail-a": {
+                "curved-rail": { "type": "curved-rail-a", "name": "curved-rail" }
+            },
+            "item": {
+                "assembler": { "type": "item", "name": "assembler", "place_result": "assembler" },
+                "gear": { "type": "item", "name": "gear" },
+                "plate": { "type": "item", "name": "plate" },
+                "brick": {
+                    "type": "item",
+                    "name": "brick",
+                    "place_as_tile": { "result": "path", "condition_size": 1 }
+                }
+            },
+            "recipe": {
+                "gear": {
+                    "type": "recipe",
+                    "name": "gear",
+                    "ingredients": [{ "type": "item", "name": "plate", "amount": 2 }],
+                    "results": [{ "type": "item", "name": "gear", "amount": 1 }]
+                }
+            },
+            "tile": {
+                "path": { "type": "tile", "name": "path" },
+                "grass": { "type": "tile", "name": "grass" }
+            }
+        }"#;
+
+        let names = ["assembler", "gear", "straight-rail", "path"]
+            .map(str::to_owned)
+            .into();
+        let filtered: HashMap<String, HashMap<String, serde_json::Value>> =
+            serde_json::from_slice(&DataRaw::filter_dump(dump.as_bytes(), &names).unwrap())
+                .unwrap();
+
+        let kept = |section: &str| {
+            let mut names = filtered[section].keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(kept("quality"), ["normal"]);
+        assert_eq!(kept("assembling-machine"), ["assembler"]);
+        assert_eq!(kept("rail-planner"), ["rail"]);
+        assert_eq!(kept("curved-rail-a"), ["curved-rail"]);
+        assert_eq!(kept("item"), ["assembler", "brick", "gear"]);
+        assert_eq!(kept("recipe"), ["gear"]);
+        assert_eq!(kept("tile"), ["path"]);
+    }
+
+    #[test]
+    fn recipe_graph_queries() {
+        let mut dump = DataRaw::skeleton().unwrap();
+        dump.extend(
+            serde_json::from_str::<serde_json::Map<_, _>>(
+                r#"{
+                    "recipe": {
+                        "iron-gear-wheel": {
+                            "type": "recipe",
+                            "name": "iron-gear-wheel",
+                            "ingredients": [["iron-plate", 2]],
+                            "result": "iron-gear-wheel"
+                        },
+                        "gear-recycling": {
+                            "type": "recipe",
+                            "name": "gear-recycling",
+                            "category": "recycling",
+                            "ingredients": [{ "type": "item", "name": "iron-gear-wheel", "amount": 1 }],
+                            "results": [
+                                { "type": "item", "name": "iron-plate", "amount_min": 1, "amount_max": 3, "probability": 0.5 },
+                                { "type": "fluid", "name": "steam", "amount": 10 }
+                            ]
+                        }
+                    }
+                }"#,
+            )
+            .unwrap(),
+        );
+        let data =
+            DataUtil::new(DataRaw::load_from_bytes(&serde_json::to_vec(&dump).unwrap()).unwrap());
+
+        assert_eq!(
+            data.recipes_for_product("iron-plate"),
+            [&RecipeID::new("gear-recycling")]
+        );
+        assert_eq!(
+            data.recipes_for_product("steam"),
+            [&RecipeID::new("gear-recycling")]
+        );
+        assert_eq!(
+            data.recipes_using("iron-plate"),
+            [&RecipeID::new("iron-gear-wheel")]
+        );
+        assert!(data.recipes_for_product("copper-plate").is_empty());
+
+        assert_eq!(
+            data.ingredients_of("iron-gear-wheel").unwrap(),
+            [recipe::RecipeComponent {
+                kind: recipe::ComponentType::Item,
+                name: "iron-plate".to_owned(),
+                amount: 2.0,
+            }]
+        );
+        assert_eq!(
+            data.products_of("gear-recycling").unwrap(),
+            [
+                recipe::RecipeComponent {
+                    kind: recipe::ComponentType::Item,
+                    name: "iron-plate".to_owned(),
+                    amount: 1.0,
+                },
+                recipe::RecipeComponent {
+                    kind: recipe::ComponentType::Fluid,
+                    name: "steam".to_owned(),
+                    amount: 10.0,
+                }
+            ]
+        );
+        assert!(data.ingredients_of("missing").is_none());
+        assert!(data.machines_for_recipe("iron-gear-wheel").is_empty());
+    }
+
+    #[test]
+    fn sound_round_trip() {
+        let sounds = [
+            serde_json::json!({
+                "category": "game-effect",
+                "aggregation": { "max_count": 3, "remove": true, "count_already_playing": true },
+                "variations": [
+                    { "filename": "__base__/sound/open-1.ogg", "volume": 0.5 },
+                    "__base__/sound/open-2.ogg"
+                ]
+            }),
+            serde_json::json!({
+                "filename": "__base__/sound/machine-open.ogg",
+                "volume": 0.5,
+                "modifiers": { "type": "main-menu", "volume_multiplier": 1.5 }
+            }),
+            serde_json::json!([
+                { "filename": "__base__/sound/car-metal-impact-2.ogg", "volume": 0.5 },
+                { "filename": "__base__/sound/car-metal-impact-3.ogg", "volume": 0.5 }
+            ]),
+        ];
+
+        for json in sounds {
+            let sound = serde_json::from_value::<types::Sound>(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&sound).unwrap(), json);
+        }
+
+        let layered = serde_json::json!({
+            "layers": [
+                { "filename": "__base__/sound/fight/tank-cannon-1.ogg" },
+                { "filename": "__base__/sound/fight/tank-cannon-2.ogg", "volume": 0.5 }
+            ]
+        });
+        let sound = serde_json::from_value::<types::LayeredSound>(layered.clone()).unwrap();
+        assert!(matches!(&sound, types::LayeredSound::Layers { layers } if layers.len() == 2));
+        assert_eq!(serde_json::to_value(&sound).unwrap(), layered);
+    }
+
+    #[cfg(feature = "preserve_unknown")]
+    #[test]
+    fn preserve_unknown_fields() {
+        let json = serde_json::json!({
+            "type": "fluid",
+            "name": "water",
+            "icon": "__base__/graphics/icons/fluid/water.png",
+            "icon_size": 64,
+            "default_temperature": 15,
+            "base_color": { "r": 0, "g": 0.34, "b": 0.6 },
+            "flow_color": { "r": 0.7, "g": 0.7, "b": 0.7 },
+            "some_future_field": { "nested": [1, 2, 3] },
+            "another_one": "hello"
+        });
+
+        let fluid = serde_json::from_value::<fluid::FluidPrototype>(json).unwrap();
+        assert_eq!(fluid.extra.len(), 2);
+        assert!(fluid.extra.contains_key("some_future_field"));
+
+        let value = serde_json::to_value(&fluid).unwrap();
+        assert_eq!(value["some_future_field"]["nested"][2], 3);
+        assert_eq!(value["another_one"], "hello");
+        assert_eq!(value["name"], "water");
+    }
+
+    #[cfg(feature = "preserve_unknown")]
+    #[test]
+    fn preserve_unknown_sections() {
+        let mut dump = DataRaw::skeleton().unwrap();
+        dump.extend(
+            serde_json::from_str::<serde_json::Map<_, _>>(
+                r#"{
+                    "fluid": {
+                        "water": {
+                            "type": "fluid",
+                            "name": "water",
+                            "icon": "__base__/graphics/icons/fluid/water.png",
+                            "default_temperature": 15,
+                            "base_color": { "r": 0, "g": 0.34, "b": 0.6 },
+                            "flow_color": { "r": 0.7, "g": 0.7, "b": 0.7 }
+                        }
+                    },
+                    "achievement": {
+                        "so-long-and-thanks-for-all-the-fish": {
+                            "type": "achievement",
+                            "name": "so-long-and-thanks-for-all-the-fish",
+                            "icon": "__base__/graphics/achievement/so-long-and-thanks-for-all-the-fish.png",
+                            "icon_size": 128
+                        }
+                    },
+                    "shortcut": {}
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        let data = DataRaw::load_from_bytes(&serde_json::to_vec(&dump).unwrap()).unwrap();
+        assert_eq!(data.other.len(), 2);
+        assert!(data.other.contains_key("achievement"));
+        assert!(data.other.contains_key("shortcut"));
+        assert!(data.fluid.fluid.contains_key(&FluidID::new("water")));
+
+        let value = serde_json::to_value(&data).unwrap();
+        assert_eq!(
+            value["achievement"]["so-long-and-thanks-for-all-the-fish"]["icon_size"],
+            128
+        );
+        assert_eq!(value["fluid"]["water"]["name"], "water");
+    }
+
+    #[test]
+    fn parse_background() {
+        assert_eq!("lab".parse(), Ok(Background::LabTiles));
+        assert_eq!("Transparent".parse(), Ok(Background::Transparent));
+        assert_eq!("space".parse(), Ok(Background::Starfield));
+        assert_eq!(
+            "grid".parse(),
+            Ok(Background::Grid {
+                spacing: 1,
+                chunk_lines: true
+            })
+        );
+        assert_eq!(
+            "#FF8000".parse(),
+            Ok(Background::Solid(image::Rgba([0xff, 0x80, 0x00, 0xff])))
+        );
+        assert_eq!(
+            "#ff800040".parse(),
+            Ok(Background::Solid(image::Rgba([0xff, 0x80, 0x00, 0x40])))
+        );
+        assert!("#ff80".parse::<Background>().is_err());
+        assert!("#gg8000".parse::<Background>().is_err());
+        assert!("#+f8000".parse::<Background>().is_err());
+        assert!("#aébcd".parse::<Background>().is_err());
+        assert!("checkered".parse::<Background>().is_err());
+    }
+
     mod deserialize {
         use super::load_data;
 
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use serde_helper as helper;
+use types::{Color, FactorioArray};
+
+/// [`Types/SettingType`](https://lua-api.factorio.com/latest/prototypes/ModSettingPrototype.html#setting_type)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SettingType {
+    Startup,
+    RuntimeGlobal,
+    RuntimePerUser,
+}
+
+/// [`Prototypes/ModSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/ModSettingPrototype.html)
+pub type ModSettingPrototype<T> = crate::BasePrototype<ModSettingPrototypeData<T>>;
+
+/// [`Prototypes/ModSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/ModSettingPrototype.html)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModSettingPrototypeData<T> {
+    pub setting_type: SettingType,
+
+    #[serde(
+        default,
+        deserialize_with = "helper::bool_deserializer",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub hidden: bool,
+
+    #[serde(flatten)]
+    child: T,
+}
+
+impl<T> std::ops::Deref for ModSettingPrototypeData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.child
+    }
+}
+
+/// [`Prototypes/BoolSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/BoolSettingPrototype.html)
+pub type BoolSettingPrototype = ModSettingPrototype<BoolSettingPrototypeData>;
+
+/// [`Prototypes/BoolSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/BoolSettingPrototype.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BoolSettingPrototypeData {
+    #[serde(deserialize_with = "helper::bool_deserializer")]
+    pub default_value: bool,
+
+    pub forced_value: Option<bool>,
+}
+
+/// [`Prototypes/IntSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/IntSettingPrototype.html)
+pub type IntSettingPrototype = ModSettingPrototype<IntSettingPrototypeData>;
+
+/// [`Prototypes/IntSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/IntSettingPrototype.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IntSettingPrototypeData {
+    #[serde(deserialize_with = "helper::truncating_deserializer")]
+    pub default_value: i64,
+
+    #[serde(default, deserialize_with = "helper::truncating_opt_deserializer")]
+    pub minimum_value: Option<i64>,
+
+    #[serde(default, deserialize_with = "helper::truncating_opt_deserializer")]
+    pub maximum_value: Option<i64>,
+
+    pub allowed_values: Option<FactorioArray<i64>>,
+}
+
+/// [`Prototypes/DoubleSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/DoubleSettingPrototype.html)
+pub type DoubleSettingPrototype = ModSettingPrototype<DoubleSettingPrototypeData>;
+
+/// [`Prototypes/DoubleSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/DoubleSettingPrototype.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DoubleSettingPrototypeData {
+    pub default_value: f64,
+    pub minimum_value: Option<f64>,
+    pub maximum_value: Option<f64>,
+    pub allowed_values: Option<FactorioArray<f64>>,
+}
+
+/// [`Prototypes/StringSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/StringSettingPrototype.html)
+pub type StringSettingPrototype = ModSettingPrototype<StringSettingPrototypeData>;
+
+/// [`Prototypes/StringSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/StringSettingPrototype.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StringSettingPrototypeData {
+    pub default_value: String,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_blank: bool,
+
+    pub allowed_values: Option<FactorioArray<String>>,
+}
+
+/// [`Prototypes/ColorSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/ColorSettingPrototype.html)
+pub type ColorSettingPrototype = ModSettingPrototype<ColorSettingPrototypeData>;
+
+/// [`Prototypes/ColorSettingPrototype`](https://lua-api.factorio.com/latest/prototypes/ColorSettingPrototype.html)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ColorSettingPrototypeData {
+    pub default_value: Color,
+}
+
+/// All mod settings of the dump by name. Only present if a mod defines any of its type.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AllTypes {
+    #[serde(default)]
+    pub bool_setting: HashMap<String, BoolSettingPrototype>,
+
+    #[serde(default)]
+    pub int_setting: HashMap<String, IntSettingPrototype>,
+
+    #[serde(default)]
+    pub double_setting: HashMap<String, DoubleSettingPrototype>,
+
+    #[serde(default)]
+    pub string_setting: HashMap<String, StringSettingPrototype>,
+
+    #[serde(default)]
+    pub color_setting: HashMap<String, ColorSettingPrototype>,
+}
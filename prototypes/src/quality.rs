@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use types::{Color, Icon, RenderableGraphics};
+
+/// [`Prototypes/QualityPrototype`](https://lua-api.factorio.com/latest/prototypes/QualityPrototype.html)
+pub type QualityPrototype = crate::BasePrototype<QualityPrototypeData>;
+
+/// [`Prototypes/QualityPrototype`](https://lua-api.factorio.com/latest/prototypes/QualityPrototype.html)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QualityPrototypeData {
+    pub level: u32,
+    pub color: Color,
+
+    #[serde(flatten)]
+    pub icon: Icon,
+}
+
+impl QualityPrototypeData {
+    pub fn get_icon(
+        &self,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.icon.render(scale, used_mods, image_cache, &())
+    }
+}
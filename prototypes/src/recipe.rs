@@ -44,63 +44,42 @@ impl RecipePrototypeData {
         items: &crate::item::AllTypes,
         fluids: &crate::fluid::AllTypes,
     ) -> Option<types::GraphicsOutput> {
-        if let Some(icon) = self.icon.as_ref() {
-            return icon.render(scale, used_mods, image_cache, &());
+        if let Some(icon) = self
+            .icon
+            .as_ref()
+            .and_then(|icon| icon.render(scale, used_mods, image_cache, &()))
+        {
+            return Some(icon);
         }
 
+        // recipes without (a working) icon show the icon of their main product
         let recipe = self.recipe.get_data();
 
         match &recipe.results {
             RecipeDataResult::Multiple { results } => {
-                if results.is_empty() {
-                    return None;
-                }
-
-                if results.len() == 1 {
-                    return match results.first() {
-                        Some(ProductPrototype::Specific(
-                            SpecificProductPrototype::FluidProductPrototype { name, .. },
-                        )) => fluids.get_icon(name, scale, used_mods, image_cache),
-                        Some(
-                            ProductPrototype::SimpleItem(name, _)
-                            | ProductPrototype::UntaggedItem(ItemProductPrototype { name, .. })
-                            | ProductPrototype::Specific(
-                                SpecificProductPrototype::ItemProductPrototype(
-                                    ItemProductPrototype { name, .. },
-                                ),
-                            ),
-                        ) => items.get_icon(name, scale, used_mods, image_cache),
-                        _ => None,
-                    };
-                }
-
-                let main_product = recipe.main_product.as_ref()?;
-
-                for product in results {
-                    match product {
-                        ProductPrototype::Specific(
-                            SpecificProductPrototype::FluidProductPrototype { name, .. },
-                        ) => {
-                            if **name == *main_product {
-                                return fluids.get_icon(name, scale, used_mods, image_cache);
-                            }
-                        }
-                        ProductPrototype::SimpleItem(name, _)
-                        | ProductPrototype::UntaggedItem(ItemProductPrototype { name, .. })
-                        | ProductPrototype::Specific(
-                            SpecificProductPrototype::ItemProductPrototype(ItemProductPrototype {
-                                name,
-                                ..
-                            }),
-                        ) => {
-                            if **name == *main_product {
-                                return items.get_icon(name, scale, used_mods, image_cache);
-                            }
-                        }
-                    }
+                let main_product = recipe
+                    .main_product
+                    .as_deref()
+                    .filter(|name| !name.is_empty());
+
+                let product = match main_product {
+                    Some(main_product) => results
+                        .iter()
+                        .find(|product| product.name() == main_product),
+                    None if results.len() == 1 => results.first(),
+                    None => None,
+                }?;
+
+                match product {
+                    ProductPrototype::Specific(
+                        SpecificProductPrototype::FluidProductPrototype { name, .. },
+                    ) => fluids.get_icon(name, scale, used_mods, image_cache),
+                    ProductPrototype::SimpleItem(name, _)
+                    | ProductPrototype::UntaggedItem(ItemProductPrototype { name, .. })
+                    | ProductPrototype::Specific(SpecificProductPrototype::ItemProductPrototype(
+                        ItemProductPrototype { name, .. },
+                    )) => items.get_icon(name, scale, used_mods, image_cache),
                 }
-
-                None
             }
             RecipeDataResult::Single { result, .. } => {
                 items.get_icon(result, scale, used_mods, image_cache)
@@ -199,6 +178,43 @@ impl DifficultyRecipeData {
     }
 }
 
+/// Whether a [`RecipeComponent`] is an item or a fluid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentType {
+    Item,
+    Fluid,
+}
+
+/// Ingredient or product of a recipe, independent of the notation used in the prototype.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecipeComponent {
+    #[serde(rename = "type")]
+    pub kind: ComponentType,
+    pub name: String,
+
+    /// Amount per craft. For products this is the expected amount, including the probability.
+    pub amount: f64,
+}
+
+impl RecipeComponent {
+    fn item(name: &ItemID, amount: f64) -> Self {
+        Self {
+            kind: ComponentType::Item,
+            name: name.to_string(),
+            amount,
+        }
+    }
+
+    fn fluid(name: &FluidID, amount: f64) -> Self {
+        Self {
+            kind: ComponentType::Fluid,
+            name: name.to_string(),
+            amount,
+        }
+    }
+}
+
 /// [`Types/RecipeData`](https://lua-api.factorio.com/latest/types/RecipeData.html)
 #[skip_serializing_none]
 #[derive(Debug, Deserialize, Serialize)]
@@ -314,6 +330,64 @@ pub struct RecipeData {
     pub unlock_results: bool,
 }
 
+impl RecipeData {
+    #[must_use]
+    pub fn ingredients(&self) -> Vec<RecipeComponent> {
+        self.ingredients
+            .iter()
+            .map(|ingredient| match ingredient {
+                IngredientPrototype::SimpleItem(name, amount)
+                | IngredientPrototype::UntaggedItem(ItemIngredientPrototype {
+                    name, amount, ..
+                })
+                | IngredientPrototype::Specific(
+                    SpecificIngredientPrototype::ItemIngredientPrototype(ItemIngredientPrototype {
+                        name,
+                        amount,
+                        ..
+                    }),
+                ) => RecipeComponent::item(name, f64::from(*amount)),
+                IngredientPrototype::Specific(
+                    SpecificIngredientPrototype::FluidIngredientPrototype { name, amount, .. },
+                ) => RecipeComponent::fluid(name, *amount),
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn products(&self) -> Vec<RecipeComponent> {
+        let results = match &self.results {
+            RecipeDataResult::Multiple { results } => results,
+            RecipeDataResult::Single {
+                result,
+                result_count,
+            } => return vec![RecipeComponent::item(result, f64::from(*result_count))],
+        };
+
+        results
+            .iter()
+            .map(|product| match product {
+                ProductPrototype::SimpleItem(name, amount) => {
+                    RecipeComponent::item(name, f64::from(*amount))
+                }
+                ProductPrototype::UntaggedItem(product)
+                | ProductPrototype::Specific(SpecificProductPrototype::ItemProductPrototype(
+                    product,
+                )) => RecipeComponent::item(
+                    &product.name,
+                    product.amount.expected() * product.probability,
+                ),
+                ProductPrototype::Specific(SpecificProductPrototype::FluidProductPrototype {
+                    name,
+                    amount,
+                    probability,
+                    ..
+                }) => RecipeComponent::fluid(name, amount.expected() * probability),
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum RecipeDataResult {
@@ -413,6 +487,23 @@ pub enum ProductPrototype {
     UntaggedItem(ItemProductPrototype),
 }
 
+impl ProductPrototype {
+    /// Name of the produced item or fluid.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Specific(SpecificProductPrototype::FluidProductPrototype { name, .. }) => {
+                name.as_str()
+            }
+            Self::SimpleItem(name, _)
+            | Self::UntaggedItem(ItemProductPrototype { name, .. })
+            | Self::Specific(SpecificProductPrototype::ItemProductPrototype(
+                ItemProductPrototype { name, .. },
+            )) => name.as_str(),
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "type")]
@@ -478,6 +569,20 @@ pub enum ProductFluidAmount {
     Range { amount_min: f64, amount_max: f64 },
 }
 
+impl ProductFluidAmount {
+    /// Average amount, assuming an even distribution for ranges.
+    #[must_use]
+    pub const fn expected(&self) -> f64 {
+        match self {
+            Self::Static { amount } => *amount,
+            Self::Range {
+                amount_min,
+                amount_max,
+            } => f64::midpoint(*amount_min, *amount_max),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum ProductItemAmount {
@@ -494,6 +599,20 @@ pub enum ProductItemAmount {
     },
 }
 
+impl ProductItemAmount {
+    /// Average amount, assuming an even distribution for ranges.
+    #[must_use]
+    pub fn expected(&self) -> f64 {
+        match self {
+            Self::Static { amount } => f64::from(*amount),
+            Self::Range {
+                amount_min,
+                amount_max,
+            } => f64::midpoint(f64::from(*amount_min), f64::from(*amount_max)),
+        }
+    }
+}
+
 namespace_struct! {
     AllTypes,
     RecipeID,
@@ -600,4 +719,40 @@ mod test {
 
         let _ = serde_json::from_str::<RecipePrototype>(recipe).unwrap();
     }
+
+    #[test]
+    fn deserialize_layered_icon_without_size() {
+        let recipe = r#"{
+            "type": "recipe",
+            "name": "kovarex-enrichment-process",
+            "energy_required": 60,
+            "category": "centrifuging",
+            "ingredients": [["uranium-235", 40], ["uranium-238", 5]],
+            "icons": [
+              { "icon": "__base__/graphics/icons/uranium-processing.png" },
+              {
+                "icon": "__base__/graphics/icons/uranium-235.png",
+                "scale": 0.25,
+                "shift": [-8, 8],
+                "tint": [1, 1, 1, 0.5]
+              }
+            ],
+            "main_product": "uranium-235",
+            "results": [["uranium-235", 41], ["uranium-238", 2]]
+          }"#;
+
+        let recipe = serde_json::from_str::<RecipePrototype>(recipe).unwrap();
+        let Some(Icon::Array { icons, .. }) = &recipe.icon else {
+            panic!("expected layered icons");
+        };
+        assert_eq!(icons.len(), 2);
+        assert!(icons.iter().all(|layer| layer.icon_size.is_none()));
+
+        let data = recipe.recipe.get_data();
+        let RecipeDataResult::Multiple { results } = &data.results else {
+            panic!("expected multiple results");
+        };
+        let main_product = data.main_product.as_deref().unwrap();
+        assert!(results.iter().any(|product| product.name() == main_product));
+    }
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use types::{Icon, RenderableGraphics};
+
+/// [`Prototypes/SpaceLocationPrototype`](https://lua-api.factorio.com/latest/prototypes/SpaceLocationPrototype.html)
+pub type SpaceLocationPrototype = crate::BasePrototype<SpaceLocationPrototypeData>;
+
+/// [`Prototypes/PlanetPrototype`](https://lua-api.factorio.com/latest/prototypes/PlanetPrototype.html)
+pub type PlanetPrototype = crate::BasePrototype<SpaceLocationPrototypeData>;
+
+/// [`Prototypes/SpaceLocationPrototype`](https://lua-api.factorio.com/latest/prototypes/SpaceLocationPrototype.html)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SpaceLocationPrototypeData {
+    #[serde(flatten)]
+    pub icon: Option<Icon>,
+}
+
+impl SpaceLocationPrototypeData {
+    pub fn get_icon(
+        &self,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.icon
+            .as_ref()?
+            .render(scale, used_mods, image_cache, &())
+    }
+}
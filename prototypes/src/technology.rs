@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use serde_helper as helper;
+use types::{
+    EntityID, FactorioArray, FluidID, Icon, ItemID, RecipeID, TechnologyID,
+    EXPECTED_TECHNOLOGY_ICON_SIZE,
+};
+
+use crate::helper_macro::namespace_struct;
+
+/// [`Prototypes/TechnologyPrototype`](https://lua-api.factorio.com/latest/prototypes/TechnologyPrototype.html)
+pub type TechnologyPrototype = crate::BasePrototype<TechnologyPrototypeData>;
+
+/// [`Prototypes/TechnologyPrototype`](https://lua-api.factorio.com/latest/prototypes/TechnologyPrototype.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TechnologyPrototypeData {
+    #[serde(flatten)]
+    pub icon: Icon,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prerequisites: FactorioArray<TechnologyID>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: FactorioArray<Modifier>,
+
+    /// Either `unit` or `research_trigger` is set.
+    pub unit: Option<TechnologyUnit>,
+    pub research_trigger: Option<ResearchTrigger>,
+
+    pub max_level: Option<MaxLevel>,
+
+    #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+    pub enabled: bool,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub hidden: bool,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub upgrade: bool,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub essential: bool,
+
+    #[serde(default, skip_serializing_if = "helper::is_default")]
+    pub ignore_tech_cost_multiplier: bool,
+}
+
+impl TechnologyPrototypeData {
+    pub fn get_icon(
+        &self,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.icon
+            .render_expected(EXPECTED_TECHNOLOGY_ICON_SIZE, scale, used_mods, image_cache)
+    }
+
+    /// Recipes unlocked by researching the technology.
+    pub fn unlocked_recipes(&self) -> impl Iterator<Item = &RecipeID> {
+        self.effects.iter().filter_map(|effect| match effect {
+            Modifier::UnlockRecipe { recipe } => Some(recipe),
+            Modifier::Other(_) => None,
+        })
+    }
+}
+
+/// [`Types/TechnologyUnit`](https://lua-api.factorio.com/latest/types/TechnologyUnit.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TechnologyUnit {
+    /// Either `count` or `count_formula` is set.
+    #[serde(default, deserialize_with = "helper::truncating_opt_deserializer")]
+    pub count: Option<u64>,
+    pub count_formula: Option<String>,
+
+    pub time: f64,
+
+    pub ingredients: FactorioArray<ResearchIngredient>,
+}
+
+/// [`Types/ResearchIngredient`](https://lua-api.factorio.com/latest/types/ResearchIngredient.html)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResearchIngredient(
+    pub ItemID,
+    #[serde(deserialize_with = "helper::truncating_deserializer")] pub u16,
+);
+
+/// [`Types/TechnologyTrigger`](https://lua-api.factorio.com/latest/types/TechnologyTrigger.html)
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ResearchTrigger {
+    MineEntity {
+        entity: EntityID,
+    },
+    CraftItem {
+        item: ItemID,
+
+        #[serde(
+            default = "helper::u32_1",
+            skip_serializing_if = "helper::is_1_u32",
+            deserialize_with = "helper::truncating_deserializer"
+        )]
+        count: u32,
+    },
+    CraftFluid {
+        fluid: FluidID,
+
+        #[serde(default = "helper::f64_1", skip_serializing_if = "helper::is_1_f64")]
+        amount: f64,
+    },
+    SendItemToOrbit {
+        item: ItemID,
+    },
+    CaptureSpawner {
+        entity: Option<EntityID>,
+    },
+    BuildEntity {
+        entity: EntityID,
+    },
+    CreateSpacePlatform,
+}
+
+/// [`Types/Modifier`](https://lua-api.factorio.com/latest/types/Modifier.html)
+///
+/// Only recipe unlocks are modelled, all other effects are kept as they are.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Modifier {
+    UnlockRecipe {
+        recipe: RecipeID,
+    },
+
+    #[serde(untagged)]
+    Other(serde_json::Map<String, serde_json::Value>),
+}
+
+/// `max_level` of [`TechnologyPrototype`]s, either a level or `"infinite"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MaxLevel {
+    Level(u32),
+    Infinite(Infinite),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Infinite {
+    Infinite,
+}
+
+namespace_struct! {
+    AllTypes,
+    TechnologyID,
+    "technology"
+}
+
+impl AllTypes {
+    pub fn get_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.technology
+            .get(&TechnologyID::new(name))
+            .and_then(|tech| tech.get_icon(scale, used_mods, image_cache))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn deserialize_technology() {
+        let tech = r#"{
+            "type": "technology",
+            "name": "physical-projectile-damage-7",
+            "icons": [
+              { "icon": "__base__/graphics/technology/physical-projectile-damage-2.png", "icon_size": 256 },
+              { "icon": "__core__/graphics/icons/technology/constants/constant-damage.png", "icon_size": 128, "scale": 0.5, "shift": [50, 50] }
+            ],
+            "effects": [
+              { "type": "ammo-damage", "ammo_category": "bullet", "modifier": 0.4 },
+              { "type": "unlock-recipe", "recipe": "uranium-rounds-magazine" },
+              { "type": "cliff-deconstruction-enabled", "modifier": true }
+            ],
+            "prerequisites": ["physical-projectile-damage-6", "space-science-pack"],
+            "unit": {
+              "count_formula": "2^(L-7)*1000",
+              "ingredients": [["automation-science-pack", 1], ["space-science-pack", 1]],
+              "time": 60
+            },
+            "max_level": "infinite",
+            "upgrade": true
+          }"#;
+
+        let tech = serde_json::from_str::<TechnologyPrototype>(tech).unwrap();
+        assert_eq!(tech.prerequisites.len(), 2);
+        assert_eq!(tech.max_level, Some(MaxLevel::Infinite(Infinite::Infinite)));
+        assert_eq!(
+            tech.unlocked_recipes().collect::<Vec<_>>(),
+            [&RecipeID::new("uranium-rounds-magazine")]
+        );
+        assert!(
+            matches!(&tech.effects[2], Modifier::Other(other) if other["type"] == "cliff-deconstruction-enabled")
+        );
+
+        let unit = tech.unit.as_ref().unwrap();
+        assert_eq!(unit.count, None);
+        assert_eq!(unit.ingredients[1].0, ItemID::new("space-science-pack"));
+
+        let tech = r#"{
+            "type": "technology",
+            "name": "electronics",
+            "icon": "__base__/graphics/technology/electronics.png",
+            "icon_size": 256,
+            "effects": [{ "type": "unlock-recipe", "recipe": "lab" }],
+            "research_trigger": { "type": "craft-item", "item": "copper-plate", "count": 10 }
+          }"#;
+
+        let tech = serde_json::from_str::<TechnologyPrototype>(tech).unwrap();
+        assert!(tech.unit.is_none());
+        assert!(matches!(
+            tech.research_trigger,
+            Some(ResearchTrigger::CraftItem { count: 10, .. })
+        ));
+    }
+}
@@ -4,8 +4,8 @@ use serde_with::skip_serializing_none;
 
 use serde_helper as helper;
 use types::{
-    CollisionMask, Color, FactorioArray, Icon, ImageCache, MapPosition, PlaceableBy,
-    RenderableGraphics, TileID, TileRenderOpts, TileSprite, TileSpriteWithProbability,
+    CollisionMaskConnector, Color, FactorioArray, Icon, ImageCache, MapPosition, PlaceableBy,
+    RenderableGraphics, Sound, TileID, TileRenderOpts, TileSprite, TileSpriteWithProbability,
 };
 
 use crate::{helper_macro::namespace_struct, InternalRenderLayer};
@@ -46,7 +46,7 @@ impl TilePrototype {
 #[skip_serializing_none]
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TilePrototypeData {
-    pub collision_mask: CollisionMask,
+    pub collision_mask: CollisionMaskConnector,
     pub layer: u8,
 
     pub variants: TileTransitionsVariants,
@@ -71,9 +71,10 @@ pub struct TilePrototypeData {
     #[serde(default = "Color::white", skip_serializing_if = "Color::is_white")]
     pub tint: Color,
 
-    // pub walking_sound: Option<Sound>,
+    pub walking_sound: Option<Sound>,
+    pub mined_sound: Option<Sound>,
+    // not implemented
     // pub build_sound: Option<Sound or TileBuildSound>,
-    // pub mined_sound: Option<Sound>,
     #[serde(default = "helper::f64_1", skip_serializing_if = "helper::is_1_f64")]
     pub walking_speed_modifier: f64,
     #[serde(default = "helper::f64_1", skip_serializing_if = "helper::is_1_f64")]
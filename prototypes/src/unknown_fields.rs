@@ -0,0 +1,97 @@
+//! Round-trip support for prototype fields that are not modelled (yet), enabled by the `preserve_unknown` feature.
+//!
+//! Only the top level fields of a prototype are preserved, unknown fields of nested types are still dropped.
+
+use serde::{ser::Error as _, Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use types::{LocalisedString, Order};
+
+use crate::BasePrototype;
+
+/// Serde representation of a [`BasePrototype`] that captures every field the base doesn't know.
+#[skip_serializing_none]
+#[derive(Deserialize)]
+pub struct RawPrototype<T> {
+    #[serde(rename = "type")]
+    type_: String,
+
+    name: String,
+
+    #[serde(default)]
+    order: Order,
+
+    localised_name: Option<LocalisedString>,
+    localised_description: Option<LocalisedString>,
+
+    #[serde(flatten)]
+    child: T,
+
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+impl<T: Serialize> From<RawPrototype<T>> for BasePrototype<T> {
+    fn from(raw: RawPrototype<T>) -> Self {
+        let mut extra = raw.extra;
+
+        // everything the child writes back on its own doesn't need to be kept around
+        if let Ok(Value::Object(known)) = serde_json::to_value(&raw.child) {
+            extra.retain(|key, _| !known.contains_key(key));
+        }
+
+        Self {
+            type_: raw.type_,
+            name: raw.name,
+            order: raw.order,
+            localised_name: raw.localised_name,
+            localised_description: raw.localised_description,
+            child: raw.child,
+            extra,
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize)]
+struct KnownFields<'a, T> {
+    #[serde(rename = "type")]
+    type_: &'a str,
+
+    name: &'a str,
+    order: Option<&'a Order>,
+
+    localised_name: Option<&'a LocalisedString>,
+    localised_description: Option<&'a LocalisedString>,
+
+    #[serde(flatten)]
+    child: &'a T,
+}
+
+impl<T: Serialize> Serialize for BasePrototype<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let known = KnownFields {
+            type_: &self.type_,
+            name: &self.name,
+            order: (!self.order.is_empty()).then_some(&self.order),
+            localised_name: self.localised_name.as_ref(),
+            localised_description: self.localised_description.as_ref(),
+            child: &self.child,
+        };
+
+        let Value::Object(mut fields) = serde_json::to_value(known).map_err(S::Error::custom)?
+        else {
+            return Err(S::Error::custom("prototype did not serialize to an object"));
+        };
+
+        // modelled fields win, unknown ones only fill the gaps
+        for (key, value) in &self.extra {
+            if !fields.contains_key(key) {
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+
+        fields.serialize(serializer)
+    }
+}
@@ -0,0 +1,224 @@
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use imageproc::{drawing::draw_filled_rect_mut, rect::Rect};
+
+use blueprint::Blueprint;
+use mod_util::UsedMods;
+use prototypes::{DataUtil, TargetSize};
+use types::ImageCache;
+
+use crate::{
+    bom::BillOfMaterials,
+    overlay,
+    text::{format_count, Font},
+};
+
+pub(crate) const FOOTER_COLOR: Rgba<u8> = Rgba([0x24, 0x24, 0x24, 0xff]);
+pub(crate) const SLOT_COLOR: Rgba<u8> = Rgba([0x3e, 0x3e, 0x3e, 0xff]);
+pub(crate) const TEXT_COLOR: Rgba<u8> = Rgba([0xff, 0xe6, 0xc0, 0xff]);
+
+/// Scale the item icons of the bill of materials get rendered at before resizing them to the slots.
+const BOM_ICON_SCALE: f64 = 0.5;
+
+/// Lengths in tiles the scale bar can have.
+const SCALE_STEPS: [u32; 10] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000];
+
+/// Appends a footer below the render that shows the size of the blueprint in tiles
+/// and a scale bar mapping tiles to pixels.
+///
+/// The footer is only added at the bottom so pixel positions of the render stay valid.
+#[must_use]
+pub fn add_footer(img: &DynamicImage, size: &TargetSize, font: &Font) -> DynamicImage {
+    let width = img.width();
+    let footer_height = (width / 32).clamp(24, 96);
+    let padding = footer_height / 4;
+    let font_size = footer_height as f32 * 0.55;
+
+    let mut res = RgbaImage::from_pixel(width, img.height() + footer_height, FOOTER_COLOR);
+    imageops::replace(&mut res, &img.to_rgba8(), 0, 0);
+
+    let footer_top = img.height().cast_signed();
+    let text_y = footer_top + ((footer_height as f32 - font_size) / 2.0) as i32;
+
+    let tiles_x = (size.bottom_right().x() - size.top_left().x()).round();
+    let tiles_y = (size.bottom_right().y() - size.top_left().y()).round();
+    let dimensions = format!("{tiles_x} × {tiles_y} tiles");
+    let (dimensions_width, _) = font.measure(&dimensions, font_size);
+    font.draw(
+        &mut res,
+        &dimensions,
+        (padding.cast_signed(), text_y),
+        font_size,
+        TEXT_COLOR,
+    );
+
+    // longest step that still fits into a quarter of the image
+    let tile_res = size.tile_res();
+    let Some(tiles) = SCALE_STEPS
+        .iter()
+        .rev()
+        .copied()
+        .find(|&tiles| f64::from(tiles) * tile_res <= f64::from(width) / 4.0)
+    else {
+        return res.into();
+    };
+
+    let bar_width = (f64::from(tiles) * tile_res).round() as u32;
+    let bar_right = (width - padding).cast_signed();
+    let bar_left = bar_right - bar_width.cast_signed();
+    let thickness = (footer_height / 10).max(2);
+    let tick_height = footer_height / 3;
+    let center = footer_top + (footer_height / 2).cast_signed();
+
+    draw_filled_rect_mut(
+        &mut res,
+        Rect::at(bar_left, center - (thickness / 2).cast_signed()).of_size(bar_width, thickness),
+        TEXT_COLOR,
+    );
+    for x in [bar_left, bar_right - thickness.cast_signed()] {
+        draw_filled_rect_mut(
+            &mut res,
+            Rect::at(x, center - (tick_height / 2).cast_signed()).of_size(thickness, tick_height),
+            TEXT_COLOR,
+        );
+    }
+
+    let unit = if tiles == 1 { "tile" } else { "tiles" };
+    let label = format!("{tiles} {unit} = {bar_width} px");
+    let (label_width, _) = font.measure(&label, font_size);
+    let label_x = bar_left - (padding + label_width).cast_signed();
+
+    // skip the label on narrow renders instead of drawing over the dimensions
+    if label_x > (2 * padding + dimensions_width).cast_signed() {
+        font.draw(&mut res, &label, (label_x, text_y), font_size, TEXT_COLOR);
+    }
+
+    res.into()
+}
+
+/// Appends a grid of item slots below the render, like the inventory of the game,
+/// that shows the items needed to build the blueprint with their counts.
+#[must_use]
+pub fn add_bill_of_materials(
+    img: &DynamicImage,
+    bom: &BillOfMaterials,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    font: &Font,
+) -> DynamicImage {
+    let items = bom.sorted();
+    if items.is_empty() {
+        return img.clone();
+    }
+
+    let width = img.width();
+    let slot = (width / 16).clamp(32, 128);
+    let padding = slot / 8;
+    let columns = ((width.saturating_sub(padding)) / (slot + padding)).max(1);
+    let rows = (items.len() as u32).div_ceil(columns);
+    let grid_height = rows * (slot + padding) + padding;
+    let font_size = slot as f32 * 0.35;
+    let icon_size = slot - 2 * padding;
+
+    let mut res = RgbaImage::from_pixel(width, img.height() + grid_height, FOOTER_COLOR);
+    imageops::replace(&mut res, &img.to_rgba8(), 0, 0);
+
+    for ((name, count), idx) in items.into_iter().zip(0u32..) {
+        let x = padding + (idx % columns) * (slot + padding);
+        let y = img.height() + padding + (idx / columns) * (slot + padding);
+
+        draw_filled_rect_mut(
+            &mut res,
+            Rect::at(x.cast_signed(), y.cast_signed()).of_size(slot, slot),
+            SLOT_COLOR,
+        );
+
+        if let Some((icon, _)) = data.get_item_icon(name, BOM_ICON_SCALE, used_mods, image_cache) {
+            let icon = icon.resize_exact(icon_size, icon_size, imageops::FilterType::Lanczos3);
+            imageops::overlay(
+                &mut res,
+                &icon.to_rgba8(),
+                i64::from(x + padding),
+                i64::from(y + padding),
+            );
+        }
+
+        let label = format_count(count.try_into().unwrap_or(i64::MAX));
+        let (label_width, label_height) = font.measure(&label, font_size);
+        font.draw(
+            &mut res,
+            &label,
+            (
+                (x + slot)
+                    .saturating_sub(label_width + padding / 2)
+                    .cast_signed(),
+                (y + slot)
+                    .saturating_sub(label_height + padding / 2)
+                    .cast_signed(),
+            ),
+            font_size,
+            TEXT_COLOR,
+        );
+    }
+
+    res.into()
+}
+
+/// Appends a list of the parameters of a parametrised blueprint below the render.
+///
+/// Each line shows the label used by the [`Parameters`](crate::overlay::Overlay::Parameters)
+/// overlay and the number of entities using the parameter.
+#[must_use]
+pub fn add_parameter_list(img: &DynamicImage, bp: &Blueprint, font: &Font) -> DynamicImage {
+    if bp.parameters.is_empty() {
+        return img.clone();
+    }
+
+    let width = img.width();
+    let font_size = (width / 48).clamp(14, 48) as f32;
+    let line_height = (font_size * 1.4).ceil() as u32;
+    let padding = line_height / 2;
+    let list_height = 2 * padding + line_height * bp.parameters.len() as u32;
+
+    let mut res = RgbaImage::from_pixel(width, img.height() + list_height, FOOTER_COLOR);
+    imageops::replace(&mut res, &img.to_rgba8(), 0, 0);
+
+    let users = bp.parameter_users();
+    for ((idx, parameter), y) in bp
+        .parameters
+        .iter()
+        .enumerate()
+        .zip((img.height() + padding..).step_by(line_height as usize))
+    {
+        let name = parameter
+            .name()
+            .filter(|name| !name.is_empty())
+            .map(|name| format!(" ({name})"))
+            .unwrap_or_default();
+        let usage = match users[idx].len() {
+            0 => String::new(),
+            1 => " – 1 entity".to_owned(),
+            count => format!(" – {count} entities"),
+        };
+        let flag = if parameter.is_parametrised() {
+            ""
+        } else {
+            ", not parametrised"
+        };
+        let line = format!(
+            "{}: {}{name}{usage}{flag}",
+            overlay::parameter_label(idx),
+            parameter.value()
+        );
+
+        font.draw(
+            &mut res,
+            &line,
+            (padding.cast_signed(), y.cast_signed()),
+            font_size,
+            TEXT_COLOR,
+        );
+    }
+
+    res.into()
+}
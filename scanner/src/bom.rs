@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use prototypes::DataUtil;
+use types::ItemID;
+
+/// Items needed to build a blueprint, including rails, tiles & requested modules.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BillOfMaterials {
+    /// Total count by item name.
+    pub items: BTreeMap<String, u64>,
+
+    /// Entities & tiles that can't be placed by any item, by their name.
+    pub unplaceable: BTreeMap<String, u64>,
+}
+
+impl BillOfMaterials {
+    #[must_use]
+    pub fn new(bp: &blueprint::Blueprint, data: &DataUtil) -> Self {
+        let mut entities = BTreeMap::<&str, u64>::new();
+        let mut tiles = BTreeMap::<&str, u64>::new();
+        let mut res = Self::default();
+
+        for e in &bp.entities {
+            *entities.entry(e.name.as_str()).or_default() += 1;
+
+            for (item, count) in &e.items {
+                res.add(item, u64::from(*count));
+            }
+        }
+
+        for tile in &bp.tiles {
+            *tiles.entry(tile.name.as_str()).or_default() += 1;
+        }
+
+        for (name, count) in entities {
+            res.place(name, count, data.entity_item_to_place(name));
+        }
+
+        for (name, count) in tiles {
+            res.place(name, count, data.tile_item_to_place(name));
+        }
+
+        res
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Items sorted by descending count, ties are sorted by name.
+    #[must_use]
+    pub fn sorted(&self) -> Vec<(&str, u64)> {
+        let mut res = self
+            .items
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect::<Vec<_>>();
+        res.sort_by(|(a_name, a_count), (b_name, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+        });
+
+        res
+    }
+
+    fn add(&mut self, item: &str, count: u64) {
+        *self.items.entry(item.to_owned()).or_default() += count;
+    }
+
+    fn place(&mut self, name: &str, count: u64, item: Option<(&ItemID, u32)>) {
+        if let Some((item, item_count)) = item {
+            self.add(item, count * u64::from(item_count));
+        } else {
+            *self.unplaceable.entry(name.to_owned()).or_default() += count;
+        }
+    }
+}
+
+impl std::fmt::Display for BillOfMaterials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .items
+            .values()
+            .map(|count| count.to_string().len())
+            .max()
+            .unwrap_or_default();
+
+        for (name, count) in self.sorted() {
+            writeln!(f, "{count:>width$} {name}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+//! Overview image of a blueprint book: a grid with the thumbnail, index & label of every entry.
+
+use error_stack::{report, Result, ResultExt};
+use image::{imageops, DynamicImage, RgbaImage};
+
+use mod_util::UsedMods;
+use prototypes::DataUtil;
+use types::ImageCache;
+
+use crate::{
+    annotation::{FOOTER_COLOR, SLOT_COLOR, TEXT_COLOR},
+    render_thumbnail,
+    text::{self, Font},
+    ScannerError,
+};
+
+const THUMB_SIZE: u32 = 128;
+const LABEL_SIZE: f32 = 16.0;
+const PADDING: u32 = 12;
+const COLUMNS: u32 = 6;
+
+/// Entry of a book, nested books are listed right before their own entries.
+#[derive(Debug, Clone)]
+pub struct IndexEntry<'a> {
+    /// Indices from the outermost book down to the entry, e.g. `2.0` for the first entry of the book at index 2.
+    pub path: String,
+    pub data: &'a blueprint::Data,
+}
+
+/// Flattens the entries of the book and all nested books in index order.
+#[must_use]
+pub fn entries(book: &blueprint::Book) -> Vec<IndexEntry<'_>> {
+    let mut res = Vec::new();
+    collect_entries(book, "", &mut res);
+    res
+}
+
+fn collect_entries<'a>(book: &'a blueprint::Book, prefix: &str, res: &mut Vec<IndexEntry<'a>>) {
+    let mut blueprints = book.blueprints.iter().collect::<Vec<_>>();
+    blueprints.sort_by_key(|entry| entry.index);
+
+    for entry in blueprints {
+        let path = format!("{prefix}{}", entry.index);
+        let data: &blueprint::Data = entry;
+
+        res.push(IndexEntry {
+            path: path.clone(),
+            data,
+        });
+
+        if let Some(nested) = data.as_book() {
+            collect_entries(nested, &format!("{path}."), res);
+        }
+    }
+}
+
+/// Renders a grid with the thumbnail (see [`render_thumbnail`]), index & label of every entry of the book.
+///
+/// Entries without a thumbnail get an empty slot. Returns `None` if `raw_bp` is not a book or the book is empty.
+#[must_use]
+pub fn book_index(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    font: &Font,
+) -> Option<DynamicImage> {
+    let entries = entries(raw_bp.as_book()?);
+    if entries.is_empty() {
+        return None;
+    }
+
+    let count = entries.len() as u32;
+    let columns = count.min(COLUMNS);
+    let rows = count.div_ceil(COLUMNS);
+
+    let line_height = (LABEL_SIZE * 1.3).ceil() as u32;
+    let cell_width = THUMB_SIZE + PADDING;
+    let cell_height = THUMB_SIZE + PADDING / 2 + 2 * line_height + PADDING;
+
+    let mut res = RgbaImage::from_pixel(
+        PADDING + columns * cell_width,
+        PADDING + rows * cell_height,
+        FOOTER_COLOR,
+    );
+    let slot = RgbaImage::from_pixel(THUMB_SIZE, THUMB_SIZE, SLOT_COLOR);
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let idx = idx as u32;
+        let x = PADDING + (idx % COLUMNS) * cell_width;
+        let y = PADDING + (idx / COLUMNS) * cell_height;
+
+        imageops::overlay(&mut res, &slot, i64::from(x), i64::from(y));
+        if let Some(thumb) = render_thumbnail(entry.data, data, used_mods, image_cache) {
+            let thumb = thumb
+                .resize_exact(THUMB_SIZE, THUMB_SIZE, imageops::FilterType::Lanczos3)
+                .to_rgba8();
+            imageops::overlay(&mut res, &thumb, i64::from(x), i64::from(y));
+        }
+
+        let label = if entry.data.label().is_empty() {
+            entry.data.item()
+        } else {
+            entry.data.label()
+        };
+
+        let text_y = y + THUMB_SIZE + PADDING / 2;
+        for (line, text) in [entry.path.as_str(), label].into_iter().enumerate() {
+            font.draw(
+                &mut res,
+                &fit_text(text, THUMB_SIZE, font),
+                (
+                    x.cast_signed(),
+                    (text_y + line as u32 * line_height).cast_signed(),
+                ),
+                LABEL_SIZE,
+                TEXT_COLOR,
+            );
+        }
+    }
+
+    Some(res.into())
+}
+
+/// Shortens the text with an ellipsis until it fits into `width` pixels.
+fn fit_text(text: &str, width: u32, font: &Font) -> String {
+    if font.measure(text, LABEL_SIZE).0 <= width {
+        return text.to_owned();
+    }
+
+    let mut chars = text.chars().collect::<Vec<_>>();
+    while !chars.is_empty() {
+        chars.pop();
+
+        let shortened = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if font.measure(&shortened, LABEL_SIZE).0 <= width {
+            return shortened;
+        }
+    }
+
+    String::new()
+}
+
+/// Renders the [`book_index`] of a blueprint book as PNG, using the GUI font of the core mod.
+pub fn book_index_png(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+) -> Result<Vec<u8>, ScannerError> {
+    if !raw_bp.is_book() {
+        return Err(report!(ScannerError::NoBlueprint))
+            .attach_printable("the blueprint string does not contain a blueprint book");
+    }
+
+    let font = Font::load(used_mods, text::DEFAULT_FONT)
+        .ok_or_else(|| report!(ScannerError::SetupError))
+        .attach_printable("failed to load the font for the book index")?;
+
+    let index = book_index(raw_bp, data, used_mods, &mut ImageCache::new(), &font)
+        .ok_or_else(|| report!(ScannerError::RenderError))
+        .attach_printable("the blueprint book is empty")?;
+
+    crate::encode_png(&index)
+}
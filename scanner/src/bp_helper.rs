@@ -1,12 +1,18 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use mod_util::{AnyBasic, DependencyList};
-use strum::IntoEnumIterator;
+use blueprint::{EntityNumber, GetIDs, SignalID, WireConnectorID};
 
-use crate::preset::Preset;
+use mod_util::{AnyBasic, DependencyList, UsedMods};
+use prototypes::{
+    entity::{PipeToGroundPrototype, Type as EntityType, UndergroundBeltPrototype},
+    ConnectedEntities, DataUtil, DataUtilAccess, Region,
+};
+use types::{BoundingBox, Direction, GraphicsOutput, ImageCache, MapPosition};
+
+use crate::preset::Presets;
 
 #[must_use]
-pub fn get_used_versions(bp: &blueprint::Blueprint) -> DependencyList {
+pub fn get_used_versions(bp: &blueprint::Blueprint, presets: &Presets) -> DependencyList {
     if let Some(meta_info) = bp.get_meta_info_mods() {
         return meta_info;
     }
@@ -14,31 +20,31 @@ pub fn get_used_versions(bp: &blueprint::Blueprint) -> DependencyList {
     let mut auto_detected = DependencyList::new();
     for entity in &bp.entities {
         // trying to auto detect mods
-        check_prefix(&entity.name, &mut auto_detected);
+        check_prefix(&entity.name, presets, &mut auto_detected);
 
         if !entity.recipe.is_empty() {
-            check_prefix(&entity.recipe, &mut auto_detected);
-        }
-
-        if !entity.filter.is_empty() {
-            check_prefix(&entity.filter, &mut auto_detected);
+            check_prefix(&entity.recipe, presets, &mut auto_detected);
         }
 
-        for filter in &entity.filters {
-            check_prefix(filter, &mut auto_detected);
+        for filter in entity
+            .filter
+            .iter()
+            .chain(entity.filters.iter().map(|f| &**f))
+        {
+            check_prefix(&filter.name, presets, &mut auto_detected);
         }
 
         for item in entity.items.keys() {
-            check_prefix(item, &mut auto_detected);
+            check_prefix(item, presets, &mut auto_detected);
         }
     }
 
     auto_detected
 }
 
-fn check_prefix(id: &str, dep_list: &mut DependencyList) {
-    for preset in Preset::iter() {
-        let Some(prefix) = preset.known_prefix() else {
+fn check_prefix(id: &str, presets: &Presets, dep_list: &mut DependencyList) {
+    for preset in presets.iter() {
+        let Some(prefix) = &preset.prefix else {
             continue;
         };
 
@@ -72,3 +78,370 @@ pub fn get_used_startup_settings(bp: &blueprint::Blueprint) -> Option<&BTreeMap<
 
     None
 }
+
+/// Names of all prototypes referenced by the blueprint (or book), including its own item.
+#[must_use]
+pub fn get_used_names(bp: &blueprint::Data) -> HashSet<String> {
+    let ids = bp.get_ids();
+    let mut names = HashSet::from([bp.item().to_owned()]);
+
+    names.extend(ids.entity.iter().map(ToString::to_string));
+    names.extend(ids.item.iter().map(ToString::to_string));
+    names.extend(ids.fluid.iter().map(ToString::to_string));
+    names.extend(ids.recipe.iter().map(ToString::to_string));
+    names.extend(ids.tile.iter().map(ToString::to_string));
+    names.extend(ids.virtual_signal.iter().map(ToString::to_string));
+
+    names
+}
+
+/// Collision box of a blueprint entity in map coordinates.
+#[must_use]
+pub fn entity_area(e: &blueprint::Entity, data: &DataUtil) -> Option<BoundingBox> {
+    let e_data = data.get_entity(&e.name)?;
+    Some(place_box(e, e_data.collision_box()))
+}
+
+/// Area covered by all entities & tiles of a blueprint, extended to whole tiles.
+/// Entities without a prototype count as a single tile at their position.
+#[must_use]
+pub fn blueprint_bounds(bp: &blueprint::Blueprint, data: &DataUtil) -> Option<BoundingBox> {
+    let entities = bp.entities.iter().map(|e| {
+        entity_area(e, data).unwrap_or_else(|| {
+            let position = MapPosition::from(&e.position);
+            BoundingBox(position, position).expand(0.5)
+        })
+    });
+    let tiles = bp.tiles.iter().map(|t| {
+        let position = MapPosition::from(&t.position);
+        BoundingBox(position, position + MapPosition::Tuple(1.0, 1.0))
+    });
+
+    let bounds = entities.chain(tiles).reduce(|a, b| a.union(&b))?;
+    Some(BoundingBox(
+        MapPosition::Tuple(bounds.left().floor(), bounds.top().floor()),
+        MapPosition::Tuple(bounds.right().ceil(), bounds.bottom().ceil()),
+    ))
+}
+
+/// Copy of the blueprint that only keeps the entities & tiles within [`Region::MARGIN`] of the region.
+#[must_use]
+pub fn crop(bp: &blueprint::Blueprint, region: &Region) -> blueprint::Blueprint {
+    let mut cropped = bp.clone();
+    cropped
+        .entities
+        .retain(|e| region.contains(&(&e.position).into(), Region::MARGIN));
+    cropped
+        .tiles
+        .retain(|t| region.contains(&(&t.position).into(), Region::MARGIN));
+
+    cropped
+}
+
+/// Distance between the copies of a blueprint when tiling it.
+///
+/// Uses the snapping grid if set, otherwise the size of all entities & tiles, plus `spacing` tiles.
+/// Blueprints with rails or train stops are kept on the 2 tile rail grid.
+#[must_use]
+pub fn tiling_step(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    spacing: u32,
+) -> Option<blueprint::Position> {
+    let (width, height) = if let Some(grid) = &bp.snapping.snap_to_grid {
+        (f64::from(grid.x), f64::from(grid.y))
+    } else {
+        let bounds = blueprint_bounds(bp, data)?;
+        (bounds.width(), bounds.height())
+    };
+
+    let rails = bp.entities.iter().any(|e| {
+        e.name.contains("rail")
+            || matches!(
+                data.get_entity_type(&e.name),
+                Some(
+                    EntityType::StraightRail
+                        | EntityType::CurvedRail
+                        | EntityType::RailSignal
+                        | EntityType::RailChainSignal
+                        | EntityType::TrainStop
+                )
+            )
+    });
+
+    let step = |size: f64| {
+        let step = size.ceil() + f64::from(spacing);
+        if rails && step % 2.0 != 0.0 {
+            step + 1.0
+        } else {
+            step
+        }
+    };
+
+    Some(blueprint::Position {
+        x: step(width) as f32,
+        y: step(height) as f32,
+    })
+}
+
+/// Selection box of a blueprint entity in map coordinates.
+#[must_use]
+pub fn entity_selection_area(e: &blueprint::Entity, data: &DataUtil) -> Option<BoundingBox> {
+    let e_data = data.get_entity(&e.name)?;
+    Some(place_box(e, e_data.selection_box()))
+}
+
+/// Icon of any kind of signal, e.g. an item, fluid, recipe or planet.
+pub fn signal_icon(
+    signal: &SignalID,
+    data: &DataUtil,
+    scale: f64,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+) -> Option<GraphicsOutput> {
+    let name = signal.name()?;
+
+    match signal {
+        SignalID::Item { .. } => data.get_item_icon(&name, scale, used_mods, image_cache),
+        SignalID::Fluid { .. } => data.get_fluid_icon(&name, scale, used_mods, image_cache),
+        SignalID::Virtual { .. } => data.get_signal_icon(&name, scale, used_mods, image_cache),
+        SignalID::Recipe { .. } => data.get_recipe_icon(&name, scale, used_mods, image_cache),
+        SignalID::Entity { .. } => data.get_entity_icon(&name, scale, used_mods, image_cache),
+        SignalID::SpaceLocation { .. } => {
+            data.get_space_location_icon(&name, scale, used_mods, image_cache)
+        }
+        SignalID::AsteroidChunk { .. } => {
+            data.get_asteroid_chunk_icon(&name, scale, used_mods, image_cache)
+        }
+        SignalID::Quality { .. } => data.get_quality_icon(&name, scale, used_mods, image_cache),
+    }
+}
+
+/// Wire connections of all entities, grouped by the wire connection points of the renderer.
+/// The flag marks power switches.
+#[must_use]
+pub fn wire_connections(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+) -> HashMap<EntityNumber, ([ConnectedEntities; 3], bool)> {
+    let switches = bp
+        .entities
+        .iter()
+        .filter(|e| matches!(data.get_entity_type(&e.name), Some(EntityType::PowerSwitch)))
+        .map(|e| e.entity_number)
+        .collect::<HashSet<_>>();
+
+    let mut res = HashMap::<_, ([ConnectedEntities; 3], bool)>::new();
+    for wire in &bp.wires {
+        let [a, b] = wire.ends();
+
+        for ((source, connector), (target, _)) in [(a, b), (b, a)] {
+            let is_switch = switches.contains(&source);
+            let (point, wire_type) = connection_point(connector, is_switch);
+
+            let entry = res.entry(source).or_default();
+            entry.1 = is_switch;
+            entry.0[point].entry(target).or_default()[wire_type] = true;
+        }
+    }
+
+    res
+}
+
+/// Connection point & wire type (copper, red, green) of a wire connector.
+const fn connection_point(connector: WireConnectorID, is_switch: bool) -> (usize, usize) {
+    match connector {
+        WireConnectorID::CircuitRed => (0, 1),
+        WireConnectorID::CircuitGreen => (0, 2),
+        WireConnectorID::CombinatorOutputRed => (1, 1),
+        WireConnectorID::CombinatorOutputGreen => (1, 2),
+        WireConnectorID::PoleCopper if is_switch => (1, 0),
+        WireConnectorID::PoleCopper => (0, 0),
+        WireConnectorID::PowerSwitchRightCopper => (2, 0),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UndergroundKind {
+    Belt { input: bool },
+    Pipe,
+}
+
+struct Underground<'a> {
+    id: u64,
+    name: &'a str,
+    position: MapPosition,
+    direction: Direction,
+    max_distance: u32,
+    kind: UndergroundKind,
+}
+
+/// Position on the half tile grid, so rail ends & connection points can be matched without float comparisons.
+pub type GridKey = (i32, i32);
+
+#[must_use]
+pub fn grid_key(position: &MapPosition) -> GridKey {
+    let (x, y) = position.as_tuple();
+    ((x * 2.0).round() as i32, (y * 2.0).round() as i32)
+}
+
+/// Fluid or heat connection at a [`GridKey`] position facing a direction.
+pub type ConnectionKey = (GridKey, Direction);
+
+#[must_use]
+pub fn connection_key(position: &MapPosition, direction: Direction) -> ConnectionKey {
+    (grid_key(position), direction)
+}
+
+/// The connection that connects to `key`: one at the position `key` points at, facing back.
+#[must_use]
+pub fn matching_connection(key: ConnectionKey) -> ConnectionKey {
+    let ((x, y), dir) = key;
+    let (dx, dy) = dir.get_offset().as_tuple();
+    let target = MapPosition::Tuple(f64::from(x) / 2.0 + dx, f64::from(y) / 2.0 + dy);
+
+    connection_key(&target, dir.flip())
+}
+
+/// Returns how many tiles `to` is away from `from` when walking in `direction`.
+/// Positions that are not on the same axis or behind `from` return [`None`].
+fn steps_along(from: &MapPosition, to: &MapPosition, direction: Direction) -> Option<u32> {
+    const EPSILON: f64 = 0.125;
+
+    let (dx, dy) = (*to - from).as_tuple();
+    let (ox, oy) = direction.get_offset().as_tuple();
+
+    let (along, across) = if ox == 0.0 {
+        (dy * oy, dx)
+    } else if oy == 0.0 {
+        (dx * ox, dy)
+    } else {
+        return None;
+    };
+
+    if across.abs() > EPSILON || along < 1.0 - EPSILON || (along - along.round()).abs() > EPSILON {
+        return None;
+    }
+
+    Some(along.round() as u32)
+}
+
+fn collect_undergrounds<'a>(bp: &'a blueprint::Blueprint, data: &DataUtil) -> Vec<Underground<'a>> {
+    bp.entities
+        .iter()
+        .filter_map(|e| {
+            let (max_distance, kind) =
+                if let Some(proto) = data.get_proto::<UndergroundBeltPrototype>(&e.name) {
+                    (
+                        u32::from(proto.max_distance),
+                        UndergroundKind::Belt {
+                            input: matches!(e.type_, Some(blueprint::UndergroundType::Input)),
+                        },
+                    )
+                } else if let Some(proto) = data.get_proto::<PipeToGroundPrototype>(&e.name) {
+                    (
+                        proto.fluid_box.max_underground_distance()?,
+                        UndergroundKind::Pipe,
+                    )
+                } else {
+                    return None;
+                };
+
+            Some(Underground {
+                id: e.entity_number,
+                name: e.name.as_str(),
+                position: (&e.position).into(),
+                direction: e.direction.eight_way(),
+                max_distance,
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// A paired underground belt or pipe-to-ground.
+#[derive(Debug, Clone)]
+pub struct UndergroundPair {
+    /// Input belt or the pipe-to-ground the pair was found from.
+    pub source: EntityNumber,
+    pub target: EntityNumber,
+
+    pub start: MapPosition,
+    pub end: MapPosition,
+
+    /// Direction from `start` towards `end`.
+    pub direction: Direction,
+
+    /// Distance between both entities in tiles.
+    pub steps: u32,
+    pub pipe: bool,
+}
+
+/// Pairs underground belts and pipe-to-grounds that are within the `max_distance` /
+/// `max_underground_distance` of their prototype, like the game connects them.
+#[must_use]
+pub fn underground_pairs(bp: &blueprint::Blueprint, data: &DataUtil) -> Vec<UndergroundPair> {
+    let undergrounds = collect_undergrounds(bp, data);
+    let mut found = HashSet::new();
+    let mut pairs = Vec::new();
+
+    for source in &undergrounds {
+        // belts pair from input to output, pipes from their underground side
+        let search_dir = match source.kind {
+            UndergroundKind::Belt { input: true } => source.direction,
+            UndergroundKind::Belt { input: false } => continue,
+            UndergroundKind::Pipe => source.direction.flip(),
+        };
+
+        let Some((steps, target)) = undergrounds
+            .iter()
+            .filter(|t| {
+                t.id != source.id
+                    && t.name == source.name
+                    && t.direction.is_straight(&source.direction)
+            })
+            .filter_map(|t| Some((steps_along(&source.position, &t.position, search_dir)?, t)))
+            .filter(|(steps, _)| *steps <= source.max_distance)
+            .min_by_key(|(steps, _)| *steps)
+        else {
+            continue;
+        };
+
+        let paired = match source.kind {
+            UndergroundKind::Belt { .. } => {
+                target.kind == UndergroundKind::Belt { input: false }
+                    && target.direction == source.direction
+            }
+            UndergroundKind::Pipe => target.direction == search_dir,
+        };
+
+        if !paired || !found.insert((source.id.min(target.id), source.id.max(target.id))) {
+            continue;
+        }
+
+        pairs.push(UndergroundPair {
+            source: source.id,
+            target: target.id,
+            start: source.position,
+            end: target.position,
+            direction: search_dir,
+            steps,
+            pipe: source.kind == UndergroundKind::Pipe,
+        });
+    }
+
+    pairs
+}
+
+fn place_box(e: &blueprint::Entity, area: BoundingBox) -> BoundingBox {
+    // diagonal directions only occur on entities without a meaningful rotated footprint
+    let BoundingBox(tl, br) = match e.direction.eight_way() {
+        Direction::North | Direction::East | Direction::South | Direction::West => {
+            area.rotate(e.direction.eight_way())
+        }
+        _ => area,
+    };
+
+    let position = MapPosition::from(&e.position);
+    BoundingBox(position + tl, position + br)
+}
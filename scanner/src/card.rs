@@ -0,0 +1,435 @@
+//! Small info cards of single entity prototypes & deconstruction planners, e.g. for chat bot commands or to annotate renders.
+
+use error_stack::{report, Result, ResultExt};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+
+use blueprint::{FilterMode, TileSelectionMode};
+use mod_util::UsedMods;
+use prototypes::{
+    entity::{
+        AssemblingMachinePrototype, BeaconPrototype, FurnacePrototype, LabPrototype,
+        MiningDrillPrototype, RocketSiloPrototype,
+    },
+    DataUtil, DataUtilAccess,
+};
+use types::{EntityID, ImageCache, ItemID, TileID};
+
+use crate::{
+    annotation::{FOOTER_COLOR, SLOT_COLOR, TEXT_COLOR},
+    text::{self, Font},
+    ScannerError,
+};
+
+const TITLE_SIZE: f32 = 28.0;
+const TEXT_SIZE: f32 = 20.0;
+const ICON_SIZE: u32 = 64;
+const PADDING: u32 = 12;
+
+/// Scale the icon gets rendered at before resizing it to [`ICON_SIZE`].
+const ICON_SCALE: f64 = 0.5;
+
+const FILTER_SLOT_SIZE: u32 = 40;
+const FILTER_SLOT_GAP: u32 = 4;
+const FILTER_COLUMNS: u32 = 10;
+
+/// Background of the filter slots of a blacklist, like the red slots in game.
+const BLACKLIST_SLOT_COLOR: Rgba<u8> = Rgba([0x6e, 0x2e, 0x2a, 0xff]);
+
+/// Key stats of an entity prototype as `(label, value)` pairs, in the order they are shown on the card.
+#[must_use]
+pub fn entity_stats(name: &str, data: &DataUtil) -> Vec<(&'static str, String)> {
+    let mut res = Vec::new();
+    let Some(entity) = data.get_entity(name) else {
+        return res;
+    };
+
+    let collision_box = entity.collision_box();
+    res.push((
+        "Size",
+        format!(
+            "{}×{}",
+            collision_box.width().ceil(),
+            collision_box.height().ceil()
+        ),
+    ));
+
+    let id = EntityID::new(name);
+    let (speed, energy_usage, modules) =
+        if let Some(proto) = data.get_proto::<AssemblingMachinePrototype>(&id) {
+            (
+                ("Crafting speed", proto.crafting_speed),
+                &proto.energy_usage,
+                proto.module_specification.as_ref(),
+            )
+        } else if let Some(proto) = data.get_proto::<FurnacePrototype>(&id) {
+            (
+                ("Crafting speed", proto.crafting_speed),
+                &proto.energy_usage,
+                proto.module_specification.as_ref(),
+            )
+        } else if let Some(proto) = data.get_proto::<RocketSiloPrototype>(&id) {
+            (
+                ("Crafting speed", proto.crafting_speed),
+                &proto.energy_usage,
+                proto.module_specification.as_ref(),
+            )
+        } else if let Some(proto) = data.get_proto::<MiningDrillPrototype>(&id) {
+            (
+                ("Mining speed", proto.mining_speed),
+                &proto.energy_usage,
+                proto.module_specification.as_ref(),
+            )
+        } else if let Some(proto) = data.get_proto::<LabPrototype>(&id) {
+            (
+                ("Research speed", proto.researching_speed),
+                &proto.energy_usage,
+                proto.module_specification.as_ref(),
+            )
+        } else if let Some(proto) = data.get_proto::<BeaconPrototype>(&id) {
+            (
+                ("Effectivity", proto.distribution_effectivity),
+                &proto.energy_usage,
+                Some(&proto.module_specification),
+            )
+        } else {
+            return res;
+        };
+
+    res.push((speed.0, speed.1.to_string()));
+    res.push(("Power", energy_usage.clone()));
+
+    if let Some(slots) = modules
+        .map(|modules| modules.module_slots)
+        .filter(|&slots| slots > 0)
+    {
+        res.push(("Module slots", slots.to_string()));
+    }
+
+    res
+}
+
+/// Renders a card with the icon, name & [`entity_stats`] of the entity prototype `name`.
+///
+/// The icon of the item that places the entity is used, the card is drawn without it
+/// if there is no such item.
+#[must_use]
+pub fn entity_card(
+    name: &str,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    font: &Font,
+) -> Option<DynamicImage> {
+    data.get_entity(name)?;
+
+    let icon = data
+        .entity_item_to_place(name)
+        .and_then(|(item, _)| data.get_item_icon(item, ICON_SCALE, used_mods, image_cache))
+        .map(|(icon, _)| {
+            icon.resize_exact(ICON_SIZE, ICON_SIZE, imageops::FilterType::Lanczos3)
+                .to_rgba8()
+        });
+
+    let lines = entity_stats(name, data)
+        .into_iter()
+        .map(|(label, value)| format!("{label}: {value}"))
+        .collect::<Vec<_>>();
+
+    let (title_width, title_height) = font.measure(name, TITLE_SIZE);
+    let line_height = (TEXT_SIZE * 1.3).ceil() as u32;
+    let text_width = lines
+        .iter()
+        .map(|line| font.measure(line, TEXT_SIZE).0)
+        .chain(std::iter::once(title_width))
+        .max()
+        .unwrap_or_default();
+    let text_height = title_height + PADDING / 2 + line_height * lines.len() as u32;
+
+    let text_left = if icon.is_some() {
+        2 * PADDING + ICON_SIZE
+    } else {
+        PADDING
+    };
+    let width = text_left + text_width + PADDING;
+    let height = 2 * PADDING + text_height.max(ICON_SIZE);
+
+    let mut res = RgbaImage::from_pixel(width, height, FOOTER_COLOR);
+
+    if let Some(icon) = icon {
+        let slot = RgbaImage::from_pixel(ICON_SIZE, ICON_SIZE, SLOT_COLOR);
+        imageops::overlay(&mut res, &slot, i64::from(PADDING), i64::from(PADDING));
+        imageops::overlay(&mut res, &icon, i64::from(PADDING), i64::from(PADDING));
+    }
+
+    let x = text_left.cast_signed();
+    font.draw(
+        &mut res,
+        name,
+        (x, PADDING.cast_signed()),
+        TITLE_SIZE,
+        TEXT_COLOR,
+    );
+
+    let mut y = PADDING + title_height + PADDING / 2;
+    for line in &lines {
+        font.draw(&mut res, line, (x, y.cast_signed()), TEXT_SIZE, TEXT_COLOR);
+        y += line_height;
+    }
+
+    Some(res.into())
+}
+
+/// Renders the [`entity_card`] of `name` as PNG, using the GUI font of the core mod.
+pub fn entity_card_png(
+    name: &str,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+) -> Result<Vec<u8>, ScannerError> {
+    let font = Font::load(used_mods, text::DEFAULT_FONT)
+        .ok_or_else(|| report!(ScannerError::SetupError))
+        .attach_printable("failed to load the font for the entity card")?;
+
+    let card = entity_card(name, data, used_mods, &mut ImageCache::new(), &font)
+        .ok_or_else(|| report!(ScannerError::RenderError))
+        .attach_printable_lazy(|| format!("unknown entity {name}"))?;
+
+    crate::encode_png(&card)
+}
+
+/// Line of a planner card.
+enum PlannerRow {
+    Text(String),
+
+    /// Filter slots by their index in the planner, `None` for empty slots & unknown prototypes.
+    Filters {
+        icons: Vec<Option<DynamicImage>>,
+        blacklist: bool,
+    },
+}
+
+impl PlannerRow {
+    fn filters<'a>(
+        filters: impl Iterator<Item = (u16, Option<&'a ItemID>)>,
+        blacklist: bool,
+        data: &DataUtil,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+    ) -> Self {
+        let filters = filters.collect::<Vec<_>>();
+        let slots = filters
+            .iter()
+            .map(|(index, _)| u32::from(*index))
+            .max()
+            .unwrap_or_default()
+            .div_ceil(FILTER_COLUMNS)
+            .max(1)
+            * FILTER_COLUMNS;
+
+        let mut icons = vec![None; slots as usize];
+        for (index, item) in filters {
+            let Some(slot) = icons.get_mut(usize::from(index.saturating_sub(1))) else {
+                continue;
+            };
+
+            *slot = item
+                .and_then(|item| data.get_item_icon(item, ICON_SCALE, used_mods, image_cache))
+                .map(|(icon, _)| icon);
+        }
+
+        Self::Filters { icons, blacklist }
+    }
+
+    fn height(&self) -> u32 {
+        match self {
+            Self::Text(_) => (TEXT_SIZE * 1.3).ceil() as u32,
+            Self::Filters { icons, .. } => {
+                let rows = (icons.len() as u32).div_ceil(FILTER_COLUMNS);
+                rows * (FILTER_SLOT_SIZE + FILTER_SLOT_GAP) - FILTER_SLOT_GAP + PADDING / 2
+            }
+        }
+    }
+
+    fn draw(&self, img: &mut RgbaImage, y: u32, font: &Font) {
+        match self {
+            Self::Text(text) => {
+                font.draw(
+                    img,
+                    text,
+                    (PADDING.cast_signed(), y.cast_signed()),
+                    TEXT_SIZE,
+                    TEXT_COLOR,
+                );
+            }
+            Self::Filters { icons, blacklist } => {
+                let icon_size = FILTER_SLOT_SIZE - FILTER_SLOT_GAP;
+                let slot = RgbaImage::from_pixel(
+                    FILTER_SLOT_SIZE,
+                    FILTER_SLOT_SIZE,
+                    if *blacklist {
+                        BLACKLIST_SLOT_COLOR
+                    } else {
+                        SLOT_COLOR
+                    },
+                );
+
+                for (icon, idx) in icons.iter().zip(0u32..) {
+                    let x = i64::from(
+                        PADDING + (idx % FILTER_COLUMNS) * (FILTER_SLOT_SIZE + FILTER_SLOT_GAP),
+                    );
+                    let slot_y = i64::from(
+                        y + (idx / FILTER_COLUMNS) * (FILTER_SLOT_SIZE + FILTER_SLOT_GAP),
+                    );
+                    imageops::overlay(img, &slot, x, slot_y);
+
+                    if let Some(icon) = icon {
+                        let icon = icon
+                            .resize_exact(icon_size, icon_size, imageops::FilterType::Lanczos3)
+                            .to_rgba8();
+                        let offset = i64::from(FILTER_SLOT_GAP / 2);
+                        imageops::overlay(img, &icon, x + offset, slot_y + offset);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders a deconstruction planner like its window in game: the entity & tile filters as icon grids
+/// with their whitelist / blacklist mode, the trees & rocks only flag and the tile selection mode.
+///
+/// Filters are shown with the icon of the item that places the entity / tile, empty slots are kept
+/// so the grid matches the planner. Returns `None` if `planner` is not a deconstruction planner.
+#[must_use]
+pub fn decon_planner_card(
+    planner: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    font: &Font,
+) -> Option<DynamicImage> {
+    let blueprint::Data::DeconstructionPlanner(planner) = planner else {
+        return None;
+    };
+
+    let mut rows = Vec::new();
+
+    if planner.trees_and_rocks_only {
+        rows.push(PlannerRow::Text("Trees and rocks only".to_owned()));
+    } else {
+        let blacklist = planner.entity_filter_mode == FilterMode::Blacklist;
+        rows.push(PlannerRow::Text(
+            match (planner.entity_filters.is_empty(), blacklist) {
+                (true, _) => "Entities: all",
+                (false, false) => "Entities: whitelist",
+                (false, true) => "Entities: blacklist",
+            }
+            .to_owned(),
+        ));
+
+        if !planner.entity_filters.is_empty() {
+            rows.push(PlannerRow::filters(
+                planner.entity_filters.iter().map(|filter| {
+                    let name: &EntityID = filter;
+                    (
+                        filter.index,
+                        data.entity_item_to_place(name).map(|(item, _)| item),
+                    )
+                }),
+                blacklist,
+                data,
+                used_mods,
+                image_cache,
+            ));
+        }
+    }
+
+    rows.push(PlannerRow::Text(
+        match planner.tile_selection_mode {
+            TileSelectionMode::Normal => "Tiles: only without entities in the selection",
+            TileSelectionMode::Always => "Tiles: always",
+            TileSelectionMode::Never => "Tiles: never",
+            TileSelectionMode::Only => "Tiles: only tiles",
+        }
+        .to_owned(),
+    ));
+
+    if planner.tile_selection_mode != TileSelectionMode::Never && !planner.tile_filters.is_empty() {
+        let blacklist = planner.tile_filter_mode == FilterMode::Blacklist;
+        rows.push(PlannerRow::Text(
+            if blacklist {
+                "Tile blacklist"
+            } else {
+                "Tile whitelist"
+            }
+            .to_owned(),
+        ));
+        rows.push(PlannerRow::filters(
+            planner.tile_filters.iter().map(|filter| {
+                let name: &TileID = filter;
+                (
+                    filter.index,
+                    data.tile_item_to_place(name).map(|(item, _)| item),
+                )
+            }),
+            blacklist,
+            data,
+            used_mods,
+            image_cache,
+        ));
+    }
+
+    let title = if planner.label.is_empty() {
+        "Deconstruction planner"
+    } else {
+        &planner.label
+    };
+    let (title_width, title_height) = font.measure(title, TITLE_SIZE);
+    let grid_width = FILTER_COLUMNS * (FILTER_SLOT_SIZE + FILTER_SLOT_GAP) - FILTER_SLOT_GAP;
+    let content_width = rows
+        .iter()
+        .map(|row| match row {
+            PlannerRow::Text(text) => font.measure(text, TEXT_SIZE).0,
+            PlannerRow::Filters { .. } => grid_width,
+        })
+        .chain(std::iter::once(title_width))
+        .max()
+        .unwrap_or_default();
+
+    let width = content_width + 2 * PADDING;
+    let height =
+        2 * PADDING + title_height + PADDING / 2 + rows.iter().map(PlannerRow::height).sum::<u32>();
+
+    let mut res = RgbaImage::from_pixel(width, height, FOOTER_COLOR);
+    font.draw(
+        &mut res,
+        title,
+        (PADDING.cast_signed(), PADDING.cast_signed()),
+        TITLE_SIZE,
+        TEXT_COLOR,
+    );
+
+    let mut y = PADDING + title_height + PADDING / 2;
+    for row in &rows {
+        row.draw(&mut res, y, font);
+        y += row.height();
+    }
+
+    Some(res.into())
+}
+
+/// Renders the [`decon_planner_card`] of `planner` as PNG, using the GUI font of the core mod.
+pub fn decon_planner_card_png(
+    planner: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+) -> Result<Vec<u8>, ScannerError> {
+    let font = Font::load(used_mods, text::DEFAULT_FONT)
+        .ok_or_else(|| report!(ScannerError::SetupError))
+        .attach_printable("failed to load the font for the planner card")?;
+
+    let card = decon_planner_card(planner, data, used_mods, &mut ImageCache::new(), &font)
+        .ok_or_else(|| report!(ScannerError::NoBlueprint))
+        .attach_printable("not a deconstruction planner")?;
+
+    crate::encode_png(&card)
+}
@@ -0,0 +1,477 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use error_stack::{Context, Result, ResultExt};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+use tracing::{debug, info, warn};
+
+use mod_util::{mod_list::ModList, AnyBasic};
+use prototypes::DataRaw;
+
+use crate::DumpMethod;
+
+#[derive(Debug)]
+pub enum DumpCacheError {
+    Io(PathBuf),
+    Transcode(PathBuf),
+}
+
+impl Context for DumpCacheError {}
+
+impl std::fmt::Display for DumpCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path) => write!(f, "dump cache io error at {}", path.display()),
+            Self::Transcode(path) => {
+                write!(f, "failed to minify prototype dump at {}", path.display())
+            }
+        }
+    }
+}
+
+const PREFIX: &str = "cached-dump_";
+const SUFFIX: &str = ".json.deflate";
+
+/// Marks dumps of [`DumpMethod::Lua`], the dumps of the game keep the plain name.
+const LUA_MARKER: &str = "-lua";
+
+/// Identifies a prototype dump by the active mods (including their load order), the startup settings
+/// and how it was created.
+///
+/// The approximate [`DumpMethod::Lua`] dumps never stand in for the ones of the game and vice versa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DumpKey {
+    pub mods: u64,
+    pub settings: u64,
+    pub method: DumpMethod,
+}
+
+impl DumpKey {
+    #[must_use]
+    pub fn new(
+        mod_list: &ModList,
+        settings: &BTreeMap<String, AnyBasic>,
+        method: DumpMethod,
+    ) -> Self {
+        let (active_mods, load_order) = mod_list.active_with_order();
+        let mut hash = rustc_hash::FxHasher::default();
+        for mod_name in &load_order {
+            let Some(m) = active_mods.get(mod_name) else {
+                continue;
+            };
+            format!("{}@{}", m.info.name, m.info.version).hash(&mut hash);
+        }
+        let mods = hash.finish();
+
+        Self {
+            mods,
+            settings: settings_hash(settings),
+            method,
+        }
+    }
+
+    fn file_name(self) -> String {
+        format!("{PREFIX}{self}{SUFFIX}")
+    }
+
+    fn from_file_name(name: &str) -> Option<Self> {
+        let name = name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+        let (name, method) = name
+            .strip_suffix(LUA_MARKER)
+            .map_or((name, DumpMethod::Factorio), |name| (name, DumpMethod::Lua));
+        let (mods, settings) = name.split_once('-')?;
+
+        Some(Self {
+            mods: u64::from_str_radix(mods, 16).ok()?,
+            settings: u64::from_str_radix(settings, 16).ok()?,
+            method,
+        })
+    }
+}
+
+/// Stable hash of the startup settings, independent of their order.
+#[must_use]
+pub fn settings_hash(settings: &BTreeMap<String, AnyBasic>) -> u64 {
+    let mut active_settings = settings
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>();
+    active_settings.sort();
+
+    let mut hash = rustc_hash::FxHasher::default();
+    for setting in &active_settings {
+        setting.hash(&mut hash);
+    }
+    hash.finish()
+}
+
+impl std::fmt::Display for DumpKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:X}-{:X}", self.mods, self.settings)?;
+        match self.method {
+            DumpMethod::Factorio => Ok(()),
+            DumpMethod::Lua => f.write_str(LUA_MARKER),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DumpCacheEntry {
+    pub key: DumpKey,
+    pub path: PathBuf,
+
+    /// Size of the compressed dump in bytes.
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// Minified & deflated prototype dumps, keyed by [`DumpKey`].
+///
+/// If a maximum size is set, the least recently used dumps are evicted whenever a new one is stored.
+#[derive(Debug, Clone)]
+pub struct DumpCache {
+    root: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl DumpCache {
+    #[must_use]
+    pub const fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            max_size: None,
+        }
+    }
+
+    /// The `script-output` folder of the factorio user data directory, next to the dumps of the game.
+    #[must_use]
+    pub fn in_userdir(factorio_userdir: &Path) -> Self {
+        Self::new(factorio_userdir.join("script-output"))
+    }
+
+    /// Limits the total size of all cached dumps in bytes.
+    #[must_use]
+    pub const fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    #[must_use]
+    pub fn path(&self, key: DumpKey) -> PathBuf {
+        self.root.join(key.file_name())
+    }
+
+    /// Loads the cached dump for `key`.
+    ///
+    /// Entries that can't be read are treated as missing.
+    #[must_use]
+    pub fn lookup(&self, key: DumpKey) -> Option<DataRaw> {
+        self.load(key, DataRaw::load_from_bytes)
+    }
+
+    /// Like [`DumpCache::lookup`] but only loads the prototypes with the given names.
+    #[must_use]
+    pub fn lookup_filtered(&self, key: DumpKey, names: &HashSet<String>) -> Option<DataRaw> {
+        self.load(key, |bytes| DataRaw::load_filtered_from_bytes(bytes, names))
+    }
+
+    fn load<E: std::fmt::Display>(
+        &self,
+        key: DumpKey,
+        parse: impl FnOnce(&[u8]) -> std::result::Result<DataRaw, E>,
+    ) -> Option<DataRaw> {
+        let path = self.path(key);
+        let file = fs::File::open(&path).ok()?;
+
+        info!("loading cached prototype dump {key}");
+        let mut uncompressed = Vec::new();
+        if let Err(err) = ZlibDecoder::new(file).read_to_end(&mut uncompressed) {
+            warn!(
+                "removing corrupt cached prototype dump at {}: {err}",
+                path.display()
+            );
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        // mark the entry as recently used for eviction
+        let _ = fs::File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+
+        parse(&uncompressed)
+            .inspect_err(|err| {
+                warn!(
+                    "failed to load cached prototype dump at {}: {err}",
+                    path.display()
+                );
+            })
+            .ok()
+    }
+
+    /// Stores a minified & deflated copy of the dump created by the game.
+    /// The dump is transcoded as a stream so it never has to be held in memory as a whole.
+    pub fn store(&self, key: DumpKey, dump: &Path) -> Result<PathBuf, DumpCacheError> {
+        let source = fs::File::open(dump).change_context(DumpCacheError::Io(dump.to_path_buf()))?;
+        self.store_from(
+            key,
+            &mut serde_json::Deserializer::from_reader(BufReader::new(source)),
+            dump,
+        )
+    }
+
+    /// Like [`DumpCache::store`] for a dump that only exists in memory, e.g. of [`DumpMethod::Lua`].
+    pub fn store_bytes(&self, key: DumpKey, dump: &[u8]) -> Result<PathBuf, DumpCacheError> {
+        let path = self.path(key);
+        self.store_from(key, &mut serde_json::Deserializer::from_slice(dump), &path)
+    }
+
+    /// `origin` names the dump in errors.
+    fn store_from<'de, R: serde_json::de::Read<'de>>(
+        &self,
+        key: DumpKey,
+        deserializer: &mut serde_json::Deserializer<R>,
+        origin: &Path,
+    ) -> Result<PathBuf, DumpCacheError> {
+        let path = self.path(key);
+        let tmp = path.with_extension("part");
+
+        let mut deflate = ZlibEncoder::new(
+            BufWriter::new(fs::File::create(&tmp).change_context(DumpCacheError::Io(tmp.clone()))?),
+            flate2::Compression::best(),
+        );
+
+        serde_transcode::transcode(deserializer, &mut serde_json::Serializer::new(&mut deflate))
+            .change_context(DumpCacheError::Transcode(origin.to_path_buf()))?;
+
+        deflate
+            .finish()
+            .and_then(|mut out| out.flush())
+            .change_context(DumpCacheError::Io(tmp.clone()))?;
+
+        // only rename the finished file so an interrupted write never ends up as an entry
+        fs::rename(&tmp, &path).change_context(DumpCacheError::Io(path.clone()))?;
+        debug!("cached prototype dump at {}", path.display());
+
+        if let Some(max_size) = self.max_size {
+            self.evict(max_size)?;
+        }
+
+        Ok(path)
+    }
+
+    /// All cached dumps, least recently used first.
+    pub fn entries(&self) -> Result<Vec<DumpCacheEntry>, DumpCacheError> {
+        if !self.root.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for entry in
+            fs::read_dir(&self.root).change_context(DumpCacheError::Io(self.root.clone()))?
+        {
+            let entry = entry.change_context(DumpCacheError::Io(self.root.clone()))?;
+            let Some(key) = entry.file_name().to_str().and_then(DumpKey::from_file_name) else {
+                continue;
+            };
+
+            let path = entry.path();
+            let meta = entry
+                .metadata()
+                .change_context(DumpCacheError::Io(path.clone()))?;
+
+            if !meta.is_file() {
+                continue;
+            }
+
+            entries.push(DumpCacheEntry {
+                key,
+                path,
+                size: meta.len(),
+                last_used: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+
+        entries.sort_by_key(|entry| entry.last_used);
+        Ok(entries)
+    }
+
+    /// Removes the least recently used dumps until all of them together take at most `max_size` bytes.
+    /// The most recently used dump is always kept.
+    /// Returns the number of removed dumps and their size in bytes.
+    pub fn evict(&self, max_size: u64) -> Result<(usize, u64), DumpCacheError> {
+        let entries = self.entries()?;
+        let mut total = entries.iter().map(|entry| entry.size).sum::<u64>();
+        let mut removed = (0, 0);
+
+        for entry in &entries[..entries.len().saturating_sub(1)] {
+            if total <= max_size {
+                break;
+            }
+
+            fs::remove_file(&entry.path).change_context(DumpCacheError::Io(entry.path.clone()))?;
+            total -= entry.size;
+            removed.0 += 1;
+            removed.1 += entry.size;
+        }
+
+        if removed.0 > 0 {
+            info!(
+                "evicted {} cached prototype dumps ({} bytes) from {}",
+                removed.0,
+                removed.1,
+                self.root.display()
+            );
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes all cached dumps.
+    /// Returns the number of removed dumps and their size in bytes.
+    pub fn clear(&self) -> Result<(usize, u64), DumpCacheError> {
+        let mut removed = (0, 0);
+
+        for entry in self.entries()? {
+            fs::remove_file(&entry.path).change_context(DumpCacheError::Io(entry.path.clone()))?;
+            removed.0 += 1;
+            removed.1 += entry.size;
+        }
+
+        info!(
+            "cleared {} cached prototype dumps ({} bytes) from {}",
+            removed.0,
+            removed.1,
+            self.root.display()
+        );
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::time::Duration;
+
+    use super::*;
+
+    fn cache(test: &str) -> DumpCache {
+        let root = std::env::temp_dir().join(format!("dump_cache_{test}_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        DumpCache::new(root)
+    }
+
+    const fn key(mods: u64, method: DumpMethod) -> DumpKey {
+        DumpKey {
+            mods,
+            settings: 0xC0_FFEE,
+            method,
+        }
+    }
+
+    /// Writes a fake entry of `size` bytes that was last used `age` ago.
+    fn add_entry(cache: &DumpCache, key: DumpKey, size: usize, age: Duration) {
+        let path = cache.path(key);
+        fs::write(&path, vec![0; size]).unwrap();
+        fs::File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now() - age))
+            .unwrap();
+    }
+
+    #[test]
+    fn file_name_round_trip() {
+        for key in [key(0xAB, DumpMethod::Factorio), key(0xAB, DumpMethod::Lua)] {
+            assert_eq!(DumpKey::from_file_name(&key.file_name()), Some(key));
+        }
+        assert_eq!(
+            key(0xAB, DumpMethod::Lua).file_name(),
+            "cached-dump_AB-C0FFEE-lua.json.deflate"
+        );
+
+        for foreign in [
+            "data-raw-dump.json",
+            "cached-dump_AB.json.deflate",
+            "cached-dump_XY-12.json.deflate",
+            "cached-dump_AB-12-luau.json.deflate",
+        ] {
+            assert_eq!(DumpKey::from_file_name(foreign), None);
+        }
+
+        // other files of the game in script-output are no entries
+        let cache = cache("round_trip");
+        fs::write(cache.root().join("data-raw-dump.json"), b"{}").unwrap();
+        add_entry(&cache, key(1, DumpMethod::Lua), 1, Duration::ZERO);
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, key(1, DumpMethod::Lua));
+
+        fs::remove_dir_all(cache.root()).unwrap();
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = cache("evict");
+        add_entry(
+            &cache,
+            key(1, DumpMethod::Factorio),
+            10,
+            Duration::from_mins(3),
+        );
+        add_entry(
+            &cache,
+            key(2, DumpMethod::Factorio),
+            20,
+            Duration::from_mins(2),
+        );
+        add_entry(&cache, key(3, DumpMethod::Lua), 30, Duration::from_mins(1));
+
+        assert_eq!(cache.evict(60).unwrap(), (0, 0));
+        assert_eq!(cache.evict(50).unwrap(), (1, 10));
+        assert!(!cache.path(key(1, DumpMethod::Factorio)).exists());
+
+        // the newest dump is kept even if it alone is too large
+        assert_eq!(cache.evict(0).unwrap(), (1, 20));
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, key(3, DumpMethod::Lua));
+
+        fs::remove_dir_all(cache.root()).unwrap();
+    }
+
+    #[test]
+    fn unfinished_dumps_are_no_entries() {
+        let cache = cache("part");
+        let stored = key(1, DumpMethod::Factorio);
+        let path = cache.store_bytes(stored, br#"{ "a": 1 }"#).unwrap();
+        fs::write(
+            cache
+                .path(key(2, DumpMethod::Factorio))
+                .with_extension("part"),
+            b"interrupted",
+        )
+        .unwrap();
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, stored);
+        assert_eq!(entries[0].path, path);
+
+        fs::remove_dir_all(cache.root()).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+//! C ABI of the rendering core for applications written in other languages.
+//!
+//! Build the shared library with `cargo rustc -p scanner --lib --release --features ffi --crate-type cdylib`.
+//! Nothing is read from the file system or the mod portal, all sprites are requested through a callback of the host.
+#![allow(unsafe_code)]
+
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_void, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::Arc,
+};
+
+use mod_util::{
+    mod_info::Version,
+    mod_loader::ModError,
+    sprite_source::{self, SharedSpriteSource, SpriteSource},
+};
+use prototypes::{DataRaw, DataUtil, RenderOptions};
+use tracing::warn;
+
+use crate::render;
+
+/// Bytes handed between the host and the renderer.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ScannerBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl ScannerBuffer {
+    const fn empty() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let bytes = Box::leak(bytes.into_boxed_slice());
+
+        Self {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScannerStatus {
+    Ok = 0,
+    InvalidArgument = 1,
+    InvalidBlueprint = 2,
+    InvalidPrototypes = 3,
+    RenderError = 4,
+    Panic = 5,
+}
+
+/// Called for every sprite the renderer needs.
+///
+/// `mod_name` is the mod of the sprite (e.g. `base`), `path` the file inside of the mod (e.g. `graphics/entity/...png`).
+/// The host writes the file content to `out` and returns `true`, or `false` if it doesn't have the file.
+/// The content has to stay valid until the callback is called again or the render finishes, the renderer copies it.
+pub type SpriteCallback = unsafe extern "C" fn(
+    user_data: *mut c_void,
+    mod_name: *const c_char,
+    path: *const c_char,
+    out: *mut ScannerBuffer,
+) -> bool;
+
+#[derive(Debug)]
+struct CallbackSource {
+    callback: SpriteCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: the host guarantees that the callback can be called from the rendering thread
+unsafe impl Send for CallbackSource {}
+unsafe impl Sync for CallbackSource {}
+
+impl SpriteSource for CallbackSource {
+    fn fetch(&self, mod_name: &str, path: &str) -> Result<Vec<u8>, ModError> {
+        let not_provided = || ModError::NotProvided(format!("{mod_name}/{path}"));
+        let c_mod_name = CString::new(mod_name).map_err(|_| not_provided())?;
+        let c_path = CString::new(path).map_err(|_| not_provided())?;
+        let mut out = ScannerBuffer::empty();
+
+        // SAFETY: all pointers are valid for the duration of the call, the callback itself is the hosts responsibility
+        let found = unsafe {
+            (self.callback)(
+                self.user_data,
+                c_mod_name.as_ptr(),
+                c_path.as_ptr(),
+                &raw mut out,
+            )
+        };
+
+        if !found || out.data.is_null() {
+            return Err(not_provided());
+        }
+
+        // SAFETY: the host guarantees `out` points to `len` readable bytes until the next call
+        Ok(unsafe { std::slice::from_raw_parts(out.data, out.len) }.to_vec())
+    }
+}
+
+/// Reads a UTF-8 C string, `None` for null pointers & invalid UTF-8.
+///
+/// # Safety
+/// `ptr` has to be null or point to a nul terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Renders a blueprint to a PNG.
+///
+/// - `blueprint`: blueprint string or its decoded JSON
+/// - `prototypes` / `prototypes_len`: prototype dump JSON (`factorio --dump-data`)
+/// - `mods`: JSON object of the active mods and their versions, e.g. `{"core":"2.0.24","base":"2.0.24"}`
+/// - `sprites` / `user_data`: callback providing the sprites of the mods (not null), `user_data` is passed through as is
+///
+/// On success the PNG is written to `out` and has to be released with [`scanner_free_buffer`].
+///
+/// # Safety
+/// The strings have to be nul terminated, `prototypes` has to point to `prototypes_len` readable bytes
+/// and `out` has to be writable. The callback is only called during this function.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn scanner_render(
+    blueprint: *const c_char,
+    prototypes: *const u8,
+    prototypes_len: usize,
+    mods: *const c_char,
+    target_res: f64,
+    min_scale: f64,
+    sprites: Option<SpriteCallback>,
+    user_data: *mut c_void,
+    out: *mut ScannerBuffer,
+) -> ScannerStatus {
+    if out.is_null() || prototypes.is_null() {
+        return ScannerStatus::InvalidArgument;
+    }
+
+    let (Some(blueprint), Some(mods), Some(sprites)) =
+        (read_str(blueprint), read_str(mods), sprites)
+    else {
+        return ScannerStatus::InvalidArgument;
+    };
+    let prototypes = std::slice::from_raw_parts(prototypes, prototypes_len);
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        let Ok(versions) = serde_json::from_str::<HashMap<String, Version>>(mods) else {
+            return Err(ScannerStatus::InvalidArgument);
+        };
+        let source: SharedSpriteSource = Arc::new(CallbackSource {
+            callback: sprites,
+            user_data,
+        });
+        let used_mods = sprite_source::used_mods(versions, &source);
+
+        let bp = if blueprint.trim_start().starts_with('{') {
+            blueprint::Data::from_json(blueprint)
+        } else {
+            blueprint::Data::try_from(blueprint.trim())
+        }
+        .map_err(|err| {
+            warn!("invalid blueprint: {err}");
+            ScannerStatus::InvalidBlueprint
+        })?;
+
+        let data = DataRaw::load_from_bytes(prototypes).map_err(|err| {
+            warn!("invalid prototype dump: {err}");
+            ScannerStatus::InvalidPrototypes
+        })?;
+        let data = DataUtil::new(data);
+
+        render(
+            &bp,
+            &data,
+            &used_mods,
+            target_res,
+            min_scale,
+            &[],
+            &RenderOptions::default(),
+        )
+        .map(|output| output.image)
+        .map_err(|err| {
+            warn!("render failed: {err:?}");
+            ScannerStatus::RenderError
+        })
+    }));
+
+    match res {
+        Ok(Ok(png)) => {
+            out.write(ScannerBuffer::from_vec(png));
+            ScannerStatus::Ok
+        }
+        Ok(Err(status)) => status,
+        Err(_) => ScannerStatus::Panic,
+    }
+}
+
+/// Releases a buffer returned by [`scanner_render`].
+///
+/// # Safety
+/// `buffer` has to come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn scanner_free_buffer(buffer: ScannerBuffer) {
+    if buffer.data.is_null() {
+        return;
+    }
+
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+        buffer.data,
+        buffer.len,
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::{
+        collections::{BTreeSet, HashSet},
+        io::Cursor,
+    };
+
+    use super::*;
+
+    const BLUEPRINT: &str = r#"{
+        "blueprint": {
+            "item": "blueprint",
+            "icons": [],
+            "version": 562949954928640,
+            "entities": [
+                { "entity_number": 1, "name": "wooden-chest", "position": { "x": 0.5, "y": 0.5 } }
+            ]
+        }
+    }"#;
+
+    /// Requested sprites & the PNG every request is answered with.
+    struct Host {
+        requests: BTreeSet<(String, String)>,
+        png: Vec<u8>,
+    }
+
+    unsafe extern "C" fn serve(
+        user_data: *mut c_void,
+        mod_name: *const c_char,
+        path: *const c_char,
+        out: *mut ScannerBuffer,
+    ) -> bool {
+        let host = &mut *user_data.cast::<Host>();
+        host.requests.insert((
+            read_str(mod_name).unwrap().to_owned(),
+            read_str(path).unwrap().to_owned(),
+        ));
+
+        out.write(ScannerBuffer {
+            data: host.png.as_mut_ptr(),
+            len: host.png.len(),
+        });
+        true
+    }
+
+    /// Dump with only the prototypes the blueprint needs, re-serialized to drop the ones this version can't read.
+    fn prototypes() -> Vec<u8> {
+        let dump = std::fs::read(format!(
+            "{}/../prototypes/test_dumps/base.{}.json",
+            env!("CARGO_MANIFEST_DIR"),
+            prototypes::targeted_engine_version()
+        ))
+        .unwrap();
+        let dump =
+            DataRaw::filter_dump(&dump, &HashSet::from(["wooden-chest".to_owned()])).unwrap();
+        let (data, _) = DataRaw::load_lenient_from_bytes(&dump).unwrap();
+
+        serde_json::to_vec(&data).unwrap()
+    }
+
+    #[test]
+    fn render_with_callback() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgba8(64, 64)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let mut host = Host {
+            requests: BTreeSet::new(),
+            png,
+        };
+
+        let blueprint = CString::new(BLUEPRINT).unwrap();
+        let mods = CString::new(r#"{"core":"2.0.24","base":"2.0.24"}"#).unwrap();
+        let prototypes = prototypes();
+        let mut out = ScannerBuffer::empty();
+
+        let status = unsafe {
+            scanner_render(
+                blueprint.as_ptr(),
+                prototypes.as_ptr(),
+                prototypes.len(),
+                mods.as_ptr(),
+                256.0,
+                0.5,
+                Some(serve),
+                (&raw mut host).cast(),
+                &raw mut out,
+            )
+        };
+
+        assert_eq!(status, ScannerStatus::Ok);
+        assert!(host
+            .requests
+            .iter()
+            .any(|(mod_name, path)| mod_name == "base" && path.contains("wooden-chest")));
+
+        let rendered = unsafe { std::slice::from_raw_parts(out.data, out.len) };
+        assert!(image::load_from_memory_with_format(rendered, image::ImageFormat::Png).is_ok());
+        unsafe { scanner_free_buffer(out) };
+    }
+
+    #[test]
+    fn invalid_blueprint() {
+        let blueprint = CString::new("0not a blueprint").unwrap();
+        let mods = CString::new("{}").unwrap();
+        let mut out = ScannerBuffer::empty();
+
+        let status = unsafe {
+            scanner_render(
+                blueprint.as_ptr(),
+                b"{}".as_ptr(),
+                2,
+                mods.as_ptr(),
+                256.0,
+                0.5,
+                Some(serve),
+                ptr::null_mut(),
+                &raw mut out,
+            )
+        };
+
+        assert_eq!(status, ScannerStatus::InvalidBlueprint);
+        assert!(out.data.is_null());
+    }
+
+    #[test]
+    fn missing_callback() {
+        let blueprint = CString::new(BLUEPRINT).unwrap();
+        let mods = CString::new("{}").unwrap();
+        let mut out = ScannerBuffer::empty();
+
+        let status = unsafe {
+            scanner_render(
+                blueprint.as_ptr(),
+                b"{}".as_ptr(),
+                2,
+                mods.as_ptr(),
+                256.0,
+                0.5,
+                None,
+                ptr::null_mut(),
+                &raw mut out,
+            )
+        };
+
+        assert_eq!(status, ScannerStatus::InvalidArgument);
+        assert!(out.data.is_null());
+    }
+}
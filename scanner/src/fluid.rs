@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use blueprint::EntityNumber;
+use prototypes::{entity::Type as EntityType, DataUtil};
+
+use crate::{
+    bp_entity2render_opts,
+    bp_helper::{self, connection_key, matching_connection},
+    rail::DisjointSet,
+};
+
+/// Entities that share their fluid with everything they are connected to.
+const fn is_network_member(entity_type: &EntityType) -> bool {
+    matches!(
+        entity_type,
+        EntityType::Pipe
+            | EntityType::InfinityPipe
+            | EntityType::PipeToGround
+            | EntityType::Pump
+            | EntityType::StorageTank
+    )
+}
+
+/// Fluid systems of a blueprint, made of pipes, pipe-to-grounds, pumps & storage tanks.
+///
+/// Other entities with fluid boxes like machines or boilers separate systems.
+/// Pumps only join the system they pump from.
+#[derive(Debug, Default)]
+pub struct FluidNetworks {
+    members: Vec<EntityNumber>,
+    networks: Vec<usize>,
+    count: usize,
+}
+
+impl FluidNetworks {
+    #[must_use]
+    pub fn new(bp: &blueprint::Blueprint, data: &DataUtil) -> Self {
+        let mut members = Vec::new();
+        let mut connections = Vec::new();
+        let mut indices = HashMap::new();
+
+        for e in &bp.entities {
+            let Some(entity_type) = data.get_entity_type(&e.name) else {
+                continue;
+            };
+
+            if !is_network_member(entity_type) {
+                continue;
+            }
+
+            let Some(e_data) = data.get_entity(&e.name) else {
+                continue;
+            };
+
+            let idx = members.len();
+            members.push(e.entity_number);
+            indices.insert(e.entity_number, idx);
+
+            let pump = matches!(entity_type, EntityType::Pump);
+            connections.extend(
+                e_data
+                    .pipe_connections(&bp_entity2render_opts(e, data))
+                    .into_iter()
+                    .filter(|(_, dir)| !pump || *dir == e.direction.eight_way())
+                    .map(|(pos, dir)| (connection_key(&pos, dir), idx)),
+            );
+        }
+
+        let mut sets = DisjointSet::new(members.len());
+        let lookup = connections.iter().copied().collect::<HashMap<_, _>>();
+
+        for (key, idx) in &connections {
+            if let Some(other) = lookup.get(&matching_connection(*key)) {
+                sets.union(*idx, *other);
+            }
+        }
+
+        for pair in bp_helper::underground_pairs(bp, data)
+            .into_iter()
+            .filter(|pair| pair.pipe)
+        {
+            if let (Some(a), Some(b)) = (indices.get(&pair.source), indices.get(&pair.target)) {
+                sets.union(*a, *b);
+            }
+        }
+
+        let mut ids = HashMap::new();
+        let networks = (0..members.len())
+            .map(|idx| {
+                let root = sets.find(idx);
+                let next_id = ids.len();
+                *ids.entry(root).or_insert(next_id)
+            })
+            .collect();
+
+        Self {
+            members,
+            networks,
+            count: ids.len(),
+        }
+    }
+
+    /// Number of separate fluid networks.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.count
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Network id of an entity, ids are consecutive starting at 0 in order of the entities.
+    #[must_use]
+    pub fn network_of(&self, entity_number: EntityNumber) -> Option<usize> {
+        self.members
+            .iter()
+            .position(|member| *member == entity_number)
+            .map(|idx| self.networks[idx])
+    }
+
+    /// All network members with their network id.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityNumber, usize)> + '_ {
+        self.members
+            .iter()
+            .copied()
+            .zip(self.networks.iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::test_util::{base_data, blueprint};
+
+    #[test]
+    fn groups_connected_pipes() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"pipe","position":{"x":0.5,"y":0.5}},
+            {"entity_number":2,"name":"pipe","position":{"x":1.5,"y":0.5}},
+            {"entity_number":3,"name":"pipe","position":{"x":1.5,"y":1.5}},
+            {"entity_number":4,"name":"pipe","position":{"x":4.5,"y":0.5}},
+            {"entity_number":5,"name":"pipe-to-ground","position":{"x":5.5,"y":0.5},"direction":12},
+            {"entity_number":6,"name":"pipe-to-ground","position":{"x":9.5,"y":0.5},"direction":4},
+            {"entity_number":7,"name":"pipe","position":{"x":10.5,"y":0.5}},
+            {"entity_number":8,"name":"wooden-chest","position":{"x":0.5,"y":2.5}},
+            {"entity_number":9,"name":"pipe","position":{"x":0.5,"y":3.5}}"#,
+            "",
+        );
+        let networks = FluidNetworks::new(&bp, base_data());
+
+        assert_eq!(networks.len(), 3);
+        assert_eq!(networks.iter().count(), 8);
+        assert_eq!(networks.network_of(8), None);
+
+        let network = |number| networks.network_of(number).unwrap();
+        assert!([1, 2, 3].into_iter().all(|number| network(number) == 0));
+        assert!([4, 5, 6, 7].into_iter().all(|number| network(number) == 1));
+        assert_eq!(network(9), 2);
+    }
+
+    #[test]
+    fn pumps_only_join_their_input() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"pipe","position":{"x":0.5,"y":0.5}},
+            {"entity_number":2,"name":"pump","position":{"x":0.5,"y":2},"direction":8},
+            {"entity_number":3,"name":"pipe","position":{"x":0.5,"y":3.5}}"#,
+            "",
+        );
+        let networks = FluidNetworks::new(&bp, base_data());
+
+        // the pump faces south, so it pumps from the pipe north of it
+        assert_eq!(networks.len(), 2);
+        assert_eq!(networks.network_of(1), Some(0));
+        assert_eq!(networks.network_of(2), Some(0));
+        assert_eq!(networks.network_of(3), Some(1));
+    }
+}
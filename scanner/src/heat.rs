@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use blueprint::EntityNumber;
+use prototypes::{
+    entity::{
+        BoilerPrototype, HeatInterfacePrototype, HeatPipePrototype, ReactorPrototype,
+        Type as EntityType,
+    },
+    DataUtil, DataUtilAccess,
+};
+use types::AnyEnergySource;
+
+use crate::{
+    bp_entity2render_opts,
+    bp_helper::{connection_key, matching_connection},
+};
+
+/// Min working temperature of heat exchangers in vanilla, used if the blueprint has no heat consumer.
+const DEFAULT_WORKING_TEMPERATURE: f64 = 500.0;
+
+/// Heat of an entity that is connected to a heat source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeatState {
+    /// Number of heat conducting entities between the entity and the nearest heat source.
+    pub distance: u32,
+
+    /// Max temperature of the nearest heat source.
+    pub source_temperature: f64,
+
+    /// Temperature the entity reaches if every step loses the minimum temperature gradient.
+    pub temperature: f64,
+}
+
+/// Heat pipes, reactors, heat interfaces & heat consumers of a blueprint, connected by their heat connections.
+///
+/// Reactors & heat interfaces are heat sources, the distance to them is searched breadth first.
+/// The estimated temperatures are an upper bound, heat transfer under load needs a steeper gradient.
+#[derive(Debug, Default)]
+pub struct HeatNetwork {
+    states: HashMap<EntityNumber, Option<HeatState>>,
+
+    /// Highest min working temperature of all heat consumers.
+    pub working_temperature: f64,
+}
+
+/// Heat conducting entities of a blueprint with the entities they exchange heat with.
+struct HeatGraph<'a> {
+    members: Vec<&'a blueprint::Entity>,
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl<'a> HeatGraph<'a> {
+    fn new(bp: &'a blueprint::Blueprint, data: &DataUtil) -> Self {
+        let mut members = Vec::new();
+        let mut connections = Vec::new();
+
+        for e in &bp.entities {
+            let Some(e_data) = data.get_entity(&e.name) else {
+                continue;
+            };
+
+            let heat_connections = e_data.heat_connections(&bp_entity2render_opts(e, data));
+            if heat_connections.is_empty() {
+                continue;
+            }
+
+            let idx = members.len();
+            members.push(e);
+            connections.extend(
+                heat_connections
+                    .into_iter()
+                    .map(|(pos, dir)| (connection_key(&pos, dir), idx)),
+            );
+        }
+
+        let lookup = connections.iter().copied().collect::<HashMap<_, _>>();
+        let mut neighbours = vec![Vec::new(); members.len()];
+
+        for (key, idx) in &connections {
+            let Some(other) = lookup.get(&matching_connection(*key)) else {
+                continue;
+            };
+
+            if other != idx && !neighbours[*idx].contains(other) {
+                neighbours[*idx].push(*other);
+            }
+        }
+
+        Self {
+            members,
+            neighbours,
+        }
+    }
+}
+
+impl HeatNetwork {
+    #[must_use]
+    pub fn new(bp: &blueprint::Blueprint, data: &DataUtil) -> Self {
+        let graph = HeatGraph::new(bp, data);
+        let mut working_temperature = None::<f64>;
+        let mut states = vec![None::<HeatState>; graph.members.len()];
+        let mut gradients = vec![1.0; graph.members.len()];
+        let mut queue = VecDeque::new();
+
+        for (idx, e) in graph.members.iter().enumerate() {
+            let source_temperature = match data.get_entity_type(&e.name) {
+                Some(EntityType::Reactor) => data
+                    .get_proto::<ReactorPrototype>(&e.name)
+                    .map(|proto| proto.heat_buffer.max_temperature),
+                Some(EntityType::HeatInterface) => data
+                    .get_proto::<HeatInterfacePrototype>(&e.name)
+                    .map(|proto| proto.heat_buffer.max_temperature),
+                Some(EntityType::HeatPipe) => {
+                    if let Some(proto) = data.get_proto::<HeatPipePrototype>(&e.name) {
+                        gradients[idx] = proto.heat_buffer.min_temperature_gradient;
+                    }
+
+                    None
+                }
+                Some(EntityType::Boiler) => {
+                    if let Some(AnyEnergySource::Heat { data: source }) = data
+                        .get_proto::<BoilerPrototype>(&e.name)
+                        .map(|proto| &proto.energy_source)
+                    {
+                        working_temperature = Some(
+                            working_temperature
+                                .unwrap_or_default()
+                                .max(source.min_working_temperature),
+                        );
+                    }
+
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some(temperature) = source_temperature {
+                states[idx] = Some(HeatState {
+                    distance: 0,
+                    source_temperature: temperature,
+                    temperature,
+                });
+                queue.push_back(idx);
+            }
+        }
+
+        while let Some(idx) = queue.pop_front() {
+            let Some(state) = states[idx] else {
+                continue;
+            };
+
+            for &next in &graph.neighbours[idx] {
+                if states[next].is_some() {
+                    continue;
+                }
+
+                states[next] = Some(HeatState {
+                    distance: state.distance + 1,
+                    source_temperature: state.source_temperature,
+                    temperature: state.temperature - gradients[next],
+                });
+                queue.push_back(next);
+            }
+        }
+
+        Self {
+            states: graph
+                .members
+                .iter()
+                .map(|e| e.entity_number)
+                .zip(states)
+                .collect(),
+            working_temperature: working_temperature.unwrap_or(DEFAULT_WORKING_TEMPERATURE),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// `None` if the entity does not conduct heat, `Some(None)` if it is not connected to a heat source.
+    #[must_use]
+    pub fn state_of(&self, entity_number: EntityNumber) -> Option<Option<HeatState>> {
+        self.states.get(&entity_number).copied()
+    }
+
+    /// Whether the entity conducts heat but can't reach the working temperature of the heat consumers.
+    #[must_use]
+    pub fn too_cold(&self, entity_number: EntityNumber) -> bool {
+        self.state_of(entity_number).is_some_and(|state| {
+            state.is_none_or(|state| state.temperature < self.working_temperature)
+        })
+    }
+}
+
+/// Reactors of a blueprint with the reactors they share heat connections with, each one adds the neighbour bonus.
+#[must_use]
+pub fn reactor_neighbours(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+) -> BTreeMap<EntityNumber, Vec<EntityNumber>> {
+    let graph = HeatGraph::new(bp, data);
+    let is_reactor = |idx: usize| {
+        matches!(
+            data.get_entity_type(&graph.members[idx].name),
+            Some(EntityType::Reactor)
+        )
+    };
+
+    (0..graph.members.len())
+        .filter(|idx| is_reactor(*idx))
+        .map(|idx| {
+            (
+                graph.members[idx].entity_number,
+                graph.neighbours[idx]
+                    .iter()
+                    .filter(|other| is_reactor(**other))
+                    .map(|other| graph.members[*other].entity_number)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    // the temperatures are whole numbers, so they are compared exactly
+    #![allow(clippy::unwrap_used, clippy::float_cmp)]
+    use super::*;
+    use crate::test_util::{base_data, blueprint};
+
+    fn reactor(number: u64, x: f64, y: f64) -> String {
+        format!(
+            r#"{{"entity_number":{number},"name":"nuclear-reactor","position":{{"x":{x},"y":{y}}}}}"#
+        )
+    }
+
+    /// `count` heat pipes in a row to the east of `x`, numbered from `first`.
+    fn heat_pipes(first: u64, x: f64, y: f64, count: u64) -> Vec<String> {
+        (0..count)
+            .map(|idx| {
+                format!(
+                    r#"{{"entity_number":{},"name":"heat-pipe","position":{{"x":{},"y":{y}}}}}"#,
+                    first + idx,
+                    x + idx as f64
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chain_past_the_max_transfer_distance() {
+        // 1000° reactor, every heat pipe loses 1°, so the 501st one is below the 500° of heat exchangers
+        let mut entities = vec![reactor(1, 2.5, 2.5)];
+        entities.extend(heat_pipes(2, 5.5, 2.5, 501));
+        let bp = blueprint(&entities.join(","), "");
+        let network = HeatNetwork::new(&bp, base_data());
+
+        assert_eq!(network.len(), 502);
+        assert_eq!(network.working_temperature, DEFAULT_WORKING_TEMPERATURE);
+
+        let source = network.state_of(1).unwrap().unwrap();
+        assert_eq!(source.distance, 0);
+        assert_eq!(source.temperature, 1000.0);
+
+        let first = network.state_of(2).unwrap().unwrap();
+        assert_eq!(first.distance, 1);
+        assert_eq!(first.source_temperature, 1000.0);
+        assert_eq!(first.temperature, 999.0);
+
+        let last_warm = network.state_of(501).unwrap().unwrap();
+        assert_eq!(last_warm.distance, 500);
+        assert_eq!(last_warm.temperature, 500.0);
+        assert!(!network.too_cold(501));
+
+        let cold = network.state_of(502).unwrap().unwrap();
+        assert_eq!(cold.distance, 501);
+        assert_eq!(cold.temperature, 499.0);
+        assert!(network.too_cold(502));
+    }
+
+    #[test]
+    fn nearest_of_two_sources() {
+        let mut entities = vec![reactor(1, 2.5, 2.5), reactor(2, 17.5, 2.5)];
+        entities.extend(heat_pipes(3, 5.5, 2.5, 10));
+        let bp = blueprint(&entities.join(","), "");
+        let network = HeatNetwork::new(&bp, base_data());
+
+        let distances = (3..13)
+            .map(|number| network.state_of(number).unwrap().unwrap().distance)
+            .collect::<Vec<_>>();
+        assert_eq!(distances, [1, 2, 3, 4, 5, 5, 4, 3, 2, 1]);
+        assert!((3..13).all(|number| !network.too_cold(number)));
+    }
+
+    #[test]
+    fn disconnected_and_non_conducting_entities() {
+        let mut entities = heat_pipes(1, 0.5, 0.5, 2);
+        entities.push(
+            r#"{"entity_number":3,"name":"heat-exchanger","position":{"x":5.5,"y":5},"direction":8}"#
+                .to_owned(),
+        );
+        entities.push(
+            r#"{"entity_number":4,"name":"wooden-chest","position":{"x":9.5,"y":9.5}}"#.to_owned(),
+        );
+        let bp = blueprint(&entities.join(","), "");
+        let network = HeatNetwork::new(&bp, base_data());
+
+        assert_eq!(network.working_temperature, 500.0);
+        assert_eq!(network.state_of(1), Some(None));
+        assert!(network.too_cold(1));
+        assert_eq!(network.state_of(4), None);
+        assert!(!network.too_cold(4));
+    }
+
+    #[test]
+    fn neighbouring_reactors() {
+        let mut entities = vec![
+            reactor(1, 2.5, 2.5),
+            reactor(2, 7.5, 2.5),
+            reactor(3, 7.5, 7.5),
+            reactor(4, 30.5, 2.5),
+        ];
+        entities.extend(heat_pipes(5, 32.5, 2.5, 1));
+        let bp = blueprint(&entities.join(","), "");
+        let mut neighbours = reactor_neighbours(&bp, base_data());
+        for others in neighbours.values_mut() {
+            others.sort_unstable();
+        }
+
+        assert_eq!(neighbours.len(), 4);
+        assert_eq!(neighbours[&1], [2]);
+        assert_eq!(neighbours[&2], [1, 3]);
+        assert_eq!(neighbours[&3], [2]);
+
+        // heat pipes don't count as neighbours
+        assert!(neighbours[&4].is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! Sprite-less render of a blueprint: every tile colored by the entities on it.
+//!
+//! No graphics are loaded, only the entity prototypes are used for the footprints,
+//! so it stays fast for blueprints that are way too big for a sprite render.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use prototypes::{entity::Type as EntityType, Background, DataUtil};
+use types::{BoundingBox, MapPosition};
+
+use crate::bp_helper;
+
+/// Largest size of a tile in pixels, small blueprints don't need more detail.
+const MAX_TILE_PX: f64 = 16.0;
+
+/// Cells around a cell that count towards its density.
+const DENSITY_RADIUS: usize = 2;
+
+/// Behind the heatmap unless the background is transparent or a solid color.
+const BACKGROUND: Rgba<u8> = Rgba([0x1b, 0x1b, 0x1b, 0xff]);
+
+/// Color stops of the density gradient, from sparse to dense.
+const DENSITY_STOPS: [(f64, [u8; 3]); 4] = [
+    (0.0, [0x1e, 0x2a, 0x78]),
+    (0.35, [0x00, 0x9c, 0xb8]),
+    (0.7, [0xf0, 0xcc, 0x1e]),
+    (1.0, [0xe0, 0x28, 0x1e]),
+];
+
+/// What the heatmap colors show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Coloring {
+    /// Number of entities around each tile, from blue (sparse) to red (dense)
+    #[default]
+    Density,
+
+    /// Kind of the entity on each tile, e.g. belts yellow & inserters cyan
+    Class,
+}
+
+/// Rough kind of an entity, each drawn in its own color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityClass {
+    Belt,
+    Inserter,
+    Production,
+    Mining,
+    Fluid,
+    Power,
+    Rail,
+    Logistics,
+    Circuit,
+    Defense,
+    Other,
+}
+
+impl EntityClass {
+    #[must_use]
+    pub const fn from_type(ty: &EntityType) -> Self {
+        use EntityType as T;
+
+        match ty {
+            T::TransportBelt
+            | T::UndergroundBelt
+            | T::Splitter
+            | T::Loader
+            | T::Loader1x1
+            | T::LinkedBelt => Self::Belt,
+            T::Inserter => Self::Inserter,
+            T::AssemblingMachine | T::Furnace | T::RocketSilo | T::Lab | T::Beacon => {
+                Self::Production
+            }
+            T::MiningDrill | T::OffshorePump => Self::Mining,
+            T::Pipe | T::InfinityPipe | T::PipeToGround | T::Pump | T::StorageTank => Self::Fluid,
+            T::Accumulator
+            | T::Boiler
+            | T::BurnerGenerator
+            | T::ElectricEnergyInterface
+            | T::ElectricPole
+            | T::PowerSwitch
+            | T::Generator
+            | T::Reactor
+            | T::HeatInterface
+            | T::HeatPipe
+            | T::SolarPanel => Self::Power,
+            T::CurvedRail
+            | T::StraightRail
+            | T::RailSignal
+            | T::RailChainSignal
+            | T::TrainStop
+            | T::Locomotive
+            | T::CargoWagon
+            | T::FluidWagon
+            | T::ArtilleryWagon => Self::Rail,
+            T::Container
+            | T::LogisticContainer
+            | T::InfinityContainer
+            | T::LinkedContainer
+            | T::Roboport => Self::Logistics,
+            T::ArithmeticCombinator
+            | T::DeciderCombinator
+            | T::ConstantCombinator
+            | T::ProgrammableSpeaker
+            | T::Lamp => Self::Circuit,
+            T::Wall
+            | T::Gate
+            | T::Turret
+            | T::AmmoTurret
+            | T::ElectricTurret
+            | T::FluidTurret
+            | T::ArtilleryTurret
+            | T::LandMine
+            | T::Radar => Self::Defense,
+            _ => Self::Other,
+        }
+    }
+
+    #[must_use]
+    pub const fn color(self) -> Rgba<u8> {
+        let [r, g, b] = match self {
+            Self::Belt => [0xe8, 0xc0, 0x20],
+            Self::Inserter => [0x30, 0xc8, 0xe0],
+            Self::Production => [0x40, 0x70, 0xe8],
+            Self::Mining => [0x9a, 0x6a, 0x3a],
+            Self::Fluid => [0x20, 0xa8, 0x60],
+            Self::Power => [0xf0, 0x80, 0x20],
+            Self::Rail => [0x90, 0x90, 0x90],
+            Self::Logistics => [0xb0, 0x50, 0xd0],
+            Self::Circuit => [0xe0, 0x50, 0x90],
+            Self::Defense => [0xd0, 0x30, 0x30],
+            Self::Other => [0x60, 0x60, 0x68],
+        };
+
+        Rgba([r, g, b, 0xff])
+    }
+}
+
+/// Grid of cells covering the blueprint, each cell is `cell` tiles wide and drawn as `cell_px` pixels.
+struct Grid {
+    left: f64,
+    top: f64,
+    cell: f64,
+    cell_px: u32,
+    columns: usize,
+    rows: usize,
+}
+
+impl Grid {
+    /// Small blueprints get multiple pixels per tile, huge ones multiple tiles per pixel
+    /// so the image side stays around `target_res`.
+    fn new(bounds: &BoundingBox, target_res: f64) -> Option<Self> {
+        let width = (bounds.right() - bounds.left()).ceil();
+        let height = (bounds.bottom() - bounds.top()).ceil();
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+
+        let tile_px = target_res / (width * height).sqrt();
+        let (cell, cell_px) = if tile_px >= 1.0 {
+            (1.0, tile_px.min(MAX_TILE_PX).floor() as u32)
+        } else {
+            (tile_px.recip().ceil(), 1)
+        };
+
+        Some(Self {
+            left: bounds.left(),
+            top: bounds.top(),
+            cell,
+            cell_px,
+            columns: (width / cell).ceil() as usize,
+            rows: (height / cell).ceil() as usize,
+        })
+    }
+
+    fn cell_of(&self, x: f64, y: f64) -> usize {
+        let col = (((x - self.left) / self.cell) as usize).min(self.columns - 1);
+        let row = (((y - self.top) / self.cell) as usize).min(self.rows - 1);
+
+        row * self.columns + col
+    }
+
+    /// Cells touched by the tiles of `area`.
+    fn cells_of(&self, area: &BoundingBox) -> impl Iterator<Item = usize> + '_ {
+        // shrink slightly so boxes ending exactly on a tile border don't spill into the next tile
+        let (left, top) = (area.left() + 0.01, area.top() + 0.01);
+        let (right, bottom) = (
+            (area.right() - 0.01).max(left),
+            (area.bottom() - 0.01).max(top),
+        );
+
+        let first = self.cell_of(left, top);
+        let last = self.cell_of(right, bottom);
+        let (first_row, first_col) = (first / self.columns, first % self.columns);
+        let (last_row, last_col) = (last / self.columns, last % self.columns);
+
+        (first_row..=last_row)
+            .flat_map(move |row| (first_col..=last_col).map(move |col| row * self.columns + col))
+    }
+
+    fn image(&self, cells: &[Option<Rgba<u8>>], background: Option<Rgba<u8>>) -> DynamicImage {
+        let px = self.cell_px as usize;
+        let empty = background.unwrap_or(Rgba([0, 0, 0, 0]));
+
+        RgbaImage::from_fn(
+            (self.columns * px) as u32,
+            (self.rows * px) as u32,
+            |x, y| {
+                let (col, row) = (x as usize / px, y as usize / px);
+                cells[row * self.columns + col].unwrap_or(empty)
+            },
+        )
+        .into()
+    }
+}
+
+/// Footprints of the entities in map coordinates with their class.
+/// Entities without a prototype count as a single tile at their position.
+fn footprints(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+) -> Vec<(BoundingBox, MapPosition, EntityClass)> {
+    bp.entities
+        .iter()
+        .map(|e| {
+            let position = MapPosition::from(&e.position);
+            let area = bp_helper::entity_area(e, data).unwrap_or_else(|| {
+                BoundingBox(
+                    position + MapPosition::Tuple(-0.5, -0.5),
+                    position + MapPosition::Tuple(0.5, 0.5),
+                )
+            });
+            let class = data
+                .get_entity_type(&e.name)
+                .map_or(EntityClass::Other, EntityClass::from_type);
+
+            (area, position, class)
+        })
+        .collect()
+}
+
+fn density_color(value: f64) -> Rgba<u8> {
+    let value = value.clamp(0.0, 1.0);
+    let idx = DENSITY_STOPS
+        .iter()
+        .rposition(|(stop, _)| *stop <= value)
+        .unwrap_or_default()
+        .min(DENSITY_STOPS.len() - 2);
+
+    let (from, from_color) = DENSITY_STOPS[idx];
+    let (to, to_color) = DENSITY_STOPS[idx + 1];
+    let t = (value - from) / (to - from);
+
+    let channel = |c: usize| {
+        (f64::from(to_color[c]) - f64::from(from_color[c])).mul_add(t, f64::from(from_color[c]))
+            as u8
+    };
+
+    Rgba([channel(0), channel(1), channel(2), 0xff])
+}
+
+/// Entity counts summed over a square of `radius` cells around every cell.
+fn neighborhood_sums(counts: &[u32], columns: usize, rows: usize, radius: usize) -> Vec<u32> {
+    // summed-area table with an extra zero row & column
+    let stride = columns + 1;
+    let mut table = vec![0u32; stride * (rows + 1)];
+    for row in 0..rows {
+        for col in 0..columns {
+            table[(row + 1) * stride + col + 1] = counts[row * columns + col]
+                + table[row * stride + col + 1]
+                + table[(row + 1) * stride + col]
+                - table[row * stride + col];
+        }
+    }
+
+    let mut res = Vec::with_capacity(counts.len());
+    for row in 0..rows {
+        let (top, bottom) = (row.saturating_sub(radius), (row + radius + 1).min(rows));
+        for col in 0..columns {
+            let (left, right) = (col.saturating_sub(radius), (col + radius + 1).min(columns));
+
+            res.push(
+                table[bottom * stride + right] + table[top * stride + left]
+                    - table[top * stride + right]
+                    - table[bottom * stride + left],
+            );
+        }
+    }
+
+    res
+}
+
+/// Renders the heatmap of the blueprint entities with roughly `target_res` pixels per side.
+/// Returns `None` if the blueprint has no entities.
+#[must_use]
+pub fn heatmap(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    target_res: f64,
+    coloring: Coloring,
+    background: Background,
+) -> Option<DynamicImage> {
+    let footprints = footprints(bp, data);
+    let bounds = footprints
+        .iter()
+        .map(|(area, _, _)| area.clone())
+        .reduce(|a, b| a.union(&b))?;
+    let bounds = BoundingBox(
+        MapPosition::Tuple(bounds.left().floor(), bounds.top().floor()),
+        MapPosition::Tuple(bounds.right().ceil(), bounds.bottom().ceil()),
+    );
+
+    let grid = Grid::new(&bounds, target_res)?;
+    let cell_count = grid.columns * grid.rows;
+
+    let cells = match coloring {
+        Coloring::Density => {
+            let mut counts = vec![0u32; cell_count];
+            for (_, position, _) in &footprints {
+                let (x, y) = position.as_tuple();
+                counts[grid.cell_of(x, y)] += 1;
+            }
+
+            let mut occupied = vec![false; cell_count];
+            for (area, _, _) in &footprints {
+                for cell in grid.cells_of(area) {
+                    occupied[cell] = true;
+                }
+            }
+
+            let sums = neighborhood_sums(&counts, grid.columns, grid.rows, DENSITY_RADIUS);
+            let max = sums.iter().copied().max().unwrap_or_default().max(1);
+
+            sums.into_iter()
+                .zip(occupied)
+                .map(|(sum, occupied)| {
+                    occupied.then(|| density_color(f64::from(sum) / f64::from(max)))
+                })
+                .collect::<Vec<_>>()
+        }
+        Coloring::Class => {
+            let mut cells = vec![None; cell_count];
+            for (area, _, class) in &footprints {
+                for cell in grid.cells_of(area) {
+                    cells[cell] = Some(class.color());
+                }
+            }
+
+            cells
+        }
+    };
+
+    let background = match background {
+        Background::Transparent => None,
+        Background::Solid(color) => Some(color),
+        _ => Some(BACKGROUND),
+    };
+
+    Some(grid.image(&cells, background))
+}
@@ -0,0 +1,175 @@
+//! Export of all item, fluid, recipe & virtual signal icons, e.g. for web UIs that need the exact modded icon set.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Result, ResultExt};
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use mod_util::UsedMods;
+use prototypes::DataUtil;
+use types::ImageCache;
+
+use crate::ScannerError;
+
+/// File name of the index written next to the icon directories.
+pub const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IconKind {
+    Item,
+    Fluid,
+    Recipe,
+    VirtualSignal,
+}
+
+impl IconKind {
+    pub const ALL: [Self; 4] = [Self::Item, Self::Fluid, Self::Recipe, Self::VirtualSignal];
+
+    /// Name of the directory the icons of this kind are written to.
+    #[must_use]
+    pub const fn dir_name(self) -> &'static str {
+        match self {
+            Self::Item => "item",
+            Self::Fluid => "fluid",
+            Self::Recipe => "recipe",
+            Self::VirtualSignal => "virtual-signal",
+        }
+    }
+
+    fn names(self, data: &DataUtil) -> BTreeSet<&str> {
+        match self {
+            Self::Item => data.item_names().collect(),
+            Self::Fluid => data.fluid_names().collect(),
+            Self::Recipe => data.recipe_names().collect(),
+            Self::VirtualSignal => data.signal_names().collect(),
+        }
+    }
+
+    fn icon(
+        self,
+        name: &str,
+        data: &DataUtil,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        match self {
+            Self::Item => data.get_item_icon(name, scale, used_mods, image_cache),
+            Self::Fluid => data.get_fluid_icon(name, scale, used_mods, image_cache),
+            Self::Recipe => data.get_recipe_icon(name, scale, used_mods, image_cache),
+            Self::VirtualSignal => data.get_signal_icon(name, scale, used_mods, image_cache),
+        }
+    }
+}
+
+/// Exported icon, the path is relative to the output directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct IconEntry {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Contents of the [`INDEX_FILE`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IconIndex {
+    pub scale: f64,
+
+    /// Exported icons by kind & prototype name.
+    pub icons: BTreeMap<IconKind, BTreeMap<String, IconEntry>>,
+
+    /// Prototypes whose icon could not be rendered, by kind.
+    pub missing: BTreeMap<IconKind, BTreeSet<String>>,
+}
+
+impl IconIndex {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.icons.values().map(BTreeMap::len).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Renders the icon of every item, fluid, recipe & virtual signal at `scale` (0.5 gives 64px icons)
+/// and writes them as `<kind>/<name>.png` together with the [`INDEX_FILE`] into `out_dir`.
+#[instrument(skip_all, fields(scale))]
+pub fn export(
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    scale: f64,
+    out_dir: &Path,
+) -> Result<IconIndex, ScannerError> {
+    let mut image_cache = ImageCache::new();
+    let mut index = IconIndex {
+        scale,
+        ..Default::default()
+    };
+
+    for kind in IconKind::ALL {
+        let dir = out_dir.join(kind.dir_name());
+        fs::create_dir_all(&dir)
+            .change_context(ScannerError::SetupError)
+            .attach_printable_lazy(|| format!("failed to create {}", dir.display()))?;
+
+        for name in kind.names(data) {
+            let Some((img, _)) = kind.icon(name, data, scale, used_mods, &mut image_cache) else {
+                debug!("no icon for {} {name}", kind.dir_name());
+                index
+                    .missing
+                    .entry(kind)
+                    .or_default()
+                    .insert(name.to_owned());
+                continue;
+            };
+
+            let path = Path::new(kind.dir_name()).join(format!("{}.png", file_name(name)));
+            fs::write(out_dir.join(&path), crate::encode_png(&img)?)
+                .change_context(ScannerError::RenderError)
+                .attach_printable_lazy(|| format!("failed to write {}", path.display()))?;
+
+            index.icons.entry(kind).or_default().insert(
+                name.to_owned(),
+                IconEntry {
+                    path,
+                    width: img.width(),
+                    height: img.height(),
+                },
+            );
+        }
+    }
+
+    let index_path = out_dir.join(INDEX_FILE);
+    fs::write(
+        &index_path,
+        serde_json::to_vec_pretty(&index).change_context(ScannerError::RenderError)?,
+    )
+    .change_context(ScannerError::RenderError)
+    .attach_printable_lazy(|| format!("failed to write {}", index_path.display()))?;
+
+    Ok(index)
+}
+
+/// Prototype names are used as file names, anything that could escape the directory is replaced.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_owned()
+}
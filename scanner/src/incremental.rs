@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+
+use error_stack::Result;
+use tracing::info;
+
+use mod_util::UsedMods;
+use prototypes::{
+    diagnostics::Diagnostics, DataUtil, EntityWireConnections, InternalRenderLayer, PixelRect,
+    RenderLayerBuffer, RenderOptions, TargetSize,
+};
+use types::{BoundingBox, ImageCache};
+
+use crate::{
+    bp_helper,
+    job::{RenderJob, RenderJobError},
+};
+
+/// Entities within this many tiles of a changed entity get re-rendered as well,
+/// since belts, pipes & walls depend on their neighbors.
+const NEIGHBOR_DISTANCE: f64 = 1.0;
+
+/// Change to a single entity of an [`IncrementalRender`].
+#[derive(Debug, Clone)]
+pub enum EntityChange {
+    /// Adds the entity, an existing entity with the same entity number gets replaced.
+    Added(Box<blueprint::Entity>),
+    Removed(blueprint::EntityNumber),
+    Moved {
+        entity_number: blueprint::EntityNumber,
+        position: blueprint::Position,
+    },
+}
+
+/// A render that can be updated with small changes to its entities, e.g. for an editor.
+///
+/// The layers of the initial render are kept and only the pixels around changed entities
+/// get re-rendered, together with all entities that overlap them. Wires are redrawn completely.
+/// The image keeps the size of the initial render and overlays are not supported.
+pub struct IncrementalRender<'a> {
+    bp: blueprint::Blueprint,
+    data: &'a DataUtil,
+    used_mods: &'a UsedMods,
+    options: RenderOptions,
+
+    layers: RenderLayerBuffer,
+    image_cache: ImageCache,
+}
+
+impl std::fmt::Debug for IncrementalRender<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IncrementalRender")
+            .field("entities", &self.bp.entities.len())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> IncrementalRender<'a> {
+    /// Renders the whole blueprint once.
+    pub fn new(
+        bp: blueprint::Blueprint,
+        data: &'a DataUtil,
+        used_mods: &'a UsedMods,
+        size: TargetSize,
+        options: RenderOptions,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Self, RenderJobError> {
+        let mut image_cache = ImageCache::new();
+        let layers = RenderJob::new(&bp, data, used_mods)
+            .options(options)
+            .run(RenderLayerBuffer::new(size), &mut image_cache, diagnostics)?
+            .layers;
+
+        Ok(Self {
+            bp,
+            data,
+            used_mods,
+            options,
+            layers,
+            image_cache,
+        })
+    }
+
+    #[must_use]
+    pub const fn blueprint(&self) -> &blueprint::Blueprint {
+        &self.bp
+    }
+
+    /// The current state of the render.
+    #[must_use]
+    pub fn image(&mut self) -> image::DynamicImage {
+        self.layers.combine()
+    }
+
+    /// Applies the changes to the blueprint and re-renders the affected area.
+    /// Returns the area of the image that changed, `None` if nothing changed.
+    pub fn apply(
+        &mut self,
+        changes: impl IntoIterator<Item = EntityChange>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Option<PixelRect>, RenderJobError> {
+        let mut dirty = PixelRect::new(0, 0, 0, 0);
+        let mut affected = HashSet::new();
+        let mut touched = Vec::new();
+
+        for change in changes {
+            let entity_number = match &change {
+                EntityChange::Added(e) => e.entity_number,
+                EntityChange::Removed(entity_number)
+                | EntityChange::Moved { entity_number, .. } => *entity_number,
+            };
+
+            if let Some(idx) = self
+                .bp
+                .entities
+                .iter()
+                .position(|e| e.entity_number == entity_number)
+            {
+                touched.extend(bp_helper::entity_area(&self.bp.entities[idx], self.data));
+                if let Some(region) = self.layers.entity_region(entity_number) {
+                    dirty = dirty.union(&region);
+                }
+
+                match change {
+                    EntityChange::Added(e) => self.bp.entities[idx] = *e,
+                    EntityChange::Removed(_) => {
+                        self.bp.entities.remove(idx);
+                    }
+                    EntityChange::Moved { position, .. } => {
+                        self.bp.entities[idx].position = position;
+                    }
+                }
+            } else if let EntityChange::Added(e) = change {
+                self.bp.entities.push(*e);
+            } else {
+                continue;
+            }
+
+            self.layers.forget_entity(entity_number);
+            affected.insert(entity_number);
+        }
+
+        if affected.is_empty() {
+            return Ok(None);
+        }
+
+        touched.extend(
+            self.bp
+                .entities
+                .iter()
+                .filter(|e| affected.contains(&e.entity_number))
+                .filter_map(|e| bp_helper::entity_area(e, self.data)),
+        );
+
+        // neighbors that might connect differently now
+        for e in &self.bp.entities {
+            let Some(area) = self.neighbor_area(e) else {
+                continue;
+            };
+
+            if touched.iter().any(|t| area.intersects(t)) {
+                affected.insert(e.entity_number);
+            }
+        }
+
+        // the new regions of the affected entities are only known after rendering them
+        let mut changed_layers = self.new_layers();
+        self.render_entities(&affected, &mut changed_layers, diagnostics)?;
+        for &entity_number in &affected {
+            for region in [
+                self.layers.entity_region(entity_number),
+                changed_layers.entity_region(entity_number),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                dirty = dirty.union(&region);
+            }
+        }
+
+        // everything else that overlaps the dirty area has to be redrawn as well
+        let mut overlapping = self
+            .bp
+            .entities
+            .iter()
+            .map(|e| e.entity_number)
+            .filter(|n| {
+                !affected.contains(n)
+                    && self
+                        .layers
+                        .entity_region(*n)
+                        .is_some_and(|region| region.intersects(&dirty))
+            })
+            .collect::<HashSet<_>>();
+
+        info!(
+            "re-rendering {} changed & {} overlapping entities",
+            affected.len(),
+            overlapping.len()
+        );
+
+        // entities are drawn in blueprint order, the overlapping ones can only be drawn
+        // on top of the already rendered ones if they come after them
+        if !self.draws_after(&overlapping, &affected) {
+            overlapping.extend(affected);
+            changed_layers = self.new_layers();
+        }
+        self.render_entities(&overlapping, &mut changed_layers, diagnostics)?;
+
+        self.layers.merge(changed_layers, dirty);
+        self.redraw_wires();
+
+        let size = self.layers.target_size();
+        let dirty = dirty.clamp(size.width(), size.height());
+        Ok((!dirty.is_empty()).then_some(dirty))
+    }
+
+    /// Area of the entity grown by [`NEIGHBOR_DISTANCE`].
+    fn neighbor_area(&self, e: &blueprint::Entity) -> Option<BoundingBox> {
+        bp_helper::entity_area(e, self.data).map(|area| area.expand(NEIGHBOR_DISTANCE))
+    }
+
+    fn new_layers(&self) -> RenderLayerBuffer {
+        RenderLayerBuffer::new(self.layers.target_size().clone())
+    }
+
+    /// Whether all entities of `later` come after the ones of `earlier` in the blueprint.
+    fn draws_after(&self, later: &HashSet<u64>, earlier: &HashSet<u64>) -> bool {
+        let mut seen_later = false;
+
+        for e in &self.bp.entities {
+            if later.contains(&e.entity_number) {
+                seen_later = true;
+            } else if seen_later && earlier.contains(&e.entity_number) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn render_entities(
+        &mut self,
+        entities: &HashSet<u64>,
+        layers: &mut RenderLayerBuffer,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(), RenderJobError> {
+        if entities.is_empty() {
+            return Ok(());
+        }
+
+        RenderJob::new(&self.bp, self.data, self.used_mods)
+            .options(self.options)
+            .only_entities(entities)
+            .run_entities(layers, &mut self.image_cache, diagnostics)
+    }
+
+    fn redraw_wires(&mut self) {
+        self.layers.clear_layer(InternalRenderLayer::Wire);
+
+        let Some(util_sprites) = self.data.util_sprites().filter(|_| self.options.wires) else {
+            return;
+        };
+
+        let mut wire_connections = EntityWireConnections::new();
+        for (entity_number, connections) in bp_helper::wire_connections(&self.bp, self.data) {
+            let Some(e) = self
+                .bp
+                .entities
+                .iter()
+                .find(|e| e.entity_number == entity_number)
+            else {
+                continue;
+            };
+
+            wire_connections.insert(entity_number, ((&e.position).into(), connections));
+        }
+
+        self.layers.draw_wires(
+            &wire_connections,
+            util_sprites,
+            self.used_mods,
+            &mut self.image_cache,
+            &self.options,
+        );
+    }
+}
@@ -0,0 +1,1336 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use error_stack::{report, Context, Result};
+use image::imageops;
+use imageproc::geometric_transformations::{self, rotate_about_center};
+use serde::Serialize;
+use tracing::{info, instrument, warn};
+
+use mod_util::UsedMods;
+use prototypes::{
+    diagnostics::{DiagnosticKind, Diagnostics},
+    entity::{InserterPrototype, Type as EntityType, WallPrototype},
+    tile::TilePrototype,
+    utility_sprites::UtilitySprites,
+    DataUtil, DataUtilAccess, EntityWireConnections, IconStyle, InternalRenderLayer,
+    RenderLayerBuffer, RenderOptions,
+};
+use types::{
+    ConnectedDirections, Direction, GraphicsOutput, ImageCache, MapPosition, RenderableGraphics,
+    SimpleGraphicsRenderOpts, Vector,
+};
+
+use crate::{
+    bp_entity2render_opts, bp_helper, elapsed_ms, overlay, telemetry, text, RenderTimings,
+    TypeTiming,
+};
+
+#[derive(Debug)]
+pub enum RenderJobError {
+    Cancelled(RenderStage),
+    MissingSprites(&'static str),
+    Placeholders(Vec<String>),
+}
+
+impl Context for RenderJobError {}
+
+impl std::fmt::Display for RenderJobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled(stage) => write!(f, "render was cancelled during the {stage:?} stage"),
+            Self::MissingSprites(name) => write!(f, "failed to load the {name} sprites"),
+            Self::Placeholders(names) => {
+                write!(f, "failed to render entities: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+/// Flag to abort a running [`RenderJob`], can be shared with other threads.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Stages of a [`RenderJob`] in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderStage {
+    Entities,
+    Tiles,
+    Wires,
+    Overlays,
+    Done,
+}
+
+/// Progress of a [`RenderJob`], `done` out of `total` items of the current stage are finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RenderProgress {
+    pub stage: RenderStage,
+    pub done: usize,
+    pub total: usize,
+}
+
+type ProgressCallback<'a> = Box<dyn Fn(RenderProgress) + Send + Sync + 'a>;
+type FluidConnections = HashMap<MapPosition, HashSet<Direction>>;
+
+/// Sprites & fonts for alt-mode that are shared by all entities.
+struct AltModeAssets<'a> {
+    util_sprites: &'a UtilitySprites,
+    indicator_arrow: GraphicsOutput,
+    indicator_line: GraphicsOutput,
+    font: Option<text::Font>,
+}
+
+/// Result of [`RenderJob::run`].
+#[derive(Debug)]
+pub struct RenderJobOutput {
+    pub layers: RenderLayerBuffer,
+
+    /// Prototype names that are not part of the loaded data.
+    pub unknown: HashSet<String>,
+
+    /// Names of the entities that were drawn as placeholders, sorted.
+    pub placeholders: Vec<String>,
+}
+
+/// Renders a blueprint into a [`RenderLayerBuffer`] stage by stage.
+///
+/// The job checks its [`CancellationToken`] between every entity & tile and reports its
+/// progress to an optional callback, so long renders can be aborted and monitored.
+pub struct RenderJob<'a> {
+    bp: &'a blueprint::Blueprint,
+    data: &'a DataUtil,
+    used_mods: &'a UsedMods,
+    overlays: &'a [overlay::Overlay],
+    animation_progress: f64,
+    options: RenderOptions,
+    entity_filter: Option<&'a HashSet<u64>>,
+
+    cancel: CancellationToken,
+    progress: Option<ProgressCallback<'a>>,
+    timings: Option<&'a Mutex<RenderTimings>>,
+}
+
+impl std::fmt::Debug for RenderJob<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderJob")
+            .field("overlays", &self.overlays)
+            .field("animation_progress", &self.animation_progress)
+            .field("options", &self.options)
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> RenderJob<'a> {
+    #[must_use]
+    pub fn new(bp: &'a blueprint::Blueprint, data: &'a DataUtil, used_mods: &'a UsedMods) -> Self {
+        Self {
+            bp,
+            data,
+            used_mods,
+            overlays: &[],
+            animation_progress: 0.0,
+            options: RenderOptions::default(),
+            entity_filter: None,
+            cancel: CancellationToken::default(),
+            progress: None,
+            timings: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn overlays(mut self, overlays: &'a [overlay::Overlay]) -> Self {
+        self.overlays = overlays;
+        self
+    }
+
+    /// Point in the animation cycle of animated entities like belts, from 0 to 1.
+    #[must_use]
+    pub const fn animation_progress(mut self, progress: f64) -> Self {
+        self.animation_progress = progress;
+        self
+    }
+
+    #[must_use]
+    pub const fn options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Only render the entities with the given entity numbers, the others are still used as neighbors.
+    #[must_use]
+    pub(crate) const fn only_entities(mut self, entity_numbers: &'a HashSet<u64>) -> Self {
+        self.entity_filter = Some(entity_numbers);
+        self
+    }
+
+    #[must_use]
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    #[must_use]
+    pub fn on_progress(mut self, callback: impl Fn(RenderProgress) + Send + Sync + 'a) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Record the time of the entity, tile, wire & overlay stages and of the entities per prototype type.
+    #[must_use]
+    pub const fn timings(mut self, timings: &'a Mutex<RenderTimings>) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    fn record(&self, record: impl FnOnce(&mut RenderTimings)) {
+        if let Some(timings) = self.timings {
+            record(
+                &mut timings
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner),
+            );
+        }
+    }
+
+    fn report(&self, stage: RenderStage, done: usize, total: usize) {
+        if let Some(callback) = &self.progress {
+            callback(RenderProgress { stage, done, total });
+        }
+    }
+
+    fn check(&self, stage: RenderStage) -> Result<(), RenderJobError> {
+        if self.cancel.is_cancelled() {
+            return Err(report!(RenderJobError::Cancelled(stage)));
+        }
+
+        Ok(())
+    }
+
+    /// Runs all stages and returns the rendered layers with the names of unknown prototypes & placeholders.
+    /// Problems of individual entities are recorded in `diagnostics`.
+    #[instrument(skip_all)]
+    pub fn run(
+        &self,
+        mut render_layers: RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<RenderJobOutput, RenderJobError> {
+        let assets = self.load_assets(render_layers.scale(), image_cache)?;
+        let mut unknown = HashSet::new();
+        let mut wire_connections = EntityWireConnections::new();
+        self.skip_layers(&mut render_layers);
+
+        let start = Instant::now();
+        let mut stage = start;
+        let placeholders = self.render_entities(
+            &mut render_layers,
+            image_cache,
+            &assets,
+            &mut unknown,
+            &mut wire_connections,
+            diagnostics,
+        );
+        telemetry::stage_finished(telemetry::Stage::Entities, start);
+        let entities = elapsed_ms(&mut stage);
+        self.record(|timings| timings.entities = entities);
+        self.check(RenderStage::Entities)?;
+
+        let mut placeholders = placeholders.into_iter().collect::<Vec<_>>();
+        placeholders.sort_unstable();
+        if !placeholders.is_empty() {
+            warn!("rendered placeholders for: {}", placeholders.join(", "));
+
+            if self.options.strict {
+                return Err(report!(RenderJobError::Placeholders(placeholders)));
+            }
+        }
+
+        let mut stage = Instant::now();
+        self.render_tiles(&mut render_layers, image_cache, &mut unknown);
+        let tiles = elapsed_ms(&mut stage);
+        self.record(|timings| timings.tiles = tiles);
+        self.check(RenderStage::Tiles)?;
+
+        self.report(RenderStage::Wires, 0, 1);
+        if self.options.wires {
+            let start = stage;
+            render_layers.draw_wires(
+                &wire_connections,
+                assets.util_sprites,
+                self.used_mods,
+                image_cache,
+                &self.options,
+            );
+            telemetry::stage_finished(telemetry::Stage::Wires, start);
+        }
+        let wires = elapsed_ms(&mut stage);
+        self.record(|timings| timings.wires = wires);
+        self.check(RenderStage::Wires)?;
+
+        self.draw_overlays(&mut render_layers, image_cache, &assets);
+        let overlays = elapsed_ms(&mut stage);
+        self.record(|timings| timings.overlays = overlays);
+        self.check(RenderStage::Overlays)?;
+
+        render_layers.generate_background(&self.options);
+        self.report(RenderStage::Done, 1, 1);
+
+        Ok(RenderJobOutput {
+            layers: render_layers,
+            unknown,
+            placeholders,
+        })
+    }
+
+    /// Renders only the entities without their wires, e.g. to update parts of an existing render.
+    pub(crate) fn run_entities(
+        &self,
+        render_layers: &mut RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(), RenderJobError> {
+        let assets = self.load_assets(render_layers.scale(), image_cache)?;
+        self.skip_layers(render_layers);
+
+        self.render_entities(
+            render_layers,
+            image_cache,
+            &assets,
+            &mut HashSet::new(),
+            &mut EntityWireConnections::new(),
+            diagnostics,
+        );
+        self.check(RenderStage::Entities)
+    }
+
+    fn skip_layers(&self, render_layers: &mut RenderLayerBuffer) {
+        if !self.options.shadows {
+            render_layers.skip_layer(InternalRenderLayer::Shadow);
+        }
+        if self.options.icon_style == IconStyle::Plain {
+            render_layers.skip_layer(InternalRenderLayer::IconOutline);
+        }
+        render_layers.set_icon_style(self.options.icon_style);
+
+        if self.options.night {
+            render_layers.set_darkness(RenderOptions::NIGHT_DARKNESS);
+        } else {
+            render_layers.skip_layer(InternalRenderLayer::Light);
+        }
+    }
+
+    fn load_assets(
+        &self,
+        scale: f64,
+        image_cache: &mut ImageCache,
+    ) -> Result<AltModeAssets<'a>, RenderJobError> {
+        let Some(util_sprites) = self.data.util_sprites() else {
+            return Err(report!(RenderJobError::MissingSprites("util sprites")));
+        };
+
+        let Some(indicator_arrow) = util_sprites.indication_arrow.render(
+            scale * 1.25,
+            self.used_mods,
+            image_cache,
+            &SimpleGraphicsRenderOpts::default(),
+        ) else {
+            return Err(report!(RenderJobError::MissingSprites("indicator arrow")));
+        };
+
+        let Some(indicator_line) = util_sprites.indication_line.render(
+            scale * 1.25,
+            self.used_mods,
+            image_cache,
+            &SimpleGraphicsRenderOpts::default(),
+        ) else {
+            return Err(report!(RenderJobError::MissingSprites("indicator line")));
+        };
+
+        // only needed for the counts of logistic requests
+        let font = (self.options.alt_mode
+            && self
+                .bp
+                .entities
+                .iter()
+                .any(|e| !e.request_filters.is_empty()))
+        .then(|| text::Font::load(self.used_mods, text::DEFAULT_FONT))
+        .flatten();
+
+        Ok(AltModeAssets {
+            util_sprites,
+            indicator_arrow,
+            indicator_line,
+            font,
+        })
+    }
+
+    fn fluid_connections(&self) -> (FluidConnections, FluidConnections) {
+        let Self { bp, data, .. } = *self;
+        let mut pipe_connections = FluidConnections::new();
+        let mut heat_connections = FluidConnections::new();
+
+        // pipe / heat connections
+        bp.entities.iter().for_each(|e| {
+            let Some(e_data) = data.get_entity(&e.name) else {
+                return;
+            };
+
+            let options = bp_entity2render_opts(e, data);
+            e_data
+                .pipe_connections(&options)
+                .iter()
+                .copied()
+                .for_each(|(pos, dir)| {
+                    pipe_connections.entry(pos).or_default().insert(dir);
+                });
+            e_data
+                .heat_connections(&options)
+                .iter()
+                .copied()
+                .for_each(|(pos, dir)| {
+                    heat_connections.entry(pos).or_default().insert(dir);
+                });
+        });
+
+        (pipe_connections, heat_connections)
+    }
+
+    /// Renders all entities with their alt-mode icons and collects their wire connections.
+    /// Returns the names of entities that were drawn as placeholders.
+    #[allow(clippy::too_many_lines)]
+    fn render_entities(
+        &self,
+        render_layers: &mut RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+        assets: &AltModeAssets,
+        unknown: &mut HashSet<String>,
+        wire_connections: &mut EntityWireConnections,
+        diagnostics: &mut Diagnostics,
+    ) -> HashSet<String> {
+        let Self {
+            bp,
+            data,
+            used_mods,
+            animation_progress,
+            options,
+            ..
+        } = *self;
+        let AltModeAssets {
+            util_sprites,
+            indicator_arrow,
+            indicator_line,
+            font,
+        } = assets;
+
+        let (pipe_connections, heat_connections) = self.fluid_connections();
+        let mut entity_wires = bp_helper::wire_connections(bp, data);
+        let mut placeholders = HashSet::new();
+        let mut type_timings = BTreeMap::<String, TypeTiming>::new();
+
+        // render entities
+        let entities = bp
+            .entities
+            .iter()
+            .filter(|e| {
+                self.entity_filter
+                    .is_none_or(|filter| filter.contains(&e.entity_number))
+            })
+            .collect::<Vec<_>>();
+        let total = entities.len();
+        let rendered_count = entities
+            .into_iter()
+            .enumerate()
+            .take_while(|_| !self.cancel.is_cancelled())
+            .filter_map(|(idx, e)| {
+                let Some(e_data) = data.get_entity(&e.name) else {
+                    unknown.insert((*e.name).clone());
+                    diagnostics.error(
+                        &e.name,
+                        &bp_entity2render_opts(e, data),
+                        DiagnosticKind::UnknownEntity,
+                    );
+                    self.report(RenderStage::Entities, idx + 1, total);
+                    return None;
+                };
+
+                render_layers.begin_entity(e.entity_number);
+                let start = self.timings.map(|_| Instant::now());
+
+                let mut connected_gates: Vec<Direction> = Vec::new();
+                let mut draw_gate_patch = false;
+                let connections = data.get_entity_type(&e.name).and_then(|entity_type| {
+                    if entity_type.connectable() {
+                        let mut up = false;
+                        let mut down = false;
+                        let mut left = false;
+                        let mut right = false;
+
+                        let pos: types::MapPosition = (&e.position).into();
+
+                        match entity_type {
+                            EntityType::Pipe | EntityType::InfinityPipe | EntityType::PipeToGround => {
+                                for (p, dirs) in &pipe_connections {
+                                    if p.is_close(&pos, 0.5) {
+                                        for dir in dirs {
+                                            match dir {
+                                                Direction::North => up = true,
+                                                Direction::South => down = true,
+                                                Direction::East => right = true,
+                                                Direction::West => left = true,
+                                                _ => {}
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            EntityType::HeatPipe | EntityType::HeatInterface => {
+                                for (p, dirs) in &heat_connections {
+                                    if p.is_close(&pos, 0.5) {
+                                        for dir in dirs {
+                                            match dir {
+                                                Direction::North => up = true,
+                                                Direction::South => down = true,
+                                                Direction::East => right = true,
+                                                Direction::West => left = true,
+                                                _ => {}
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            _ => {
+                                for other in &bp.entities {
+                                    if other == e {
+                                        continue;
+                                    }
+
+                                    let Some(other_type) = data.get_entity_type(&other.name) else {
+                                        continue;
+                                    };
+
+                                    if !entity_type.can_connect_to(other_type) {
+                                        continue;
+                                    }
+
+                                    if matches!(entity_type, EntityType::Wall)
+                                        && matches!(other_type, EntityType::Wall)
+                                    {
+                                        let Some(src) = data
+                                            .get_proto::<WallPrototype>(&e.name)
+                                            .map(|p| p.visual_merge_group)
+                                        else {
+                                            continue;
+                                        };
+
+                                        let Some(dst) = data
+                                            .get_proto::<WallPrototype>(&other.name)
+                                            .map(|p| p.visual_merge_group)
+                                        else {
+                                            continue;
+                                        };
+
+                                        if src != dst {
+                                            continue;
+                                        }
+                                    }
+
+                                    let other_pos: types::MapPosition = (&other.position).into();
+
+                                    match entity_type {
+                                        EntityType::Gate
+                                            if pos.is_cardinal_neighbor(&other_pos)
+                                                == Some(Direction::South) =>
+                                        {
+                                            draw_gate_patch = true;
+                                        }
+                                        EntityType::Wall => {
+                                            if let Some(dir) = pos.is_cardinal_neighbor(&other_pos) {
+                                                if matches!(other_type, EntityType::Gate) {
+                                                    if dir.is_straight(&other.direction.eight_way()) {
+                                                        connected_gates.push(dir);
+                                                    }
+                                                } else {
+                                                    match dir {
+                                                        Direction::North => up = true,
+                                                        Direction::South => down = true,
+                                                        Direction::East => right = true,
+                                                        Direction::West => left = true,
+                                                        _ => {}
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        EntityType::TransportBelt => {
+                                            let neighbor = match other_type {
+                                                EntityType::TransportBelt => {
+                                                    pos.is_cardinal_neighbor(&other_pos)
+                                                }
+                                                EntityType::UndergroundBelt
+                                                | EntityType::LinkedBelt => {
+                                                    let dir = pos.is_cardinal_neighbor(&other_pos);
+
+                                                    if let Some(dir) = dir {
+                                                        let Some(u_output) =
+                                                            other.type_.as_ref().map(|t| {
+                                                                matches!(
+                                                                    t,
+                                                                    blueprint::UndergroundType::Output
+                                                                )
+                                                            })
+                                                        else {
+                                                            continue;
+                                                        };
+
+                                                        let other_dir = if u_output {
+                                                            other.direction.eight_way().flip()
+                                                        } else {
+                                                            other.direction.eight_way()
+                                                        };
+
+                                                        if dir != other_dir {
+                                                            continue;
+                                                        }
+                                                    }
+
+                                                    dir
+                                                }
+                                                EntityType::Splitter => {
+                                                    pos.is_2wide_cardinal_neighbor(&other_pos)
+                                                }
+                                                EntityType::Loader => {
+                                                    pos.is_2long_cardinal_neighbor(&other_pos)
+                                                }
+                                                _ => continue,
+                                            };
+
+                                            if let Some(dir) = neighbor {
+                                                if dir != other.direction.eight_way().flip() {
+                                                    continue;
+                                                }
+
+                                                match dir {
+                                                    Direction::North => up = true,
+                                                    Direction::South => down = true,
+                                                    Direction::East => right = true,
+                                                    Direction::West => left = true,
+                                                    _ => {}
+                                                }
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                        }
+
+                        Some(ConnectedDirections::from_directions(up, down, left, right))
+                    } else {
+                        None
+                    }
+                });
+
+                let mut render_opts = bp_entity2render_opts(e, data);
+                render_opts.connections = connections;
+                render_opts.connected_gates = connected_gates;
+                render_opts.draw_gate_patch = draw_gate_patch;
+                render_opts.circuit_connected = entity_wires.contains_key(&e.entity_number);
+                render_opts.seed = options.seed;
+                render_opts.progress = animation_progress;
+
+                'recipe_icon: {
+                    if !e.recipe.is_empty() && e_data.recipe_visible() {
+                        if !data.contains_recipe(&e.recipe) {
+                            unknown.insert((*e.recipe).clone());
+                            diagnostics.warn(
+                                &e.name,
+                                &render_opts,
+                                DiagnosticKind::UnknownRecipe {
+                                    recipe: (*e.recipe).clone(),
+                                },
+                            );
+                            break 'recipe_icon;
+                        }
+
+                        if !options.alt_mode {
+                            break 'recipe_icon;
+                        }
+
+                        if let Some(icon) = data.get_recipe_icon(
+                            &e.recipe,
+                            render_layers.scale() * 0.75,
+                            used_mods,
+                            image_cache,
+                        ) {
+                            render_layers.add(
+                                icon,
+                                &render_opts.position,
+                                InternalRenderLayer::IconOverlay,
+                            );
+                        } else {
+                            warn!(
+                                "failed to render recipe icon for {} at {:?} [{}]",
+                                e.recipe, e.position, e.name
+                            );
+                            diagnostics.warn(
+                                &e.name,
+                                &render_opts,
+                                DiagnosticKind::MissingIcon {
+                                    icon: (*e.recipe).clone(),
+                                },
+                            );
+                        }
+                    }
+                }
+
+                // filter icons / priority arrows
+                'filters_priority: {
+                    if !options.alt_mode {
+                        break 'filters_priority;
+                    }
+
+                    if let Some(prio_in) = &e.input_priority {
+                        let offset = e.direction.rotate_vector(
+                            prio_in.as_vector() + Vector::Tuple(0.0, 0.25) + indicator_arrow.1,
+                        );
+
+                        let arrow = match e.direction.eight_way() {
+                            Direction::North => indicator_arrow.0.clone(),
+                            Direction::East => imageops::rotate90(&indicator_arrow.0).into(),
+                            Direction::South => imageops::rotate180(&indicator_arrow.0).into(),
+                            Direction::West => imageops::rotate270(&indicator_arrow.0).into(),
+                            _ => break 'filters_priority,
+                        };
+
+                        render_layers.add(
+                            (arrow, offset),
+                            &render_opts.position,
+                            InternalRenderLayer::DirectionOverlay,
+                        );
+                    }
+
+                    if let Some(prio_out) = &e.output_priority {
+                        if let Some(item) = &e.filter {
+                            let Some(filter) = data.get_item_icon(
+                                &item.name,
+                                render_layers.scale() * 2.2,
+                                used_mods,
+                                image_cache,
+                            ) else {
+                                warn!(
+                                    "failed to render filter icon for {} at {:?} [{}]",
+                                    item, e.position, e.name
+                                );
+                                diagnostics.warn(
+                                    &e.name,
+                                    &render_opts,
+                                    DiagnosticKind::MissingIcon {
+                                        icon: item.name.to_string(),
+                                    },
+                                );
+                                break 'filters_priority;
+                            };
+
+                            let offset = e.direction.rotate_vector(prio_out.as_vector() + filter.1);
+
+                            render_layers.add(
+                                (filter.0, offset),
+                                &render_opts.position,
+                                InternalRenderLayer::IconOverlay,
+                            );
+                        } else {
+                            let offset = e.direction.rotate_vector(
+                                prio_out.as_vector() + Vector::Tuple(0.0, -0.25) + indicator_arrow.1,
+                            );
+
+                            let arrow = match e.direction.eight_way() {
+                                Direction::North => indicator_arrow.0.clone(),
+                                Direction::East => imageops::rotate90(&indicator_arrow.0).into(),
+                                Direction::South => imageops::rotate180(&indicator_arrow.0).into(),
+                                Direction::West => imageops::rotate270(&indicator_arrow.0).into(),
+                                _ => break 'filters_priority,
+                            };
+
+                            render_layers.add(
+                                (arrow, offset),
+                                &render_opts.position,
+                                InternalRenderLayer::DirectionOverlay,
+                            );
+                        }
+                    }
+
+                    if !e.filters.is_empty() {
+                        let filter_count = e.filters.len();
+                        let mut offset = if filter_count == 1 {
+                            Vector::Tuple(0.0, 0.0)
+                        } else if filter_count == 2 {
+                            Vector::Tuple(-0.25, 0.0)
+                        } else {
+                            Vector::Tuple(-0.25, -0.25)
+                        };
+
+                        for idx in 0..filter_count.min(4) {
+                            if idx == 2 {
+                                offset += Vector::Tuple(-1.0, 0.5);
+                            }
+
+                            let Some(filter) = data.get_item_icon(
+                                &e.filters[idx].name,
+                                render_layers.scale() * 2.2,
+                                used_mods,
+                                image_cache,
+                            ) else {
+                                warn!(
+                                    "failed to render filter icon for {} at {:?} [{}]",
+                                    e.filters[idx], e.position, e.name
+                                );
+                                diagnostics.warn(
+                                    &e.name,
+                                    &render_opts,
+                                    DiagnosticKind::MissingIcon {
+                                        icon: e.filters[idx].name.to_string(),
+                                    },
+                                );
+                                continue;
+                            };
+
+                            render_layers.add(
+                                (filter.0, filter.1 + offset),
+                                &render_opts.position,
+                                InternalRenderLayer::IconOverlay,
+                            );
+
+                            offset += Vector::Tuple(0.5, 0.0);
+                        }
+                    }
+                }
+
+                // modules / item requests
+                {
+                    if options.alt_mode && !e.items.is_empty() {
+                        let mut items = e.items.iter().collect::<Vec<_>>();
+                        items.sort_unstable_by_key(|a| a.0);
+
+                        let scale = render_layers.scale() * 2.3;
+                        let s_box = e_data.selection_box();
+                        let width = s_box.width() - 0.25;
+                        let height = s_box.height();
+                        let count = items.iter().map(|(_, &c)| c).sum::<u32>();
+
+                        let row_len = (width / 0.5).floor() as u32;
+                        let row_count = (f64::from(count) / f64::from(row_len)).ceil() as u32;
+                        let row_len = (f64::from(count) / f64::from(row_count)).ceil() as u32;
+
+                        let start_y =
+                            ((height / 4.0) - (f64::from(row_count - 1) / 2.0) + 0.25).max(0.0);
+                        let mut offset = Vector::Tuple(0.0, start_y);
+
+                        let icons = items
+                            .iter()
+                            .filter_map(|(name, _)| {
+                                Some((
+                                    (*name).clone(),
+                                    data.get_item_icon(name, scale, used_mods, image_cache)?,
+                                ))
+                            })
+                            .collect::<HashMap<_, _>>();
+
+                        for chunk in e
+                            .items
+                            .iter()
+                            .flat_map(|(i, c)| std::iter::repeat_n(i, *c as usize))
+                            .collect::<Vec<_>>()
+                            .as_slice()
+                            .chunks(row_len as usize)
+                        {
+                            let count = chunk.len() as u32;
+                            if count == 0 {
+                                continue;
+                            }
+
+                            let start_x = f64::from(count - 1) * -0.25; // count / 2 * -0.5
+                            offset += Vector::Tuple(start_x, 0.0);
+
+                            for &item in chunk {
+                                if let Some(icon) = icons.get(item) {
+                                    render_layers.add(
+                                        (icon.0.clone(), offset),
+                                        &render_opts.position,
+                                        InternalRenderLayer::IconOverlay,
+                                    );
+                                }
+
+                                offset += Vector::Tuple(0.5, 0.0);
+                            }
+
+                            offset = Vector::Tuple(0.0, offset.y() + 0.5);
+                        }
+                    }
+                }
+
+                // logistic requests
+                if options.alt_mode {
+                    let requests = e.request_filters.iter().take(4).collect::<Vec<_>>();
+                    let request_count = requests.len();
+                    let text_size = (8.0 / render_layers.scale()) as f32;
+
+                    for (idx, request) in requests.into_iter().enumerate() {
+                        let offset = match (request_count, idx) {
+                            (1, _) => Vector::Tuple(0.0, 0.0),
+                            (2, idx) => Vector::Tuple((idx as f64).mul_add(0.5, -0.25), 0.0),
+                            (_, idx) => Vector::Tuple(
+                                ((idx % 2) as f64).mul_add(0.5, -0.25),
+                                ((idx / 2) as f64).mul_add(0.5, -0.25),
+                            ),
+                        };
+
+                        let Some(icon) = data.get_item_icon(
+                            &request.name,
+                            render_layers.scale() * 2.2,
+                            used_mods,
+                            image_cache,
+                        ) else {
+                            warn!(
+                                "failed to render request icon for {} at {:?} [{}]",
+                                request.name, e.position, e.name
+                            );
+                            diagnostics.warn(
+                                &e.name,
+                                &render_opts,
+                                DiagnosticKind::MissingIcon {
+                                    icon: request.name.to_string(),
+                                },
+                            );
+                            continue;
+                        };
+
+                        render_layers.add(
+                            (icon.0, icon.1 + offset),
+                            &render_opts.position,
+                            InternalRenderLayer::IconOverlay,
+                        );
+
+                        let Some(font) = font.as_ref().filter(|_| request.count > 0) else {
+                            continue;
+                        };
+
+                        let label = font.render(
+                            &text::format_count(request.count.into()),
+                            text_size,
+                            image::Rgba([255, 255, 255, 255]),
+                        );
+                        render_layers.add(
+                            (label.into(), offset + Vector::Tuple(0.1, 0.15)),
+                            &render_opts.position,
+                            InternalRenderLayer::IconOverlay,
+                        );
+                    }
+                }
+
+                // inserter indicators
+                'inserter_indicators: {
+                    if !options.alt_mode {
+                        break 'inserter_indicators;
+                    }
+
+                    let Some(proto) = data.get_proto::<InserterPrototype>(&e.name) else {
+                        break 'inserter_indicators;
+                    };
+
+                    #[allow(clippy::items_after_statements)]
+                    fn indicator_helper(
+                        pos: Vector,
+                        opts: &prototypes::entity::RenderOpts,
+                        graphics: &(image::DynamicImage, Vector),
+                        layers: &mut RenderLayerBuffer,
+                    ) {
+                        let img = if pos.x() != 0.0 && pos.x() != 0.0 {
+                            let angle = pos.y().atan2(pos.x()) + std::f64::consts::FRAC_PI_2;
+                            rotate_about_center(
+                                &graphics.0.to_rgba8(),
+                                angle as f32,
+                                geometric_transformations::Interpolation::Nearest,
+                                image::Rgba([0, 0, 0, 0]),
+                            )
+                            .into()
+                        } else if pos.y() < 0.0 {
+                            graphics.0.clone()
+                        } else if pos.y() > 0.0 {
+                            imageops::rotate180(&graphics.0).into()
+                        } else if pos.x() > 0.0 {
+                            imageops::rotate90(&graphics.0).into()
+                        } else {
+                            imageops::rotate270(&graphics.0).into()
+                        };
+
+                        layers.add(
+                            (img, pos.shorten_by(0.45)),
+                            &opts.position,
+                            InternalRenderLayer::DirectionOverlay,
+                        );
+                    }
+
+                    indicator_helper(
+                        proto.get_pickup_position(
+                            e.direction.eight_way(),
+                            e.pickup_position.as_ref().map(std::convert::Into::into),
+                        ),
+                        &render_opts,
+                        indicator_line,
+                        render_layers,
+                    );
+                    indicator_helper(
+                        proto.get_insert_position(
+                            e.direction.eight_way(),
+                            e.drop_position.as_ref().map(std::convert::Into::into),
+                        ),
+                        &render_opts,
+                        indicator_arrow,
+                        render_layers,
+                    );
+                }
+
+                // store wire connections for wire rendering
+                if let Some(connections) = entity_wires.remove(&e.entity_number) {
+                    wire_connections.insert(
+                        e.entity_number,
+                        (e.position.clone().into(), connections),
+                    );
+                }
+
+                let rendered = data.render_entity(
+                    &e.name,
+                    &render_opts,
+                    used_mods,
+                    render_layers,
+                    image_cache,
+                    diagnostics,
+                );
+
+                if rendered.is_some() && options.night {
+                    for light in e_data.lights() {
+                        render_layers.add_light(&render_opts.position, light);
+                    }
+                }
+
+                if rendered.is_none() {
+                    placeholders.insert((*e.name).clone());
+                    draw_placeholder(
+                        e,
+                        data,
+                        util_sprites.sprites.get("questionmark"),
+                        used_mods,
+                        render_layers,
+                        image_cache,
+                    );
+                }
+
+                render_layers.end_entity();
+
+                if let (Some(start), Some(entity_type)) = (start, data.get_entity_type(&e.name)) {
+                    let timing = type_timings.entry(type_name(entity_type)).or_default();
+                    timing.count += 1;
+                    timing.ms += start.elapsed().as_secs_f64() * 1000.0;
+                }
+
+                self.report(RenderStage::Entities, idx + 1, total);
+                rendered
+            })
+            .count();
+
+        info!("entities: {}, layers: {rendered_count}", bp.entities.len());
+        self.record(|timings| timings.entity_types = type_timings);
+
+        placeholders
+    }
+
+    fn render_tiles(
+        &self,
+        render_layers: &mut RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+        unknown: &mut HashSet<String>,
+    ) {
+        let Self {
+            bp,
+            data,
+            used_mods,
+            ..
+        } = *self;
+
+        // render tiles
+        let total = bp.tiles.len();
+        let rendered_count = bp
+            .tiles
+            .iter()
+            .enumerate()
+            .take_while(|_| !self.cancel.is_cancelled())
+            .filter_map(|(idx, t)| {
+                let rendered = data.get_proto::<TilePrototype>(&t.name).map_or_else(
+                    || {
+                        unknown.insert((*t.name).clone());
+                        None
+                    },
+                    |tile| {
+                        let position: MapPosition = (&t.position).into();
+                        tile.render(
+                            &(position + MapPosition::Tuple(0.5, 0.5)),
+                            used_mods,
+                            render_layers,
+                            image_cache,
+                        )
+                    },
+                );
+
+                self.report(RenderStage::Tiles, idx + 1, total);
+                rendered
+            })
+            .count();
+
+        info!("tiles: {}, layers: {rendered_count}", bp.tiles.len());
+    }
+
+    fn draw_overlays(
+        &self,
+        render_layers: &mut RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+        assets: &AltModeAssets,
+    ) {
+        let Self {
+            bp,
+            data,
+            used_mods,
+            overlays,
+            ..
+        } = *self;
+
+        self.report(RenderStage::Overlays, 0, overlays.len());
+
+        if overlays.contains(&overlay::Overlay::UndergroundPairs) {
+            overlay::draw_underground_pairs(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::RoboportCoverage) {
+            overlay::draw_roboport_coverage(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::Power) {
+            overlay::draw_power(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::RailBlocks) {
+            overlay::draw_rail_blocks(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::CombinatorContents) {
+            overlay::draw_combinator_contents(bp, data, used_mods, image_cache, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::TurretRange) {
+            overlay::draw_turret_range(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::ArtilleryRange) {
+            overlay::draw_artillery_range(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::FluidNetworks) {
+            overlay::draw_fluid_networks(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::BeltDirections) {
+            overlay::draw_belt_directions(bp, data, &assets.indicator_arrow, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::Parameters) {
+            overlay::draw_parameters(bp, data, used_mods, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::DeciderConditions) {
+            overlay::draw_decider_conditions(bp, data, used_mods, image_cache, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::HeatGradient) {
+            overlay::draw_heat_gradient(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::ReactorBonus) {
+            overlay::draw_reactor_bonus(bp, data, used_mods, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::Overlaps) {
+            overlay::draw_overlaps(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::SnapGrid) {
+            overlay::draw_snap_grid(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::WireReach) {
+            overlay::draw_unreachable_wires(bp, data, render_layers);
+        }
+
+        if overlays.contains(&overlay::Overlay::Quality) {
+            overlay::draw_quality(bp, data, used_mods, image_cache, render_layers);
+        }
+    }
+}
+
+/// Draws a box sized to the collision box of the entity with a question mark on top.
+/// Used for entities whose sprites failed to render.
+fn draw_placeholder(
+    e: &blueprint::Entity,
+    data: &DataUtil,
+    questionmark: Option<&types::Sprite>,
+    used_mods: &UsedMods,
+    render_layers: &mut RenderLayerBuffer,
+    image_cache: &mut ImageCache,
+) {
+    const FILL_COLOR: image::Rgba<u8> = image::Rgba([200, 0, 200, 160]);
+    const EDGE_COLOR: image::Rgba<u8> = image::Rgba([70, 0, 70, 255]);
+
+    let position = MapPosition::from(&e.position);
+    let area = bp_helper::entity_area(e, data)
+        .filter(|area| area.width() > 0.0 && area.height() > 0.0)
+        .unwrap_or_else(|| types::BoundingBox(position, position).expand(0.5));
+
+    render_layers.fill_area(&area, FILL_COLOR, InternalRenderLayer::Entity);
+    render_layers.outline_area(&area, EDGE_COLOR, InternalRenderLayer::Entity);
+
+    let Some(icon) = questionmark.and_then(|sprite| {
+        sprite.render(
+            render_layers.scale() * area.width().min(area.height()) * 0.8,
+            used_mods,
+            image_cache,
+            &SimpleGraphicsRenderOpts::default(),
+        )
+    }) else {
+        return;
+    };
+
+    render_layers.add(icon, &area.center(), InternalRenderLayer::IconOverlay);
+}
+
+/// Prototype type string of an entity type, e.g. `transport-belt`.
+fn type_name(entity_type: &EntityType) -> String {
+    let debug = format!("{entity_type:?}");
+    let mut name = String::with_capacity(debug.len() + 4);
+
+    for (idx, c) in debug.char_indices() {
+        let prev = debug[..idx].chars().next_back();
+        if c.is_ascii_uppercase() && idx > 0
+            || c.is_ascii_digit() && prev.is_some_and(|p| p.is_ascii_alphabetic())
+        {
+            name.push('-');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::test_util::{base_data, blueprint, used_mods};
+
+    /// Renders three chests & two tiles, cancelling the job once `cancel_at` is reported.
+    fn run_job(
+        cancel_at: Option<RenderProgress>,
+    ) -> (Result<RenderJobOutput, RenderJobError>, Vec<RenderProgress>) {
+        let data = base_data();
+        let mut bp = blueprint(
+            r#"{"entity_number":1,"name":"wooden-chest","position":{"x":0.5,"y":0.5}},
+            {"entity_number":2,"name":"wooden-chest","position":{"x":1.5,"y":0.5}},
+            {"entity_number":3,"name":"wooden-chest","position":{"x":2.5,"y":0.5}}"#,
+            "",
+        );
+        bp.tiles = serde_json::from_str(
+            r#"[{"name":"stone-path","position":{"x":0,"y":1}},
+                {"name":"stone-path","position":{"x":1,"y":1}}]"#,
+        )
+        .unwrap();
+
+        let size = crate::calculate_target_size(&bp, data, 256.0, 0.5, None).unwrap();
+        let used_mods = used_mods();
+        let reported = Mutex::new(Vec::new());
+        let cancellation = CancellationToken::new();
+
+        let res = RenderJob::new(&bp, data, &used_mods)
+            .cancellation(cancellation.clone())
+            .on_progress(|progress| {
+                reported.lock().unwrap().push(progress);
+                if Some(progress) == cancel_at {
+                    cancellation.cancel();
+                }
+            })
+            .run(
+                RenderLayerBuffer::new(size),
+                &mut ImageCache::new(),
+                &mut Diagnostics::new(),
+            );
+
+        (res, reported.into_inner().unwrap())
+    }
+
+    const fn progress(stage: RenderStage, done: usize, total: usize) -> RenderProgress {
+        RenderProgress { stage, done, total }
+    }
+
+    #[test]
+    fn reports_every_item() {
+        let (res, reported) = run_job(None);
+
+        assert!(res.is_ok());
+        assert_eq!(
+            reported,
+            [
+                progress(RenderStage::Entities, 1, 3),
+                progress(RenderStage::Entities, 2, 3),
+                progress(RenderStage::Entities, 3, 3),
+                progress(RenderStage::Tiles, 1, 2),
+                progress(RenderStage::Tiles, 2, 2),
+                progress(RenderStage::Wires, 0, 1),
+                progress(RenderStage::Overlays, 0, 0),
+                progress(RenderStage::Done, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn cancel_from_progress_callback() {
+        let (res, reported) = run_job(Some(progress(RenderStage::Entities, 1, 3)));
+
+        let err = res.unwrap_err();
+        assert!(matches!(
+            err.current_context(),
+            RenderJobError::Cancelled(RenderStage::Entities)
+        ));
+        assert_eq!(reported, [progress(RenderStage::Entities, 1, 3)]);
+    }
+}
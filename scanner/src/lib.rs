@@ -1,41 +1,59 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
-    env, fs,
-    hash::{Hash, Hasher},
-    io::{Read, Write},
-    path::{Path, PathBuf},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    io::Read,
+    path::Path,
     process::Command,
+    sync::Mutex,
+    time::Instant,
 };
 
-use error_stack::{ensure, report, Context, Result, ResultExt};
-use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+use error_stack::{report, Context, Result, ResultExt};
 use image::{codecs::png, imageops, ImageEncoder};
-use imageproc::geometric_transformations::{self, rotate_about_center};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use tracing::{debug, error, field, info, info_span, instrument, warn};
-
-use blueprint::{ConnectionDataExt, SignalID};
-use mod_util::{
-    mod_info::{DependencyVersion, Version},
-    mod_list::ModList,
-    mod_loader::Mod,
-    mod_settings::SettingsDat,
-    AnyBasic, DependencyList, UsedMods, UsedVersions,
-};
+use tracing::{debug, info, instrument, warn};
+
+use mod_util::{mod_list::ModList, mod_settings::SettingsDat, AnyBasic, UsedMods};
 use prototypes::{
-    entity::{InserterPrototype, Type as EntityType, WallPrototype},
-    tile::TilePrototype,
-    ConnectedEntities, DataRaw, DataUtil, DataUtilAccess, EntityWireConnections,
-    InternalRenderLayer, RenderLayerBuffer, TargetSize,
-};
-use types::{
-    ConnectedDirections, Direction, ImageCache, MapPosition, RenderableGraphics,
-    SimpleGraphicsRenderOpts, Vector,
+    diagnostics::Diagnostics, tile::TilePrototype, Background, DataRaw, DataUtil, DataUtilAccess,
+    InternalRenderLayer, RenderLayerBuffer, RenderOptions, TargetSize,
 };
+use types::{BoundingBox, ImageCache, MapPosition, Vector};
 
+pub use prototypes::Region;
+#[cfg(feature = "portal")]
+pub use setup::*;
+
+pub mod annotation;
+pub mod bom;
+pub mod book_index;
 pub mod bp_helper;
+pub mod card;
+pub mod dump_cache;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fluid;
+pub mod heat;
+pub mod heatmap;
+pub mod icons;
+pub mod incremental;
+pub mod job;
+pub mod lockfile;
+pub mod mod_cache;
+#[cfg(feature = "portal")]
+pub mod mod_detect;
+pub mod mod_settings;
+pub mod occupancy;
+pub mod overlay;
 pub mod preset;
+pub mod rail;
+#[cfg(feature = "portal")]
+mod setup;
+pub mod tech_tree;
+pub mod telemetry;
+pub mod text;
+pub mod validate;
 
 #[derive(Debug)]
 pub enum ScannerError {
@@ -43,6 +61,7 @@ pub enum ScannerError {
     RenderError,
     NoBlueprint,
     ServerError,
+    InvalidBlueprint,
 }
 
 impl Context for ScannerError {}
@@ -54,72 +73,94 @@ impl std::fmt::Display for ScannerError {
             Self::RenderError => write!(f, "render error"),
             Self::NoBlueprint => write!(f, "no blueprint"),
             Self::ServerError => write!(f, "server error"),
+            Self::InvalidBlueprint => write!(f, "invalid blueprint"),
         }
     }
 }
 
-#[allow(clippy::too_many_lines)]
+/// How the prototype dump is created when none is given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum DumpMethod {
+    /// Run the game with `--dump-data`
+    #[default]
+    Factorio,
+
+    /// Run the settings & data stage of the mods in an embedded Lua, for servers that can't run the game
+    Lua,
+}
+
 #[instrument(skip_all)]
+#[allow(clippy::implicit_hasher)]
 pub fn get_protodump(
     factorio_userdir: &Path,
     factorio_bin: &Path,
+    dump_method: DumpMethod,
     mod_list: &ModList,
     (bp_settings, bp_version): (&BTreeMap<String, AnyBasic>, u64),
+    used_names: Option<&HashSet<String>>,
+    dump_cache: &dump_cache::DumpCache,
 ) -> Result<DataRaw, ScannerError> {
     // check if cached dump exists and load it if available
-    let cached_path = {
-        let (active_mods, load_order) = mod_list.active_with_order();
-        let mut hash = rustc_hash::FxHasher::default();
-        for mod_name in &load_order {
-            let Some(m) = active_mods.get(mod_name) else {
-                continue;
-            };
-            format!("{}@{}", m.info.name, m.info.version).hash(&mut hash);
-        }
-        let mods_hash = hash.finish();
+    let key = dump_cache::DumpKey::new(mod_list, bp_settings, dump_method);
+    let cached = used_names.map_or_else(
+        || dump_cache.lookup(key),
+        |names| dump_cache.lookup_filtered(key, names),
+    );
+    if let Some(data) = cached {
+        return Ok(data);
+    }
 
-        let mut active_settings = bp_settings
-            .iter()
-            .map(|(k, v)| format!("{k}={v}"))
-            .collect::<Vec<_>>();
-        active_settings.sort();
+    match dump_method {
+        DumpMethod::Factorio => {
+            dump_with_factorio(
+                factorio_userdir,
+                factorio_bin,
+                mod_list,
+                bp_settings,
+                bp_version,
+            )?;
+
+            // store minified + deflated version of dump in the cache
+            let dump_path = factorio_userdir.join("script-output/data-raw-dump.json");
+            dump_cache
+                .store(key, &dump_path)
+                .change_context(ScannerError::SetupError)?;
 
-        let mut hash = rustc_hash::FxHasher::default();
-        for setting in &active_settings {
-            setting.hash(&mut hash);
+            used_names.map_or_else(
+                || DataRaw::load(&dump_path),
+                |names| DataRaw::load_filtered(&dump_path, names),
+            )
         }
-        let settings_hash = hash.finish();
-
-        let cached_path = factorio_userdir.join(format!(
-            "script-output/cached-dump_{mods_hash:X}-{settings_hash:X}.json.deflate"
-        ));
-
-        if cached_path.exists() {
-            info!("loading cached prototype dump");
-            let mut deflate = ZlibDecoder::new(
-                fs::File::open(&cached_path)
-                    .change_context(ScannerError::SetupError)
-                    .attach_printable(format!(
-                        "failed to open cached prototype dump at {cached_path:?}"
-                    ))?,
-            );
-
-            let mut uncompressed = Vec::new();
-
-            deflate
-                .read_to_end(&mut uncompressed)
-                .change_context(ScannerError::SetupError)
-                .attach_printable(format!(
-                    "failed to decompress cached prototype dump at {cached_path:?}"
-                ))?;
-
-            return DataRaw::load_from_bytes(&uncompressed)
-                .change_context(ScannerError::SetupError);
+        #[cfg(not(feature = "datastage"))]
+        DumpMethod::Lua => {
+            return Err(report!(ScannerError::SetupError))
+                .attach_printable("the lua dump method needs the datastage feature");
         }
+        #[cfg(feature = "datastage")]
+        DumpMethod::Lua => {
+            // kept in memory, the dump of the game in `script-output` stays untouched
+            let bytes = dump_with_lua(mod_list, bp_settings)?;
+            dump_cache
+                .store_bytes(key, &bytes)
+                .change_context(ScannerError::SetupError)?;
 
-        cached_path
-    };
+            used_names.map_or_else(
+                || DataRaw::load_from_bytes(&bytes),
+                |names| DataRaw::load_filtered_from_bytes(&bytes, names),
+            )
+        }
+    }
+    .change_context(ScannerError::SetupError)
+}
 
+/// Lets the game write the prototype dump to `script-output/data-raw-dump.json`.
+fn dump_with_factorio(
+    factorio_userdir: &Path,
+    factorio_bin: &Path,
+    mod_list: &ModList,
+    bp_settings: &BTreeMap<String, AnyBasic>,
+    bp_version: u64,
+) -> Result<(), ScannerError> {
     mod_list.save().change_context(ScannerError::SetupError)?;
     debug!("updated mod-list.json");
 
@@ -141,125 +182,118 @@ pub fn get_protodump(
 
     if dump_out.status.success() {
         debug!("prototype dump success");
+        Ok(())
     } else {
-        return Err(report!(ScannerError::SetupError)
+        Err(report!(ScannerError::SetupError)
             .attach_printable(format!(
                 "prototype dump failed with exit code {}",
                 dump_out.status.code().unwrap_or(-1)
             ))
-            .attach_printable(String::from_utf8_lossy(&dump_out.stdout).to_string()));
+            .attach_printable(String::from_utf8_lossy(&dump_out.stdout).to_string()))
     }
+}
 
-    let dump_path = factorio_userdir.join("script-output/data-raw-dump.json");
-    let dump_bytes = fs::read(&dump_path)
-        .change_context(ScannerError::SetupError)
-        .attach_printable(format!("failed to read prototype dump at {dump_path:?}"))?;
-
-    // store minified + deflated version of dump in script-output folder
-    {
-        let minified = serde_json::to_vec(
-            &serde_json::from_slice::<serde_json::Value>(&dump_bytes)
-                .change_context(ScannerError::SetupError)
-                .attach_printable("failed to minify prototype dump")?,
-        )
-        .change_context(ScannerError::SetupError)
-        .attach_printable("failed to minify prototype dump")?;
-
-        let mut deflate = ZlibEncoder::new(
-            fs::File::create(&cached_path)
-                .change_context(ScannerError::SetupError)
-                .attach_printable(format!(
-                    "failed to create cached prototype dump at {cached_path:?}"
-                ))?,
-            flate2::Compression::best(),
-        );
+/// Runs the settings & data stage of the active mods with [`factorio_datastage`] and returns the dump as JSON.
+#[cfg(feature = "datastage")]
+#[instrument(skip_all)]
+fn dump_with_lua(
+    mod_list: &ModList,
+    bp_settings: &BTreeMap<String, AnyBasic>,
+) -> Result<Vec<u8>, ScannerError> {
+    let (active_mods, load_order) = mod_list.active_with_order();
 
-        deflate
-            .write_all(&minified)
-            .change_context(ScannerError::SetupError)
-            .attach_printable(format!(
-                "failed to compress cached prototype dump at {cached_path:?}"
-            ))?;
-    }
+    let loader = factorio_datastage::DataLoader::new(&active_mods, &load_order)
+        .change_context(ScannerError::SetupError)?;
+    loader
+        .settings_stage()
+        .change_context(ScannerError::SetupError)?;
+    loader
+        .data_stage(bp_settings)
+        .change_context(ScannerError::SetupError)?;
+    let raw = loader.get_raw().change_context(ScannerError::SetupError)?;
+    debug!("data stage defined {} prototype types", raw.len());
 
-    DataRaw::load_from_bytes(&dump_bytes).change_context(ScannerError::SetupError)
+    serde_json::to_vec(&raw).change_context(ScannerError::SetupError)
 }
 
+/// Calculates the image size needed to fit the blueprint.
+/// If a `region` is given the image is clamped to it.
 #[must_use]
-#[instrument(skip_all, fields(entities = bp.entities.len(), tiles = bp.tiles.len()))]
 pub fn calculate_target_size(
     bp: &blueprint::Blueprint,
     data: &DataUtil,
     target_res: f64,
     min_scale: f64,
+    region: Option<&Region>,
 ) -> Option<TargetSize> {
-    const TILE_RES: f64 = 32.0;
-
-    let mut min_x = f64::MAX;
-    let mut min_y = f64::MAX;
-    let mut max_x = f64::MIN;
-    let mut max_y = f64::MIN;
-
-    for entity in &bp.entities {
-        let Some(e_proto) = data.get_entity(&entity.name) else {
-            continue;
-        };
-
-        let e_pos: MapPosition = (&entity.position).into();
-        let c_box = e_proto.drawing_box();
-
-        let tl = e_pos + c_box.top_left();
-        let br = e_pos + c_box.bottom_right();
-
-        if tl.x() < min_x {
-            min_x = tl.x();
-        }
-
-        if tl.y() < min_y {
-            min_y = tl.y();
-        }
-
-        if br.x() > max_x {
-            max_x = br.x();
-        }
+    fitted_target_size(bp, data, target_res, min_scale, region, None)
+}
 
-        if br.y() > max_y {
-            max_y = br.y();
-        }
-    }
+/// [`calculate_target_size`] that also fits the parts of `overlays` reaching past the blueprint,
+/// e.g. the range of artillery.
+#[must_use]
+pub fn calculate_overlay_target_size(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    target_res: f64,
+    min_scale: f64,
+    region: Option<&Region>,
+    overlays: &[overlay::Overlay],
+) -> Option<TargetSize> {
+    let extra = if overlays.contains(&overlay::Overlay::ArtilleryRange) {
+        overlay::artillery_range_area(bp, data)
+    } else {
+        None
+    };
 
-    for tile in &bp.tiles {
-        if data.get_proto::<TilePrototype>(&tile.name).is_none() {
-            continue;
-        }
+    fitted_target_size(bp, data, target_res, min_scale, region, extra.as_ref())
+}
 
-        let t_pos: MapPosition = (&tile.position).into();
-        let (x, y) = t_pos.as_tuple();
+#[instrument(skip_all, fields(entities = bp.entities.len(), tiles = bp.tiles.len()))]
+fn fitted_target_size(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    target_res: f64,
+    min_scale: f64,
+    region: Option<&Region>,
+    extra: Option<&BoundingBox>,
+) -> Option<TargetSize> {
+    const TILE_RES: f64 = 32.0;
 
-        if x < min_x {
-            min_x = x;
-        }
+    let entities = bp.entities.iter().filter_map(|entity| {
+        let bounds = data.get_entity_bounds(&entity.name, entity.direction.eight_way())?;
+        let e_pos: MapPosition = (&entity.position).into();
 
-        if y < min_y {
-            min_y = y;
-        }
+        Some(BoundingBox(
+            e_pos + bounds.top_left(),
+            e_pos + bounds.bottom_right(),
+        ))
+    });
 
-        if x > max_x {
-            max_x = x;
-        }
+    let tiles = bp
+        .tiles
+        .iter()
+        .filter(|tile| data.get_proto::<TilePrototype>(&tile.name).is_some())
+        .map(|tile| {
+            let t_pos: MapPosition = (&tile.position).into();
+            BoundingBox(t_pos, t_pos)
+        });
 
-        if y > max_y {
-            max_y = y;
-        }
-    }
+    let bounds = entities
+        .chain(tiles)
+        .chain(extra.cloned())
+        .reduce(|a, b| a.union(&b))?
+        .expand(0.5);
+    let bounds = tile_aligned(&bounds);
 
-    let min_x = (min_x - 0.5).floor();
-    let min_y = (min_y - 0.5).floor();
-    let max_x = (max_x + 0.5).ceil();
-    let max_y = (max_y + 0.5).ceil();
+    let bounds = if let Some(region) = region {
+        bounds.intersection(&tile_aligned(&region.area()))?
+    } else {
+        bounds
+    };
 
-    let width = (max_x - min_x).abs().ceil();
-    let height = (max_y - min_y).abs().ceil();
+    let width = bounds.width().abs().ceil();
+    let height = bounds.height().abs().ceil();
 
     if width == 0.0 || height == 0.0 {
         return None;
@@ -278,20 +312,107 @@ pub fn calculate_target_size(
         (width * tile_res).ceil() as u32,
         (height * tile_res).ceil() as u32,
         scale,
-        MapPosition::XY { x: min_x, y: min_y },
-        MapPosition::XY { x: max_x, y: max_y },
+        MapPosition::XY {
+            x: bounds.left(),
+            y: bounds.top(),
+        },
+        MapPosition::XY {
+            x: bounds.right(),
+            y: bounds.bottom(),
+        },
+    ))
+}
+
+/// Lowers the resolution of `size` so the image has at most `max_pixels` pixels.
+///
+/// Returns `None` if `size` already fits into the budget. Huge blueprints end up with less
+/// than a pixel per tile, the image is at least 1x1 px.
+#[must_use]
+pub fn limit_target_size(size: &TargetSize, max_pixels: u64) -> Option<TargetSize> {
+    const TILE_RES: f64 = 32.0;
+
+    if u64::from(size.width()) * u64::from(size.height()) <= max_pixels {
+        return None;
+    }
+
+    let area = BoundingBox(*size.top_left(), *size.bottom_right());
+    let (width, height) = (area.width().abs(), area.height().abs());
+    let max_pixels = max_pixels.max(1) as f64;
+    let mut tile_res = (max_pixels / (width * height)).sqrt();
+
+    // the short side can't get thinner than a pixel, the long side gets the whole budget
+    if width.min(height) * tile_res < 1.0 {
+        tile_res = max_pixels / width.max(height);
+    }
+
+    Some(TargetSize::new(
+        ((width * tile_res).floor() as u32).max(1),
+        ((height * tile_res).floor() as u32).max(1),
+        TILE_RES / tile_res,
+        *size.top_left(),
+        *size.bottom_right(),
     ))
 }
 
+/// [`calculate_overlay_target_size`] limited to the pixel budget of `options`,
+/// with a warning for the report if the resolution had to be lowered.
+fn budgeted_target_size(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    target_res: f64,
+    min_scale: f64,
+    region: Option<&Region>,
+    overlays: &[overlay::Overlay],
+    options: &RenderOptions,
+) -> Result<(TargetSize, Option<String>), ScannerError> {
+    let size = calculate_overlay_target_size(bp, data, target_res, min_scale, region, overlays)
+        .ok_or(ScannerError::RenderError)
+        .attach_printable_lazy(|| {
+            if region.is_some() {
+                "the region does not contain anything to render"
+            } else {
+                "the blueprint is empty"
+            }
+        })?;
+
+    let Some(max_pixels) = options.max_pixels else {
+        return Ok((size, None));
+    };
+
+    Ok(match limit_target_size(&size, max_pixels) {
+        Some(limited) => {
+            let warning = format!(
+                "{}x{} px exceed the budget of {max_pixels} pixels, lowered the resolution to {}x{} px",
+                size.width(),
+                size.height(),
+                limited.width(),
+                limited.height()
+            );
+            warn!("{warning}");
+            (limited, Some(warning))
+        }
+        None => (size, None),
+    })
+}
+
+/// Smallest box with whole tile coordinates that contains `area`.
+const fn tile_aligned(area: &BoundingBox) -> BoundingBox {
+    BoundingBox(
+        MapPosition::Tuple(area.left().floor(), area.top().floor()),
+        MapPosition::Tuple(area.right().ceil(), area.bottom().ceil()),
+    )
+}
+
 pub fn bp_entity2render_opts(
     value: &blueprint::Entity,
     data: &DataUtil,
 ) -> prototypes::entity::RenderOpts {
     prototypes::entity::RenderOpts {
         position: (&value.position).into(),
-        direction: value.direction,
+        direction: value.direction.eight_way(),
         orientation: value.orientation,
         variation: value.variation,
+        mirrored: value.mirror,
         pickup_position: value
             .pickup_position
             .as_ref()
@@ -315,743 +436,563 @@ pub fn bp_entity2render_opts(
         }),
         runtime_tint: value.color.as_ref().map(std::convert::Into::into),
         entity_id: value.entity_number,
-        circuit_connected: value.connections.is_some() || !value.neighbours.is_empty(),
+        // depends on the wires of the whole blueprint
+        circuit_connected: false,
         logistic_connected: value
             .control_behavior
             .as_ref()
             .is_some_and(|c| c.connect_to_logistic_network.unwrap_or_default()),
         fluid_recipe: data.recipe_has_fluid(&value.recipe),
+        seed: None,
+        progress: 0.0,
     }
 }
 
-#[instrument(skip_all, fields(preset, mods))]
-pub async fn load_data(
-    bp: &blueprint::Data,
-    factorio_appdir: &Path,
-    factorio_userdir: &Path,
-    factorio_bin: &Path,
-    preset: Option<preset::Preset>,
-    mods: &[String],
-    prototype_dump: Option<PathBuf>,
-) -> Result<(DataUtil, UsedMods), ScannerError> {
-    let bp = bp
-        .as_blueprint()
-        .ok_or(report!(ScannerError::NoBlueprint))?;
+/// Startup settings used to dump the data, explicitly passed settings take precedence over the ones from the BP & the preset.
+#[must_use]
+pub fn startup_settings(
+    bp: Option<&blueprint::Blueprint>,
+    preset: Option<&preset::Preset>,
+    settings: &[(String, AnyBasic)],
+) -> BTreeMap<String, AnyBasic> {
+    let mut startup_settings = preset
+        .map(|preset| preset.settings.clone())
+        .unwrap_or_default();
+    if let Some(bp_settings) = bp.and_then(bp_helper::get_used_startup_settings) {
+        startup_settings.extend(bp_settings.clone());
+    }
+    startup_settings.extend(settings.iter().cloned());
+    startup_settings
+}
 
-    info!("loaded BP");
+/// Encoded PNGs & metadata of a render.
+#[derive(Debug, Clone)]
+pub struct RenderOutput {
+    pub image: Vec<u8>,
+    pub report: RenderReport,
+    pub thumbnail: Option<Vec<u8>>,
 
-    let mut mod_list = ModList::generate_custom(factorio_appdir.join("data"), factorio_userdir)
-        .change_context(ScannerError::SetupError)?;
+    /// Every populated layer as a separate transparent PNG, only filled if requested.
+    pub layers: Vec<(InternalRenderLayer, Vec<u8>)>,
+}
 
-    // get used mods from preset or detect from BP meta info
-    let mut required_mods = std::iter::once((
-        "base".to_owned(),
-        DependencyVersion::Exact(prototypes::targeted_engine_version()),
-    ))
-    .collect::<HashMap<_, _>>();
-    required_mods.extend(
-        preset
-            .as_ref()
-            .map_or_else(|| bp_helper::get_used_versions(bp), |p| p.used_mods()),
-    );
-    required_mods.extend(mods.iter().map(|m| (m.clone(), DependencyVersion::Any)));
+/// Metadata of a render that allows mapping image pixels back to blueprint coordinates.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderReport {
+    /// Scale of the sprites, 0.5 is the vanilla HR resolution.
+    pub scale: f64,
+    pub width: u32,
+    pub height: u32,
+
+    /// Pixels per tile.
+    pub tile_resolution: f64,
+
+    /// World-space area that is covered by the image.
+    pub top_left: MapPosition,
+    pub bottom_right: MapPosition,
+
+    pub entities: Vec<EntityAnchor>,
 
-    debug!(
-        "required mods: {}",
-        required_mods
+    /// Prototype names that are not part of the loaded data.
+    pub unknown: BTreeSet<String>,
+
+    /// Names of the entities that could not be rendered and were drawn as placeholders.
+    pub placeholders: Vec<String>,
+
+    /// Problems of individual entities, e.g. missing sprites or unknown recipes.
+    pub diagnostics: Diagnostics,
+
+    /// Problems of the whole render, e.g. a resolution that was lowered to fit the pixel budget.
+    pub warnings: Vec<String>,
+
+    pub timings: RenderTimings,
+}
+
+impl RenderReport {
+    /// Pixel-space selection boxes of all known entities by their entity number.
+    #[must_use]
+    pub fn hitboxes(&self) -> BTreeMap<u64, PixelBox> {
+        self.entities
             .iter()
-            .map(|(n, v)| format!("{n} {v}"))
-            .collect::<Vec<_>>()
-            .join(", ")
-    );
+            .filter_map(|e| Some((e.entity_number, e.hitbox?)))
+            .collect()
+    }
+}
 
-    if !required_mods.is_empty() {
-        debug!("checking mod dependencies");
+/// Pixel position of an entity in the render.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityAnchor {
+    pub entity_number: u64,
+    pub name: String,
+    pub position: MapPosition,
+    pub pixel: (f32, f32),
 
-        let used_mods = resolve_mod_dependencies(&required_mods, &mut mod_list)
-            .await
-            .change_context(ScannerError::SetupError)?;
+    /// Selection box in pixels, `None` for unknown entities.
+    pub hitbox: Option<PixelBox>,
+}
 
-        let missing = mod_list.enable_mods(&used_mods);
-        if missing.is_empty() {
-            debug!("all mods are already installed");
-        } else {
-            info!("downloading missing mods from mod portal");
-            download_mods(missing, &factorio_userdir.join("mods"))
-                .await
-                .change_context(ScannerError::SetupError)?;
+/// Axis aligned box in image pixels.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PixelBox {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+/// Time spent per render stage in milliseconds.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RenderTimings {
+    /// Loading the prototype dump & preparing the mods, only measured by the CLI.
+    pub setup: f64,
+
+    pub layers: f64,
+    /// Part of `layers`.
+    pub entities: f64,
+    /// Part of `layers`.
+    pub tiles: f64,
+    /// Part of `layers`.
+    pub wires: f64,
+    /// Part of `layers`.
+    pub overlays: f64,
+
+    pub combine: f64,
+    /// Distance field of the icon outlines / drop shadows, part of `combine`.
+    pub icon_style: f64,
+
+    pub encode: f64,
+    pub thumbnail: f64,
+
+    /// Entity stage split by the prototype type of the entities, e.g. `transport-belt`.
+    pub entity_types: BTreeMap<String, TypeTiming>,
+}
+
+/// Time spent rendering all entities of one prototype type.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TypeTiming {
+    pub count: usize,
+    pub ms: f64,
+}
+
+impl std::fmt::Display for RenderTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stages = [
+            ("setup", self.setup),
+            ("layers", self.layers),
+            ("  entities", self.entities),
+            ("  tiles", self.tiles),
+            ("  wires", self.wires),
+            ("  overlays", self.overlays),
+            ("combine", self.combine),
+            ("  icon style", self.icon_style),
+            ("encode", self.encode),
+            ("thumbnail", self.thumbnail),
+        ];
+
+        for (stage, ms) in stages {
+            writeln!(f, "{stage:<14}{ms:>10.1} ms")?;
         }
-    }
 
-    let active_mods = mod_list.active_mods();
-    debug!(
-        "{} mods active: {:?}",
-        active_mods.len(),
-        active_mods.keys().collect::<Vec<_>>()
-    );
+        let mut types = self.entity_types.iter().collect::<Vec<_>>();
+        types.sort_by(|(_, a), (_, b)| b.ms.total_cmp(&a.ms));
+        let width = types
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or_default();
 
-    let data = if let Some(path) = prototype_dump {
-        DataRaw::load(&path).change_context(ScannerError::SetupError)?
-    } else {
-        get_protodump(
-            factorio_userdir,
-            factorio_bin,
-            &mod_list,
-            (
-                bp_helper::get_used_startup_settings(bp).unwrap_or(&BTreeMap::new()),
-                bp.version,
-            ),
-        )?
-    };
+        if !types.is_empty() {
+            writeln!(f)?;
+        }
+
+        for (name, timing) in types {
+            writeln!(
+                f,
+                "{name:<width$}{:>10.1} ms over {} entities",
+                timing.ms, timing.count
+            )?;
+        }
+
+        Ok(())
+    }
+}
 
-    info!("loaded prototype data");
-    Ok((DataUtil::new(data), active_mods))
+pub(crate) fn elapsed_ms(start: &mut Instant) -> f64 {
+    let ms = start.elapsed().as_secs_f64() * 1000.0;
+    *start = Instant::now();
+    ms
 }
 
 #[instrument(skip_all)]
+#[allow(clippy::too_many_lines)]
 pub fn render(
     raw_bp: &blueprint::Data,
     data: &DataUtil,
     used_mods: &UsedMods,
     target_res: f64,
     min_scale: f64,
-) -> Result<(Vec<u8>, HashSet<String>, Option<Vec<u8>>), ScannerError> {
+    overlays: &[overlay::Overlay],
+    options: &RenderOptions,
+) -> Result<RenderOutput, ScannerError> {
     let bp = raw_bp
         .as_blueprint()
-        .ok_or(report!(ScannerError::NoBlueprint))?;
+        .ok_or_else(|| report!(ScannerError::NoBlueprint))?;
 
-    let size =
-        calculate_target_size(bp, data, target_res, min_scale).ok_or(ScannerError::RenderError)?;
-    info!("target size: {size}");
+    telemetry::render_started();
 
-    let image_cache = &mut ImageCache::new();
-    let (img, unknown) = render_bp(
-        bp,
-        data,
-        used_mods,
-        RenderLayerBuffer::new(size),
-        image_cache,
-    )
-    .ok_or(ScannerError::RenderError)?;
-    info!("render completed");
-
-    let mut res = Vec::new();
-    let enc = png::PngEncoder::new_with_quality(
-        &mut res,
-        png::CompressionType::Best,
-        png::FilterType::default(),
-    );
+    let region = options.region.as_ref();
+    let cropped = region.map(|region| bp_helper::crop(bp, region));
+    let bp = cropped.as_ref().unwrap_or(bp);
 
-    enc.write_image(
-        img.as_bytes(),
-        img.width(),
-        img.height(),
-        img.color().into(),
-    )
-    .change_context(ScannerError::RenderError)?;
-
-    let thumbnail = render_thumbnail(raw_bp, data, used_mods, image_cache).map(|t| {
-        let mut res = Vec::new();
-        let enc = png::PngEncoder::new_with_quality(
-            &mut res,
-            png::CompressionType::Best,
-            png::FilterType::default(),
-        );
-
-        let _ = enc.write_image(t.as_bytes(), t.width(), t.height(), t.color().into());
-        res
-    });
+    let (size, size_warning) =
+        budgeted_target_size(bp, data, target_res, min_scale, region, overlays, options)?;
+    info!("target size: {size}");
 
-    Ok((res, unknown, thumbnail))
-}
+    let job_timings = Mutex::new(RenderTimings::default());
+    let mut stage = Instant::now();
 
-#[instrument(skip_all)]
-#[allow(clippy::too_many_lines)]
-pub fn render_bp(
-    bp: &blueprint::Blueprint,
-    data: &prototypes::DataUtil,
-    used_mods: &UsedMods,
-    mut render_layers: RenderLayerBuffer,
-    image_cache: &mut ImageCache,
-) -> Option<(image::DynamicImage, HashSet<String>)> {
-    let mut unknown = HashSet::new();
-    let mut wire_connections = EntityWireConnections::new();
-    let mut pipe_connections = HashMap::<MapPosition, HashSet<Direction>>::new();
-    let mut heat_connections = HashMap::<MapPosition, HashSet<Direction>>::new();
+    let image_cache = &mut ImageCache::new();
+    let mut diagnostics = Diagnostics::new();
+    let job::RenderJobOutput {
+        mut layers,
+        unknown,
+        placeholders,
+    } = job::RenderJob::new(bp, data, used_mods)
+        .overlays(overlays)
+        .options(*options)
+        .timings(&job_timings)
+        .run(
+            RenderLayerBuffer::new(size.clone()),
+            image_cache,
+            &mut diagnostics,
+        )
+        .change_context(ScannerError::RenderError)?;
+    let mut timings = job_timings
+        .into_inner()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    timings.layers = elapsed_ms(&mut stage);
+
+    layers.stylize_icons();
+    timings.icon_style = elapsed_ms(&mut stage);
+    let mut img = layers.combine();
+    let parameter_list =
+        overlays.contains(&overlay::Overlay::Parameters) && !bp.parameters.is_empty();
+    if options.annotations || options.bill_of_materials || parameter_list {
+        if let Some(font) = text::Font::load(used_mods, text::DEFAULT_FONT) {
+            if options.annotations {
+                img = annotation::add_footer(&img, &size, &font);
+            }
 
-    let Some(util_sprites) = data.util_sprites() else {
-        warn!("failed to load util sprites, required for wire rendering & alt mode");
-        return None;
-    };
+            if parameter_list {
+                img = annotation::add_parameter_list(&img, bp, &font);
+            }
 
-    let Some(indicator_arrow) = util_sprites.indication_arrow.render(
-        render_layers.scale() * 1.25,
-        used_mods,
-        image_cache,
-        &SimpleGraphicsRenderOpts::default(),
-    ) else {
-        warn!("failed to load indicator arrow sprite, required for alt mode");
-        return None;
-    };
+            if options.bill_of_materials {
+                let bom = bom::BillOfMaterials::new(bp, data);
+                img = annotation::add_bill_of_materials(
+                    &img,
+                    &bom,
+                    data,
+                    used_mods,
+                    image_cache,
+                    &font,
+                );
+            }
+        } else {
+            warn!("skipping annotations, no font available");
+        }
+    }
+    timings.combine = timings.icon_style + elapsed_ms(&mut stage);
+    info!("render completed");
 
-    let Some(indicator_line) = util_sprites.indication_line.render(
-        render_layers.scale() * 1.25,
-        used_mods,
-        image_cache,
-        &SimpleGraphicsRenderOpts::default(),
-    ) else {
-        warn!("failed to load indicator line sprite, required for alt mode");
-        return None;
+    let encode_start = Instant::now();
+    let res = encode_png(&img)?;
+    let layers = if options.split_layers {
+        InternalRenderLayer::all()
+            .into_iter()
+            .filter_map(|layer| layers.layer(layer).map(|img| (layer, img)))
+            .map(|(layer, img)| Ok((layer, encode_png(img)?)))
+            .collect::<Result<Vec<_>, ScannerError>>()?
+    } else {
+        Vec::new()
     };
+    timings.encode = elapsed_ms(&mut stage);
+    telemetry::stage_finished(telemetry::Stage::Encode, encode_start);
 
-    // pipe / heat connections
-    bp.entities.iter().for_each(|e| {
-        let Some(e_data) = data.get_entity(&e.name) else {
-            return;
-        };
-
-        let options = bp_entity2render_opts(e, data);
-        e_data
-            .pipe_connections(&options)
-            .iter()
-            .copied()
-            .for_each(|(pos, dir)| {
-                pipe_connections.entry(pos).or_default().insert(dir);
-            });
-        e_data
-            .heat_connections(&options)
-            .iter()
-            .copied()
-            .for_each(|(pos, dir)| {
-                heat_connections.entry(pos).or_default().insert(dir);
-            });
-    });
+    let thumbnail =
+        render_thumbnail(raw_bp, data, used_mods, image_cache).and_then(|t| encode_png(&t).ok());
+    timings.thumbnail = elapsed_ms(&mut stage);
 
-    // render entities
-    let rendered_count = bp
+    let entities = bp
         .entities
         .iter()
-        .filter_map(|e| {
-            let Some(e_data) = data.get_entity(&e.name) else {
-                unknown.insert((*e.name).clone());
-                return None;
-            };
-
-            let mut connected_gates: Vec<Direction> = Vec::new();
-            let mut draw_gate_patch = false;
-            let connections = data.get_entity_type(&e.name).and_then(|entity_type| {
-                if entity_type.connectable() {
-                    let mut up = false;
-                    let mut down = false;
-                    let mut left = false;
-                    let mut right = false;
-
-                    let pos: types::MapPosition = (&e.position).into();
-
-                    match entity_type {
-                        EntityType::Pipe | EntityType::InfinityPipe | EntityType::PipeToGround => {
-                            for (p, dirs) in &pipe_connections {
-                                if p.is_close(&pos, 0.5) {
-                                    for dir in dirs {
-                                        match dir {
-                                            Direction::North => up = true,
-                                            Direction::South => down = true,
-                                            Direction::East => right = true,
-                                            Direction::West => left = true,
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        EntityType::HeatPipe | EntityType::HeatInterface => {
-                            for (p, dirs) in &heat_connections {
-                                if p.is_close(&pos, 0.5) {
-                                    for dir in dirs {
-                                        match dir {
-                                            Direction::North => up = true,
-                                            Direction::South => down = true,
-                                            Direction::East => right = true,
-                                            Direction::West => left = true,
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            for other in &bp.entities {
-                                if other == e {
-                                    continue;
-                                }
-
-                                let Some(other_type) = data.get_entity_type(&other.name) else {
-                                    continue;
-                                };
-
-                                if !entity_type.can_connect_to(other_type) {
-                                    continue;
-                                }
-
-                                if matches!(entity_type, EntityType::Wall)
-                                    && matches!(other_type, EntityType::Wall)
-                                {
-                                    let Some(src) = data
-                                        .get_proto::<WallPrototype>(&e.name)
-                                        .map(|p| p.visual_merge_group)
-                                    else {
-                                        continue;
-                                    };
-
-                                    let Some(dst) = data
-                                        .get_proto::<WallPrototype>(&other.name)
-                                        .map(|p| p.visual_merge_group)
-                                    else {
-                                        continue;
-                                    };
-
-                                    if src != dst {
-                                        continue;
-                                    }
-                                }
-
-                                let other_pos: types::MapPosition = (&other.position).into();
-
-                                match entity_type {
-                                    EntityType::Gate => {
-                                        match pos.is_cardinal_neighbor(&other_pos) {
-                                            Some(dir) => {
-                                                if dir == Direction::South {
-                                                    draw_gate_patch = true;
-                                                }
-                                            }
-                                            None => continue,
-                                        }
-                                    }
-                                    EntityType::Wall => {
-                                        match pos.is_cardinal_neighbor(&other_pos) {
-                                            Some(dir) => {
-                                                if matches!(other_type, EntityType::Gate) {
-                                                    if dir.is_straight(&other.direction) {
-                                                        connected_gates.push(dir);
-                                                    }
-                                                } else {
-                                                    match dir {
-                                                        Direction::North => up = true,
-                                                        Direction::South => down = true,
-                                                        Direction::East => right = true,
-                                                        Direction::West => left = true,
-                                                        _ => continue,
-                                                    }
-                                                }
-                                            }
-                                            None => continue,
-                                        }
-                                    }
-                                    EntityType::TransportBelt => {
-                                        let neighbor = match other_type {
-                                            EntityType::TransportBelt => {
-                                                pos.is_cardinal_neighbor(&other_pos)
-                                            }
-                                            EntityType::UndergroundBelt
-                                            | EntityType::LinkedBelt => {
-                                                let dir = pos.is_cardinal_neighbor(&other_pos);
-
-                                                if let Some(dir) = dir {
-                                                    let Some(u_output) =
-                                                        other.type_.as_ref().map(|t| {
-                                                            matches!(
-                                                                t,
-                                                                blueprint::UndergroundType::Output
-                                                            )
-                                                        })
-                                                    else {
-                                                        continue;
-                                                    };
-
-                                                    let other_dir = if u_output {
-                                                        other.direction.flip()
-                                                    } else {
-                                                        other.direction
-                                                    };
-
-                                                    if dir != other_dir {
-                                                        continue;
-                                                    }
-                                                }
-
-                                                dir
-                                            }
-                                            EntityType::Splitter => {
-                                                pos.is_2wide_cardinal_neighbor(&other_pos)
-                                            }
-                                            EntityType::Loader => {
-                                                pos.is_2long_cardinal_neighbor(&other_pos)
-                                            }
-                                            _ => continue,
-                                        };
-
-                                        if let Some(dir) = neighbor {
-                                            if dir != other.direction.flip() {
-                                                continue;
-                                            }
-
-                                            match dir {
-                                                Direction::North => up = true,
-                                                Direction::South => down = true,
-                                                Direction::East => right = true,
-                                                Direction::West => left = true,
-                                                _ => {}
-                                            }
-                                        }
-                                    }
-                                    _ => continue,
-                                }
-                            }
-                        }
-                    }
-
-                    Some(ConnectedDirections::from_directions(up, down, left, right))
-                } else {
-                    None
+        .map(|e| {
+            let position = (&e.position).into();
+
+            let hitbox = bp_helper::entity_selection_area(e, data).map(|area| {
+                let (left, top) = size.map_to_pixel(area.top_left());
+                let (right, bottom) = size.map_to_pixel(area.bottom_right());
+
+                PixelBox {
+                    left,
+                    top,
+                    right,
+                    bottom,
                 }
             });
 
-            let mut render_opts = bp_entity2render_opts(e, data);
-            render_opts.connections = connections;
-            render_opts.connected_gates = connected_gates;
-            render_opts.draw_gate_patch = draw_gate_patch;
-
-            'recipe_icon: {
-                if !e.recipe.is_empty() && e_data.recipe_visible() {
-                    if !data.contains_recipe(&e.recipe) {
-                        unknown.insert((*e.recipe).clone());
-                        break 'recipe_icon;
-                    }
-
-                    if let Some(icon) = data.get_recipe_icon(
-                        &e.recipe,
-                        render_layers.scale() * 0.75,
-                        used_mods,
-                        image_cache,
-                    ) {
-                        render_layers.add(
-                            icon,
-                            &render_opts.position,
-                            InternalRenderLayer::IconOverlay,
-                        );
-                    } else {
-                        warn!(
-                            "failed to render recipe icon for {} at {:?} [{}]",
-                            e.recipe, e.position, e.name
-                        );
-                    }
-                }
+            EntityAnchor {
+                entity_number: e.entity_number,
+                name: (*e.name).clone(),
+                position,
+                pixel: size.map_to_pixel(&position),
+                hitbox,
             }
+        })
+        .collect();
+
+    let report = RenderReport {
+        scale: size.scale(),
+        width: img.width(),
+        height: img.height(),
+        tile_resolution: size.tile_res(),
+        top_left: *size.top_left(),
+        bottom_right: *size.bottom_right(),
+        entities,
+        unknown: unknown.into_iter().collect(),
+        placeholders,
+        diagnostics,
+        warnings: size_warning.into_iter().collect(),
+        timings,
+    };
 
-            // filter icons / priority arrows
-            'filters_priority: {
-                if let Some(prio_in) = &e.input_priority {
-                    let offset = e.direction.rotate_vector(
-                        prio_in.as_vector() + Vector::Tuple(0.0, 0.25) + indicator_arrow.1,
-                    );
-
-                    let arrow = match e.direction {
-                        Direction::North => indicator_arrow.0.clone(),
-                        Direction::East => imageops::rotate90(&indicator_arrow.0).into(),
-                        Direction::South => imageops::rotate180(&indicator_arrow.0).into(),
-                        Direction::West => imageops::rotate270(&indicator_arrow.0).into(),
-                        _ => break 'filters_priority,
-                    };
-
-                    render_layers.add(
-                        (arrow, offset),
-                        &render_opts.position,
-                        InternalRenderLayer::DirectionOverlay,
-                    );
-                }
+    Ok(RenderOutput {
+        image: res,
+        report,
+        thumbnail,
+        layers,
+    })
+}
 
-                if let Some(prio_out) = &e.output_priority {
-                    if e.filter.is_empty() {
-                        let offset = e.direction.rotate_vector(
-                            prio_out.as_vector() + Vector::Tuple(0.0, -0.25) + indicator_arrow.1,
-                        );
-
-                        let arrow = match e.direction {
-                            Direction::North => indicator_arrow.0.clone(),
-                            Direction::East => imageops::rotate90(&indicator_arrow.0).into(),
-                            Direction::South => imageops::rotate180(&indicator_arrow.0).into(),
-                            Direction::West => imageops::rotate270(&indicator_arrow.0).into(),
-                            _ => break 'filters_priority,
-                        };
-
-                        render_layers.add(
-                            (arrow, offset),
-                            &render_opts.position,
-                            InternalRenderLayer::DirectionOverlay,
-                        );
-                    } else {
-                        let Some(filter) = data.get_item_icon(
-                            &e.filter,
-                            render_layers.scale() * 2.2,
-                            used_mods,
-                            image_cache,
-                        ) else {
-                            warn!(
-                                "failed to render filter icon for {} at {:?} [{}]",
-                                e.filter, e.position, e.name
-                            );
-                            break 'filters_priority;
-                        };
-
-                        let offset = e.direction.rotate_vector(prio_out.as_vector() + filter.1);
-
-                        render_layers.add(
-                            (filter.0, offset),
-                            &render_opts.position,
-                            InternalRenderLayer::IconOverlay,
-                        );
-                    }
-                }
+/// Frames of an animated render, see [`render_animation`].
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationOptions {
+    /// Number of evenly spaced points of the animation cycle that are rendered.
+    pub frames: u32,
 
-                if !e.filters.is_empty() {
-                    let filter_count = e.filters.len();
-                    let mut offset = if filter_count == 1 {
-                        Vector::Tuple(0.0, 0.0)
-                    } else if filter_count == 2 {
-                        Vector::Tuple(-0.25, 0.0)
-                    } else {
-                        Vector::Tuple(-0.25, -0.25)
-                    };
-
-                    for idx in 0..filter_count.min(4) {
-                        if idx == 2 {
-                            offset += Vector::Tuple(-1.0, 0.5);
-                        }
-
-                        let Some(filter) = data.get_item_icon(
-                            &e.filters[idx],
-                            render_layers.scale() * 2.2,
-                            used_mods,
-                            image_cache,
-                        ) else {
-                            warn!(
-                                "failed to render filter icon for {} at {:?} [{}]",
-                                e.filters[idx], e.position, e.name
-                            );
-                            continue;
-                        };
-
-                        render_layers.add(
-                            (filter.0, filter.1 + offset),
-                            &render_opts.position,
-                            InternalRenderLayer::IconOverlay,
-                        );
-
-                        offset += Vector::Tuple(0.5, 0.0);
-                    }
-                }
-            }
+    /// Time every frame is shown in milliseconds.
+    pub frame_delay: u32,
+}
 
-            // modules / item requests
-            {
-                if !e.items.is_empty() {
-                    let mut items = e.items.iter().collect::<Vec<_>>();
-                    items.sort_unstable_by_key(|a| a.0);
-
-                    let scale = render_layers.scale() * 2.3;
-                    let s_box = e_data.selection_box();
-                    let width = s_box.width() - 0.25;
-                    let height = s_box.height();
-                    let count = items.iter().map(|(_, &c)| c).sum::<u32>();
-
-                    let row_len = (width / 0.5).floor() as u32;
-                    let row_count = (f64::from(count) / f64::from(row_len)).ceil() as u32;
-                    let row_len = (f64::from(count) / f64::from(row_count)).ceil() as u32;
-
-                    let start_y =
-                        ((height / 4.0) - (f64::from(row_count - 1) / 2.0) + 0.25).max(0.0);
-                    let mut offset = Vector::Tuple(0.0, start_y);
-
-                    let icons = items
-                        .iter()
-                        .filter_map(|(name, _)| {
-                            Some((
-                                (*name).clone(),
-                                data.get_item_icon(name, scale, used_mods, image_cache)?,
-                            ))
-                        })
-                        .collect::<HashMap<_, _>>();
-
-                    for chunk in e
-                        .items
-                        .iter()
-                        .flat_map(|(i, c)| std::iter::repeat(i).take(*c as usize))
-                        .collect::<Vec<_>>()
-                        .as_slice()
-                        .chunks(row_len as usize)
-                    {
-                        let count = chunk.len() as u32;
-                        if count == 0 {
-                            continue;
-                        }
-
-                        let start_x = f64::from(count - 1) * -0.25; // count / 2 * -0.5
-                        offset += Vector::Tuple(start_x, 0.0);
-
-                        for &item in chunk {
-                            if let Some(icon) = icons.get(item) {
-                                render_layers.add(
-                                    (icon.0.clone(), offset),
-                                    &render_opts.position,
-                                    InternalRenderLayer::IconOverlay,
-                                );
-                            }
-
-                            offset += Vector::Tuple(0.5, 0.0);
-                        }
-
-                        offset = Vector::Tuple(0.0, offset.y() + 0.5);
-                    }
-                }
-            }
+/// Renders the animation cycle of animated entities like belts & machines as a looping GIF.
+///
+/// Every frame is a full render, so this takes `frames` times as long as [`render`].
+/// The pixel budget of `options` is shared by all frames.
+/// Annotations and the bill of materials are not added.
+#[instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub fn render_animation(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    target_res: f64,
+    min_scale: f64,
+    overlays: &[overlay::Overlay],
+    options: &RenderOptions,
+    animation: AnimationOptions,
+) -> Result<Vec<u8>, ScannerError> {
+    let bp = raw_bp
+        .as_blueprint()
+        .ok_or_else(|| report!(ScannerError::NoBlueprint))?;
+
+    let region = options.region.as_ref();
+    let cropped = region.map(|region| bp_helper::crop(bp, region));
+    let bp = cropped.as_ref().unwrap_or(bp);
+
+    // every frame is kept until the GIF is encoded
+    let frame_count = animation.frames.max(1);
+    let budget = RenderOptions {
+        max_pixels: options
+            .max_pixels
+            .map(|max_pixels| max_pixels / u64::from(frame_count)),
+        ..*options
+    };
+    let (size, _) =
+        budgeted_target_size(bp, data, target_res, min_scale, region, overlays, &budget)?;
+    info!("target size: {size}, {frame_count} frames");
 
-            // inserter indicators
-            'inserter_indicators: {
-                let Some(proto) = data.get_proto::<InserterPrototype>(&e.name) else {
-                    break 'inserter_indicators;
-                };
-
-                #[allow(clippy::items_after_statements)]
-                fn indicator_helper(
-                    pos: Vector,
-                    opts: &prototypes::entity::RenderOpts,
-                    graphics: &(image::DynamicImage, Vector),
-                    layers: &mut RenderLayerBuffer,
-                ) {
-                    let img = if pos.x() != 0.0 && pos.x() != 0.0 {
-                        let angle = pos.y().atan2(pos.x()) + std::f64::consts::FRAC_PI_2;
-                        rotate_about_center(
-                            &graphics.0.to_rgba8(),
-                            angle as f32,
-                            geometric_transformations::Interpolation::Nearest,
-                            image::Rgba([0, 0, 0, 0]),
-                        )
-                        .into()
-                    } else if pos.y() < 0.0 {
-                        graphics.0.clone()
-                    } else if pos.y() > 0.0 {
-                        imageops::rotate180(&graphics.0).into()
-                    } else if pos.x() > 0.0 {
-                        imageops::rotate90(&graphics.0).into()
-                    } else {
-                        imageops::rotate270(&graphics.0).into()
-                    };
-
-                    layers.add(
-                        (img, pos.shorten_by(0.45)),
-                        &opts.position,
-                        InternalRenderLayer::DirectionOverlay,
-                    );
-                }
+    let image_cache = &mut ImageCache::new();
+    let delay = image::Delay::from_numer_denom_ms(animation.frame_delay, 1);
+
+    let mut frames = Vec::with_capacity(frame_count as usize);
+    for frame in 0..frame_count {
+        let mut layers = job::RenderJob::new(bp, data, used_mods)
+            .overlays(overlays)
+            .options(*options)
+            .animation_progress(f64::from(frame) / f64::from(frame_count))
+            .run(
+                RenderLayerBuffer::new(size.clone()),
+                image_cache,
+                &mut Diagnostics::new(),
+            )
+            .change_context(ScannerError::RenderError)
+            .attach_printable_lazy(|| format!("failed to render frame {frame}"))?
+            .layers;
+
+        frames.push(image::Frame::from_parts(
+            layers.combine().to_rgba8(),
+            0,
+            0,
+            delay,
+        ));
+    }
 
-                indicator_helper(
-                    proto.get_pickup_position(
-                        e.direction,
-                        e.pickup_position.as_ref().map(std::convert::Into::into),
-                    ),
-                    &render_opts,
-                    &indicator_line,
-                    &mut render_layers,
-                );
-                indicator_helper(
-                    proto.get_insert_position(
-                        e.direction,
-                        e.drop_position.as_ref().map(std::convert::Into::into),
-                    ),
-                    &render_opts,
-                    &indicator_arrow,
-                    &mut render_layers,
-                );
-            }
+    let mut res = Vec::new();
+    {
+        let mut encoder = image::codecs::gif::GifEncoder::new(&mut res);
+        encoder
+            .set_repeat(image::codecs::gif::Repeat::Infinite)
+            .change_context(ScannerError::RenderError)?;
+        encoder
+            .encode_frames(frames)
+            .change_context(ScannerError::RenderError)?;
+    }
 
-            // store wire connections for wire rendering
-            let mut wires0 = e
-                .neighbours
-                .iter()
-                .map(|n| (*n, [true, false, false]))
-                .collect::<ConnectedEntities>();
-            let mut wires1 = ConnectedEntities::new();
-            let mut wires2 = ConnectedEntities::new();
-            let mut is_switch = false;
-
-            if let Some(circuit_cons) = &e.connections {
-                match circuit_cons {
-                    blueprint::Connection::SingleOne { one } => one.transform(&mut wires0),
-                    blueprint::Connection::SingleTwo { two } => two.transform(&mut wires1),
-                    blueprint::Connection::Double { one, two } => {
-                        one.transform(&mut wires0);
-                        two.transform(&mut wires1);
-                    }
-                    blueprint::Connection::Switch { one, cu0, cu1 } => {
-                        one.transform(&mut wires0);
-                        cu0.transform(&mut wires1);
-                        cu1.transform(&mut wires2);
-                        is_switch = true;
-                    }
-                }
-            }
+    Ok(res)
+}
 
-            if !wires0.is_empty() || !wires1.is_empty() | !wires2.is_empty() {
-                wire_connections.insert(
-                    e.entity_number,
-                    (
-                        e.position.clone().into(),
-                        ([wires0, wires1, wires2], is_switch),
-                    ),
-                );
-            }
+/// Background matching the surface the blueprint is meant for:
+/// stars behind space platforms, terrain behind planet blueprints and lab tiles otherwise.
+#[must_use]
+pub const fn surface_background(hint: blueprint::SurfaceHint) -> Background {
+    match hint {
+        blueprint::SurfaceHint::Unknown => Background::LabTiles,
+        blueprint::SurfaceHint::Planet => Background::TERRAIN,
+        blueprint::SurfaceHint::SpacePlatform => Background::Starfield,
+    }
+}
 
-            data.render_entity(
-                &e.name,
-                &render_opts,
-                used_mods,
-                &mut render_layers,
-                image_cache,
-            )
-        })
-        .count();
+/// Side length of the images returned by [`thumbnail`].
+pub const THUMBNAIL_SIZE: u32 = 256;
 
-    info!("entities: {}, layers: {rendered_count}", bp.entities.len());
+/// Renders only the icon composite of a blueprint or book as a [`THUMBNAIL_SIZE`] PNG,
+/// without rendering any entities. Only the item & icon prototypes of `data` are used.
+#[instrument(skip_all)]
+pub fn thumbnail(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+) -> Result<Vec<u8>, ScannerError> {
+    let mut img = render_thumbnail(raw_bp, data, used_mods, &mut ImageCache::new())
+        .ok_or(ScannerError::RenderError)
+        .attach_printable("could not find the blueprint item icon")?;
+
+    if img.width() != THUMBNAIL_SIZE || img.height() != THUMBNAIL_SIZE {
+        img = img.resize_exact(
+            THUMBNAIL_SIZE,
+            THUMBNAIL_SIZE,
+            imageops::FilterType::Lanczos3,
+        );
+    }
 
-    // render tiles
-    let rendered_count = bp
-        .tiles
-        .iter()
-        .filter_map(|t| {
-            let Some(tile) = data.get_proto::<TilePrototype>(&t.name) else {
-                unknown.insert((*t.name).clone());
-                return None;
-            };
+    encode_png(&img)
+}
 
-            let position: MapPosition = (&t.position).into();
-            tile.render(
-                &(position + MapPosition::Tuple(0.5, 0.5)),
-                used_mods,
-                &mut render_layers,
-                image_cache,
-            )
-        })
-        .count();
+/// Renders the entity heatmap (see [`heatmap::heatmap`]) of a blueprint as PNG.
+/// Only the entity prototypes of `data` are used, no sprites are loaded.
+#[instrument(skip_all)]
+pub fn render_heatmap(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    target_res: f64,
+    coloring: heatmap::Coloring,
+    region: Option<&Region>,
+    background: Background,
+) -> Result<Vec<u8>, ScannerError> {
+    let bp = raw_bp
+        .as_blueprint()
+        .ok_or_else(|| report!(ScannerError::NoBlueprint))?;
 
-    info!("tiles: {}, layers: {rendered_count}", bp.tiles.len());
+    let cropped = region.map(|region| bp_helper::crop(bp, region));
+    let bp = cropped.as_ref().unwrap_or(bp);
+
+    let img = heatmap::heatmap(bp, data, target_res, coloring, background)
+        .ok_or(ScannerError::RenderError)
+        .attach_printable("the blueprint has no entities")?;
+    info!("heatmap size: {}x{}", img.width(), img.height());
+
+    encode_png(&img)
+}
+
+fn encode_png(img: &image::DynamicImage) -> Result<Vec<u8>, ScannerError> {
+    let mut res = Vec::new();
+    let enc = png::PngEncoder::new_with_quality(
+        &mut res,
+        png::CompressionType::Best,
+        png::FilterType::default(),
+    );
 
-    render_layers.draw_wires(&wire_connections, util_sprites, used_mods, image_cache);
-    render_layers.generate_background();
+    enc.write_image(
+        img.as_bytes(),
+        img.width(),
+        img.height(),
+        img.color().into(),
+    )
+    .change_context(ScannerError::RenderError)?;
+
+    Ok(res)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn render_bp(
+    bp: &blueprint::Blueprint,
+    data: &prototypes::DataUtil,
+    used_mods: &UsedMods,
+    render_layers: RenderLayerBuffer,
+    image_cache: &mut ImageCache,
+    overlays: &[overlay::Overlay],
+    options: &RenderOptions,
+    diagnostics: &mut Diagnostics,
+) -> Option<(image::DynamicImage, HashSet<String>)> {
+    let (mut render_layers, unknown) = render_bp_layers(
+        bp,
+        data,
+        used_mods,
+        render_layers,
+        image_cache,
+        overlays,
+        options,
+        diagnostics,
+    )?;
 
     Some((render_layers.combine(), unknown))
 }
 
+/// Renders the blueprint into the individual layers without combining them.
+///
+/// Entities that fail to render are replaced by a placeholder, unless [`RenderOptions::strict`] is set
+/// in which case the whole render fails. Problems of individual entities are recorded in `diagnostics`.
+/// Use a [`job::RenderJob`] directly to cancel the render or to track its progress.
+#[allow(clippy::too_many_arguments)]
+pub fn render_bp_layers(
+    bp: &blueprint::Blueprint,
+    data: &prototypes::DataUtil,
+    used_mods: &UsedMods,
+    render_layers: RenderLayerBuffer,
+    image_cache: &mut ImageCache,
+    overlays: &[overlay::Overlay],
+    options: &RenderOptions,
+    diagnostics: &mut Diagnostics,
+) -> Option<(RenderLayerBuffer, HashSet<String>)> {
+    job::RenderJob::new(bp, data, used_mods)
+        .overlays(overlays)
+        .options(*options)
+        .run(render_layers, image_cache, diagnostics)
+        .map(|output| (output.layers, output.unknown))
+        .map_err(|err| warn!("{err}"))
+        .ok()
+}
+
 #[instrument(skip_all)]
 pub fn render_thumbnail(
     bp: &blueprint::Data,
@@ -1115,26 +1056,7 @@ pub fn render_thumbnail(
                 offset += Vector::Tuple(-1.0, 0.5);
             }
 
-            let res = match &icon.signal {
-                SignalID::Item { name } => data.get_item_icon(
-                    name.clone().unwrap_or_default().as_str(),
-                    scale,
-                    used_mods,
-                    image_cache,
-                ),
-                SignalID::Fluid { name } => data.get_fluid_icon(
-                    name.clone().unwrap_or_default().as_str(),
-                    scale,
-                    used_mods,
-                    image_cache,
-                ),
-                SignalID::Virtual { name } => data.get_signal_icon(
-                    name.clone().unwrap_or_default().as_str(),
-                    scale,
-                    used_mods,
-                    image_cache,
-                ),
-            };
+            let res = bp_helper::signal_icon(&icon.signal, data, scale, used_mods, image_cache);
 
             let Some((res, _)) = res else {
                 return;
@@ -1179,188 +1101,120 @@ impl PlayerData {
     }
 }
 
-#[derive(Debug)]
-pub struct DependencyResolutionError;
-
-impl Context for DependencyResolutionError {}
-
-impl std::fmt::Display for DependencyResolutionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "mod dependency resolving error")
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn size(width: f64, height: f64, tile_res: f64) -> TargetSize {
+        TargetSize::new(
+            (width * tile_res) as u32,
+            (height * tile_res) as u32,
+            32.0 / tile_res,
+            MapPosition::Tuple(0.0, 0.0),
+            MapPosition::Tuple(width, height),
+        )
     }
-}
 
-#[instrument(skip_all, fields(required = required.keys().cloned().collect::<Vec<_>>().join(", ")))]
-pub async fn resolve_mod_dependencies(
-    required: &DependencyList,
-    mod_list: &mut ModList,
-) -> Result<UsedVersions, DependencyResolutionError> {
-    // load local dependency info of required mods and their dependencies
-    {
-        let span = info_span!("load_local_deps", loaded_mods = field::Empty).entered();
-        let mut queue = required
-            .iter()
-            .map(|(n, d)| (n.clone(), *d))
-            .collect::<Vec<_>>();
-        let mut completed = HashSet::new();
+    #[test]
+    fn sizes_within_budget_are_kept() {
+        assert!(limit_target_size(&size(100.0, 50.0, 32.0), 3200 * 1600).is_none());
+        assert!(limit_target_size(&size(100.0, 50.0, 32.0), 3200 * 1600 - 1).is_some());
+    }
 
-        while let Some((name, dep_version)) = &queue.pop() {
-            if completed.contains(name) {
-                continue;
-            }
+    #[test]
+    fn limited_size_fits_budget() {
+        // 100x50 tiles at 32 px per tile
+        let limited = limit_target_size(&size(100.0, 50.0, 32.0), 1_000_000).unwrap();
 
-            completed.insert(name.clone());
+        assert!(u64::from(limited.width()) * u64::from(limited.height()) <= 1_000_000);
+        assert_eq!((limited.width(), limited.height()), (1414, 707));
 
-            if let Some(deps) = mod_list.load_local_dependency_info(name, dep_version) {
-                for (dep_name, dep) in deps {
-                    if !dep.is_required() {
-                        continue;
-                    }
+        // the resolution is not rounded to whole pixels per tile
+        assert!((limited.tile_res() - 200.0f64.sqrt()).abs() < 1e-9);
+        assert!((limited.scale() - 32.0 / 200.0f64.sqrt()).abs() < 1e-9);
+        assert_eq!(limited.top_left(), &MapPosition::Tuple(0.0, 0.0));
+        assert_eq!(limited.bottom_right(), &MapPosition::Tuple(100.0, 50.0));
+    }
 
-                    if !completed.contains(&dep_name) {
-                        queue.push((dep_name, *dep.version()));
-                    }
-                }
-            }
-        }
+    #[test]
+    fn huge_blueprints_get_less_than_a_pixel_per_tile() {
+        let limited = limit_target_size(&size(4000.0, 1000.0, 32.0), 40_000).unwrap();
 
-        span.record("loaded_mods", completed.len());
-        span.exit();
-    }
+        assert_eq!((limited.width(), limited.height()), (400, 100));
+        assert!((limited.tile_res() - 0.1).abs() < 1e-9);
 
-    // try to resolve dependencies with local mods
-    match mod_list
-        .solve_dependencies(required)
-        .change_context(DependencyResolutionError)
-        .attach_printable_lazy(|| "could not resolve dependencies with local mods")
-    {
-        Ok(res) => return Ok(res),
-        Err(err) => info!("{err:?}"),
+        // very long & thin blueprints are a single pixel wide
+        let limited = limit_target_size(&size(100_000.0, 1.0, 32.0), 10).unwrap();
+        assert_eq!((limited.width(), limited.height()), (10, 1));
+        assert!((limited.tile_res() - 0.0001).abs() < 1e-12);
     }
+}
 
-    info!("fetching dependency info from mod portal");
-
-    let mut process_queue = required.keys().cloned().collect::<Vec<_>>();
-    let mut fetched_deps = Vec::new();
-    fetched_deps.extend(Mod::wube_mods().map(std::string::ToString::to_string));
+#[cfg(test)]
+pub(crate) mod test_util {
+    #![allow(clippy::unwrap_used)]
 
-    while let Some(name) = process_queue.pop() {
-        if fetched_deps.contains(&name) {
-            continue;
-        }
+    use std::{io::Cursor, path::Path, sync::Arc, sync::OnceLock};
 
-        let info = factorio_api::full_info(&name)
-            .await
-            .change_context(DependencyResolutionError)
-            .attach_printable_lazy(|| format!("fetching mod info for {name} failed"))?;
+    use mod_util::{
+        mod_loader::ModError,
+        sprite_source::{self, SharedSpriteSource, SpriteSource},
+        UsedMods,
+    };
+    use prototypes::{DataRaw, DataUtil};
 
-        let deps_info = info
-            .releases
-            .into_iter()
-            .map(|r| (r.version, r.info_json.dependencies))
-            .collect::<HashMap<_, _>>();
-
-        mod_list.set_dependency_info(&name.clone(), deps_info.clone());
-
-        let queue_add = deps_info
-            .values()
-            .flatten()
-            .filter_map(|d| {
-                if d.is_required() {
-                    Some(d.name().clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<HashSet<_>>();
+    /// The prototypes of the base game test dump, loaded once for all tests.
+    pub fn base_data() -> &'static DataUtil {
+        static DATA: OnceLock<DataUtil> = OnceLock::new();
 
-        debug!("fetched dependency info for {name}");
+        DATA.get_or_init(|| {
+            let dump = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!(
+                "../prototypes/test_dumps/base.{}.json",
+                prototypes::targeted_engine_version()
+            ));
+            let (data, _) = DataRaw::load_lenient(&dump).unwrap();
 
-        process_queue.extend(queue_add);
-        fetched_deps.push(name);
+            DataUtil::new(data)
+        })
     }
 
-    info!("collected dependency info for {} mods", fetched_deps.len());
-
-    mod_list
-        .solve_dependencies(required)
-        .change_context(DependencyResolutionError)
-}
-
-#[derive(Debug)]
-pub enum ModDownloadError {
-    MissingCredentials,
-    TriedToDownloadWubeMod(String, Version),
-    DownloadFailed(String, Version),
-    SaveFailed(String, Version),
-}
-
-impl Context for ModDownloadError {}
+    /// Answers every sprite request with the same transparent image.
+    #[derive(Debug)]
+    struct BlankSprites(Vec<u8>);
 
-impl std::fmt::Display for ModDownloadError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::MissingCredentials => {
-                write!(f, "missing credentials for mod portal")
-            }
-            Self::TriedToDownloadWubeMod(name, version) => {
-                write!(f, "tried to download wube mod {name} v{version}")
-            }
-            Self::DownloadFailed(name, version) => {
-                write!(f, "failed to download mod {name} v{version}")
-            }
-            Self::SaveFailed(name, version) => write!(f, "failed to save mod {name} v{version}",),
+    impl SpriteSource for BlankSprites {
+        fn fetch(&self, _mod_name: &str, _path: &str) -> Result<Vec<u8>, ModError> {
+            Ok(self.0.clone())
         }
     }
-}
-
-#[instrument(skip_all, fields(count = missing.len()))]
-pub async fn download_mods(
-    missing: UsedVersions,
-    destination: &Path,
-) -> Result<(), ModDownloadError> {
-    let (username, token) = {
-        let env_username = env::var("FACTORIO_USERNAME").ok();
-        let env_token = env::var("FACTORIO_TOKEN").ok();
-
-        if let (Some(username), Some(token)) = (env_username.clone(), env_token.clone()) {
-            (username, token)
-        } else {
-            let player_data = PlayerData::load(&destination.join("../player-data.json"))
-                .change_context(ModDownloadError::MissingCredentials).attach_printable("you can either use the game to login to your account\nor you provide the environment variables FACTORIO_USERNAME & FACTORIO_TOKEN\nwhich also work from a .env file")?;
-
-            match (
-                player_data.username,
-                player_data.token,
-                env_username,
-                env_token,
-            ) {
-                (Some(username), Some(token), _, _)
-                | (Some(username), None, _, Some(token))
-                | (None, Some(token), Some(username), _) => (username, token),
-                _ => return Err(report!(ModDownloadError::MissingCredentials).attach_printable("you can either use the game to login to your account\nor you provide the environment variables FACTORIO_USERNAME & FACTORIO_TOKEN\nwhich also work from a .env file"))
-            }
-        }
-    };
 
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(2));
+    /// `core` & `base` with a blank image for every sprite, to render without the game files.
+    pub fn used_mods() -> UsedMods {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgba8(64, 64)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let source: SharedSpriteSource = Arc::new(BlankSprites(png));
+        let version = prototypes::targeted_engine_version();
+
+        sprite_source::used_mods(
+            [("core".to_owned(), version), ("base".to_owned(), version)],
+            &source,
+        )
+    }
 
-    for (name, version) in missing {
-        ensure!(
-            !Mod::wube_mods().contains(&name.as_str()),
-            ModDownloadError::TriedToDownloadWubeMod(name, version)
+    /// A 2.0 blueprint with the given entities and wires as JSON, positions are kept as they are.
+    pub fn blueprint(entities: &str, wires: &str) -> blueprint::Blueprint {
+        let json = format!(
+            r#"{{"blueprint":{{"item":"blueprint","icons":[],"version":562949954928640,
+                "entities":[{entities}],"wires":[{wires}]}}}}"#
         );
 
-        info!("downloading {name} v{version}");
-        let dl = factorio_api::fetch_mod(&name, &version, &username, &token)
-            .await
-            .change_context(ModDownloadError::DownloadFailed(name.clone(), version))?;
-
-        fs::write(destination.join(format!("{name}_{version}.zip")), dl)
-            .change_context(ModDownloadError::SaveFailed(name, version))?;
-
-        interval.tick().await;
+        serde_json::from_str::<blueprint::Data>(&json)
+            .unwrap()
+            .as_blueprint()
+            .unwrap()
+            .clone()
     }
-
-    Ok(())
 }
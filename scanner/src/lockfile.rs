@@ -0,0 +1,113 @@
+//! Resolved mod environment of a render, to reproduce it later with `--locked`.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Context, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use mod_util::{
+    mod_info::{DependencyVersion, Version},
+    AnyBasic, DependencyList, UsedMods,
+};
+
+use crate::dump_cache;
+
+#[derive(Debug)]
+pub enum LockfileError {
+    Io(PathBuf),
+    Parse(PathBuf),
+    Serialize,
+}
+
+impl Context for LockfileError {}
+
+impl std::fmt::Display for LockfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path) => write!(f, "lockfile io error at {}", path.display()),
+            Self::Parse(path) => write!(f, "invalid lockfile {}", path.display()),
+            Self::Serialize => write!(f, "failed to serialize lockfile"),
+        }
+    }
+}
+
+/// Exact versions of the active mods & a hash of the startup settings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    /// Version of the `base` mod, the game itself can't be downloaded.
+    pub factorio: Version,
+
+    /// Hash of the startup settings as used for the prototype dump cache.
+    pub settings: String,
+
+    /// Every active mod except `core` & `base`, the DLCs included.
+    #[serde(default)]
+    pub mods: BTreeMap<String, Version>,
+}
+
+impl Lockfile {
+    #[must_use]
+    pub fn new(active_mods: &UsedMods, settings: &BTreeMap<String, AnyBasic>) -> Self {
+        let factorio = active_mods
+            .get("base")
+            .map_or_else(prototypes::targeted_engine_version, |m| m.info.version);
+
+        let mods = active_mods
+            .iter()
+            .filter(|(name, _)| !matches!(name.as_str(), "core" | "base"))
+            .map(|(name, m)| (name.clone(), m.info.version))
+            .collect();
+
+        Self {
+            factorio,
+            settings: Self::hash(settings),
+            mods,
+        }
+    }
+
+    fn hash(settings: &BTreeMap<String, AnyBasic>) -> String {
+        format!("{:X}", dump_cache::settings_hash(settings))
+    }
+
+    /// The default lockfile path for a render output, `<out>.lock`.
+    #[must_use]
+    pub fn path_for(out: &Path) -> PathBuf {
+        let mut path = out.as_os_str().to_owned();
+        path.push(".lock");
+        path.into()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, LockfileError> {
+        let content =
+            std::fs::read_to_string(path).change_context(LockfileError::Io(path.to_path_buf()))?;
+
+        toml::from_str(&content).change_context(LockfileError::Parse(path.to_path_buf()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LockfileError> {
+        let content = toml::to_string(self).change_context(LockfileError::Serialize)?;
+
+        std::fs::write(path, content).change_context(LockfileError::Io(path.to_path_buf()))
+    }
+
+    /// The locked mods with their exact versions, `base` included.
+    #[must_use]
+    pub fn required_mods(&self) -> DependencyList {
+        std::iter::once(("base".to_owned(), DependencyVersion::Exact(self.factorio)))
+            .chain(
+                self.mods
+                    .iter()
+                    .map(|(name, version)| (name.clone(), DependencyVersion::Exact(*version))),
+            )
+            .collect()
+    }
+
+    #[must_use]
+    pub fn settings_match(&self, settings: &BTreeMap<String, AnyBasic>) -> bool {
+        self.settings == Self::hash(settings)
+    }
+}
@@ -3,13 +3,17 @@
 use std::{
     env,
     fs::{self},
+    io,
     path::{Path, PathBuf},
     process::ExitCode,
+    time::{Duration, Instant},
 };
 
 use clap::{Parser, Subcommand};
-use error_stack::{Context, Result, ResultExt};
-use tracing::{error, info, warn};
+use error_stack::{report, Context, Result, ResultExt};
+use tracing::{debug, error, info, warn};
+
+use prototypes::{Background, DataUtil, IconStyle, RenderOptions, WirePalette};
 
 #[allow(clippy::wildcard_imports)]
 use scanner::*;
@@ -29,31 +33,116 @@ struct Cli {
     #[clap(long, value_parser)]
     factorio_bin: Option<PathBuf>,
 
+    /// Format of the log output, json writes one object per line for log collectors
+    #[clap(long, value_enum, env = "SCANNER_LOG_FORMAT", default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+
     #[clap(flatten)]
     args: CommandArgs,
 }
 
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ProfileFormat {
+    Table,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum RenderMode {
+    /// Draw the sprites of all entities
+    #[default]
+    Sprites,
+
+    /// Color every tile by its entities without loading any sprites, fast even for gigantic blueprints
+    Heatmap,
+}
+
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Parser, Debug)]
 struct CommandArgs {
-    /// Blueprint string or file to render
     #[clap(subcommand)]
-    input: Input,
+    command: Command,
 
-    /// Path to the data dump json file. If not set, the data will be dumped automatically
+    /// Path to the data dump json file. If not set, the data will be dumped automatically.
+    /// With a dump no Factorio installation is needed, the sprites are read from the mods folder
     #[clap(long, value_parser)]
     prototype_dump: Option<PathBuf>,
 
-    /// Preset to use
-    #[clap(long, value_enum)]
-    preset: Option<preset::Preset>,
+    /// How the prototypes are dumped without `--prototype-dump`. `lua` doesn't need the game binary,
+    /// only the mods & the `data` folder of the game
+    #[clap(long, value_enum, default_value_t = DumpMethod::Factorio)]
+    dump_method: DumpMethod,
+
+    /// Skip prototypes of the data dump that fail to load instead of aborting, for dumps not created by the game
+    #[clap(long)]
+    lenient_dump: bool,
+
+    /// Only load the prototypes the blueprint uses & the ones they need. Faster for big modpacks,
+    /// but queries about other prototypes (e.g. recipe suggestions) only know the loaded ones
+    #[clap(long)]
+    filter_prototypes: bool,
+
+    /// Preset to use, one of the bundled ones or defined in a preset file
+    #[clap(long, value_name = "NAME")]
+    preset: Option<String>,
+
+    /// TOML file with additional presets, can be repeated. Presets with the name of a bundled one replace it
+    #[clap(long = "preset-file", value_name = "PATH", value_parser)]
+    preset_files: Vec<PathBuf>,
+
+    /// Lockfile of an earlier render to use the exact same mods again, instead of the preset & the mod info of the blueprint.
+    /// Renders write their lockfile next to the output as `<out>.lock`
+    #[clap(long, value_name = "FILE", value_parser)]
+    locked: Option<PathBuf>,
+
+    /// Enable the mods found on the mod portal for unknown prototypes of blueprints without mod info.
+    /// Without it they are only proposed
+    #[clap(long)]
+    auto_detect_mods: bool,
+
+    /// DLCs to enable, e.g. `space-age,quality`. Required ones are added & all others stay disabled.
+    /// Replaces the DLCs of the preset & the blueprint
+    #[clap(
+        long,
+        value_name = "DLC",
+        use_value_delimiter = true,
+        value_delimiter = ','
+    )]
+    dlc: Vec<mod_util::dlc::Dlc>,
 
     /// List of additional mods to use
     #[clap(long, value_parser, use_value_delimiter = true, value_delimiter = ',')]
     mods: Vec<String>,
 
-    /// Path to the output file
-    #[clap(short, long, value_parser)]
-    out: PathBuf,
+    /// Unzipped mod directory to use, e.g. a mod in development. Gets symlinked into the mods folder, can be repeated
+    #[clap(long = "mod-dir", value_name = "PATH", value_parser)]
+    mod_dirs: Vec<PathBuf>,
+
+    /// Startup mod setting to use when dumping the data, can be repeated. Overrides settings from the blueprint
+    #[clap(long = "setting", value_name = "NAME=VALUE", value_parser = parse_setting)]
+    settings: Vec<(String, mod_util::AnyBasic)>,
+
+    /// Directory to cache downloaded mods in, shared between all mods folders [default: ~/.cache/factorio-scanner/mods]
+    #[clap(long, value_parser)]
+    mod_cache: Option<PathBuf>,
+
+    /// Maximum total size of the cached prototype dumps in MiB, the least recently used ones are removed first
+    #[clap(long, value_name = "MIB", env = "SCANNER_DUMP_CACHE_SIZE")]
+    dump_cache_size: Option<u64>,
+
+    /// Path to the output file (directory for `icons`), required for rendering
+    #[clap(short, long, value_parser, global = true)]
+    out: Option<PathBuf>,
+
+    /// Utility sprites set to draw wires, indicators & placeholders with, for mods that add their own set
+    #[clap(long, value_name = "NAME", default_value = DataUtil::DEFAULT_UTIL_SPRITES)]
+    utility_sprites: String,
 
     /// Target resolution (1 side of a square) in pixels
     #[clap(long = "res", default_value_t = 2048.0)]
@@ -62,10 +151,249 @@ struct CommandArgs {
     /// Minimum scale to use (below 0.5 makes not much sense, vanilla HR mode is 0.5)
     #[clap(long, default_value_t = 0.5)]
     min_scale: f64,
+
+    /// How to draw the blueprint
+    #[clap(long, value_enum, default_value_t)]
+    mode: RenderMode,
+
+    /// What the colors of `--mode heatmap` show
+    #[clap(long, value_enum, default_value_t)]
+    heatmap_by: heatmap::Coloring,
+
+    /// Render this many frames of animated entities like belts & machines into an animated GIF
+    #[clap(long, value_name = "N", conflicts_with = "all")]
+    frames: Option<u32>,
+
+    /// Time every frame of `--frames` is shown in milliseconds
+    #[clap(long, value_name = "MS", default_value_t = 50)]
+    frame_delay: u32,
+
+    /// List of additional overlays to draw
+    #[clap(
+        long = "overlay",
+        value_enum,
+        use_value_delimiter = true,
+        value_delimiter = ','
+    )]
+    overlays: Vec<overlay::Overlay>,
+
+    /// Fail the render instead of drawing placeholders for entities that could not be rendered
+    #[clap(long)]
+    strict: bool,
+
+    /// Don't draw recipe, filter & module icons or inserter / priority indicators
+    #[clap(long)]
+    no_alt_mode: bool,
+
+    /// Don't draw circuit & copper wires
+    #[clap(long)]
+    no_wires: bool,
+
+    /// Skip drawing wires if the blueprint has more than this many, 0 draws all of them
+    #[clap(long, default_value_t = RenderOptions::DEFAULT_MAX_WIRES)]
+    max_wires: usize,
+
+    /// Draw wires as plain anti-aliased lines instead of sprites, much faster for huge blueprints
+    #[clap(long)]
+    fast_wires: bool,
+
+    /// Lower the resolution of renders with more pixels than this, 0 allows any size
+    #[clap(long, default_value_t = RenderOptions::DEFAULT_MAX_PIXELS)]
+    max_pixels: u64,
+
+    /// Wire colors: default, colorblind or #COPPER,#RED,#GREEN
+    #[clap(long, value_name = "PALETTE", default_value = "default")]
+    wire_colors: WirePalette,
+
+    /// Draw red wires dashed and green wires dotted so they can be told apart without their color
+    #[clap(long)]
+    dashed_wires: bool,
+
+    /// Low quality profile for fast renders: no shadows or icon outlines, nearest neighbor scaling, wires as lines and always the first sprite variation
+    #[clap(long)]
+    fast: bool,
+
+    /// Don't draw entity shadows
+    #[clap(long)]
+    no_shadows: bool,
+
+    /// Render the blueprint at night, lit up by lamps & other entities with lights
+    #[clap(long)]
+    night: bool,
+
+    /// Style of the alt-mode icon backdrop: none, outline[:WIDTH[:#COLOR]] or shadow[:OFFSET[:#COLOR]], sizes in pixels at 32 pixels per tile [default: outline:6:#000000]
+    #[clap(long, value_name = "STYLE")]
+    icon_style: Option<IconStyle>,
+
+    /// Background to draw behind the blueprint: lab-tiles, transparent, starfield, terrain, grid or a #RRGGBB[AA] color [default: lab-tiles]
+    #[clap(long)]
+    background: Option<Background>,
+
+    /// Leave the background transparent, same as `--background transparent`
+    #[clap(long, conflicts_with = "background")]
+    transparent_background: bool,
+
+    /// Spacing of the grid lines in tiles when using `--background grid`
+    #[clap(long, value_name = "TILES", default_value_t = 1)]
+    grid_spacing: u32,
+
+    /// Don't highlight chunk borders (every 32 tiles) when using `--background grid`
+    #[clap(long)]
+    no_chunk_lines: bool,
+
+    /// Add a footer with the blueprint dimensions in tiles and a scale bar
+    #[clap(long)]
+    annotate: bool,
+
+    /// Append an icon grid with the items needed to build the blueprint below the render
+    #[clap(long)]
+    bom: bool,
+
+    /// Seed to pick sprite variations deterministically, only the first variation is used if not set
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Only render the area x1,y1,x2,y2 of the blueprint (in tiles)
+    #[clap(long, value_name = "X1,Y1,X2,Y2", allow_hyphen_values = true)]
+    region: Option<Region>,
+
+    /// Additionally write every populated render layer as a separate transparent PNG next to the output file
+    #[clap(long)]
+    split_layers: bool,
+
+    /// Write render metadata (scale, world area, entity pixel positions, per-entity diagnostics, timings) as JSON next to the output file
+    #[clap(long)]
+    report: bool,
+
+    /// Write the pixel-space selection boxes of all entities by entity number as JSON next to the output file
+    #[clap(long)]
+    emit_hitboxes: bool,
+
+    /// Print the wall time per render stage & per entity prototype type to stdout
+    #[clap(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "table")]
+    profile: Option<ProfileFormat>,
+
+    /// Render every blueprint of a book into <out>.<index>.png, nested entries as <out>.<book>.<index>.png.
+    /// Each gets a space, planet or lab tiles background depending on its contents unless --background is set
+    #[clap(long)]
+    all: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[clap(flatten)]
+    Render(Input),
+
+    /// Render only the icon thumbnail of a blueprint
+    Thumbnail(BlueprintSource),
+
+    /// Render an overview of a blueprint book with the thumbnail, index & label of every entry
+    BookIndex(BlueprintSource),
+
+    /// List the items needed to build a blueprint, including rails, tiles & modules
+    Bom {
+        #[clap(flatten)]
+        source: BlueprintSource,
+
+        /// Print the list as JSON, including entities & tiles that can't be built by an item
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Check a blueprint for entities with overlapping collision boxes & wires out of reach, fails if any are found
+    Validate {
+        #[clap(flatten)]
+        source: BlueprintSource,
+
+        /// Print the overlaps & wires as JSON, including the collision boxes
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Repeat a blueprint in a grid, e.g. to build solar or smelter arrays
+    Tile {
+        #[clap(flatten)]
+        source: BlueprintSource,
+
+        /// Number of copies next to each other
+        #[clap(long, default_value_t = 1)]
+        nx: u32,
+
+        /// Number of copies below each other
+        #[clap(long, default_value_t = 1)]
+        ny: u32,
+
+        /// Empty tiles between the copies
+        #[clap(long, default_value_t = 0)]
+        spacing: u32,
+    },
+
+    /// Remove save & player specific data from a blueprint before publishing it
+    Scrub {
+        #[clap(flatten)]
+        source: BlueprintSource,
+
+        /// Also replace train stop & schedule station names with generic ones
+        #[clap(long)]
+        station_names: bool,
+
+        /// Player name to redact from labels & descriptions, can be repeated
+        #[clap(long = "player", value_name = "NAME")]
+        players: Vec<String>,
+    },
+
+    /// Decode & re-encode a blueprint string with normalized positions, stable ordering and maximum compression
+    Reencode(BlueprintSource),
+
+    /// Print the JSON inside a blueprint string
+    Decode {
+        #[clap(flatten)]
+        source: BlueprintSource,
+
+        /// Pretty-print the JSON instead of keeping it on a single line
+        #[clap(long)]
+        pretty: bool,
+    },
+
+    /// Encode JSON into a blueprint string
+    Encode {
+        /// Path to the JSON file, use '-' to read from stdin
+        #[clap(value_parser)]
+        file: PathBuf,
+
+        /// Remove all whitespace from the JSON before encoding it
+        #[clap(long)]
+        minify: bool,
+    },
+
+    /// Export all item, fluid, recipe & virtual signal icons as PNGs with a JSON index into the --out directory
+    Icons {
+        /// Scale to render the icons at, 0.5 gives 64px icons
+        #[clap(long, default_value_t = 0.5)]
+        scale: f64,
+    },
+
+    /// Export the research tree of the loaded mods, written to --out or printed if not set
+    TechTree {
+        /// Output format
+        #[clap(long, value_enum, default_value_t = tech_tree::Format::Json)]
+        format: tech_tree::Format,
+    },
+
+    /// Export the startup settings of the loaded mods with their defaults & accepted values, written to --out or printed if not set
+    Settings,
+
+    /// Manage the caches of downloaded mods & prototype dumps
+    #[clap(subcommand)]
+    Cache(CacheCommand),
 }
 
+// commands that render a blueprint, they only differ in where it is read from
 #[derive(Subcommand, Debug)]
 enum Input {
+    /// Render a blueprint from a file or a blueprint string, whichever the argument is
+    Render(BlueprintSource),
+
     /// Provide a blueprint string directly
     String {
         /// The blueprint string
@@ -79,6 +407,67 @@ enum Input {
         #[clap(value_parser)]
         file: PathBuf,
     },
+
+    /// Read the blueprint string from stdin
+    #[clap(alias = "-")]
+    Stdin,
+
+    /// Path to a file that contains the decoded JSON of a blueprint, e.g. from `decode` or another tool
+    Json {
+        /// Path to the JSON file, use '-' to read from stdin
+        #[clap(value_parser)]
+        file: PathBuf,
+    },
+
+    /// Link to a blueprint on factorioprints.com, factorio.school or a page that contains a blueprint string
+    #[cfg(feature = "url")]
+    Url {
+        /// The link to the blueprint
+        #[clap(value_parser)]
+        url: String,
+    },
+}
+
+/// Blueprint argument of the commands that work on a single blueprint.
+#[derive(clap::Args, Debug)]
+struct BlueprintSource {
+    /// Blueprint string, path to a file that contains one or '-' to read it from stdin
+    #[clap(value_name = "BLUEPRINT")]
+    blueprint: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Manage the downloaded mods shared between all mods folders
+    #[clap(subcommand)]
+    Mods(ModCacheCommand),
+
+    /// Manage the prototype dumps cached in the script-output folder
+    #[clap(subcommand)]
+    Dumps(DumpCacheCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ModCacheCommand {
+    /// Remove cached mods that were not used recently
+    Prune {
+        /// Remove mods that were not used for this many days, 0 clears the whole cache
+        #[clap(long, default_value_t = 30)]
+        max_age_days: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DumpCacheCommand {
+    /// List the cached prototype dumps, least recently used first
+    Ls,
+
+    /// Remove all cached prototype dumps, or only the least recently used ones with --max-size
+    Clear {
+        /// Only remove dumps until the remaining ones take at most this many MiB
+        #[clap(long, value_name = "MIB")]
+        max_size: Option<u64>,
+    },
 }
 
 #[derive(Debug)]
@@ -93,18 +482,92 @@ impl std::fmt::Display for BlueprintInputError {
 }
 
 impl Input {
-    fn get_bp_string(self) -> Result<String, BlueprintInputError> {
-        match self {
-            Self::String { string } => Ok(string),
-            Self::File { file } => fs::read_to_string(file).change_context(BlueprintInputError),
-        }
+    // only fetching urls awaits anything
+    #[cfg_attr(not(feature = "url"), allow(clippy::unused_async))]
+    async fn get_bp(&self) -> Result<blueprint::Data, BlueprintInputError> {
+        let bp_string = match self {
+            Self::Render(source) => return source.get_bp(),
+            Self::String { string } => string.clone(),
+            Self::File { file } => fs::read_to_string(file).change_context(BlueprintInputError)?,
+            Self::Stdin => io::read_to_string(io::stdin()).change_context(BlueprintInputError)?,
+            Self::Json { file } => {
+                let json = read_file_or_stdin(file)
+                    .change_context(BlueprintInputError)
+                    .attach_printable_lazy(|| format!("failed to read {}", file.display()))?;
+
+                return blueprint::Data::from_json(&json).change_context(BlueprintInputError);
+            }
+            #[cfg(feature = "url")]
+            Self::Url { url } => factorio_api::blueprint_sites::fetch_blueprint(url)
+                .await
+                .change_context(BlueprintInputError)?,
+        };
+
+        blueprint::Data::from_text(&bp_string).change_context(BlueprintInputError)
+    }
+}
+
+impl BlueprintSource {
+    fn get_bp(&self) -> Result<blueprint::Data, BlueprintInputError> {
+        detect_input(&self.blueprint)
+    }
+
+    /// Text of the file or stdin if the argument is one, otherwise the argument itself.
+    fn read(&self) -> Result<String, BlueprintInputError> {
+        Ok(read_input(&self.blueprint)?.unwrap_or_else(|| self.blueprint.clone()))
+    }
+}
+
+fn background(args: &CommandArgs) -> Background {
+    if args.transparent_background {
+        return Background::Transparent;
+    }
+
+    match args.background.unwrap_or_default() {
+        Background::Grid { .. } => Background::Grid {
+            spacing: args.grid_spacing.max(1),
+            chunk_lines: !args.no_chunk_lines,
+        },
+        background => background,
+    }
+}
+
+/// Parses a `name=value` startup setting. Values are interpreted as bool or number if possible.
+fn parse_setting(arg: &str) -> std::result::Result<(String, mod_util::AnyBasic), String> {
+    let Some((name, value)) = arg.split_once('=').filter(|(name, _)| !name.is_empty()) else {
+        return Err(format!("invalid setting '{arg}', expected NAME=VALUE"));
+    };
+
+    let value = value
+        .parse()
+        .map(mod_util::AnyBasic::Bool)
+        .or_else(|_| value.parse().map(mod_util::AnyBasic::Number))
+        .unwrap_or_else(|_| mod_util::AnyBasic::String(value.to_owned()));
+
+    Ok((name.to_owned(), value))
+}
+
+/// Sets up the logger, filtered by `RUST_LOG`.
+/// Events from `tracing` are forwarded to it as well.
+fn init_logging(format: LogFormat) {
+    // without RUST_LOG only errors are shown, like env_logger did before
+    let filter = tracing_subscriber::EnvFilter::builder()
+        .with_default_directive(tracing::Level::ERROR.into())
+        .from_env_lossy();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr);
+
+    match format {
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder.json().flatten_event(true).init(),
     }
 }
 
 fn main() -> ExitCode {
     dotenv::dotenv().ok();
     let cli = Cli::parse();
-    pretty_env_logger::init();
+    init_logging(cli.log_format);
 
     info!(
         "starting {} v{} with prototypes v{} & types v{}",
@@ -114,43 +577,556 @@ fn main() -> ExitCode {
         types::targeted_engine_version()
     );
 
-    let (factorio_appdir, factorio_userdir, factorio_bin) = match infer_paths(&cli) {
-        Ok(tup) => tup,
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
         Err(err) => {
-            error!("{err}");
-            return ExitCode::FAILURE;
+            error!("{err:#?}");
+            ExitCode::FAILURE
         }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), ScannerError> {
+    let args = &cli.args;
+    let out = args.out.as_deref();
+
+    let mod_cache = mod_cache::ModCache::new(
+        args.mod_cache
+            .clone()
+            .map_or_else(mod_cache::ModCache::default_dir, Ok)
+            .change_context(ScannerError::SetupError)?,
+    );
+
+    // commands that don't need a Factorio installation
+    match &args.command {
+        Command::Cache(CacheCommand::Mods(ModCacheCommand::Prune { max_age_days })) => {
+            return mod_cache
+                .prune(Duration::from_secs(
+                    max_age_days.saturating_mul(24 * 60 * 60),
+                ))
+                .map(|_| ())
+                .change_context(ScannerError::SetupError);
+        }
+        Command::Scrub {
+            source,
+            station_names,
+            players,
+        } => {
+            let options = blueprint::ScrubOptions {
+                station_names: *station_names,
+                player_names: players.clone(),
+            };
+
+            return scrub_command(&read_source(source)?, &options, out);
+        }
+        Command::Reencode(source) => return reencode_command(&read_source(source)?, out),
+        Command::Decode { source, pretty } => {
+            return decode_command(&read_source(source)?, *pretty, out);
+        }
+        Command::Encode { file, minify } => return encode_command(file, *minify, out),
+        _ => {}
+    }
+
+    if out.is_none()
+        && matches!(
+            args.command,
+            Command::Render(_)
+                | Command::Thumbnail(_)
+                | Command::BookIndex(_)
+                | Command::Icons { .. }
+        )
+    {
+        return Err(report!(ScannerError::SetupError))
+            .attach_printable("--out is required to render a blueprint or export icons");
+    }
+
+    let (factorio, factorio_userdir, factorio_bin) =
+        infer_paths(cli).map_err(|err| report!(ScannerError::SetupError).attach_printable(err))?;
+
+    let dump_cache = dump_cache::DumpCache::in_userdir(&factorio_userdir).with_max_size(
+        args.dump_cache_size
+            .map(|mib| mib.saturating_mul(1024 * 1024)),
+    );
+
+    if let Command::Cache(CacheCommand::Dumps(command)) = &args.command {
+        return dump_cache_command(command, &dump_cache);
+    }
+
+    let setup = Setup {
+        args,
+        presets: load_presets(args)?,
+        locked: args
+            .locked
+            .as_deref()
+            .map(lockfile::Lockfile::load)
+            .transpose()
+            .change_context(ScannerError::SetupError)?,
+        factorio,
+        factorio_userdir,
+        factorio_bin,
+        mod_cache,
+        dump_cache,
     };
 
-    let rt = match tokio::runtime::Builder::new_current_thread()
+    let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
-        .change_context(ScannerError::ServerError)
-    {
-        Ok(rt) => rt,
+        .change_context(ScannerError::ServerError)?;
+
+    rt.block_on(async {
+        match &args.command {
+            Command::Render(input) => render_command(&setup, input, out).await,
+            Command::Thumbnail(source) => thumbnail_command(&setup, source, out).await,
+            Command::BookIndex(source) => book_index_command(&setup, source, out).await,
+            Command::Bom { source, json } => bom_command(&setup, source, *json, out).await,
+            Command::Validate { source, json } => {
+                validate_command(&setup, source, *json, out).await
+            }
+            Command::Tile {
+                source,
+                nx,
+                ny,
+                spacing,
+            } => tile_command(&setup, source, (*nx, *ny), *spacing, out).await,
+            Command::Icons { scale } => icons_command(&setup, *scale, out).await,
+            Command::TechTree { format } => tech_tree_command(&setup, *format, out).await,
+            Command::Settings => settings_command(&setup, out).await,
+            // handled above, they don't load any prototypes
+            Command::Scrub { .. }
+            | Command::Reencode(_)
+            | Command::Decode { .. }
+            | Command::Encode { .. }
+            | Command::Cache(_) => Ok(()),
+        }
+    })
+}
+
+fn read_source(source: &BlueprintSource) -> Result<String, ScannerError> {
+    source.read().change_context(ScannerError::NoBlueprint)
+}
+
+/// Paths, caches & presets of the commands that load prototypes.
+struct Setup<'a> {
+    args: &'a CommandArgs,
+    presets: preset::Presets,
+    locked: Option<lockfile::Lockfile>,
+    factorio: PathBuf,
+    factorio_userdir: PathBuf,
+    factorio_bin: PathBuf,
+    mod_cache: mod_cache::ModCache,
+    dump_cache: dump_cache::DumpCache,
+}
+
+impl Setup<'_> {
+    fn preset(&self) -> Option<&preset::Preset> {
+        self.args
+            .preset
+            .as_deref()
+            .and_then(|name| self.presets.get(name))
+    }
+
+    /// Loads the prototypes of the preset & mods, with a blueprint its mods & settings are used as well.
+    async fn load_data(
+        &self,
+        bp: Option<&blueprint::Data>,
+        extra_mods: &[String],
+    ) -> Result<(DataUtil, mod_util::UsedMods), ScannerError> {
+        let mods = self
+            .args
+            .mods
+            .iter()
+            .chain(extra_mods)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        load_data(
+            bp,
+            &self.factorio,
+            &self.factorio_userdir,
+            &self.factorio_bin,
+            self.args.dump_method,
+            &self.presets,
+            self.preset(),
+            self.locked.as_ref(),
+            &self.args.dlc,
+            &mods,
+            &self.args.mod_dirs,
+            &self.args.settings,
+            self.args.prototype_dump.clone(),
+            self.args.lenient_dump,
+            self.args.filter_prototypes,
+            &self.mod_cache,
+            &self.dump_cache,
+        )
+        .await
+    }
+
+    /// Resolves & downloads the mods of the preset, `--mods` & `--mod-dir` without loading any prototypes.
+    async fn load_mods(&self) -> Result<mod_util::mod_list::ModList, ScannerError> {
+        load_mods(
+            None,
+            &self.factorio,
+            &self.factorio_userdir,
+            &self.presets,
+            self.preset(),
+            self.locked.as_ref(),
+            &self.args.dlc,
+            &self.args.mods,
+            &self.args.mod_dirs,
+            false,
+            &self.mod_cache,
+        )
+        .await
+    }
+
+    /// Loads the prototypes for the blueprint, including detected mods for its unknown prototypes.
+    /// Also writes the lockfile next to the rendered image `out` and selects the utility sprites.
+    /// Returns the setup time in milliseconds as well.
+    async fn load_blueprint_data(
+        &self,
+        bp: &blueprint::Data,
+        out: Option<&Path>,
+    ) -> Result<(DataUtil, mod_util::UsedMods, f64), ScannerError> {
+        let start = Instant::now();
+        let (mut data, mut active_mods) = self.load_data(Some(bp), &[]).await?;
+
+        if self.preset().is_none() && self.locked.is_none() && self.args.prototype_dump.is_none() {
+            if let Some(detected) = detect_mods(bp, &data, self.args.auto_detect_mods).await {
+                (data, active_mods) = self.load_data(Some(bp), &detected).await?;
+            }
+        }
+
+        let setup_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let startup_settings =
+            startup_settings(bp.as_blueprint(), self.preset(), &self.args.settings);
+        if let Some(locked) = &self.locked {
+            if !locked.settings_match(&startup_settings) {
+                warn!("the startup settings differ from the ones of the lockfile");
+            }
+        } else if let Some(out) = out {
+            let path = lockfile::Lockfile::path_for(out);
+            lockfile::Lockfile::new(&active_mods, &startup_settings)
+                .save(&path)
+                .change_context(ScannerError::SetupError)?;
+            debug!("saved lockfile to {path:?}");
+        }
+
+        let utility_sprites = &self.args.utility_sprites;
+        if !data.select_util_sprites(utility_sprites) {
+            return Err(report!(ScannerError::SetupError)).attach_printable(format!(
+                "unknown utility sprites {utility_sprites:?}, available: {}",
+                data.util_sprite_sets().join(", ")
+            ));
+        }
+
+        Ok((data, active_mods, setup_ms))
+    }
+}
+
+/// Proposes mods for the unknown prototypes of the blueprint, returns them if they should be enabled.
+async fn detect_mods(bp: &blueprint::Data, data: &DataUtil, enable: bool) -> Option<Vec<String>> {
+    let unknown = mod_detect::unknown_names(bp.as_blueprint()?, data);
+    if unknown.is_empty() {
+        return None;
+    }
+
+    info!(
+        "searching the mod portal for {} unknown prototypes",
+        unknown.len()
+    );
+    let detection = mod_detect::detect(&unknown).await;
+    if detection.mods.is_empty() {
+        warn!("no mods found for the unknown prototypes");
+        return None;
+    }
+
+    if !enable {
+        warn!("the blueprint probably needs these mods, enable them with --auto-detect-mods or --mods:\n{detection}");
+        return None;
+    }
+
+    info!("enabling detected mods:\n{detection}");
+    Some(detection.mod_names())
+}
+
+/// The bundled presets & the ones of the preset files, `--preset` has to be one of them.
+fn load_presets(args: &CommandArgs) -> Result<preset::Presets, ScannerError> {
+    let mut presets = preset::Presets::bundled();
+    for path in &args.preset_files {
+        presets
+            .load_file(path)
+            .change_context(ScannerError::SetupError)?;
+    }
+
+    if let Some(name) = &args.preset {
+        if presets.get(name).is_none() {
+            return Err(report!(ScannerError::SetupError)).attach_printable(format!(
+                "unknown preset {name:?}, available: {}",
+                presets.names().join(", ")
+            ));
+        }
+    }
+
+    Ok(presets)
+}
+
+/// Prints the scrubbed blueprint string or writes it to `out`.
+fn scrub_command(
+    bp_string: &str,
+    options: &blueprint::ScrubOptions,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let mut data =
+        blueprint::Data::from_text(bp_string).change_context(ScannerError::NoBlueprint)?;
+    blueprint::scrub(&mut data, options);
+
+    let scrubbed = String::try_from(data).change_context(ScannerError::NoBlueprint)?;
+    output_text(&scrubbed, out)
+}
+
+/// Prints the canonical form of the blueprint string or writes it to `out`.
+/// The sizes before & after are reported on stderr.
+fn reencode_command(bp_string: &str, out: Option<&Path>) -> Result<(), ScannerError> {
+    let (data, bp_string) = match blueprint::Data::try_from(bp_string.trim()) {
+        Ok(data) => (data, bp_string.trim()),
         Err(err) => {
-            error!("{err:#?}");
-            return ExitCode::FAILURE;
+            let extracted = blueprint::extract(bp_string)
+                .ok_or(err)
+                .change_context(ScannerError::NoBlueprint)?;
+            (extracted.data, &bp_string[extracted.span])
         }
     };
+    let reencoded = String::try_from(data).change_context(ScannerError::NoBlueprint)?;
+
+    let (before, after) = (bp_string.len(), reencoded.len());
+    info!(
+        "reencoded {before} -> {after} bytes ({:+.1}%)",
+        (after as f64 / before as f64 - 1.0) * 100.0
+    );
+
+    output_text(&reencoded, out)
+}
+
+/// Prints the JSON of the blueprint string without upgrading or normalizing it.
+fn decode_command(bp_string: &str, pretty: bool, out: Option<&Path>) -> Result<(), ScannerError> {
+    let json =
+        blueprint::bp_string_to_json(bp_string.trim()).change_context(ScannerError::NoBlueprint)?;
 
-    if let Err(err) = rt.block_on(render_command(
-        cli.args.input,
-        &factorio_appdir,
-        &factorio_userdir,
-        &factorio_bin,
-        cli.args.preset,
-        &cli.args.mods,
-        cli.args.prototype_dump,
-        cli.args.target_res,
-        cli.args.min_scale,
-        &cli.args.out,
-    )) {
-        error!("{err:#?}");
-        return ExitCode::FAILURE;
+    if pretty {
+        output_text(&transcode_json(&json, true)?, out)
+    } else {
+        output_text(&json, out)
+    }
+}
+
+/// Encodes the JSON as is, it is only checked to be valid JSON.
+fn encode_command(file: &Path, minify: bool, out: Option<&Path>) -> Result<(), ScannerError> {
+    let json = read_file_or_stdin(file)
+        .change_context(ScannerError::NoBlueprint)
+        .attach_printable_lazy(|| format!("failed to read {}", file.display()))?;
+
+    let json = if minify {
+        transcode_json(&json, false)?
+    } else {
+        serde_json::from_str::<serde::de::IgnoredAny>(&json)
+            .change_context(ScannerError::NoBlueprint)?;
+        json
     };
 
-    ExitCode::SUCCESS
+    let bp_string =
+        blueprint::json_to_bp_string(&json).change_context(ScannerError::NoBlueprint)?;
+    output_text(&bp_string, out)
+}
+
+/// Decodes the blueprint in `input`, it is read from stdin or the file if it is '-' or an existing file,
+/// otherwise it has to be a blueprint string.
+fn detect_input(input: &str) -> Result<blueprint::Data, BlueprintInputError> {
+    if let Some(text) = read_input(input)? {
+        return blueprint::Data::from_text(&text).change_context(BlueprintInputError);
+    }
+
+    let string = input.trim();
+    if !string.starts_with('0') {
+        return Err(report!(BlueprintInputError)).attach_printable(format!(
+            "{input:?} is neither an existing file nor a blueprint string (those start with '0'), \
+            use the `file` or `string` command to force one of them"
+        ));
+    }
+
+    blueprint::Data::try_from(string)
+        .change_context(BlueprintInputError)
+        .attach_printable("the argument looks like a blueprint string but could not be decoded")
+}
+
+/// Reads the file, or stdin if `input` is '-'. `None` if `input` is no existing file.
+fn read_input(input: &str) -> Result<Option<String>, BlueprintInputError> {
+    let path = Path::new(input);
+    if input != "-" && !path.is_file() {
+        return Ok(None);
+    }
+
+    read_file_or_stdin(path)
+        .map(Some)
+        .change_context(BlueprintInputError)
+        .attach_printable_lazy(|| format!("failed to read {}", path.display()))
+}
+
+/// Reads the whole file, or stdin if `file` is '-'.
+fn read_file_or_stdin(file: &Path) -> io::Result<String> {
+    if file == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(file)
+    }
+}
+
+/// Re-formats the JSON while keeping the order of all keys.
+fn transcode_json(json: &str, pretty: bool) -> Result<String, ScannerError> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let mut res = Vec::new();
+
+    if pretty {
+        serde_transcode::transcode(
+            &mut deserializer,
+            &mut serde_json::Serializer::pretty(&mut res),
+        )
+    } else {
+        serde_transcode::transcode(
+            &mut deserializer,
+            &mut serde_json::Serializer::new(&mut res),
+        )
+    }
+    .change_context(ScannerError::NoBlueprint)
+    .attach_printable("invalid JSON")?;
+
+    String::from_utf8(res).change_context(ScannerError::NoBlueprint)
+}
+
+/// Loads the data of the preset & mods without a blueprint and exports all icons into `out`.
+async fn icons_command(
+    setup: &Setup<'_>,
+    scale: f64,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let out = out.ok_or(ScannerError::SetupError)?;
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(report!(ScannerError::SetupError))
+            .attach_printable(format!("invalid icon scale {scale}"));
+    }
+
+    let (data, active_mods) = setup.load_data(None, &[]).await?;
+    let index = icons::export(&data, &active_mods, scale, out)?;
+
+    let missing = index.missing.values().flatten().count();
+    if missing > 0 {
+        warn!(
+            "{missing} prototypes have no icon, see {}",
+            icons::INDEX_FILE
+        );
+    }
+
+    info!("exported {} icons to {out:?}", index.len());
+    Ok(())
+}
+
+/// Loads the data of the preset & mods without a blueprint and exports the research tree.
+async fn tech_tree_command(
+    setup: &Setup<'_>,
+    format: tech_tree::Format,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let (data, _) = setup.load_data(None, &[]).await?;
+
+    let tree = tech_tree::TechTree::new(&data);
+    info!("exporting {} technologies", tree.technologies.len());
+
+    let text = match format {
+        tech_tree::Format::Json => {
+            serde_json::to_string_pretty(&tree).change_context(ScannerError::RenderError)?
+        }
+        tech_tree::Format::Dot => tree.to_dot(),
+    };
+
+    output_text(text.trim_end(), out)
+}
+
+/// Loads the data of the preset & mods without a blueprint and exports their startup settings.
+async fn settings_command(setup: &Setup<'_>, out: Option<&Path>) -> Result<(), ScannerError> {
+    // a given dump already contains the settings, otherwise only the settings stage is run
+    let data;
+    let staged;
+    let all = if setup.args.prototype_dump.is_some() {
+        data = setup.load_data(None, &[]).await?.0;
+        data.mod_settings()
+    } else {
+        staged = mod_settings::settings_stage(&setup.load_mods().await?)?;
+        &staged
+    };
+
+    let settings = mod_settings::StartupSettings::new(all);
+    info!("exporting {} startup settings", settings.settings.len());
+
+    let text = serde_json::to_string_pretty(&settings).change_context(ScannerError::RenderError)?;
+    output_text(&text, out)
+}
+
+/// Lists or clears the cached prototype dumps.
+fn dump_cache_command(
+    command: &DumpCacheCommand,
+    dump_cache: &dump_cache::DumpCache,
+) -> Result<(), ScannerError> {
+    match command {
+        DumpCacheCommand::Ls => {
+            let entries = dump_cache
+                .entries()
+                .change_context(ScannerError::SetupError)?;
+            let now = std::time::SystemTime::now();
+
+            for entry in &entries {
+                let days = now
+                    .duration_since(entry.last_used)
+                    .unwrap_or_default()
+                    .as_secs()
+                    / (24 * 60 * 60);
+
+                println!(
+                    "{:<33}  {:>8.1} MiB  used {days} days ago",
+                    entry.key.to_string(),
+                    entry.size as f64 / 1024.0 / 1024.0
+                );
+            }
+
+            eprintln!(
+                "{} cached prototype dumps ({:.1} MiB) in {}",
+                entries.len(),
+                entries.iter().map(|entry| entry.size).sum::<u64>() as f64 / 1024.0 / 1024.0,
+                dump_cache.root().display()
+            );
+        }
+        DumpCacheCommand::Clear { max_size } => {
+            max_size
+                .map_or_else(
+                    || dump_cache.clear(),
+                    |max_size| dump_cache.evict(max_size.saturating_mul(1024 * 1024)),
+                )
+                .change_context(ScannerError::SetupError)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn output_text(text: &str, out: Option<&Path>) -> Result<(), ScannerError> {
+    if let Some(out) = out {
+        fs::write(out, text)
+            .change_context(ScannerError::SetupError)
+            .attach_printable_lazy(|| format!("failed to write {}", out.display()))?;
+    } else {
+        println!("{text}");
+    }
+
+    Ok(())
 }
 
 fn get_home(argument: &str) -> std::result::Result<PathBuf, String> {
@@ -160,7 +1136,9 @@ fn get_home(argument: &str) -> std::result::Result<PathBuf, String> {
     }
 }
 
+/// The paths are not checked if a prototype dump is given since no game installation is needed to render it.
 fn infer_paths(cli: &Cli) -> std::result::Result<(PathBuf, PathBuf, PathBuf), String> {
+    let dump_only = cli.args.prototype_dump.is_some();
     let factorio_appdir = cli.factorio.clone().map_or_else(
         || match env::consts::OS {
             "linux" => Ok(Path::new(&get_home("--factorio")?).join(".factorio")),
@@ -170,10 +1148,11 @@ fn infer_paths(cli: &Cli) -> std::result::Result<(PathBuf, PathBuf, PathBuf), St
         Ok,
     )?;
 
-    if !factorio_appdir.join("data").is_dir() {
+    if !dump_only && !factorio_appdir.join("data").is_dir() {
         return Err(format!(
-            "Factorio app directory at {factorio_appdir:?} doesn't exist \
-            or doesn't contain 'data', check --factorio"
+            "Factorio app directory at {} doesn't exist \
+            or doesn't contain 'data', check --factorio",
+            factorio_appdir.display()
         ));
     }
 
@@ -186,10 +1165,11 @@ fn infer_paths(cli: &Cli) -> std::result::Result<(PathBuf, PathBuf, PathBuf), St
         Ok::<PathBuf, String>,
     )?;
 
-    if !factorio_userdir.join("mods").is_dir() {
+    if !dump_only && !factorio_userdir.join("mods").is_dir() {
         return Err(format!(
-            "Factorio user data directory at {factorio_userdir:?} doesn't exist \
-            or doesn't contain 'mods', check --factorio-userdir"
+            "Factorio user data directory at {} doesn't exist \
+            or doesn't contain 'mods', check --factorio-userdir",
+            factorio_userdir.display()
         ));
     }
 
@@ -201,52 +1181,399 @@ fn infer_paths(cli: &Cli) -> std::result::Result<(PathBuf, PathBuf, PathBuf), St
             default => factorio_appdir.join("bin/x64/factorio"),
         });
 
-    if !factorio_bin.exists() {
+    if !dump_only && cli.args.dump_method == DumpMethod::Factorio && !factorio_bin.exists() {
         return Err(format!(
-            "Factorio binary not found at {factorio_bin:?}, check --factorio-bin"
+            "Factorio binary not found at {}, check --factorio-bin",
+            factorio_bin.display()
         ));
     }
 
     Ok((factorio_appdir, factorio_userdir, factorio_bin))
 }
 
-#[allow(clippy::too_many_arguments)]
+fn source_bp(source: &BlueprintSource) -> Result<blueprint::Data, ScannerError> {
+    source.get_bp().change_context(ScannerError::NoBlueprint)
+}
+
+/// Prints the items needed to build the blueprint or writes them to `out`.
+async fn bom_command(
+    setup: &Setup<'_>,
+    source: &BlueprintSource,
+    json: bool,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let bp = source_bp(source)?;
+    let (data, _, _) = setup.load_blueprint_data(&bp, None).await?;
+
+    let bp = bp.as_blueprint().ok_or(ScannerError::NoBlueprint)?;
+    let bom = bom::BillOfMaterials::new(bp, &data);
+
+    if !bom.unplaceable.is_empty() {
+        warn!("not buildable by any item: {:?}", bom.unplaceable);
+    }
+
+    let bom = if json {
+        serde_json::to_string_pretty(&bom).change_context(ScannerError::RenderError)?
+    } else {
+        bom.to_string()
+    };
+
+    output_text(bom.trim_end(), out)
+}
+
+/// Prints the overlapping entities & wires out of reach, fails if there are any.
+async fn validate_command(
+    setup: &Setup<'_>,
+    source: &BlueprintSource,
+    json: bool,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let bp = source_bp(source)?;
+    let (data, _, _) = setup.load_blueprint_data(&bp, None).await?;
+
+    let bp = bp.as_blueprint().ok_or(ScannerError::NoBlueprint)?;
+    let overlapping = validate::overlaps(bp, &data);
+    let unreachable = validate::unreachable_wires(bp, &data);
+
+    if json {
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "overlaps": overlapping,
+            "wires": unreachable,
+        }))
+        .change_context(ScannerError::RenderError)?;
+        output_text(&json, out)?;
+    } else if !overlapping.is_empty() || !unreachable.is_empty() {
+        let lines = overlapping
+            .iter()
+            .map(ToString::to_string)
+            .chain(unreachable.iter().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        output_text(&lines.join("\n"), out)?;
+    }
+
+    if !overlapping.is_empty() || !unreachable.is_empty() {
+        return Err(report!(ScannerError::InvalidBlueprint)).attach_printable(format!(
+            "{} overlapping entity pairs, {} wires out of reach",
+            overlapping.len(),
+            unreachable.len()
+        ));
+    }
+
+    info!("no overlapping entities or wires out of reach found");
+    Ok(())
+}
+
+/// Prints the blueprint string of `nx` x `ny` copies of the blueprint or writes it to `out`.
+async fn tile_command(
+    setup: &Setup<'_>,
+    source: &BlueprintSource,
+    (nx, ny): (u32, u32),
+    spacing: u32,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let bp = source_bp(source)?;
+    let (data, _, _) = setup.load_blueprint_data(&bp, None).await?;
+
+    let bp = bp.as_blueprint().ok_or(ScannerError::NoBlueprint)?;
+    let step = bp_helper::tiling_step(bp, &data, spacing)
+        .ok_or(ScannerError::NoBlueprint)
+        .attach_printable("the blueprint is empty")?;
+    info!("tiling {nx}x{ny} copies every {}x{} tiles", step.x, step.y);
+
+    let tiled = blueprint::repeat(bp, nx.max(1), ny.max(1), &step);
+    let string = String::try_from(blueprint::Data::Blueprint(tiled))
+        .change_context(ScannerError::NoBlueprint)?;
+    output_text(&string, out)
+}
+
+async fn thumbnail_command(
+    setup: &Setup<'_>,
+    source: &BlueprintSource,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let out = out.ok_or(ScannerError::SetupError)?;
+    let bp = source_bp(source)?;
+    let (data, active_mods, _) = setup.load_blueprint_data(&bp, Some(out)).await?;
+
+    let thumb = thumbnail(&bp, &data, &active_mods)?;
+    fs::write(out, thumb).change_context(ScannerError::RenderError)?;
+    info!("saved thumbnail to {out:?}");
+
+    Ok(())
+}
+
+async fn book_index_command(
+    setup: &Setup<'_>,
+    source: &BlueprintSource,
+    out: Option<&Path>,
+) -> Result<(), ScannerError> {
+    let out = out.ok_or(ScannerError::SetupError)?;
+    let bp = source_bp(source)?;
+    let (data, active_mods, _) = setup.load_blueprint_data(&bp, Some(out)).await?;
+
+    let index = book_index::book_index_png(&bp, &data, &active_mods)?;
+    fs::write(out, index).change_context(ScannerError::RenderError)?;
+    info!("saved book index to {out:?}");
+
+    Ok(())
+}
+
+/// Render options of the command line arguments.
+fn render_options(args: &CommandArgs) -> RenderOptions {
+    let options = RenderOptions {
+        alt_mode: !args.no_alt_mode,
+        wires: !args.no_wires,
+        shadows: !args.no_shadows,
+        background: background(args),
+        annotations: args.annotate,
+        bill_of_materials: args.bom,
+        max_wires: Some(args.max_wires).filter(|&max| max > 0),
+        fast_wires: args.fast_wires,
+        max_pixels: Some(args.max_pixels).filter(|&max| max > 0),
+        wire_palette: args.wire_colors,
+        dashed_wires: args.dashed_wires,
+        night: args.night,
+        icon_style: args.icon_style.unwrap_or_default(),
+        seed: args.seed,
+        strict: args.strict,
+        region: args.region,
+        split_layers: args.split_layers,
+        ..Default::default()
+    };
+
+    if args.fast {
+        options.fast()
+    } else {
+        options
+    }
+}
+
+/// Renders the blueprint into `out`, or every blueprint of a book with `--all`.
+#[allow(clippy::too_many_lines)]
 async fn render_command(
-    input: Input,
-    factorio: &Path,
-    factorio_userdir: &Path,
-    factorio_bin: &Path,
-    preset: Option<preset::Preset>,
-    mods: &[String],
-    prototype_dump: Option<PathBuf>,
-    target_res: f64,
-    min_scale: f64,
-    out: &Path,
+    setup: &Setup<'_>,
+    input: &Input,
+    out: Option<&Path>,
 ) -> Result<(), ScannerError> {
-    let bp_string = input
-        .get_bp_string()
+    let out = out.ok_or(ScannerError::SetupError)?;
+    let bp = input
+        .get_bp()
+        .await
         .change_context(ScannerError::NoBlueprint)?;
+    let (data, active_mods, setup_ms) = setup.load_blueprint_data(&bp, Some(out)).await?;
+
+    let args = setup.args;
+    let options = &render_options(args);
+    let (target_res, min_scale, overlays) = (args.target_res, args.min_scale, &args.overlays);
+    let region = options.region;
+    let (write_report, emit_hitboxes, profile) = (args.report, args.emit_hitboxes, args.profile);
+    let surface_backgrounds = args.background.is_none() && !args.transparent_background;
+    let heatmap = (args.mode == RenderMode::Heatmap).then_some(args.heatmap_by);
+    let animation = args.frames.map(|frames| AnimationOptions {
+        frames,
+        frame_delay: args.frame_delay,
+    });
+
+    if matches!(bp, blueprint::Data::DeconstructionPlanner(_)) {
+        let card = card::decon_planner_card_png(&bp, &data, &active_mods)?;
+        fs::write(out, card).change_context(ScannerError::RenderError)?;
+        info!("saved deconstruction planner card to {out:?}");
+
+        return Ok(());
+    }
+
+    if args.all {
+        let book = bp
+            .as_book()
+            .ok_or(ScannerError::NoBlueprint)
+            .attach_printable("--all needs a blueprint book")?;
+
+        for entry in book_index::entries(book) {
+            let Some(entry_bp) = entry.data.as_blueprint() else {
+                continue;
+            };
+
+            let mut options = *options;
+            if surface_backgrounds {
+                let hint = entry_bp.surface_hint();
+                debug!("entry {} looks like a {hint:?} blueprint", entry.path);
+                options.background = surface_background(hint);
+            }
+
+            let entry_out = out.with_extension(format!("{}.png", entry.path));
+            if let Some(coloring) = heatmap {
+                match render_heatmap(
+                    entry.data,
+                    &data,
+                    target_res,
+                    coloring,
+                    region.as_ref(),
+                    options.background,
+                ) {
+                    Ok(img) => {
+                        fs::write(&entry_out, img).change_context(ScannerError::RenderError)?;
+                        info!("saved heatmap to {entry_out:?}");
+                    }
+                    Err(err) => warn!("skipping entry {}: {err}", entry.path),
+                }
+                continue;
+            }
+
+            let mut output = match render(
+                entry.data,
+                &data,
+                &active_mods,
+                target_res,
+                min_scale,
+                overlays,
+                &options,
+            ) {
+                Ok(output) => output,
+                Err(err) => {
+                    warn!("skipping entry {}: {err}", entry.path);
+                    continue;
+                }
+            };
+
+            output.report.timings.setup = setup_ms;
+            save_render(output, &entry_out, write_report, emit_hitboxes, profile)?;
+        }
 
-    let bp = blueprint::Data::try_from(bp_string).change_context(ScannerError::NoBlueprint)?;
-    let (data, active_mods) = load_data(
+        return Ok(());
+    }
+
+    if let Some(coloring) = heatmap {
+        let img = render_heatmap(
+            &bp,
+            &data,
+            target_res,
+            coloring,
+            region.as_ref(),
+            options.background,
+        )?;
+        fs::write(out, img).change_context(ScannerError::RenderError)?;
+        info!("saved heatmap to {out:?}");
+
+        return Ok(());
+    }
+
+    if let Some(bp) = bp.as_blueprint() {
+        let overlapping = validate::overlaps(bp, &data).len();
+        if overlapping > 0 {
+            warn!("{overlapping} pairs of entities overlap, see `scanner validate` or `--overlay overlaps`");
+        }
+
+        let unreachable = validate::unreachable_wires(bp, &data).len();
+        if unreachable > 0 {
+            warn!("{unreachable} wires are out of reach and would be dropped in game, see `scanner validate` or `--overlay wire-reach`");
+        }
+    }
+
+    if let Some(animation) = animation {
+        if !out
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+        {
+            warn!("animations are always saved as GIF, consider using a .gif extension");
+        }
+
+        let gif = render_animation(
+            &bp,
+            &data,
+            &active_mods,
+            target_res,
+            min_scale,
+            overlays,
+            options,
+            animation,
+        )?;
+        fs::write(out, gif).change_context(ScannerError::RenderError)?;
+        info!("saved animation to {out:?}");
+
+        return Ok(());
+    }
+
+    let mut output = render(
         &bp,
-        factorio,
-        factorio_userdir,
-        factorio_bin,
-        preset,
-        mods,
-        prototype_dump,
-    )
-    .await?;
-    let (res, missing, thumb) = render(&bp, &data, &active_mods, target_res, min_scale)?;
+        &data,
+        &active_mods,
+        target_res,
+        min_scale,
+        overlays,
+        options,
+    )?;
+
+    output.report.timings.setup = setup_ms;
+    save_render(output, out, write_report, emit_hitboxes, profile)
+}
+
+/// Writes the render & its optional extra files next to `out`, missing prototypes & failed entities are logged
+/// and the timings are printed if profiling is requested.
+fn save_render(
+    output: RenderOutput,
+    out: &Path,
+    write_report: bool,
+    emit_hitboxes: bool,
+    profile: Option<ProfileFormat>,
+) -> Result<(), ScannerError> {
+    let RenderOutput {
+        image,
+        report,
+        thumbnail: thumb,
+        layers,
+    } = output;
 
-    if !missing.is_empty() {
-        warn!("missing prototypes: {missing:?}");
+    if !report.unknown.is_empty() {
+        warn!("missing prototypes: {:?}", report.unknown);
     }
 
-    fs::write(out, res).change_context(ScannerError::RenderError)?;
+    let failed = report.diagnostics.failed_entities();
+    if !failed.is_empty() {
+        warn!("{} entities failed to render", failed.len());
+    }
+    for diagnostic in &report.diagnostics {
+        debug!("{diagnostic}");
+    }
+
+    fs::write(out, image).change_context(ScannerError::RenderError)?;
     info!("saved render to {out:?}");
 
+    for (layer, img) in layers {
+        let layer_path = out.with_extension(format!("{}.png", layer.name()));
+        fs::write(&layer_path, img).change_context(ScannerError::RenderError)?;
+        info!("saved {layer:?} layer to {layer_path:?}");
+    }
+
+    if write_report {
+        let report_path = out.with_extension("json");
+        fs::write(
+            &report_path,
+            serde_json::to_vec_pretty(&report).change_context(ScannerError::RenderError)?,
+        )
+        .change_context(ScannerError::RenderError)?;
+        info!("saved render report to {report_path:?}");
+    }
+
+    if emit_hitboxes {
+        let hitbox_path = out.with_extension("hitboxes.json");
+        fs::write(
+            &hitbox_path,
+            serde_json::to_vec(&report.hitboxes()).change_context(ScannerError::RenderError)?,
+        )
+        .change_context(ScannerError::RenderError)?;
+        info!("saved hitboxes to {hitbox_path:?}");
+    }
+
+    match profile {
+        Some(ProfileFormat::Table) => print!("{}", report.timings),
+        Some(ProfileFormat::Json) => println!(
+            "{}",
+            serde_json::to_string_pretty(&report.timings)
+                .change_context(ScannerError::RenderError)?
+        ),
+        None => {}
+    }
+
     if let Some(thumb) = thumb {
         fs::write(out.with_extension("thumb.png"), thumb)
             .change_context(ScannerError::RenderError)?;
@@ -255,3 +1582,38 @@ async fn render_command(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const BP_STRING: &str =
+        include_str!("../../blueprint/tests/train_schedule_temporary_record.txt");
+
+    #[test]
+    fn detect_input_reads_files() {
+        let path = env::temp_dir().join(format!("scanner_detect_input_{}.txt", std::process::id()));
+        fs::write(&path, format!("{BP_STRING}\n")).unwrap();
+
+        let res = detect_input(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert!(res.unwrap().as_blueprint().is_some());
+    }
+
+    #[test]
+    fn detect_input_decodes_strings() {
+        assert!(detect_input(BP_STRING).unwrap().as_blueprint().is_some());
+
+        let err = format!("{:?}", detect_input("0eNotABlueprint").unwrap_err());
+        assert!(err.contains("could not be decoded"));
+    }
+
+    #[test]
+    fn detect_input_rejects_other_arguments() {
+        let err = format!("{:?}", detect_input("missing/blueprint.txt").unwrap_err());
+        assert!(err.contains("neither an existing file nor a blueprint string"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use error_stack::{report, Context, Result, ResultExt};
+use sha1::{Digest, Sha1};
+use tracing::{debug, info};
+
+use mod_util::mod_info::Version;
+
+#[derive(Debug)]
+pub enum ModCacheError {
+    NoCacheDir,
+    HashMismatch(String, Version),
+    Io(PathBuf),
+}
+
+impl Context for ModCacheError {}
+
+impl std::fmt::Display for ModCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoCacheDir => write!(f, "could not determine the mod cache directory"),
+            Self::HashMismatch(name, version) => {
+                write!(f, "sha1 of downloaded mod {name} v{version} does not match")
+            }
+            Self::Io(path) => write!(f, "mod cache io error at {}", path.display()),
+        }
+    }
+}
+
+/// Content addressed storage for downloaded mod zips that is shared between all mods folders.
+///
+/// Entries are keyed by name, version and the sha1 reported by the mod portal.
+#[derive(Debug, Clone)]
+pub struct ModCache {
+    root: PathBuf,
+}
+
+impl ModCache {
+    #[must_use]
+    pub const fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// `$XDG_CACHE_HOME/factorio-scanner/mods`, falling back to `~/.cache`.
+    pub fn default_dir() -> Result<PathBuf, ModCacheError> {
+        let cache_home = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .ok_or_else(|| report!(ModCacheError::NoCacheDir))?;
+
+        Ok(cache_home.join("factorio-scanner/mods"))
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn entry_path(&self, name: &str, version: &Version, sha1: &str) -> PathBuf {
+        self.root
+            .join(format!("{name}_{version}_{}.zip", sha1.to_lowercase()))
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str, version: &Version, sha1: &str) -> Option<PathBuf> {
+        let path = self.entry_path(name, version, sha1);
+        path.is_file().then_some(path)
+    }
+
+    /// Stores a downloaded mod after verifying its checksum.
+    pub fn insert(
+        &self,
+        name: &str,
+        version: &Version,
+        sha1: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, ModCacheError> {
+        if !format!("{:x}", Sha1::digest(data)).eq_ignore_ascii_case(sha1) {
+            return Err(report!(ModCacheError::HashMismatch(
+                name.to_owned(),
+                *version
+            )));
+        }
+
+        fs::create_dir_all(&self.root).change_context(ModCacheError::Io(self.root.clone()))?;
+
+        // write to a temporary file first so an interrupted write never ends up as an entry
+        let path = self.entry_path(name, version, sha1);
+        let tmp = path.with_extension("part");
+        fs::write(&tmp, data).change_context(ModCacheError::Io(tmp.clone()))?;
+        fs::rename(&tmp, &path).change_context(ModCacheError::Io(path.clone()))?;
+
+        debug!("cached {name} v{version} at {}", path.display());
+        Ok(path)
+    }
+
+    /// Places a cached mod into a mods folder.
+    ///
+    /// Hard links are used when possible so pruning the cache never breaks a mods folder,
+    /// otherwise the zip gets copied.
+    pub fn install(
+        &self,
+        cached: &Path,
+        mods_dir: &Path,
+        name: &str,
+        version: &Version,
+    ) -> Result<(), ModCacheError> {
+        let target = mods_dir.join(format!("{name}_{version}.zip"));
+        if target.exists() {
+            fs::remove_file(&target).change_context(ModCacheError::Io(target.clone()))?;
+        }
+
+        if fs::hard_link(cached, &target).is_err() {
+            fs::copy(cached, &target).change_context(ModCacheError::Io(target))?;
+        }
+
+        // mark the entry as recently used for pruning
+        fs::File::options()
+            .append(true)
+            .open(cached)
+            .and_then(|f| f.set_modified(SystemTime::now()))
+            .change_context(ModCacheError::Io(cached.to_path_buf()))?;
+
+        Ok(())
+    }
+
+    /// Removes all entries that were not used within `max_age`.
+    /// Returns the number of removed entries and their size in bytes.
+    pub fn prune(&self, max_age: Duration) -> Result<(usize, u64), ModCacheError> {
+        if !self.root.is_dir() {
+            return Ok((0, 0));
+        }
+
+        let now = SystemTime::now();
+        let mut removed = (0, 0);
+
+        for entry in
+            fs::read_dir(&self.root).change_context(ModCacheError::Io(self.root.clone()))?
+        {
+            let entry = entry.change_context(ModCacheError::Io(self.root.clone()))?;
+            let path = entry.path();
+            let meta = entry
+                .metadata()
+                .change_context(ModCacheError::Io(path.clone()))?;
+
+            if !meta.is_file() {
+                continue;
+            }
+
+            let age = meta
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+
+            if age < max_age {
+                continue;
+            }
+
+            fs::remove_file(&path).change_context(ModCacheError::Io(path.clone()))?;
+            removed.0 += 1;
+            removed.1 += meta.len();
+        }
+
+        info!(
+            "pruned {} cached mods ({} bytes) from {}",
+            removed.0,
+            removed.1,
+            self.root.display()
+        );
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    const DATA: &[u8] = b"not really a mod zip";
+
+    fn sha1(data: &[u8]) -> String {
+        format!("{:x}", Sha1::digest(data))
+    }
+
+    #[test]
+    fn prune_removes_unused_entries() {
+        let root = env::temp_dir().join(format!("scanner_mod_cache_{}", std::process::id()));
+        let mods_dir = root.join("mods");
+        let cache = ModCache::new(root.join("cache"));
+        let version = Version::new(1, 2, 3);
+
+        let old = cache
+            .insert("old-mod", &version, &sha1(b"old"), b"old")
+            .unwrap();
+        let used = cache
+            .insert("used-mod", &version, &sha1(DATA), DATA)
+            .unwrap();
+        assert_eq!(
+            cache.get("used-mod", &version, &sha1(DATA)),
+            Some(used.clone())
+        );
+        assert!(cache
+            .insert("broken-mod", &version, &sha1(b"other"), DATA)
+            .is_err());
+
+        let long_ago = SystemTime::now() - Duration::from_hours(24 * 30);
+        for entry in [&old, &used] {
+            fs::File::options()
+                .append(true)
+                .open(entry)
+                .and_then(|f| f.set_modified(long_ago))
+                .unwrap();
+        }
+
+        // installing marks the entry as used
+        fs::create_dir_all(&mods_dir).unwrap();
+        cache
+            .install(&used, &mods_dir, "used-mod", &version)
+            .unwrap();
+        assert_eq!(fs::read(mods_dir.join("used-mod_1.2.3.zip")).unwrap(), DATA);
+
+        let removed = cache.prune(Duration::from_hours(24)).unwrap();
+        assert_eq!(removed, (1, 3));
+        assert!(!old.exists());
+        assert!(used.exists());
+
+        // a missing cache has nothing to prune
+        let missing = ModCache::new(root.join("missing"));
+        assert_eq!(missing.prune(Duration::ZERO).unwrap(), (0, 0));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+//! Guesses the mods of a blueprint without mod info from the names of its unknown prototypes.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use blueprint::GetIDs;
+use factorio_api::{FactorioApiError, PortalCategory, PortalListParams, PortalSearchPageSize};
+use prototypes::DataUtil;
+use tracing::{debug, warn};
+
+/// Upper bound of mod portal searches per blueprint.
+const MAX_SEARCHES: usize = 8;
+
+/// Search results considered per search term.
+const RESULTS_PER_SEARCH: u16 = 10;
+
+/// Longest name prefix that is searched for on its own, longer ones are likely regular words.
+const MAX_PREFIX_LEN: usize = 8;
+
+/// A mod portal mod that probably adds some of the unknown prototypes.
+#[derive(Debug, Clone)]
+pub struct DetectedMod {
+    pub name: String,
+    pub title: String,
+    pub downloads: u32,
+
+    /// Unknown prototypes attributed to the mod.
+    pub prototypes: BTreeSet<String>,
+}
+
+/// Mods proposed for the unknown prototypes of a blueprint.
+#[derive(Debug, Clone, Default)]
+pub struct Detection {
+    pub mods: Vec<DetectedMod>,
+
+    /// Unknown prototypes no mod was found for.
+    pub unresolved: BTreeSet<String>,
+}
+
+impl Detection {
+    #[must_use]
+    pub fn mod_names(&self) -> Vec<String> {
+        self.mods.iter().map(|m| m.name.clone()).collect()
+    }
+}
+
+impl std::fmt::Display for Detection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.mods {
+            writeln!(
+                f,
+                "{} ({}, {} downloads): {}",
+                m.name,
+                m.title,
+                m.downloads,
+                m.prototypes.iter().cloned().collect::<Vec<_>>().join(", ")
+            )?;
+        }
+
+        if !self.unresolved.is_empty() {
+            writeln!(
+                f,
+                "unresolved: {}",
+                self.unresolved
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Entity, item & recipe names used by the blueprint that are not part of the loaded data.
+#[must_use]
+pub fn unknown_names(bp: &blueprint::Blueprint, data: &DataUtil) -> BTreeSet<String> {
+    let ids = bp.get_ids();
+    let items = data.item_names().collect::<HashSet<_>>();
+
+    let entities = ids
+        .entity
+        .iter()
+        .map(|id| id.as_str())
+        .filter(|name| !data.contains_entity(name));
+    let recipes = ids
+        .recipe
+        .iter()
+        .map(|id| id.as_str())
+        .filter(|name| !data.contains_recipe(name));
+    let unknown_items = ids
+        .item
+        .iter()
+        .map(|id| id.as_str())
+        .filter(|name| !items.contains(name));
+
+    entities
+        .chain(recipes)
+        .chain(unknown_items)
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Term to search the mod portal with, the prefix mods commonly put in front of their names or the whole name.
+fn search_term(name: &str) -> String {
+    match name.split_once(['-', '_']) {
+        Some((prefix, _)) if !prefix.is_empty() && prefix.len() <= MAX_PREFIX_LEN => {
+            prefix.to_lowercase()
+        }
+        _ => name.replace(['-', '_'], " ").to_lowercase(),
+    }
+}
+
+/// Searches the mod portal for the mods that add the given prototypes.
+///
+/// Names sharing a prefix are searched together & the most downloaded mod
+/// whose name or title contains the search term is preferred.
+/// Failed searches are logged and their names left unresolved,
+/// no further searches are made if the portal has no search.
+pub async fn detect(names: &BTreeSet<String>) -> Detection {
+    let mut terms = BTreeMap::<String, BTreeSet<String>>::new();
+    for name in names {
+        terms
+            .entry(search_term(name))
+            .or_default()
+            .insert(name.clone());
+    }
+
+    let mut terms = terms.into_iter().collect::<Vec<_>>();
+    terms.sort_by_key(|(_, names)| std::cmp::Reverse(names.len()));
+
+    let mut res = Detection::default();
+    let mut search_available = true;
+    for (idx, (term, term_names)) in terms.into_iter().enumerate() {
+        if idx >= MAX_SEARCHES || !search_available {
+            res.unresolved.extend(term_names);
+            continue;
+        }
+
+        let params = PortalListParams::new()
+            .hide_deprecated(true)
+            .page_size(PortalSearchPageSize::Custom(RESULTS_PER_SEARCH));
+        let results = match factorio_api::portal_search(&term, params).await {
+            Ok(response) => response.results,
+            Err(FactorioApiError::SearchUnavailable) => {
+                warn!("the mod portal search is not available, mods can't be detected");
+                search_available = false;
+                res.unresolved.extend(term_names);
+                continue;
+            }
+            Err(err) => {
+                warn!("mod portal search for {term:?} failed: {err}");
+                res.unresolved.extend(term_names);
+                continue;
+            }
+        };
+
+        let candidates = results.into_iter().filter(|entry| {
+            !matches!(
+                entry.category,
+                Some(
+                    PortalCategory::Localizations
+                        | PortalCategory::Scenarios
+                        | PortalCategory::Internal
+                )
+            )
+        });
+
+        let (matching, other): (Vec<_>, Vec<_>) = candidates.partition(|entry| {
+            entry.name.to_lowercase().contains(&term) || entry.title.to_lowercase().contains(&term)
+        });
+
+        let best = if matching.is_empty() { other } else { matching }
+            .into_iter()
+            .max_by_key(|entry| entry.downloads_count);
+
+        let Some(best) = best else {
+            debug!("no mod found for {term:?}");
+            res.unresolved.extend(term_names);
+            continue;
+        };
+
+        debug!("attributing {term:?} to {}", best.name);
+        if let Some(existing) = res.mods.iter_mut().find(|m| m.name == best.name) {
+            existing.prototypes.extend(term_names);
+        } else {
+            res.mods.push(DetectedMod {
+                name: best.name,
+                title: best.title,
+                downloads: best.downloads_count,
+                prototypes: term_names,
+            });
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_terms() {
+        assert_eq!(search_term("kr-advanced-furnace"), "kr");
+        assert_eq!(search_term("se_space-pipe"), "se");
+        assert_eq!(search_term("nullius-boiler"), "nullius");
+        assert_eq!(search_term("Cube-Rail"), "cube");
+
+        // prefixes that are too long are likely words of the name
+        assert_eq!(
+            search_term("underground-storage-tank"),
+            "underground storage tank"
+        );
+        assert_eq!(search_term("-leading_dash"), " leading dash");
+        assert_eq!(search_term("Flamethrower"), "flamethrower");
+    }
+}
@@ -0,0 +1,222 @@
+//! Startup settings of the loaded mod set, to find the `--setting` overrides a modpack accepts.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use prototypes::mod_setting::{AllTypes, ModSettingPrototype, SettingType};
+use types::{Color, FactorioArray};
+
+#[cfg(feature = "datastage")]
+use {
+    crate::ScannerError,
+    error_stack::{Result, ResultExt},
+    mod_util::mod_list::ModList,
+    tracing::instrument,
+};
+
+/// Runs only the settings stage of the active mods, without starting the game or loading any other prototypes.
+#[cfg(feature = "datastage")]
+#[instrument(skip_all)]
+pub fn settings_stage(mod_list: &ModList) -> Result<AllTypes, ScannerError> {
+    let (active_mods, load_order) = mod_list.active_with_order();
+    let settings = factorio_datastage::settings_stage(&active_mods, &load_order)
+        .change_context(ScannerError::SetupError)?;
+
+    serde_json::from_value(serde_json::Value::Object(settings))
+        .change_context(ScannerError::SetupError)
+        .attach_printable("invalid setting prototypes")
+}
+
+/// All startup settings by name.
+#[derive(Debug, Serialize)]
+pub struct StartupSettings<'a> {
+    pub settings: BTreeMap<&'a str, Setting<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Setting<'a> {
+    #[serde(flatten)]
+    pub value: SettingValue<'a>,
+
+    /// Hidden settings are not shown in the game but can still be set.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+/// Type, default & the accepted values of a setting.
+/// No `allowed_values` means any value of the type inside of the min / max is accepted.
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SettingValue<'a> {
+    Bool {
+        default_value: bool,
+
+        /// Value the setting is locked to, it can't be changed if set.
+        forced_value: Option<bool>,
+    },
+    Int {
+        default_value: i64,
+        minimum_value: Option<i64>,
+        maximum_value: Option<i64>,
+        allowed_values: Option<&'a [i64]>,
+    },
+    Double {
+        default_value: f64,
+        minimum_value: Option<f64>,
+        maximum_value: Option<f64>,
+        allowed_values: Option<&'a [f64]>,
+    },
+    String {
+        default_value: &'a str,
+
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        allow_blank: bool,
+
+        allowed_values: Option<&'a [String]>,
+    },
+    Color {
+        default_value: &'a Color,
+    },
+}
+
+/// Allowed values of a setting, `None` if there is no restriction.
+fn allowed<T>(values: Option<&FactorioArray<T>>) -> Option<&[T]> {
+    values
+        .map(|values| values.as_slice())
+        .filter(|values| !values.is_empty())
+}
+
+impl<'a> StartupSettings<'a> {
+    /// The startup settings of `all`, e.g. [`prototypes::DataUtil::mod_settings`] or [`settings_stage`].
+    #[must_use]
+    pub fn new(all: &'a AllTypes) -> Self {
+        let mut settings = BTreeMap::new();
+
+        let mut insert = |name: &'a str, hidden: bool, value: SettingValue<'a>| {
+            settings.insert(name, Setting { value, hidden });
+        };
+
+        for (name, setting) in startup(&all.bool_setting) {
+            insert(
+                name,
+                setting.hidden,
+                SettingValue::Bool {
+                    default_value: setting.default_value,
+                    forced_value: setting.forced_value,
+                },
+            );
+        }
+
+        for (name, setting) in startup(&all.int_setting) {
+            insert(
+                name,
+                setting.hidden,
+                SettingValue::Int {
+                    default_value: setting.default_value,
+                    minimum_value: setting.minimum_value,
+                    maximum_value: setting.maximum_value,
+                    allowed_values: allowed(setting.allowed_values.as_ref()),
+                },
+            );
+        }
+
+        for (name, setting) in startup(&all.double_setting) {
+            insert(
+                name,
+                setting.hidden,
+                SettingValue::Double {
+                    default_value: setting.default_value,
+                    minimum_value: setting.minimum_value,
+                    maximum_value: setting.maximum_value,
+                    allowed_values: allowed(setting.allowed_values.as_ref()),
+                },
+            );
+        }
+
+        for (name, setting) in startup(&all.string_setting) {
+            insert(
+                name,
+                setting.hidden,
+                SettingValue::String {
+                    default_value: &setting.default_value,
+                    allow_blank: setting.allow_blank,
+                    allowed_values: allowed(setting.allowed_values.as_ref()),
+                },
+            );
+        }
+
+        for (name, setting) in startup(&all.color_setting) {
+            insert(
+                name,
+                setting.hidden,
+                SettingValue::Color {
+                    default_value: &setting.default_value,
+                },
+            );
+        }
+
+        Self { settings }
+    }
+}
+
+fn startup<T>(
+    settings: &HashMap<String, ModSettingPrototype<T>>,
+) -> impl Iterator<Item = (&str, &ModSettingPrototype<T>)> {
+    settings
+        .iter()
+        .filter(|(_, setting)| setting.setting_type == SettingType::Startup)
+        .map(|(name, setting)| (name.as_str(), setting))
+}
+
+#[cfg(all(test, feature = "datastage"))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::{fs, sync::Arc};
+
+    use mod_util::{
+        mod_info::Version,
+        sprite_source::{self, DirectorySource, SharedSpriteSource},
+    };
+
+    use super::*;
+
+    const SETTINGS: &str = r#"data:extend({
+        { type = "int-setting", name = "count", setting_type = "startup", default_value = 4, allowed_values = { 2, 4, 8 } },
+        { type = "bool-setting", name = "hidden-flag", setting_type = "startup", default_value = false, hidden = true },
+        { type = "string-setting", name = "per-user", setting_type = "runtime-per-user", default_value = "" },
+        { type = "color-setting", name = "tint", setting_type = "startup", default_value = { r = 1, g = 0.5, b = 0 } },
+    })"#;
+
+    #[test]
+    fn staged_startup_settings() {
+        let root = std::env::temp_dir().join(format!("staged_settings_{}", std::process::id()));
+        fs::create_dir_all(root.join("some-mod")).unwrap();
+        fs::write(root.join("some-mod/settings.lua"), SETTINGS).unwrap();
+
+        let source: SharedSpriteSource = Arc::new(DirectorySource::new(&root));
+        let mods =
+            sprite_source::used_mods([("some-mod".to_owned(), Version::new(1, 0, 0))], &source);
+        let staged = factorio_datastage::settings_stage(&mods, &["some-mod".to_owned()]).unwrap();
+        fs::remove_dir_all(root).unwrap();
+
+        let all: AllTypes = serde_json::from_value(serde_json::Value::Object(staged)).unwrap();
+        let settings = StartupSettings::new(&all);
+
+        assert_eq!(
+            settings.settings.keys().copied().collect::<Vec<_>>(),
+            ["count", "hidden-flag", "tint"]
+        );
+        assert!(settings.settings["hidden-flag"].hidden);
+        assert!(matches!(
+            settings.settings["count"].value,
+            SettingValue::Int {
+                default_value: 4,
+                allowed_values: Some([2, 4, 8]),
+                ..
+            }
+        ));
+    }
+}
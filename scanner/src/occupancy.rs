@@ -0,0 +1,215 @@
+//! Tile grid of a blueprint with the entities covering each tile, e.g. for auto-placement tools.
+
+use blueprint::EntityNumber;
+use prototypes::DataUtil;
+use types::{BoundingBox, MapPosition};
+
+use crate::bp_helper;
+
+/// Collision boxes have to cover at least this much of a tile (in tiles) to occupy it.
+const MIN_COVERAGE: f64 = 0.01;
+
+/// Which entities occupy every tile inside the bounds of a blueprint.
+///
+/// Tile `(x, y)` is the square from `(x, y)` to `(x + 1, y + 1)` in blueprint coordinates.
+/// Footprints are the collision boxes of the prototypes, entities without a prototype occupy
+/// the tile at their position. Entities without a collision box (e.g. some decoratives) don't occupy anything.
+#[derive(Debug, Clone, Default)]
+pub struct OccupancyGrid {
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+    cells: Vec<Vec<EntityNumber>>,
+}
+
+/// Tile range `[start, end)` covered by `start..end` in one axis.
+fn tile_range(start: f64, end: f64) -> (i64, i64) {
+    (
+        (start + MIN_COVERAGE).floor() as i64,
+        (end - MIN_COVERAGE).ceil() as i64,
+    )
+}
+
+impl OccupancyGrid {
+    #[must_use]
+    pub fn new(bp: &blueprint::Blueprint, data: &DataUtil) -> Self {
+        let footprints = bp
+            .entities
+            .iter()
+            .filter_map(|e| {
+                let area = bp_helper::entity_area(e, data).unwrap_or_else(|| {
+                    let (x, y) = MapPosition::from(&e.position).as_tuple();
+                    BoundingBox(
+                        MapPosition::Tuple(x.floor(), y.floor()),
+                        MapPosition::Tuple(x.floor() + 1.0, y.floor() + 1.0),
+                    )
+                });
+
+                (area.width() > MIN_COVERAGE && area.height() > MIN_COVERAGE)
+                    .then_some((e.entity_number, area))
+            })
+            .collect::<Vec<_>>();
+
+        let Some(bounds) = footprints
+            .iter()
+            .map(|(_, area)| area.clone())
+            .reduce(|a, b| a.union(&b))
+        else {
+            return Self::default();
+        };
+
+        let (left, right) = tile_range(bounds.left(), bounds.right());
+        let (top, bottom) = tile_range(bounds.top(), bounds.bottom());
+        let tiles = usize::try_from((right - left) * (bottom - top)).unwrap_or_default();
+
+        let mut grid = Self {
+            left,
+            top,
+            right,
+            bottom,
+            cells: vec![Vec::new(); tiles],
+        };
+
+        for (entity_number, area) in footprints {
+            let (x_start, x_end) = tile_range(area.left(), area.right());
+            let (y_start, y_end) = tile_range(area.top(), area.bottom());
+
+            for y in y_start..y_end {
+                for x in x_start..x_end {
+                    if let Some(idx) = grid.index(x, y) {
+                        grid.cells[idx].push(entity_number);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        if !(self.left..self.right).contains(&x) || !(self.top..self.bottom).contains(&y) {
+            return None;
+        }
+
+        usize::try_from((y - self.top) * (self.right - self.left) + x - self.left).ok()
+    }
+
+    /// X coordinate of the leftmost column of tiles.
+    #[must_use]
+    pub const fn left(&self) -> i64 {
+        self.left
+    }
+
+    /// Y coordinate of the topmost row of tiles.
+    #[must_use]
+    pub const fn top(&self) -> i64 {
+        self.top
+    }
+
+    /// Number of tile columns.
+    #[must_use]
+    pub const fn width(&self) -> i64 {
+        self.right - self.left
+    }
+
+    /// Number of tile rows.
+    #[must_use]
+    pub const fn height(&self) -> i64 {
+        self.bottom - self.top
+    }
+
+    /// Area covered by the grid in blueprint coordinates.
+    #[must_use]
+    pub const fn bounds(&self) -> BoundingBox {
+        BoundingBox(
+            MapPosition::Tuple(self.left as f64, self.top as f64),
+            MapPosition::Tuple(self.right as f64, self.bottom as f64),
+        )
+    }
+
+    /// Entities occupying the tile, more than one if their collision boxes overlap (e.g. crossing rails).
+    /// Tiles outside of the grid are empty.
+    #[must_use]
+    pub fn occupants(&self, x: i64, y: i64) -> &[EntityNumber] {
+        self.index(x, y).map_or(&[], |idx| &self.cells[idx])
+    }
+
+    #[must_use]
+    pub fn is_free(&self, x: i64, y: i64) -> bool {
+        self.occupants(x, y).is_empty()
+    }
+
+    /// Whether no entity occupies any tile touched by `area`, e.g. the collision box of an entity to place.
+    #[must_use]
+    pub fn is_area_free(&self, area: &BoundingBox) -> bool {
+        let (x_start, x_end) = tile_range(area.left(), area.right());
+        let (y_start, y_end) = tile_range(area.top(), area.bottom());
+
+        (y_start..y_end).all(|y| (x_start..x_end).all(|x| self.is_free(x, y)))
+    }
+
+    /// Tiles inside the grid without any entity, row by row.
+    pub fn free_tiles(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.tiles()
+            .filter(|(_, occupants)| occupants.is_empty())
+            .map(|(tile, _)| tile)
+    }
+
+    /// Every tile of the grid with its occupants, row by row.
+    pub fn tiles(&self) -> impl Iterator<Item = ((i64, i64), &[EntityNumber])> + '_ {
+        (self.top..self.bottom)
+            .flat_map(move |y| (self.left..self.right).map(move |x| (x, y)))
+            .zip(self.cells.iter().map(Vec::as_slice))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{base_data, blueprint};
+
+    #[test]
+    fn grid_covers_collision_boxes() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"storage-tank","position":{"x":1.5,"y":1.5}},
+            {"entity_number":2,"name":"transport-belt","position":{"x":3.5,"y":0.5}},
+            {"entity_number":3,"name":"unknown-entity","position":{"x":4.5,"y":2.5}}"#,
+            "",
+        );
+        let grid = OccupancyGrid::new(&bp, base_data());
+
+        assert_eq!((grid.left(), grid.top()), (0, 0));
+        assert_eq!((grid.width(), grid.height()), (5, 3));
+        assert_eq!(grid.tiles().count(), 15);
+
+        // the collision box of the tank is slightly smaller than 3x3 but still occupies all tiles
+        for (x, y) in [(0, 0), (2, 0), (1, 1), (0, 2), (2, 2)] {
+            assert_eq!(grid.occupants(x, y), [1]);
+        }
+        assert_eq!(grid.occupants(3, 0), [2]);
+        assert_eq!(grid.occupants(4, 2), [3]);
+
+        assert!(grid.is_free(3, 1));
+        assert!(grid.is_free(-1, 0));
+        assert_eq!(grid.free_tiles().count(), 4);
+
+        assert!(grid.is_area_free(&BoundingBox(
+            MapPosition::Tuple(3.0, 1.0),
+            MapPosition::Tuple(5.0, 2.0)
+        )));
+        assert!(!grid.is_area_free(&BoundingBox(
+            MapPosition::Tuple(2.5, 1.0),
+            MapPosition::Tuple(4.0, 2.0)
+        )));
+    }
+
+    #[test]
+    fn empty_blueprint() {
+        let grid = OccupancyGrid::new(&blueprint("", ""), base_data());
+
+        assert_eq!((grid.width(), grid.height()), (0, 0));
+        assert!(grid.is_free(0, 0));
+        assert_eq!(grid.tiles().count(), 0);
+    }
+}
@@ -0,0 +1,1345 @@
+use std::collections::BTreeMap;
+
+use image::{imageops, DynamicImage, Rgba};
+
+use blueprint::{ArithmeticData, DeciderData, EntityNumber, SignalID};
+use mod_util::UsedMods;
+
+use prototypes::{
+    entity::{
+        AmmoTurretPrototype, ArtilleryTurretPrototype, ArtilleryWagonPrototype,
+        ElectricPolePrototype, ElectricTurretPrototype, FluidTurretPrototype, ReactorPrototype,
+        RoboportPrototype, TurretPrototype, Type as EntityType,
+    },
+    item::GunPrototype,
+    DataUtil, DataUtilAccess, InternalRenderLayer, RenderLayerBuffer,
+};
+use types::{
+    AttackParameters, BoundingBox, Direction, GraphicsOutput, ImageCache, ItemID, MapPosition,
+    RealOrientation, Vector,
+};
+
+use crate::{
+    bp_helper,
+    fluid::FluidNetworks,
+    heat::{self, HeatNetwork},
+    rail::RailGraph,
+    text::{self, Font},
+    validate,
+};
+
+/// Optional overlays that can be drawn on top of a rendered blueprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Overlay {
+    /// Dashed lines between paired underground belts and pipe-to-grounds
+    UndergroundPairs,
+
+    /// Shaded construction & logistic areas of roboports
+    RoboportCoverage,
+
+    /// Electric pole supply areas & wire reach, unpowered entities are marked red
+    Power,
+
+    /// Rails colored by signal block & the direction of rail / chain signals
+    RailBlocks,
+
+    /// Signals of constant combinators & operands of arithmetic / decider combinators
+    CombinatorContents,
+
+    /// Attack ranges of turrets, including the minimum range of artillery
+    TurretRange,
+
+    /// Automatic & manual firing ranges of artillery turrets & wagons, the render is enlarged to fit them
+    ArtilleryRange,
+
+    /// Pipes, pumps & storage tanks tinted by the fluid network they belong to
+    FluidNetworks,
+
+    /// Arrows on belts, underground belt exits & splitter outputs showing the belt direction
+    BeltDirections,
+
+    /// Entities using a parameter of a parametrised blueprint, labeled with the parameter number
+    Parameters,
+
+    /// Number of conditions & the signals of the first condition of decider combinators
+    DeciderConditions,
+
+    /// Heat pipes & reactors tinted from hot to cold by the distance to the nearest heat source, too cold ones are marked red
+    HeatGradient,
+
+    /// Links between neighbouring reactors, every reactor is labeled with its neighbour bonus
+    ReactorBonus,
+
+    /// Red boxes around entities whose collision boxes overlap, see `scanner validate`
+    Overlaps,
+
+    /// Lines of the grid the blueprint snaps to, if it has one
+    SnapGrid,
+
+    /// Orange lines between entities connected by wires that are out of reach, see `scanner validate`
+    WireReach,
+
+    /// Entities above normal quality outlined in the color of their quality with its icon as badge
+    Quality,
+}
+
+const BELT_PAIR_COLOR: Rgba<u8> = Rgba([255, 200, 50, 220]);
+const PIPE_PAIR_COLOR: Rgba<u8> = Rgba([80, 170, 255, 220]);
+
+const CONSTRUCTION_AREA_COLOR: Rgba<u8> = Rgba([60, 200, 110, 50]);
+const CONSTRUCTION_EDGE_COLOR: Rgba<u8> = Rgba([60, 200, 110, 160]);
+const LOGISTIC_AREA_COLOR: Rgba<u8> = Rgba([255, 170, 0, 70]);
+const LOGISTIC_EDGE_COLOR: Rgba<u8> = Rgba([255, 170, 0, 200]);
+
+const SUPPLY_AREA_COLOR: Rgba<u8> = Rgba([80, 170, 255, 50]);
+const SUPPLY_EDGE_COLOR: Rgba<u8> = Rgba([80, 170, 255, 160]);
+const POLE_CONNECTION_COLOR: Rgba<u8> = Rgba([255, 230, 120, 200]);
+const UNPOWERED_COLOR: Rgba<u8> = Rgba([230, 40, 40, 110]);
+
+const HOT_COLOR: Rgba<u8> = Rgba([255, 200, 40, 110]);
+const COLD_COLOR: Rgba<u8> = Rgba([60, 120, 255, 110]);
+const TOO_COLD_COLOR: Rgba<u8> = Rgba([230, 40, 40, 150]);
+
+const REACTOR_LINK_COLOR: Rgba<u8> = Rgba([120, 255, 120, 220]);
+
+const RAIL_BLOCK_COLORS: [Rgba<u8>; 8] = [
+    Rgba([230, 25, 75, 230]),
+    Rgba([60, 180, 75, 230]),
+    Rgba([255, 225, 25, 230]),
+    Rgba([0, 130, 200, 230]),
+    Rgba([245, 130, 48, 230]),
+    Rgba([145, 30, 180, 230]),
+    Rgba([70, 240, 240, 230]),
+    Rgba([240, 50, 230, 230]),
+];
+const RAIL_SIGNAL_COLOR: Rgba<u8> = Rgba([255, 255, 255, 240]);
+const CHAIN_SIGNAL_COLOR: Rgba<u8> = Rgba([80, 160, 255, 240]);
+
+const FLUID_NETWORK_COLORS: [Rgba<u8>; 8] = [
+    Rgba([0, 130, 200, 140]),
+    Rgba([230, 25, 75, 140]),
+    Rgba([60, 180, 75, 140]),
+    Rgba([255, 225, 25, 140]),
+    Rgba([145, 30, 180, 140]),
+    Rgba([245, 130, 48, 140]),
+    Rgba([70, 240, 240, 140]),
+    Rgba([240, 50, 230, 140]),
+];
+
+const SIGNAL_COUNT_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+const PARAMETER_AREA_COLOR: Rgba<u8> = Rgba([180, 120, 255, 60]);
+const PARAMETER_EDGE_COLOR: Rgba<u8> = Rgba([180, 120, 255, 220]);
+
+const OVERLAP_AREA_COLOR: Rgba<u8> = Rgba([230, 30, 30, 110]);
+const OVERLAP_EDGE_COLOR: Rgba<u8> = Rgba([255, 40, 40, 230]);
+
+const SNAP_GRID_COLOR: Rgba<u8> = Rgba([255, 255, 255, 150]);
+
+const UNREACHABLE_WIRE_COLOR: Rgba<u8> = Rgba([255, 150, 0, 240]);
+
+const QUALITY_EDGE_ALPHA: u8 = 230;
+
+const TURRET_RANGE_COLOR: Rgba<u8> = Rgba([230, 60, 60, 45]);
+const TURRET_RANGE_EDGE_COLOR: Rgba<u8> = Rgba([230, 60, 60, 180]);
+const MIN_RANGE_EDGE_COLOR: Rgba<u8> = Rgba([255, 170, 0, 220]);
+
+const ARTILLERY_RANGE_COLOR: Rgba<u8> = Rgba([230, 60, 60, 35]);
+const MANUAL_RANGE_EDGE_COLOR: Rgba<u8> = Rgba([120, 200, 255, 220]);
+
+/// How many signals of a constant combinator are shown.
+const MAX_CONSTANT_SIGNALS: usize = 4;
+
+/// Square area with the given `radius` around `center`.
+const fn square_area(center: &MapPosition, radius: f64) -> BoundingBox {
+    BoundingBox(*center, *center).expand(radius)
+}
+
+/// Draws dashed lines between underground belt and pipe-to-ground pairs that are within
+/// the `max_distance` / `max_underground_distance` of their prototype.
+pub fn draw_underground_pairs(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    for pair in bp_helper::underground_pairs(bp, data) {
+        if pair.steps <= 1 {
+            continue;
+        }
+
+        let color = if pair.pipe {
+            PIPE_PAIR_COLOR
+        } else {
+            BELT_PAIR_COLOR
+        };
+
+        // keep the line between the two entities instead of drawing over them
+        let half_step: MapPosition = (pair.direction.get_offset() * 0.5).into();
+        let start = pair.start + half_step;
+        let end = pair.end - half_step;
+
+        render_layers.draw_dashed_line(&start, &end, color, InternalRenderLayer::UndergroundPairs);
+    }
+}
+
+/// Shades the construction and logistic areas of all roboports in the blueprint.
+/// Overlapping areas are shaded uniformly so coverage gaps stand out.
+pub fn draw_roboport_coverage(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let roboports = bp
+        .entities
+        .iter()
+        .filter_map(|e| {
+            let proto = data.get_proto::<RoboportPrototype>(&e.name)?;
+            Some((MapPosition::from(&e.position), proto))
+        })
+        .collect::<Vec<_>>();
+
+    if roboports.is_empty() {
+        return;
+    }
+
+    // construction areas first so the smaller logistic areas stay visible on top
+    for (pos, proto) in &roboports {
+        if proto.draw_construction_radius_visualization {
+            render_layers.fill_area(
+                &square_area(pos, proto.construction_radius),
+                CONSTRUCTION_AREA_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+    }
+
+    for (pos, proto) in &roboports {
+        if proto.draw_logistic_radius_visualization {
+            render_layers.fill_area(
+                &square_area(pos, proto.logistics_radius),
+                LOGISTIC_AREA_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+    }
+
+    for (pos, proto) in &roboports {
+        if proto.draw_construction_radius_visualization {
+            render_layers.outline_area(
+                &square_area(pos, proto.construction_radius),
+                CONSTRUCTION_EDGE_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+
+        if proto.draw_logistic_radius_visualization {
+            render_layers.outline_area(
+                &square_area(pos, proto.logistics_radius),
+                LOGISTIC_EDGE_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+    }
+}
+
+/// Shades the supply areas of all electric poles in the blueprint, connects poles that are
+/// within wire reach of each other and marks electric entities outside of any supply area.
+pub fn draw_power(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let poles = bp
+        .entities
+        .iter()
+        .filter_map(|e| {
+            let proto = data.get_proto::<ElectricPolePrototype>(&e.name)?;
+            let position = MapPosition::from(&e.position);
+            let supply_area = square_area(&position, proto.supply_area_distance);
+
+            Some((
+                e.entity_number,
+                position,
+                supply_area,
+                proto.wire_connection_data.get_max_distance(),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    for (_, _, supply_area, _) in &poles {
+        render_layers.fill_area(
+            supply_area,
+            SUPPLY_AREA_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+
+    for (_, _, supply_area, _) in &poles {
+        render_layers.outline_area(
+            supply_area,
+            SUPPLY_EDGE_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+
+    for (idx, (_, pos, _, reach)) in poles.iter().enumerate() {
+        for (_, other_pos, _, other_reach) in &poles[idx + 1..] {
+            let (dx, dy) = (*other_pos - pos).as_tuple();
+
+            if dx.hypot(dy) <= reach.min(*other_reach) {
+                render_layers.draw_dashed_line(
+                    pos,
+                    other_pos,
+                    POLE_CONNECTION_COLOR,
+                    InternalRenderLayer::AreaOverlay,
+                );
+            }
+        }
+    }
+
+    for e in &bp.entities {
+        let is_pole = poles.iter().any(|(id, ..)| *id == e.entity_number);
+        if is_pole
+            || !data
+                .get_entity(&e.name)
+                .is_some_and(prototypes::entity::Renderable::uses_electricity)
+        {
+            continue;
+        }
+
+        let Some(area) = bp_helper::entity_area(e, data) else {
+            continue;
+        };
+
+        if !poles
+            .iter()
+            .any(|(_, _, supply_area, _)| supply_area.intersects(&area))
+        {
+            render_layers.fill_area(&area, UNPOWERED_COLOR, InternalRenderLayer::AreaOverlay);
+        }
+    }
+}
+
+/// Outlines all entities whose collision boxes overlap (see [`validate::overlaps`]) and fills the overlapping parts.
+pub fn draw_overlaps(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    for overlap in validate::overlaps(bp, data) {
+        for area in &overlap.areas {
+            render_layers.outline_area(area, OVERLAP_EDGE_COLOR, InternalRenderLayer::AreaOverlay);
+        }
+
+        render_layers.fill_area(
+            &overlap.intersection,
+            OVERLAP_AREA_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+}
+
+/// Draws a line between all entities connected by wires that are out of reach (see [`validate::unreachable_wires`])
+/// and outlines the entities.
+pub fn draw_unreachable_wires(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    for wire in validate::unreachable_wires(bp, data) {
+        let [start, end] = &wire.positions;
+        render_layers.draw_line(
+            start,
+            end,
+            UNREACHABLE_WIRE_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+
+        for entity in &wire.entities {
+            let Some(area) = bp
+                .entities
+                .iter()
+                .find(|e| e.entity_number == *entity)
+                .and_then(|e| bp_helper::entity_area(e, data))
+            else {
+                continue;
+            };
+
+            render_layers.outline_area(
+                &area,
+                UNREACHABLE_WIRE_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+    }
+}
+
+/// Draws the lines of the snapping grid over the area of the blueprint.
+/// The grid starts at the top left corner of the blueprint, shifted by the position relative to the grid for absolute snapping.
+pub fn draw_snap_grid(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let Some(size) = &bp.snapping.snap_to_grid else {
+        return;
+    };
+    let (width, height) = (f64::from(size.x), f64::from(size.y));
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+
+    let Some(bounds) = bp_helper::blueprint_bounds(bp, data) else {
+        return;
+    };
+
+    let offset = bp.snapping.grid_offset();
+    let origin_x = bounds.left() + f64::from(offset.x);
+    let origin_y = bounds.top() + f64::from(offset.y);
+
+    // extend the grid to whole cells around the blueprint
+    let first_col = ((bounds.left() - origin_x) / width).floor() as i64;
+    let last_col = ((bounds.right() - origin_x) / width).ceil() as i64;
+    let first_row = ((bounds.top() - origin_y) / height).floor() as i64;
+    let last_row = ((bounds.bottom() - origin_y) / height).ceil() as i64;
+
+    let grid_x = |col: i64| (col as f64).mul_add(width, origin_x);
+    let grid_y = |row: i64| (row as f64).mul_add(height, origin_y);
+
+    for col in first_col..=last_col {
+        render_layers.draw_dashed_line(
+            &MapPosition::Tuple(grid_x(col), grid_y(first_row)),
+            &MapPosition::Tuple(grid_x(col), grid_y(last_row)),
+            SNAP_GRID_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+
+    for row in first_row..=last_row {
+        render_layers.draw_dashed_line(
+            &MapPosition::Tuple(grid_x(first_col), grid_y(row)),
+            &MapPosition::Tuple(grid_x(last_col), grid_y(row)),
+            SNAP_GRID_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+}
+
+/// Colors all rails by the signal block they belong to and draws an arrow for every rail
+/// and chain signal pointing in the direction of travel it applies to.
+pub fn draw_rail_blocks(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    // offsets of the parallel lines that make up a rail, in tiles
+    const RAIL_WIDTH_OFFSETS: [f64; 3] = [-0.125, 0.0, 0.125];
+    const ARROW_LENGTH: f64 = 0.4;
+    const ARROW_WIDTH: f64 = 0.25;
+
+    let graph = RailGraph::new(bp, data);
+
+    for (piece, block) in graph.pieces().iter().zip(graph.blocks()) {
+        let color = RAIL_BLOCK_COLORS[block % RAIL_BLOCK_COLORS.len()];
+
+        for segment in piece.path.windows(2) {
+            let (dx, dy) = (segment[1] - segment[0]).as_tuple();
+            let length = dx.hypot(dy);
+            if length == 0.0 {
+                continue;
+            }
+
+            for offset in RAIL_WIDTH_OFFSETS {
+                let normal = MapPosition::Tuple(-dy / length * offset, dx / length * offset);
+                render_layers.draw_line(
+                    &(segment[0] + normal),
+                    &(segment[1] + normal),
+                    color,
+                    InternalRenderLayer::AreaOverlay,
+                );
+            }
+        }
+    }
+
+    for signal in graph.signals() {
+        let (dx, dy) = signal.travel_direction.as_tuple();
+        let tip =
+            signal.position + MapPosition::Tuple(dx * ARROW_LENGTH / 2.0, dy * ARROW_LENGTH / 2.0);
+        let base =
+            signal.position - MapPosition::Tuple(dx * ARROW_LENGTH / 2.0, dy * ARROW_LENGTH / 2.0);
+        let side = MapPosition::Tuple(-dy * ARROW_WIDTH / 2.0, dx * ARROW_WIDTH / 2.0);
+
+        render_layers.fill_polygon(
+            &[tip, base + side, base - side],
+            if signal.chain {
+                CHAIN_SIGNAL_COLOR
+            } else {
+                RAIL_SIGNAL_COLOR
+            },
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+}
+
+/// Tints pipes, pumps & storage tanks by the fluid network they belong to and connects
+/// paired pipe-to-grounds of a network with a dashed line.
+pub fn draw_fluid_networks(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let networks = FluidNetworks::new(bp, data);
+    let color = |network: usize| FLUID_NETWORK_COLORS[network % FLUID_NETWORK_COLORS.len()];
+
+    for e in &bp.entities {
+        let Some(network) = networks.network_of(e.entity_number) else {
+            continue;
+        };
+
+        let Some(area) = bp_helper::entity_area(e, data) else {
+            continue;
+        };
+
+        render_layers.fill_area(&area, color(network), InternalRenderLayer::AreaOverlay);
+    }
+
+    for pair in bp_helper::underground_pairs(bp, data)
+        .into_iter()
+        .filter(|pair| pair.pipe && pair.steps > 1)
+    {
+        let Some(network) = networks.network_of(pair.source) else {
+            continue;
+        };
+
+        let half_step: MapPosition = (pair.direction.get_offset() * 0.5).into();
+        render_layers.draw_dashed_line(
+            &(pair.start + half_step),
+            &(pair.end - half_step),
+            color(network),
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+}
+
+/// Mixes `a` & `b`, `t = 0.0` is `a` and `t = 1.0` is `b`.
+fn lerp_color(a: Rgba<u8>, b: Rgba<u8>, t: f64) -> Rgba<u8> {
+    let t = t.clamp(0.0, 1.0);
+    Rgba(std::array::from_fn(|idx| {
+        let (a, b) = (f64::from(a.0[idx]), f64::from(b.0[idx]));
+        (b - a).mul_add(t, a).round() as u8
+    }))
+}
+
+/// Tints all heat conducting entities by their estimated temperature, from the temperature of the
+/// nearest heat source down to the working temperature of the heat consumers.
+///
+/// Entities that are not connected to a heat source or can't reach the working temperature are outlined in red.
+pub fn draw_heat_gradient(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let network = HeatNetwork::new(bp, data);
+    if network.is_empty() {
+        return;
+    }
+
+    for e in &bp.entities {
+        let Some(state) = network.state_of(e.entity_number) else {
+            continue;
+        };
+
+        let Some(area) = bp_helper::entity_area(e, data) else {
+            continue;
+        };
+
+        if network.too_cold(e.entity_number) {
+            render_layers.fill_area(&area, TOO_COLD_COLOR, InternalRenderLayer::AreaOverlay);
+            render_layers.outline_area(&area, TOO_COLD_COLOR, InternalRenderLayer::AreaOverlay);
+            continue;
+        }
+
+        let Some(state) = state else {
+            continue;
+        };
+
+        let range = state.source_temperature - network.working_temperature;
+        let t = if range > 0.0 {
+            (state.source_temperature - state.temperature) / range
+        } else {
+            0.0
+        };
+
+        render_layers.fill_area(
+            &area,
+            lerp_color(HOT_COLOR, COLD_COLOR, t),
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+}
+
+/// Connects the centers of neighbouring reactors and labels every reactor with its neighbour bonus,
+/// assuming all of them are working.
+///
+/// The labels are left out if the core font can't be loaded.
+pub fn draw_reactor_bonus(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let neighbours = heat::reactor_neighbours(bp, data);
+    if neighbours.is_empty() {
+        return;
+    }
+
+    let positions = bp
+        .entities
+        .iter()
+        .filter(|e| neighbours.contains_key(&e.entity_number))
+        .map(|e| (e.entity_number, (MapPosition::from(&e.position), &e.name)))
+        .collect::<BTreeMap<_, _>>();
+
+    for (number, others) in &neighbours {
+        for other in others.iter().filter(|other| *other > number) {
+            if let (Some((a, _)), Some((b, _))) = (positions.get(number), positions.get(other)) {
+                render_layers.draw_line(a, b, REACTOR_LINK_COLOR, InternalRenderLayer::AreaOverlay);
+            }
+        }
+    }
+
+    let Some(font) = Font::load(used_mods, text::DEFAULT_FONT) else {
+        return;
+    };
+    let text_size = (16.0 / render_layers.scale()) as f32;
+
+    for (number, others) in &neighbours {
+        let Some((position, name)) = positions.get(number) else {
+            continue;
+        };
+
+        let bonus = data
+            .get_proto::<ReactorPrototype>(*name)
+            .map_or(1.0, |proto| proto.neighbour_bonus);
+
+        render_layers.add(
+            (
+                font.render(
+                    &format!("+{}%", (others.len() as f64 * bonus * 100.0).round()),
+                    text_size,
+                    SIGNAL_COUNT_COLOR,
+                )
+                .into(),
+                Vector::Tuple(0.0, 0.0),
+            ),
+            position,
+            InternalRenderLayer::IconOverlay,
+        );
+    }
+}
+
+/// The indication arrow rotated to point in `direction`.
+fn rotated_arrow(arrow: &DynamicImage, direction: Direction) -> Option<DynamicImage> {
+    match direction {
+        Direction::North => Some(arrow.clone()),
+        Direction::East => Some(imageops::rotate90(arrow).into()),
+        Direction::South => Some(imageops::rotate180(arrow).into()),
+        Direction::West => Some(imageops::rotate270(arrow).into()),
+        _ => None,
+    }
+}
+
+/// Draws the `indicator_arrow` on every transport belt, underground belt exit and on both
+/// outputs of splitters so the direction of belts is visible at small scales.
+pub fn draw_belt_directions(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    (arrow, arrow_shift): &GraphicsOutput,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    const SPLITTER_OUTPUTS: [Vector; 2] = [Vector::Tuple(-0.5, 0.0), Vector::Tuple(0.5, 0.0)];
+    const CENTER: [Vector; 1] = [Vector::Tuple(0.0, 0.0)];
+
+    for e in &bp.entities {
+        let offsets: &[Vector] = match data.get_entity_type(&e.name) {
+            Some(EntityType::TransportBelt) => &CENTER,
+            Some(EntityType::UndergroundBelt)
+                if matches!(e.type_, Some(blueprint::UndergroundType::Output)) =>
+            {
+                &CENTER
+            }
+            Some(EntityType::Splitter) => &SPLITTER_OUTPUTS,
+            _ => continue,
+        };
+
+        let Some(arrow) = rotated_arrow(arrow, e.direction.eight_way()) else {
+            continue;
+        };
+
+        let position = MapPosition::from(&e.position);
+        for offset in offsets {
+            render_layers.add(
+                (
+                    arrow.clone(),
+                    e.direction.rotate_vector(*offset + *arrow_shift),
+                ),
+                &position,
+                InternalRenderLayer::DirectionOverlay,
+            );
+        }
+    }
+}
+
+enum CombinatorOperand<'a> {
+    Signal(&'a SignalID, Option<i32>),
+    Constant(i32),
+}
+
+fn combinator_operands(e: &blueprint::Entity) -> Vec<CombinatorOperand<'_>> {
+    use CombinatorOperand::{Constant, Signal};
+
+    let Some(behavior) = &e.control_behavior else {
+        return Vec::new();
+    };
+
+    if let Some(arithmetic) = &behavior.arithmetic_conditions {
+        return match arithmetic {
+            ArithmeticData::SignalSignal {
+                first_signal,
+                second_signal,
+                ..
+            } => [first_signal, second_signal]
+                .into_iter()
+                .flatten()
+                .map(|s| Signal(s, None))
+                .collect(),
+            ArithmeticData::SignalConstant {
+                first_signal,
+                second_constant,
+                ..
+            } => first_signal
+                .iter()
+                .map(|s| Signal(s, None))
+                .chain([Constant(*second_constant)])
+                .collect(),
+            ArithmeticData::ConstantSignal {
+                first_constant,
+                second_signal,
+                ..
+            } => std::iter::once(Constant(*first_constant))
+                .chain(second_signal.iter().map(|s| Signal(s, None)))
+                .collect(),
+            ArithmeticData::ConstantConstant {
+                first_constant,
+                second_constant,
+                ..
+            } => vec![Constant(*first_constant), Constant(*second_constant)],
+        };
+    }
+
+    if let Some(decider) = &behavior.decider_conditions {
+        return match decider {
+            DeciderData::Conditions { conditions, .. } => {
+                conditions.first().map_or_else(Vec::new, |condition| {
+                    condition
+                        .first_signal
+                        .iter()
+                        .map(|s| Signal(s, None))
+                        .chain([condition
+                            .second_signal
+                            .as_ref()
+                            .map_or_else(|| Constant(condition.constant), |s| Signal(s, None))])
+                        .collect()
+                })
+            }
+            DeciderData::Signal {
+                first_signal,
+                second_signal,
+                ..
+            } => [first_signal, second_signal]
+                .into_iter()
+                .flatten()
+                .map(|s| Signal(s, None))
+                .collect(),
+            DeciderData::Constant {
+                first_signal,
+                constant,
+                ..
+            } => first_signal
+                .iter()
+                .map(|s| Signal(s, None))
+                .chain([Constant(*constant)])
+                .collect(),
+        };
+    }
+
+    behavior
+        .filters
+        .iter()
+        .take(MAX_CONSTANT_SIGNALS)
+        .map(|f| Signal(&f.signal, Some(f.count)))
+        .collect()
+}
+
+/// Draws the first [`MAX_CONSTANT_SIGNALS`] signals of constant combinators and the operands
+/// of arithmetic & decider combinators in a row above the entity.
+///
+/// Counts & constants are drawn with the core font and left out if it can't be loaded.
+pub fn draw_combinator_contents(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    const SPACING: f64 = 0.5;
+
+    let font = Font::load(used_mods, text::DEFAULT_FONT);
+    let icon_scale = render_layers.scale() * 2.2;
+    let text_size = (10.0 / render_layers.scale()) as f32;
+
+    for e in &bp.entities {
+        let operands = combinator_operands(e);
+        if operands.is_empty() {
+            continue;
+        }
+
+        let position = MapPosition::from(&e.position);
+        let top = bp_helper::entity_selection_area(e, data)
+            .map_or_else(|| position.y() - 0.5, |area| area.top_left().y());
+        let start = position
+            + MapPosition::Tuple(
+                (operands.len() - 1) as f64 * SPACING / -2.0,
+                top - position.y() - SPACING / 2.0,
+            );
+
+        for (idx, operand) in operands.into_iter().enumerate() {
+            let slot = start + MapPosition::Tuple(idx as f64 * SPACING, 0.0);
+
+            let label = match operand {
+                CombinatorOperand::Signal(signal, count) => {
+                    if let Some(icon) =
+                        bp_helper::signal_icon(signal, data, icon_scale, used_mods, image_cache)
+                    {
+                        render_layers.add(icon, &slot, InternalRenderLayer::IconOverlay);
+                    }
+
+                    count.map(|count| (text::format_count(count.into()), Vector::Tuple(0.1, 0.15)))
+                }
+                CombinatorOperand::Constant(constant) => {
+                    Some((text::format_count(constant.into()), Vector::Tuple(0.0, 0.0)))
+                }
+            };
+
+            let (Some(font), Some((label, shift))) = (&font, label) else {
+                continue;
+            };
+
+            render_layers.add(
+                (
+                    font.render(&label, text_size, SIGNAL_COUNT_COLOR).into(),
+                    shift,
+                ),
+                &slot,
+                InternalRenderLayer::IconOverlay,
+            );
+        }
+    }
+}
+
+/// Draws the signals of the first condition of every decider combinator below the entity,
+/// followed by the total number of conditions if there is more than one.
+///
+/// The condition count is left out if the core font can't be loaded.
+pub fn draw_decider_conditions(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    const SPACING: f64 = 0.5;
+
+    let font = Font::load(used_mods, text::DEFAULT_FONT);
+    let icon_scale = render_layers.scale() * 2.2;
+    let text_size = (10.0 / render_layers.scale()) as f32;
+
+    for e in &bp.entities {
+        let Some(decider) = e
+            .control_behavior
+            .as_ref()
+            .and_then(|bhv| bhv.decider_conditions.as_ref())
+        else {
+            continue;
+        };
+
+        let signals = <[_; 2]>::from(decider.first_condition_signals())
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let count = decider.condition_count();
+        let slots = signals.len() + usize::from(count > 1);
+        if slots == 0 {
+            continue;
+        }
+
+        let position = MapPosition::from(&e.position);
+        let bottom = bp_helper::entity_selection_area(e, data)
+            .map_or_else(|| position.y() + 0.5, |area| area.bottom_right().y());
+        let start = position
+            + MapPosition::Tuple(
+                (slots - 1) as f64 * SPACING / -2.0,
+                bottom - position.y() + SPACING / 2.0,
+            );
+
+        for (idx, signal) in signals.iter().enumerate() {
+            let slot = start + MapPosition::Tuple(idx as f64 * SPACING, 0.0);
+            if let Some(icon) =
+                bp_helper::signal_icon(signal, data, icon_scale, used_mods, image_cache)
+            {
+                render_layers.add(icon, &slot, InternalRenderLayer::IconOverlay);
+            }
+        }
+
+        let Some(font) = font.as_ref().filter(|_| count > 1) else {
+            continue;
+        };
+
+        render_layers.add(
+            (
+                font.render(&format!("×{count}"), text_size, SIGNAL_COUNT_COLOR)
+                    .into(),
+                Vector::Tuple(0.0, 0.0),
+            ),
+            &(start + MapPosition::Tuple(signals.len() as f64 * SPACING, 0.0)),
+            InternalRenderLayer::IconOverlay,
+        );
+    }
+}
+
+struct TurretRange {
+    position: MapPosition,
+    orientation: RealOrientation,
+    sweep: f64,
+    range: f64,
+    min_range: f64,
+}
+
+impl TurretRange {
+    fn new(e: &blueprint::Entity, attack: &AttackParameters) -> Option<Self> {
+        if attack.range <= 0.0 {
+            return None;
+        }
+
+        // the turn range is centered on the direction the turret is facing
+        let sweep = f64::from(attack.turn_range).clamp(0.0, 1.0);
+        let orientation = (*e.direction.eight_way().to_orientation() - sweep / 2.0).rem_euclid(1.0);
+
+        Some(Self {
+            position: (&e.position).into(),
+            orientation: orientation.into(),
+            sweep,
+            range: attack.range.into(),
+            min_range: attack.min_range.into(),
+        })
+    }
+}
+
+fn collect_turret_ranges(bp: &blueprint::Blueprint, data: &DataUtil) -> Vec<TurretRange> {
+    let gun = |id: &ItemID| {
+        data.get_proto::<GunPrototype>(id)
+            .map(|gun| &gun.attack_parameters)
+    };
+
+    bp.entities
+        .iter()
+        .filter_map(|e| {
+            let attack = if let Some(proto) = data.get_proto::<TurretPrototype>(&e.name) {
+                &proto.attack_parameters
+            } else if let Some(proto) = data.get_proto::<AmmoTurretPrototype>(&e.name) {
+                &proto.attack_parameters
+            } else if let Some(proto) = data.get_proto::<ElectricTurretPrototype>(&e.name) {
+                &proto.attack_parameters
+            } else if let Some(proto) = data.get_proto::<FluidTurretPrototype>(&e.name) {
+                &proto.attack_parameters
+            } else if let Some(proto) = data.get_proto::<ArtilleryTurretPrototype>(&e.name) {
+                gun(&proto.gun)?
+            } else if let Some(proto) = data.get_proto::<ArtilleryWagonPrototype>(&e.name) {
+                gun(&proto.gun)?
+            } else {
+                return None;
+            };
+
+            TurretRange::new(e, attack)
+        })
+        .collect()
+}
+
+/// Shades the attack range of all turrets, artillery turrets & artillery wagons.
+///
+/// Turrets with a limited turn range only cover the sector they can aim at
+/// and the minimum range of artillery is drawn as an additional ring.
+pub fn draw_turret_range(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let turrets = collect_turret_ranges(bp, data);
+
+    for turret in &turrets {
+        render_layers.fill_sector(
+            &turret.position,
+            turret.range,
+            turret.orientation,
+            turret.sweep,
+            TURRET_RANGE_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+
+    for turret in &turrets {
+        render_layers.draw_arc(
+            &turret.position,
+            turret.range,
+            turret.orientation,
+            turret.sweep,
+            TURRET_RANGE_EDGE_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+
+        if turret.min_range > 0.0 {
+            render_layers.draw_arc(
+                &turret.position,
+                turret.min_range,
+                turret.orientation,
+                turret.sweep,
+                MIN_RANGE_EDGE_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+    }
+}
+
+struct ArtilleryRange {
+    position: MapPosition,
+    range: f64,
+    min_range: f64,
+    manual_range: f64,
+    automatic: bool,
+}
+
+fn collect_artillery_ranges(bp: &blueprint::Blueprint, data: &DataUtil) -> Vec<ArtilleryRange> {
+    bp.entities
+        .iter()
+        .filter_map(|e| {
+            let (gun, manual_range_modifier, disable_automatic_firing) =
+                if let Some(proto) = data.get_proto::<ArtilleryTurretPrototype>(&e.name) {
+                    (
+                        &proto.gun,
+                        proto.manual_range_modifier,
+                        proto.disable_automatic_firing,
+                    )
+                } else if let Some(proto) = data.get_proto::<ArtilleryWagonPrototype>(&e.name) {
+                    (
+                        &proto.gun,
+                        proto.manual_range_modifier,
+                        proto.disable_automatic_firing,
+                    )
+                } else {
+                    return None;
+                };
+
+            let attack = &data.get_proto::<GunPrototype>(gun)?.attack_parameters;
+            let range = f64::from(attack.range);
+            if range <= 0.0 {
+                return None;
+            }
+
+            Some(ArtilleryRange {
+                position: (&e.position).into(),
+                range,
+                min_range: attack.min_range.into(),
+                manual_range: range * manual_range_modifier.max(1.0),
+                automatic: !disable_automatic_firing,
+            })
+        })
+        .collect()
+}
+
+/// Area covered by the manual range of all artillery in the blueprint, to fit [`draw_artillery_range`] into the render.
+#[must_use]
+pub fn artillery_range_area(bp: &blueprint::Blueprint, data: &DataUtil) -> Option<BoundingBox> {
+    collect_artillery_ranges(bp, data)
+        .iter()
+        .map(|artillery| square_area(&artillery.position, artillery.manual_range))
+        .reduce(|a, b| a.union(&b))
+}
+
+/// Shades the automatic firing range of artillery turrets & wagons and outlines their manual range.
+///
+/// Artillery with automatic firing disabled only gets the manual range, the minimum range
+/// is drawn as an additional ring. Research bonuses are not included.
+pub fn draw_artillery_range(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let artillery = collect_artillery_ranges(bp, data);
+    let full_circle = RealOrientation::from(0.0);
+
+    for range in artillery.iter().filter(|range| range.automatic) {
+        render_layers.fill_sector(
+            &range.position,
+            range.range,
+            full_circle,
+            1.0,
+            ARTILLERY_RANGE_COLOR,
+            InternalRenderLayer::AreaOverlay,
+        );
+    }
+
+    for range in &artillery {
+        let mut rings = vec![(range.manual_range, MANUAL_RANGE_EDGE_COLOR)];
+        if range.automatic {
+            rings.push((range.range, TURRET_RANGE_EDGE_COLOR));
+        }
+        if range.min_range > 0.0 {
+            rings.push((range.min_range, MIN_RANGE_EDGE_COLOR));
+        }
+
+        for (radius, color) in rings {
+            render_layers.draw_arc(
+                &range.position,
+                radius,
+                full_circle,
+                1.0,
+                color,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+    }
+}
+
+/// Label of the parameter at `idx` of [`blueprint::BlueprintData::parameters`], as shown in the render.
+#[must_use]
+pub fn parameter_label(idx: usize) -> String {
+    format!("P{}", idx + 1)
+}
+
+/// Outlines all entities that use a blueprint parameter and labels them with the
+/// [`parameter_label`]s of the parameters they use.
+///
+/// The labels are drawn with the core font and left out if it can't be loaded.
+pub fn draw_parameters(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    let mut labels = BTreeMap::<EntityNumber, Vec<String>>::new();
+    for (idx, users) in bp.parameter_users().into_iter().enumerate() {
+        for number in users {
+            labels.entry(number).or_default().push(parameter_label(idx));
+        }
+    }
+
+    if labels.is_empty() {
+        return;
+    }
+
+    let font = Font::load(used_mods, text::DEFAULT_FONT);
+    let text_size = (12.0 / render_layers.scale()) as f32;
+
+    for e in &bp.entities {
+        let Some(labels) = labels.get(&e.entity_number) else {
+            continue;
+        };
+
+        if let Some(area) = bp_helper::entity_area(e, data) {
+            render_layers.fill_area(
+                &area,
+                PARAMETER_AREA_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+            render_layers.outline_area(
+                &area,
+                PARAMETER_EDGE_COLOR,
+                InternalRenderLayer::AreaOverlay,
+            );
+        }
+
+        let Some(font) = &font else {
+            continue;
+        };
+
+        render_layers.add(
+            (
+                font.render(&labels.join(" "), text_size, SIGNAL_COUNT_COLOR)
+                    .into(),
+                Vector::Tuple(0.0, 0.0),
+            ),
+            &MapPosition::from(&e.position),
+            InternalRenderLayer::IconOverlay,
+        );
+    }
+}
+
+/// Outlines all entities above normal quality in the color of their quality prototype and
+/// badges them with its icon in the bottom left corner, like the game does.
+///
+/// Qualities that are missing from the prototypes are skipped.
+pub fn draw_quality(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    render_layers: &mut RenderLayerBuffer,
+) {
+    const BADGE_SIZE: f64 = 0.5;
+
+    let icon_scale = render_layers.scale() * 2.2;
+
+    for e in &bp.entities {
+        let Some(quality) = e.quality.as_ref().and_then(|q| data.get_quality(q)) else {
+            continue;
+        };
+
+        if quality.level == 0 {
+            continue;
+        }
+
+        let Some(area) = bp_helper::entity_selection_area(e, data) else {
+            continue;
+        };
+
+        let [r, g, b, _] = quality.color.to_rgba().map(|c| (c * 255.0).round() as u8);
+        render_layers.outline_area(
+            &area,
+            Rgba([r, g, b, QUALITY_EDGE_ALPHA]),
+            InternalRenderLayer::AreaOverlay,
+        );
+
+        if let Some(icon) = data.get_quality_icon(&quality.name, icon_scale, used_mods, image_cache)
+        {
+            let badge = MapPosition::Tuple(
+                area.left() + BADGE_SIZE / 2.0,
+                area.bottom() - BADGE_SIZE / 2.0,
+            );
+            render_layers.add(icon, &badge, InternalRenderLayer::IconOverlay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use image::GenericImageView;
+    use prototypes::TargetSize;
+    use types::EntityID;
+
+    use super::*;
+    use crate::test_util::{base_data, blueprint};
+
+    /// Draws an overlay of `bp` onto layers covering the area between `top_left` and
+    /// `bottom_right` at `tile_res` pixels per tile.
+    fn draw(
+        bp: &blueprint::Blueprint,
+        overlay: fn(&blueprint::Blueprint, &DataUtil, &mut RenderLayerBuffer),
+        top_left: (f64, f64),
+        bottom_right: (f64, f64),
+        tile_res: f64,
+    ) -> RenderLayerBuffer {
+        let width = ((bottom_right.0 - top_left.0) * tile_res) as u32;
+        let height = ((bottom_right.1 - top_left.1) * tile_res) as u32;
+
+        let mut layers = RenderLayerBuffer::new(TargetSize::new(
+            width,
+            height,
+            32.0 / tile_res,
+            MapPosition::Tuple(top_left.0, top_left.1),
+            MapPosition::Tuple(bottom_right.0, bottom_right.1),
+        ));
+        overlay(bp, base_data(), &mut layers);
+
+        layers
+    }
+
+    fn pixel(layers: &RenderLayerBuffer, layer: InternalRenderLayer, x: f64, y: f64) -> Rgba<u8> {
+        let (px, py) = layers.target_size().map_to_pixel(&MapPosition::Tuple(x, y));
+
+        layers
+            .layer(layer)
+            .map_or(Rgba([0; 4]), |img| img.get_pixel(px as u32, py as u32))
+    }
+
+    #[test]
+    fn interrupted_undergrounds_pair_with_the_closest() {
+        // input, input, output, output, all facing east
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"underground-belt","position":{"x":0.5,"y":0.5},"direction":4,"type":"input"},
+            {"entity_number":2,"name":"underground-belt","position":{"x":2.5,"y":0.5},"direction":4,"type":"input"},
+            {"entity_number":3,"name":"underground-belt","position":{"x":4.5,"y":0.5},"direction":4,"type":"output"},
+            {"entity_number":4,"name":"underground-belt","position":{"x":6.5,"y":0.5},"direction":4,"type":"output"}"#,
+            "",
+        );
+
+        let pairs = bp_helper::underground_pairs(&bp, base_data());
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].source, pairs[0].target), (2, 3));
+        assert_eq!(pairs[0].steps, 2);
+
+        let layers = draw(&bp, draw_underground_pairs, (0.0, 0.0), (8.0, 1.0), 8.0);
+        let at = |x| pixel(&layers, InternalRenderLayer::UndergroundPairs, x, 0.5);
+
+        assert_eq!(at(3.5), BELT_PAIR_COLOR);
+        assert_eq!(at(1.5)[3], 0);
+        assert_eq!(at(5.5)[3], 0);
+    }
+
+    #[test]
+    fn entities_outside_supply_areas_are_unpowered() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"small-electric-pole","position":{"x":0.5,"y":0.5}},
+            {"entity_number":2,"name":"small-lamp","position":{"x":1.5,"y":0.5}},
+            {"entity_number":3,"name":"small-lamp","position":{"x":10.5,"y":10.5}}"#,
+            "",
+        );
+
+        let layers = draw(&bp, draw_power, (-4.0, -4.0), (12.0, 12.0), 32.0);
+        let at = |x, y| pixel(&layers, InternalRenderLayer::AreaOverlay, x, y);
+
+        assert_eq!(at(1.5, 0.5), SUPPLY_AREA_COLOR);
+        assert_eq!(at(10.5, 10.5), UNPOWERED_COLOR);
+        assert_eq!(at(6.5, 6.5)[3], 0);
+    }
+
+    #[test]
+    fn roboport_areas() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"roboport","position":{"x":2,"y":2}}"#,
+            "",
+        );
+        let proto = base_data()
+            .get_proto::<RoboportPrototype>(&EntityID::new("roboport"))
+            .unwrap();
+        let (logistic, construction) = (proto.logistics_radius, proto.construction_radius);
+        assert!(logistic + 1.0 < construction);
+
+        let extent = construction + 5.0;
+        let layers = draw(
+            &bp,
+            draw_roboport_coverage,
+            (2.0 - extent, 2.0 - extent),
+            (2.0 + extent, 2.0 + extent),
+            4.0,
+        );
+        let at = |offset: f64| pixel(&layers, InternalRenderLayer::AreaOverlay, 2.0 + offset, 2.0);
+
+        // the smaller logistic area is shaded on top of the construction area
+        assert_eq!(at(0.0), LOGISTIC_AREA_COLOR);
+        assert_eq!(at(logistic - 0.5), LOGISTIC_AREA_COLOR);
+        assert_eq!(at(logistic + 0.5), CONSTRUCTION_AREA_COLOR);
+        assert_eq!(at(-construction + 0.5), CONSTRUCTION_AREA_COLOR);
+        assert_eq!(at(construction + 2.0)[3], 0);
+    }
+}
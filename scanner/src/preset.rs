@@ -1,146 +1,266 @@
-use clap::builder::PossibleValue;
-use strum::{EnumIter, VariantArray};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Context, Result, ResultExt};
+use serde::Deserialize;
+use tracing::{debug, error};
 
 use mod_util::{
+    dlc::Dlc,
     mod_info::{DependencyVersion, Version},
-    DependencyList,
+    AnyBasic, DependencyList,
 };
 
-#[allow(clippy::module_name_repetitions)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, VariantArray)]
-pub enum Preset {
-    K2,
-    SE,
-    K2SE,
-    IR3,
-    PyAE,
-    FF,
-    FFK2,
-    EI,
-    EIK2,
-    Nullius,
-    SeaBlock,
-    Ultracube,
+#[derive(Debug)]
+pub enum PresetError {
+    Io(PathBuf),
+    Parse(PathBuf),
 }
 
-macro_rules! mod_p {
-    ( $( $name:literal $ver:literal:$ver2:literal:$ver3:literal ),* ) => {
-        vec![
-            $(
-                ($name.to_owned(), DependencyVersion::HigherOrEqual(Version::new($ver, $ver2, $ver3))),
-            )+
-        ]
-    };
+impl Context for PresetError {}
+
+impl std::fmt::Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path) => write!(f, "failed to read preset file {}", path.display()),
+            Self::Parse(path) => write!(f, "invalid preset file {}", path.display()),
+        }
+    }
+}
+
+/// Preset files shipped with the scanner, see `scanner/presets`.
+const BUNDLED: [(&str, &str); 9] = [
+    (
+        "krastorio2.toml",
+        include_str!("../presets/krastorio2.toml"),
+    ),
+    (
+        "space_exploration.toml",
+        include_str!("../presets/space_exploration.toml"),
+    ),
+    (
+        "industrial_revolution.toml",
+        include_str!("../presets/industrial_revolution.toml"),
+    ),
+    ("pyanodons.toml", include_str!("../presets/pyanodons.toml")),
+    (
+        "freight_forwarding.toml",
+        include_str!("../presets/freight_forwarding.toml"),
+    ),
+    (
+        "exotic_industries.toml",
+        include_str!("../presets/exotic_industries.toml"),
+    ),
+    ("nullius.toml", include_str!("../presets/nullius.toml")),
+    ("seablock.toml", include_str!("../presets/seablock.toml")),
+    ("ultracube.toml", include_str!("../presets/ultracube.toml")),
+];
+
+/// Mods & startup settings of a modpack.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub name: String,
+
+    /// Alternative names, matched case insensitive like the name.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+
+    /// Prefix of the prototype names of the modpack, used to detect it in blueprints without mod info.
+    pub prefix: Option<String>,
+
+    /// DLCs to enable, unless `--dlc` is set.
+    #[serde(default)]
+    pub dlc: Vec<Dlc>,
+
+    /// Minimum version of every mod of the preset.
+    #[serde(default)]
+    pub mods: BTreeMap<String, Version>,
+
+    /// Startup settings, overridden by the ones of the blueprint & `--setting`.
+    #[serde(default)]
+    pub settings: BTreeMap<String, AnyBasic>,
 }
 
 impl Preset {
     #[must_use]
-    pub fn used_mods(self) -> DependencyList {
-        match self {
-            Self::K2 => mod_p!["Krastorio2" 1:3:23],
-            Self::SE => mod_p!["space-exploration" 0:6:130],
-            Self::K2SE => mod_p![
-                "Krastorio2" 1:3:23,
-                "space-exploration" 0:6:130
-            ],
-            Self::IR3 => mod_p!["IndustrialRevolution3" 3:1:20],
-            Self::PyAE => mod_p!["pyalternativeenergy" 1:2:25],
-            Self::FF => mod_p!["FreightForwardingPack" 1:2:1],
-            Self::FFK2 => mod_p![
-                "FreightForwardingPack" 1:2:1,
-                "Krastorio2" 1:3:23
-            ],
-            Self::EI => mod_p!["exotic-industries-modpack" 0:5:10],
-            Self::EIK2 => mod_p![
-                "exotic-industries-modpack" 0:5:10,
-                "Krastorio2" 1:3:23
-            ],
-            Self::Nullius => mod_p!["nullius" 1:9:1],
-            Self::SeaBlock => mod_p!["SeaBlockMetaPack" 1:1:4],
-            Self::Ultracube => mod_p!["Ultracube" 0:5:4],
-        }
-        .iter()
-        .cloned()
-        .collect()
+    pub fn used_mods(&self) -> DependencyList {
+        self.mods
+            .iter()
+            .map(|(name, version)| (name.clone(), DependencyVersion::HigherOrEqual(*version)))
+            .collect()
     }
 
-    // TODO: used settings
+    /// Whether `name` is the name or one of the aliases of the preset.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Content of a preset file, any number of `[[preset]]` tables.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetFile {
+    #[serde(default)]
+    preset: Vec<Preset>,
+}
+
+/// All known presets, the bundled ones & the ones loaded from user files.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Default)]
+pub struct Presets {
+    presets: Vec<Preset>,
+}
 
+impl Presets {
+    /// The presets shipped with the scanner.
     #[must_use]
-    pub const fn known_prefix(self) -> Option<&'static str> {
-        #[allow(clippy::match_same_arms)]
-        match self {
-            Self::K2 => Some("kr-"),
-            Self::SE => Some("se-"),
-            Self::K2SE => None,
-            Self::IR3 => None,
-            Self::PyAE => Some("py-"),
-            Self::FF => Some("ff-"),
-            Self::FFK2 => None,
-            Self::EI => Some("ei_"),
-            Self::EIK2 => None,
-            Self::Nullius => Some("nullius-"),
-            Self::SeaBlock => None,
-            Self::Ultracube => Some("cube-"),
+    pub fn bundled() -> Self {
+        let mut res = Self::default();
+
+        for (name, content) in BUNDLED {
+            match toml::from_str::<PresetFile>(content) {
+                Ok(file) => file.preset.into_iter().for_each(|p| res.add(p)),
+                Err(err) => error!("invalid bundled preset file {name}: {err}"),
+            }
         }
+
+        res
     }
-}
 
-impl std::fmt::Display for Preset {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+    /// Loads all presets of a TOML file. Presets with the name of an already known one replace it.
+    /// Returns the number of loaded presets.
+    pub fn load_file(&mut self, path: &Path) -> Result<usize, PresetError> {
+        let content =
+            std::fs::read_to_string(path).change_context(PresetError::Io(path.to_path_buf()))?;
+        let file = toml::from_str::<PresetFile>(&content)
+            .change_context(PresetError::Parse(path.to_path_buf()))?;
+
+        let count = file.preset.len();
+        for preset in file.preset {
+            debug!("loaded preset {} from {}", preset.name, path.display());
+            self.add(preset);
+        }
+
+        Ok(count)
     }
-}
 
-impl std::str::FromStr for Preset {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "k2" => Ok(Self::K2),
-            "se" => Ok(Self::SE),
-            "k2se" | "k2+se" | "sek2" | "se+k2" => Ok(Self::K2SE),
-            "ir3" => Ok(Self::IR3),
-            "pyae" | "pyanodons" => Ok(Self::PyAE),
-            "ff" | "freightforwarding" => Ok(Self::FF),
-            "ffk2" => Ok(Self::FFK2),
-            "ei" | "exoticindustries" => Ok(Self::EI),
-            "eik2" | "ei+k2" | "k2ei" | "k2+ei" => Ok(Self::EIK2),
-            "nullius" => Ok(Self::Nullius),
-            "seablock" | "sb" => Ok(Self::SeaBlock),
-            "ultracube" => Ok(Self::Ultracube),
-            _ => Err(format!("unknown preset: {s}")),
+    pub fn add(&mut self, preset: Preset) {
+        if let Some(existing) = self
+            .presets
+            .iter_mut()
+            .find(|p| p.name.eq_ignore_ascii_case(&preset.name))
+        {
+            *existing = preset;
+        } else {
+            self.presets.push(preset);
         }
     }
+
+    /// The preset with the given name or alias.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|p| p.matches(name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.presets.iter()
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<&str> {
+        self.presets.iter().map(|p| p.name.as_str()).collect()
+    }
 }
 
-impl clap::ValueEnum for Preset {
-    fn value_variants<'a>() -> &'a [Self] {
-        Self::VARIANTS
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn bundled_files_parse() {
+        for (name, content) in BUNDLED {
+            let file = toml::from_str::<PresetFile>(content)
+                .unwrap_or_else(|err| panic!("invalid bundled preset file {name}: {err}"));
+            assert!(!file.preset.is_empty(), "{name} has no presets");
+        }
+
+        assert_eq!(Presets::bundled().names().len(), 12);
     }
 
-    fn to_possible_value(&self) -> Option<PossibleValue> {
-        match self {
-            Self::K2 => Some(PossibleValue::new("K2").alias("k2")),
-            Self::SE => Some(PossibleValue::new("SE").alias("se")),
-            Self::K2SE => Some(
-                PossibleValue::new("K2SE")
-                    .aliases(["k2se", "K2+SE", "k2+se", "SEK2", "sek2", "SE+K2", "se+k2"]),
+    #[test]
+    fn old_names_resolve() {
+        let presets = Presets::bundled();
+        let cases: [(&[&str], &str, &[&str]); 12] = [
+            (&["K2", "k2"], "K2", &["Krastorio2"]),
+            (&["SE", "se"], "SE", &["space-exploration"]),
+            (
+                &[
+                    "K2SE", "k2se", "K2+SE", "k2+se", "SEK2", "sek2", "SE+K2", "se+k2",
+                ],
+                "K2SE",
+                &["Krastorio2", "space-exploration"],
+            ),
+            (&["IR3", "ir3"], "IR3", &["IndustrialRevolution3"]),
+            (
+                &["PyAE", "pyae", "pyanodons"],
+                "PyAE",
+                &["pyalternativeenergy"],
+            ),
+            (
+                &["FF", "ff", "FreightForwarding", "freightforwarding"],
+                "FF",
+                &["FreightForwardingPack"],
             ),
-            Self::IR3 => Some(PossibleValue::new("IR3").alias("ir3")),
-            Self::PyAE => Some(PossibleValue::new("PyAE").aliases(["pyae", "pyanodons"])),
-            Self::FF => Some(PossibleValue::new("FF").aliases(["ff", "FreightForwarding"])),
-            Self::FFK2 => Some(PossibleValue::new("FFK2").aliases(["ffk2", "FF+K2", "ff+k2"])),
-            Self::EI => Some(PossibleValue::new("EI").aliases(["ei", "exoticindustries"])),
-            Self::EIK2 => Some(
-                PossibleValue::new("EIK2")
-                    .aliases(["eik2", "EI+K2", "ei+k2", "K2EI", "k2ei", "K2+EI", "k2+ei"]),
+            (
+                &["FFK2", "ffk2", "FF+K2", "ff+k2"],
+                "FFK2",
+                &["FreightForwardingPack", "Krastorio2"],
             ),
-            Self::Nullius => Some(PossibleValue::new("Nullius").alias("nullius")),
-            Self::SeaBlock => {
-                Some(PossibleValue::new("SeaBlock").aliases(["seablock", "SB", "sb"]))
+            (
+                &["EI", "ei", "exoticindustries"],
+                "EI",
+                &["exotic-industries-modpack"],
+            ),
+            (
+                &[
+                    "EIK2", "eik2", "EI+K2", "ei+k2", "K2EI", "k2ei", "K2+EI", "k2+ei",
+                ],
+                "EIK2",
+                &["exotic-industries-modpack", "Krastorio2"],
+            ),
+            (&["Nullius", "nullius"], "Nullius", &["nullius"]),
+            (
+                &["SeaBlock", "seablock", "SB", "sb"],
+                "SeaBlock",
+                &["SeaBlockMetaPack"],
+            ),
+            (&["Ultracube", "ultracube"], "Ultracube", &["Ultracube"]),
+        ];
+
+        for (names, expected, mods) in cases {
+            for name in names {
+                let preset = presets
+                    .get(name)
+                    .unwrap_or_else(|| panic!("{name} is not known"));
+                assert_eq!(preset.name, expected, "{name}");
+
+                let used = preset.used_mods();
+                for m in mods {
+                    assert!(used.contains_key(*m), "{expected} is missing {m}");
+                }
             }
-            Self::Ultracube => Some(PossibleValue::new("Ultracube").alias("ultracube")),
         }
     }
+
+    #[test]
+    fn unknown_name() {
+        assert!(Presets::bundled().get("k2+se+ei").is_none());
+    }
 }
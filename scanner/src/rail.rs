@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use prototypes::{
+    entity::{
+        CurvedRailPrototype, RailChainSignalPrototype, RailSignalPrototype, StraightRailPrototype,
+    },
+    DataUtil, DataUtilAccess,
+};
+use types::{BoundingBox, Direction, MapPosition, Vector};
+
+use crate::bp_helper::{grid_key, GridKey};
+
+/// Number of straight segments used to approximate a curved rail.
+const CURVE_SEGMENTS: u32 = 8;
+
+/// Maximum distance between a signal and the rail joint it belongs to.
+const SIGNAL_JOINT_DISTANCE: f64 = 2.0;
+
+/// One end of a rail piece.
+#[derive(Debug, Clone)]
+pub struct RailEnd {
+    pub position: MapPosition,
+
+    /// Direction in which the rail leaves the piece at this end.
+    pub direction: Direction,
+}
+
+/// A single straight or curved rail entity.
+#[derive(Debug, Clone)]
+pub struct RailPiece {
+    pub entity_number: u64,
+    pub ends: [RailEnd; 2],
+
+    /// Center line of the rail, approximated by straight segments for curves.
+    pub path: Vec<MapPosition>,
+}
+
+/// A rail or chain signal that is attached to a rail joint.
+#[derive(Debug, Clone)]
+pub struct RailSignal {
+    pub entity_number: u64,
+    pub position: MapPosition,
+    pub chain: bool,
+
+    /// Normalized direction of travel of the trains this signal applies to.
+    pub travel_direction: Vector,
+
+    joint: GridKey,
+}
+
+fn unit_vector(direction: Direction) -> Vector {
+    let (x, y) = direction.get_offset().as_tuple();
+    let length = x.hypot(y);
+
+    Vector::new(x / length, y / length)
+}
+
+/// Endpoints relative to the rail position together with the direction the rail leaves
+/// through them, based on the 1.1 rail geometry.
+const fn straight_rail_ends(direction: Direction) -> [((f64, f64), Direction); 2] {
+    use Direction::{East, North, NorthEast, NorthWest, South, SouthEast, SouthWest, West};
+
+    match direction {
+        North | South => [((0.0, -1.0), North), ((0.0, 1.0), South)],
+        East | West => [((-1.0, 0.0), West), ((1.0, 0.0), East)],
+        NorthEast => [((0.0, -1.0), NorthWest), ((1.0, 0.0), SouthEast)],
+        SouthEast => [((1.0, 0.0), NorthEast), ((0.0, 1.0), SouthWest)],
+        SouthWest => [((0.0, 1.0), SouthEast), ((-1.0, 0.0), NorthWest)],
+        NorthWest => [((-1.0, 0.0), SouthWest), ((0.0, -1.0), NorthEast)],
+    }
+}
+
+/// The first end is the straight one, the second end is the diagonal one.
+const fn curved_rail_ends(direction: Direction) -> [((f64, f64), Direction); 2] {
+    use Direction::{East, North, NorthEast, NorthWest, South, SouthEast, SouthWest, West};
+
+    match direction {
+        North => [((-1.0, 4.0), South), ((2.0, -3.0), NorthEast)],
+        NorthEast => [((1.0, 4.0), South), ((-2.0, -3.0), NorthWest)],
+        East => [((-4.0, -1.0), West), ((3.0, 2.0), SouthEast)],
+        SouthEast => [((-4.0, 1.0), West), ((3.0, -2.0), NorthEast)],
+        South => [((1.0, -4.0), North), ((-2.0, 3.0), SouthWest)],
+        SouthWest => [((-1.0, -4.0), North), ((2.0, 3.0), SouthEast)],
+        West => [((4.0, 1.0), East), ((-3.0, -2.0), NorthWest)],
+        NorthWest => [((4.0, -1.0), East), ((-3.0, 2.0), SouthWest)],
+    }
+}
+
+/// Approximates the curve between both ends with a quadratic bezier whose control point
+/// is the intersection of the tangents at both ends.
+fn curve_path(start: &RailEnd, end: &RailEnd) -> Vec<MapPosition> {
+    const fn cross(a: &Vector, b: &Vector) -> f64 {
+        let (a_x, a_y) = a.as_tuple();
+        let (b_x, b_y) = b.as_tuple();
+
+        a_x * b_y - a_y * b_x
+    }
+
+    let p0: Vector = start.position.into();
+    let p2: Vector = end.position.into();
+
+    // tangents point into the rail
+    let start_tangent = unit_vector(start.direction) * -1.0;
+    let end_tangent = unit_vector(end.direction) * -1.0;
+
+    let denominator = cross(&start_tangent, &end_tangent);
+    if denominator.abs() < f64::EPSILON {
+        return vec![start.position, end.position];
+    }
+
+    let p1 = p0 + start_tangent * (cross(&(p2 - p0), &end_tangent) / denominator);
+
+    (0..=CURVE_SEGMENTS)
+        .map(|idx| {
+            let t = f64::from(idx) / f64::from(CURVE_SEGMENTS);
+
+            (p0 * ((1.0 - t) * (1.0 - t)) + p1 * (2.0 * (1.0 - t) * t) + p2 * (t * t)).into()
+        })
+        .collect()
+}
+
+/// Whether the segments `a` and `b` cross each other. Touching endpoints and collinear
+/// overlaps are not considered crossings.
+fn segments_cross(a: (&MapPosition, &MapPosition), b: (&MapPosition, &MapPosition)) -> bool {
+    fn orientation(p: &MapPosition, q: &MapPosition, r: &MapPosition) -> f64 {
+        let (p_x, p_y) = p.as_tuple();
+        let (q_x, q_y) = q.as_tuple();
+        let (r_x, r_y) = r.as_tuple();
+
+        (q_x - p_x).mul_add(r_y - p_y, -((q_y - p_y) * (r_x - p_x)))
+    }
+
+    const EPSILON: f64 = 1e-6;
+
+    let d1 = orientation(b.0, b.1, a.0);
+    let d2 = orientation(b.0, b.1, a.1);
+    let d3 = orientation(a.0, a.1, b.0);
+    let d4 = orientation(a.0, a.1, b.1);
+
+    ((d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON))
+        && ((d3 > EPSILON && d4 < -EPSILON) || (d3 < -EPSILON && d4 > EPSILON))
+}
+
+/// Minimal union-find used to merge rail pieces into blocks.
+pub(crate) struct DisjointSet(Vec<usize>);
+
+impl DisjointSet {
+    pub(crate) fn new(size: usize) -> Self {
+        Self((0..size).collect())
+    }
+
+    pub(crate) fn find(&mut self, idx: usize) -> usize {
+        let parent = self.0[idx];
+        if parent == idx {
+            return idx;
+        }
+
+        let root = self.find(parent);
+        self.0[idx] = root;
+        root
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a != b {
+            self.0[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+/// Connectivity graph of all rails & signals of a blueprint in blueprint coordinates.
+#[derive(Debug, Default)]
+pub struct RailGraph {
+    pieces: Vec<RailPiece>,
+    signals: Vec<RailSignal>,
+
+    /// All rail ends meeting at a joint as `(piece index, end index)`.
+    joints: HashMap<GridKey, Vec<(usize, usize)>>,
+}
+
+impl RailGraph {
+    #[must_use]
+    pub fn new(bp: &blueprint::Blueprint, data: &DataUtil) -> Self {
+        let mut graph = Self::default();
+
+        for e in &bp.entities {
+            let curved = if data.get_proto::<StraightRailPrototype>(&e.name).is_some() {
+                false
+            } else if data.get_proto::<CurvedRailPrototype>(&e.name).is_some() {
+                true
+            } else {
+                continue;
+            };
+
+            graph.add_rail(
+                e.entity_number,
+                MapPosition::from(&e.position),
+                e.direction.eight_way(),
+                curved,
+            );
+        }
+
+        for e in &bp.entities {
+            let chain = if data.get_proto::<RailSignalPrototype>(&e.name).is_some() {
+                false
+            } else if data
+                .get_proto::<RailChainSignalPrototype>(&e.name)
+                .is_some()
+            {
+                true
+            } else {
+                continue;
+            };
+
+            let position = MapPosition::from(&e.position);
+            if let Some(signal) = graph.attach_signal(e.entity_number, position, chain) {
+                graph.signals.push(signal);
+            }
+        }
+
+        graph
+    }
+
+    fn add_rail(
+        &mut self,
+        entity_number: u64,
+        position: MapPosition,
+        direction: Direction,
+        curved: bool,
+    ) {
+        let ends = if curved {
+            curved_rail_ends(direction)
+        } else {
+            straight_rail_ends(direction)
+        };
+
+        let ends = ends.map(|((x, y), direction)| RailEnd {
+            position: position + MapPosition::Tuple(x, y),
+            direction,
+        });
+
+        let path = if curved {
+            curve_path(&ends[0], &ends[1])
+        } else {
+            vec![ends[0].position, ends[1].position]
+        };
+
+        let idx = self.pieces.len();
+        for (end_idx, end) in ends.iter().enumerate() {
+            self.joints
+                .entry(grid_key(&end.position))
+                .or_default()
+                .push((idx, end_idx));
+        }
+
+        self.pieces.push(RailPiece {
+            entity_number,
+            ends,
+            path,
+        });
+    }
+
+    /// Finds the closest rail joint for a signal and derives its direction of travel,
+    /// trains pass signals on their right hand side.
+    fn attach_signal(
+        &self,
+        entity_number: u64,
+        position: MapPosition,
+        chain: bool,
+    ) -> Option<RailSignal> {
+        let (joint, (piece, end)) = self
+            .joints
+            .iter()
+            .filter_map(|(key, ends)| Some((*key, *ends.first()?)))
+            .map(|(key, end)| {
+                let joint_pos = &self.pieces[end.0].ends[end.1].position;
+                (position.distance_to(joint_pos), key, end)
+            })
+            .filter(|(distance, ..)| *distance <= SIGNAL_JOINT_DISTANCE)
+            .min_by(|(a, ..), (b, ..)| a.total_cmp(b))
+            .map(|(_, key, end)| (key, end))?;
+
+        let rail_end = &self.pieces[piece].ends[end];
+        let axis = unit_vector(rail_end.direction);
+        let (offset_x, offset_y) = (position - rail_end.position).as_tuple();
+        let (axis_x, axis_y) = axis.as_tuple();
+
+        // right hand side of (x, y) is (-y, x) with the y axis pointing down
+        let travel_direction = if offset_x.mul_add(-axis_y, offset_y * axis_x) >= 0.0 {
+            axis
+        } else {
+            axis * -1.0
+        };
+
+        Some(RailSignal {
+            entity_number,
+            position,
+            chain,
+            travel_direction,
+            joint,
+        })
+    }
+
+    #[must_use]
+    pub fn pieces(&self) -> &[RailPiece] {
+        &self.pieces
+    }
+
+    #[must_use]
+    pub fn signals(&self) -> &[RailSignal] {
+        &self.signals
+    }
+
+    /// Assigns every rail piece to a signal block. Pieces are part of the same block when
+    /// they are connected through a joint without a signal or when they cross each other.
+    /// The returned block ids are consecutive, starting at 0, in order of the pieces.
+    #[must_use]
+    pub fn blocks(&self) -> Vec<usize> {
+        let mut sets = DisjointSet::new(self.pieces.len());
+
+        for (key, ends) in &self.joints {
+            if self.signals.iter().any(|s| s.joint == *key) {
+                continue;
+            }
+
+            for (idx, (a_piece, a_end)) in ends.iter().enumerate() {
+                for (b_piece, b_end) in &ends[idx + 1..] {
+                    let a_dir = self.pieces[*a_piece].ends[*a_end].direction;
+                    let b_dir = self.pieces[*b_piece].ends[*b_end].direction;
+
+                    if a_dir.flip() == b_dir {
+                        sets.union(*a_piece, *b_piece);
+                    }
+                }
+            }
+        }
+
+        for (a_idx, b_idx) in self.crossing_candidates() {
+            let a = &self.pieces[a_idx].path;
+            let b = &self.pieces[b_idx].path;
+
+            let crosses = a.windows(2).any(|a_seg| {
+                b.windows(2)
+                    .any(|b_seg| segments_cross((&a_seg[0], &a_seg[1]), (&b_seg[0], &b_seg[1])))
+            });
+
+            if crosses {
+                sets.union(a_idx, b_idx);
+            }
+        }
+
+        let mut ids = HashMap::new();
+        (0..self.pieces.len())
+            .map(|idx| {
+                let root = sets.find(idx);
+                let next_id = ids.len();
+                *ids.entry(root).or_insert(next_id)
+            })
+            .collect()
+    }
+
+    /// Pairs of pieces that share a chunk of the map and could therefore cross.
+    fn crossing_candidates(&self) -> Vec<(usize, usize)> {
+        const CHUNK_SIZE: f64 = 8.0;
+
+        let mut chunks = HashMap::<(i32, i32), Vec<usize>>::new();
+        for (idx, piece) in self.pieces.iter().enumerate() {
+            let Some(bounds) = piece
+                .path
+                .iter()
+                .map(|p| BoundingBox(*p, *p))
+                .reduce(|a, b| a.union(&b))
+            else {
+                continue;
+            };
+
+            let chunk = |value: f64| (value / CHUNK_SIZE).floor() as i32;
+
+            for cx in chunk(bounds.left())..=chunk(bounds.right()) {
+                for cy in chunk(bounds.top())..=chunk(bounds.bottom()) {
+                    chunks.entry((cx, cy)).or_default().push(idx);
+                }
+            }
+        }
+
+        let mut candidates = chunks
+            .values()
+            .flat_map(|pieces| {
+                pieces.iter().enumerate().flat_map(move |(idx, a)| {
+                    pieces[idx + 1..]
+                        .iter()
+                        .map(move |b| (*a.min(b), *a.max(b)))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    /// North-south rails from y = 0 to y = 10 at x = 1 with a joint at every even y.
+    fn straight_line() -> RailGraph {
+        let mut graph = RailGraph::default();
+        for (idx, y) in [1.0, 3.0, 5.0, 7.0, 9.0].into_iter().enumerate() {
+            graph.add_rail(
+                idx as u64 + 1,
+                MapPosition::Tuple(1.0, y),
+                Direction::North,
+                false,
+            );
+        }
+
+        graph
+    }
+
+    #[test]
+    fn disjoint_set() {
+        let mut sets = DisjointSet::new(5);
+        sets.union(3, 4);
+        sets.union(1, 3);
+
+        assert_eq!(sets.find(4), 1);
+        assert_eq!(sets.find(3), sets.find(1));
+        assert_ne!(sets.find(0), sets.find(4));
+        assert_eq!(sets.find(2), 2);
+    }
+
+    #[test]
+    fn rails_without_signals_are_one_block() {
+        let graph = straight_line();
+
+        assert_eq!(graph.pieces().len(), 5);
+        assert_eq!(graph.blocks(), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn signals_attach_to_the_closest_joint() {
+        let graph = straight_line();
+
+        let right = graph
+            .attach_signal(10, MapPosition::Tuple(2.5, 4.0), false)
+            .unwrap();
+        assert_eq!(right.joint, grid_key(&MapPosition::Tuple(1.0, 4.0)));
+        assert_eq!(right.travel_direction.as_tuple(), (0.0, -1.0));
+
+        let left = graph
+            .attach_signal(11, MapPosition::Tuple(-0.5, 4.5), true)
+            .unwrap();
+        assert_eq!(left.joint, grid_key(&MapPosition::Tuple(1.0, 4.0)));
+        assert_eq!(left.travel_direction.as_tuple(), (0.0, 1.0));
+        assert!(left.chain);
+
+        assert!(graph
+            .attach_signal(12, MapPosition::Tuple(4.5, 4.0), false)
+            .is_none());
+    }
+
+    #[test]
+    fn signals_separate_blocks() {
+        let mut graph = straight_line();
+        let signal = graph
+            .attach_signal(10, MapPosition::Tuple(2.5, 4.0), false)
+            .unwrap();
+        graph.signals.push(signal);
+
+        assert_eq!(graph.blocks(), [0, 0, 1, 1, 1]);
+
+        // a crossing rail joins the block it crosses, even without a shared joint
+        graph.add_rail(20, MapPosition::Tuple(1.0, 9.0), Direction::East, false);
+        assert_eq!(graph.blocks(), [0, 0, 1, 1, 1, 1]);
+
+        let signal = graph
+            .attach_signal(11, MapPosition::Tuple(2.5, 6.0), true)
+            .unwrap();
+        graph.signals.push(signal);
+        assert_eq!(graph.blocks(), [0, 0, 1, 2, 2, 2]);
+    }
+}
@@ -0,0 +1,502 @@
+//! Resolving, downloading & enabling the mods of a blueprint and loading their prototypes.
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use error_stack::{ensure, report, Context, Result, ResultExt};
+use tracing::{debug, field, info, info_span, instrument, warn};
+
+use mod_util::{
+    dlc::{Dlc, FeatureFlags},
+    mod_info::{DependencyVersion, Version},
+    mod_list::ModList,
+    mod_loader::Mod,
+    AnyBasic, DependencyList, UsedMods, UsedVersions,
+};
+use prototypes::{DataRaw, DataUtil};
+
+use crate::{
+    bp_helper, dump_cache, get_protodump, lockfile, mod_cache, preset, startup_settings, telemetry,
+    DumpMethod, PlayerData, ScannerError,
+};
+
+/// Resolves, downloads & enables the mods needed for `bp`, see [`load_data`].
+///
+/// With a prototype dump (`with_dump`) and no Factorio installation only the mods folder is used.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+#[instrument(skip_all)]
+pub async fn load_mods(
+    bp: Option<&blueprint::Data>,
+    factorio_appdir: &Path,
+    factorio_userdir: &Path,
+    presets: &preset::Presets,
+    preset: Option<&preset::Preset>,
+    locked: Option<&lockfile::Lockfile>,
+    dlcs: &[Dlc],
+    mods: &[String],
+    mod_dirs: &[PathBuf],
+    with_dump: bool,
+    mod_cache: &mod_cache::ModCache,
+) -> Result<ModList, ScannerError> {
+    // planners have no blueprint but their filters still need the prototypes
+    let bp = bp.and_then(blueprint::Data::as_blueprint);
+
+    // the dump already contains every prototype, without a game the mods are only needed for their sprites
+    let dump_only = with_dump && !factorio_appdir.join("data/core").is_dir();
+    let mut mod_list = if dump_only {
+        info!("no Factorio installation found, only using the mods folder for sprites");
+        ModList::generate_from_mods_dir(factorio_userdir.join("mods"))
+    } else {
+        ModList::generate_custom(factorio_appdir.join("data"), factorio_userdir)
+    }
+    .change_context(ScannerError::SetupError)?;
+
+    // get used mods from the lockfile, preset or detect from BP meta info
+    let mut required_mods = std::iter::once((
+        "base".to_owned(),
+        DependencyVersion::Exact(prototypes::targeted_engine_version()),
+    ))
+    .collect::<HashMap<_, _>>();
+    if let Some(locked) = locked {
+        required_mods.extend(locked.required_mods());
+    } else if let Some(preset) = preset {
+        required_mods.extend(preset.used_mods());
+    } else if let Some(bp) = bp {
+        required_mods.extend(bp_helper::get_used_versions(bp, presets));
+    }
+    required_mods.extend(mods.iter().map(|m| (m.clone(), DependencyVersion::Any)));
+
+    // the explicitly selected DLCs replace the ones of the other sources
+    let dlcs = Dlc::with_required(if dlcs.is_empty() {
+        preset.map_or(&[], |preset| preset.dlc.as_slice())
+    } else {
+        dlcs
+    });
+    let mut missing_dlcs = Vec::new();
+    if !dlcs.is_empty() {
+        let available = mod_list.available_dlcs();
+        for missing in dlcs.iter().filter(|dlc| !available.contains(dlc)) {
+            if !dump_only {
+                return Err(report!(ScannerError::SetupError))
+                    .attach_printable(format!("DLC {missing} is not part of the installed game"));
+            }
+
+            // without a game it is reported together with the other missing mods
+            warn!("DLC {missing} is not part of the mods folder");
+            missing_dlcs.push(missing.mod_name());
+        }
+
+        required_mods.retain(|name, _| Dlc::from_mod_name(name).is_none());
+        required_mods.extend(
+            dlcs.iter()
+                .map(|dlc| (dlc.mod_name().to_owned(), DependencyVersion::Any)),
+        );
+    }
+
+    for dir in mod_dirs {
+        let (name, version) = mod_list
+            .add_mod_dir(dir)
+            .change_context(ScannerError::SetupError)?;
+
+        debug!("using {name} v{version} from {}", dir.display());
+        required_mods.insert(name, DependencyVersion::Exact(version));
+    }
+
+    debug!(
+        "required mods: {}",
+        required_mods
+            .iter()
+            .map(|(n, v)| format!("{n} {v}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    if dump_only {
+        // dependencies can't be resolved without the game, use whatever is available locally
+        required_mods.insert("core".to_owned(), DependencyVersion::Any);
+        let available = required_mods
+            .iter()
+            .filter_map(|(name, version)| {
+                let entry = mod_list.list.get(name)?;
+                let version = entry
+                    .versions
+                    .keys()
+                    .filter(|v| version.allows(**v))
+                    .max()?;
+
+                Some((name.clone(), *version))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut missing = required_mods
+            .keys()
+            .filter(|name| !available.contains_key(*name))
+            .map(String::as_str)
+            .chain(missing_dlcs)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            missing.dedup();
+            warn!(
+                "not in the mods folder, their sprites will be missing: {}",
+                missing.join(", ")
+            );
+        }
+
+        mod_list.enable_mods(&available);
+    } else if !required_mods.is_empty() {
+        debug!("checking mod dependencies");
+
+        let used_mods = resolve_mod_dependencies(&required_mods, &mut mod_list)
+            .await
+            .change_context(ScannerError::SetupError)?;
+
+        let missing = mod_list.enable_mods(&used_mods);
+        if missing.is_empty() {
+            debug!("all mods are already installed");
+        } else {
+            info!("downloading missing mods from mod portal");
+            download_mods(missing, &factorio_userdir.join("mods"), mod_cache)
+                .await
+                .change_context(ScannerError::SetupError)?;
+        }
+    }
+
+    let active_mods = mod_list.active_mods();
+    debug!(
+        "{} mods active: {:?}",
+        active_mods.len(),
+        active_mods.keys().collect::<Vec<_>>()
+    );
+
+    if !dlcs.is_empty() {
+        for dlc in mod_list.enabled_dlcs() {
+            if !dlcs.contains(&dlc) {
+                warn!("DLC {dlc} is required by one of the mods");
+            }
+        }
+    }
+    info!("feature flags: {}", FeatureFlags::from_mods(&active_mods));
+
+    Ok(mod_list)
+}
+
+/// Prepares the mods & loads the prototype data needed to render `bp`.
+///
+/// Without a blueprint only the preset, `mods` & `mod_dirs` are used and all prototypes are loaded.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+#[instrument(skip_all, fields(preset, mods))]
+pub async fn load_data(
+    bp: Option<&blueprint::Data>,
+    factorio_appdir: &Path,
+    factorio_userdir: &Path,
+    factorio_bin: &Path,
+    dump_method: DumpMethod,
+    presets: &preset::Presets,
+    preset: Option<&preset::Preset>,
+    locked: Option<&lockfile::Lockfile>,
+    dlcs: &[Dlc],
+    mods: &[String],
+    mod_dirs: &[PathBuf],
+    settings: &[(String, AnyBasic)],
+    prototype_dump: Option<PathBuf>,
+    lenient_dump: bool,
+    filter_prototypes: bool,
+    mod_cache: &mod_cache::ModCache,
+    dump_cache: &dump_cache::DumpCache,
+) -> Result<(DataUtil, UsedMods), ScannerError> {
+    let start = Instant::now();
+    let raw_bp = bp;
+    // planners have no blueprint but their filters still need the prototypes
+    let bp = bp.and_then(blueprint::Data::as_blueprint);
+
+    if raw_bp.is_some() {
+        info!("loaded BP");
+    }
+
+    let mod_list = load_mods(
+        raw_bp,
+        factorio_appdir,
+        factorio_userdir,
+        presets,
+        preset,
+        locked,
+        dlcs,
+        mods,
+        mod_dirs,
+        prototype_dump.is_some(),
+        mod_cache,
+    )
+    .await?;
+    let active_mods = mod_list.active_mods();
+
+    // only the prototypes used by the BP are needed to render it
+    let used_names = raw_bp
+        .filter(|_| filter_prototypes)
+        .map(bp_helper::get_used_names);
+
+    let data = if let Some(path) = prototype_dump {
+        if lenient_dump {
+            let (data, skipped) = if let Some(used_names) = &used_names {
+                let bytes = std::fs::read(&path).change_context(ScannerError::SetupError)?;
+                let filtered = DataRaw::filter_dump(&bytes, used_names)
+                    .change_context(ScannerError::SetupError)?;
+                DataRaw::load_lenient_from_bytes(&filtered)
+            } else {
+                DataRaw::load_lenient(&path)
+            }
+            .change_context(ScannerError::SetupError)?;
+
+            for prototype in &skipped {
+                warn!(
+                    "skipped prototype {}.{} at {}: {}",
+                    prototype.section, prototype.name, prototype.path, prototype.error
+                );
+            }
+
+            data
+        } else if let Some(used_names) = &used_names {
+            DataRaw::load_filtered(&path, used_names).change_context(ScannerError::SetupError)?
+        } else {
+            DataRaw::load(&path).change_context(ScannerError::SetupError)?
+        }
+    } else {
+        let startup_settings = startup_settings(bp, preset, settings);
+
+        get_protodump(
+            factorio_userdir,
+            factorio_bin,
+            dump_method,
+            &mod_list,
+            (
+                &startup_settings,
+                bp.map(|bp| bp.version)
+                    .or_else(|| raw_bp.map(blueprint::Data::version))
+                    .unwrap_or(blueprint::compat::VERSION_2_0),
+            ),
+            used_names.as_ref(),
+            dump_cache,
+        )?
+    };
+
+    info!("loaded prototype data");
+    let data = DataUtil::new(data);
+    telemetry::stage_finished(telemetry::Stage::Setup, start);
+
+    Ok((data, active_mods))
+}
+
+#[derive(Debug)]
+pub struct DependencyResolutionError;
+
+impl Context for DependencyResolutionError {}
+
+impl std::fmt::Display for DependencyResolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mod dependency resolving error")
+    }
+}
+
+#[instrument(skip_all, fields(required = required.keys().cloned().collect::<Vec<_>>().join(", ")))]
+pub async fn resolve_mod_dependencies(
+    required: &DependencyList,
+    mod_list: &mut ModList,
+) -> Result<UsedVersions, DependencyResolutionError> {
+    // load local dependency info of required mods and their dependencies
+    {
+        let span = info_span!("load_local_deps", loaded_mods = field::Empty).entered();
+        let mut queue = required
+            .iter()
+            .map(|(n, d)| (n.clone(), *d))
+            .collect::<Vec<_>>();
+        let mut completed = HashSet::new();
+
+        while let Some((name, dep_version)) = &queue.pop() {
+            if completed.contains(name) {
+                continue;
+            }
+
+            completed.insert(name.clone());
+
+            if let Some(deps) = mod_list.load_local_dependency_info(name, dep_version) {
+                for (dep_name, dep) in deps {
+                    if !dep.is_required() {
+                        continue;
+                    }
+
+                    if !completed.contains(&dep_name) {
+                        queue.push((dep_name, *dep.version()));
+                    }
+                }
+            }
+        }
+
+        span.record("loaded_mods", completed.len());
+        span.exit();
+    }
+
+    // try to resolve dependencies with local mods
+    match mod_list
+        .solve_dependencies(required)
+        .change_context(DependencyResolutionError)
+        .attach_printable_lazy(|| "could not resolve dependencies with local mods")
+    {
+        Ok(res) => return Ok(res),
+        Err(err) => info!("{err:?}"),
+    }
+
+    info!("fetching dependency info from mod portal");
+
+    let mut process_queue = required.keys().cloned().collect::<Vec<_>>();
+    let mut fetched_deps = Vec::new();
+    fetched_deps.extend(Mod::wube_mods().map(std::string::ToString::to_string));
+
+    while let Some(name) = process_queue.pop() {
+        if fetched_deps.contains(&name) {
+            continue;
+        }
+
+        let info = factorio_api::full_info(&name)
+            .await
+            .change_context(DependencyResolutionError)
+            .attach_printable_lazy(|| format!("fetching mod info for {name} failed"))?;
+
+        let deps_info = info
+            .releases
+            .into_iter()
+            .map(|r| (r.version, r.info_json.dependencies))
+            .collect::<HashMap<_, _>>();
+
+        mod_list.set_dependency_info(&name.clone(), deps_info.clone());
+
+        let queue_add = deps_info
+            .values()
+            .flatten()
+            .filter_map(|d| {
+                if d.is_required() {
+                    Some(d.name().clone())
+                } else {
+                    None
+                }
+            })
+            .collect::<HashSet<_>>();
+
+        debug!("fetched dependency info for {name}");
+
+        process_queue.extend(queue_add);
+        fetched_deps.push(name);
+    }
+
+    info!("collected dependency info for {} mods", fetched_deps.len());
+
+    mod_list
+        .solve_dependencies(required)
+        .change_context(DependencyResolutionError)
+}
+
+#[derive(Debug)]
+pub enum ModDownloadError {
+    MissingCredentials,
+    TriedToDownloadWubeMod(String, Version),
+    DownloadFailed(String, Version),
+    SaveFailed(String, Version),
+}
+
+impl Context for ModDownloadError {}
+
+impl std::fmt::Display for ModDownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingCredentials => {
+                write!(f, "missing credentials for mod portal")
+            }
+            Self::TriedToDownloadWubeMod(name, version) => {
+                write!(f, "tried to download wube mod {name} v{version}")
+            }
+            Self::DownloadFailed(name, version) => {
+                write!(f, "failed to download mod {name} v{version}")
+            }
+            Self::SaveFailed(name, version) => write!(f, "failed to save mod {name} v{version}"),
+        }
+    }
+}
+
+#[instrument(skip_all, fields(count = missing.len()))]
+pub async fn download_mods(
+    missing: UsedVersions,
+    destination: &Path,
+    cache: &mod_cache::ModCache,
+) -> Result<(), ModDownloadError> {
+    let (username, token) = if factorio_api::requires_auth() {
+        let env_username = env::var("FACTORIO_USERNAME").ok();
+        let env_token = env::var("FACTORIO_TOKEN").ok();
+
+        if let (Some(username), Some(token)) = (env_username.clone(), env_token.clone()) {
+            (username, token)
+        } else {
+            let player_data = PlayerData::load(&destination.join("../player-data.json"))
+                .change_context(ModDownloadError::MissingCredentials).attach_printable("you can either use the game to login to your account\nor you provide the environment variables FACTORIO_USERNAME & FACTORIO_TOKEN\nwhich also work from a .env file")?;
+
+            match (
+                player_data.username,
+                player_data.token,
+                env_username,
+                env_token,
+            ) {
+                (Some(username), Some(token), _, _)
+                | (Some(username), None, _, Some(token))
+                | (None, Some(token), Some(username), _) => (username, token),
+                _ => return Err(report!(ModDownloadError::MissingCredentials).attach_printable("you can either use the game to login to your account\nor you provide the environment variables FACTORIO_USERNAME & FACTORIO_TOKEN\nwhich also work from a .env file"))
+            }
+        }
+    } else {
+        // public mirrors don't need credentials
+        (String::new(), String::new())
+    };
+
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(2));
+
+    for (name, version) in missing {
+        ensure!(
+            !Mod::wube_mods().contains(&name.as_str()),
+            ModDownloadError::TriedToDownloadWubeMod(name, version)
+        );
+
+        let release = factorio_api::short_info(&name)
+            .await
+            .change_context(ModDownloadError::DownloadFailed(name.clone(), version))?
+            .releases
+            .into_iter()
+            .find(|r| r.version == version)
+            .ok_or_else(|| report!(ModDownloadError::DownloadFailed(name.clone(), version)))
+            .attach_printable("release not found on the mod portal")?;
+
+        let cached = if let Some(cached) = cache.get(&name, &version, &release.sha1) {
+            info!("using cached {name} v{version}");
+            telemetry::mod_cache_hit();
+            cached
+        } else {
+            info!("downloading {name} v{version}");
+            let dl = factorio_api::fetch_mod_raw(&release.download_url, &username, &token)
+                .await
+                .change_context(ModDownloadError::DownloadFailed(name.clone(), version))?;
+
+            telemetry::mod_downloaded();
+            interval.tick().await;
+
+            cache
+                .insert(&name, &version, &release.sha1, &dl)
+                .change_context(ModDownloadError::SaveFailed(name.clone(), version))?
+        };
+
+        cache
+            .install(&cached, destination, &name, &version)
+            .change_context(ModDownloadError::SaveFailed(name, version))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Research tree of the loaded mod set, exported as JSON or Graphviz.
+
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use prototypes::{
+    technology::{MaxLevel, ResearchTrigger},
+    DataUtil,
+};
+
+/// Output formats of [`TechTree`].
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+
+    /// Graphviz `dot` graph with an edge from every prerequisite to the technology
+    Dot,
+}
+
+/// All technologies by name.
+#[derive(Debug, Serialize)]
+pub struct TechTree<'a> {
+    pub technologies: BTreeMap<&'a str, Technology<'a>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct Technology<'a> {
+    pub prerequisites: Vec<&'a str>,
+
+    /// Recipes unlocked by the technology.
+    pub unlocks: Vec<&'a str>,
+
+    pub cost: Option<ResearchCost<'a>>,
+    pub trigger: Option<&'a ResearchTrigger>,
+    pub max_level: Option<MaxLevel>,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub upgrade: bool,
+}
+
+/// Science packs needed to research a technology, `count` times the ingredients taking `time` seconds each.
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct ResearchCost<'a> {
+    pub count: Option<u64>,
+    pub count_formula: Option<&'a str>,
+    pub time: f64,
+    pub ingredients: BTreeMap<&'a str, u16>,
+}
+
+impl<'a> TechTree<'a> {
+    #[must_use]
+    pub fn new(data: &'a DataUtil) -> Self {
+        let technologies = data
+            .technologies()
+            .map(|(name, tech)| {
+                let cost = tech.unit.as_ref().map(|unit| ResearchCost {
+                    count: unit.count,
+                    count_formula: unit.count_formula.as_deref(),
+                    time: unit.time,
+                    ingredients: unit
+                        .ingredients
+                        .iter()
+                        .map(|ingredient| (ingredient.0.as_str(), ingredient.1))
+                        .collect(),
+                });
+
+                let mut prerequisites = tech
+                    .prerequisites
+                    .iter()
+                    .map(|prerequisite| prerequisite.as_str())
+                    .collect::<Vec<_>>();
+                prerequisites.sort_unstable();
+
+                let unlocks = tech
+                    .unlocked_recipes()
+                    .map(|recipe| recipe.as_str())
+                    .collect();
+
+                (
+                    name.as_str(),
+                    Technology {
+                        prerequisites,
+                        unlocks,
+                        cost,
+                        trigger: tech.research_trigger.as_ref(),
+                        max_level: tech.max_level,
+                        upgrade: tech.upgrade,
+                    },
+                )
+            })
+            .collect();
+
+        Self { technologies }
+    }
+
+    /// Graphviz `dot` source of the tree, the nodes are labeled with the research cost.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut res = String::from("digraph tech_tree {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for (name, tech) in &self.technologies {
+            let label = tech.cost.as_ref().map_or_else(
+                || name.to_string(),
+                |cost| {
+                    let count = cost
+                        .count_formula
+                        .map_or_else(|| cost.count.unwrap_or_default().to_string(), str::to_owned);
+                    format!("{name}\\n{count} × {}s", cost.time)
+                },
+            );
+
+            let _ = writeln!(res, "    {} [label={}];", quote(name), quote(&label));
+        }
+
+        for (name, tech) in &self.technologies {
+            for prerequisite in &tech.prerequisites {
+                let _ = writeln!(res, "    {} -> {};", quote(prerequisite), quote(name));
+            }
+        }
+
+        res.push_str("}\n");
+        res
+    }
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('"', "\\\""))
+}
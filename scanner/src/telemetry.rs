@@ -0,0 +1,74 @@
+//! Metrics of the render pipeline for hosting the scanner as a service.
+//!
+//! With the `metrics` feature the values are recorded through the [`metrics`](https://docs.rs/metrics)
+//! facade, any exporter (e.g. prometheus) installed by the host application collects them.
+//! Without the feature all functions are no-ops.
+
+// the functions are empty without the `metrics` feature
+#![allow(clippy::missing_const_for_fn)]
+
+use std::time::Instant;
+
+/// Counter of started renders.
+pub const RENDERS: &str = "scanner_renders_total";
+
+/// Histogram of the duration of each [`Stage`] in seconds, labeled with `stage`.
+pub const STAGE_DURATION: &str = "scanner_render_stage_duration_seconds";
+
+/// Counters of the image cache, recorded by `types`.
+pub use types::{IMAGE_CACHE_HITS, IMAGE_CACHE_MISSES};
+
+/// Counter of mods downloaded from the mod portal.
+pub const MOD_DOWNLOADS: &str = "scanner_mod_downloads_total";
+
+/// Counter of mods that were already in the mod cache.
+pub const MOD_CACHE_HITS: &str = "scanner_mod_cache_hits_total";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Preparing mods & loading the prototype data.
+    Setup,
+    Entities,
+    Wires,
+
+    /// Encoding the final image & layers as PNG.
+    Encode,
+}
+
+impl Stage {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Setup => "setup",
+            Self::Entities => "entities",
+            Self::Wires => "wires",
+            Self::Encode => "encode",
+        }
+    }
+}
+
+pub(crate) fn render_started() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(RENDERS).increment(1);
+}
+
+/// Records the time since `start` as the duration of `stage`.
+pub(crate) fn stage_finished(stage: Stage, start: Instant) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(STAGE_DURATION, "stage" => stage.as_str()).record(start.elapsed());
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = (stage, start);
+}
+
+#[cfg(feature = "portal")]
+pub(crate) fn mod_downloaded() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(MOD_DOWNLOADS).increment(1);
+}
+
+#[cfg(feature = "portal")]
+pub(crate) fn mod_cache_hit() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(MOD_CACHE_HITS).increment(1);
+}
@@ -0,0 +1,94 @@
+use ab_glyph::{FontVec, PxScale};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use tracing::warn;
+
+use mod_util::UsedMods;
+
+/// Font of the Factorio GUI, shipped with the core mod.
+pub const DEFAULT_FONT: &str = "fonts/TitilliumWeb-SemiBold.ttf";
+
+/// A TrueType font used to draw labels onto renders.
+pub struct Font(FontVec);
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Font").finish_non_exhaustive()
+    }
+}
+
+impl Font {
+    /// Loads a font from the given path inside the core mod.
+    #[must_use]
+    pub fn load(used_mods: &UsedMods, path: &str) -> Option<Self> {
+        let Some(core) = used_mods.get("core") else {
+            warn!("core mod not found, can't load font {path}");
+            return None;
+        };
+
+        let data = core
+            .get_file(path)
+            .map_err(|err| warn!("failed to load font {path}: {err}"))
+            .ok()?;
+
+        Self::from_bytes(data)
+    }
+
+    /// Parses a TrueType / OpenType font.
+    #[must_use]
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        FontVec::try_from_vec(data)
+            .map_err(|err| warn!("failed to parse font: {err}"))
+            .ok()
+            .map(Self)
+    }
+
+    /// Size of `text` in pixels when drawn with a height of `size` pixels.
+    #[must_use]
+    pub fn measure(&self, text: &str, size: f32) -> (u32, u32) {
+        text_size(PxScale::from(size), &self.0, text)
+    }
+
+    /// Renders single line `text` into a tightly sized transparent image.
+    #[must_use]
+    pub fn render(&self, text: &str, size: f32, color: Rgba<u8>) -> RgbaImage {
+        let (width, height) = self.measure(text, size);
+        let mut img = RgbaImage::new(width.max(1), height.max(1));
+        self.draw(&mut img, text, (0, 0), size, color);
+
+        img
+    }
+
+    /// Draws single line `text` with its top left corner at `x`, `y`.
+    pub fn draw(
+        &self,
+        img: &mut RgbaImage,
+        text: &str,
+        (x, y): (i32, i32),
+        size: f32,
+        color: Rgba<u8>,
+    ) {
+        draw_text_mut(img, color, x, y, PxScale::from(size), &self.0, text);
+    }
+}
+
+/// Short form of a signal count like the game shows it, e.g. `950`, `1.5k` or `-12M`.
+#[must_use]
+pub fn format_count(count: i64) -> String {
+    const SUFFIXES: [(f64, &str); 3] = [(1e9, "G"), (1e6, "M"), (1e3, "k")];
+
+    let count = count as f64;
+    let Some((value, suffix)) = SUFFIXES
+        .iter()
+        .find(|(factor, _)| count.abs() >= *factor)
+        .map(|(factor, suffix)| (count / factor, suffix))
+    else {
+        return count.to_string();
+    };
+
+    if value.abs() >= 10.0 {
+        format!("{}{suffix}", value.trunc())
+    } else {
+        format!("{}{suffix}", (value * 10.0).trunc() / 10.0)
+    }
+}
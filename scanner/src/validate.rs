@@ -0,0 +1,348 @@
+//! Checks for blueprints edited by external tools that could not be built like this in game.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use blueprint::EntityNumber;
+use prototypes::{entity::Type as EntityType, DataUtil};
+use types::{BoundingBox, MapPosition};
+
+use crate::bp_helper;
+
+/// Overlaps smaller than this (in tiles) are rounding noise of touching collision boxes.
+const MIN_OVERLAP: f64 = 0.01;
+
+/// Collision mask of entity types without an explicit one.
+const DEFAULT_MASK: [&str; 4] = ["item", "object", "player", "water_tile"];
+const BELT_MASK: [&str; 4] = ["object", "floor", "transport_belt", "water_tile"];
+const RAIL_MASK: [&str; 4] = ["item", "floor", "rail", "water_tile"];
+
+/// Two entities whose collision boxes overlap and whose collision masks share a layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct Overlap {
+    pub entities: [EntityNumber; 2],
+    pub names: [String; 2],
+    pub positions: [MapPosition; 2],
+
+    /// Collision boxes of both entities in map coordinates.
+    pub areas: [BoundingBox; 2],
+
+    /// The overlapping part of the collision boxes.
+    pub intersection: BoundingBox,
+}
+
+fn fmt_entity(
+    f: &mut std::fmt::Formatter<'_>,
+    entity: EntityNumber,
+    name: &str,
+    position: &MapPosition,
+) -> std::fmt::Result {
+    // blueprint positions are single precision, avoid printing the conversion noise
+    let (x, y) = position.as_tuple();
+    write!(f, "#{entity} {name} at ({}, {})", x as f32, y as f32)
+}
+
+impl std::fmt::Display for Overlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_entity(f, self.entities[0], &self.names[0], &self.positions[0])?;
+        write!(f, " overlaps ")?;
+        fmt_entity(f, self.entities[1], &self.names[1], &self.positions[1])
+    }
+}
+
+/// Wires between two entities that are further apart than the wire reach of one of them.
+/// The game drops these wires when building the blueprint.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreachableWire {
+    pub entities: [EntityNumber; 2],
+    pub names: [String; 2],
+    pub positions: [MapPosition; 2],
+
+    /// Distance between the entities in tiles.
+    pub distance: f64,
+
+    /// The smaller wire reach of both entities.
+    pub reach: f64,
+}
+
+impl std::fmt::Display for UnreachableWire {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "wire from ")?;
+        fmt_entity(f, self.entities[0], &self.names[0], &self.positions[0])?;
+        write!(f, " to ")?;
+        fmt_entity(f, self.entities[1], &self.names[1], &self.positions[1])?;
+        write!(
+            f,
+            " is {:.2} tiles long, the reach is {}",
+            self.distance, self.reach
+        )
+    }
+}
+
+/// Entity with everything needed to check it against others.
+struct Collider<'a> {
+    entity: &'a blueprint::Entity,
+    area: BoundingBox,
+    kind: &'a EntityType,
+    layers: HashSet<String>,
+
+    /// Entities with the same name don't collide with each other.
+    not_colliding_with_itself: bool,
+}
+
+impl Collider<'_> {
+    const fn is_rail(&self) -> bool {
+        matches!(self.kind, EntityType::StraightRail | EntityType::CurvedRail)
+    }
+
+    fn collides_with(&self, other: &Self) -> bool {
+        // crossings & gates on rails are handled by the game itself
+        if self.is_rail() && (other.is_rail() || matches!(other.kind, EntityType::Gate))
+            || other.is_rail() && matches!(self.kind, EntityType::Gate)
+        {
+            return false;
+        }
+
+        if (self.not_colliding_with_itself || other.not_colliding_with_itself)
+            && self.entity.name == other.entity.name
+        {
+            return false;
+        }
+
+        !self.layers.is_disjoint(&other.layers)
+    }
+}
+
+const fn default_mask(kind: &EntityType) -> &'static [&'static str] {
+    match kind {
+        EntityType::TransportBelt
+        | EntityType::UndergroundBelt
+        | EntityType::Splitter
+        | EntityType::Loader
+        | EntityType::Loader1x1
+        | EntityType::LinkedBelt => &BELT_MASK,
+        EntityType::StraightRail | EntityType::CurvedRail => &RAIL_MASK,
+        _ => &DEFAULT_MASK,
+    }
+}
+
+fn collider<'a>(e: &'a blueprint::Entity, data: &'a DataUtil) -> Option<Collider<'a>> {
+    let kind = data.get_entity_type(&e.name)?;
+
+    // vehicles are placed by orientation and sit on top of rails
+    if matches!(
+        kind,
+        EntityType::Car
+            | EntityType::SpiderVehicle
+            | EntityType::Locomotive
+            | EntityType::CargoWagon
+            | EntityType::FluidWagon
+            | EntityType::ArtilleryWagon
+    ) {
+        return None;
+    }
+
+    let area = bp_helper::entity_area(e, data)?;
+    if area.width() <= MIN_OVERLAP || area.height() <= MIN_OVERLAP {
+        return None;
+    }
+
+    let (layers, not_colliding_with_itself) = match data.get_entity(&e.name)?.collision_mask() {
+        Some(mask) => {
+            if mask.colliding_with_tiles_only {
+                return None;
+            }
+
+            (
+                mask.layer_names().cloned().collect(),
+                mask.not_colliding_with_itself,
+            )
+        }
+        None => (
+            default_mask(kind)
+                .iter()
+                .map(|&layer| layer.to_owned())
+                .collect(),
+            false,
+        ),
+    };
+
+    Some(Collider {
+        entity: e,
+        area,
+        kind,
+        layers,
+        not_colliding_with_itself,
+    })
+}
+
+/// Finds all pairs of entities that could not be placed next to each other like this in game.
+///
+/// Uses the collision boxes & masks of the prototypes, entities without a prototype and vehicles are skipped.
+#[must_use]
+pub fn overlaps(bp: &blueprint::Blueprint, data: &DataUtil) -> Vec<Overlap> {
+    let colliders = bp
+        .entities
+        .iter()
+        .filter_map(|e| collider(e, data))
+        .collect::<Vec<_>>();
+
+    // only entities sharing a tile can overlap
+    let mut tiles = HashMap::<(i64, i64), Vec<usize>>::new();
+    for (idx, collider) in colliders.iter().enumerate() {
+        let area = &collider.area;
+        for x in area.left().floor() as i64..area.right().ceil() as i64 {
+            for y in area.top().floor() as i64..area.bottom().ceil() as i64 {
+                tiles.entry((x, y)).or_default().push(idx);
+            }
+        }
+    }
+
+    let mut checked = HashSet::new();
+    let mut res = Vec::new();
+    for indices in tiles.values() {
+        for (pos, &a) in indices.iter().enumerate() {
+            for &b in &indices[pos + 1..] {
+                if !checked.insert((a, b)) {
+                    continue;
+                }
+
+                let (a, b) = (&colliders[a], &colliders[b]);
+                let Some(intersection) = a.area.intersection(&b.area) else {
+                    continue;
+                };
+
+                if intersection.width() < MIN_OVERLAP
+                    || intersection.height() < MIN_OVERLAP
+                    || !a.collides_with(b)
+                {
+                    continue;
+                }
+
+                res.push(Overlap {
+                    entities: [a.entity.entity_number, b.entity.entity_number],
+                    names: [a.entity.name.to_string(), b.entity.name.to_string()],
+                    positions: [(&a.entity.position).into(), (&b.entity.position).into()],
+                    areas: [a.area.clone(), b.area.clone()],
+                    intersection,
+                });
+            }
+        }
+    }
+
+    res.sort_by_key(|overlap| overlap.entities);
+    res
+}
+
+/// Finds all pairs of entities connected by wires that are out of reach of each other.
+///
+/// Uses the `maximum_wire_distance` / `circuit_wire_max_distance` of the prototypes,
+/// entities without a prototype or wire reach are skipped.
+#[must_use]
+pub fn unreachable_wires(bp: &blueprint::Blueprint, data: &DataUtil) -> Vec<UnreachableWire> {
+    let entities = bp
+        .entities
+        .iter()
+        .map(|e| (e.entity_number, e))
+        .collect::<HashMap<_, _>>();
+
+    let pairs = bp
+        .wires
+        .iter()
+        .map(|wire| {
+            let (a, b) = (wire.0, wire.2);
+            (a.min(b), a.max(b))
+        })
+        .collect::<HashSet<_>>();
+
+    let mut res = pairs
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let (a, b) = (entities.get(&a)?, entities.get(&b)?);
+            let reach = data
+                .get_entity(&a.name)?
+                .wire_reach()?
+                .min(data.get_entity(&b.name)?.wire_reach()?);
+
+            let positions = [
+                MapPosition::from(&a.position),
+                MapPosition::from(&b.position),
+            ];
+            let (dx, dy) = (positions[1] - positions[0]).as_tuple();
+            let distance = dx.hypot(dy);
+
+            (distance > reach).then(|| UnreachableWire {
+                entities: [a.entity_number, b.entity_number],
+                names: [a.name.to_string(), b.name.to_string()],
+                positions,
+                distance,
+                reach,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    res.sort_by_key(|wire| wire.entities);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{base_data, blueprint};
+
+    #[test]
+    fn finds_overlapping_entities() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"wooden-chest","position":{"x":0.5,"y":0.5}},
+            {"entity_number":2,"name":"wooden-chest","position":{"x":0.8,"y":0.5}},
+            {"entity_number":3,"name":"wooden-chest","position":{"x":1.5,"y":0.5}},
+            {"entity_number":4,"name":"transport-belt","position":{"x":3.5,"y":0.5}},
+            {"entity_number":5,"name":"transport-belt","position":{"x":4.5,"y":0.5}},
+            {"entity_number":6,"name":"unknown-entity","position":{"x":4.5,"y":0.5}}"#,
+            "",
+        );
+        let overlaps = overlaps(&bp, base_data());
+
+        // touching collision boxes & entities without a prototype don't overlap
+        assert_eq!(overlaps.len(), 1);
+
+        let overlap = &overlaps[0];
+        assert_eq!(overlap.entities, [1, 2]);
+        assert!((overlap.intersection.width() - 0.4).abs() < 1e-6);
+        assert!((overlap.intersection.height() - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn uses_explicit_collision_masks() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"oil-refinery","position":{"x":2.5,"y":2.5}},
+            {"entity_number":2,"name":"big-electric-pole","position":{"x":1,"y":1}},
+            {"entity_number":3,"name":"big-electric-pole","position":{"x":7,"y":1}}"#,
+            "",
+        );
+        let overlaps = overlaps(&bp, base_data());
+
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].entities, [1, 2]);
+    }
+
+    #[test]
+    fn finds_wires_out_of_reach() {
+        let bp = blueprint(
+            r#"{"entity_number":1,"name":"small-electric-pole","position":{"x":0.5,"y":0.5}},
+            {"entity_number":2,"name":"small-electric-pole","position":{"x":6.5,"y":0.5}},
+            {"entity_number":3,"name":"small-electric-pole","position":{"x":14.5,"y":0.5}}"#,
+            "[1,5,2,5],[2,5,3,5],[3,1,2,1]",
+        );
+        let wires = unreachable_wires(&bp, base_data());
+
+        // wires between the same entities are only reported once
+        assert_eq!(wires.len(), 1);
+
+        let wire = &wires[0];
+        assert_eq!(wire.entities, [2, 3]);
+        assert!((wire.distance - 8.0).abs() < 1e-6);
+        assert!((wire.reach - 7.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,98 @@
+//! Loading only the prototypes a blueprint uses has to give the same results as loading all of them.
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use prototypes::{DataRaw, DataUtil};
+use scanner::{bom::BillOfMaterials, bp_helper};
+
+const BLUEPRINT: &str = r#"{
+    "blueprint": {
+        "item": "blueprint",
+        "icons": [],
+        "version": 562949954928640,
+        "entities": [
+            { "entity_number": 1, "name": "transport-belt", "position": { "x": 0.5, "y": 0.5 } },
+            { "entity_number": 2, "name": "transport-belt", "position": { "x": 0.5, "y": 1.5 } },
+            { "entity_number": 3, "name": "inserter", "position": { "x": 1.5, "y": 0.5 }, "direction": 4 },
+            { "entity_number": 4, "name": "assembling-machine-1", "position": { "x": 3.5, "y": 0.5 }, "recipe": "iron-gear-wheel" },
+            { "entity_number": 5, "name": "small-electric-pole", "position": { "x": 1.5, "y": 1.5 } },
+            { "entity_number": 6, "name": "pipe", "position": { "x": 5.5, "y": 0.5 } },
+            { "entity_number": 7, "name": "straight-rail", "position": { "x": 8, "y": 8 } },
+            { "entity_number": 8, "name": "curved-rail-a", "position": { "x": 12, "y": 8 } }
+        ],
+        "tiles": [
+            { "name": "stone-path", "position": { "x": 0, "y": 0 } },
+            { "name": "stone-path", "position": { "x": 1, "y": 0 } },
+            { "name": "concrete", "position": { "x": 0, "y": 1 } },
+            { "name": "refined-concrete", "position": { "x": 1, "y": 1 } }
+        ]
+    }
+}"#;
+
+fn load_dump(filter: Option<&blueprint::Data>) -> DataUtil {
+    let dump = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!(
+        "../prototypes/test_dumps/base.{}.json",
+        prototypes::targeted_engine_version()
+    ));
+    let mut bytes = fs::read(dump).unwrap();
+
+    if let Some(bp) = filter {
+        bytes = DataRaw::filter_dump(&bytes, &bp_helper::get_used_names(bp)).unwrap();
+    }
+
+    let (data, _) = DataRaw::load_lenient_from_bytes(&bytes).unwrap();
+    DataUtil::new(data)
+}
+
+/// Icons the blueprint needs that have a prototype to take them from:
+/// the items of the BOM, the recipes & the products the recipe icons fall back to.
+fn available_icons(
+    bp: &blueprint::Blueprint,
+    bom: &BillOfMaterials,
+    data: &DataUtil,
+) -> BTreeSet<String> {
+    let items = data.item_names().collect::<BTreeSet<_>>();
+    let recipes = bp
+        .entities
+        .iter()
+        .map(|e| e.recipe.as_str())
+        .filter(|recipe| data.contains_recipe(recipe))
+        .collect::<BTreeSet<_>>();
+
+    let products = items.iter().filter(|item| {
+        data.recipes_for_product(item)
+            .iter()
+            .any(|recipe| recipes.contains(recipe.as_str()))
+    });
+
+    bom.items
+        .keys()
+        .chain(bom.unplaceable.keys())
+        .filter(|name| items.contains(name.as_str()))
+        .map(|name| format!("item/{name}"))
+        .chain(products.map(|item| format!("product/{item}")))
+        .chain(recipes.iter().map(|recipe| format!("recipe/{recipe}")))
+        .collect()
+}
+
+#[test]
+fn filtered_load_matches_full_load() {
+    let data = blueprint::Data::from_json(BLUEPRINT).unwrap();
+    let bp = data.as_blueprint().unwrap();
+
+    let full = load_dump(None);
+    let filtered = load_dump(Some(&data));
+
+    let full_bom = BillOfMaterials::new(bp, &full);
+    let filtered_bom = BillOfMaterials::new(bp, &filtered);
+
+    assert_eq!(full_bom.items, filtered_bom.items);
+    assert_eq!(full_bom.unplaceable, filtered_bom.unplaceable);
+    assert!(full_bom.items.contains_key("assembling-machine-1"));
+
+    let icons = available_icons(bp, &full_bom, &full);
+    assert!(icons.contains("product/iron-gear-wheel"));
+    assert_eq!(icons, available_icons(bp, &filtered_bom, &filtered));
+    assert!(filtered.item_names().count() < full.item_names().count());
+}
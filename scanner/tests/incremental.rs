@@ -0,0 +1,168 @@
+//! Incremental renders have to look the same as rendering the changed blueprint from scratch.
+//!
+//! Rendering needs the sprites of a local Factorio installation, so the test is ignored by default.
+//! Run it with `FACTORIO_PATH=<factorio> cargo test -p scanner --test incremental -- --ignored`.
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{collections::HashMap, env, path::PathBuf};
+
+use image::GenericImageView;
+
+use mod_util::{mod_list::ModList, UsedMods};
+use prototypes::{
+    diagnostics::Diagnostics, DataRaw, DataUtil, RenderLayerBuffer, RenderOptions, TargetSize,
+};
+use scanner::{
+    incremental::{EntityChange, IncrementalRender},
+    job::RenderJob,
+};
+use types::ImageCache;
+
+const TARGET_RES: f64 = 512.0;
+const MIN_SCALE: f64 = 0.5;
+
+const BLUEPRINT: &str = r#"{
+    "blueprint": {
+        "item": "blueprint",
+        "icons": [],
+        "version": 562949954928640,
+        "entities": [
+            { "entity_number": 1, "name": "transport-belt", "position": { "x": 0.5, "y": 0.5 }, "direction": 4 },
+            { "entity_number": 2, "name": "transport-belt", "position": { "x": 1.5, "y": 0.5 }, "direction": 4 },
+            { "entity_number": 3, "name": "transport-belt", "position": { "x": 2.5, "y": 0.5 }, "direction": 4 },
+            { "entity_number": 4, "name": "inserter", "position": { "x": 1.5, "y": 1.5 } },
+            { "entity_number": 5, "name": "wooden-chest", "position": { "x": 1.5, "y": 2.5 } },
+            { "entity_number": 6, "name": "small-electric-pole", "position": { "x": 2.5, "y": 1.5 } },
+            { "entity_number": 7, "name": "small-electric-pole", "position": { "x": 6.5, "y": 1.5 } },
+            { "entity_number": 8, "name": "pipe", "position": { "x": 4.5, "y": 3.5 } },
+            { "entity_number": 9, "name": "pipe", "position": { "x": 5.5, "y": 3.5 } },
+            { "entity_number": 10, "name": "wooden-chest", "position": { "x": 12.5, "y": 4.5 } },
+            { "entity_number": 11, "name": "small-electric-pole", "position": { "x": 12.5, "y": 6.5 } },
+            { "entity_number": 12, "name": "storage-tank", "position": { "x": 7.5, "y": 4.5 } }
+        ],
+        "wires": [[6, 5, 7, 5]]
+    }
+}"#;
+
+fn setup() -> (DataUtil, UsedMods) {
+    let factorio = PathBuf::from(
+        env::var("FACTORIO_PATH")
+            .expect("FACTORIO_PATH has to point to a factorio installation to render the sprites"),
+    );
+    let base_version = prototypes::targeted_engine_version();
+
+    let mut mod_list = ModList::generate_custom(factorio.join("data"), &factorio).unwrap();
+    let missing = mod_list.enable_mods(&HashMap::from([("base".to_owned(), base_version)]));
+    assert!(missing.is_empty());
+
+    let dump = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join(format!("../prototypes/test_dumps/base.{base_version}.json"));
+    let (data, _) = DataRaw::load_lenient(&dump).unwrap();
+
+    (DataUtil::new(data), mod_list.active_mods())
+}
+
+fn entity(bp: &blueprint::Blueprint, entity_number: u64) -> blueprint::Entity {
+    bp.entities
+        .iter()
+        .find(|e| e.entity_number == entity_number)
+        .cloned()
+        .unwrap()
+}
+
+fn assert_matches_full_render(
+    incremental: &mut IncrementalRender,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    size: TargetSize,
+    options: RenderOptions,
+) {
+    let bp = incremental.blueprint().clone();
+    let mut full = RenderJob::new(&bp, data, used_mods)
+        .options(options)
+        .run(
+            RenderLayerBuffer::new(size),
+            &mut ImageCache::new(),
+            &mut Diagnostics::new(),
+        )
+        .unwrap()
+        .layers;
+
+    let expected = full.combine();
+    let actual = incremental.image();
+    assert_eq!(expected.dimensions(), actual.dimensions());
+
+    let mismatched = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|((.., a), (.., b))| a != b)
+        .count();
+    assert_eq!(
+        mismatched, 0,
+        "incremental render differs in {mismatched} pixels"
+    );
+}
+
+#[test]
+#[ignore = "needs a factorio installation in FACTORIO_PATH"]
+fn apply_matches_full_render() {
+    let (data, used_mods) = setup();
+    let options = RenderOptions {
+        seed: Some(0),
+        ..Default::default()
+    };
+
+    let bp = blueprint::Data::from_json(BLUEPRINT)
+        .unwrap()
+        .as_blueprint()
+        .unwrap()
+        .clone();
+    let size = scanner::calculate_target_size(&bp, &data, TARGET_RES, MIN_SCALE, None).unwrap();
+
+    let mut added = entity(&bp, 3);
+    added.entity_number = 13;
+    added.position = entity(&bp, 9).position;
+    added.position.y -= 1.0;
+
+    // new entities are drawn last, so the pole now has to be drawn below the chest
+    let mut readded = entity(&bp, 10);
+    readded.entity_number = 14;
+
+    let mut moved = entity(&bp, 5).position;
+    moved.x += 1.0;
+
+    let mut incremental = IncrementalRender::new(
+        bp,
+        &data,
+        &used_mods,
+        size.clone(),
+        options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap();
+
+    // the pole overlaps the chest above it without being its neighbor
+    for changes in [
+        vec![
+            EntityChange::Added(Box::new(added)),
+            EntityChange::Moved {
+                entity_number: 5,
+                position: moved,
+            },
+            EntityChange::Removed(2),
+            EntityChange::Removed(7),
+        ],
+        vec![EntityChange::Removed(10)],
+        vec![EntityChange::Added(Box::new(readded))],
+    ] {
+        let dirty = incremental
+            .apply(changes, &mut Diagnostics::new())
+            .unwrap()
+            .expect("the changes are visible");
+        assert!(!dirty.is_empty());
+
+        assert_matches_full_render(&mut incremental, &data, &used_mods, size.clone(), options);
+    }
+
+    assert_eq!(incremental.blueprint().entities.len(), 11);
+}
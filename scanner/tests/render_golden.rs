@@ -0,0 +1,203 @@
+//! Golden image regression tests for the renderer.
+//!
+//! Every blueprint from `blueprint/tests` is rendered with the vanilla prototype dump and each
+//! render layer is compared against the checked-in images in `tests/golden/<blueprint>/`.
+//!
+//! The sprites are not the ones of the game: every file is answered with a generated pattern
+//! in a color derived from its path. That keeps the test independent of a Factorio installation
+//! while positions, crops, scaling & layering of every sprite still show up in the images.
+//! Failed layers are written to `target/tmp/golden/<blueprint>/` together with a diff image,
+//! set `UPDATE_GOLDEN=1` to (re)generate the golden images & commit them afterwards.
+#![allow(clippy::unwrap_used)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Cursor,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use golden::Golden;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use mod_util::{
+    mod_loader::ModError,
+    sprite_source::{self, SharedSpriteSource, SpriteSource},
+    UsedMods,
+};
+use prototypes::{
+    diagnostics::Diagnostics, DataRaw, DataUtil, InternalRenderLayer, RenderLayerBuffer,
+    RenderOptions,
+};
+use types::ImageCache;
+
+const TARGET_RES: f64 = 1024.0;
+const MIN_SCALE: f64 = 0.5;
+const SEED: u64 = 0;
+
+/// Edge length of the generated sprite sheets, crops outside of it are clamped like for real files.
+const SPRITE_SIZE: u32 = 1024;
+
+const COMBINED_NAME: &str = "combined";
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Serves a checkerboard in a color derived from the requested path for every file.
+#[derive(Debug, Default)]
+struct PatternSource {
+    encoded: Mutex<HashMap<[u8; 3], Arc<Vec<u8>>>>,
+}
+
+impl PatternSource {
+    /// FNV-1a, stable across platforms & compiler versions unlike the std hasher.
+    fn color(mod_name: &str, path: &str) -> [u8; 3] {
+        let hash = mod_name
+            .bytes()
+            .chain([b'/'])
+            .chain(path.bytes())
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+                (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+            });
+
+        let [r, g, b, ..] = hash.to_le_bytes();
+        [r, g, b]
+    }
+
+    /// Transparent checkerboard whose opaque cells encode their position, so crops at different offsets differ.
+    fn png(color: [u8; 3]) -> Vec<u8> {
+        let [r, g, b] = color;
+        let img = RgbaImage::from_fn(SPRITE_SIZE, SPRITE_SIZE, |x, y| {
+            if (x / 8 + y / 8) % 2 == 0 {
+                Rgba([r ^ (x / 4) as u8, g ^ (y / 4) as u8, b, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+}
+
+impl SpriteSource for PatternSource {
+    fn fetch(&self, mod_name: &str, path: &str) -> Result<Vec<u8>, ModError> {
+        let color = Self::color(mod_name, path);
+        let png = self
+            .encoded
+            .lock()
+            .unwrap()
+            .entry(color)
+            .or_insert_with(|| Arc::new(Self::png(color)))
+            .clone();
+
+        Ok(png.to_vec())
+    }
+}
+
+fn setup() -> (DataUtil, UsedMods) {
+    let base_version = prototypes::targeted_engine_version();
+
+    let dump = manifest_dir().join(format!("../prototypes/test_dumps/base.{base_version}.json"));
+    let (data, _) = DataRaw::load_lenient(&dump).unwrap();
+
+    let source: SharedSpriteSource = Arc::new(PatternSource::default());
+    let used_mods = sprite_source::used_mods(
+        [
+            ("core".to_owned(), base_version),
+            ("base".to_owned(), base_version),
+        ],
+        &source,
+    );
+
+    (DataUtil::new(data), used_mods)
+}
+
+fn render_layers(
+    bp_string: &str,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+) -> (DynamicImage, RenderLayerBuffer) {
+    let raw_bp = blueprint::Data::try_from(bp_string.trim().to_owned()).unwrap();
+    let bp = raw_bp.as_blueprint().unwrap();
+
+    let size = scanner::calculate_target_size(bp, data, TARGET_RES, MIN_SCALE, None).unwrap();
+    let (mut layers, _) = scanner::render_bp_layers(
+        bp,
+        data,
+        used_mods,
+        RenderLayerBuffer::new(size),
+        &mut ImageCache::new(),
+        &[],
+        &RenderOptions {
+            seed: Some(SEED),
+            strict: true,
+            ..Default::default()
+        },
+        &mut Diagnostics::new(),
+    )
+    .unwrap();
+
+    (layers.combine(), layers)
+}
+
+/// Compares the render against the golden images and returns a description per failed layer.
+fn compare(golden: &Golden, combined: &DynamicImage, layers: &RenderLayerBuffer) -> Vec<String> {
+    let mut images = vec![(COMBINED_NAME.to_owned(), Some(combined))];
+    images.extend(
+        InternalRenderLayer::all()
+            .into_iter()
+            .map(|layer| (format!("{layer:?}"), layers.layer(layer))),
+    );
+
+    images
+        .into_iter()
+        .filter_map(|(name, image)| {
+            // blueprints without any renderable entity result in empty images
+            let image = image.filter(|image| image.width() > 0 && image.height() > 0);
+            golden
+                .check(&name, image)
+                .err()
+                .map(|mismatch| format!("{name}: {mismatch}"))
+        })
+        .collect()
+}
+
+#[test]
+fn render_golden() {
+    let (data, used_mods) = setup();
+
+    let mut failures = Vec::new();
+    for entry in fs::read_dir(manifest_dir().join("../blueprint/tests")).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let (combined, layers) =
+            render_layers(&fs::read_to_string(&path).unwrap(), &data, &used_mods);
+
+        let golden = Golden::new(
+            manifest_dir().join("tests/golden").join(&name),
+            Path::new(env!("CARGO_TARGET_TMPDIR"))
+                .join("golden")
+                .join(&name),
+        );
+        let diffs = compare(&golden, &combined, &layers);
+
+        if !diffs.is_empty() {
+            failures.push(format!("{name}:\n  {}", diffs.join("\n  ")));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "renders differ from the golden images, run with UPDATE_GOLDEN=1 to accept:\n{}",
+        failures.join("\n")
+    );
+}
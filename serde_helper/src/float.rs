@@ -83,6 +83,11 @@ mod f64 {
         1.0
     }
 
+    #[must_use]
+    pub const fn f64_1_333() -> f64 {
+        1.333
+    }
+
     #[must_use]
     pub const fn f64_1_5() -> f64 {
         1.5
@@ -168,6 +173,11 @@ mod f64 {
         (*value - f64_1()).abs() < f64::EPSILON
     }
 
+    #[must_use]
+    pub fn is_1_333_f64(value: &f64) -> bool {
+        (*value - f64_1_333()).abs() < f64::EPSILON
+    }
+
     #[must_use]
     pub fn is_1_5_f64(value: &f64) -> bool {
         (*value - f64_1_5()).abs() < f64::EPSILON
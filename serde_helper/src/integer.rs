@@ -140,6 +140,11 @@ mod u8 {
         120
     }
 
+    #[must_use]
+    pub const fn u8_127() -> u8 {
+        127
+    }
+
     #[must_use]
     pub const fn u8_max() -> u8 {
         u8::MAX
@@ -260,6 +265,11 @@ mod u8 {
         *value == u8_120()
     }
 
+    #[must_use]
+    pub const fn is_127_u8(value: &u8) -> bool {
+        *value == u8_127()
+    }
+
     #[must_use]
     pub const fn is_max_u8(value: &u8) -> bool {
         *value == u8_max()
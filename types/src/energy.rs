@@ -1,18 +1,23 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
 use crate::FactorioArray;
 
-use super::{helper, Direction, FluidBox, FuelCategory, MapPosition, Sprite4Way};
+use super::{helper, Direction16, FluidBox, FuelCategory, MapPosition, Sprite4Way};
 
 /// [`Types/Energy`](https://lua-api.factorio.com/latest/types/Energy.html)
 pub type Energy = String;
 
+/// [`Types/AirbornePollutantID`](https://lua-api.factorio.com/latest/types/AirbornePollutantID.html)
+pub type AirbornePollutantID = String;
+
 /// [`Types/BaseEnergySource`](https://lua-api.factorio.com/latest/types/BaseEnergySource.html)
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BaseEnergySource<T> {
-    #[serde(default, skip_serializing_if = "helper::is_default")]
-    pub emissions_per_minute: f64,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub emissions_per_minute: HashMap<AirbornePollutantID, f64>,
 
     #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
     pub render_no_power_icon: bool,
@@ -184,5 +189,7 @@ pub enum ElectricUsagePriority {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HeatConnection {
     pub position: MapPosition,
-    pub direction: Direction,
+
+    /// 2.0 connections face their neighbour in one of the 16 directions.
+    pub direction: Direction16,
 }
@@ -121,6 +121,7 @@ pub enum RenderLayer {
     HigherObjectAbove,
     ItemInInserterHand,
     Wires,
+    UnderElevated,
     WiresAbove,
     EntityInfoIcon,
     EntityInfoIconAbove,
@@ -320,7 +321,7 @@ pub fn merge_renders(renders: &[Option<GraphicsOutput>], scale: f64) -> Option<G
     let px_per_tile = TILE_RES / scale;
     let width = (max_x - min_x) * px_per_tile;
     let height = (max_y - min_y) * px_per_tile;
-    let res_shift = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    let res_shift = (f64::midpoint(min_x, max_x), f64::midpoint(min_y, max_y));
     let center = (
         res_shift.0.mul_add(-px_per_tile, width / 2.0),
         res_shift.1.mul_add(-px_per_tile, height / 2.0),
@@ -340,6 +341,56 @@ pub fn merge_renders(renders: &[Option<GraphicsOutput>], scale: f64) -> Option<G
     Some((combined, res_shift.into()))
 }
 
+/// Most mipmap levels a sprite can have.
+const MAX_MIPMAP_LEVEL: u8 = 5;
+
+/// Mipmap level to use for a sprite that is drawn at `factor` times its resolution, at most `max_level`.
+///
+/// Every level halves the resolution, the smallest level that still has at least the drawn resolution is picked.
+#[must_use]
+pub fn mipmap_level(factor: f64, max_level: u8) -> u8 {
+    if factor <= 0.0 || factor >= 0.5 {
+        return 0;
+    }
+
+    ((1.0 / factor).log2().floor() as u8).min(max_level)
+}
+
+/// Area of mipmap `level` of a `width` x `height` sprite at `x`, `y` as (`x`, `y`, `width`, `height`).
+/// The mipmaps are placed right of the sprite, each one half the size of the previous one.
+#[must_use]
+pub const fn mipmap_rect(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    level: u8,
+) -> (u32, u32, u32, u32) {
+    (
+        x + 2 * width - ((2 * width) >> level),
+        y,
+        width >> level,
+        height >> level,
+    )
+}
+
+/// Number of mipmap levels right of a `size` x `size` sprite at the left edge of an image that is `image_width` wide.
+#[must_use]
+pub const fn mipmap_count(image_width: u32, size: u32) -> u8 {
+    let mut level = 0;
+
+    while level < MAX_MIPMAP_LEVEL {
+        let (x, _, width, _) = mipmap_rect(0, 0, size, size, level + 1);
+        if width == 0 || x + width > image_width {
+            break;
+        }
+
+        level += 1;
+    }
+
+    level
+}
+
 pub trait Scale {
     fn scale(&self) -> f64;
 }
@@ -484,18 +535,26 @@ impl FetchSprite for SpriteParams {
         let (offset_x, offset_y) = offset;
         let (width, height) = self.get_size();
 
-        let img = filename.load(used_mods, image_cache)?.crop_imm(
-            (x + offset_x) as u32,
-            (y + offset_y) as u32,
-            width as u32,
-            height as u32,
-        );
+        // mipmaps are only laid out for single sprites, not for the frames of sprite sheets
+        let factor = self.scale / scale;
+        let level = if offset == (0, 0) {
+            mipmap_level(factor, self.mipmap_count)
+        } else {
+            0
+        };
 
-        let mut img = img.resize(
-            (f64::from(img.width()) * self.scale / scale).round() as u32,
-            (f64::from(img.height()) * self.scale / scale).round() as u32,
-            image::imageops::FilterType::Nearest,
-        );
+        let mut img = filename.load_scaled(
+            used_mods,
+            image_cache,
+            mipmap_rect(
+                (x + offset_x) as u32,
+                (y + offset_y) as u32,
+                width as u32,
+                height as u32,
+                level,
+            ),
+            factor * f64::from(1u32 << level),
+        )?;
 
         // apply tint if applicable
         let tint = if self.apply_runtime_tint {
@@ -791,7 +850,7 @@ impl RenderableGraphics for RotatedSpriteParams {
             used_mods,
             image_cache,
             opts.runtime_tint,
-            (column as i16, row as i16),
+            (column.cast_signed(), row.cast_signed()),
         )
     }
 }
@@ -887,7 +946,7 @@ impl RenderableGraphics for RotatedSpriteParamsMultiFile {
             used_mods,
             image_cache,
             opts.runtime_tint,
-            (column as i16, row as i16),
+            (column.cast_signed(), row.cast_signed()),
         )
     }
 }
@@ -895,6 +954,38 @@ impl RenderableGraphics for RotatedSpriteParamsMultiFile {
 /// [`Types/RotatedSprite`](https://lua-api.factorio.com/latest/types/RotatedSprite.html)
 pub type RotatedSprite = MultiFileGraphics<RotatedSpriteParams, RotatedSpriteParamsMultiFile>;
 
+/// [`Types/RollingStockRotatedSlopedGraphics`](https://lua-api.factorio.com/latest/types/RollingStockRotatedSlopedGraphics.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollingStockRotatedSlopedGraphics {
+    pub rotated: RotatedSprite,
+    pub sloped: Option<RotatedSprite>,
+
+    #[serde(
+        default = "helper::f64_1_333",
+        skip_serializing_if = "helper::is_1_333_f64"
+    )]
+    pub slope_angle_between_frames: f64,
+
+    #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+    pub slope_back_equals_front: bool,
+}
+
+impl RenderableGraphics for RollingStockRotatedSlopedGraphics {
+    type RenderOpts = RotatedSpriteRenderOpts;
+
+    fn render(
+        &self,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        opts: &Self::RenderOpts,
+    ) -> Option<GraphicsOutput> {
+        // blueprints can't contain rolling stock on ramps, so the sloped frames are never needed
+        self.rotated.render(scale, used_mods, image_cache, opts)
+    }
+}
+
 /// [`Types/SpriteNWaySheet`](https://lua-api.factorio.com/latest/types/SpriteNWaySheet.html)
 /// variant for `Sprite4Way`
 #[derive(Debug, Serialize, Deserialize)]
@@ -1086,14 +1177,11 @@ impl RenderableGraphics for Sprite4Way {
                 east,
                 south,
                 west,
-            } => match opts.direction {
+            } => match opts.direction.four_way() {
                 Direction::North => north,
                 Direction::East => east,
                 Direction::South => south,
-                Direction::West => west,
-                _ => {
-                    unimplemented!("Sprite4Way does not support diagonals")
-                }
+                _ => west,
             }
             .render(scale, used_mods, image_cache, &opts.into()),
         }
@@ -1175,7 +1263,7 @@ pub struct SpriteSheetParams {
     )]
     pub repeat_count: u32,
 
-    // TODO: support the default based on variation_count
+    /// Frames per row of the sheet, [`None`] (or 0) puts all variations into one row.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub line_length: Option<u32>,
 
@@ -1260,6 +1348,62 @@ impl Scale for SpriteSheetParams {
 /// [`Types/SpriteSheet`](https://lua-api.factorio.com/latest/types/SpriteSheet.html)
 pub type SpriteSheet = SimpleGraphics<SpriteSheetParams>;
 
+impl SpriteSheet {
+    /// Renders the 1-based `variation`, wrapping around the `variation_count` of every layer.
+    fn render_variation(
+        &self,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        variation: NonZeroU32,
+        opts: &SimpleGraphicsRenderOpts,
+    ) -> Option<GraphicsOutput> {
+        match self {
+            Self::Layered { layers } => {
+                let renders = layers
+                    .iter()
+                    .map(|layer| {
+                        layer.render_variation(scale, used_mods, image_cache, variation, opts)
+                    })
+                    .collect::<Vec<_>>();
+
+                merge_renders(&renders, scale)
+            }
+            Self::Simple {
+                filename,
+                data,
+                hr_version,
+            } => {
+                // TODO: option to enable/disable HR mode
+                if let Some(hr_version) = hr_version {
+                    if scale < data.scale() {
+                        return hr_version.render_variation(
+                            scale,
+                            used_mods,
+                            image_cache,
+                            variation,
+                            opts,
+                        );
+                    }
+                }
+
+                let count = data.variation_count.max(1);
+                let index = (variation.get() - 1) % count;
+                let line_length = data.line_length.filter(|&len| len > 0).unwrap_or(count);
+
+                data.fetch_offset(
+                    scale,
+                    filename,
+                    used_mods,
+                    image_cache,
+                    opts.runtime_tint,
+                    ((index % line_length) as i16, (index / line_length) as i16),
+                )
+            }
+        }
+    }
+}
+
 /// [`Types/SpriteVariations`](https://lua-api.factorio.com/latest/types/SpriteVariations.html)
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -1271,6 +1415,7 @@ pub enum SpriteVariations {
 
 #[derive(Debug, Clone, Copy)]
 pub struct SpriteVariationsRenderOpts {
+    /// 1-based, wraps around the available variations.
     pub variation: NonZeroU32,
     pub runtime_tint: Option<Color>,
 }
@@ -1305,15 +1450,11 @@ impl RenderableGraphics for SpriteVariations {
     ) -> Option<GraphicsOutput> {
         match self {
             Self::Struct { sheet } | Self::SpriteSheet(sheet) => {
-                // TODO: implement variations here
-                sheet.render(scale, used_mods, image_cache, &opts.into())
+                sheet.render_variation(scale, used_mods, image_cache, opts.variation, &opts.into())
             }
-            Self::Array(variations) => variations.get((opts.variation.get() - 1) as usize)?.render(
-                scale,
-                used_mods,
-                image_cache,
-                &opts.into(),
-            ),
+            Self::Array(variations) => variations
+                .get(((opts.variation.get() - 1) as usize).checked_rem(variations.len())?)?
+                .render(scale, used_mods, image_cache, &opts.into()),
         }
     }
 }
@@ -1482,18 +1623,17 @@ impl FetchSprite for TileSpriteParams {
         let (offset_x, offset_y) = offset;
         let (width, height) = self.get_size();
 
-        let img = filename.load(used_mods, image_cache)?.crop_imm(
-            (x + offset_x) as u32,
-            (y + offset_y) as u32,
-            width as u32,
-            height as u32,
-        );
-
-        let mut img = img.resize(
-            (f64::from(img.width()) * self.scale / scale).round() as u32,
-            (f64::from(img.height()) * self.scale / scale).round() as u32,
-            image::imageops::FilterType::Nearest,
-        );
+        let mut img = filename.load_scaled(
+            used_mods,
+            image_cache,
+            (
+                (x + offset_x) as u32,
+                (y + offset_y) as u32,
+                width as u32,
+                height as u32,
+            ),
+            self.scale / scale,
+        )?;
 
         if let Some(tint) = runtime_tint {
             if !Color::is_white(&tint) {
@@ -1612,18 +1752,17 @@ impl FetchSprite for TileSpriteProbabilityParams {
         let (offset_x, offset_y) = offset;
         let (width, height) = self.get_size();
 
-        let img = filename.load(used_mods, image_cache)?.crop_imm(
-            (x + offset_x) as u32,
-            (y + offset_y) as u32,
-            width as u32,
-            height as u32,
-        );
-
-        let mut img = img.resize(
-            (f64::from(img.width()) * self.scale / scale).round() as u32,
-            (f64::from(img.height()) * self.scale / scale).round() as u32,
-            image::imageops::FilterType::Nearest,
-        );
+        let mut img = filename.load_scaled(
+            used_mods,
+            image_cache,
+            (
+                (x + offset_x) as u32,
+                (y + offset_y) as u32,
+                width as u32,
+                height as u32,
+            ),
+            self.scale / scale,
+        )?;
 
         if let Some(tint) = runtime_tint {
             if !Color::is_white(&tint) {
@@ -2127,25 +2266,13 @@ impl RenderableGraphics for Animation4Way {
                 east,
                 south,
                 west,
-            } => match opts.direction {
+            } => match opts.direction.four_way() {
                 Direction::North => north,
-                Direction::NorthEast => {
-                    unimplemented!("Animation4Way does not support diagonals")
-                }
                 Direction::East => east.as_ref().unwrap_or(north),
-                Direction::SouthEast => {
-                    unimplemented!("Animation4Way does not support diagonals")
-                }
                 Direction::South => south.as_ref().unwrap_or(north),
-                Direction::SouthWest => {
-                    unimplemented!("Animation4Way does not support diagonals")
-                }
-                Direction::West => west
+                _ => west
                     .as_ref()
                     .unwrap_or_else(|| east.as_ref().unwrap_or(north)),
-                Direction::NorthWest => {
-                    unimplemented!("Animation4Way does not support diagonals")
-                }
             }
             .render(scale, used_mods, image_cache, &opts.into()),
         }
@@ -2238,6 +2365,7 @@ pub enum AnimationVariations {
 
 #[derive(Debug, Clone, Copy)]
 pub struct AnimationVariationsRenderOpts {
+    /// 1-based, wraps around the available variations.
     pub variation: NonZeroU32,
     pub progress: f64,
     pub runtime_tint: Option<Color>,
@@ -2277,12 +2405,9 @@ impl RenderableGraphics for AnimationVariations {
             Self::Animation(animation) => {
                 animation.render(scale, used_mods, image_cache, &opts.into())
             }
-            Self::Array(animations) => animations.get((opts.variation.get() - 1) as usize)?.render(
-                scale,
-                used_mods,
-                image_cache,
-                &opts.into(),
-            ),
+            Self::Array(animations) => animations
+                .get(((opts.variation.get() - 1) as usize).checked_rem(animations.len())?)?
+                .render(scale, used_mods, image_cache, &opts.into()),
             Self::Sheets { sheets } => todo!(), //merge_layers(sheets,  used_mods, image_cache, opts),
             Self::Sheet { sheet } => todo!(),
         }
@@ -2630,16 +2755,13 @@ impl RenderableGraphics for RotatedAnimation4Way {
                 east,
                 south,
                 west,
-            } => match opts.direction {
+            } => match opts.direction.four_way() {
                 Direction::North => north,
                 Direction::East => east.as_ref().unwrap_or(north),
                 Direction::South => south.as_ref().unwrap_or(north),
-                Direction::West => west
+                _ => west
                     .as_ref()
                     .unwrap_or_else(|| east.as_ref().unwrap_or(north)),
-                _ => {
-                    unimplemented!("RotatedAnimation4Way does not support diagonals")
-                }
             }
             .render(scale, used_mods, image_cache, &opts.into()),
         }
@@ -2653,3 +2775,49 @@ pub enum RotatedAnimationVariations {
     Animation(RotatedAnimation),
     Array(FactorioArray<RotatedAnimation>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mipmap_level_halves_per_level() {
+        assert_eq!(mipmap_level(1.0, MAX_MIPMAP_LEVEL), 0);
+        assert_eq!(mipmap_level(0.5, MAX_MIPMAP_LEVEL), 0);
+        assert_eq!(mipmap_level(0.49, MAX_MIPMAP_LEVEL), 1);
+        assert_eq!(mipmap_level(0.25, MAX_MIPMAP_LEVEL), 2);
+        assert_eq!(mipmap_level(0.2, MAX_MIPMAP_LEVEL), 2);
+        assert_eq!(mipmap_level(0.125, MAX_MIPMAP_LEVEL), 3);
+    }
+
+    #[test]
+    fn mipmap_level_is_clamped() {
+        assert_eq!(mipmap_level(0.01, MAX_MIPMAP_LEVEL), MAX_MIPMAP_LEVEL);
+        assert_eq!(mipmap_level(0.01, 2), 2);
+        assert_eq!(mipmap_level(0.1, 0), 0);
+        assert_eq!(mipmap_level(0.0, MAX_MIPMAP_LEVEL), 0);
+        assert_eq!(mipmap_level(-1.0, MAX_MIPMAP_LEVEL), 0);
+    }
+
+    #[test]
+    fn mipmap_rect_is_right_of_the_sprite() {
+        assert_eq!(mipmap_rect(10, 20, 64, 32, 0), (10, 20, 64, 32));
+        assert_eq!(mipmap_rect(10, 20, 64, 32, 1), (74, 20, 32, 16));
+        assert_eq!(mipmap_rect(10, 20, 64, 32, 2), (106, 20, 16, 8));
+        assert_eq!(mipmap_rect(10, 20, 64, 32, 3), (122, 20, 8, 4));
+    }
+
+    #[test]
+    fn mipmap_count_fits_the_image() {
+        // no room right of the sprite
+        assert_eq!(mipmap_count(64, 64), 0);
+
+        // 64 + 32 + 16
+        assert_eq!(mipmap_count(112, 64), 2);
+        assert_eq!(mipmap_count(111, 64), 1);
+
+        // the levels stop at a width of 0 or the most levels a sprite can have
+        assert_eq!(mipmap_count(16, 8), 3);
+        assert_eq!(mipmap_count(4096, 64), MAX_MIPMAP_LEVEL);
+    }
+}
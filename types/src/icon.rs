@@ -1,13 +1,29 @@
-use image::Rgba;
+use image::{imageops, DynamicImage, Rgba};
 use serde::{Deserialize, Serialize};
 
-use crate::{merge_renders, FactorioArray, GraphicsOutput, ImageCache, RenderableGraphics};
+use crate::{
+    mipmap_count, mipmap_level, mipmap_rect, FactorioArray, GraphicsOutput, ImageCache,
+    RenderableGraphics,
+};
 
 use super::{helper, Color, FileName, SpriteSizeType, Vector};
 
 /// [`Types/IconMipMapType`](https://lua-api.factorio.com/latest/types/IconMipMapType.html)
 pub type IconMipMapType = u8;
 
+/// Size of the icon files when `icon_size` is not given.
+pub const DEFAULT_ICON_SIZE: SpriteSizeType = 64;
+
+/// `expected_icon_size` of items, recipes, fluids & signals.
+pub const EXPECTED_ICON_SIZE: f64 = 64.0;
+
+/// `expected_icon_size` of technologies.
+pub const EXPECTED_TECHNOLOGY_ICON_SIZE: f64 = 256.0;
+
+const fn default_icon_size() -> SpriteSizeType {
+    DEFAULT_ICON_SIZE
+}
+
 /// [`Types/IconData`](https://lua-api.factorio.com/latest/types/IconData.html)
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged)]
@@ -79,10 +95,24 @@ impl std::ops::Deref for IconData {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct IconDataRenderOpts {
+    /// `icon_size` of the prototype, used for layers without their own.
     pub icon_size: Option<SpriteSizeType>,
     pub icon_mipmaps: Option<IconMipMapType>,
+
+    /// Size the whole icon is displayed at, see [`EXPECTED_ICON_SIZE`].
+    pub expected_icon_size: f64,
+}
+
+impl Default for IconDataRenderOpts {
+    fn default() -> Self {
+        Self {
+            icon_size: None,
+            icon_mipmaps: None,
+            expected_icon_size: EXPECTED_ICON_SIZE,
+        }
+    }
 }
 
 impl RenderableGraphics for IconData {
@@ -95,44 +125,67 @@ impl RenderableGraphics for IconData {
         image_cache: &mut ImageCache,
         opts: &Self::RenderOpts,
     ) -> Option<GraphicsOutput> {
-        let ((Some(icon_size), _) | (None, Some(icon_size))) = (self.icon_size, opts.icon_size)
-        else {
-            return None;
-        };
+        let icon_size = self
+            .icon_size
+            .or(opts.icon_size)
+            .unwrap_or(DEFAULT_ICON_SIZE) as u32;
 
-        let icon_size = icon_size as u32;
-
-        // technically not 100% correct, technology icons default to 256/icon_size
+        // the whole icon is `expected_icon_size / 2` pixels in size at a scale of 1
         let icon_scale = self
             .scale
-            .map_or_else(|| 32.0 / f64::from(icon_size), |scale| scale);
+            .unwrap_or_else(|| opts.expected_icon_size / 2.0 / f64::from(icon_size));
 
-        let img = self
-            .icon()
-            .load(used_mods, image_cache)?
-            .crop_imm(0, 0, icon_size, icon_size);
+        // 2.0 icons always have their mipmaps right of the full size icon
+        let factor = icon_scale / scale;
+        let image_width = self.icon().load(used_mods, image_cache)?.width();
+        let level = mipmap_level(factor, mipmap_count(image_width, icon_size));
 
-        let mut img = img.resize(
-            (f64::from(img.width()) * icon_scale / scale).round() as u32,
-            (f64::from(img.height()) * icon_scale / scale).round() as u32,
-            image::imageops::FilterType::Nearest,
-        );
+        let mut img = self.icon().load_scaled(
+            used_mods,
+            image_cache,
+            mipmap_rect(0, 0, icon_size, icon_size, level),
+            factor * f64::from(1u32 << level),
+        )?;
 
         if !Color::is_white(&self.tint) {
-            let mut img_buf = img.to_rgba8();
-            let [tint_r, tint_g, tint_b, tint_a] = self.tint.to_rgba();
-
-            for Rgba([r, g, b, a]) in img_buf.pixels_mut() {
-                *r = (f64::from(*r) * tint_r).round() as u8;
-                *g = (f64::from(*g) * tint_g).round() as u8;
-                *b = (f64::from(*b) * tint_b).round() as u8;
-                *a = (f64::from(*a) * tint_a).round() as u8;
-            }
-            img = img_buf.into();
+            img = tint_premultiplied(&img, &self.tint);
+        }
+
+        // shifts are given in pixels of the whole icon, which is drawn with 32 pixels per tile
+        let shift = self.shift / 32.0;
+
+        Some((img, shift))
+    }
+}
+
+/// Tints the image like the game does, which multiplies the color channels premultiplied by alpha.
+/// Layers that end up fully transparent, e.g. additive layers with a tint alpha of 0, are dropped.
+fn tint_premultiplied(img: &image::DynamicImage, tint: &Color) -> image::DynamicImage {
+    let mut img_buf = img.to_rgba8();
+    let [tint_r, tint_g, tint_b, tint_a] = tint.to_rgba();
+
+    for Rgba([r, g, b, a]) in img_buf.pixels_mut() {
+        let alpha = f64::from(*a) / 255.0;
+        let res_alpha = alpha * tint_a;
+
+        if res_alpha <= 0.0 {
+            *a = 0;
+            continue;
         }
 
-        Some((img, self.shift))
+        let channel = |value: u8, tint: f64| {
+            (f64::from(value) * alpha * tint / res_alpha)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+
+        *r = channel(*r, tint_r);
+        *g = channel(*g, tint_g);
+        *b = channel(*b, tint_b);
+        *a = (res_alpha * 255.0).round() as u8;
     }
+
+    img_buf.into()
 }
 
 /// [`Types/IconData`](https://lua-api.factorio.com/latest/types/IconData.html)
@@ -155,7 +208,7 @@ pub struct CommonIconData {
     #[serde(default, skip_serializing_if = "Vector::is_0_vector")]
     pub shift: Vector,
 
-    // TODO: Defaults to `32/icon_size` for items and recipes, `256/icon_size` for technologies.
+    /// Defaults to `expected_icon_size / 2 / icon_size`, see [`IconDataRenderOpts::expected_icon_size`].
     pub scale: Option<f64>,
 
     #[serde(
@@ -189,7 +242,10 @@ pub enum Icon {
     Single {
         icon: FileName,
 
-        #[serde(deserialize_with = "helper::truncating_deserializer")]
+        #[serde(
+            default = "default_icon_size",
+            deserialize_with = "helper::truncating_deserializer"
+        )]
         icon_size: SpriteSizeType,
 
         #[serde(
@@ -201,15 +257,17 @@ pub enum Icon {
     },
 }
 
-impl RenderableGraphics for Icon {
-    type RenderOpts = ();
-
-    fn render(
+impl Icon {
+    /// Renders the icon for a prototype with the given `expected_icon_size`,
+    /// e.g. [`EXPECTED_TECHNOLOGY_ICON_SIZE`] for technologies.
+    ///
+    /// The result is centered on the icon, layers shifted outside of it grow the image on both sides.
+    pub fn render_expected(
         &self,
+        expected_icon_size: f64,
         scale: f64,
         used_mods: &mod_util::UsedMods,
         image_cache: &mut ImageCache,
-        opts: &Self::RenderOpts,
     ) -> Option<GraphicsOutput> {
         match self {
             Self::Array {
@@ -224,6 +282,7 @@ impl RenderableGraphics for Icon {
                 &IconDataRenderOpts {
                     icon_size: *icon_size,
                     icon_mipmaps: Some(*icon_mipmaps),
+                    expected_icon_size,
                 },
             ),
             Self::Single {
@@ -244,12 +303,33 @@ impl RenderableGraphics for Icon {
                 scale,
                 used_mods,
                 image_cache,
-                &IconDataRenderOpts::default(),
+                &IconDataRenderOpts {
+                    expected_icon_size,
+                    ..Default::default()
+                },
             ),
         }
     }
 }
 
+impl RenderableGraphics for Icon {
+    type RenderOpts = ();
+
+    fn render(
+        &self,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut ImageCache,
+        opts: &Self::RenderOpts,
+    ) -> Option<GraphicsOutput> {
+        self.render_expected(EXPECTED_ICON_SIZE, scale, used_mods, image_cache)
+    }
+}
+
+/// Stacks the icon layers on top of each other, the first layer is at the bottom.
+///
+/// The shifts of the layers (in tiles) are relative to the center of the icon,
+/// the merged image stays centered on it so it can be drawn without an additional shift.
 pub fn merge_icon_layers<O, T: RenderableGraphics<RenderOpts = O>>(
     layers: &[T],
     scale: f64,
@@ -257,6 +337,8 @@ pub fn merge_icon_layers<O, T: RenderableGraphics<RenderOpts = O>>(
     image_cache: &mut ImageCache,
     opts: &O,
 ) -> Option<GraphicsOutput> {
+    const TILE_RES: f64 = 32.0;
+
     let layers = layers
         .iter()
         .filter_map(|layer| layer.render(scale, used_mods, image_cache, opts))
@@ -266,17 +348,41 @@ pub fn merge_icon_layers<O, T: RenderableGraphics<RenderOpts = O>>(
         return None;
     }
 
-    let (base_icon, base_shift) = &layers[0];
-    let base_size = f64::from(base_icon.width());
-
-    let layers = layers
-        .iter()
-        .map(|(img, shift)| {
-            let shift = shift - base_shift;
-
-            Some((img.clone(), shift / base_size))
-        })
-        .collect::<Vec<_>>();
+    let px_per_tile = TILE_RES / scale;
+    let (half_width, half_height) = layers.iter().fold(
+        (0.0f64, 0.0f64),
+        |(half_width, half_height), (img, shift)| {
+            let (shift_x, shift_y) = shift.as_tuple();
+
+            (
+                half_width.max(
+                    shift_x
+                        .abs()
+                        .mul_add(px_per_tile, f64::from(img.width()) / 2.0),
+                ),
+                half_height.max(
+                    shift_y
+                        .abs()
+                        .mul_add(px_per_tile, f64::from(img.height()) / 2.0),
+                ),
+            )
+        },
+    );
+
+    let mut combined = DynamicImage::new_rgba8(
+        (half_width * 2.0).ceil() as u32,
+        (half_height * 2.0).ceil() as u32,
+    );
+    let center_x = f64::from(combined.width()) / 2.0;
+    let center_y = f64::from(combined.height()) / 2.0;
+
+    for (img, shift) in &layers {
+        let (shift_x, shift_y) = shift.as_tuple();
+        let x = shift_x.mul_add(px_per_tile, center_x - f64::from(img.width()) / 2.0);
+        let y = shift_y.mul_add(px_per_tile, center_y - f64::from(img.height()) / 2.0);
+
+        imageops::overlay(&mut combined, img, x.round() as i64, y.round() as i64);
+    }
 
-    merge_renders(layers.as_slice(), scale)
+    Some((combined, Vector::default()))
 }
@@ -35,6 +35,7 @@ macro_rules! ids {
 
 ids!(
     AmmoCategoryID,
+    AsteroidChunkID,
     DamageTypeID,
     EntityID,
     EquipmentGridID,
@@ -45,9 +46,12 @@ ids!(
     ItemID,
     ItemSubGroupID,
     MouseCursorID,
+    QualityID,
     RecipeCategoryID,
     RecipeID,
     ResourceCategoryID,
+    SpaceLocationID,
+    TechnologyID,
     TileID,
     VirtualSignalID
 );
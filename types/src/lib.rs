@@ -5,7 +5,11 @@
     clippy::module_name_repetitions
 )]
 
-use std::{collections::HashMap, fmt, hash::Hash};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+};
 
 use konst::{
     iter::collect_const, primitive::parse_u16, result::unwrap_ctx, string::split as konst_split,
@@ -16,7 +20,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_with::skip_serializing_none;
 use tracing::warn;
 
-use mod_util::{mod_info::Version, UsedMods};
+use mod_util::{mod_info::Version, sprite_source::SpriteSource, UsedMods};
 
 #[must_use]
 pub const fn targeted_engine_version() -> Version {
@@ -35,6 +39,8 @@ mod icon;
 mod ids;
 mod item;
 mod module;
+mod sound;
+mod trigger;
 mod wire;
 
 pub use empty_array_fix::*;
@@ -44,6 +50,8 @@ pub use icon::*;
 pub use ids::*;
 pub use item::*;
 pub use module::*;
+pub use sound::*;
+pub use trigger::*;
 pub use wire::*;
 
 /// [`Types/AmmoType`](https://lua-api.factorio.com/latest/types/AmmoType.html)
@@ -70,8 +78,8 @@ pub struct AmmoType {
 
     #[serde(default, skip_serializing_if = "helper::is_default")]
     pub source_type: AmmoSourceType,
-    // not implemented
-    // pub action: Option<Trigger>,
+
+    pub action: Option<Trigger>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -95,6 +103,7 @@ pub enum AmmoSourceType {
 }
 
 /// [`Types/BaseAttackParameters`](https://lua-api.factorio.com/latest/types/BaseAttackParameters.html)
+#[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BaseAttackParameters {
     pub range: f32,
@@ -149,9 +158,11 @@ pub struct BaseAttackParameters {
 
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub use_shooter_direction: bool,
+
+    pub sound: Option<LayeredSound>,
+    pub cyclic_sound: Option<CyclicSound>,
     // not implemented
     // ammo_type, ammo_categories, ammo_category: are these mutually exclusive?
-    // sound, cyclic_sound
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -650,7 +661,99 @@ pub enum Vector3D {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileName(String);
 
-pub type ImageCache = HashMap<String, Option<image::DynamicImage>>;
+/// Decoded sprite files by their [`FileName`] and the cropped & resized sprites of [`FileName::load_scaled`].
+///
+/// Files are kept as long as the cache, the resized sprites are bounded by [`ImageCache::max_scaled_bytes`]
+/// and evicted first in first out, so a long running process that renders at many scales doesn't grow without limit.
+/// Cache hits don't refresh an entry, a sprite that is used all the time is still evicted once it is the oldest.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    files: HashMap<String, Option<image::DynamicImage>>,
+    scaled: HashMap<String, image::DynamicImage>,
+    scaled_order: VecDeque<String>,
+    scaled_bytes: usize,
+    max_scaled_bytes: usize,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageCache {
+    /// Default limit of the resized sprites, far more than a single large render needs.
+    pub const DEFAULT_MAX_SCALED_BYTES: usize = 256 * 1024 * 1024;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_scaled_bytes(Self::DEFAULT_MAX_SCALED_BYTES)
+    }
+
+    #[must_use]
+    pub fn with_max_scaled_bytes(max_scaled_bytes: usize) -> Self {
+        Self {
+            files: HashMap::new(),
+            scaled: HashMap::new(),
+            scaled_order: VecDeque::new(),
+            scaled_bytes: 0,
+            max_scaled_bytes,
+        }
+    }
+
+    #[must_use]
+    pub const fn max_scaled_bytes(&self) -> usize {
+        self.max_scaled_bytes
+    }
+
+    /// Memory used by the resized sprites in bytes.
+    #[must_use]
+    pub const fn scaled_bytes(&self) -> usize {
+        self.scaled_bytes
+    }
+
+    fn insert_scaled(&mut self, key: String, img: image::DynamicImage) {
+        let size = img.as_bytes().len();
+        if size > self.max_scaled_bytes {
+            return;
+        }
+
+        while self.scaled_bytes + size > self.max_scaled_bytes {
+            let Some(oldest) = self.scaled_order.pop_front() else {
+                break;
+            };
+
+            if let Some(evicted) = self.scaled.remove(&oldest) {
+                self.scaled_bytes -= evicted.as_bytes().len();
+            }
+        }
+
+        self.scaled_bytes += size;
+        self.scaled_order.push_back(key.clone());
+        self.scaled.insert(key, img);
+    }
+}
+
+/// Counter of [`ImageCache`] lookups that were served from the cache, source images & scaled sprites alike.
+pub const IMAGE_CACHE_HITS: &str = "scanner_image_cache_hits_total";
+
+/// Counter of [`ImageCache`] lookups that had to load or scale the image.
+pub const IMAGE_CACHE_MISSES: &str = "scanner_image_cache_misses_total";
+
+// empty without the `metrics` feature
+#[allow(clippy::missing_const_for_fn)]
+fn record_cache_lookup(hit: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(if hit {
+        IMAGE_CACHE_HITS
+    } else {
+        IMAGE_CACHE_MISSES
+    })
+    .increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = hit;
+}
 
 impl FileName {
     #[must_use]
@@ -658,27 +761,27 @@ impl FileName {
         Self(filename)
     }
 
+    /// Loads & decodes the image through `source`, usually the [`UsedMods`].
+    /// Results are cached, including failed loads.
     pub fn load<'a>(
         &self,
-        used_mods: &UsedMods,
+        source: &(impl SpriteSource + ?Sized),
         image_cache: &'a mut ImageCache,
     ) -> Option<&'a image::DynamicImage> {
         let filename = &self.0;
 
-        if image_cache.contains_key(filename) {
-            return image_cache.get(filename)?.as_ref();
+        let hit = image_cache.files.contains_key(filename);
+        record_cache_lookup(hit);
+
+        if hit {
+            return image_cache.files.get(filename)?.as_ref();
         }
 
         let re = regex::Regex::new(r"^__([^/\\]+)__").ok()?;
         let mod_name = re.captures(filename)?.get(1)?.as_str();
         let sprite_path = &filename[(2 + mod_name.len() + 2 + 1)..]; // +1 to include the slash to prevent joining to interpret it as a absolute path
 
-        let Some(m) = used_mods.get(mod_name) else {
-            warn!("Mod {mod_name} not found");
-            return None;
-        };
-
-        let file_data = match m.get_file(sprite_path) {
+        let file_data = match source.fetch(mod_name, sprite_path) {
             Ok(d) => d,
             Err(e) => {
                 warn!("Error loading {filename}: {e}");
@@ -686,14 +789,43 @@ impl FileName {
             }
         };
 
-        let img = image::load_from_memory_with_format(
-            &used_mods.get(mod_name)?.get_file(sprite_path).ok()?,
-            image::ImageFormat::Png,
-        )
-        .ok();
+        let img = image::load_from_memory_with_format(&file_data, image::ImageFormat::Png).ok();
+
+        image_cache.files.insert(filename.clone(), img);
+        image_cache.files.get(filename)?.as_ref()
+    }
+
+    /// Crops `rect` (`x`, `y`, `width`, `height`) out of the image and resizes it by `factor`.
+    /// The result is cached as well, so sprites drawn many times at the same scale are only resized once.
+    /// Unlike the files these entries are evicted once they exceed [`ImageCache::max_scaled_bytes`].
+    pub fn load_scaled(
+        &self,
+        source: &(impl SpriteSource + ?Sized),
+        image_cache: &mut ImageCache,
+        rect: (u32, u32, u32, u32),
+        factor: f64,
+    ) -> Option<image::DynamicImage> {
+        let (x, y, width, height) = rect;
+        let key = format!("{}#{x},{y},{width},{height}@{factor}", self.0);
+
+        let cached = image_cache.scaled.get(&key);
+        record_cache_lookup(cached.is_some());
+
+        if let Some(img) = cached {
+            return Some(img.clone());
+        }
 
-        image_cache.insert(filename.clone(), img);
-        image_cache.get(filename)?.as_ref()
+        let img = self
+            .load(source, image_cache)?
+            .crop_imm(x, y, width, height);
+        let img = img.resize(
+            (f64::from(img.width()) * factor).round() as u32,
+            (f64::from(img.height()) * factor).round() as u32,
+            image::imageops::FilterType::Nearest,
+        );
+
+        image_cache.insert_scaled(key, img.clone());
+        Some(img)
     }
 }
 
@@ -704,7 +836,7 @@ pub enum LocalisedString {
     Bool(bool),
     String(String),
     Number(f64),
-    Array(FactorioArray<LocalisedString>),
+    Array(FactorioArray<Self>),
 }
 
 /// [`Types/Order`](https://lua-api.factorio.com/latest/types/Order.html)
@@ -1050,6 +1182,9 @@ pub enum PipeConnectionDefinition {
     Static {
         position: Vector,
 
+        /// 2.0 connections are placed inside of the entity and face their neighbour in this direction.
+        direction: Option<Direction16>,
+
         #[serde(
             default,
             skip_serializing_if = "helper::is_default",
@@ -1152,6 +1287,25 @@ pub struct FluidBox {
 }
 
 impl FluidBox {
+    /// Returns the largest underground distance of all pipe connections, if any connection goes underground.
+    #[must_use]
+    pub fn max_underground_distance(&self) -> Option<u32> {
+        self.pipe_connections
+            .iter()
+            .map(|c| match c {
+                PipeConnectionDefinition::Directional {
+                    max_underground_distance,
+                    ..
+                }
+                | PipeConnectionDefinition::Static {
+                    max_underground_distance,
+                    ..
+                } => *max_underground_distance,
+            })
+            .filter(|&d| d != 0)
+            .max()
+    }
+
     #[must_use]
     pub fn connection_points(&self, direction: Direction) -> Vec<MapPosition> {
         self.pipe_connections
@@ -1171,6 +1325,7 @@ impl FluidBox {
                 }
                 PipeConnectionDefinition::Static {
                     position,
+                    direction: facing,
                     max_underground_distance,
                     ..
                 } => {
@@ -1178,19 +1333,30 @@ impl FluidBox {
                         return None;
                     }
 
-                    Some(direction.rotate_vector(*position).into())
+                    // the connected position of 2.0 connections is the neighbouring tile
+                    let position = facing.map_or(*position, |facing| {
+                        *position + facing.eight_way().get_offset()
+                    });
+
+                    Some(direction.rotate_vector(position).into())
                 }
             })
             .collect()
     }
 }
 
+/// [`Types/SignalIDConnector`](https://lua-api.factorio.com/latest/types/SignalIDConnector.html)
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "lowercase")]
+#[serde(tag = "type", rename_all = "kebab-case")]
 pub enum SignalIDConnector {
     Virtual { name: VirtualSignalID },
     Item { name: ItemID },
     Fluid { name: FluidID },
+    Recipe { name: RecipeID },
+    Entity { name: EntityID },
+    SpaceLocation { name: SpaceLocationID },
+    AsteroidChunk { name: AsteroidChunkID },
+    Quality { name: QualityID },
 }
 
 /// [`Types/SelectionModeFlags`](https://lua-api.factorio.com/latest/types/SelectionModeFlags.html)
@@ -1261,8 +1427,33 @@ pub enum PlaceableBy {
     Multiple(FactorioArray<ItemToPlace>),
 }
 
-/// [`Types/CollisionMask`](https://lua-api.factorio.com/latest/types/CollisionMask.html)
-pub type CollisionMask = FactorioArray<String>;
+/// [`Types/CollisionLayerID`](https://lua-api.factorio.com/latest/types/CollisionLayerID.html)
+pub type CollisionLayerID = String;
+
+/// [`Types/CollisionMaskConnector`](https://lua-api.factorio.com/latest/types/CollisionMaskConnector.html)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CollisionMaskConnector {
+    /// Every layer of the mask, the values are always `true`.
+    pub layers: HashMap<CollisionLayerID, bool>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub not_colliding_with_itself: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub consider_tile_transitions: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub colliding_with_tiles_only: bool,
+}
+
+impl CollisionMaskConnector {
+    /// Names of the layers in this mask.
+    pub fn layer_names(&self) -> impl Iterator<Item = &CollisionLayerID> {
+        self.layers
+            .iter()
+            .filter_map(|(layer, &active)| active.then_some(layer))
+    }
+}
 
 /// Union used in [`Types/EntityPrototypeFlags`](https://lua-api.factorio.com/latest/types/EntityPrototypeFlags.html)
 #[derive(Debug, Serialize, Deserialize)]
@@ -1331,7 +1522,7 @@ impl MapPosition {
     }
 
     #[must_use]
-    pub fn as_tuple_mut(&mut self) -> (&mut f64, &mut f64) {
+    pub const fn as_tuple_mut(&mut self) -> (&mut f64, &mut f64) {
         match self {
             Self::Tuple(x, y) | Self::XY { x, y } => (x, y),
         }
@@ -1410,11 +1601,11 @@ impl MapPosition {
     }
 
     #[must_use]
-    pub fn center_to(&self, other: &Self) -> Self {
+    pub const fn center_to(&self, other: &Self) -> Self {
         let (x1, y1) = self.as_tuple();
         let (x2, y2) = other.as_tuple();
 
-        Self::Tuple((x1 + x2) / 2.0, (y1 + y2) / 2.0)
+        Self::Tuple(f64::midpoint(x1, x2), f64::midpoint(y1, y2))
     }
 
     #[must_use]
@@ -1617,6 +1808,15 @@ impl std::ops::DivAssign<f64> for MapPosition {
 pub struct BoundingBox(pub MapPosition, pub MapPosition);
 
 impl BoundingBox {
+    /// Box spanned by two opposite corners in any order.
+    #[must_use]
+    pub const fn from_corners(a: &MapPosition, b: &MapPosition) -> Self {
+        Self(
+            MapPosition::Tuple(a.x().min(b.x()), a.y().min(b.y())),
+            MapPosition::Tuple(a.x().max(b.x()), a.y().max(b.y())),
+        )
+    }
+
     #[must_use]
     pub const fn top_left(&self) -> &MapPosition {
         &self.0
@@ -1658,11 +1858,102 @@ impl BoundingBox {
     }
 
     #[must_use]
-    pub fn center(&self) -> MapPosition {
+    pub const fn center(&self) -> MapPosition {
         let (x1, y1) = self.0.as_tuple();
         let (x2, y2) = self.1.as_tuple();
 
-        MapPosition::Tuple((x1 + x2) / 2.0, (y1 + y2) / 2.0)
+        MapPosition::Tuple(f64::midpoint(x1, x2), f64::midpoint(y1, y2))
+    }
+
+    /// Whether the position is inside the box or on its edge.
+    #[must_use]
+    pub fn contains(&self, position: &MapPosition) -> bool {
+        let (x, y) = position.as_tuple();
+
+        x >= self.left() && x <= self.right() && y >= self.top() && y <= self.bottom()
+    }
+
+    /// Whether both boxes overlap. Boxes that only touch at their edges do not intersect.
+    #[must_use]
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.left() < other.right()
+            && other.left() < self.right()
+            && self.top() < other.bottom()
+            && other.top() < self.bottom()
+    }
+
+    /// Overlapping part of both boxes, `None` if they don't [intersect](Self::intersects).
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        self.intersects(other).then(|| {
+            Self(
+                MapPosition::Tuple(self.left().max(other.left()), self.top().max(other.top())),
+                MapPosition::Tuple(
+                    self.right().min(other.right()),
+                    self.bottom().min(other.bottom()),
+                ),
+            )
+        })
+    }
+
+    /// Smallest box containing both boxes.
+    #[must_use]
+    pub const fn union(&self, other: &Self) -> Self {
+        Self(
+            MapPosition::Tuple(self.left().min(other.left()), self.top().min(other.top())),
+            MapPosition::Tuple(
+                self.right().max(other.right()),
+                self.bottom().max(other.bottom()),
+            ),
+        )
+    }
+
+    /// Box grown by `amount` on every side, negative amounts shrink it.
+    #[must_use]
+    pub const fn expand(&self, amount: f64) -> Self {
+        Self(
+            MapPosition::Tuple(self.left() - amount, self.top() - amount),
+            MapPosition::Tuple(self.right() + amount, self.bottom() + amount),
+        )
+    }
+
+    /// Coordinates of all tiles the box covers at least partially, row by row.
+    ///
+    /// Tile `(x, y)` spans from `x` to `x + 1` & `y` to `y + 1`, tiles only touched at an edge are not covered.
+    pub fn tiles(&self) -> impl Iterator<Item = (i32, i32)> {
+        let left = self.left().floor() as i32;
+        let top = self.top().floor() as i32;
+        let right = (self.right().ceil() as i32).max(left);
+        let bottom = (self.bottom().ceil() as i32).max(top);
+
+        (top..bottom).flat_map(move |y| (left..right).map(move |x| (x, y)))
+    }
+
+    /// Axis aligned box containing this box rotated to fit the direction.
+    /// The box is assumed to be in the north direction.
+    #[must_use]
+    pub fn rotate(&self, direction: Direction) -> Self {
+        let corners = [
+            (self.left(), self.top()),
+            (self.right(), self.top()),
+            (self.right(), self.bottom()),
+            (self.left(), self.bottom()),
+        ]
+        .map(|corner| direction.rotate_vector(corner.into()).as_tuple());
+
+        let (mut min_x, mut min_y) = corners[0];
+        let (mut max_x, mut max_y) = corners[0];
+        for (x, y) in corners {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+
+        Self(
+            MapPosition::Tuple(min_x, min_y),
+            MapPosition::Tuple(max_x, max_y),
+        )
     }
 }
 
@@ -1710,17 +2001,19 @@ impl Direction {
 
     /// Rotate the provided vector to fit the direction.
     /// The vector is assumed to be in the north direction.
+    ///
+    /// Cardinal directions swap & mirror the components so they stay exact,
+    /// diagonal directions rotate the vector by their orientation.
     #[must_use]
     pub fn rotate_vector(self, vector: Vector) -> Vector {
         let (x_fac, y_fac, swap) = match self {
             Self::North => (1.0, 1.0, false),
-            Self::NorthEast => todo!(),
             Self::East => (-1.0, 1.0, true),
-            Self::SouthEast => todo!(),
             Self::South => (-1.0, -1.0, false),
-            Self::SouthWest => todo!(),
             Self::West => (1.0, -1.0, true),
-            Self::NorthWest => todo!(),
+            Self::NorthEast | Self::SouthEast | Self::SouthWest | Self::NorthWest => {
+                return vector.rotate(self.to_orientation());
+            }
         };
 
         let (x, y) = if swap {
@@ -1732,6 +2025,32 @@ impl Direction {
         Vector::new(x * x_fac, y * y_fac)
     }
 
+    /// Nearest cardinal direction, diagonals are rounded counter-clockwise.
+    #[must_use]
+    pub const fn to_cardinal(self) -> Self {
+        match self {
+            Self::North | Self::NorthEast => Self::North,
+            Self::East | Self::SouthEast => Self::East,
+            Self::South | Self::SouthWest => Self::South,
+            Self::West | Self::NorthWest => Self::West,
+        }
+    }
+
+    /// Direction to pick the sprite of a 4-way sprite with, see [`Self::to_cardinal`].
+    ///
+    /// 4-way sprites have nothing to show for diagonals, so those are reported instead of silently rounded.
+    #[must_use]
+    pub fn four_way(self) -> Self {
+        let cardinal = self.to_cardinal();
+        if cardinal != self {
+            warn!(
+                "4-way sprites only support cardinal directions, using {cardinal:?} for {self:?}"
+            );
+        }
+
+        cardinal
+    }
+
     #[must_use]
     pub const fn is_straight(&self, other: &Self) -> bool {
         match self {
@@ -1820,6 +2139,80 @@ impl TryFrom<u8> for Direction {
     }
 }
 
+/// Direction in the 16 steps of 2.0 blueprints & prototypes, the odd steps are used by half-diagonal rails.
+/// Only the 8 directions of [`Self::eight_way`] are rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "u8", into = "u8")]
+pub struct Direction16(u8);
+
+impl Direction16 {
+    #[must_use]
+    pub const fn new(step: u8) -> Self {
+        Self(step % 16)
+    }
+
+    #[must_use]
+    pub const fn step(self) -> u8 {
+        self.0
+    }
+
+    /// The rendered direction, odd steps are rounded down to the previous one of the 8 directions.
+    #[must_use]
+    pub const fn eight_way(self) -> Direction {
+        match self.0 / 2 {
+            0 => Direction::North,
+            1 => Direction::NorthEast,
+            2 => Direction::East,
+            3 => Direction::SouthEast,
+            4 => Direction::South,
+            5 => Direction::SouthWest,
+            6 => Direction::West,
+            _ => Direction::NorthWest,
+        }
+    }
+
+    #[must_use]
+    pub fn to_orientation(self) -> RealOrientation {
+        RealOrientation::new(f64::from(self.0) / 16.0)
+    }
+
+    /// Rotate the provided vector, assumed to be in the north direction, to fit the direction.
+    ///
+    /// The 8 directions rotate like [`Direction::rotate_vector`], the odd steps by their own orientation.
+    #[must_use]
+    pub fn rotate_vector(self, vector: Vector) -> Vector {
+        if self.0.is_multiple_of(2) {
+            return self.eight_way().rotate_vector(vector);
+        }
+
+        vector.rotate(self.to_orientation())
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    #[must_use]
+    pub const fn is_default(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<u8> for Direction16 {
+    fn from(step: u8) -> Self {
+        Self::new(step)
+    }
+}
+
+impl From<Direction16> for u8 {
+    fn from(direction: Direction16) -> Self {
+        direction.0
+    }
+}
+
+impl From<Direction> for Direction16 {
+    fn from(direction: Direction) -> Self {
+        Self(direction as u8 * 2)
+    }
+}
+
 /// Single element of [`Types/Resistances`](https://lua-api.factorio.com/latest/types/Resistances.html)
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Resistance {
@@ -1898,6 +2291,16 @@ pub enum LightDefinition {
     Array(FactorioArray<LightDefinitionData>),
 }
 
+impl LightDefinition {
+    /// All lights of the definition, either the single one or every entry of the array.
+    pub fn lights(&self) -> impl Iterator<Item = &LightDefinitionData> {
+        match self {
+            Self::Struct(light) => std::slice::from_ref(light).iter(),
+            Self::Array(lights) => lights.iter(),
+        }
+    }
+}
+
 /// [`Types/BoxSpecification`](https://lua-api.factorio.com/latest/types/BoxSpecification.html)
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
@@ -2062,13 +2465,14 @@ pub struct BeaconGraphicsSet {
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct BeaconGraphicsSetRenderOpts {
+    pub progress: f64,
     pub runtime_tint: Option<Color>,
 }
 
 impl From<&BeaconGraphicsSetRenderOpts> for AnimationRenderOpts {
     fn from(value: &BeaconGraphicsSetRenderOpts) -> Self {
         Self {
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -2229,6 +2633,7 @@ pub struct TransportBeltAnimationSet {
 pub struct TransportBeltAnimationSetRenderOpts {
     pub direction: Direction,
     pub connections: Option<ConnectedDirections>,
+    pub progress: f64,
 
     pub runtime_tint: Option<Color>,
 
@@ -2238,7 +2643,7 @@ pub struct TransportBeltAnimationSetRenderOpts {
 impl From<&TransportBeltAnimationSetRenderOpts> for RotatedAnimationRenderOpts {
     fn from(value: &TransportBeltAnimationSetRenderOpts) -> Self {
         Self {
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
             orientation: RealOrientation::default(),
             override_index: value.index_override,
@@ -2488,7 +2893,7 @@ impl From<&WorkingVisualisationRenderOpts> for AnimationRenderOpts {
 impl From<&MiningDrillGraphicsRenderOpts> for WorkingVisualisationRenderOpts {
     fn from(value: &MiningDrillGraphicsRenderOpts) -> Self {
         Self {
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
             direction: value.direction,
         }
@@ -2632,7 +3037,9 @@ impl GuiMode {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HeatConnection {
     pub position: MapPosition,
-    pub direction: Direction,
+
+    /// 2.0 connections face their neighbour in one of the 16 directions.
+    pub direction: Direction16,
 }
 
 /// [`Types/HeatBuffer`](https://lua-api.factorio.com/latest/types/HeatBuffer.html)
@@ -2670,7 +3077,7 @@ impl HeatBuffer {
         self.connections
             .iter()
             .map(|c| {
-                let offset: MapPosition = (c.direction.get_offset()).into();
+                let offset: MapPosition = (c.direction.eight_way().get_offset()).into();
                 c.position + offset
             })
             .collect()
@@ -2920,6 +3327,7 @@ pub struct MiningDrillGraphicsSet {
 #[derive(Debug, Clone, Copy, Default)]
 pub struct MiningDrillGraphicsRenderOpts {
     pub direction: Direction,
+    pub progress: f64,
     pub runtime_tint: Option<Color>,
 }
 
@@ -2927,7 +3335,7 @@ impl From<&MiningDrillGraphicsRenderOpts> for Animation4WayRenderOpts {
     fn from(value: &MiningDrillGraphicsRenderOpts) -> Self {
         Self {
             direction: value.direction,
-            progress: 0.0,
+            progress: value.progress,
             runtime_tint: value.runtime_tint,
         }
     }
@@ -3018,8 +3426,9 @@ pub enum CircuitConnectorSecondaryDrawOrder {
 }
 
 // Comparator variants
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub enum Comparator {
+    #[default]
     #[serde(rename = "<")]
     Less,
     #[serde(rename = "≤", alias = "<=")]
@@ -3076,3 +3485,145 @@ pub enum ArithmeticOperation {
     #[serde(rename = "?", other)]
     Unknown,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vector, expected: (f64, f64)) {
+        let (x, y) = actual.as_tuple();
+        assert!(
+            (x - expected.0).abs() < 1e-9 && (y - expected.1).abs() < 1e-9,
+            "expected {expected:?}, got {:?}",
+            (x, y)
+        );
+    }
+
+    #[test]
+    fn rotate_vector_cardinal() {
+        let vector = Vector::new(1.0, -2.0);
+
+        assert_eq!(
+            Direction::North.rotate_vector(vector).as_tuple(),
+            (1.0, -2.0)
+        );
+        assert_eq!(Direction::East.rotate_vector(vector).as_tuple(), (2.0, 1.0));
+        assert_eq!(
+            Direction::South.rotate_vector(vector).as_tuple(),
+            (-1.0, 2.0)
+        );
+        assert_eq!(
+            Direction::West.rotate_vector(vector).as_tuple(),
+            (-2.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn rotate_vector_diagonal() {
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let up = Vector::new(0.0, -1.0);
+
+        assert_close(Direction::NorthEast.rotate_vector(up), (half, -half));
+        assert_close(Direction::SouthEast.rotate_vector(up), (half, half));
+        assert_close(Direction::SouthWest.rotate_vector(up), (-half, half));
+        assert_close(Direction::NorthWest.rotate_vector(up), (-half, -half));
+    }
+
+    #[test]
+    fn rotate_vector_matches_offsets() {
+        let up = Direction::North.get_offset();
+
+        for direction in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            assert_close(
+                direction.rotate_vector(up),
+                direction.get_offset().as_tuple(),
+            );
+        }
+    }
+
+    #[test]
+    fn rotate_vector_16_way() {
+        let up = Vector::new(0.0, -1.0);
+
+        for step in (0..16).step_by(2) {
+            let direction = Direction16::new(step);
+            assert_close(
+                direction.rotate_vector(up),
+                direction.eight_way().rotate_vector(up).as_tuple(),
+            );
+        }
+
+        // the odd steps are a 16th of a turn past the previous direction
+        let (sin, cos) = (std::f64::consts::TAU / 16.0).sin_cos();
+        assert_close(Direction16::new(1).rotate_vector(up), (sin, -cos));
+        assert_close(Direction16::new(3).rotate_vector(up), (cos, -sin));
+        assert_close(Direction16::new(5).rotate_vector(up), (cos, sin));
+        assert_close(Direction16::new(7).rotate_vector(up), (sin, cos));
+        assert_close(Direction16::new(9).rotate_vector(up), (-sin, cos));
+        assert_close(Direction16::new(15).rotate_vector(up), (-sin, -cos));
+
+        assert_close(
+            Direction16::new(3).rotate_vector(Vector::new(1.0, -2.0)),
+            (2.0f64.mul_add(cos, sin), 2.0f64.mul_add(-sin, cos)),
+        );
+    }
+
+    #[test]
+    fn orientation_16_way() {
+        assert_eq!(Direction16::new(0).to_orientation(), 0.0);
+        assert_eq!(Direction16::new(1).to_orientation(), 0.0625);
+        assert_eq!(Direction16::new(4).to_orientation(), 0.25);
+        assert_eq!(Direction16::new(13).to_orientation(), 0.8125);
+    }
+
+    #[test]
+    fn cardinal_fallback() {
+        assert_eq!(Direction::NorthEast.to_cardinal(), Direction::North);
+        assert_eq!(Direction::SouthEast.to_cardinal(), Direction::East);
+        assert_eq!(Direction::SouthWest.to_cardinal(), Direction::South);
+        assert_eq!(Direction::NorthWest.to_cardinal(), Direction::West);
+        assert_eq!(Direction::West.to_cardinal(), Direction::West);
+    }
+
+    #[derive(Debug)]
+    struct Png(Vec<u8>);
+
+    impl SpriteSource for Png {
+        fn fetch(&self, _: &str, _: &str) -> Result<Vec<u8>, mod_util::mod_loader::ModError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn scaled_sprites_are_bounded() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgba8(64, 64)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let source = Png(png);
+
+        // room for two 32x32 RGBA sprites
+        let mut cache = ImageCache::with_max_scaled_bytes(2 * 32 * 32 * 4);
+        let file = FileName::new("__base__/graphics/sprite.png".to_owned());
+        for rect in [(0, 0, 32, 32), (32, 0, 32, 32), (0, 32, 32, 32)] {
+            assert!(file.load_scaled(&source, &mut cache, rect, 1.0).is_some());
+        }
+
+        assert_eq!(cache.scaled_bytes(), cache.max_scaled_bytes());
+        assert_eq!(cache.scaled.len(), 2);
+        assert!(!cache.scaled.keys().any(|key| key.ends_with("#0,0,32,32@1")));
+        assert_eq!(cache.files.len(), 1);
+
+        // larger than the whole limit, returned but never cached
+        assert!(file
+            .load_scaled(&source, &mut cache, (0, 0, 64, 64), 1.0)
+            .is_some());
+        assert_eq!(cache.scaled.len(), 2);
+    }
+}
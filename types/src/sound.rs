@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use serde_helper as helper;
+
+use crate::{FactorioArray, FileName};
+
+/// [`Types/Sound`](https://lua-api.factorio.com/latest/types/Sound.html)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Sound {
+    Struct(Box<SoundData>),
+    Variations(FactorioArray<SoundDefinition>),
+    FileName(FileName),
+}
+
+/// [`Types/Sound`](https://lua-api.factorio.com/latest/types/Sound.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SoundData {
+    pub category: Option<SoundType>,
+
+    #[serde(
+        default = "helper::u8_127",
+        skip_serializing_if = "helper::is_127_u8",
+        deserialize_with = "helper::truncating_deserializer"
+    )]
+    pub priority: u8,
+
+    pub aggregation: Option<AggregationSpecification>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_random_repeat: bool,
+
+    #[serde(default = "helper::f64_1", skip_serializing_if = "helper::is_1_f64")]
+    pub audible_distance_modifier: f64,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "helper::truncating_opt_deserializer"
+    )]
+    pub speed_smoothing_window_size: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variations: FactorioArray<SoundDefinition>,
+
+    /// Set when the sound is defined by a single file instead of [`SoundData::variations`].
+    #[serde(flatten)]
+    pub definition: Option<SoundDefinitionData>,
+    // not implemented
+    // pub game_controller_vibration_data: Option<GameControllerVibrationData>,
+    // pub advanced_volume_control: Option<AdvancedVolumeControl>,
+}
+
+/// [`Types/SoundDefinition`](https://lua-api.factorio.com/latest/types/SoundDefinition.html)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SoundDefinition {
+    Struct(SoundDefinitionData),
+    FileName(FileName),
+}
+
+/// [`Types/SoundDefinition`](https://lua-api.factorio.com/latest/types/SoundDefinition.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SoundDefinitionData {
+    pub filename: FileName,
+
+    #[serde(default = "helper::f32_1", skip_serializing_if = "helper::is_1_f32")]
+    pub volume: f32,
+
+    pub min_volume: Option<f32>,
+    pub max_volume: Option<f32>,
+
+    pub preload: Option<bool>,
+
+    pub speed: Option<f32>,
+    pub min_speed: Option<f32>,
+    pub max_speed: Option<f32>,
+
+    pub modifiers: Option<SoundModifiers>,
+}
+
+/// [`Types/SoundType`](https://lua-api.factorio.com/latest/types/SoundType.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SoundType {
+    GameEffect,
+    GuiEffect,
+    Ambient,
+    Environment,
+    Walking,
+    Alert,
+    Enemy,
+    Object,
+    WorldAmbient,
+    Weapon,
+    Explosion,
+}
+
+/// [`Types/AggregationSpecification`](https://lua-api.factorio.com/latest/types/AggregationSpecification.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregationSpecification {
+    #[serde(deserialize_with = "helper::truncating_deserializer")]
+    pub max_count: u32,
+
+    #[serde(default = "helper::f32_1", skip_serializing_if = "helper::is_1_f32")]
+    pub progress_threshold: f32,
+
+    pub remove: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub count_already_playing: bool,
+}
+
+/// Single [`SoundModifier`] or an array of them.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SoundModifiers {
+    Single(SoundModifier),
+    Multiple(FactorioArray<SoundModifier>),
+}
+
+/// [`Types/SoundModifier`](https://lua-api.factorio.com/latest/types/SoundModifier.html)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SoundModifier {
+    #[serde(rename = "type")]
+    pub type_: SoundModifierType,
+
+    pub volume_multiplier: f32,
+}
+
+/// [`Types/SoundModifierType`](https://lua-api.factorio.com/latest/types/SoundModifierType.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SoundModifierType {
+    Game,
+    MainMenu,
+    Tips,
+    Driving,
+    ElevatedRail,
+    Space,
+}
+
+/// [`Types/LayeredSound`](https://lua-api.factorio.com/latest/types/LayeredSound.html)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LayeredSound {
+    Layers { layers: FactorioArray<Sound> },
+    Single(Sound),
+}
+
+/// [`Types/InterruptibleSound`](https://lua-api.factorio.com/latest/types/InterruptibleSound.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InterruptibleSound {
+    pub sound: Sound,
+    pub stopped_sound: Option<Sound>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "helper::is_default",
+        deserialize_with = "helper::truncating_deserializer"
+    )]
+    pub fade_ticks: u32,
+}
+
+/// [`Types/CyclicSound`](https://lua-api.factorio.com/latest/types/CyclicSound.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CyclicSound {
+    pub begin_sound: Option<Sound>,
+    pub middle_sound: Option<Sound>,
+    pub end_sound: Option<Sound>,
+}
@@ -0,0 +1,523 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use serde_helper as helper;
+
+use crate::{
+    BoundingBox, DamageTypeID, EntityID, EntityPrototypeFlags, FactorioArray, ForceCondition,
+    ItemID, TileID, Vector,
+};
+
+/// [`Types/Trigger`](https://lua-api.factorio.com/latest/types/Trigger.html)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Trigger {
+    Single(Box<TriggerItem>),
+    Multiple(FactorioArray<TriggerItem>),
+}
+
+impl Trigger {
+    /// All trigger items, regardless of the notation used in the prototype.
+    #[must_use]
+    pub fn items(&self) -> &[TriggerItem] {
+        match self {
+            Self::Single(item) => std::slice::from_ref(item),
+            Self::Multiple(items) => items,
+        }
+    }
+}
+
+/// [`Types/TriggerTargetMask`](https://lua-api.factorio.com/latest/types/TriggerTargetMask.html)
+pub type TriggerTargetMask = FactorioArray<String>;
+
+/// [`Types/TriggerItem`](https://lua-api.factorio.com/latest/types/TriggerItem.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TriggerItemBase {
+    pub action_delivery: Option<TriggerDeliveryUnion>,
+    pub source_effects: Option<TriggerEffect>,
+    pub entity_flags: Option<EntityPrototypeFlags>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ignore_collision_condition: bool,
+
+    pub trigger_target_mask: Option<TriggerTargetMask>,
+
+    #[serde(
+        default = "helper::u32_1",
+        skip_serializing_if = "helper::is_1_u32",
+        deserialize_with = "helper::truncating_deserializer"
+    )]
+    pub repeat_count: u32,
+
+    #[serde(default = "helper::f32_1", skip_serializing_if = "helper::is_1_f32")]
+    pub probability: f32,
+
+    pub force: Option<ForceCondition>,
+    // not implemented
+    // pub collision_mask: Option<CollisionMaskConnector>,
+}
+
+/// [`Types/TriggerItem`](https://lua-api.factorio.com/latest/types/TriggerItem.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TriggerItem {
+    /// [`Types/DirectTriggerItem`](https://lua-api.factorio.com/latest/types/DirectTriggerItem.html)
+    Direct {
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        filter_enabled: bool,
+
+        #[serde(flatten)]
+        base: TriggerItemBase,
+    },
+
+    /// [`Types/AreaTriggerItem`](https://lua-api.factorio.com/latest/types/AreaTriggerItem.html)
+    Area {
+        radius: f64,
+
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        trigger_from_target: bool,
+
+        #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+        target_entities: bool,
+
+        #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+        show_in_tooltip: bool,
+
+        #[serde(flatten)]
+        base: TriggerItemBase,
+    },
+
+    /// [`Types/LineTriggerItem`](https://lua-api.factorio.com/latest/types/LineTriggerItem.html)
+    Line {
+        range: f64,
+        width: f64,
+        range_effects: Option<TriggerEffect>,
+
+        #[serde(flatten)]
+        base: TriggerItemBase,
+    },
+
+    /// [`Types/ClusterTriggerItem`](https://lua-api.factorio.com/latest/types/ClusterTriggerItem.html)
+    Cluster {
+        #[serde(deserialize_with = "helper::truncating_deserializer")]
+        cluster_count: u32,
+
+        distance: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        distance_deviation: f32,
+
+        #[serde(flatten)]
+        base: TriggerItemBase,
+    },
+}
+
+impl std::ops::Deref for TriggerItem {
+    type Target = TriggerItemBase;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Direct { base, .. }
+            | Self::Area { base, .. }
+            | Self::Line { base, .. }
+            | Self::Cluster { base, .. } => base,
+        }
+    }
+}
+
+/// Single [`TriggerDelivery`] or an array of them.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TriggerDeliveryUnion {
+    Single(Box<TriggerDelivery>),
+    Multiple(FactorioArray<TriggerDelivery>),
+}
+
+impl TriggerDeliveryUnion {
+    #[must_use]
+    pub fn deliveries(&self) -> &[TriggerDelivery] {
+        match self {
+            Self::Single(delivery) => std::slice::from_ref(delivery),
+            Self::Multiple(deliveries) => deliveries,
+        }
+    }
+}
+
+/// [`Types/TriggerDelivery`](https://lua-api.factorio.com/latest/types/TriggerDelivery.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TriggerDeliveryBase {
+    pub source_effects: Option<TriggerEffect>,
+    pub target_effects: Option<TriggerEffect>,
+}
+
+/// [`Types/TriggerDelivery`](https://lua-api.factorio.com/latest/types/TriggerDelivery.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TriggerDelivery {
+    /// [`Types/InstantTriggerDelivery`](https://lua-api.factorio.com/latest/types/InstantTriggerDelivery.html)
+    Instant {
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+
+    /// [`Types/ProjectileTriggerDelivery`](https://lua-api.factorio.com/latest/types/ProjectileTriggerDelivery.html)
+    Projectile {
+        projectile: EntityID,
+        starting_speed: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        starting_speed_deviation: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        direction_deviation: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        range_deviation: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        max_range: f64,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        min_range: f64,
+
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+
+    /// [`Types/BeamTriggerDelivery`](https://lua-api.factorio.com/latest/types/BeamTriggerDelivery.html)
+    Beam {
+        beam: EntityID,
+
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        add_to_shooter: bool,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        max_length: f64,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        duration: u32,
+
+        source_offset: Option<Vector>,
+
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+
+    /// [`Types/StreamTriggerDelivery`](https://lua-api.factorio.com/latest/types/StreamTriggerDelivery.html)
+    Stream {
+        stream: EntityID,
+        source_offset: Option<Vector>,
+
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+
+    /// [`Types/ArtilleryTriggerDelivery`](https://lua-api.factorio.com/latest/types/ArtilleryTriggerDelivery.html)
+    Artillery {
+        projectile: EntityID,
+        starting_speed: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        starting_speed_deviation: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        direction_deviation: f32,
+
+        #[serde(default, skip_serializing_if = "helper::is_default")]
+        range_deviation: f32,
+
+        #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+        trigger_fired_artillery: bool,
+
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+
+    /// [`Types/ChainTriggerDelivery`](https://lua-api.factorio.com/latest/types/ChainTriggerDelivery.html)
+    Chain {
+        chain: String,
+
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+
+    /// [`Types/DelayedTriggerDelivery`](https://lua-api.factorio.com/latest/types/DelayedTriggerDelivery.html)
+    Delayed {
+        delayed_trigger: String,
+
+        #[serde(flatten)]
+        base: TriggerDeliveryBase,
+    },
+}
+
+impl std::ops::Deref for TriggerDelivery {
+    type Target = TriggerDeliveryBase;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Instant { base }
+            | Self::Projectile { base, .. }
+            | Self::Beam { base, .. }
+            | Self::Stream { base, .. }
+            | Self::Artillery { base, .. }
+            | Self::Chain { base, .. }
+            | Self::Delayed { base, .. } => base,
+        }
+    }
+}
+
+/// [`Types/TriggerEffect`](https://lua-api.factorio.com/latest/types/TriggerEffect.html)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TriggerEffect {
+    Single(Box<TriggerEffectItem>),
+    Multiple(FactorioArray<TriggerEffectItem>),
+}
+
+impl TriggerEffect {
+    #[must_use]
+    pub fn effects(&self) -> &[TriggerEffectItem] {
+        match self {
+            Self::Single(effect) => std::slice::from_ref(effect),
+            Self::Multiple(effects) => effects,
+        }
+    }
+}
+
+/// [`Types/TriggerEffectItem`](https://lua-api.factorio.com/latest/types/TriggerEffectItem.html)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TriggerEffectItemBase {
+    #[serde(
+        default = "helper::u16_1",
+        skip_serializing_if = "helper::is_1_u16",
+        deserialize_with = "helper::truncating_deserializer"
+    )]
+    pub repeat_count: u16,
+
+    #[serde(
+        default,
+        skip_serializing_if = "helper::is_default",
+        deserialize_with = "helper::truncating_deserializer"
+    )]
+    pub repeat_count_deviation: u16,
+
+    #[serde(default = "helper::f32_1", skip_serializing_if = "helper::is_1_f32")]
+    pub probability: f32,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub affects_target: bool,
+
+    #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+    pub show_in_tooltip: bool,
+    // not implemented
+    // pub damage_type_filters: Option<DamageTypeFilters>,
+}
+
+/// [`Types/DamageParameters`](https://lua-api.factorio.com/latest/types/DamageParameters.html)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DamageParameters {
+    pub amount: f32,
+
+    #[serde(rename = "type")]
+    pub type_: DamageTypeID,
+}
+
+/// [`Types/CreateEntityTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateEntityTriggerEffectItem.html)
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateEntityTriggerEffectData {
+    pub entity_name: EntityID,
+    pub offset_deviation: Option<BoundingBox>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trigger_created_entity: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_buildability: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub find_non_colliding_position: bool,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub as_enemy: bool,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub offsets: FactorioArray<Vector>,
+
+    #[serde(flatten)]
+    pub base: TriggerEffectItemBase,
+}
+
+/// [`Types/TriggerEffectItem`](https://lua-api.factorio.com/latest/types/TriggerEffectItem.html)
+///
+/// Effects that only play sounds or spawn visual decoration are not modelled, they are kept as [`TriggerEffectItem::Other`].
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TriggerEffectItem {
+    /// [`Types/DamageEntityTriggerEffectItem`](https://lua-api.factorio.com/latest/types/DamageEntityTriggerEffectItem.html)
+    Damage {
+        damage: DamageParameters,
+
+        #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+        apply_damage_to_trees: bool,
+
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        use_substitute: bool,
+
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "helper::truncating_opt_deserializer"
+        )]
+        lower_distance_threshold: Option<u16>,
+
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "helper::truncating_opt_deserializer"
+        )]
+        upper_distance_threshold: Option<u16>,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/CreateEntityTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateEntityTriggerEffectItem.html)
+    CreateEntity(CreateEntityTriggerEffectData),
+
+    /// [`Types/CreateExplosionTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateExplosionTriggerEffectItem.html)
+    CreateExplosion(CreateEntityTriggerEffectData),
+
+    /// [`Types/CreateFireTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateFireTriggerEffectItem.html)
+    CreateFire(CreateEntityTriggerEffectData),
+
+    /// [`Types/CreateSmokeTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateSmokeTriggerEffectItem.html)
+    CreateSmoke(CreateEntityTriggerEffectData),
+
+    /// [`Types/CreateTrivialSmokeEffectItem`](https://lua-api.factorio.com/latest/types/CreateTrivialSmokeEffectItem.html)
+    CreateTrivialSmoke {
+        smoke_name: String,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/CreateParticleTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateParticleTriggerEffectItem.html)
+    CreateParticle {
+        particle_name: String,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/CreateStickerTriggerEffectItem`](https://lua-api.factorio.com/latest/types/CreateStickerTriggerEffectItem.html)
+    CreateSticker {
+        sticker: EntityID,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/NestedTriggerEffectItem`](https://lua-api.factorio.com/latest/types/NestedTriggerEffectItem.html)
+    NestedResult {
+        action: Trigger,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/PushBackTriggerEffectItem`](https://lua-api.factorio.com/latest/types/PushBackTriggerEffectItem.html)
+    PushBack {
+        distance: f32,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/DestroyCliffsTriggerEffectItem`](https://lua-api.factorio.com/latest/types/DestroyCliffsTriggerEffectItem.html)
+    DestroyCliffs {
+        radius: f32,
+        explosion_at_trigger: Option<EntityID>,
+        explosion_at_cliff: Option<EntityID>,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/ShowExplosionOnChartTriggerEffectItem`](https://lua-api.factorio.com/latest/types/ShowExplosionOnChartTriggerEffectItem.html)
+    ShowExplosionOnChart {
+        scale: f32,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/InsertItemTriggerEffectItem`](https://lua-api.factorio.com/latest/types/InsertItemTriggerEffectItem.html)
+    InsertItem {
+        item: ItemID,
+
+        #[serde(
+            default = "helper::u32_1",
+            skip_serializing_if = "helper::is_1_u32",
+            deserialize_with = "helper::truncating_deserializer"
+        )]
+        count: u32,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/ScriptTriggerEffectItem`](https://lua-api.factorio.com/latest/types/ScriptTriggerEffectItem.html)
+    Script {
+        effect_id: String,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// [`Types/SetTileTriggerEffectItem`](https://lua-api.factorio.com/latest/types/SetTileTriggerEffectItem.html)
+    SetTile {
+        tile_name: TileID,
+        radius: f32,
+
+        #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
+        apply_projection: bool,
+
+        #[serde(flatten)]
+        base: TriggerEffectItemBase,
+    },
+
+    /// Sound, decorative, camera & other purely visual effects.
+    #[serde(other)]
+    Other,
+}
+
+impl TriggerEffectItem {
+    /// Common properties of the effect, [`TriggerEffectItem::Other`] has none.
+    #[must_use]
+    pub const fn base(&self) -> Option<&TriggerEffectItemBase> {
+        match self {
+            Self::CreateEntity(data)
+            | Self::CreateExplosion(data)
+            | Self::CreateFire(data)
+            | Self::CreateSmoke(data) => Some(&data.base),
+            Self::Damage { base, .. }
+            | Self::CreateTrivialSmoke { base, .. }
+            | Self::CreateParticle { base, .. }
+            | Self::CreateSticker { base, .. }
+            | Self::NestedResult { base, .. }
+            | Self::PushBack { base, .. }
+            | Self::DestroyCliffs { base, .. }
+            | Self::ShowExplosionOnChart { base, .. }
+            | Self::InsertItem { base, .. }
+            | Self::Script { base, .. }
+            | Self::SetTile { base, .. } => Some(base),
+            Self::Other => None,
+        }
+    }
+}